@@ -0,0 +1,735 @@
+use std::time::Duration;
+
+use reqwest::header::{AUTHORIZATION, COOKIE, RETRY_AFTER};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use webreg_types::sessions::SESSION_TOKEN_HEADER;
+
+use crate::error::{ClientError, Result};
+use crate::models::*;
+use crate::retry::{is_retryable, RetryPolicy};
+
+/// How long a request may take, unless another timeout is given.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How requests are tied to a student's WebReg session.
+#[derive(Debug, Clone)]
+enum SessionAuth {
+    /// The student's WebReg cookies, sent with every request.
+    Cookies(String),
+    /// A session token from `POST /sessions`, which the server exchanges for the cookies.
+    Token(String),
+}
+
+/// A builder for a [`WebRegClient`].
+pub struct WebRegClientBuilder {
+    base_url: String,
+    api_key: Option<String>,
+    session: Option<SessionAuth>,
+    retry: RetryPolicy,
+    timeout: Duration,
+    http: Option<Client>,
+}
+
+impl WebRegClientBuilder {
+    /// Sets the API key (`prefix#key`) that's sent with every request. This is needed if the
+    /// server was built with the `auth` feature.
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Sets the student's WebReg cookies, which are needed by the endpoints that act on a
+    /// student's behalf (e.g., adding a section).
+    pub fn cookies(mut self, cookies: impl Into<String>) -> Self {
+        self.session = Some(SessionAuth::Cookies(cookies.into()));
+        self
+    }
+
+    /// Sets the session token to send in place of the student's cookies. See
+    /// [`WebRegClient::create_session`].
+    pub fn session_token(mut self, token: impl Into<String>) -> Self {
+        self.session = Some(SessionAuth::Token(token.into()));
+        self
+    }
+
+    /// Sets how failed requests are retried. By default, a request is retried up to 3 times.
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets how long a request may take, including reading its response. The default is 30
+    /// seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the HTTP client to send requests with, e.g., to share a connection pool.
+    pub fn http_client(mut self, http: Client) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    /// Builds the client.
+    pub fn build(self) -> WebRegClient {
+        WebRegClient {
+            http: self.http.unwrap_or_default(),
+            base_url: self.base_url.trim_end_matches('/').to_owned(),
+            api_key: self.api_key,
+            session: self.session,
+            retry: self.retry,
+            timeout: self.timeout,
+        }
+    }
+}
+
+/// A client for the API.
+///
+/// Every endpoint that students or integrations use has a method here; the admin API
+/// doesn't. Endpoints without a method can still be called with [`WebRegClient::get`] and
+/// [`WebRegClient::post`].
+///
+/// Cloning a client is cheap, and clones share their connection pool.
+#[derive(Debug, Clone)]
+pub struct WebRegClient {
+    http: Client,
+    base_url: String,
+    api_key: Option<String>,
+    session: Option<SessionAuth>,
+    retry: RetryPolicy,
+    timeout: Duration,
+}
+
+impl WebRegClient {
+    /// Creates a builder for a client of the server at the given URL (e.g.,
+    /// `http://localhost:3000`).
+    pub fn builder(base_url: impl Into<String>) -> WebRegClientBuilder {
+        WebRegClientBuilder {
+            base_url: base_url.into(),
+            api_key: None,
+            session: None,
+            retry: RetryPolicy::default(),
+            timeout: DEFAULT_TIMEOUT,
+            http: None,
+        }
+    }
+
+    /// Creates a copy of this client that uses the given session token in place of any
+    /// cookies or token that this client uses.
+    pub fn with_session_token(&self, token: impl Into<String>) -> Self {
+        Self {
+            session: Some(SessionAuth::Token(token.into())),
+            ..self.clone()
+        }
+    }
+
+    // ================================================================================ //
+    //                                 General endpoints                                //
+    // ================================================================================ //
+
+    /// `GET /health`
+    pub async fn health(&self) -> Result<Value> {
+        self.get("/health", &()).await
+    }
+
+    /// `GET /terms`
+    pub async fn terms(&self) -> Result<Vec<Term>> {
+        self.get("/terms", &()).await
+    }
+
+    /// `GET /live/:term/course_info`
+    pub async fn course_info(
+        &self,
+        term: &str,
+        subject: &str,
+        number: &str,
+    ) -> Result<Vec<CourseSection>> {
+        self.get(&live(term, "course_info"), &course(subject, number))
+            .await
+    }
+
+    /// `POST /live/:term/course_info/batch`
+    ///
+    /// Looks up several courses at once. A course that couldn't be looked up has an error
+    /// in its result rather than failing the others.
+    ///
+    /// # Parameters
+    /// - `courses`: The courses' subjects and numbers (e.g., `("CSE", "110")`).
+    pub async fn course_info_batch(
+        &self,
+        term: &str,
+        courses: &[(&str, &str)],
+    ) -> Result<CourseInfoBatch> {
+        let body = BodyCourseInfoBatch {
+            courses: courses
+                .iter()
+                .map(|(subject, number)| course(subject, number))
+                .collect(),
+        };
+        self.post(&live(term, "course_info/batch"), &body).await
+    }
+
+    /// `GET /live/:term/prerequisites`
+    pub async fn prerequisites(
+        &self,
+        term: &str,
+        subject: &str,
+        number: &str,
+    ) -> Result<PrerequisiteInfo> {
+        self.get(&live(term, "prerequisites"), &course(subject, number))
+            .await
+    }
+
+    /// `GET /live/:term/search`
+    pub async fn search(
+        &self,
+        term: &str,
+        search: &BodySearchType,
+    ) -> Result<Vec<SearchResultItem>> {
+        let path = live(term, "search");
+        let resp = self
+            .send(true, || self.request(Method::GET, &path).json(search))
+            .await?;
+        decode(resp).await
+    }
+
+    /// `GET /live/:term/search/v2`
+    pub async fn search_v2(&self, term: &str, query: &SearchV2QueryStr) -> Result<SearchPage> {
+        self.get(&live(term, "search/v2"), query).await
+    }
+
+    /// `GET /live/:term/department_codes`
+    pub async fn department_codes(&self, term: &str) -> Result<Vec<String>> {
+        self.get(&live(term, "department_codes"), &()).await
+    }
+
+    /// `GET /live/:term/subject_codes`
+    pub async fn subject_codes(&self, term: &str) -> Result<Vec<String>> {
+        self.get(&live(term, "subject_codes"), &()).await
+    }
+
+    /// `GET /live/:term/course_text`
+    pub async fn course_text(&self, term: &str, subjects: &[&str]) -> Result<Value> {
+        let query = SubjListQueryStr {
+            subjects: subjects.join(":"),
+        };
+        self.get(&live(term, "course_text"), &query).await
+    }
+
+    /// `GET /live/:term/section_text`
+    pub async fn section_text(&self, term: &str, subject: &str, number: &str) -> Result<Value> {
+        self.get(&live(term, "section_text"), &course(subject, number))
+            .await
+    }
+
+    // ================================================================================ //
+    //                                   Schedule data                                  //
+    // ================================================================================ //
+
+    /// `GET /live/:term/schedule_data`
+    pub async fn schedule_data(&self, term: &str) -> Result<Vec<Value>> {
+        self.get(&live(term, "schedule_data"), &()).await
+    }
+
+    /// `GET /live/:term/schedule_data/:section_id`
+    pub async fn section_meetings(&self, term: &str, section_id: &str) -> Result<Value> {
+        self.get(&live(term, &format!("schedule_data/{section_id}")), &())
+            .await
+    }
+
+    // ================================================================================ //
+    //                       Endpoints that act on a student's behalf                   //
+    // ================================================================================ //
+
+    /// `GET /live/:term/schedule`
+    pub async fn schedule(
+        &self,
+        term: &str,
+        schedule_name: Option<&str>,
+    ) -> Result<Vec<ScheduledSection>> {
+        let query = ScheduleQueryStr {
+            name: schedule_name.map(str::to_owned),
+        };
+        self.get(&live(term, "schedule"), &query).await
+    }
+
+    /// `GET /live/:term/schedule_list`
+    pub async fn schedule_list(&self, term: &str) -> Result<Vec<String>> {
+        self.get(&live(term, "schedule_list"), &()).await
+    }
+
+    /// `GET /live/:term/events`
+    pub async fn events(&self, term: &str) -> Result<Vec<Event>> {
+        let events: Success<Vec<Event>> = self.get(&live(term, "events"), &()).await?;
+        Ok(events.success)
+    }
+
+    /// `POST /live/:term/register_term`
+    pub async fn register_term(&self, term: &str) -> Result<()> {
+        let path = live(term, "register_term");
+        let resp = self
+            .send(false, || self.request(Method::POST, &path))
+            .await?;
+        expect_success(resp).await
+    }
+
+    /// `POST /live/:term/rename_schedule`
+    pub async fn rename_schedule(&self, term: &str, body: &BodyScheduleNameChange) -> Result<bool> {
+        self.post_success(&live(term, "rename_schedule"), body)
+            .await
+    }
+
+    /// `POST /live/:term/validate_add_section`
+    pub async fn validate_add_section(&self, term: &str, body: &BodyAddInfo) -> Result<bool> {
+        self.post_success(&live(term, "validate_add_section"), body)
+            .await
+    }
+
+    /// `POST /live/:term/add_section`
+    ///
+    /// Unless `force` is set, the section is checked against the student's schedule first,
+    /// and a conflict is returned as a `409` error.
+    ///
+    /// # Returns
+    /// The receipt. Unlike most responses, a receipt is returned (rather than an error) even
+    /// if the section can't be seen in the student's schedule afterward; check
+    /// [`EnrollmentReceipt::verified`].
+    pub async fn add_section(
+        &self,
+        term: &str,
+        body: &BodyAddInfo,
+        force: bool,
+    ) -> Result<EnrollmentReceipt> {
+        self.post_outcome(&live(term, "add_section"), &force_query(force), body)
+            .await
+    }
+
+    /// `POST /live/:term/drop_section`
+    ///
+    /// # Returns
+    /// The receipt, as with [`WebRegClient::add_section`].
+    pub async fn drop_section(&self, term: &str, section_id: &str) -> Result<EnrollmentReceipt> {
+        let body = BodySectionId {
+            section_id: section_id.to_owned(),
+        };
+        self.post_outcome(&live(term, "drop_section"), &(), &body)
+            .await
+    }
+
+    /// `POST /live/:term/swap_sections`
+    ///
+    /// # Returns
+    /// The outcome of the swap, including when it failed partway.
+    pub async fn swap_sections(
+        &self,
+        term: &str,
+        body: &BodySwapSections,
+        force: bool,
+    ) -> Result<SwapOutcome> {
+        self.post_outcome(&live(term, "swap_sections"), &force_query(force), body)
+            .await
+    }
+
+    /// `POST /live/:term/bulk_add`
+    pub async fn bulk_add(
+        &self,
+        term: &str,
+        body: &BodyBulkAdd,
+        force: bool,
+    ) -> Result<Vec<BulkAddResult>> {
+        let results: BulkAddResults = self
+            .post_outcome(&live(term, "bulk_add"), &force_query(force), body)
+            .await?;
+        Ok(results.results)
+    }
+
+    /// `POST /live/:term/validate_schedule`
+    ///
+    /// Asks WebReg whether each of the sections could be added, without adding any.
+    pub async fn validate_schedule(
+        &self,
+        term: &str,
+        body: &BodyBulkAdd,
+    ) -> Result<ScheduleValidation> {
+        self.post(&live(term, "validate_schedule"), body).await
+    }
+
+    /// `POST /live/:term/validate_add_plan`
+    pub async fn validate_add_plan(&self, term: &str, body: &BodyPlanAdd) -> Result<bool> {
+        self.post_success(&live(term, "validate_add_plan"), body)
+            .await
+    }
+
+    /// `POST /live/:term/add_plan`
+    pub async fn add_plan(&self, term: &str, body: &BodyPlanAdd) -> Result<bool> {
+        self.post_success(&live(term, "add_plan"), body).await
+    }
+
+    /// `POST /live/:term/remove_plan`
+    pub async fn remove_plan(&self, term: &str, body: &BodySectionScheduleNameId) -> Result<bool> {
+        self.post_success(&live(term, "remove_plan"), body).await
+    }
+
+    // ================================================================================ //
+    //                                    Degree audit                                  //
+    // ================================================================================ //
+
+    /// `GET /degree_audit`
+    ///
+    /// # Parameters
+    /// - `refresh`: Whether to fetch a fresh audit rather than use the cached one.
+    pub async fn degree_audit(&self, refresh: bool) -> Result<AuditFetch> {
+        self.get(
+            "/degree_audit",
+            &AuditQueryParams {
+                refresh,
+                timeout: None,
+                anonymize: false,
+                run_async: false,
+            },
+        )
+        .await
+    }
+
+    /// `GET /degree_audit/progress`
+    pub async fn degree_progress(&self, refresh: bool) -> Result<DegreeProgress> {
+        self.get(
+            "/degree_audit/progress",
+            &AuditQueryParams {
+                refresh,
+                timeout: None,
+                anonymize: false,
+                run_async: false,
+            },
+        )
+        .await
+    }
+
+    /// `GET /degree_audit/progress?anonymize=true`
+    ///
+    /// Gets the degree progress without the student's name and PID, and with a hash in
+    /// place of the audit's ID, so that it can be shared.
+    pub async fn anonymized_degree_progress(&self, refresh: bool) -> Result<DegreeProgress> {
+        self.get(
+            "/degree_audit/progress",
+            &AuditQueryParams {
+                refresh,
+                timeout: None,
+                anonymize: true,
+                run_async: false,
+            },
+        )
+        .await
+    }
+
+    /// `GET /degree_audit/completed_courses`
+    pub async fn completed_courses(&self, refresh: bool) -> Result<Vec<CourseRequirement>> {
+        self.get(
+            "/degree_audit/completed_courses",
+            &AuditQueryParams {
+                refresh,
+                timeout: None,
+                anonymize: false,
+                run_async: false,
+            },
+        )
+        .await
+    }
+
+    /// `GET /degree_audit/requirements`
+    pub async fn requirements(&self, refresh: bool) -> Result<Vec<Value>> {
+        self.get(
+            "/degree_audit/requirements",
+            &AuditQueryParams {
+                refresh,
+                timeout: None,
+                anonymize: false,
+                run_async: false,
+            },
+        )
+        .await
+    }
+
+    /// `GET /degree_audit/next_courses`
+    pub async fn next_courses(&self, refresh: bool) -> Result<Vec<NextCourseRecommendation>> {
+        self.get(
+            "/degree_audit/next_courses",
+            &AuditQueryParams {
+                refresh,
+                timeout: None,
+                anonymize: false,
+                run_async: false,
+            },
+        )
+        .await
+    }
+
+    /// `GET /degree_audit/subrequirement/:subreq_id/eligible_courses`
+    pub async fn eligible_courses(&self, subreq_id: &str, refresh: bool) -> Result<Value> {
+        self.get(
+            &format!("/degree_audit/subrequirement/{subreq_id}/eligible_courses"),
+            &AuditQueryParams {
+                refresh,
+                timeout: None,
+                anonymize: false,
+                run_async: false,
+            },
+        )
+        .await
+    }
+
+    /// `GET /degree_audit/graduation_plan`
+    pub async fn graduation_plan(
+        &self,
+        query: &GraduationPlanQueryParams,
+    ) -> Result<GraduationPlanResponse> {
+        self.get("/degree_audit/graduation_plan", query).await
+    }
+
+    /// `GET /degree_audit/report.pdf`
+    ///
+    /// # Returns
+    /// The contents of the report's `.pdf` file.
+    pub async fn degree_audit_report(&self, refresh: bool) -> Result<Vec<u8>> {
+        let query = AuditQueryParams {
+            refresh,
+            timeout: None,
+            anonymize: false,
+            run_async: false,
+        };
+        let resp = self
+            .send(true, || {
+                self.request(Method::GET, "/degree_audit/report.pdf")
+                    .query(&query)
+            })
+            .await?;
+
+        let status = resp.status();
+        let bytes = resp.bytes().await?;
+        if status.is_success() {
+            Ok(bytes.to_vec())
+        } else {
+            Err(ClientError::from_response(status, &bytes))
+        }
+    }
+
+    // ================================================================================ //
+    //                                      Sessions                                    //
+    // ================================================================================ //
+
+    /// `POST /sessions`
+    ///
+    /// # Returns
+    /// The session's token, which can be used in place of the cookies with
+    /// [`WebRegClient::with_session_token`].
+    pub async fn create_session(&self, cookies: &str) -> Result<String> {
+        let body = BodySessionCookies {
+            cookies: cookies.to_owned(),
+        };
+        let resp = self
+            .send(false, || {
+                self.request(Method::POST, "/sessions").json(&body)
+            })
+            .await?;
+        let token: SessionToken = decode(resp).await?;
+        Ok(token.token)
+    }
+
+    /// `GET /sessions`
+    pub async fn session_info(&self) -> Result<SessionInfo> {
+        self.get("/sessions", &()).await
+    }
+
+    /// `PUT /sessions`
+    pub async fn update_session(&self, cookies: &str) -> Result<()> {
+        let body = BodySessionCookies {
+            cookies: cookies.to_owned(),
+        };
+        let resp = self
+            .send(true, || self.request(Method::PUT, "/sessions").json(&body))
+            .await?;
+        expect_success(resp).await
+    }
+
+    /// `DELETE /sessions`
+    pub async fn delete_session(&self) -> Result<()> {
+        let resp = self
+            .send(true, || self.request(Method::DELETE, "/sessions"))
+            .await?;
+        expect_success(resp).await
+    }
+
+    // ================================================================================ //
+    //                                  Other endpoints                                 //
+    // ================================================================================ //
+
+    /// Sends a `GET` request to any endpoint. It's retried as described in [`RetryPolicy`].
+    ///
+    /// # Parameters
+    /// - `path`: The endpoint's path (e.g., `/live/FA24/rooms/CENTR`).
+    /// - `query`: The query string, or `&()` for none.
+    pub async fn get<T, Q>(&self, path: &str, query: &Q) -> Result<T>
+    where
+        T: DeserializeOwned,
+        Q: Serialize + ?Sized,
+    {
+        let resp = self
+            .send(true, || self.request(Method::GET, path).query(query))
+            .await?;
+        decode(resp).await
+    }
+
+    /// Sends a `POST` request with a JSON body to any endpoint. Since it may change
+    /// something, it's only retried if the server turned it away before acting on it.
+    ///
+    /// # Parameters
+    /// - `path`: The endpoint's path.
+    /// - `body`: The request body.
+    pub async fn post<T, B>(&self, path: &str, body: &B) -> Result<T>
+    where
+        T: DeserializeOwned,
+        B: Serialize + ?Sized,
+    {
+        let resp = self
+            .send(false, || self.request(Method::POST, path).json(body))
+            .await?;
+        decode(resp).await
+    }
+
+    /// Sends a `POST` request whose response says whether WebReg accepted it.
+    async fn post_success<B: Serialize>(&self, path: &str, body: &B) -> Result<bool> {
+        let success: Success = self.post(path, body).await?;
+        Ok(success.success)
+    }
+
+    /// Sends a `POST` request for an enrollment change, whose response describes the
+    /// outcome even if the change couldn't be verified (with a `502`).
+    async fn post_outcome<T, Q, B>(&self, path: &str, query: &Q, body: &B) -> Result<T>
+    where
+        T: DeserializeOwned,
+        Q: Serialize + ?Sized,
+        B: Serialize,
+    {
+        let resp = self
+            .send(false, || {
+                self.request(Method::POST, path).query(query).json(body)
+            })
+            .await?;
+
+        let status = resp.status();
+        let bytes = resp.bytes().await?;
+        if status.is_success() {
+            return Ok(serde_json::from_slice(&bytes)?);
+        }
+
+        // A 502 is either an outcome (if it decodes as one) or an error from WebReg
+        match status {
+            StatusCode::BAD_GATEWAY => serde_json::from_slice(&bytes)
+                .map_err(|_| ClientError::from_response(status, &bytes)),
+            _ => Err(ClientError::from_response(status, &bytes)),
+        }
+    }
+
+    /// Creates a request to the given path, with the API key and session attached.
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let mut req = self
+            .http
+            .request(method, format!("{}{path}", self.base_url))
+            .timeout(self.timeout);
+
+        if let Some(api_key) = &self.api_key {
+            req = req.header(AUTHORIZATION, format!("Bearer {api_key}"));
+        }
+
+        match &self.session {
+            Some(SessionAuth::Cookies(cookies)) => req.header(COOKIE, cookies),
+            Some(SessionAuth::Token(token)) => req.header(SESSION_TOKEN_HEADER, token),
+            None => req,
+        }
+    }
+
+    /// Sends a request, retrying it as long as the retry policy allows.
+    ///
+    /// # Parameters
+    /// - `idempotent`: Whether the request can safely be sent more than once. If not, it's
+    ///   only retried if the server turned it away before acting on it.
+    /// - `build`: Creates the request for each attempt.
+    ///
+    /// # Returns
+    /// The last response, whatever its status.
+    async fn send(&self, idempotent: bool, build: impl Fn() -> RequestBuilder) -> Result<Response> {
+        let mut retry = 0;
+        loop {
+            let result = build().send().await;
+            let should_retry = match &result {
+                Ok(resp) => is_retryable(resp.status(), idempotent),
+                // A request that couldn't connect never reached the server
+                Err(e) => e.is_connect() || (idempotent && e.is_timeout()),
+            };
+
+            let retry_after = result.as_ref().ok().and_then(retry_after);
+            match self.retry.delay(retry, retry_after) {
+                Some(delay) if should_retry => {
+                    tokio::time::sleep(delay).await;
+                    retry += 1;
+                }
+                _ => return Ok(result?),
+            }
+        }
+    }
+}
+
+/// Reads a response's body as JSON, or as an error if the response wasn't successful.
+async fn decode<T: DeserializeOwned>(resp: Response) -> Result<T> {
+    let status = resp.status();
+    let bytes = resp.bytes().await?;
+    if status.is_success() {
+        Ok(serde_json::from_slice(&bytes)?)
+    } else {
+        Err(ClientError::from_response(status, &bytes))
+    }
+}
+
+/// Checks that a response without a body was successful.
+async fn expect_success(resp: Response) -> Result<()> {
+    let status = resp.status();
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(ClientError::from_response(status, &resp.bytes().await?))
+    }
+}
+
+/// Gets how long a response asks to wait before retrying, if it does.
+fn retry_after(resp: &Response) -> Option<Duration> {
+    resp.headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Gets the path of an endpoint under `/live/:term`.
+fn live(term: &str, endpoint: &str) -> String {
+    format!("/live/{term}/{endpoint}")
+}
+
+fn course(subject: &str, number: &str) -> CourseQueryStr {
+    CourseQueryStr {
+        subject: subject.to_owned(),
+        number: number.to_owned(),
+    }
+}
+
+fn force_query(force: bool) -> ForceQueryStr {
+    ForceQueryStr {
+        force: force.then_some(true),
+    }
+}
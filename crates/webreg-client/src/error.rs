@@ -0,0 +1,70 @@
+use reqwest::StatusCode;
+use serde_json::Value;
+use thiserror::Error;
+
+/// Why a request failed.
+#[derive(Debug, Error)]
+pub enum ClientError {
+    /// The server responded with an error.
+    #[error("the server responded with {status}: {message}")]
+    Api {
+        status: StatusCode,
+        /// The server's description of the error.
+        message: String,
+        /// The response body, if it was JSON. Besides `code` and `message`, some errors
+        /// have `details`, like the fields that weren't valid, or the conflicting section
+        /// (`details.conflict`) when a section couldn't be added.
+        body: Option<Value>,
+    },
+    /// The request couldn't be sent, or the response couldn't be read.
+    #[error("the request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    /// The response wasn't in the expected format.
+    #[error("the response couldn't be decoded: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+impl ClientError {
+    /// Creates an error from an error response's status and body.
+    pub(crate) fn from_response(status: StatusCode, body: &[u8]) -> Self {
+        let json = serde_json::from_slice::<Value>(body).ok();
+        let text = String::from_utf8_lossy(body);
+        let message = match json
+            .as_ref()
+            .and_then(|b| b["message"].as_str().or(b["error"].as_str()))
+        {
+            Some(error) => error.to_owned(),
+            None if json.is_none() && !text.trim().is_empty() => text.trim().to_owned(),
+            None => status
+                .canonical_reason()
+                .unwrap_or("unknown error")
+                .to_owned(),
+        };
+
+        Self::Api {
+            status,
+            message,
+            body: json,
+        }
+    }
+
+    /// The code that the server gave the error (e.g., `SECTION_FULL`), if it gave one.
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            ClientError::Api { body, .. } => body.as_ref()?["code"].as_str(),
+            _ => None,
+        }
+    }
+
+    /// The status that the server responded with, if it responded with an error.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ClientError::Api { status, .. } => Some(*status),
+            ClientError::Http(e) => e.status(),
+            ClientError::Decode(_) => None,
+        }
+    }
+}
+
+/// The result of a request.
+pub type Result<T> = std::result::Result<T, ClientError>;
@@ -0,0 +1,43 @@
+//! A typed client for the webreg_scraper API, so that other Rust tools don't need to build
+//! requests and parse responses by hand.
+//!
+//! The request and response types are the server's own (see [`models`]), and requests are
+//! retried with backoff when the server is busy or can't reach WebReg (see
+//! [`RetryPolicy`]).
+//!
+//! ```no_run
+//! use webreg_client::models::BodyAddInfo;
+//! use webreg_client::WebRegClient;
+//!
+//! # async fn run() -> webreg_client::Result<()> {
+//! let client = WebRegClient::builder("http://localhost:3000")
+//!     .api_key("prefix#key")
+//!     .cookies("jlinksessionidx=...")
+//!     .build();
+//!
+//! let sections = client.course_info("FA24", "CSE", "100").await?;
+//! let receipt = client
+//!     .add_section(
+//!         "FA24",
+//!         &BodyAddInfo {
+//!             section_id: sections[0].section_id.clone(),
+//!             grading_option: None,
+//!             unit_count: None,
+//!             validate: None,
+//!         },
+//!         false,
+//!     )
+//!     .await?;
+//! println!("Added {}: {}", receipt.section_id, receipt.verified);
+//! # Ok(())
+//! # }
+//! ```
+
+mod client;
+mod error;
+pub mod models;
+mod retry;
+
+pub use client::{WebRegClient, WebRegClientBuilder};
+pub use error::{ClientError, Result};
+pub use retry::RetryPolicy;
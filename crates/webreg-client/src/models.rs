@@ -0,0 +1,153 @@
+//! The request and response types of the API.
+//!
+//! Most of these are shared with the server (see `webreg_types`), so that requests and
+//! responses can't drift from what the server accepts and returns. The rest describe
+//! responses that the server builds on the fly.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub use webreg_types::degree_audit::{
+    AuditFetch, CourseRequirement, DegreeAudit, DegreeProgress, GraduationPlan,
+    NextCourseRecommendation, PlanWarning, PlannedCourse, PlannedTerm, UnmetNeed,
+};
+pub use webreg_types::receipts::{EnrollmentAction, EnrollmentReceipt};
+pub use webreg_types::requests::{
+    AuditQueryParams, BodyAddInfo, BodyBulkAdd, BodyCourseInfoBatch, BodyPlanAdd,
+    BodyScheduleNameChange, BodySearchType, BodySectionId, BodySectionScheduleNameId,
+    BodySessionCookies, BodySwapSections, CourseQueryStr, ForceQueryStr, GraduationPlanQueryParams,
+    ScheduleQueryStr, SearchV2QueryStr, SubjListQueryStr,
+};
+pub use webreg_types::sessions::SessionInfo;
+pub use webreg_types::webweg::{
+    CoursePrerequisite, CourseSection, EnrollmentStatus, Event, Meeting, MeetingDay,
+    PrerequisiteInfo, ScheduledSection, SearchResultItem, Term,
+};
+
+/// The response of endpoints that only say whether WebReg accepted the request. (The
+/// `events` endpoint also uses this shape, with the events in `success`.)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Success<T = bool> {
+    pub success: T,
+}
+
+/// A page of results from `search/v2`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchPage {
+    /// The number of courses that matched, across every page.
+    pub total: usize,
+    /// The cursor of the next page, if there is one.
+    pub next_cursor: Option<String>,
+    /// The courses on this page, along with their sections.
+    pub results: Vec<Value>,
+}
+
+/// The outcome of looking up one course in a batch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CourseInfoResult {
+    pub subject: String,
+    pub number: String,
+    /// Either `ok` or `error`.
+    pub status: String,
+    /// The course's sections, for `ok`.
+    #[serde(default)]
+    pub sections: Vec<CourseSection>,
+    /// The error's code, for `error`.
+    #[serde(default)]
+    pub code: Option<String>,
+    /// Why the course couldn't be looked up, for `error`.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// The response of `course_info/batch`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CourseInfoBatch {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<CourseInfoResult>,
+}
+
+/// The outcome of a swap.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapOutcome {
+    /// Whether the new section was added in place of the old one.
+    pub swapped: bool,
+    /// If the swap failed after the old section was dropped, whether it was added back.
+    #[serde(default)]
+    pub restored: Option<bool>,
+    /// Why the new section couldn't be added, if it couldn't.
+    #[serde(default)]
+    pub error: Option<String>,
+    pub drop: EnrollmentReceipt,
+    #[serde(default)]
+    pub add: Option<EnrollmentReceipt>,
+    #[serde(default)]
+    pub restore: Option<EnrollmentReceipt>,
+}
+
+/// The outcome of adding one section in a bulk add.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkAddResult {
+    pub section_id: String,
+    /// One of `added`, `unverified`, `conflict`, or `error`.
+    pub status: String,
+    /// The section that this one conflicts with, for `conflict`.
+    #[serde(default)]
+    pub conflict: Option<Value>,
+    /// Why the section couldn't be added, for `error`.
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The receipt, for `added` and `unverified`.
+    #[serde(default)]
+    pub receipt: Option<EnrollmentReceipt>,
+}
+
+/// The response of `bulk_add`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct BulkAddResults {
+    pub results: Vec<BulkAddResult>,
+}
+
+/// What WebReg said about one section of a candidate schedule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionValidation {
+    pub section_id: String,
+    /// One of `valid`, `invalid`, or `error`.
+    pub status: String,
+    /// WebReg's messages, for `invalid`, or what went wrong, for `error`.
+    #[serde(default)]
+    pub messages: Vec<String>,
+    /// The enrollment restrictions in the section's notes (e.g., "Department approval
+    /// required"), which WebReg may still enforce.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// The underlying error, for `error`.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// The response of `validate_schedule`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleValidation {
+    /// One of `enrollable`, `not_enrollable`, or `unknown`.
+    pub verdict: String,
+    pub enrollable: bool,
+    pub results: Vec<SectionValidation>,
+}
+
+/// The response of `POST /sessions`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SessionToken {
+    pub token: String,
+}
+
+/// A graduation plan, along with what it was built from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraduationPlanResponse {
+    pub audit_id: String,
+    pub units_per_term: f32,
+    /// Whether prerequisites could be looked up from WebReg while building the plan.
+    pub prerequisites_checked: bool,
+    pub plan: GraduationPlan,
+}
@@ -0,0 +1,119 @@
+//! When, and how long to wait before, a failed request is retried.
+
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use webreg_types::retry::backoff;
+
+/// How failed requests are retried.
+///
+/// Requests are retried with exponential backoff, with up to half of each delay replaced by
+/// random jitter so that clients that failed together don't retry together. If the server
+/// says how long to wait (with `Retry-After`), that's used instead, unless it's longer than
+/// [`RetryPolicy::max_delay`], in which case the request isn't retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// The most times that a request is retried.
+    pub max_retries: u32,
+    /// The delay before the first retry, which doubles for each retry after it.
+    pub base_delay: Duration,
+    /// The longest delay before a retry.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Gets how long to wait before retrying a request.
+    ///
+    /// # Parameters
+    /// - `retry`: The number of times the request was already retried.
+    /// - `retry_after`: How long the server asked to wait, if it did.
+    ///
+    /// # Returns
+    /// The delay, or nothing if the request shouldn't be retried.
+    pub(crate) fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if retry >= self.max_retries {
+            return None;
+        }
+
+        if let Some(retry_after) = retry_after {
+            return (retry_after <= self.max_delay).then_some(retry_after);
+        }
+
+        Some(backoff(self.base_delay, self.max_delay, retry, true))
+    }
+}
+
+/// Whether a response with the given status is worth retrying.
+///
+/// Requests that change something (e.g., adding a section) are only retried if the server
+/// turned them away before acting on them: when they were rate limited (`429`), or when the
+/// server couldn't take them (`503`). Other requests are also retried when WebReg couldn't
+/// be reached (`502`, `504`).
+///
+/// # Parameters
+/// - `status`: The response's status.
+/// - `idempotent`: Whether the request can safely be sent more than once.
+pub(crate) fn is_retryable(status: StatusCode, idempotent: bool) -> bool {
+    match status {
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => true,
+        StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => idempotent,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(3),
+        };
+
+        for (retry, cap) in [(0, 1), (1, 2), (2, 3)] {
+            let delay = policy.delay(retry, None).unwrap();
+            let cap = Duration::from_secs(cap);
+            assert!(
+                cap / 2 <= delay && delay <= cap,
+                "{delay:?} for retry {retry}"
+            );
+        }
+
+        assert_eq!(None, policy.delay(3, None));
+
+        // The server's delay is used if it isn't too long
+        assert_eq!(
+            Some(Duration::from_secs(2)),
+            policy.delay(0, Some(Duration::from_secs(2)))
+        );
+        assert_eq!(None, policy.delay(0, Some(Duration::from_secs(60))));
+
+        assert_eq!(None, RetryPolicy::none().delay(0, None));
+
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS, false));
+        assert!(is_retryable(StatusCode::BAD_GATEWAY, true));
+        assert!(!is_retryable(StatusCode::BAD_GATEWAY, false));
+        assert!(!is_retryable(StatusCode::CONFLICT, true));
+    }
+}
@@ -0,0 +1,316 @@
+//! Degree audits, what's computed from them, and graduation plans.
+
+use serde::{Deserialize, Serialize};
+
+/// Raw degree audit response from webregautoin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DegreeAuditResponse {
+    #[serde(rename = "auditId")]
+    pub audit_id: String,
+
+    #[serde(rename = "scrapedAt")]
+    pub scraped_at: String,
+
+    pub url: String,
+
+    /// Full HTML content of the degree audit page
+    /// This will be parsed to extract structured data
+    pub html: String,
+}
+
+/// Parsed degree audit data (to be implemented after HTML inspection)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DegreeAudit {
+    pub audit_id: String,
+    pub student_info: StudentInfo,
+    pub requirements: Vec<Requirement>,
+    pub scraped_at: String,
+    /// Blocks of the audit that couldn't be parsed and were left out
+    #[serde(default)]
+    pub parse_warnings: Vec<ParseWarning>,
+    /// The version of the DARS report that the audit was parsed as. Audits cached before
+    /// versions were detected don't have one
+    #[serde(default)]
+    pub report_version: Option<ReportVersion>,
+}
+
+/// A block of the audit HTML that couldn't be parsed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseWarning {
+    /// The kind of block (`requirement` or `subrequirement`), or `report_version` if the
+    /// report's version couldn't be detected
+    pub kind: String,
+    /// The block's `id` attribute, if it has one
+    pub block_id: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudentInfo {
+    pub student_id: Option<String>,
+    pub name: Option<String>,
+    pub major: Option<String>,
+    pub college: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Requirement {
+    pub category: String,
+    pub name: String,
+    pub status: RequirementStatus,
+    pub credits_required: Option<f32>,
+    pub credits_completed: Option<f32>,
+    pub courses: Vec<CourseRequirement>,
+    pub subrequirements: Vec<Subrequirement>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RequirementStatus {
+    Complete,
+    InProgress,
+    NotStarted,
+    NotApplicable,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CourseRequirement {
+    pub course_code: String,
+    pub title: Option<String>,
+    pub units: Option<f32>,
+    pub grade: Option<String>,
+    pub term: Option<String>,
+    pub status: CourseStatus,
+    /// The institution or exam (e.g., `AP CALCULUS BC`) that transfer credit came from
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CourseStatus {
+    Completed,
+    InProgress,
+    Planned,
+    Required,
+    /// Credit from another institution or from an AP/IB exam
+    Transfer,
+}
+
+/// Represents an eligible course extracted from selectcourses table
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct EligibleCourse {
+    pub department: String,    // e.g., "MATH", "CSE"
+    pub course_number: String, // e.g., "170A", "107"
+    pub full_code: String,     // e.g., "MATH 170A", "CSE 107"
+}
+
+/// Course category grouping (e.g., "APPLIED MATH", "GEN MATH-CS")
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CourseCategory {
+    pub name: String, // e.g., "APPLIED MATH"
+    pub courses: Vec<EligibleCourse>,
+}
+
+/// Represents a subrequirement (from div.subrequirement)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subrequirement {
+    pub id: String,          // From subrequirement id attribute
+    pub title: String,       // e.g., "General Math-CS Electives"
+    pub required_units: f32, // From rqdhours attribute
+    #[serde(default)]
+    pub required_courses: Option<u32>, // From rqdcourses attribute
+    #[serde(default)]
+    pub needs: Option<String>, // e.g., "NEEDS: 8.00 UNITS 2 COURSES"
+    #[serde(default)]
+    pub courses_needed: Option<u32>, // From the NEEDS table
+    pub units_completed: f32, // Calculated from completed courses
+    pub units_remaining: f32, // required_units - units_completed
+    pub status: RequirementStatus, // Parsed from status class
+    pub eligible_courses: Vec<EligibleCourse>, // Courses that can fulfill this
+    pub completed_courses: Vec<CourseRequirement>, // Already completed
+    pub category_groups: Vec<CourseCategory>, // Groups like "APPLIED MATH", "COMPUTATIONAL"
+}
+
+/// Summary per requirement for progress tracking
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequirementSummary {
+    pub category: String,
+    pub name: String,
+    pub status: RequirementStatus,
+    pub units_required: f32,
+    pub units_completed: f32,
+    pub units_remaining: f32,
+    pub subrequirements_count: usize,
+    pub completed_subrequirements: usize,
+}
+
+/// Recommended next course to take
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NextCourseRecommendation {
+    pub subrequirement_title: String,
+    pub priority: u32, // 1 = highest priority
+    pub eligible_courses: Vec<EligibleCourse>,
+    pub units_needed: f32,
+}
+
+/// Aggregated degree progress data for UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DegreeProgress {
+    pub audit_id: String,
+    pub student_info: StudentInfo,
+    pub total_units_required: f32,
+    pub total_units_completed: f32,
+    pub total_units_remaining: f32,
+    /// The units from transfer courses and AP/IB exams, which are included in
+    /// `total_units_completed`
+    pub transfer_units: f32,
+    /// Units from upper-division (100-199) courses, against the student's program's minimum
+    #[serde(default)]
+    pub upper_division: UnitProgress,
+    /// Units earned in residence (i.e., not from transfer or exam credit), against the
+    /// student's program's minimum
+    #[serde(default)]
+    pub residency: UnitProgress,
+    pub requirements_summary: Vec<RequirementSummary>,
+    pub next_courses_to_take: Vec<NextCourseRecommendation>,
+    /// The unit-weighted GPA of the student's letter-graded courses, if they have any
+    #[serde(default)]
+    pub gpa: Option<f32>,
+    /// Courses whose grades aren't on the grade scale, and so weren't counted as passing
+    #[serde(default)]
+    pub unknown_grades: Vec<UnknownGrade>,
+    /// Progress towards the requirement sets that the student defined themselves (see
+    /// `/users/:id/custom_requirements`), in the order they were created
+    #[serde(default)]
+    pub custom_requirements: Vec<CustomRequirementProgress>,
+}
+
+/// A course whose grade isn't on the grade scale
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnknownGrade {
+    pub course_code: String,
+    pub grade: String,
+}
+
+/// Progress towards a requirement set that the student defined themselves (e.g., a pre-med
+/// checklist)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomRequirementProgress {
+    pub requirement_id: i64,
+    pub name: String,
+    pub status: RequirementStatus,
+    pub units_required: f32,
+    pub units_completed: f32,
+    pub units_remaining: f32,
+    pub subrequirements: Vec<CustomSubrequirementProgress>,
+}
+
+/// Progress towards one subrequirement of a custom requirement set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomSubrequirementProgress {
+    pub title: String,
+    pub status: RequirementStatus,
+    pub units_required: f32,
+    pub units_completed: f32,
+    pub units_remaining: f32,
+    /// The passed or transferred courses that count towards the subrequirement
+    pub courses_completed: Vec<String>,
+    /// The courses being taken that would count towards the subrequirement once passed
+    pub courses_in_progress: Vec<String>,
+}
+
+/// Progress towards one of a program's unit minimums
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UnitProgress {
+    pub required: f32,
+    pub completed: f32,
+    pub remaining: f32,
+}
+
+impl UnitProgress {
+    /// Creates progress towards a minimum, given the units completed so far
+    pub fn new(required: f32, completed: f32) -> Self {
+        Self {
+            required,
+            completed,
+            remaining: (required - completed).max(0.0),
+        }
+    }
+}
+
+/// A version of the DARS report, as told apart by its markup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportVersion {
+    /// The uAchieve report, whose blocks are named in camel case (e.g., `div.requirement`
+    /// and `.reqTitle`).
+    Classic,
+    /// The restyled uAchieve Self-Service report, whose blocks are named in kebab case
+    /// (e.g., `div.requirement-block` and `.requirement-title`).
+    SelfService,
+}
+
+impl ReportVersion {
+    /// The version that's assumed when none can be detected.
+    pub const FALLBACK: ReportVersion = ReportVersion::Classic;
+}
+
+/// A degree audit, along with whether it came from an expired cache entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditFetch {
+    #[serde(flatten)]
+    pub audit: DegreeAudit,
+    /// Whether the audit has expired and is being refreshed in the background.
+    pub stale: bool,
+}
+
+/// A course placed in the plan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedCourse {
+    pub course_code: String,
+    pub subrequirement_title: String,
+    pub units: f32,
+}
+
+/// The courses planned for a single quarter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedTerm {
+    pub term: String,
+    pub units: f32,
+    pub courses: Vec<PlannedCourse>,
+}
+
+/// A subrequirement that couldn't be fully planned.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnmetNeed {
+    pub subrequirement_title: String,
+    pub courses_remaining: usize,
+    /// Eligible courses whose prerequisites couldn't be satisfied by completed or planned
+    /// courses.
+    pub blocked_by_prerequisites: Vec<String>,
+}
+
+/// A course that's only offered in one quarter of the year, and so can't easily be moved
+/// to another quarter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanWarning {
+    pub course_code: String,
+    pub subrequirement_title: String,
+    /// The season that the course is offered in (e.g., `fall`).
+    pub only_offered_in: String,
+    /// The quarter that the course was planned for, if it was.
+    pub planned_term: Option<String>,
+    pub message: String,
+}
+
+/// A multi-quarter plan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraduationPlan {
+    pub terms: Vec<PlannedTerm>,
+    pub unmet: Vec<UnmetNeed>,
+    /// Courses in the plan, or required by requirements that couldn't be planned, that are
+    /// only offered in one quarter of the year.
+    #[serde(default)]
+    pub warnings: Vec<PlanWarning>,
+    /// Whether every remaining requirement was planned.
+    pub complete: bool,
+}
@@ -0,0 +1,12 @@
+//! The request and response models of the webreg_scraper API, shared by the server and its
+//! client so that neither has to depend on the other.
+//!
+//! With the `webweg` feature, the copies of webweg's types can be converted to and from
+//! webweg's own.
+
+pub mod degree_audit;
+pub mod receipts;
+pub mod requests;
+pub mod retry;
+pub mod sessions;
+pub mod webweg;
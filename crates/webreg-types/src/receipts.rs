@@ -0,0 +1,43 @@
+//! Receipts for enrollment changes, which say whether each change can be seen in the
+//! student's schedule afterward.
+
+use serde::{Deserialize, Serialize};
+
+use crate::webweg::ScheduledSection;
+
+/// A change to a student's enrollment.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EnrollmentAction {
+    Add,
+    Drop,
+}
+
+impl EnrollmentAction {
+    /// The name of the action, as stored in the action log.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnrollmentAction::Add => "add",
+            EnrollmentAction::Drop => "drop",
+        }
+    }
+}
+
+/// The outcome of an enrollment change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnrollmentReceipt {
+    /// The ID of the change in the action log, if it could be recorded.
+    pub action_id: Option<i64>,
+    pub action: EnrollmentAction,
+    pub section_id: String,
+    /// Whether WebReg said that the change succeeded.
+    pub webreg_success: bool,
+    /// Whether the change can be seen in the student's schedule.
+    pub verified: bool,
+    /// The section as it appeared in the student's schedule before the change, if it did.
+    pub before: Option<ScheduledSection>,
+    /// The section as it appears in the student's schedule after the change, if it does.
+    pub after: Option<ScheduledSection>,
+    /// When the change was made, in RFC 3339 format.
+    pub timestamp: String,
+}
@@ -0,0 +1,322 @@
+//! The bodies and query strings of API requests.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "webweg")]
+use webweg::wrapper::input_types::{
+    CourseLevelFilter, DayOfWeek, SearchRequestBuilder, SearchType,
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodySectionId {
+    #[serde(rename = "sectionId")]
+    pub section_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodySectionScheduleNameId {
+    #[serde(rename = "sectionId")]
+    pub section_id: String,
+
+    #[serde(rename = "scheduleName")]
+    pub schedule_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyScheduleNameChange {
+    #[serde(rename = "oldName")]
+    pub old_name: String,
+
+    #[serde(rename = "newName")]
+    pub new_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyAddInfo {
+    #[serde(rename = "sectionId")]
+    pub section_id: String,
+    #[serde(rename = "gradingOption")]
+    pub grading_option: Option<String>,
+    #[serde(rename = "unitCount")]
+    pub unit_count: Option<i64>,
+    #[serde(rename = "validate")]
+    pub validate: Option<bool>,
+}
+
+/// A structure meant for a request body, used to drop one section and add another in its
+/// place.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodySwapSections {
+    #[serde(rename = "dropSectionId")]
+    pub drop_section_id: String,
+    pub add: BodyAddInfo,
+}
+
+/// A structure meant for a request body, used to add several sections at once.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyBulkAdd {
+    pub sections: Vec<BodyAddInfo>,
+}
+
+/// A structure meant for a request body, used to look several courses up at once.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyCourseInfoBatch {
+    pub courses: Vec<CourseQueryStr>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyPlanAdd {
+    #[serde(rename = "subjectCode")]
+    pub subject_code: String,
+    #[serde(rename = "courseCode")]
+    pub course_code: String,
+    #[serde(rename = "sectionId")]
+    pub section_id: String,
+    #[serde(rename = "sectionCode")]
+    pub section_code: String,
+    #[serde(rename = "gradingOption")]
+    pub grading_option: Option<String>,
+    #[serde(rename = "scheduleName")]
+    pub schedule_name: Option<String>,
+    #[serde(rename = "unitCount")]
+    pub unit_count: i64,
+    pub validate: Option<bool>,
+}
+
+/// A structure meant for a request body, used to register a student's WebReg cookies.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodySessionCookies {
+    pub cookies: String,
+}
+
+/// A structure meant for a query string, intended to require the user to provide a name
+/// for the schedule.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ScheduleQueryStr {
+    pub name: Option<String>,
+}
+
+/// A structure meant for a query string, intended to have the user provide a course to
+/// search up in some way.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CourseQueryStr {
+    pub subject: String,
+    pub number: String,
+}
+
+/// A structure meant for a query string, intended to let the user skip the checks made
+/// before a section is added
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ForceQueryStr {
+    pub force: Option<bool>,
+}
+
+/// A structure meant for a query string, intended to have the user search for courses and
+/// narrow down the results. Lists (e.g., `subjects` and `days`) are comma-separated
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SearchV2QueryStr {
+    pub subjects: Option<String>,
+    pub departments: Option<String>,
+    pub title: Option<String>,
+    #[serde(rename = "onlyOpen")]
+    pub only_open: Option<bool>,
+    /// Only include sections taught by someone whose name contains this.
+    pub instructor: Option<String>,
+    /// The days that sections may meet on (e.g., `Tu,Th`).
+    pub days: Option<String>,
+    /// The earliest that sections may start, in `HH:MM` format.
+    #[serde(rename = "startTime")]
+    pub start_time: Option<String>,
+    /// The latest that sections may end, in `HH:MM` format.
+    #[serde(rename = "endTime")]
+    pub end_time: Option<String>,
+    #[serde(rename = "minUnits")]
+    pub min_units: Option<f32>,
+    #[serde(rename = "maxUnits")]
+    pub max_units: Option<f32>,
+    /// A college requirement category (e.g., `Natural Sciences`) that courses must count
+    /// towards.
+    #[serde(rename = "geCategory")]
+    pub ge_category: Option<String>,
+    /// Only look at this college's requirement categories (e.g., `RE`).
+    pub college: Option<String>,
+    /// `course` (the default), `units`, or `seats`.
+    pub sort: Option<String>,
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// A structure meant for a query string, intended to have the user provide a "list" of
+/// subject code (e.g., CSE)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SubjListQueryStr {
+    pub subjects: String,
+}
+
+/// Query parameters for degree audit endpoints.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditQueryParams {
+    /// If true, bypass cache and fetch fresh data
+    #[serde(default)]
+    pub refresh: bool,
+    /// The most seconds to wait for a new audit to be generated, clamped to the configured
+    /// maximum. Only `/degree_audit` uses it
+    pub timeout: Option<u64>,
+    /// If true, leave out the student's name and PID, and hash the audit's ID (see
+    /// `degree_audit::anonymize`)
+    #[serde(default)]
+    pub anonymize: bool,
+    /// If true, a new audit is generated in the background instead of being waited for.
+    /// Only `/degree_audit` uses it
+    #[serde(default, rename = "async")]
+    pub run_async: bool,
+}
+
+/// Query parameters for the graduation plan endpoint.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraduationPlanQueryParams {
+    /// If true, bypass cache and fetch fresh data
+    #[serde(default)]
+    pub refresh: bool,
+    /// The maximum number of units to plan for each quarter
+    pub units_per_term: Option<f32>,
+    /// The first quarter to plan (e.g., `FA24`). Defaults to the latest regular quarter
+    /// that the scraper is configured for
+    pub start_term: Option<String>,
+    /// If true, hash the audit's ID (see `degree_audit::anonymize`)
+    #[serde(default)]
+    pub anonymize: bool,
+}
+
+// https://serde.rs/enum-representations.html#untagged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+pub enum BodySearchType {
+    SectionId {
+        #[serde(rename = "sectionId")]
+        section_id: String,
+    },
+    SectionIds {
+        #[serde(rename = "sectionIds")]
+        section_ids: Vec<String>,
+    },
+    SearchAdvanced {
+        subjects: Option<Vec<String>>,
+        courses: Option<Vec<String>>,
+        departments: Option<Vec<String>>,
+        instructor: Option<String>,
+        title: Option<String>,
+        #[serde(rename = "onlyOpen")]
+        only_open: Option<bool>,
+        #[serde(rename = "startHour")]
+        start_hour: Option<i64>,
+        #[serde(rename = "startMin")]
+        start_min: Option<i64>,
+        #[serde(rename = "endHour")]
+        end_hour: Option<i64>,
+        #[serde(rename = "endMin")]
+        end_min: Option<i64>,
+        days: Option<Vec<String>>,
+        #[serde(rename = "levelFilter")]
+        level_filter: Option<Vec<String>>,
+    },
+}
+
+#[cfg(feature = "webweg")]
+impl From<BodySearchType> for SearchType {
+    fn from(value: BodySearchType) -> Self {
+        match value {
+            BodySearchType::SectionId { section_id } => SearchType::BySection(section_id),
+            BodySearchType::SectionIds { section_ids } => {
+                SearchType::ByMultipleSections(section_ids)
+            }
+            BodySearchType::SearchAdvanced {
+                subjects,
+                courses,
+                departments,
+                instructor,
+                title,
+                only_open,
+                start_hour,
+                start_min,
+                end_hour,
+                end_min,
+                days,
+                level_filter,
+            } => {
+                let mut search = SearchRequestBuilder::new();
+                if let Some(s) = subjects {
+                    search.subjects = s;
+                }
+
+                if let Some(c) = courses {
+                    search.courses = c;
+                }
+
+                if let Some(d) = departments {
+                    search.departments = d;
+                }
+
+                if let Some(i) = instructor {
+                    search = search.set_instructor(i);
+                }
+
+                if let Some(t) = title {
+                    search = search.set_title(t);
+                }
+
+                if let Some(o) = only_open {
+                    search.only_open = o;
+                }
+
+                if let (Some(h), Some(m)) = (
+                    start_hour.and_then(|h| u32::try_from(h).ok()),
+                    start_min.and_then(|m| u32::try_from(m).ok()),
+                ) {
+                    search = search.set_start_time(h, m);
+                }
+
+                if let (Some(h), Some(m)) = (
+                    end_hour.and_then(|h| u32::try_from(h).ok()),
+                    end_min.and_then(|m| u32::try_from(m).ok()),
+                ) {
+                    search = search.set_end_time(h, m);
+                }
+
+                if let Some(d) = days {
+                    for day in d {
+                        match day.as_str() {
+                            "M" | "m" => search = search.apply_day(DayOfWeek::Monday),
+                            "Tu" | "tu" => search = search.apply_day(DayOfWeek::Tuesday),
+                            "W" | "w" => search = search.apply_day(DayOfWeek::Wednesday),
+                            "Th" | "th" => search = search.apply_day(DayOfWeek::Thursday),
+                            "F" | "f" => search = search.apply_day(DayOfWeek::Friday),
+                            "Sa" | "sa" => search = search.apply_day(DayOfWeek::Saturday),
+                            "Su" | "su" => search = search.apply_day(DayOfWeek::Sunday),
+                            _ => {}
+                        }
+                    }
+                }
+
+                if let Some(f) = level_filter {
+                    for level in f {
+                        match level.as_str() {
+                            "l" | "L" => {
+                                search = search.filter_courses_by(CourseLevelFilter::LowerDivision)
+                            }
+                            "u" | "U" => {
+                                search = search.filter_courses_by(CourseLevelFilter::UpperDivision)
+                            }
+                            "g" | "G" => {
+                                search = search.filter_courses_by(CourseLevelFilter::Graduate)
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+
+                SearchType::Advanced(search)
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! The backoff between retries, which the server uses for WebReg requests and clients use
+//! for API requests.
+
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Gets how long to wait before a retry.
+///
+/// The delay starts at `base` and doubles with each retry, up to `max`. With `jitter`, a
+/// random part of up to half of the delay is taken off, so that requests that failed
+/// together aren't retried together.
+///
+/// # Parameters
+/// - `base`: The delay before the first retry.
+/// - `max`: The longest delay.
+/// - `retry`: The number of retries made before this one.
+/// - `jitter`: Whether to randomize the delay.
+///
+/// # Returns
+/// The delay.
+pub fn backoff(base: Duration, max: Duration, retry: u32, jitter: bool) -> Duration {
+    let delay = base.saturating_mul(1 << retry.min(16)).min(max);
+    if jitter && !delay.is_zero() {
+        delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    } else {
+        delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let base = Duration::from_secs(1);
+        let max = Duration::from_secs(3);
+        assert_eq!(Duration::from_secs(1), backoff(base, max, 0, false));
+        assert_eq!(Duration::from_secs(2), backoff(base, max, 1, false));
+        assert_eq!(Duration::from_secs(3), backoff(base, max, 2, false));
+        assert_eq!(Duration::from_secs(3), backoff(base, max, 40, false));
+
+        for retry in 0..4 {
+            let cap = backoff(base, max, retry, false);
+            let delay = backoff(base, max, retry, true);
+            assert!(
+                cap / 2 <= delay && delay <= cap,
+                "{delay:?} for retry {retry}"
+            );
+        }
+    }
+}
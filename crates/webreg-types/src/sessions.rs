@@ -0,0 +1,20 @@
+//! Students' WebReg sessions, which clients register their cookies with once and then
+//! refer to by token.
+
+use serde::{Deserialize, Serialize};
+
+/// The header that clients use to pass their session token.
+pub const SESSION_TOKEN_HEADER: &str = "X-Session-Token";
+
+/// Public information about a session. This never includes the cookies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    /// When the session was created, in RFC 3339 format.
+    pub created_at: String,
+    /// When the session was last used, in RFC 3339 format.
+    pub last_used: Option<String>,
+    /// The number of requests made in the current rate limit window.
+    pub window_requests: usize,
+    /// The maximum number of requests allowed per window.
+    pub rate_limit: usize,
+}
@@ -0,0 +1,288 @@
+//! Copies of webweg's types that can be deserialized.
+//!
+//! webweg's types can only be serialized, so anything that reads them back (e.g., a stored
+//! snapshot of a student's schedule, or a client reading a response) goes through these
+//! instead. Each serializes the same way as the webweg type that it copies, and with the
+//! `webweg` feature, can be converted to and from it.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "webweg")]
+use webweg::types as ww;
+
+/// An hour or minute (see `webweg::types::TimeType`).
+pub type TimeType = u32;
+
+/// A meeting of a section (see `webweg::types::Meeting`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Meeting {
+    pub meeting_type: String,
+    pub meeting_days: MeetingDay,
+    pub start_hr: TimeType,
+    pub start_min: TimeType,
+    pub end_hr: TimeType,
+    pub end_min: TimeType,
+    pub building: String,
+    pub room: String,
+    pub instructors: Vec<String>,
+}
+
+/// The days that a meeting is on (see `webweg::types::MeetingDay`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MeetingDay {
+    Repeated(Vec<String>),
+    OneTime(String),
+    None,
+}
+
+/// A section in a student's schedule (see `webweg::types::ScheduledSection`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledSection {
+    pub section_id: String,
+    pub subject_code: String,
+    pub course_code: String,
+    pub course_title: String,
+    pub section_code: String,
+    pub section_capacity: i64,
+    pub enrolled_count: i64,
+    pub available_seats: i64,
+    pub grade_option: String,
+    pub all_instructors: Vec<String>,
+    pub units: i64,
+    pub enrolled_status: EnrollmentStatus,
+    pub waitlist_ct: i64,
+    pub meetings: Vec<Meeting>,
+}
+
+/// A student's enrollment in a section (see `webweg::types::EnrollmentStatus`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "enroll_status")]
+pub enum EnrollmentStatus {
+    Enrolled,
+    Waitlist { waitlist_pos: i64 },
+    Planned,
+    Unknown,
+}
+
+/// A section of a course (see `webweg::types::CourseSection`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CourseSection {
+    pub subj_course_id: String,
+    pub section_id: String,
+    pub section_code: String,
+    pub all_instructors: Vec<String>,
+    pub available_seats: i64,
+    pub enrolled_ct: i64,
+    pub total_seats: i64,
+    pub waitlist_ct: i64,
+    pub meetings: Vec<Meeting>,
+    pub is_visible: bool,
+}
+
+/// A course found by searching (see `webweg::types::SearchResultItem`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchResultItem {
+    pub subj_code: String,
+    pub course_code: String,
+    pub course_title: String,
+}
+
+/// A course's prerequisites (see `webweg::types::PrerequisiteInfo`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrerequisiteInfo {
+    /// Each inner list is a set of courses, one of which must have been taken.
+    pub course_prerequisites: Vec<Vec<CoursePrerequisite>>,
+    pub exam_prerequisites: Vec<String>,
+}
+
+/// A course that's a prerequisite (see `webweg::types::CoursePrerequisite`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoursePrerequisite {
+    pub subj_course_id: String,
+    pub course_title: String,
+}
+
+/// An event on a student's WebReg calendar (see `webweg::types::Event`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Event {
+    pub location: String,
+    pub start_hr: TimeType,
+    pub start_min: TimeType,
+    pub end_hr: TimeType,
+    pub end_min: TimeType,
+    pub name: String,
+    pub days: Vec<String>,
+    pub timestamp: String,
+}
+
+/// A term that's on WebReg (see `webweg::types::Term`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Term {
+    pub seq_id: i64,
+    pub term_code: String,
+}
+
+#[cfg(feature = "webweg")]
+impl From<Meeting> for ww::Meeting {
+    fn from(meeting: Meeting) -> Self {
+        Self {
+            meeting_type: meeting.meeting_type,
+            meeting_days: match meeting.meeting_days {
+                MeetingDay::Repeated(days) => ww::MeetingDay::Repeated(days),
+                MeetingDay::OneTime(day) => ww::MeetingDay::OneTime(day),
+                MeetingDay::None => ww::MeetingDay::None,
+            },
+            start_hr: meeting.start_hr,
+            start_min: meeting.start_min,
+            end_hr: meeting.end_hr,
+            end_min: meeting.end_min,
+            building: meeting.building,
+            room: meeting.room,
+            instructors: meeting.instructors,
+        }
+    }
+}
+
+#[cfg(feature = "webweg")]
+impl From<ScheduledSection> for ww::ScheduledSection {
+    fn from(section: ScheduledSection) -> Self {
+        Self {
+            section_id: section.section_id,
+            subject_code: section.subject_code,
+            course_code: section.course_code,
+            course_title: section.course_title,
+            section_code: section.section_code,
+            section_capacity: section.section_capacity,
+            enrolled_count: section.enrolled_count,
+            available_seats: section.available_seats,
+            grade_option: section.grade_option,
+            all_instructors: section.all_instructors,
+            units: section.units,
+            enrolled_status: match section.enrolled_status {
+                EnrollmentStatus::Enrolled => ww::EnrollmentStatus::Enrolled,
+                EnrollmentStatus::Waitlist { waitlist_pos } => {
+                    ww::EnrollmentStatus::Waitlist { waitlist_pos }
+                }
+                EnrollmentStatus::Planned => ww::EnrollmentStatus::Planned,
+                EnrollmentStatus::Unknown => ww::EnrollmentStatus::Unknown,
+            },
+            waitlist_ct: section.waitlist_ct,
+            meetings: section.meetings.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[cfg(feature = "webweg")]
+impl From<ww::Meeting> for Meeting {
+    fn from(meeting: ww::Meeting) -> Self {
+        Self {
+            meeting_type: meeting.meeting_type,
+            meeting_days: match meeting.meeting_days {
+                ww::MeetingDay::Repeated(days) => MeetingDay::Repeated(days),
+                ww::MeetingDay::OneTime(day) => MeetingDay::OneTime(day),
+                ww::MeetingDay::None => MeetingDay::None,
+            },
+            start_hr: meeting.start_hr,
+            start_min: meeting.start_min,
+            end_hr: meeting.end_hr,
+            end_min: meeting.end_min,
+            building: meeting.building,
+            room: meeting.room,
+            instructors: meeting.instructors,
+        }
+    }
+}
+
+#[cfg(feature = "webweg")]
+impl From<ww::ScheduledSection> for ScheduledSection {
+    fn from(section: ww::ScheduledSection) -> Self {
+        Self {
+            section_id: section.section_id,
+            subject_code: section.subject_code,
+            course_code: section.course_code,
+            course_title: section.course_title,
+            section_code: section.section_code,
+            section_capacity: section.section_capacity,
+            enrolled_count: section.enrolled_count,
+            available_seats: section.available_seats,
+            grade_option: section.grade_option,
+            all_instructors: section.all_instructors,
+            units: section.units,
+            enrolled_status: match section.enrolled_status {
+                ww::EnrollmentStatus::Enrolled => EnrollmentStatus::Enrolled,
+                ww::EnrollmentStatus::Waitlist { waitlist_pos } => {
+                    EnrollmentStatus::Waitlist { waitlist_pos }
+                }
+                ww::EnrollmentStatus::Planned => EnrollmentStatus::Planned,
+                ww::EnrollmentStatus::Unknown => EnrollmentStatus::Unknown,
+            },
+            waitlist_ct: section.waitlist_ct,
+            meetings: section.meetings.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "webweg"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheduled_section_round_trip() {
+        let section = ww::ScheduledSection {
+            section_id: "123456".to_string(),
+            subject_code: "CSE".to_string(),
+            course_code: "100".to_string(),
+            course_title: "Advanced Data Structures".to_string(),
+            section_code: "A01".to_string(),
+            section_capacity: 100,
+            enrolled_count: 99,
+            available_seats: 0,
+            grade_option: "L".to_string(),
+            all_instructors: vec!["Doe, Jane".to_string()],
+            units: 4,
+            enrolled_status: ww::EnrollmentStatus::Waitlist { waitlist_pos: 3 },
+            waitlist_ct: 5,
+            meetings: vec![
+                ww::Meeting {
+                    meeting_type: "LE".to_string(),
+                    meeting_days: ww::MeetingDay::Repeated(vec!["M".into(), "W".into()]),
+                    start_hr: 9,
+                    start_min: 0,
+                    end_hr: 9,
+                    end_min: 50,
+                    building: "CENTR".to_string(),
+                    room: "101".to_string(),
+                    instructors: vec!["Doe, Jane".to_string()],
+                },
+                ww::Meeting {
+                    meeting_type: "FI".to_string(),
+                    meeting_days: ww::MeetingDay::OneTime("2024-12-10".to_string()),
+                    start_hr: 8,
+                    start_min: 0,
+                    end_hr: 10,
+                    end_min: 59,
+                    building: "CENTR".to_string(),
+                    room: "101".to_string(),
+                    instructors: vec![],
+                },
+                ww::Meeting {
+                    meeting_type: "DI".to_string(),
+                    meeting_days: ww::MeetingDay::None,
+                    start_hr: 0,
+                    start_min: 0,
+                    end_hr: 0,
+                    end_min: 0,
+                    building: "TBA".to_string(),
+                    room: "TBA".to_string(),
+                    instructors: vec![],
+                },
+            ],
+        };
+
+        let json = serde_json::to_value(&section).unwrap();
+        let copy: ScheduledSection = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(json, serde_json::to_value(&copy).unwrap());
+        assert_eq!(section, ww::ScheduledSection::from(copy.clone()));
+        assert_eq!(copy, ScheduledSection::from(section));
+    }
+}
@@ -0,0 +1,121 @@
+//! Benchmarks for the part of `/add_section` that runs locally: looking the new section and
+//! the student's schedule up in the database, and checking the new section against the
+//! schedule for duplicate courses and time conflicts. The rest of the endpoint waits on
+//! WebReg itself, so it isn't benchmarked.
+
+use std::process::ExitCode;
+
+use criterion::{criterion_group, Criterion};
+use webreg::db::ScheduleDbManager;
+use webreg::schedule::{check_add, MeetingDates, MeetingSlot, ScheduledSection, SubSession};
+use webweg::types::{CourseSection, Meeting, MeetingDay};
+
+mod common;
+
+const TERM: &str = "FA24";
+/// About the size of a fall term.
+const COURSES: usize = 1500;
+/// About as many sections as a student is enrolled in.
+const SCHEDULE_SIZE: usize = 5;
+
+/// The largest slowdown each benchmark may have against the baseline.
+const THRESHOLDS: &[(&str, f64)] = &[("add_section/guard", 0.10), ("add_section/check", 0.10)];
+
+/// Creates an in-memory database holding a synthetic term, where every course has one
+/// section with a lecture and a discussion, spread out over the week.
+fn fixture() -> ScheduleDbManager {
+    let db = ScheduleDbManager::new(":memory:");
+    let meeting = |meeting_type: &str, days: &[&str], start_hr: u32| Meeting {
+        meeting_type: meeting_type.to_owned(),
+        meeting_days: MeetingDay::Repeated(days.iter().map(|d| d.to_string()).collect()),
+        start_min: 0,
+        start_hr,
+        end_min: 50,
+        end_hr: start_hr,
+        building: "CENTR".to_owned(),
+        room: "101".to_owned(),
+        instructors: vec!["Doe, Jane".to_owned()],
+    };
+
+    for course in 0..COURSES {
+        let start_hr = 8 + (course % 10) as u32;
+        let section = CourseSection {
+            subj_course_id: course_name(course),
+            section_id: section_id(course),
+            section_code: "A01".to_owned(),
+            all_instructors: vec!["Doe, Jane".to_owned()],
+            available_seats: 10,
+            enrolled_ct: 90,
+            total_seats: 100,
+            waitlist_ct: 0,
+            meetings: vec![
+                meeting("LE", &["M", "W", "F"], start_hr),
+                meeting("DI", &["Tu"], start_hr),
+            ],
+            is_visible: true,
+        };
+
+        db.insert_course_with_sections(TERM, vec![section], None, &MeetingDates::default())
+            .expect("Failed to insert fixture");
+    }
+
+    db
+}
+
+fn course_name(course: usize) -> String {
+    format!("S{:03} {}", course / 20, course % 20)
+}
+
+fn section_id(course: usize) -> String {
+    format!("{}", 100_000 + course)
+}
+
+/// Looks a section up, as the endpoint does before checking it.
+fn lookup(db: &ScheduleDbManager, section_id: &str) -> (String, ScheduledSection) {
+    let (course, section, meetings) = db.get_section(TERM, section_id).unwrap().unwrap();
+    let scheduled = ScheduledSection {
+        term: TERM.to_owned(),
+        title: format!("{} ({})", course.trim(), section.section_code.trim()),
+        section_id: section.section_id,
+        sub_session: SubSession::from_term(TERM),
+        slots: meetings.iter().filter_map(MeetingSlot::from_db).collect(),
+    };
+    (course, scheduled)
+}
+
+fn bench_add_section(c: &mut Criterion) {
+    let db = fixture();
+    // Sections that don't overlap each other, or the new section, so that every meeting is
+    // compared
+    let schedule_ids: Vec<_> = (1..=SCHEDULE_SIZE).map(section_id).collect();
+    let new_id = section_id(0);
+
+    let mut group = c.benchmark_group("add_section");
+    group.bench_function("guard", |b| {
+        b.iter(|| {
+            let (course, new_section) = lookup(&db, &new_id);
+            let schedule: Vec<_> = schedule_ids.iter().map(|id| lookup(&db, id)).collect();
+            check_add(&course, &new_section, &schedule)
+        })
+    });
+
+    let (course, new_section) = lookup(&db, &new_id);
+    let schedule: Vec<_> = schedule_ids.iter().map(|id| lookup(&db, id)).collect();
+    assert!(check_add(&course, &new_section, &schedule).is_none());
+    group.bench_function("check", |b| {
+        b.iter(|| check_add(&course, &new_section, &schedule))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_add_section);
+
+fn main() -> ExitCode {
+    common::clear_changes(THRESHOLDS);
+    benches();
+    criterion::Criterion::default()
+        .configure_from_args()
+        .final_summary();
+    common::check_regressions(THRESHOLDS)
+}
@@ -0,0 +1,86 @@
+//! Regression thresholds for the benchmarks.
+//!
+//! When a run is compared against a saved baseline (`cargo bench -- --baseline <name>`),
+//! Criterion records each benchmark's change in `change/estimates.json`. After the
+//! benchmarks run, [`check_regressions`] fails the run if any of them got slower than its
+//! threshold allows, so that a regression can't go unnoticed in the output. The changes
+//! left by earlier runs are cleared first (see [`clear_changes`]), so that a benchmark that
+//! didn't run this time (e.g., because it was filtered out) isn't judged by an old result.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use serde_json::Value;
+
+/// Removes the changes that earlier runs recorded for each benchmark, so that only the
+/// changes recorded by this run are checked.
+///
+/// # Parameters
+/// - `thresholds`: Each benchmark's ID (`group/function`), along with its threshold.
+pub fn clear_changes(thresholds: &[(&str, f64)]) {
+    let dir = criterion_dir();
+    for (id, _) in thresholds {
+        let path = dir.join(id).join("change");
+        if let Err(e) = std::fs::remove_dir_all(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("{id}: couldn't clear {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// Checks each benchmark's change against its threshold.
+///
+/// # Parameters
+/// - `thresholds`: Each benchmark's ID (`group/function`), along with the largest slowdown
+///   it may have, as a fraction (e.g., `0.1` for 10%).
+///
+/// # Returns
+/// A failure if any benchmark is slower than its threshold allows, even at the lower
+/// bound of its confidence interval.
+pub fn check_regressions(thresholds: &[(&str, f64)]) -> ExitCode {
+    let dir = criterion_dir();
+    let mut regressed = false;
+    for (id, threshold) in thresholds {
+        let path = dir.join(id).join("change").join("estimates.json");
+        let Ok(raw) = std::fs::read_to_string(&path) else {
+            // Nothing to compare against yet, or the benchmark didn't run
+            continue;
+        };
+
+        let Some(lower_bound) = serde_json::from_str::<Value>(&raw)
+            .ok()
+            .and_then(|v| v["mean"]["confidence_interval"]["lower_bound"].as_f64())
+        else {
+            eprintln!("{id}: couldn't read {}", path.display());
+            continue;
+        };
+
+        if lower_bound > *threshold {
+            eprintln!(
+                "{id}: at least {:.1}% slower than the baseline (threshold: {:.1}%)",
+                lower_bound * 100.0,
+                threshold * 100.0
+            );
+            regressed = true;
+        }
+    }
+
+    if regressed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// The directory that Criterion writes its results to.
+fn criterion_dir() -> PathBuf {
+    if let Ok(home) = std::env::var("CRITERION_HOME") {
+        return PathBuf::from(home);
+    }
+
+    std::env::var("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../target"))
+        .join("criterion")
+}
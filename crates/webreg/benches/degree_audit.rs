@@ -0,0 +1,116 @@
+//! Benchmarks for degree audits: parsing an audit's HTML, and computing progress from the
+//! parsed audit.
+
+use std::fmt::Write;
+use std::process::ExitCode;
+
+use criterion::{criterion_group, Criterion};
+use webreg::degree_audit::config::RequirementsConfig;
+use webreg::degree_audit::{parse_degree_audit_html, DegreeAuditResponse, DegreeProgressProcessor};
+
+mod common;
+
+/// About the size of a double major's audit, which is the largest that DARS produces.
+const REQUIREMENTS: usize = 40;
+const SUBREQUIREMENTS_PER_REQUIREMENT: usize = 6;
+const COURSES_PER_SUBREQUIREMENT: usize = 8;
+
+/// The largest slowdown each benchmark may have against the baseline.
+const THRESHOLDS: &[(&str, f64)] = &[
+    ("degree_audit/parse", 0.10),
+    ("degree_audit/progress", 0.10),
+];
+
+/// Creates a synthetic audit page in the layout that DARS uses, where each subrequirement
+/// has courses that were taken, courses that can still be taken, and what it still needs.
+fn fixture() -> DegreeAuditResponse {
+    let mut html = String::from(
+        r#"<html><head><script>var x = 1;</script></head><body>
+        <div id="headerInfo"><span class="float-right">Jane Doe</span></div>
+        <div class="includeTopText">Major(s): CS26</div>"#,
+    );
+
+    for req in 0..REQUIREMENTS {
+        let _ = write!(
+            html,
+            r#"<div class="requirement Status_IP category_Major" id="r{req}" rqdhours="48">
+            <div class="reqTitle">Requirement {req}</div>"#
+        );
+
+        for sub in 0..SUBREQUIREMENTS_PER_REQUIREMENT {
+            let _ = write!(
+                html,
+                r#"<div class="subrequirement Status_NO" id="r{req}s{sub}" rqdhours="8" rqdcourses="2">
+                <span class="subreqTitle">Subrequirement {sub}</span>
+                <table class="subreqNeeds"><tr>
+                    <td class="count">NEEDS:</td>
+                    <td class="hours"><span class="hours number">8.00</span> UNITS</td>
+                    <td class="count"><span class="count number">2</span> COURSES</td>
+                </tr></table>
+                <table class="completedCourses">"#
+            );
+            for course in 0..COURSES_PER_SUBREQUIREMENT {
+                let _ = write!(
+                    html,
+                    r#"<tr class="takenCourse">
+                        <td class="term">FA2{}</td><td class="course">CSE {req}{sub}{course}</td>
+                        <td class="credit">4.0</td><td class="grade">A-</td>
+                        <td class="description"><span class="descLine">COURSE TITLE</span></td>
+                    </tr>"#,
+                    course % 4
+                );
+            }
+            html.push_str(
+                r#"</table><table class="selectcourses"><tr><td class="fromcourselist">"#,
+            );
+            for course in 0..COURSES_PER_SUBREQUIREMENT {
+                let _ = write!(
+                    html,
+                    r#"<span class="course" department="MATH" number="1{req}{course}">
+                        <span class="number">MATH 1{req}{course}</span>
+                    </span>"#
+                );
+            }
+            html.push_str("</td></tr></table></div>");
+        }
+
+        html.push_str("</div>");
+    }
+
+    html.push_str("</body></html>");
+    DegreeAuditResponse {
+        audit_id: "bench".to_owned(),
+        scraped_at: "2024-10-01T00:00:00Z".to_owned(),
+        url: String::new(),
+        html,
+    }
+}
+
+fn bench_degree_audit(c: &mut Criterion) {
+    let raw = fixture();
+    let mut group = c.benchmark_group("degree_audit");
+    group.sample_size(20);
+
+    group.bench_function("parse", |b| {
+        b.iter(|| parse_degree_audit_html(&raw).unwrap())
+    });
+
+    let audit = parse_degree_audit_html(&raw).unwrap();
+    let processor = DegreeProgressProcessor::new(RequirementsConfig::default());
+    group.bench_function("progress", |b| {
+        b.iter(|| processor.compute_degree_progress(&audit).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_degree_audit);
+
+fn main() -> ExitCode {
+    common::clear_changes(THRESHOLDS);
+    benches();
+    criterion::Criterion::default()
+        .configure_from_args()
+        .final_summary();
+    common::check_regressions(THRESHOLDS)
+}
@@ -0,0 +1,124 @@
+//! Benchmarks for serving a term's schedule data: reading it from the database, and
+//! formatting it as `/schedule_data`'s JSON and as `/schedule_data/export`'s CSV.
+
+use std::process::ExitCode;
+
+use criterion::{criterion_group, BatchSize, Criterion, Throughput};
+use webreg::db::ScheduleDbManager;
+use webreg::schedule::{csv_row, section_json, MeetingDates, CSV_HEADER};
+use webweg::types::{CourseSection, Meeting, MeetingDay};
+
+mod common;
+
+const TERM: &str = "FA24";
+/// About the size of a fall term.
+const COURSES: usize = 1500;
+const SECTIONS_PER_COURSE: usize = 4;
+
+/// The largest slowdown each benchmark may have against the baseline.
+const THRESHOLDS: &[(&str, f64)] = &[
+    ("schedule_data/load", 0.10),
+    ("schedule_data/json", 0.10),
+    ("schedule_data/csv", 0.10),
+];
+
+/// Creates an in-memory database holding a synthetic term, where every section has a
+/// lecture, a discussion, and a final.
+fn fixture() -> ScheduleDbManager {
+    let db = ScheduleDbManager::new(":memory:");
+    let meeting = |meeting_type: &str, days: MeetingDay, start_hr: u32| Meeting {
+        meeting_type: meeting_type.to_owned(),
+        meeting_days: days,
+        start_min: 0,
+        start_hr,
+        end_min: 50,
+        end_hr: start_hr,
+        building: "CENTR".to_owned(),
+        room: format!("{}", 100 + start_hr),
+        instructors: vec!["Doe, Jane".to_owned(), "Smith, Sam".to_owned()],
+    };
+
+    for course in 0..COURSES {
+        let subj_course_id = format!("S{:03} {}", course / 20, course % 200);
+        let sections = (0..SECTIONS_PER_COURSE)
+            .map(|section| CourseSection {
+                subj_course_id: subj_course_id.clone(),
+                section_id: format!("{}", 100_000 + course * SECTIONS_PER_COURSE + section),
+                section_code: format!("A{:02}", section + 1),
+                all_instructors: vec!["Doe, Jane".to_owned()],
+                available_seats: 10,
+                enrolled_ct: 90,
+                total_seats: 100,
+                waitlist_ct: 0,
+                meetings: vec![
+                    meeting(
+                        "LE",
+                        MeetingDay::Repeated(vec!["M".to_owned(), "W".to_owned(), "F".to_owned()]),
+                        9,
+                    ),
+                    meeting("DI", MeetingDay::Repeated(vec!["Tu".to_owned()]), 14),
+                    meeting("FI", MeetingDay::OneTime("2024-12-09".to_owned()), 8),
+                ],
+                is_visible: true,
+            })
+            .collect();
+
+        db.insert_course_with_sections(TERM, sections, None, &MeetingDates::default())
+            .expect("Failed to insert fixture");
+    }
+
+    db
+}
+
+fn bench_schedule_data(c: &mut Criterion) {
+    let db = fixture();
+    let mut group = c.benchmark_group("schedule_data");
+    group
+        .sample_size(20)
+        .throughput(Throughput::Elements((COURSES * SECTIONS_PER_COURSE) as u64));
+
+    group.bench_function("load", |b| {
+        b.iter(|| db.get_all_sections_for_term(TERM, false).unwrap())
+    });
+
+    let data = db.get_all_sections_for_term(TERM, false).unwrap();
+    group.bench_function("json", |b| {
+        b.iter_batched(
+            || data.clone(),
+            |data| {
+                let response: Vec<_> = data
+                    .into_iter()
+                    .map(|(section, meetings)| section_json(section, meetings, None))
+                    .collect();
+                serde_json::to_vec(&response).unwrap()
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let rows = db
+        .get_schedule_export_page(TERM, 0, COURSES * SECTIONS_PER_COURSE)
+        .unwrap();
+    group.bench_function("csv", |b| {
+        b.iter(|| {
+            let mut csv = String::from(CSV_HEADER);
+            for row in &rows {
+                csv.push_str(&csv_row(row));
+            }
+            csv
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_schedule_data);
+
+fn main() -> ExitCode {
+    common::clear_changes(THRESHOLDS);
+    benches();
+    criterion::Criterion::default()
+        .configure_from_args()
+        .final_summary();
+    common::check_regressions(THRESHOLDS)
+}
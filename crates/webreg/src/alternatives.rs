@@ -0,0 +1,197 @@
+//! Finding other sections to take when the section that a student wants is full.
+//!
+//! The alternatives to a section are the other sections of its course, and the sections of
+//! the courses that it's cross-listed with (see [`crate::cross_listings`]), that had open
+//! seats when the term was last scraped. They're ranked by how similar they are to the
+//! original section: how close their weekly meetings are to the original's, and whether
+//! they're taught by one of the same instructors.
+
+use std::collections::HashSet;
+
+use chrono::Weekday;
+
+use crate::db::TermSection;
+use crate::schedule::{MeetingSlot, SlotDays};
+
+/// How much of a section's similarity comes from its meeting times. The rest comes from
+/// sharing an instructor.
+const TIME_WEIGHT: f64 = 0.7;
+/// How far apart, in minutes, two meetings' start times can be before they're considered
+/// entirely different.
+const MAX_START_DIFFERENCE: f64 = 4.0 * 60.0;
+
+/// A section that can be taken instead of another.
+#[derive(Debug, Clone)]
+pub struct Alternative<'a> {
+    pub section: &'a TermSection,
+    /// Whether the section belongs to a course that the original's is cross-listed with,
+    /// rather than to the same course.
+    pub cross_listed: bool,
+    pub available_seats: i64,
+    /// How similar the section is to the original, from 0 to 1.
+    pub similarity: f64,
+    /// Whether the section is taught by one of the original's instructors.
+    pub same_instructor: bool,
+}
+
+/// Ranks the sections that can be taken instead of a section.
+///
+/// # Parameters
+/// - `target`: The section that the student wants.
+/// - `candidates`: The sections of the same course and of its cross-listings, each with
+///   whether it's cross-listed. Sections without open seats, and the target itself, are
+///   left out.
+///
+/// # Returns
+/// The alternatives, most similar first. Ties go to sections of the same course, and then
+/// to sections with more open seats.
+pub fn rank_alternatives<'a, I>(target: &TermSection, candidates: I) -> Vec<Alternative<'a>>
+where
+    I: IntoIterator<Item = (&'a TermSection, bool)>,
+{
+    let target_slots = weekly_slots(target);
+    let target_instructors = instructors(target);
+
+    let mut alternatives: Vec<_> = candidates
+        .into_iter()
+        .filter(|(section, _)| section.section.section_id != target.section.section_id)
+        .filter(|(section, _)| section.total_seats > section.enrolled_ct)
+        .map(|(section, cross_listed)| {
+            let same_instructor = !instructors(section).is_disjoint(&target_instructors);
+            let time = time_similarity(&target_slots, &weekly_slots(section));
+            let similarity =
+                TIME_WEIGHT * time + (1.0 - TIME_WEIGHT) * f64::from(u8::from(same_instructor));
+            Alternative {
+                section,
+                cross_listed,
+                available_seats: section.total_seats - section.enrolled_ct,
+                similarity: (similarity * 1000.0).round() / 1000.0,
+                same_instructor,
+            }
+        })
+        .collect();
+
+    alternatives.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then(a.cross_listed.cmp(&b.cross_listed))
+            .then(b.available_seats.cmp(&a.available_seats))
+            .then_with(|| {
+                a.section
+                    .section
+                    .section_code
+                    .cmp(&b.section.section.section_code)
+            })
+    });
+    alternatives
+}
+
+/// Gets a section's weekly meetings, with their days, start times, and types.
+fn weekly_slots(section: &TermSection) -> Vec<(Vec<Weekday>, u32, Option<String>)> {
+    section
+        .meetings
+        .iter()
+        .filter_map(MeetingSlot::from_db)
+        .filter_map(|slot| match slot.days {
+            SlotDays::Weekly(days) => Some((days, slot.start, slot.meeting_type)),
+            SlotDays::Once(_) => None,
+        })
+        .collect()
+}
+
+/// Gets the names of a section's instructors, in lowercase, leaving out placeholders like
+/// `Staff`.
+fn instructors(section: &TermSection) -> HashSet<String> {
+    section
+        .meetings
+        .iter()
+        .filter_map(|m| m.instructors.as_deref())
+        .filter_map(|list| serde_json::from_str::<Vec<String>>(list).ok())
+        .flatten()
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty() && name != "staff" && name != "tba")
+        .collect()
+}
+
+/// Scores how close a section's weekly meetings are to the original's, from 0 to 1. Each of
+/// the original's meetings is compared with the closest meeting of the same type (e.g., a
+/// lecture with a lecture), by how many days they share and how far apart they start.
+fn time_similarity(
+    target: &[(Vec<Weekday>, u32, Option<String>)],
+    candidate: &[(Vec<Weekday>, u32, Option<String>)],
+) -> f64 {
+    // A section without weekly meetings (e.g., one that's entirely online) is as close as
+    // anything else
+    if target.is_empty() {
+        return 1.0;
+    }
+
+    let total: f64 = target
+        .iter()
+        .map(|(days, start, meeting_type)| {
+            let same_type: Vec<_> = candidate
+                .iter()
+                .filter(|(_, _, t)| t == meeting_type)
+                .collect();
+            let comparable = if same_type.is_empty() {
+                candidate.iter().collect()
+            } else {
+                same_type
+            };
+
+            comparable
+                .into_iter()
+                .map(|(other_days, other_start, _)| {
+                    let shared = days.iter().filter(|d| other_days.contains(d)).count();
+                    let all = days.len() + other_days.len() - shared;
+                    let day_overlap = if all == 0 {
+                        0.0
+                    } else {
+                        shared as f64 / all as f64
+                    };
+                    let difference = f64::from(start.abs_diff(*other_start));
+                    day_overlap
+                        * (1.0 - difference.min(MAX_START_DIFFERENCE) / MAX_START_DIFFERENCE)
+                })
+                .fold(0.0, f64::max)
+        })
+        .sum();
+
+    total / target.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::term_section;
+
+    #[test]
+    fn test_rank_alternatives() {
+        let section = |id, days, start_hr, instructor, enrolled_ct| {
+            term_section("CSE 100", id, days, start_hr, instructor, enrolled_ct)
+        };
+        let target = section("1", r#"["Tu","Th"]"#, 9, "Doe, Jane", 30);
+        let candidates = [
+            (section("1", r#"["Tu","Th"]"#, 9, "Doe, Jane", 30), false),
+            // Full
+            (section("2", r#"["Tu","Th"]"#, 9, "Doe, Jane", 30), false),
+            (section("3", r#"["M","W","F"]"#, 15, "Roe, Rick", 10), false),
+            (section("4", r#"["Tu","Th"]"#, 11, "Roe, Rick", 10), false),
+            // The same class, under the course that it's cross-listed with
+            (section("5", r#"["Tu","Th"]"#, 9, "Doe, Jane", 25), true),
+            (section("6", r#"["Tu","Th"]"#, 9, "Doe, Jane", 20), false),
+        ];
+
+        let ranked = rank_alternatives(&target, candidates.iter().map(|(s, c)| (s, *c)));
+        let ids: Vec<_> = ranked
+            .iter()
+            .map(|a| a.section.section.section_id.as_str())
+            .collect();
+        assert_eq!(vec!["6", "5", "4", "3"], ids);
+        assert_eq!(1.0, ranked[0].similarity);
+        assert!(ranked[1].cross_listed);
+        assert_eq!(0.35, ranked[2].similarity);
+        assert_eq!(0.0, ranked[3].similarity);
+        assert_eq!(10, ranked[0].available_seats);
+    }
+}
@@ -0,0 +1,87 @@
+//! Scopes, which limit what an API key can be used for. Each route group of the API needs
+//! one scope, so that (for example) a key handed to a schedule viewer can't enroll anyone.
+//!
+//! Scopes are stored with the key's prefix in the schedule database. Keys without any
+//! stored scopes (those made before scopes existed, with `authmanager`, or through
+//! registration when `registration.scopes` isn't set) have every scope.
+
+use serde::{Deserialize, Serialize};
+
+/// Something that an API key can be used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiScope {
+    /// Reading schedule data, searching for courses, and the like.
+    Schedule,
+    /// Anything that needs a WebReg session, like adding and dropping sections.
+    Enroll,
+    /// Fetching and working with degree audits.
+    DegreeAudit,
+}
+
+impl ApiScope {
+    /// Every scope.
+    pub const ALL: [ApiScope; 3] = [ApiScope::Schedule, ApiScope::Enroll, ApiScope::DegreeAudit];
+
+    /// The name that the scope is stored and shown under.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::Schedule => "schedule",
+            ApiScope::Enroll => "enroll",
+            ApiScope::DegreeAudit => "degree_audit",
+        }
+    }
+}
+
+/// The scopes of the API key that a request was made with. The auth middleware attaches
+/// this to each request that it lets through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyScopes(pub Vec<ApiScope>);
+
+impl KeyScopes {
+    /// Whether the key has the given scope.
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.0.contains(&scope)
+    }
+}
+
+/// Formats scopes for storage, as a comma-separated list.
+pub fn format_scopes(scopes: &[ApiScope]) -> String {
+    let mut names: Vec<_> = scopes.iter().map(ApiScope::as_str).collect();
+    names.sort_unstable();
+    names.dedup();
+    names.join(",")
+}
+
+/// Parses stored scopes. Names that aren't known are skipped, so that a key never gets
+/// more than it was given.
+pub fn parse_scopes(scopes: &str) -> KeyScopes {
+    KeyScopes(
+        ApiScope::ALL
+            .into_iter()
+            .filter(|scope| scopes.split(',').any(|s| s.trim() == scope.as_str()))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scopes_round_trip() {
+        let stored = format_scopes(&[ApiScope::Enroll, ApiScope::Schedule, ApiScope::Enroll]);
+        assert_eq!("enroll,schedule", stored);
+
+        let scopes = parse_scopes(&stored);
+        assert!(scopes.allows(ApiScope::Schedule));
+        assert!(scopes.allows(ApiScope::Enroll));
+        assert!(!scopes.allows(ApiScope::DegreeAudit));
+
+        assert_eq!(
+            KeyScopes(vec![ApiScope::DegreeAudit]),
+            parse_scopes("admin, degree_audit")
+        );
+        assert!(parse_scopes("").0.is_empty());
+    }
+}
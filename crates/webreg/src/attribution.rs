@@ -0,0 +1,171 @@
+//! Where the data that the API serves comes from, and the terms that it's served under.
+//!
+//! Before the aggregated schedule data can be exposed publicly (e.g., as a public mirror),
+//! its provenance and terms of use have to be stated alongside it. With `dataLicense` set
+//! in the configuration file, every response of the student-facing API carries headers
+//! saying where the data comes from and under what license, and `GET /about` describes the
+//! data's provenance, when each term was last scraped, and the terms of use.
+
+use axum::http::{HeaderName, HeaderValue};
+use thiserror::Error;
+
+use crate::types::ConfigDataLicense;
+
+/// Where the data comes from, if not configured.
+pub const DEFAULT_DATA_SOURCE: &str = "UC San Diego WebReg";
+/// The header that names where the data comes from.
+pub const DATA_SOURCE_HEADER: &str = "x-data-source";
+/// The header that names the data's license.
+pub const DATA_LICENSE_HEADER: &str = "x-data-license";
+/// The header that holds the attribution notice that the data has to be shown with.
+pub const DATA_ATTRIBUTION_HEADER: &str = "x-data-attribution";
+
+/// Why the data license couldn't be loaded from the configuration file.
+#[derive(Debug, Error)]
+pub enum DataLicenseError {
+    #[error("invalid header name `{0}`")]
+    HeaderName(String),
+    #[error("invalid value for header `{0}` (only visible ASCII is allowed)")]
+    HeaderValue(String),
+}
+
+/// The data's provenance and terms of use, as configured.
+#[derive(Debug, Clone)]
+pub struct DataLicense {
+    /// Where the data comes from.
+    pub source: String,
+    /// The name of the license that the data is served under (e.g., `CC BY 4.0`).
+    pub license: Option<String>,
+    /// A link to the license's text.
+    pub license_url: Option<String>,
+    /// The notice that has to be shown with the data.
+    pub attribution: Option<String>,
+    /// The terms that the data may be used under.
+    pub terms_of_use: Option<String>,
+    /// A link to the full terms of use.
+    pub terms_url: Option<String>,
+    /// Who to contact about the data.
+    pub contact: Option<String>,
+    /// The headers that every response carries.
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl DataLicense {
+    /// Loads the data license from the configuration file, building the headers that every
+    /// response carries.
+    ///
+    /// # Parameters
+    /// - `config`: The `dataLicense` entry of the configuration file.
+    ///
+    /// # Returns
+    /// The data license, or which header couldn't be built.
+    pub fn from_config(config: ConfigDataLicense) -> Result<Self, DataLicenseError> {
+        let source = config
+            .source
+            .unwrap_or_else(|| DEFAULT_DATA_SOURCE.to_owned());
+
+        let mut raw_headers = vec![(DATA_SOURCE_HEADER.to_owned(), source.clone())];
+        if let Some(license) = &config.license {
+            raw_headers.push((DATA_LICENSE_HEADER.to_owned(), license.clone()));
+        }
+        if let Some(attribution) = &config.attribution {
+            raw_headers.push((DATA_ATTRIBUTION_HEADER.to_owned(), attribution.clone()));
+        }
+        if let Some(url) = &config.license_url {
+            raw_headers.push(("link".to_owned(), format!("<{url}>; rel=\"license\"")));
+        }
+        if let Some(url) = &config.terms_url {
+            raw_headers.push((
+                "link".to_owned(),
+                format!("<{url}>; rel=\"terms-of-service\""),
+            ));
+        }
+        raw_headers.extend(config.headers);
+
+        let headers = raw_headers
+            .into_iter()
+            .map(|(name, value)| {
+                let header_name = HeaderName::try_from(name.as_str())
+                    .map_err(|_| DataLicenseError::HeaderName(name.clone()))?;
+                let header_value = HeaderValue::from_str(&value)
+                    .map_err(|_| DataLicenseError::HeaderValue(name))?;
+                Ok((header_name, header_value))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            source,
+            license: config.license,
+            license_url: config.license_url,
+            attribution: config.attribution,
+            terms_of_use: config.terms_of_use,
+            terms_url: config.terms_url,
+            contact: config.contact,
+            headers,
+        })
+    }
+
+    /// Gets the headers that every response carries. A header may appear more than once
+    /// (e.g., `Link`).
+    pub fn headers(&self) -> &[(HeaderName, HeaderValue)] {
+        &self.headers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config(headers: HashMap<String, String>) -> ConfigDataLicense {
+        ConfigDataLicense {
+            source: None,
+            license: Some("CC BY 4.0".to_owned()),
+            license_url: Some("https://creativecommons.org/licenses/by/4.0/".to_owned()),
+            attribution: Some("Schedule data from UC San Diego.".to_owned()),
+            terms_of_use: None,
+            terms_url: Some("https://example.com/terms".to_owned()),
+            contact: None,
+            headers,
+        }
+    }
+
+    #[test]
+    fn test_data_license_headers() {
+        let license = DataLicense::from_config(config(HashMap::from([(
+            "X-Robots-Tag".to_owned(),
+            "noindex".to_owned(),
+        )])))
+        .unwrap();
+        assert_eq!(DEFAULT_DATA_SOURCE, license.source);
+
+        let headers: Vec<_> = license
+            .headers()
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.to_str().unwrap()))
+            .collect();
+        assert_eq!(
+            vec![
+                (DATA_SOURCE_HEADER, DEFAULT_DATA_SOURCE),
+                (DATA_LICENSE_HEADER, "CC BY 4.0"),
+                (DATA_ATTRIBUTION_HEADER, "Schedule data from UC San Diego."),
+                (
+                    "link",
+                    "<https://creativecommons.org/licenses/by/4.0/>; rel=\"license\""
+                ),
+                (
+                    "link",
+                    "<https://example.com/terms>; rel=\"terms-of-service\""
+                ),
+                ("x-robots-tag", "noindex"),
+            ],
+            headers
+        );
+
+        let invalid = config(HashMap::from([("Bad Header".to_owned(), "x".to_owned())]));
+        assert!(matches!(
+            DataLicense::from_config(invalid),
+            Err(DataLicenseError::HeaderName(_))
+        ));
+    }
+}
@@ -0,0 +1,339 @@
+//! The audit trail of admin edits to requirement configs and of requirement overrides.
+//!
+//! Admins can replace a college's or major's requirement config, and can override the
+//! status of a requirement on the student's audit (e.g., when a petition is approved or an
+//! advisor waives a requirement). Every such change is stored as a new version of the
+//! entity it changed, along with who made it and why; the latest version is the one in
+//! effect. Nothing is ever overwritten, so any entity can be rolled back by adding a new
+//! version with an older one's content.
+//!
+//! Config edits made this way take precedence over the files in `requirements_config`,
+//! including across restarts. The first time an entity from those files is edited, the
+//! file's version is recorded first, so that the edit can be rolled back.
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::db::ScheduleDbManager;
+use crate::degree_audit::config::{CollegeRequirements, MajorRequirements, RequirementsConfig};
+use crate::degree_audit::{DegreeAudit, RequirementStatus};
+
+/// The author recorded for a config's version from the `requirements_config` files.
+pub const FILE_AUTHOR: &str = "requirements_config";
+/// The most versions returned by one audit trail request.
+pub const MAX_TRAIL_ENTRIES: usize = 500;
+
+/// The kind of entity whose versions are tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailKind {
+    /// A college's requirement config, keyed by the college's code.
+    College,
+    /// A major's requirement config, keyed by the major's code.
+    Major,
+    /// A requirement override, keyed by the requirement's name and the user it's for (see
+    /// [`override_key`]).
+    Override,
+}
+
+impl TrailKind {
+    /// The name that the kind is stored under.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrailKind::College => "college",
+            TrailKind::Major => "major",
+            TrailKind::Override => "override",
+        }
+    }
+
+    /// Parses a stored kind.
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "college" => Some(TrailKind::College),
+            "major" => Some(TrailKind::Major),
+            "override" => Some(TrailKind::Override),
+            _ => None,
+        }
+    }
+}
+
+/// Why a requirement's status was overridden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverrideKind {
+    /// An advisor or admin changed the status directly.
+    Manual,
+    /// A petition (e.g., to count a course towards the requirement) was approved.
+    Petition,
+}
+
+/// An override of a requirement's status on the student's audit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequirementOverride {
+    /// The requirement's or subrequirement's name, as shown on the audit. Matched without
+    /// regard to case.
+    pub requirement: String,
+    pub status: RequirementStatus,
+    pub kind: OverrideKind,
+    /// The user whose audit the override is for, by the prefix of their API key. Overrides
+    /// without a user are for the shared audit, when there's no multi-user mode (see
+    /// [`crate::degree_audit::owner`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// The key that an override's versions are stored under: the requirement's name in lower
+/// case, after the user it's for and a `/` if it's for one.
+pub fn override_key(user: Option<&str>, requirement: &str) -> String {
+    let requirement = requirement.trim().to_lowercase();
+    match user {
+        Some(user) => format!("{user}/{requirement}"),
+        None => requirement,
+    }
+}
+
+/// Applies requirement overrides to an audit. A subrequirement that's overridden to be
+/// complete has all of its units counted as completed.
+///
+/// # Parameters
+/// - `audit`: The audit.
+/// - `overrides`: The overrides in effect.
+pub fn apply_overrides(audit: &mut DegreeAudit, overrides: &[RequirementOverride]) {
+    let find = |name: &str| {
+        overrides
+            .iter()
+            .find(|o| o.requirement.trim().eq_ignore_ascii_case(name.trim()))
+    };
+
+    for req in &mut audit.requirements {
+        if let Some(o) = find(&req.name) {
+            req.status = o.status.clone();
+        }
+
+        for sub in &mut req.subrequirements {
+            let Some(o) = find(&sub.title) else {
+                continue;
+            };
+
+            sub.status = o.status.clone();
+            if matches!(o.status, RequirementStatus::Complete) {
+                sub.units_completed = sub.units_completed.max(sub.required_units);
+                sub.units_remaining = 0.0;
+            }
+        }
+    }
+}
+
+/// Gets the requirement overrides in effect for a user's audit. Failures are only logged, in
+/// which case the audit is shown without overrides.
+///
+/// # Parameters
+/// - `db`: The database.
+/// - `user`: The user whose audit it is (see [`crate::degree_audit::owner::AuditOwner`]),
+///   or `None` for the shared audit.
+pub fn current_overrides(db: &ScheduleDbManager, user: Option<&str>) -> Vec<RequirementOverride> {
+    match db.get_current_trail_versions(TrailKind::Override.as_str()) {
+        Ok(entries) => entries
+            .iter()
+            .filter_map(|e| serde_json::from_str(e.content.as_deref()?).ok())
+            .filter(|o: &RequirementOverride| o.user.as_deref() == user)
+            .collect(),
+        Err(e) => {
+            warn!("Failed to load requirement overrides: {e}");
+            vec![]
+        }
+    }
+}
+
+/// Sets a college's or major's config from a version's content.
+///
+/// # Parameters
+/// - `config`: The requirements config to change.
+/// - `kind`: Whether the content is a college's or a major's config.
+/// - `code`: The college's or major's code.
+/// - `content`: The config as JSON.
+///
+/// # Returns
+/// Why the content couldn't be applied, if it couldn't.
+pub fn apply_config_version(
+    config: &mut RequirementsConfig,
+    kind: TrailKind,
+    code: &str,
+    content: &str,
+) -> Result<(), String> {
+    match kind {
+        TrailKind::College => {
+            let college: CollegeRequirements =
+                serde_json::from_str(content).map_err(|e| e.to_string())?;
+            if college.college_code != code {
+                return Err(format!(
+                    "the config is for college {}",
+                    college.college_code
+                ));
+            }
+            config.colleges.insert(code.to_owned(), college);
+        }
+        TrailKind::Major => {
+            let major: MajorRequirements =
+                serde_json::from_str(content).map_err(|e| e.to_string())?;
+            if major.major_code != code {
+                return Err(format!("the config is for major {}", major.major_code));
+            }
+            config.majors.insert(code.to_owned(), major);
+        }
+        TrailKind::Override => return Err("overrides aren't configs".to_owned()),
+    }
+
+    Ok(())
+}
+
+/// Applies the config edits recorded in the audit trail on top of the configs loaded from
+/// the `requirements_config` files. This is done on startup.
+pub fn load_config_edits(db: &ScheduleDbManager, config: &mut RequirementsConfig) {
+    for kind in [TrailKind::College, TrailKind::Major] {
+        let entries = match db.get_current_trail_versions(kind.as_str()) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Failed to load {} config edits: {e}", kind.as_str());
+                continue;
+            }
+        };
+
+        for entry in entries {
+            let content = entry.content.as_deref().unwrap_or_default();
+            if let Err(e) = apply_config_version(config, kind, &entry.entity_key, content) {
+                warn!(
+                    "Ignoring version {} of the {} config for {}: {e}",
+                    entry.version,
+                    kind.as_str(),
+                    entry.entity_key
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::degree_audit::{Requirement, StudentInfo, Subrequirement};
+
+    #[test]
+    fn test_apply_overrides() {
+        let sub = Subrequirement {
+            id: "1".to_owned(),
+            title: "Upper Division Electives".to_owned(),
+            required_units: 12.0,
+            required_courses: None,
+            needs: None,
+            courses_needed: None,
+            units_completed: 4.0,
+            units_remaining: 8.0,
+            status: RequirementStatus::InProgress,
+            eligible_courses: vec![],
+            completed_courses: vec![],
+            category_groups: vec![],
+        };
+        let mut audit = DegreeAudit {
+            audit_id: "audit".to_owned(),
+            student_info: StudentInfo {
+                student_id: None,
+                name: None,
+                major: None,
+                college: None,
+            },
+            requirements: vec![Requirement {
+                category: "Major".to_owned(),
+                name: "Major Requirements".to_owned(),
+                status: RequirementStatus::InProgress,
+                credits_required: None,
+                credits_completed: None,
+                courses: vec![],
+                subrequirements: vec![sub],
+            }],
+            scraped_at: String::new(),
+            parse_warnings: vec![],
+            report_version: None,
+        };
+
+        apply_overrides(
+            &mut audit,
+            &[RequirementOverride {
+                requirement: "upper division electives ".to_owned(),
+                status: RequirementStatus::Complete,
+                kind: OverrideKind::Petition,
+                user: None,
+            }],
+        );
+        let req = &audit.requirements[0];
+        assert!(matches!(req.status, RequirementStatus::InProgress));
+        let sub = &req.subrequirements[0];
+        assert!(matches!(sub.status, RequirementStatus::Complete));
+        assert_eq!((12.0, 0.0), (sub.units_completed, sub.units_remaining));
+    }
+
+    #[test]
+    fn test_overrides_per_user() {
+        let db = ScheduleDbManager::new(":memory:");
+        let set = |user: Option<&str>, status: RequirementStatus| {
+            let o = RequirementOverride {
+                requirement: "Writing".to_owned(),
+                status,
+                kind: OverrideKind::Manual,
+                user: user.map(str::to_owned),
+            };
+            db.record_trail_version(
+                TrailKind::Override.as_str(),
+                &override_key(user, &o.requirement),
+                Some(&serde_json::to_string(&o).unwrap()),
+                "advisor",
+                "Waived",
+            )
+            .unwrap();
+        };
+        set(Some("alice"), RequirementStatus::Complete);
+        set(Some("bob"), RequirementStatus::NotStarted);
+        set(None, RequirementStatus::InProgress);
+
+        // Each user only gets their own, and the shared audit only the unscoped one
+        let alice = current_overrides(&db, Some("alice"));
+        assert_eq!(1, alice.len());
+        assert!(matches!(alice[0].status, RequirementStatus::Complete));
+        let bob = current_overrides(&db, Some("bob"));
+        assert_eq!(1, bob.len());
+        assert!(matches!(bob[0].status, RequirementStatus::NotStarted));
+        let shared = current_overrides(&db, None);
+        assert_eq!(1, shared.len());
+        assert!(matches!(shared[0].status, RequirementStatus::InProgress));
+        assert!(current_overrides(&db, Some("carol")).is_empty());
+    }
+
+    #[test]
+    fn test_trail_versions() {
+        let db = ScheduleDbManager::new(":memory:");
+        let kind = TrailKind::Major.as_str();
+        db.record_trail_version(kind, "MA30", Some("{}"), FILE_AUTHOR, "Loaded from file")
+            .unwrap();
+        let edit = db
+            .record_trail_version(kind, "MA30", Some("{\"v\":2}"), "alec", "Catalog change")
+            .unwrap();
+        assert_eq!(2, edit.version);
+        db.record_trail_version(kind, "CS26", None, "alec", "Removed")
+            .unwrap();
+
+        // Removed entities aren't current
+        let current = db.get_current_trail_versions(kind).unwrap();
+        assert_eq!(1, current.len());
+        assert_eq!(Some("{\"v\":2}"), current[0].content.as_deref());
+
+        let trail = db.get_audit_trail(None, Some("MA30"), 10).unwrap();
+        assert_eq!(
+            vec![2, 1],
+            trail.iter().map(|e| e.version).collect::<Vec<_>>()
+        );
+        assert!(db.has_trail_versions(kind, "CS26").unwrap());
+        assert!(!db
+            .has_trail_versions(TrailKind::College.as_str(), "MA30")
+            .unwrap());
+    }
+}
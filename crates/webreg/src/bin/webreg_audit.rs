@@ -0,0 +1,12 @@
+//! `webreg-audit`, which parses degree audit pages saved from DARS offline (see
+//! [`webreg::degree_audit::cli`]). It doesn't need a configuration file or the server.
+
+use std::process::ExitCode;
+
+use clap::Parser;
+use webreg::degree_audit::cli::{run, CliArg};
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    run(CliArg::parse()).await
+}
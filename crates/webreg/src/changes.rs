@@ -0,0 +1,392 @@
+//! The feed of changes to a term's sections between scrapes.
+//!
+//! Whenever a course is scraped again, its sections are compared to the ones stored from
+//! the last scrape before they're replaced, and each section that was added, cancelled
+//! (i.e., is no longer listed), or modified is recorded. A section is modified when one of
+//! its meetings changed time, room, or instructors, or when a meeting was added or removed.
+//! The feed is served by `/live/:term/changes`, so that caches and notification bots only
+//! need what changed since they last looked.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use webweg::types::{CourseSection, MeetingDay};
+
+use crate::db::{DbMeeting, DbSection};
+
+/// The most changes returned by one request.
+pub const MAX_CHANGES: usize = 1000;
+
+/// How a section changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Cancelled,
+    Modified,
+}
+
+impl ChangeKind {
+    /// The name that the kind is stored under.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Cancelled => "cancelled",
+            ChangeKind::Modified => "modified",
+        }
+    }
+
+    /// Parses a stored kind.
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "added" => Some(ChangeKind::Added),
+            "cancelled" => Some(ChangeKind::Cancelled),
+            "modified" => Some(ChangeKind::Modified),
+            _ => None,
+        }
+    }
+}
+
+/// A change to one of a meeting's fields.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldChange {
+    /// The meeting's type (e.g., `LE`), numbered if the section has several of that type
+    /// (e.g., `DI 2`).
+    pub meeting: String,
+    /// `time`, `room`, or `instructors`, or `meeting` if the whole meeting was added or
+    /// removed.
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// A section that was added, cancelled, or modified.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionChange {
+    pub kind: ChangeKind,
+    pub section_id: String,
+    pub section_code: String,
+    /// What changed, for modified sections.
+    pub fields: Vec<FieldChange>,
+}
+
+/// A meeting, reduced to the fields whose changes are tracked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MeetingSnapshot {
+    pub meeting_type: String,
+    /// The days and times (e.g., `MWF 10:00-10:50`).
+    pub time: String,
+    /// The building and room (e.g., `CENTR 101`).
+    pub room: String,
+    /// The instructors, sorted and separated by semicolons.
+    pub instructors: String,
+}
+
+/// A section, reduced to the fields whose changes are tracked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionSnapshot {
+    pub section_id: String,
+    pub section_code: String,
+    pub meetings: Vec<MeetingSnapshot>,
+}
+
+fn format_time(days: &str, start: (i32, i32), end: (i32, i32)) -> String {
+    let time = format!("{:02}:{:02}-{:02}:{:02}", start.0, start.1, end.0, end.1);
+    match (days.is_empty(), start == (0, 0) && end == (0, 0)) {
+        (true, true) => "TBA".to_owned(),
+        (true, false) => time,
+        (false, true) => days.to_owned(),
+        (false, false) => format!("{days} {time}"),
+    }
+}
+
+fn format_room(building: &str, room: &str) -> String {
+    format!("{} {}", building.trim(), room.trim())
+        .trim()
+        .to_owned()
+}
+
+fn format_instructors<S: AsRef<str>>(instructors: &[S]) -> String {
+    let mut names: Vec<&str> = instructors
+        .iter()
+        .map(|i| i.as_ref().trim())
+        .filter(|i| !i.is_empty())
+        .collect();
+    names.sort_unstable();
+    names.join("; ")
+}
+
+impl SectionSnapshot {
+    /// Reduces a section that was just scraped.
+    pub fn from_section(section: &CourseSection) -> Self {
+        let meetings = section
+            .meetings
+            .iter()
+            .map(|m| {
+                let days = match &m.meeting_days {
+                    MeetingDay::Repeated(days) => days.concat(),
+                    MeetingDay::OneTime(date) => date.clone(),
+                    MeetingDay::None => String::new(),
+                };
+                MeetingSnapshot {
+                    meeting_type: m.meeting_type.clone(),
+                    time: format_time(
+                        &days,
+                        (m.start_hr as i32, m.start_min as i32),
+                        (m.end_hr as i32, m.end_min as i32),
+                    ),
+                    room: format_room(&m.building, &m.room),
+                    instructors: format_instructors(&m.instructors),
+                }
+            })
+            .collect();
+
+        Self {
+            section_id: section.section_id.clone(),
+            section_code: section.section_code.clone(),
+            meetings,
+        }
+    }
+
+    /// Reduces a section stored by an earlier scrape.
+    pub fn from_db(section: &DbSection, meetings: &[DbMeeting]) -> Self {
+        let meetings = meetings
+            .iter()
+            .map(|m| {
+                let stored = m.meeting_days.as_deref().unwrap_or_default();
+                let days = match m.meeting_days_type.as_str() {
+                    "repeated" => serde_json::from_str::<Vec<String>>(stored)
+                        .unwrap_or_default()
+                        .concat(),
+                    "onetime" => stored.to_owned(),
+                    _ => String::new(),
+                };
+                let instructors: Vec<String> = m
+                    .instructors
+                    .as_deref()
+                    .and_then(|i| serde_json::from_str(i).ok())
+                    .unwrap_or_default();
+                MeetingSnapshot {
+                    meeting_type: m.meeting_type.clone().unwrap_or_default(),
+                    time: format_time(
+                        &days,
+                        (m.start_hr.unwrap_or(0), m.start_min.unwrap_or(0)),
+                        (m.end_hr.unwrap_or(0), m.end_min.unwrap_or(0)),
+                    ),
+                    room: format_room(
+                        m.building.as_deref().unwrap_or_default(),
+                        m.room.as_deref().unwrap_or_default(),
+                    ),
+                    instructors: format_instructors(&instructors),
+                }
+            })
+            .collect();
+
+        Self {
+            section_id: section.section_id.clone(),
+            section_code: section.section_code.clone(),
+            meetings,
+        }
+    }
+
+    /// Labels each meeting by its type, numbering the types that the section has several
+    /// meetings of, so that meetings can be matched up between scrapes.
+    fn labeled_meetings(&self) -> Vec<(String, &MeetingSnapshot)> {
+        let mut totals: HashMap<&str, usize> = HashMap::new();
+        for m in &self.meetings {
+            *totals.entry(m.meeting_type.as_str()).or_default() += 1;
+        }
+
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        self.meetings
+            .iter()
+            .map(|m| {
+                let n = seen.entry(m.meeting_type.as_str()).or_default();
+                *n += 1;
+                let label = if totals[m.meeting_type.as_str()] > 1 {
+                    format!("{} {n}", m.meeting_type)
+                } else {
+                    m.meeting_type.clone()
+                };
+                (label, m)
+            })
+            .collect()
+    }
+}
+
+/// Compares two meetings' tracked fields.
+fn diff_meetings(
+    label: &str,
+    before: &MeetingSnapshot,
+    after: &MeetingSnapshot,
+) -> Vec<FieldChange> {
+    [
+        ("time", &before.time, &after.time),
+        ("room", &before.room, &after.room),
+        ("instructors", &before.instructors, &after.instructors),
+    ]
+    .into_iter()
+    .filter(|(_, b, a)| b != a)
+    .map(|(field, b, a)| FieldChange {
+        meeting: label.to_owned(),
+        field: field.to_owned(),
+        before: Some(b.clone()),
+        after: Some(a.clone()),
+    })
+    .collect()
+}
+
+/// Describes a whole meeting, for when it was added or removed.
+fn describe_meeting(m: &MeetingSnapshot) -> String {
+    [m.time.as_str(), m.room.as_str(), m.instructors.as_str()]
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Compares a course's sections from two scrapes.
+///
+/// # Parameters
+/// - `before`: The sections from the earlier scrape.
+/// - `after`: The sections from the later scrape.
+///
+/// # Returns
+/// The sections that were added, cancelled, or modified, in the order that they're listed.
+pub fn diff_sections(before: &[SectionSnapshot], after: &[SectionSnapshot]) -> Vec<SectionChange> {
+    let old: HashMap<&str, &SectionSnapshot> =
+        before.iter().map(|s| (s.section_id.as_str(), s)).collect();
+    let new: HashMap<&str, &SectionSnapshot> =
+        after.iter().map(|s| (s.section_id.as_str(), s)).collect();
+
+    let mut changes = vec![];
+    for section in after {
+        let Some(prev) = old.get(section.section_id.as_str()) else {
+            changes.push(SectionChange {
+                kind: ChangeKind::Added,
+                section_id: section.section_id.clone(),
+                section_code: section.section_code.clone(),
+                fields: vec![],
+            });
+            continue;
+        };
+
+        let prev_meetings: HashMap<String, &MeetingSnapshot> =
+            prev.labeled_meetings().into_iter().collect();
+        let cur_meetings = section.labeled_meetings();
+        let mut fields = vec![];
+        for (label, meeting) in &cur_meetings {
+            match prev_meetings.get(label) {
+                Some(prev) => fields.extend(diff_meetings(label, prev, meeting)),
+                None => fields.push(FieldChange {
+                    meeting: label.clone(),
+                    field: "meeting".to_owned(),
+                    before: None,
+                    after: Some(describe_meeting(meeting)),
+                }),
+            }
+        }
+        for (label, meeting) in prev.labeled_meetings() {
+            if !cur_meetings.iter().any(|(l, _)| *l == label) {
+                fields.push(FieldChange {
+                    meeting: label,
+                    field: "meeting".to_owned(),
+                    before: Some(describe_meeting(meeting)),
+                    after: None,
+                });
+            }
+        }
+
+        if !fields.is_empty() {
+            changes.push(SectionChange {
+                kind: ChangeKind::Modified,
+                section_id: section.section_id.clone(),
+                section_code: section.section_code.clone(),
+                fields,
+            });
+        }
+    }
+
+    changes.extend(
+        before
+            .iter()
+            .filter(|s| !new.contains_key(s.section_id.as_str()))
+            .map(|s| SectionChange {
+                kind: ChangeKind::Cancelled,
+                section_id: s.section_id.clone(),
+                section_code: s.section_code.clone(),
+                fields: vec![],
+            }),
+    );
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(id: &str, meetings: &[(&str, &str, &str, &str)]) -> SectionSnapshot {
+        SectionSnapshot {
+            section_id: id.to_owned(),
+            section_code: format!("A{id}"),
+            meetings: meetings
+                .iter()
+                .map(|(meeting_type, time, room, instructors)| MeetingSnapshot {
+                    meeting_type: meeting_type.to_string(),
+                    time: time.to_string(),
+                    room: room.to_string(),
+                    instructors: instructors.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_diff_sections() {
+        let before = [
+            section(
+                "1",
+                &[
+                    ("LE", "MWF 10:00-10:50", "CENTR 101", "Doe, Jane"),
+                    ("DI", "Tu 09:00-09:50", "WLH 2001", "Doe, Jane"),
+                ],
+            ),
+            section("2", &[("LE", "TuTh 11:00-12:20", "PCYNH 106", "Roe, Sam")]),
+        ];
+        let after = [
+            section(
+                "1",
+                &[("LE", "MWF 10:00-10:50", "CENTR 115", "Doe, Jane; Poe, Ed")],
+            ),
+            section("3", &[("LE", "TBA", "", "")]),
+        ];
+
+        let changes = diff_sections(&before, &after);
+        let kinds: Vec<_> = changes
+            .iter()
+            .map(|c| (c.kind, c.section_id.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                (ChangeKind::Modified, "1"),
+                (ChangeKind::Added, "3"),
+                (ChangeKind::Cancelled, "2"),
+            ],
+            kinds
+        );
+
+        let fields: Vec<_> = changes[0]
+            .fields
+            .iter()
+            .map(|f| (f.meeting.as_str(), f.field.as_str()))
+            .collect();
+        assert_eq!(
+            vec![("LE", "room"), ("LE", "instructors"), ("DI", "meeting")],
+            fields
+        );
+        assert_eq!(None, changes[0].fields[2].after);
+
+        assert!(diff_sections(&before, &before).is_empty());
+        assert_eq!("TBA", format_time("", (0, 0), (0, 0)));
+    }
+}
@@ -0,0 +1,173 @@
+//! Which courses students take together.
+//!
+//! The co-enrollment graph is built from the schedules that students have mirrored from
+//! WebReg (see [`crate::schedule_mirror`]): each student's enrolled courses in a term make up
+//! one schedule, and every pair of courses in a schedule is an edge between them, weighted by
+//! the number of schedules that have both. Only the counts are kept, not whose schedules they
+//! came from, and courses or pairs seen in fewer than [`MIN_SCHEDULES`] schedules are left out,
+//! so that no student's schedule can be picked out of the graph.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::Serialize;
+
+use crate::db::normalize_course_code;
+
+/// The fewest schedules that a course, or a pair of courses, has to appear in to be
+/// reported.
+pub const MIN_SCHEDULES: usize = 5;
+
+/// A course that's taken alongside another.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CoEnrolledCourse {
+    pub course: String,
+    /// The number of schedules that have both courses.
+    pub schedules: usize,
+    /// The share of the other course's schedules that also have this course.
+    pub share: f64,
+    /// How much more often this course is taken alongside the other than it's taken at all
+    /// (its share, divided by the share of all schedules that have it). Above `1.0`, the
+    /// courses are taken together more often than chance.
+    pub lift: f64,
+}
+
+/// The number of schedules that each course, and each pair of courses, appears in.
+#[derive(Debug, Default)]
+pub struct CoEnrollmentGraph {
+    /// The number of schedules.
+    pub schedules: usize,
+    courses: HashMap<String, usize>,
+    /// Keyed by the pair's courses in order.
+    pairs: HashMap<(String, String), usize>,
+}
+
+impl CoEnrollmentGraph {
+    /// Builds the graph from students' schedules.
+    ///
+    /// # Parameters
+    /// - `schedules`: Each schedule's courses (e.g., `CSE 100`). A course listed more than
+    ///   once in a schedule (e.g., for a lecture and a lab) counts once.
+    ///
+    /// # Returns
+    /// The graph.
+    pub fn build<S: AsRef<str>>(schedules: &[Vec<S>]) -> Self {
+        let mut graph = Self::default();
+        for schedule in schedules {
+            let courses: BTreeSet<String> = schedule
+                .iter()
+                .map(|c| normalize_course_code(c.as_ref()))
+                .collect();
+            if courses.is_empty() {
+                continue;
+            }
+
+            graph.schedules += 1;
+            for (i, course) in courses.iter().enumerate() {
+                *graph.courses.entry(course.clone()).or_default() += 1;
+                for other in courses.iter().skip(i + 1) {
+                    *graph
+                        .pairs
+                        .entry((course.clone(), other.clone()))
+                        .or_default() += 1;
+                }
+            }
+        }
+        graph
+    }
+
+    /// The number of schedules that have a course, if there are enough to report.
+    pub fn course_schedules(&self, course: &str) -> Option<usize> {
+        self.courses
+            .get(&normalize_course_code(course))
+            .copied()
+            .filter(|&count| count >= MIN_SCHEDULES)
+    }
+
+    /// Gets the courses that are most often taken alongside a course.
+    ///
+    /// # Parameters
+    /// - `course`: The course (e.g., `CSE 100`).
+    /// - `limit`: The most courses to return.
+    ///
+    /// # Returns
+    /// The courses, those in the most schedules with the course first, or nothing if the
+    /// course is in too few schedules to report.
+    pub fn neighbors(&self, course: &str, limit: usize) -> Vec<CoEnrolledCourse> {
+        let course = normalize_course_code(course);
+        let Some(total) = self.course_schedules(&course) else {
+            return vec![];
+        };
+
+        let mut neighbors: Vec<CoEnrolledCourse> = self
+            .pairs
+            .iter()
+            .filter(|(_, &count)| count >= MIN_SCHEDULES)
+            .filter_map(|((a, b), &count)| {
+                let other = if *a == course {
+                    b
+                } else if *b == course {
+                    a
+                } else {
+                    return None;
+                };
+
+                let share = count as f64 / total as f64;
+                let base_rate = self.courses[other] as f64 / self.schedules as f64;
+                Some(CoEnrolledCourse {
+                    course: other.clone(),
+                    schedules: count,
+                    share,
+                    lift: share / base_rate,
+                })
+            })
+            .collect();
+
+        neighbors.sort_by(|a, b| {
+            b.schedules
+                .cmp(&a.schedules)
+                .then_with(|| a.course.cmp(&b.course))
+        });
+        neighbors.truncate(limit);
+        neighbors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_co_enrollment_neighbors() {
+        let mut schedules = vec![];
+        for _ in 0..6 {
+            schedules.push(vec!["CSE 100", "MATH 154", "CSE 100"]);
+        }
+        for _ in 0..5 {
+            schedules.push(vec!["cse 100", "CSE 101"]);
+        }
+        // Too few schedules to report the pair
+        for _ in 0..4 {
+            schedules.push(vec!["CSE 100", "DSC 10"]);
+        }
+        for _ in 0..5 {
+            schedules.push(vec!["MATH 154"]);
+        }
+
+        let graph = CoEnrollmentGraph::build(&schedules);
+        assert_eq!(20, graph.schedules);
+        assert_eq!(Some(15), graph.course_schedules("CSE  100"));
+
+        let neighbors = graph.neighbors("CSE 100", 10);
+        let summary: Vec<_> = neighbors
+            .iter()
+            .map(|n| (n.course.as_str(), n.schedules))
+            .collect();
+        assert_eq!(vec![("MATH 154", 6), ("CSE 101", 5)], summary);
+        assert!((neighbors[0].share - 0.4).abs() < 1e-9);
+        // MATH 154 is in 11 of 20 schedules, but only 6 of CSE 100's 15
+        assert!((neighbors[0].lift - 0.4 / 0.55).abs() < 1e-9);
+
+        assert_eq!(1, graph.neighbors("CSE 100", 1).len());
+        assert!(graph.neighbors("DSC 10", 10).is_empty());
+    }
+}
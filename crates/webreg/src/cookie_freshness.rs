@@ -0,0 +1,241 @@
+//! Keeping the tracker's WebReg session cookies fresh.
+//!
+//! Session cookies stop working after a while, and otherwise that's only noticed when a
+//! request fails, so the first request after an idle period has to wait for a new login.
+//! Instead, the cookies are validated periodically with a cheap authenticated request, and
+//! new ones are requested from the cookie server (`webregautoin`) before they get too old,
+//! or as soon as a validation finds that they've stopped working.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::scraper::tracker::relogin;
+use crate::types::WrapperState;
+
+/// How often the cookies are validated, if not configured.
+pub const DEFAULT_VALIDATION_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// How old the cookies may get before new ones are requested, if not configured.
+pub const DEFAULT_MAX_COOKIE_AGE: Duration = Duration::from_secs(4 * 60 * 60);
+/// How long a single validation may take before the cookies are treated as invalid.
+const VALIDATION_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Why new cookies should be requested before they're needed.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReloginReason {
+    /// The cookies are about to expire.
+    Expiring,
+    /// The most recent validation found that the cookies don't work anymore.
+    Invalid,
+}
+
+/// The freshness of the cookies, as reported by `/login_stat/cookie_health`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CookieFreshnessReport {
+    /// When the cookies were last refreshed, in RFC 3339 format.
+    pub refreshed_at: Option<String>,
+    /// How old the cookies are, in seconds.
+    pub age_secs: Option<i64>,
+    /// When the cookies were last validated, in RFC 3339 format.
+    pub last_validated: Option<String>,
+    /// Whether the most recent validation succeeded.
+    pub valid: Option<bool>,
+    /// When new cookies will be requested, if they keep working until then, in RFC 3339
+    /// format.
+    pub relogin_due: Option<String>,
+    /// The number of times that new cookies were requested ahead of time.
+    pub proactive_relogins: u32,
+    /// Why new cookies were last requested ahead of time.
+    pub last_relogin_reason: Option<ReloginReason>,
+    /// Whether the last such request failed.
+    pub last_relogin_failed: bool,
+}
+
+#[derive(Debug, Default)]
+struct FreshnessState {
+    refreshed_at: Option<DateTime<Utc>>,
+    last_validated: Option<DateTime<Utc>>,
+    valid: Option<bool>,
+    proactive_relogins: u32,
+    last_relogin_reason: Option<ReloginReason>,
+    last_relogin_failed: bool,
+}
+
+/// Tracks how fresh the cookies are.
+pub struct CookieFreshness {
+    /// How old the cookies may get before new ones are requested.
+    max_age: Duration,
+    state: Mutex<FreshnessState>,
+    /// Held while new cookies are being requested, so that the tracker and the proactive
+    /// re-login don't both log in at once.
+    login_lock: tokio::sync::Mutex<()>,
+}
+
+impl CookieFreshness {
+    /// Creates a tracker for cookies that haven't been fetched yet.
+    ///
+    /// # Parameters
+    /// - `max_age`: How old the cookies may get before new ones are requested.
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            state: Mutex::new(FreshnessState::default()),
+            login_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Gets the lock that's held while new cookies are being requested.
+    pub fn login_lock(&self) -> &tokio::sync::Mutex<()> {
+        &self.login_lock
+    }
+
+    /// Records that new cookies were fetched and work.
+    pub fn record_refresh(&self) {
+        let now = Utc::now();
+        let mut state = self.state.lock().unwrap();
+        state.refreshed_at = Some(now);
+        state.last_validated = Some(now);
+        state.valid = Some(true);
+    }
+
+    /// Records the result of validating the cookies.
+    pub fn record_validation(&self, valid: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.last_validated = Some(Utc::now());
+        state.valid = Some(valid);
+    }
+
+    /// Records the result of requesting new cookies ahead of time.
+    pub fn record_relogin(&self, reason: ReloginReason, succeeded: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.proactive_relogins += 1;
+        state.last_relogin_reason = Some(reason);
+        state.last_relogin_failed = !succeeded;
+    }
+
+    /// When the cookies were last refreshed, if they ever were.
+    pub fn refreshed_at(&self) -> Option<DateTime<Utc>> {
+        self.state.lock().unwrap().refreshed_at
+    }
+
+    /// Whether new cookies should be requested now.
+    ///
+    /// # Parameters
+    /// - `now`: The current time.
+    ///
+    /// # Returns
+    /// Why new cookies should be requested, or nothing if they shouldn't be (including if
+    /// the tracker hasn't logged in yet, since it's already trying to).
+    pub fn needs_relogin(&self, now: DateTime<Utc>) -> Option<ReloginReason> {
+        let state = self.state.lock().unwrap();
+        let refreshed_at = state.refreshed_at?;
+        if state.valid == Some(false) {
+            Some(ReloginReason::Invalid)
+        } else if now - refreshed_at >= chrono::Duration::from_std(self.max_age).ok()? {
+            Some(ReloginReason::Expiring)
+        } else {
+            None
+        }
+    }
+
+    /// Gets how fresh the cookies are.
+    pub fn report(&self) -> CookieFreshnessReport {
+        let state = self.state.lock().unwrap();
+        let relogin_due = state
+            .refreshed_at
+            .zip(chrono::Duration::from_std(self.max_age).ok())
+            .map(|(refreshed_at, max_age)| (refreshed_at + max_age).to_rfc3339());
+        CookieFreshnessReport {
+            refreshed_at: state.refreshed_at.map(|t| t.to_rfc3339()),
+            age_secs: state.refreshed_at.map(|t| (Utc::now() - t).num_seconds()),
+            last_validated: state.last_validated.map(|t| t.to_rfc3339()),
+            valid: state.valid,
+            relogin_due,
+            proactive_relogins: state.proactive_relogins,
+            last_relogin_reason: state.last_relogin_reason,
+            last_relogin_failed: state.last_relogin_failed,
+        }
+    }
+}
+
+/// Validates the cookies, requesting new ones if they're about to expire or have stopped
+/// working. This is run periodically by the scheduler (see [`crate::scheduler`]).
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+///
+/// # Returns
+/// What was done, or why new cookies were needed but couldn't be gotten.
+pub async fn check_cookie_freshness(state: &Arc<WrapperState>) -> Result<String, String> {
+    // The tracker is still logging in for the first time, and will validate the cookies
+    // itself
+    let freshness = &state.cookie_freshness;
+    if freshness.refreshed_at().is_none() {
+        return Ok("The tracker hasn't logged in yet.".to_owned());
+    }
+
+    let valid = tokio::time::timeout(VALIDATION_TIMEOUT, state.wrapper.is_valid())
+        .await
+        .unwrap_or(false);
+    freshness.record_validation(valid);
+    if !valid {
+        warn!("The WebReg session cookies don't work anymore.");
+    }
+
+    let Some(reason) = freshness.needs_relogin(Utc::now()) else {
+        return Ok("The cookies are valid.".to_owned());
+    };
+
+    if state.cookie_server_health.is_down() {
+        warn!("New WebReg session cookies are needed, but the cookie server is down.");
+        return Err(format!(
+            "New cookies are needed ({reason:?}), but the cookie server is down."
+        ));
+    }
+
+    // The tracker is already logging in
+    let Ok(_guard) = freshness.login_lock().try_lock() else {
+        return Ok("The tracker is already getting new cookies.".to_owned());
+    };
+
+    info!("Requesting new WebReg session cookies ahead of time ({reason:?}).");
+    let succeeded = relogin(state).await;
+    freshness.record_relogin(reason, succeeded);
+    if succeeded {
+        Ok(format!("Got new cookies ({reason:?})."))
+    } else {
+        warn!("Failed to get new WebReg session cookies ahead of time.");
+        Err(format!("Failed to get new cookies ({reason:?})."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_relogin() {
+        let freshness = CookieFreshness::new(Duration::from_secs(60 * 60));
+        let now = Utc::now();
+        assert_eq!(None, freshness.needs_relogin(now));
+
+        freshness.record_refresh();
+        assert_eq!(None, freshness.needs_relogin(now));
+        assert_eq!(
+            Some(ReloginReason::Expiring),
+            freshness.needs_relogin(now + chrono::Duration::minutes(61))
+        );
+
+        freshness.record_validation(false);
+        assert_eq!(Some(ReloginReason::Invalid), freshness.needs_relogin(now));
+
+        freshness.record_refresh();
+        let report = freshness.report();
+        assert_eq!(Some(true), report.valid);
+        assert!(report.relogin_due.is_some());
+    }
+}
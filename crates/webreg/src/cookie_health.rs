@@ -0,0 +1,193 @@
+//! Health checks against the cookie server (`webregautoin`).
+//!
+//! The cookie server is checked periodically, and is only considered down after several
+//! checks in a row have failed, so that a single slow response doesn't take the cookie
+//! endpoints offline. While it's down, those endpoints respond with a `503 Service
+//! Unavailable` that says why, rather than failing in less obvious ways.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::types::WrapperState;
+
+/// How often the cookie server is checked.
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a single check may take before it counts as a failure.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// The number of failed checks in a row after which the cookie server is considered down.
+pub const FAILURES_BEFORE_DOWN: u32 = 3;
+
+/// Whether the cookie server can be reached.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CookieServerStatus {
+    /// The cookie server hasn't been checked yet.
+    Unknown,
+    Up,
+    Down,
+}
+
+/// Why a health check failed.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthCheckFailure {
+    /// The cookie server couldn't be connected to.
+    Unreachable,
+    /// The cookie server didn't respond in time.
+    Timeout,
+    /// The cookie server responded with an error status.
+    BadStatus,
+}
+
+/// The result of the most recent health checks.
+#[derive(Debug, Clone, Serialize)]
+pub struct CookieServerHealthReport {
+    pub status: CookieServerStatus,
+    /// Why the most recent check failed, if it did.
+    pub reason: Option<HealthCheckFailure>,
+    /// Details about the most recent failure, if the most recent check failed.
+    pub detail: Option<String>,
+    pub consecutive_failures: u32,
+    /// When the cookie server was last checked, in RFC 3339 format.
+    pub last_checked: Option<String>,
+}
+
+/// Tracks the health of the cookie server.
+pub struct CookieServerHealth {
+    report: Mutex<CookieServerHealthReport>,
+}
+
+impl Default for CookieServerHealth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CookieServerHealth {
+    /// Creates a tracker for a cookie server that hasn't been checked yet.
+    pub fn new() -> Self {
+        Self {
+            report: Mutex::new(CookieServerHealthReport {
+                status: CookieServerStatus::Unknown,
+                reason: None,
+                detail: None,
+                consecutive_failures: 0,
+                last_checked: None,
+            }),
+        }
+    }
+
+    /// Records a successful check.
+    pub fn record_success(&self) {
+        let mut report = self.report.lock().unwrap();
+        if report.status == CookieServerStatus::Down {
+            info!("The cookie server is reachable again.");
+        }
+
+        report.status = CookieServerStatus::Up;
+        report.reason = None;
+        report.detail = None;
+        report.consecutive_failures = 0;
+        report.last_checked = Some(Utc::now().to_rfc3339());
+    }
+
+    /// Records a failed check. The cookie server is considered down once
+    /// [`FAILURES_BEFORE_DOWN`] checks in a row have failed.
+    ///
+    /// # Parameters
+    /// - `reason`: Why the check failed.
+    /// - `detail`: Details about the failure.
+    pub fn record_failure(&self, reason: HealthCheckFailure, detail: String) {
+        let mut report = self.report.lock().unwrap();
+        report.consecutive_failures += 1;
+        if report.consecutive_failures >= FAILURES_BEFORE_DOWN
+            && report.status != CookieServerStatus::Down
+        {
+            warn!("The cookie server is down ({detail}).");
+            report.status = CookieServerStatus::Down;
+        }
+
+        report.reason = Some(reason);
+        report.detail = Some(detail);
+        report.last_checked = Some(Utc::now().to_rfc3339());
+    }
+
+    /// Whether the cookie server is known to be down.
+    pub fn is_down(&self) -> bool {
+        self.report.lock().unwrap().status == CookieServerStatus::Down
+    }
+
+    /// Gets the result of the most recent checks.
+    pub fn report(&self) -> CookieServerHealthReport {
+        self.report.lock().unwrap().clone()
+    }
+}
+
+/// Checks the cookie server periodically, forever.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+pub async fn run_cookie_server_health_check(state: Arc<WrapperState>) {
+    let Some(address) = state.cookie_server_address() else {
+        info!("There's no cookie server to check.");
+        return;
+    };
+
+    // `/start` is the cheapest endpoint the cookie server has; it doesn't log in
+    let url = format!("http://{address}/start");
+
+    let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let result = state
+            .client
+            .get(&url)
+            .timeout(HEALTH_CHECK_TIMEOUT)
+            .send()
+            .await;
+
+        let health = &state.cookie_server_health;
+        match result {
+            Ok(resp) if resp.status().is_success() => health.record_success(),
+            Ok(resp) => health.record_failure(
+                HealthCheckFailure::BadStatus,
+                format!("responded with {}", resp.status()),
+            ),
+            Err(e) if e.is_timeout() => {
+                health.record_failure(HealthCheckFailure::Timeout, e.to_string())
+            }
+            Err(e) => health.record_failure(HealthCheckFailure::Unreachable, e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_down_after_consecutive_failures() {
+        let health = CookieServerHealth::new();
+        assert_eq!(CookieServerStatus::Unknown, health.report().status);
+
+        for _ in 1..FAILURES_BEFORE_DOWN {
+            health.record_failure(HealthCheckFailure::Unreachable, "refused".to_string());
+        }
+        assert!(!health.is_down());
+
+        health.record_failure(HealthCheckFailure::Timeout, "timed out".to_string());
+        assert!(health.is_down());
+        assert_eq!(Some(HealthCheckFailure::Timeout), health.report().reason);
+
+        health.record_success();
+        let report = health.report();
+        assert_eq!(
+            (CookieServerStatus::Up, None, 0),
+            (report.status, report.reason, report.consecutive_failures)
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Checking the quarter-by-quarter course plans that students save.
+//!
+//! A course plan lists the courses a student means to take in each of the coming quarters.
+//! It's kept by the API rather than in WebReg, whose planned schedules only hold sections
+//! for the current term, and only so many of them. Whenever a plan is saved or fetched, it's
+//! checked against the prerequisites of its courses (a course's prerequisites have to be
+//! completed, or planned for an earlier quarter) and against the student's degree audit (a
+//! course that's already been completed doesn't need to be planned again).
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::db::{normalize_course_code, PlannedQuarter};
+use crate::offerings::term_sort_key;
+
+/// What's wrong with a course in a plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanIssueKind {
+    /// Not all of the course's prerequisites are completed or planned for an earlier
+    /// quarter.
+    MissingPrerequisites,
+    /// The degree audit shows that the course has already been completed.
+    AlreadyCompleted,
+    /// The course is planned for more than one quarter.
+    PlannedTwice,
+}
+
+/// A problem with a course in a plan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlanIssue {
+    /// The quarter that the course is planned for.
+    pub term: String,
+    pub course_code: String,
+    pub kind: PlanIssueKind,
+    /// The groups of prerequisites that aren't satisfied, of which one course each is
+    /// needed. This is only given for missing prerequisites.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<Vec<String>>,
+    pub message: String,
+}
+
+/// Checks a plan's courses against their prerequisites and the courses the student has
+/// completed.
+///
+/// # Parameters
+/// - `quarters`: The plan's quarters.
+/// - `completed`: The courses that the student has completed (e.g., `CSE 12`).
+/// - `prerequisites`: The prerequisites of each course. Each inner list is a group of
+///   alternatives, of which at least one must be taken. Courses without an entry aren't
+///   checked.
+///
+/// # Returns
+/// The problems found, in the order of the plan's quarters.
+pub fn check_course_plan(
+    quarters: &[PlannedQuarter],
+    completed: &HashSet<String>,
+    prerequisites: &HashMap<String, Vec<Vec<String>>>,
+) -> Vec<PlanIssue> {
+    let mut quarters: Vec<&PlannedQuarter> = quarters.iter().collect();
+    quarters.sort_by_key(|q| term_sort_key(&q.term));
+
+    let mut issues = vec![];
+    // Courses in the same quarter don't satisfy each other's prerequisites, so a quarter's
+    // courses are only counted as taken once the whole quarter has been checked
+    let mut taken = completed.clone();
+    let mut planned = HashSet::new();
+    for quarter in quarters {
+        let courses: Vec<String> = quarter
+            .courses
+            .iter()
+            .map(|c| normalize_course_code(c))
+            .collect();
+        for course in &courses {
+            let issue = |kind, missing, message| PlanIssue {
+                term: quarter.term.clone(),
+                course_code: course.clone(),
+                kind,
+                missing,
+                message,
+            };
+
+            if completed.contains(course) {
+                issues.push(issue(
+                    PlanIssueKind::AlreadyCompleted,
+                    vec![],
+                    format!("{course} has already been completed."),
+                ));
+            } else if !planned.insert(course.clone()) {
+                issues.push(issue(
+                    PlanIssueKind::PlannedTwice,
+                    vec![],
+                    format!("{course} is planned for more than one quarter."),
+                ));
+            }
+
+            let missing: Vec<Vec<String>> = prerequisites
+                .get(course)
+                .into_iter()
+                .flatten()
+                .filter(|group| !group.is_empty() && !group.iter().any(|c| taken.contains(c)))
+                .cloned()
+                .collect();
+            if !missing.is_empty() {
+                let needed = missing
+                    .iter()
+                    .map(|group| group.join(" or "))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                issues.push(issue(
+                    PlanIssueKind::MissingPrerequisites,
+                    missing,
+                    format!(
+                        "{course} needs {needed} to be completed or planned before {}.",
+                        quarter.term
+                    ),
+                ));
+            }
+        }
+        taken.extend(courses);
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quarter(term: &str, courses: &[&str]) -> PlannedQuarter {
+        PlannedQuarter {
+            term: term.to_owned(),
+            courses: courses.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_check_course_plan() {
+        let completed = HashSet::from(["CSE 11".to_owned()]);
+        let prerequisites = HashMap::from([
+            (
+                "CSE 100".to_owned(),
+                vec![
+                    vec!["CSE 12".to_owned()],
+                    vec!["CSE 21".to_owned(), "MATH 154".to_owned()],
+                ],
+            ),
+            ("CSE 12".to_owned(), vec![vec!["CSE 11".to_owned()]]),
+        ]);
+
+        // Quarters are checked in order, whatever order they're listed in
+        let plan = [
+            quarter("WI25", &["cse  100", "CSE 21"]),
+            quarter("FA24", &["CSE 12", "CSE 11"]),
+            quarter("SP25", &["CSE 21"]),
+        ];
+        let issues = check_course_plan(&plan, &completed, &prerequisites);
+        let summary: Vec<_> = issues
+            .iter()
+            .map(|i| (i.term.as_str(), i.course_code.as_str(), i.kind))
+            .collect();
+        assert_eq!(
+            vec![
+                ("FA24", "CSE 11", PlanIssueKind::AlreadyCompleted),
+                ("WI25", "CSE 100", PlanIssueKind::MissingPrerequisites),
+                ("SP25", "CSE 21", PlanIssueKind::PlannedTwice),
+            ],
+            summary
+        );
+        // CSE 21 in the same quarter doesn't count, but CSE 12 the quarter before does
+        assert_eq!(
+            vec![vec!["CSE 21".to_owned(), "MATH 154".to_owned()]],
+            issues[1].missing
+        );
+        assert_eq!(
+            "CSE 100 needs CSE 21 or MATH 154 to be completed or planned before WI25.",
+            issues[1].message
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Resolving cross-listed courses, i.e., one class that's offered under several codes (e.g.,
+//! `CSE 100` is also offered as `MATH 176`).
+//!
+//! WebReg lists each code as its own course, so cross-listings are collected from two
+//! places: the course catalog, which says which courses each course is cross-listed with,
+//! and a term's schedule, where the listings of a class share their meetings. Two courses
+//! are taken to be cross-listed in a term when a section of each meets at the same weekly
+//! times, in the same room, with the same instructors. The pairs are stored in the
+//! `cross_listings` table, and the groups that they form are used to merge search results
+//! and to treat the listings as the same course when matching a degree audit's courses.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::db::{normalize_course_code, CatalogCourse, ScheduleDbManager, TermSection};
+use crate::schedule::{MeetingSlot, SlotDays};
+
+/// Where a cross-listing was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossListingSource {
+    /// The course catalog. These hold in every term.
+    Catalog,
+    /// A term's schedule, where the courses' sections share their meetings.
+    Schedule,
+}
+
+impl CrossListingSource {
+    /// The name that the source is stored under.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CrossListingSource::Catalog => "catalog",
+            CrossListingSource::Schedule => "schedule",
+        }
+    }
+}
+
+/// Groups of courses that are cross-listed with each other. Cross-listing is transitive,
+/// so if `A` is cross-listed with `B` and `B` with `C`, all three are one group.
+#[derive(Debug, Clone, Default)]
+pub struct CrossListings {
+    groups: Vec<BTreeSet<String>>,
+    /// The index of each course's group.
+    group_of: HashMap<String, usize>,
+}
+
+impl CrossListings {
+    /// Groups pairs of cross-listed courses.
+    ///
+    /// # Parameters
+    /// - `pairs`: The pairs of course codes (e.g., `("CSE 100", "MATH 176")`).
+    ///
+    /// # Returns
+    /// The groups.
+    pub fn from_pairs<I, S>(pairs: I) -> Self
+    where
+        I: IntoIterator<Item = (S, S)>,
+        S: AsRef<str>,
+    {
+        let mut listings = Self::default();
+        for (a, b) in pairs {
+            let (a, b) = (
+                normalize_course_code(a.as_ref()),
+                normalize_course_code(b.as_ref()),
+            );
+            if a.is_empty() || b.is_empty() || a == b {
+                continue;
+            }
+
+            let group = match (listings.group_of.get(&a), listings.group_of.get(&b)) {
+                (Some(&i), Some(&j)) if i != j => {
+                    let merged = std::mem::take(&mut listings.groups[j]);
+                    for code in &merged {
+                        listings.group_of.insert(code.clone(), i);
+                    }
+                    listings.groups[i].extend(merged);
+                    i
+                }
+                (Some(&i), _) | (_, Some(&i)) => i,
+                (None, None) => {
+                    listings.groups.push(BTreeSet::new());
+                    listings.groups.len() - 1
+                }
+            };
+
+            for code in [a, b] {
+                listings.group_of.insert(code.clone(), group);
+                listings.groups[group].insert(code);
+            }
+        }
+
+        listings
+    }
+
+    /// Gets the other codes that a course is offered as.
+    ///
+    /// # Parameters
+    /// - `code`: The course's code (e.g., `CSE 100`).
+    ///
+    /// # Returns
+    /// The other codes, in alphabetical order, or nothing if the course isn't cross-listed.
+    pub fn also_offered_as(&self, code: &str) -> Vec<String> {
+        let code = normalize_course_code(code);
+        self.group_of
+            .get(&code)
+            .map(|&i| {
+                self.groups[i]
+                    .iter()
+                    .filter(|c| **c != code)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Gets the code that stands for every listing of a course, so that listings can be
+    /// compared by it.
+    ///
+    /// # Parameters
+    /// - `code`: The course's code (e.g., `MATH 176`).
+    ///
+    /// # Returns
+    /// The alphabetically first of the course's codes (e.g., `CSE 100`), or the normalized
+    /// code itself if the course isn't cross-listed.
+    pub fn canonical(&self, code: &str) -> String {
+        let code = normalize_course_code(code);
+        self.group_of
+            .get(&code)
+            .and_then(|&i| self.groups[i].first())
+            .cloned()
+            .unwrap_or(code)
+    }
+}
+
+/// Collects the cross-listings that the course catalog lists.
+///
+/// # Parameters
+/// - `courses`: The catalog's courses.
+///
+/// # Returns
+/// The pairs of cross-listed courses.
+pub fn catalog_cross_listings(courses: &[CatalogCourse]) -> Vec<(String, String)> {
+    let mut pairs = BTreeSet::new();
+    for course in courses {
+        let code = normalize_course_code(&course.code());
+        for listed in &course.cross_listings {
+            let listed = normalize_course_code(listed);
+            if listed != code {
+                pairs.insert(if code < listed {
+                    (code.clone(), listed)
+                } else {
+                    (listed, code.clone())
+                });
+            }
+        }
+    }
+
+    pairs.into_iter().collect()
+}
+
+/// Gets what a section's weekly meetings have in common with those of the section's other
+/// listings: their days, times, rooms, and instructors. Sections with a weekly meeting
+/// that isn't in a real room (e.g., `TBA` or online) have none, since unrelated sections
+/// can share those.
+fn meeting_signature(section: &TermSection) -> Option<Vec<String>> {
+    let mut signature = vec![];
+    for meeting in &section.meetings {
+        let Some(slot) = MeetingSlot::from_db(meeting) else {
+            continue;
+        };
+        let SlotDays::Weekly(_) = slot.days else {
+            continue;
+        };
+
+        let building = meeting.building.as_deref().unwrap_or_default().trim();
+        let room = meeting.room.as_deref().unwrap_or_default().trim();
+        if building.is_empty() || room.is_empty() || building == "TBA" || room == "TBA" {
+            return None;
+        }
+
+        signature.push(format!(
+            "{}|{}|{}|{building} {room}|{}",
+            meeting.meeting_days.as_deref().unwrap_or_default(),
+            slot.start,
+            slot.end,
+            meeting.instructors.as_deref().unwrap_or_default(),
+        ));
+    }
+
+    signature.sort();
+    (!signature.is_empty()).then_some(signature)
+}
+
+/// Finds courses that are cross-listed in a term, i.e., that have sections meeting at the
+/// same weekly times, in the same room, with the same instructors.
+///
+/// # Parameters
+/// - `sections`: The term's sections, keyed by their course's normalized code.
+///
+/// # Returns
+/// The pairs of cross-listed courses.
+pub fn detect_cross_listings(
+    sections: &HashMap<String, Vec<TermSection>>,
+) -> Vec<(String, String)> {
+    let mut courses_by_signature: HashMap<Vec<String>, BTreeSet<&str>> = HashMap::new();
+    for (code, sections) in sections {
+        for signature in sections.iter().filter_map(meeting_signature) {
+            courses_by_signature
+                .entry(signature)
+                .or_default()
+                .insert(code);
+        }
+    }
+
+    let mut pairs = BTreeSet::new();
+    for courses in courses_by_signature.values() {
+        let mut courses = courses.iter();
+        let Some(first) = courses.next() else {
+            continue;
+        };
+        for other in courses {
+            pairs.insert((first.to_string(), other.to_string()));
+        }
+    }
+
+    pairs.into_iter().collect()
+}
+
+/// Detects the cross-listings in a term's schedule and stores them, replacing the ones
+/// detected before. This should be called whenever the term's schedule data changes.
+///
+/// # Parameters
+/// - `db`: The database.
+/// - `term`: The term.
+///
+/// # Returns
+/// The number of pairs of cross-listed courses found.
+pub fn refresh_term_cross_listings(db: &ScheduleDbManager, term: &str) -> rusqlite::Result<usize> {
+    let pairs = detect_cross_listings(&db.get_term_sections(term)?);
+    db.replace_cross_listings(term, CrossListingSource::Schedule, &pairs)?;
+    Ok(pairs.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::term_section;
+
+    fn section(subj_course_id: &str, room: &str, instructor: &str) -> TermSection {
+        let mut section = term_section(subj_course_id, "1", r#"["Tu","Th"]"#, 9, instructor, 0);
+        section.meetings[0].room = Some(room.to_owned());
+        section
+    }
+
+    #[test]
+    fn test_cross_listings() {
+        let sections: HashMap<String, Vec<TermSection>> = [
+            ("CSE 100", section("CSE 100", "105", "Doe, Jane")),
+            ("MATH 176", section("MATH 176", "105", "Doe, Jane")),
+            // Same time and room, but a different instructor
+            ("MATH 170A", section("MATH 170A", "105", "Roe, Rick")),
+            ("COGS 9", section("COGS 9", "TBA", "Doe, Jane")),
+            ("DSC 9", section("DSC 9", "TBA", "Doe, Jane")),
+        ]
+        .into_iter()
+        .map(|(code, section)| (code.to_owned(), vec![section]))
+        .collect();
+
+        let detected = detect_cross_listings(&sections);
+        assert_eq!(
+            vec![("CSE 100".to_owned(), "MATH 176".to_owned())],
+            detected
+        );
+
+        // The catalog links MATH 176 to a third listing, which joins the same group
+        let listings = CrossListings::from_pairs(
+            detected
+                .into_iter()
+                .chain([("ECE 100".to_owned(), "math  176".to_owned())]),
+        );
+        assert_eq!("CSE 100", listings.canonical("ece 100"));
+        assert_eq!(
+            vec!["CSE 100".to_owned(), "ECE 100".to_owned()],
+            listings.also_offered_as("MATH 176")
+        );
+        assert_eq!("COGS 9", listings.canonical("COGS 9"));
+        assert!(listings.also_offered_as("COGS 9").is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+//! A log of enrollment changes made through the API.
+
+use rusqlite::Result;
+
+use super::ScheduleDbManager;
+
+/// An enrollment change to be recorded.
+#[derive(Debug, Clone)]
+pub struct EnrollmentActionEntry<'a> {
+    pub term: &'a str,
+    pub section_id: &'a str,
+    pub action: &'a str,
+    pub webreg_success: bool,
+    pub verified: bool,
+    pub before_snapshot: Option<String>, // JSON string
+    pub after_snapshot: Option<String>,  // JSON string
+    pub created_at: &'a str,
+}
+
+impl ScheduleDbManager {
+    /// Records an enrollment change, returning its ID
+    pub fn insert_enrollment_action(&self, entry: &EnrollmentActionEntry) -> Result<i64> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO enrollment_actions (term, section_id, action, webreg_success, verified,
+                                             before_snapshot, after_snapshot, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            (
+                entry.term,
+                entry.section_id,
+                entry.action,
+                entry.webreg_success,
+                entry.verified,
+                &entry.before_snapshot,
+                &entry.after_snapshot,
+                entry.created_at,
+            ),
+        )?;
+
+        Ok(db.last_insert_rowid())
+    }
+}
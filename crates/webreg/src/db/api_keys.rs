@@ -0,0 +1,83 @@
+//! Storage for the scopes of API keys (see [`crate::api_keys`]) and their response filters
+//! (see [`crate::redaction`])
+
+use rusqlite::{OptionalExtension, Result};
+
+use super::ScheduleDbManager;
+
+impl ScheduleDbManager {
+    /// Sets the scopes of an API key, identified by its prefix. `scopes` is a
+    /// comma-separated list
+    pub fn set_key_scopes(&self, key_prefix: &str, scopes: &str) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO api_key_scopes (key_prefix, scopes, updated_at)
+             VALUES (?1, ?2, datetime('now'))
+             ON CONFLICT(key_prefix) DO UPDATE SET
+                scopes = excluded.scopes,
+                updated_at = excluded.updated_at",
+            (key_prefix, scopes),
+        )?;
+
+        Ok(())
+    }
+
+    /// Gets the scopes of an API key, if any were stored for it
+    pub fn get_key_scopes(&self, key_prefix: &str) -> Result<Option<String>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            "SELECT scopes FROM api_key_scopes WHERE key_prefix = ?",
+            [key_prefix],
+            |row| row.get(0),
+        )
+        .optional()
+    }
+
+    /// Deletes the scopes of an API key, which is done when the key is revoked
+    pub fn delete_key_scopes(&self, key_prefix: &str) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "DELETE FROM api_key_scopes WHERE key_prefix = ?",
+            [key_prefix],
+        )?;
+
+        Ok(())
+    }
+
+    /// Sets the response filter of an API key, identified by its prefix. `filter` is JSON
+    pub fn set_key_filter(&self, key_prefix: &str, filter: &str) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO api_key_filters (key_prefix, filter, updated_at)
+             VALUES (?1, ?2, datetime('now'))
+             ON CONFLICT(key_prefix) DO UPDATE SET
+                filter = excluded.filter,
+                updated_at = excluded.updated_at",
+            (key_prefix, filter),
+        )?;
+
+        Ok(())
+    }
+
+    /// Gets the response filter of an API key, as JSON, if it has one
+    pub fn get_key_filter(&self, key_prefix: &str) -> Result<Option<String>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            "SELECT filter FROM api_key_filters WHERE key_prefix = ?",
+            [key_prefix],
+            |row| row.get(0),
+        )
+        .optional()
+    }
+
+    /// Deletes the response filter of an API key. Returns whether it had one
+    pub fn delete_key_filter(&self, key_prefix: &str) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        let deleted = db.execute(
+            "DELETE FROM api_key_filters WHERE key_prefix = ?",
+            [key_prefix],
+        )?;
+
+        Ok(deleted > 0)
+    }
+}
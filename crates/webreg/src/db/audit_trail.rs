@@ -0,0 +1,131 @@
+//! Storage for the versioned history of requirement configs and overrides (see
+//! [`crate::audit_trail`])
+
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use serde::Serialize;
+
+use super::ScheduleDbManager;
+
+/// A version of a requirement config or override
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrailEntry {
+    pub entry_id: i64,
+    pub kind: String,
+    pub entity_key: String,
+    pub version: i64,
+    /// The entity as JSON, or nothing if this version removed it
+    pub content: Option<String>,
+    pub author: String,
+    pub reason: String,
+    pub created_at: String,
+}
+
+const ENTRY_COLUMNS: &str =
+    "entry_id, kind, entity_key, version, content, author, reason, created_at";
+
+fn entry_from_row(row: &Row) -> Result<TrailEntry> {
+    Ok(TrailEntry {
+        entry_id: row.get(0)?,
+        kind: row.get(1)?,
+        entity_key: row.get(2)?,
+        version: row.get(3)?,
+        content: row.get(4)?,
+        author: row.get(5)?,
+        reason: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+impl ScheduleDbManager {
+    /// Adds a version of an entity, after its latest one
+    ///
+    /// Returns the new version's entry
+    pub fn record_trail_version(
+        &self,
+        kind: &str,
+        entity_key: &str,
+        content: Option<&str>,
+        author: &str,
+        reason: &str,
+    ) -> Result<TrailEntry> {
+        let db = self.db.lock().unwrap();
+        record_trail_version(&db, kind, entity_key, content, author, reason)
+    }
+
+    /// Gets a version by its entry ID
+    pub fn get_trail_entry(&self, entry_id: i64) -> Result<Option<TrailEntry>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            &format!("SELECT {ENTRY_COLUMNS} FROM audit_trail WHERE entry_id = ?"),
+            [entry_id],
+            entry_from_row,
+        )
+        .optional()
+    }
+
+    /// Gets up to `limit` versions, newest first, optionally only of one kind or entity
+    pub fn get_audit_trail(
+        &self,
+        kind: Option<&str>,
+        entity_key: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<TrailEntry>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {ENTRY_COLUMNS} FROM audit_trail
+             WHERE (?1 IS NULL OR kind = ?1) AND (?2 IS NULL OR entity_key = ?2)
+             ORDER BY entry_id DESC LIMIT ?3"
+        ))?;
+
+        let entries = stmt.query_map((kind, entity_key, limit as i64), entry_from_row)?;
+        entries.collect()
+    }
+
+    /// Gets the latest version of every entity of a kind that hasn't been removed
+    pub fn get_current_trail_versions(&self, kind: &str) -> Result<Vec<TrailEntry>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {ENTRY_COLUMNS} FROM audit_trail t
+             WHERE kind = ?1 AND content IS NOT NULL
+                AND version = (SELECT MAX(version) FROM audit_trail
+                               WHERE kind = t.kind AND entity_key = t.entity_key)
+             ORDER BY entity_key"
+        ))?;
+
+        let entries = stmt.query_map([kind], entry_from_row)?;
+        entries.collect()
+    }
+
+    /// Whether an entity has any versions
+    pub fn has_trail_versions(&self, kind: &str, entity_key: &str) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            "SELECT EXISTS (SELECT 1 FROM audit_trail WHERE kind = ? AND entity_key = ?)",
+            (kind, entity_key),
+            |row| row.get(0),
+        )
+    }
+}
+
+/// Adds a version of an entity, after its latest one, returning the new version's entry
+pub(super) fn record_trail_version(
+    conn: &Connection,
+    kind: &str,
+    entity_key: &str,
+    content: Option<&str>,
+    author: &str,
+    reason: &str,
+) -> Result<TrailEntry> {
+    conn.query_row(
+        &format!(
+            "INSERT INTO audit_trail (kind, entity_key, version, content, author, reason, created_at)
+             VALUES (?1, ?2,
+                (SELECT COALESCE(MAX(version), 0) + 1 FROM audit_trail
+                 WHERE kind = ?1 AND entity_key = ?2),
+                ?3, ?4, ?5, datetime('now'))
+             RETURNING {ENTRY_COLUMNS}"
+        ),
+        (kind, entity_key, content, author, reason),
+        entry_from_row,
+    )
+}
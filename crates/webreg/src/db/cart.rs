@@ -0,0 +1,166 @@
+//! Storage for students' course carts, which hold the courses they're considering (with the
+//! sections they'd take, notes, and a priority) independently of WebReg's plans.
+
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+
+use super::ScheduleDbManager;
+
+/// A section that a student would take for a course in their cart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CartSection {
+    pub section_id: String,
+    pub section_code: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CartItem {
+    pub item_id: i64,
+    pub term: String,
+    /// The session that the cart belongs to. This is never sent to clients.
+    #[serde(skip)]
+    pub session_token: String,
+    pub subject_code: String,
+    pub course_code: String,
+    /// The sections, most preferred first
+    pub sections: Vec<CartSection>,
+    pub notes: Option<String>,
+    /// Lower is more important
+    pub priority: i64,
+    pub grading_option: Option<String>,
+    pub unit_count: Option<i64>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// The fields of a cart item that's being added or replaced
+#[derive(Debug, Clone)]
+pub struct NewCartItem<'a> {
+    pub term: &'a str,
+    pub session_token: &'a str,
+    pub subject_code: &'a str,
+    pub course_code: &'a str,
+    pub sections: &'a [CartSection],
+    pub notes: Option<&'a str>,
+    pub priority: i64,
+    pub grading_option: Option<&'a str>,
+    pub unit_count: Option<i64>,
+}
+
+const CART_COLUMNS: &str = "item_id, term, session_token, subject_code, course_code, sections, \
+                            notes, priority, grading_option, unit_count, created_at, updated_at";
+
+impl ScheduleDbManager {
+    /// Adds a course to a session's cart, or replaces the course's item if it's already in
+    /// the cart, returning the item's ID
+    pub fn upsert_cart_item(&self, item: &NewCartItem) -> Result<i64> {
+        let db = self.db.lock().unwrap();
+        upsert_cart_item(&db, item)
+    }
+
+    /// Gets a session's cart for a term, most important first
+    pub fn get_cart(&self, term: &str, session_token: &str) -> Result<Vec<CartItem>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {CART_COLUMNS} FROM cart_items
+             WHERE term = ? AND session_token = ?
+             ORDER BY priority, item_id"
+        ))?;
+
+        let items = stmt.query_map((term, session_token), cart_item_from_row)?;
+        items.collect()
+    }
+
+    /// Gets a session's cart in every term, by term and then most important first
+    pub fn get_session_cart(&self, session_token: &str) -> Result<Vec<CartItem>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {CART_COLUMNS} FROM cart_items
+             WHERE session_token = ?
+             ORDER BY term, priority, item_id"
+        ))?;
+
+        let items = stmt.query_map([session_token], cart_item_from_row)?;
+        items.collect()
+    }
+
+    /// Gets an item in a session's cart
+    pub fn get_cart_item(
+        &self,
+        term: &str,
+        session_token: &str,
+        item_id: i64,
+    ) -> Result<Option<CartItem>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            &format!(
+                "SELECT {CART_COLUMNS} FROM cart_items
+                 WHERE term = ? AND session_token = ? AND item_id = ?"
+            ),
+            (term, session_token, item_id),
+            cart_item_from_row,
+        )
+        .optional()
+    }
+
+    /// Removes an item from a session's cart. Returns whether there was such an item
+    pub fn delete_cart_item(&self, term: &str, session_token: &str, item_id: i64) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        let deleted = db.execute(
+            "DELETE FROM cart_items WHERE term = ? AND session_token = ? AND item_id = ?",
+            (term, session_token, item_id),
+        )?;
+
+        Ok(deleted > 0)
+    }
+}
+
+/// Adds a course to a session's cart, or replaces the course's item if it's already in the
+/// cart, returning the item's ID
+pub(super) fn upsert_cart_item(conn: &Connection, item: &NewCartItem) -> Result<i64> {
+    conn.query_row(
+        "INSERT INTO cart_items (
+            term, session_token, subject_code, course_code, sections, notes, priority,
+            grading_option, unit_count, created_at, updated_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, datetime('now'), datetime('now'))
+        ON CONFLICT (term, session_token, subject_code, course_code) DO UPDATE SET
+            sections = excluded.sections,
+            notes = excluded.notes,
+            priority = excluded.priority,
+            grading_option = excluded.grading_option,
+            unit_count = excluded.unit_count,
+            updated_at = excluded.updated_at
+        RETURNING item_id",
+        (
+            item.term,
+            item.session_token,
+            item.subject_code,
+            item.course_code,
+            serde_json::to_string(item.sections).unwrap(),
+            item.notes,
+            item.priority,
+            item.grading_option,
+            item.unit_count,
+        ),
+        |row| row.get(0),
+    )
+}
+
+/// Maps a row of `CART_COLUMNS` to a cart item
+fn cart_item_from_row(row: &Row) -> Result<CartItem> {
+    let sections: String = row.get(5)?;
+    Ok(CartItem {
+        item_id: row.get(0)?,
+        term: row.get(1)?,
+        session_token: row.get(2)?,
+        subject_code: row.get(3)?,
+        course_code: row.get(4)?,
+        sections: serde_json::from_str(&sections).unwrap_or_default(),
+        notes: row.get(6)?,
+        priority: row.get(7)?,
+        grading_option: row.get(8)?,
+        unit_count: row.get(9)?,
+        created_at: row.get(10)?,
+        updated_at: row.get(11)?,
+    })
+}
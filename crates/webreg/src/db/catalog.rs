@@ -0,0 +1,192 @@
+//! Storage for the UCSD course catalog's description of each course (see
+//! [`crate::scraper::catalog`]).
+
+use std::collections::HashMap;
+
+use rusqlite::{OptionalExtension, Result, Row};
+use serde::Serialize;
+
+use super::{normalize_course_code, ScheduleDbManager};
+
+/// A course as described in the course catalog
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CatalogCourse {
+    pub subject: String,
+    pub number: String,
+    pub title: String,
+    pub description: String,
+    /// The fewest units the course can be taken for
+    pub min_units: Option<f32>,
+    /// The most units the course can be taken for
+    pub max_units: Option<f32>,
+    /// The prerequisites as written in the catalog, if it lists any
+    pub prerequisites: Option<String>,
+    /// The codes of courses that this course is cross-listed with (e.g., `MATH 176`)
+    pub cross_listings: Vec<String>,
+}
+
+impl CatalogCourse {
+    /// The course's code (e.g., `CSE 100`)
+    pub fn code(&self) -> String {
+        format!("{} {}", self.subject, self.number)
+    }
+}
+
+fn catalog_course_from_row(row: &Row) -> Result<CatalogCourse> {
+    let cross_listings: String = row.get(7)?;
+    Ok(CatalogCourse {
+        subject: row.get(0)?,
+        number: row.get(1)?,
+        title: row.get(2)?,
+        description: row.get(3)?,
+        min_units: row.get(4)?,
+        max_units: row.get(5)?,
+        prerequisites: row.get(6)?,
+        cross_listings: cross_listings
+            .split(',')
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(str::to_owned)
+            .collect(),
+    })
+}
+
+const CATALOG_COLUMNS: &str =
+    "subject, number, title, description, min_units, max_units, prerequisites, cross_listings";
+
+impl ScheduleDbManager {
+    /// Saves courses from the catalog, replacing whatever was saved for them before. The
+    /// courses are saved in one transaction
+    pub fn upsert_catalog_courses(&self, courses: &[CatalogCourse]) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        {
+            let mut stmt = tx.prepare(&format!(
+                "INSERT OR REPLACE INTO catalog_courses ({CATALOG_COLUMNS}, scraped_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, datetime('now'))"
+            ))?;
+            for c in courses {
+                stmt.execute((
+                    c.subject.to_uppercase(),
+                    c.number.to_uppercase(),
+                    &c.title,
+                    &c.description,
+                    c.min_units,
+                    c.max_units,
+                    &c.prerequisites,
+                    c.cross_listings.join(","),
+                ))?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Gets a course from the catalog, if it's been scraped
+    pub fn get_catalog_course(&self, subject: &str, number: &str) -> Result<Option<CatalogCourse>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            &format!(
+                "SELECT {CATALOG_COLUMNS} FROM catalog_courses WHERE subject = ?1 AND number = ?2"
+            ),
+            (subject.trim().to_uppercase(), number.trim().to_uppercase()),
+            catalog_course_from_row,
+        )
+        .optional()
+    }
+
+    /// Gets several courses from the catalog, keyed by the course's normalized code (e.g.,
+    /// `CSE 100`). Courses that haven't been scraped are left out
+    pub fn get_catalog_courses_for(
+        &self,
+        codes: &[String],
+    ) -> Result<HashMap<String, CatalogCourse>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {CATALOG_COLUMNS} FROM catalog_courses WHERE subject = ?1 AND number = ?2"
+        ))?;
+
+        let mut courses = HashMap::new();
+        for code in codes {
+            let code = normalize_course_code(code);
+            let Some((subject, number)) = code.split_once(' ') else {
+                continue;
+            };
+            if let Some(course) = stmt
+                .query_row((subject, number), catalog_course_from_row)
+                .optional()?
+            {
+                courses.insert(code, course);
+            }
+        }
+
+        Ok(courses)
+    }
+
+    /// Gets every course in the catalog
+    pub fn get_catalog_courses(&self) -> Result<Vec<CatalogCourse>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {CATALOG_COLUMNS} FROM catalog_courses ORDER BY subject, number"
+        ))?;
+        let courses = stmt.query_map([], catalog_course_from_row)?;
+        courses.collect()
+    }
+
+    /// Gets every stored embedding that a model made of catalog courses, as the course's
+    /// code, the hash of the text that was embedded, and the embedding
+    pub fn get_catalog_embeddings(&self, model: &str) -> Result<Vec<(String, String, Vec<f32>)>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT subject || ' ' || number, text_hash, embedding FROM catalog_embeddings
+             WHERE model = ?",
+        )?;
+        let embeddings = stmt.query_map([model], |row| {
+            let bytes: Vec<u8> = row.get(2)?;
+            let embedding = bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect();
+            Ok((row.get(0)?, row.get(1)?, embedding))
+        })?;
+        embeddings.collect()
+    }
+
+    /// Saves embeddings that a model made of catalog courses, given as the course's code,
+    /// the hash of the text that was embedded, and the embedding
+    pub fn upsert_catalog_embeddings(
+        &self,
+        model: &str,
+        embeddings: &[(String, String, Vec<f32>)],
+    ) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO catalog_embeddings
+                     (subject, number, model, text_hash, embedding)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for (code, text_hash, embedding) in embeddings {
+                let Some((subject, number)) = code.split_once(' ') else {
+                    continue;
+                };
+                let bytes: Vec<u8> = embedding.iter().flat_map(|v| v.to_le_bytes()).collect();
+                stmt.execute((subject, number, model, text_hash, bytes))?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Gets every subject code that has been scraped in any term
+    pub fn get_scraped_subjects(&self) -> Result<Vec<String>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt =
+            db.prepare("SELECT DISTINCT TRIM(subj_code) FROM courses ORDER BY TRIM(subj_code)")?;
+        let subjects = stmt.query_map([], |row| row.get(0))?;
+        subjects.collect()
+    }
+}
@@ -0,0 +1,143 @@
+//! Storage for the quarter-by-quarter course plans that students have saved (see
+//! [`crate::course_plans`])
+
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+
+use super::ScheduleDbManager;
+
+/// The courses planned for one quarter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedQuarter {
+    /// The quarter (e.g., `FA24`)
+    pub term: String,
+    /// The codes of the courses planned for the quarter (e.g., `CSE 100`)
+    pub courses: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CoursePlan {
+    pub plan_id: i64,
+    /// The session that the plan belongs to. This is never sent to clients.
+    #[serde(skip)]
+    pub session_token: String,
+    pub name: String,
+    /// The plan's quarters, in order
+    pub quarters: Vec<PlannedQuarter>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+const COURSE_PLAN_COLUMNS: &str = "plan_id, session_token, name, quarters, created_at, updated_at";
+
+impl ScheduleDbManager {
+    /// Saves a new course plan for a session, returning the plan's ID. Fails with a
+    /// constraint violation if the session already has a plan with the same name
+    pub fn insert_course_plan(
+        &self,
+        session_token: &str,
+        name: &str,
+        quarters: &[PlannedQuarter],
+    ) -> Result<i64> {
+        let db = self.db.lock().unwrap();
+        insert_course_plan(&db, session_token, name, quarters)
+    }
+
+    /// Gets one of a session's course plans
+    pub fn get_course_plan(&self, session_token: &str, plan_id: i64) -> Result<Option<CoursePlan>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            &format!(
+                "SELECT {COURSE_PLAN_COLUMNS} FROM course_plans
+                 WHERE session_token = ? AND plan_id = ?"
+            ),
+            (session_token, plan_id),
+            course_plan_from_row,
+        )
+        .optional()
+    }
+
+    /// Gets a session's course plans, by name
+    pub fn get_course_plans(&self, session_token: &str) -> Result<Vec<CoursePlan>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {COURSE_PLAN_COLUMNS} FROM course_plans
+             WHERE session_token = ?
+             ORDER BY name"
+        ))?;
+
+        let plans = stmt.query_map([session_token], course_plan_from_row)?;
+        plans.collect()
+    }
+
+    /// Counts a session's course plans
+    pub fn count_course_plans(&self, session_token: &str) -> Result<usize> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            "SELECT COUNT(*) FROM course_plans WHERE session_token = ?",
+            [session_token],
+            |row| row.get(0),
+        )
+    }
+
+    /// Replaces the name and quarters of one of a session's course plans. Returns whether
+    /// there was such a plan. Fails with a constraint violation if the session has another
+    /// plan with the new name
+    pub fn update_course_plan(
+        &self,
+        session_token: &str,
+        plan_id: i64,
+        name: &str,
+        quarters: &[PlannedQuarter],
+    ) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        let updated = db.execute(
+            "UPDATE course_plans
+             SET name = ?, quarters = ?, updated_at = datetime('now')
+             WHERE session_token = ? AND plan_id = ?",
+            (
+                name,
+                serde_json::to_string(quarters).unwrap(),
+                session_token,
+                plan_id,
+            ),
+        )?;
+
+        Ok(updated > 0)
+    }
+}
+
+/// Saves a new course plan for a session, returning the plan's ID. Fails with a constraint
+/// violation if the session already has a plan with the same name
+pub(super) fn insert_course_plan(
+    conn: &Connection,
+    session_token: &str,
+    name: &str,
+    quarters: &[PlannedQuarter],
+) -> Result<i64> {
+    conn.query_row(
+        "INSERT INTO course_plans (
+            session_token, name, quarters, created_at, updated_at
+        ) VALUES (?1, ?2, ?3, datetime('now'), datetime('now'))
+        RETURNING plan_id",
+        (
+            session_token,
+            name,
+            serde_json::to_string(quarters).unwrap(),
+        ),
+        |row| row.get(0),
+    )
+}
+
+/// Maps a row of `COURSE_PLAN_COLUMNS` to a course plan
+fn course_plan_from_row(row: &Row) -> Result<CoursePlan> {
+    let quarters: String = row.get(3)?;
+    Ok(CoursePlan {
+        plan_id: row.get(0)?,
+        session_token: row.get(1)?,
+        name: row.get(2)?,
+        quarters: serde_json::from_str(&quarters).unwrap_or_default(),
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
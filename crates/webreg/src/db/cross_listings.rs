@@ -0,0 +1,49 @@
+//! Storage for pairs of cross-listed courses (see [`crate::cross_listings`])
+
+use rusqlite::Result;
+
+use super::ScheduleDbManager;
+use crate::cross_listings::{CrossListingSource, CrossListings};
+
+impl ScheduleDbManager {
+    /// Replaces the cross-listings found in one place for a term. Cross-listings from the
+    /// catalog hold in every term, so they're stored under an empty term
+    pub fn replace_cross_listings(
+        &self,
+        term: &str,
+        source: CrossListingSource,
+        pairs: &[(String, String)],
+    ) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        tx.execute(
+            "DELETE FROM cross_listings WHERE term = ?1 AND source = ?2",
+            (term, source.as_str()),
+        )?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO cross_listings (term, course_a, course_b, source)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (course_a, course_b) in pairs {
+                stmt.execute((term, course_a, course_b, source.as_str()))?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Gets the groups of cross-listed courses in a term, including the ones from the
+    /// catalog, or in every term if none is given
+    pub fn get_cross_listings(&self, term: Option<&str>) -> Result<CrossListings> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT course_a, course_b FROM cross_listings
+             WHERE ?1 IS NULL OR term = ?1 OR term = ''",
+        )?;
+        let pairs = stmt
+            .query_map([term], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<(String, String)>>>()?;
+
+        Ok(CrossListings::from_pairs(pairs))
+    }
+}
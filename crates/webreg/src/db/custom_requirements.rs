@@ -0,0 +1,108 @@
+//! Storage for the requirement sets that users define for themselves, which their degree
+//! progress is computed against alongside their official requirements
+
+use rusqlite::{Connection, Result, Row};
+use serde::Serialize;
+
+use super::ScheduleDbManager;
+use crate::degree_audit::config::SubrequirementConfig;
+
+/// A requirement set that a user defined (e.g., a pre-med checklist)
+#[derive(Debug, Clone, Serialize)]
+pub struct CustomRequirementSet {
+    pub requirement_id: i64,
+    /// The user that the set belongs to, by the prefix of their API key. This is never sent
+    /// to clients.
+    #[serde(skip)]
+    pub key_prefix: String,
+    pub name: String,
+    pub subrequirements: Vec<SubrequirementConfig>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+const CUSTOM_REQUIREMENT_COLUMNS: &str =
+    "requirement_id, key_prefix, name, subrequirements, created_at, updated_at";
+
+impl ScheduleDbManager {
+    /// Saves a user's requirement set, replacing their set with the same name if there is
+    /// one, returning the set's ID
+    pub fn upsert_custom_requirement_set(
+        &self,
+        key_prefix: &str,
+        name: &str,
+        subrequirements: &[SubrequirementConfig],
+    ) -> Result<i64> {
+        let db = self.db.lock().unwrap();
+        upsert_custom_requirement_set(&db, key_prefix, name, subrequirements)
+    }
+
+    /// Gets a user's requirement sets, in the order they were created
+    pub fn get_custom_requirement_sets(
+        &self,
+        key_prefix: &str,
+    ) -> Result<Vec<CustomRequirementSet>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {CUSTOM_REQUIREMENT_COLUMNS} FROM custom_requirements
+             WHERE key_prefix = ?
+             ORDER BY requirement_id"
+        ))?;
+
+        let sets = stmt.query_map([key_prefix], custom_requirement_set_from_row)?;
+        sets.collect()
+    }
+
+    /// Removes one of a user's requirement sets. Returns whether there was such a set
+    pub fn delete_custom_requirement_set(
+        &self,
+        key_prefix: &str,
+        requirement_id: i64,
+    ) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        let deleted = db.execute(
+            "DELETE FROM custom_requirements WHERE key_prefix = ? AND requirement_id = ?",
+            (key_prefix, requirement_id),
+        )?;
+
+        Ok(deleted > 0)
+    }
+}
+
+/// Saves a user's requirement set, replacing their set with the same name if there is one,
+/// returning the set's ID
+pub(super) fn upsert_custom_requirement_set(
+    conn: &Connection,
+    key_prefix: &str,
+    name: &str,
+    subrequirements: &[SubrequirementConfig],
+) -> Result<i64> {
+    conn.query_row(
+        "INSERT INTO custom_requirements (
+            key_prefix, name, subrequirements, created_at, updated_at
+        ) VALUES (?1, ?2, ?3, datetime('now'), datetime('now'))
+        ON CONFLICT (key_prefix, name) DO UPDATE SET
+            subrequirements = excluded.subrequirements,
+            updated_at = excluded.updated_at
+        RETURNING requirement_id",
+        (
+            key_prefix,
+            name,
+            serde_json::to_string(subrequirements).unwrap(),
+        ),
+        |row| row.get(0),
+    )
+}
+
+/// Maps a row of `CUSTOM_REQUIREMENT_COLUMNS` to a requirement set
+fn custom_requirement_set_from_row(row: &Row) -> Result<CustomRequirementSet> {
+    let subrequirements: String = row.get(3)?;
+    Ok(CustomRequirementSet {
+        requirement_id: row.get(0)?,
+        key_prefix: row.get(1)?,
+        name: row.get(2)?,
+        subrequirements: serde_json::from_str(&subrequirements).unwrap_or_default(),
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
@@ -0,0 +1,31 @@
+//! Versions of each term's schedule data, which tag the responses built from it
+
+use rusqlite::{Connection, OptionalExtension, Result};
+
+use super::ScheduleDbManager;
+
+impl ScheduleDbManager {
+    /// Gets the version of a term's schedule data, which is 0 if it has never been written
+    pub fn get_data_version(&self, term: &str) -> Result<i64> {
+        let db = self.db.lock().unwrap();
+        let version = db
+            .query_row(
+                "SELECT version FROM term_data_versions WHERE term = ?",
+                [term],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(version.unwrap_or(0))
+    }
+}
+
+/// Gives a term's schedule data a new version. Versions start from the current time, so
+/// that they aren't reused if the database is ever recreated
+pub(super) fn bump_data_version(conn: &Connection, term: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO term_data_versions (term, version) VALUES (?1, ?2)
+         ON CONFLICT (term) DO UPDATE SET version = MAX(version + 1, excluded.version)",
+        (term, chrono::Utc::now().timestamp_millis()),
+    )?;
+    Ok(())
+}
@@ -0,0 +1,103 @@
+//! Storage for the periods during which the services that the API depends on were up or
+//! down (see [`crate::status_history`])
+
+use rusqlite::{OptionalExtension, Result};
+
+use super::ScheduleDbManager;
+
+/// A period during which a service was seen to be up or down
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusInterval {
+    pub dependency: String,
+    pub up: bool,
+    /// When the service was first seen in this state, in milliseconds since the epoch
+    pub started_at: i64,
+    /// When the service was last seen in this state, in milliseconds since the epoch
+    pub last_checked_at: i64,
+}
+
+impl ScheduleDbManager {
+    /// Records the state that a service was seen in. The service's current period is
+    /// extended if it's in the same state and was last checked at most `max_gap_ms` ago;
+    /// otherwise, the period is closed and a new one is started
+    ///
+    /// Returns whether a new period was started
+    pub fn record_dependency_status(
+        &self,
+        dependency: &str,
+        up: bool,
+        now: i64,
+        max_gap_ms: i64,
+    ) -> Result<bool> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        let current: Option<(i64, bool, i64)> = tx
+            .query_row(
+                "SELECT interval_id, up, last_checked_at FROM dependency_status_intervals
+                 WHERE dependency = ? AND closed = 0
+                 ORDER BY started_at DESC LIMIT 1",
+                [dependency],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+
+        let started = match current {
+            Some((interval_id, current_up, last_checked_at))
+                if current_up == up && now - last_checked_at <= max_gap_ms =>
+            {
+                tx.execute(
+                    "UPDATE dependency_status_intervals SET last_checked_at = ?1
+                     WHERE interval_id = ?2",
+                    (now, interval_id),
+                )?;
+                false
+            }
+            current => {
+                if let Some((interval_id, _, last_checked_at)) = current {
+                    // The change happened sometime since the last check, which is as much as
+                    // is known; a long gap is left as a gap instead
+                    let ended_at = if now - last_checked_at <= max_gap_ms {
+                        now
+                    } else {
+                        last_checked_at
+                    };
+                    tx.execute(
+                        "UPDATE dependency_status_intervals
+                         SET closed = 1, last_checked_at = ?1 WHERE interval_id = ?2",
+                        (ended_at, interval_id),
+                    )?;
+                }
+
+                tx.execute(
+                    "INSERT INTO dependency_status_intervals
+                     (dependency, up, started_at, last_checked_at) VALUES (?1, ?2, ?3, ?3)",
+                    (dependency, up, now),
+                )?;
+                true
+            }
+        };
+
+        tx.commit()?;
+        Ok(started)
+    }
+
+    /// Gets the periods of every service that end at or after `since` (in milliseconds since
+    /// the epoch), oldest first
+    pub fn get_dependency_status_intervals(&self, since: i64) -> Result<Vec<StatusInterval>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT dependency, up, started_at, last_checked_at FROM dependency_status_intervals
+             WHERE last_checked_at >= ? ORDER BY started_at",
+        )?;
+
+        let intervals = stmt.query_map([since], |row| {
+            Ok(StatusInterval {
+                dependency: row.get(0)?,
+                up: row.get(1)?,
+                started_at: row.get(2)?,
+                last_checked_at: row.get(3)?,
+            })
+        })?;
+        intervals.collect()
+    }
+}
@@ -0,0 +1,531 @@
+//! Storage for enrollment jobs, which add a student to a section once a seat opens or at a
+//! given time, along with a log of every attempt that each job made.
+
+use std::time::Duration;
+
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use serde::Serialize;
+
+use super::ScheduleDbManager;
+use crate::notify::NotifyChannel;
+use crate::scraper::throttle::WatchSettings;
+
+/// The job is waiting for its start time or for a seat to open
+pub const ENROLL_JOB_PENDING: &str = "pending";
+/// The student was enrolled in the section
+pub const ENROLL_JOB_ENROLLED: &str = "enrolled";
+/// The job gave up (e.g., it ran out of attempts)
+pub const ENROLL_JOB_FAILED: &str = "failed";
+/// The job's session no longer exists (e.g., the server restarted), so the job is paused
+/// until it's moved onto a new session
+pub const ENROLL_JOB_NEEDS_SESSION: &str = "needs_session";
+/// The job's expiry time passed before the student could be enrolled
+pub const ENROLL_JOB_EXPIRED: &str = "expired";
+/// The student cancelled the job
+pub const ENROLL_JOB_CANCELLED: &str = "cancelled";
+
+/// The job expired because its expiry time passed
+pub const EXPIRY_TIME: &str = "expiry_time";
+/// The job expired because the term's add deadline passed
+pub const EXPIRY_ADD_DEADLINE: &str = "add_deadline";
+/// The job expired because the student enrolled in another section of its course
+pub const EXPIRY_ENROLLED_IN_COURSE: &str = "enrolled_in_course";
+
+/// How a watch throttles the alerts that its channels get when its section's seats open
+/// (see [`crate::scraper::throttle`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct WatchAlerts {
+    /// Minutes to wait after an alert before alerting again
+    pub cooldown_mins: u32,
+    /// Minutes that an alert about the same number of open seats is dropped for
+    pub dedup_mins: u32,
+    /// The number of seats that need to be open before the channels are alerted
+    pub min_seats: i64,
+}
+
+impl Default for WatchAlerts {
+    fn default() -> Self {
+        Self {
+            cooldown_mins: 0,
+            dedup_mins: 0,
+            min_seats: 1,
+        }
+    }
+}
+
+impl WatchAlerts {
+    /// The settings that the watch's [`crate::scraper::throttle::AlertThrottle`] is made with
+    pub fn settings(&self) -> WatchSettings {
+        WatchSettings {
+            cooldown: Duration::from_secs(u64::from(self.cooldown_mins) * 60),
+            dedup_window: Duration::from_secs(u64::from(self.dedup_mins) * 60),
+            min_open_seats: self.min_seats,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnrollJob {
+    pub job_id: i64,
+    pub term: String,
+    /// The session that the job enrolls with. This is never sent to clients.
+    #[serde(skip)]
+    pub session_token: String,
+    pub section_id: String,
+    pub subject_code: String,
+    pub course_code: String,
+    pub grading_option: Option<String>,
+    pub unit_count: Option<i64>,
+    pub run_at: Option<String>,
+    pub expires_at: Option<String>,
+    pub status: String,
+    pub attempts: i64,
+    pub created_at: String,
+    pub updated_at: String,
+    /// Why the job expired (e.g., `add_deadline`), if it did
+    pub expiry_reason: Option<String>,
+    /// The channels to notify once the job is enrolled, fails, or expires
+    pub notify_channels: Vec<NotifyChannel>,
+    /// How the seat alerts sent to the channels are throttled
+    #[serde(flatten)]
+    pub alerts: WatchAlerts,
+}
+
+/// The fields of a new enrollment job. Times are in SQLite's `YYYY-MM-DD HH:MM:SS` format,
+/// in UTC
+#[derive(Debug, Clone)]
+pub struct NewEnrollJob<'a> {
+    pub term: &'a str,
+    pub session_token: &'a str,
+    pub section_id: &'a str,
+    pub subject_code: &'a str,
+    pub course_code: &'a str,
+    pub grading_option: Option<&'a str>,
+    pub unit_count: Option<i64>,
+    pub run_at: Option<String>,
+    pub expires_at: Option<String>,
+    pub notify_channels: &'a [NotifyChannel],
+    pub alerts: WatchAlerts,
+}
+
+/// A single attempt that an enrollment job made
+#[derive(Debug, Clone, Serialize)]
+pub struct EnrollJobAttempt {
+    pub attempt_id: i64,
+    pub job_id: i64,
+    pub outcome: String,
+    pub message: Option<String>,
+    pub action_id: Option<i64>,
+    pub attempted_at: String,
+}
+
+const JOB_COLUMNS: &str = "job_id, term, session_token, section_id, subject_code, course_code, \
+                           grading_option, unit_count, run_at, expires_at, status, attempts, \
+                           created_at, updated_at, expiry_reason, notify_channels, \
+                           alert_cooldown_mins, alert_dedup_mins, alert_min_seats";
+
+impl ScheduleDbManager {
+    /// Inserts a pending enrollment job, returning its ID
+    pub fn insert_enroll_job(&self, job: &NewEnrollJob) -> Result<i64> {
+        let db = self.db.lock().unwrap();
+        insert_enroll_job(&db, job)
+    }
+
+    /// Gets every enrollment job that a session made in a term, newest first
+    pub fn get_enroll_jobs(&self, term: &str, session_token: &str) -> Result<Vec<EnrollJob>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {JOB_COLUMNS} FROM enroll_jobs
+             WHERE term = ? AND session_token = ?
+             ORDER BY job_id DESC"
+        ))?;
+
+        let jobs = stmt.query_map((term, session_token), job_from_row)?;
+        jobs.collect()
+    }
+
+    /// Gets an enrollment job by its ID
+    pub fn get_enroll_job(&self, job_id: i64) -> Result<Option<EnrollJob>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            &format!("SELECT {JOB_COLUMNS} FROM enroll_jobs WHERE job_id = ?"),
+            [job_id],
+            job_from_row,
+        )
+        .optional()
+    }
+
+    /// Gets every pending (or paused) enrollment job that a session made, across all terms,
+    /// oldest first
+    pub fn get_pending_enroll_jobs_for_session(
+        &self,
+        session_token: &str,
+    ) -> Result<Vec<EnrollJob>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {JOB_COLUMNS} FROM enroll_jobs
+             WHERE session_token = ?1 AND status IN (?2, ?3)
+             ORDER BY job_id"
+        ))?;
+
+        let jobs = stmt.query_map(
+            (session_token, ENROLL_JOB_PENDING, ENROLL_JOB_NEEDS_SESSION),
+            job_from_row,
+        )?;
+        jobs.collect()
+    }
+
+    /// Gets every pending (or paused) enrollment job, oldest first
+    pub fn get_pending_enroll_jobs(&self) -> Result<Vec<EnrollJob>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {JOB_COLUMNS} FROM enroll_jobs WHERE status IN (?, ?) ORDER BY job_id"
+        ))?;
+
+        let jobs = stmt.query_map([ENROLL_JOB_PENDING, ENROLL_JOB_NEEDS_SESSION], job_from_row)?;
+        jobs.collect()
+    }
+
+    /// Gets every pending (or paused) enrollment job that watches a section, oldest first
+    pub fn get_pending_enroll_jobs_for_section(
+        &self,
+        term: &str,
+        section_id: &str,
+    ) -> Result<Vec<EnrollJob>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {JOB_COLUMNS} FROM enroll_jobs
+             WHERE term = ?1 AND section_id = ?2 AND status IN (?3, ?4)
+             ORDER BY job_id"
+        ))?;
+
+        let jobs = stmt.query_map(
+            (
+                term,
+                section_id,
+                ENROLL_JOB_PENDING,
+                ENROLL_JOB_NEEDS_SESSION,
+            ),
+            job_from_row,
+        )?;
+        jobs.collect()
+    }
+
+    /// Pauses a pending job whose session no longer exists. Returns whether the job was
+    /// pending
+    pub fn pause_enroll_job(&self, job_id: i64) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        let paused = db.execute(
+            "UPDATE enroll_jobs SET status = ?1, updated_at = datetime('now')
+             WHERE job_id = ?2 AND status = ?3",
+            (ENROLL_JOB_NEEDS_SESSION, job_id, ENROLL_JOB_PENDING),
+        )?;
+
+        Ok(paused > 0)
+    }
+
+    /// Moves the pending and paused jobs of a session onto another session, resuming the
+    /// paused ones. Returns the IDs of the jobs that were moved, oldest first
+    pub fn move_enroll_jobs(&self, from_token: &str, to_token: &str) -> Result<Vec<i64>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "UPDATE enroll_jobs SET session_token = ?1, status = ?2, updated_at = datetime('now')
+             WHERE session_token = ?3 AND status IN (?2, ?4)
+             RETURNING job_id",
+        )?;
+
+        let mut job_ids = stmt
+            .query_map(
+                (
+                    to_token,
+                    ENROLL_JOB_PENDING,
+                    from_token,
+                    ENROLL_JOB_NEEDS_SESSION,
+                ),
+                |row| row.get(0),
+            )?
+            .collect::<Result<Vec<i64>>>()?;
+        job_ids.sort_unstable();
+        Ok(job_ids)
+    }
+
+    /// Expires a pending (or paused) job, recording why. Returns whether the job was pending
+    /// or paused
+    pub fn expire_enroll_job(&self, job_id: i64, reason: &str) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        let expired = db.execute(
+            "UPDATE enroll_jobs SET status = ?1, expiry_reason = ?2, updated_at = datetime('now')
+             WHERE job_id = ?3 AND status IN (?4, ?5)",
+            (
+                ENROLL_JOB_EXPIRED,
+                reason,
+                job_id,
+                ENROLL_JOB_PENDING,
+                ENROLL_JOB_NEEDS_SESSION,
+            ),
+        )?;
+
+        Ok(expired > 0)
+    }
+
+    /// Marks the pending (or paused) enrollment jobs whose expiry time has passed as expired,
+    /// returning them
+    pub fn expire_overdue_enroll_jobs(&self) -> Result<Vec<EnrollJob>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "UPDATE enroll_jobs SET status = ?1, expiry_reason = ?2, updated_at = datetime('now')
+             WHERE status IN (?3, ?4) AND expires_at <= datetime('now')
+             RETURNING {JOB_COLUMNS}"
+        ))?;
+
+        let jobs = stmt.query_map(
+            (
+                ENROLL_JOB_EXPIRED,
+                EXPIRY_TIME,
+                ENROLL_JOB_PENDING,
+                ENROLL_JOB_NEEDS_SESSION,
+            ),
+            job_from_row,
+        )?;
+        jobs.collect()
+    }
+
+    /// Gets every pending enrollment job whose start time (if any) has passed
+    pub fn get_due_enroll_jobs(&self) -> Result<Vec<EnrollJob>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {JOB_COLUMNS} FROM enroll_jobs
+             WHERE status = ? AND (run_at IS NULL OR run_at <= datetime('now'))
+             ORDER BY job_id"
+        ))?;
+
+        let jobs = stmt.query_map([ENROLL_JOB_PENDING], job_from_row)?;
+        jobs.collect()
+    }
+
+    /// Gets the attempts that a job made, oldest first
+    pub fn get_enroll_job_attempts(&self, job_id: i64) -> Result<Vec<EnrollJobAttempt>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT attempt_id, job_id, outcome, message, action_id, attempted_at
+             FROM enroll_job_attempts WHERE job_id = ? ORDER BY attempt_id",
+        )?;
+
+        let attempts = stmt.query_map([job_id], |row| {
+            Ok(EnrollJobAttempt {
+                attempt_id: row.get(0)?,
+                job_id: row.get(1)?,
+                outcome: row.get(2)?,
+                message: row.get(3)?,
+                action_id: row.get(4)?,
+                attempted_at: row.get(5)?,
+            })
+        })?;
+        attempts.collect()
+    }
+
+    /// Records an attempt that a job made, and sets the job's status
+    pub fn record_enroll_attempt(
+        &self,
+        job_id: i64,
+        outcome: &str,
+        message: Option<&str>,
+        action_id: Option<i64>,
+        status: &str,
+    ) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        tx.execute(
+            "INSERT INTO enroll_job_attempts (job_id, outcome, message, action_id, attempted_at)
+             VALUES (?1, ?2, ?3, ?4, datetime('now'))",
+            (job_id, outcome, message, action_id),
+        )?;
+        tx.execute(
+            "UPDATE enroll_jobs
+             SET attempts = attempts + 1, status = ?1, updated_at = datetime('now')
+             WHERE job_id = ?2",
+            (status, job_id),
+        )?;
+        tx.commit()
+    }
+
+    /// Cancels a session's pending job. Returns whether there was such a job
+    pub fn cancel_enroll_job(&self, term: &str, session_token: &str, job_id: i64) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        let cancelled = db.execute(
+            "UPDATE enroll_jobs SET status = ?1, updated_at = datetime('now')
+             WHERE term = ?2 AND session_token = ?3 AND job_id = ?4 AND status = ?5",
+            (
+                ENROLL_JOB_CANCELLED,
+                term,
+                session_token,
+                job_id,
+                ENROLL_JOB_PENDING,
+            ),
+        )?;
+
+        Ok(cancelled > 0)
+    }
+}
+
+/// Inserts a pending enrollment job, returning its ID
+pub(super) fn insert_enroll_job(conn: &Connection, job: &NewEnrollJob) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO enroll_jobs (
+            term, session_token, section_id, subject_code, course_code, grading_option,
+            unit_count, run_at, expires_at, status, notify_channels, alert_cooldown_mins,
+            alert_dedup_mins, alert_min_seats, created_at, updated_at
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, datetime('now'),
+            datetime('now')
+        )",
+        (
+            job.term,
+            job.session_token,
+            job.section_id,
+            job.subject_code,
+            job.course_code,
+            job.grading_option,
+            job.unit_count,
+            &job.run_at,
+            &job.expires_at,
+            ENROLL_JOB_PENDING,
+            serde_json::to_string(job.notify_channels).unwrap(),
+            job.alerts.cooldown_mins,
+            job.alerts.dedup_mins,
+            job.alerts.min_seats,
+        ),
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Maps a row of `JOB_COLUMNS` to an enrollment job
+fn job_from_row(row: &Row) -> Result<EnrollJob> {
+    Ok(EnrollJob {
+        job_id: row.get(0)?,
+        term: row.get(1)?,
+        session_token: row.get(2)?,
+        section_id: row.get(3)?,
+        subject_code: row.get(4)?,
+        course_code: row.get(5)?,
+        grading_option: row.get(6)?,
+        unit_count: row.get(7)?,
+        run_at: row.get(8)?,
+        expires_at: row.get(9)?,
+        status: row.get(10)?,
+        attempts: row.get(11)?,
+        created_at: row.get(12)?,
+        updated_at: row.get(13)?,
+        expiry_reason: row.get(14)?,
+        notify_channels: row
+            .get::<_, Option<String>>(15)?
+            .and_then(|channels| serde_json::from_str(&channels).ok())
+            .unwrap_or_default(),
+        alerts: WatchAlerts {
+            cooldown_mins: row.get(16)?,
+            dedup_mins: row.get(17)?,
+            min_seats: row.get(18)?,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job<'a>(session_token: &'a str, section_id: &'a str) -> NewEnrollJob<'a> {
+        NewEnrollJob {
+            term: "FA24",
+            session_token,
+            section_id,
+            subject_code: "CSE",
+            course_code: "100",
+            grading_option: None,
+            unit_count: Some(4),
+            run_at: None,
+            expires_at: None,
+            notify_channels: &[],
+            alerts: WatchAlerts::default(),
+        }
+    }
+
+    fn status(db: &ScheduleDbManager, job_id: i64) -> String {
+        db.get_enroll_job(job_id).unwrap().unwrap().status
+    }
+
+    #[test]
+    fn test_pause_enroll_job() {
+        let db = ScheduleDbManager::new(":memory:");
+        let paused = db.insert_enroll_job(&job("a", "111111")).unwrap();
+        let cancelled = db.insert_enroll_job(&job("a", "222222")).unwrap();
+        assert!(db.cancel_enroll_job("FA24", "a", cancelled).unwrap());
+
+        assert!(db.pause_enroll_job(paused).unwrap());
+        assert_eq!(ENROLL_JOB_NEEDS_SESSION, status(&db, paused));
+        // Only pending jobs are paused
+        assert!(!db.pause_enroll_job(paused).unwrap());
+        assert!(!db.pause_enroll_job(cancelled).unwrap());
+        assert_eq!(ENROLL_JOB_CANCELLED, status(&db, cancelled));
+
+        // A paused job is never due, but is still waiting, and can't be cancelled until it's
+        // moved onto a session
+        assert!(db.get_due_enroll_jobs().unwrap().is_empty());
+        let pending: Vec<_> = db
+            .get_pending_enroll_jobs()
+            .unwrap()
+            .into_iter()
+            .map(|job| job.job_id)
+            .collect();
+        assert_eq!(vec![paused], pending);
+        assert!(!db.cancel_enroll_job("FA24", "a", paused).unwrap());
+
+        // It can still expire
+        assert!(db.expire_enroll_job(paused, EXPIRY_ADD_DEADLINE).unwrap());
+        let expired = db.get_enroll_job(paused).unwrap().unwrap();
+        assert_eq!(ENROLL_JOB_EXPIRED, expired.status);
+        assert_eq!(Some(EXPIRY_ADD_DEADLINE), expired.expiry_reason.as_deref());
+    }
+
+    #[test]
+    fn test_move_enroll_jobs() {
+        let db = ScheduleDbManager::new(":memory:");
+        let pending = db.insert_enroll_job(&job("old", "111111")).unwrap();
+        let paused = db.insert_enroll_job(&job("old", "222222")).unwrap();
+        let enrolled = db.insert_enroll_job(&job("old", "333333")).unwrap();
+        let other = db.insert_enroll_job(&job("other", "444444")).unwrap();
+        assert!(db.pause_enroll_job(paused).unwrap());
+        db.record_enroll_attempt(enrolled, "enrolled", None, None, ENROLL_JOB_ENROLLED)
+            .unwrap();
+
+        // Pending and paused jobs are moved, and the paused one is resumed
+        assert_eq!(
+            vec![pending, paused],
+            db.move_enroll_jobs("old", "new").unwrap()
+        );
+        assert_eq!(ENROLL_JOB_PENDING, status(&db, paused));
+        let moved: Vec<_> = db
+            .get_pending_enroll_jobs_for_session("new")
+            .unwrap()
+            .into_iter()
+            .map(|job| job.job_id)
+            .collect();
+        assert_eq!(vec![pending, paused], moved);
+
+        // Finished jobs, and other sessions' jobs, stay where they were
+        assert_eq!(
+            "old",
+            db.get_enroll_job(enrolled).unwrap().unwrap().session_token
+        );
+        assert_eq!(ENROLL_JOB_ENROLLED, status(&db, enrolled));
+        assert_eq!(
+            "other",
+            db.get_enroll_job(other).unwrap().unwrap().session_token
+        );
+        assert!(db.move_enroll_jobs("old", "new").unwrap().is_empty());
+
+        // The moved jobs now belong to the new session
+        assert!(db.cancel_enroll_job("FA24", "new", pending).unwrap());
+        assert!(!db.cancel_enroll_job("FA24", "old", paused).unwrap());
+    }
+}
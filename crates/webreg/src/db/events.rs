@@ -0,0 +1,272 @@
+//! Storage for the student's custom events, which are mirrored to and from WebReg. Each
+//! event belongs to the session (see [`crate::sessions`]) that made or pulled it, and every
+//! query is limited to one session's events.
+
+use rusqlite::{OptionalExtension, Result, Row};
+use serde::Serialize;
+
+use super::ScheduleDbManager;
+
+/// The event matches what's on WebReg
+pub const SYNC_STATE_SYNCED: &str = "synced";
+/// The event was created or changed locally and needs to be sent to WebReg
+pub const SYNC_STATE_PENDING_PUSH: &str = "pending_push";
+/// The event was deleted locally and needs to be removed from WebReg
+pub const SYNC_STATE_PENDING_DELETE: &str = "pending_delete";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CustomEvent {
+    pub event_id: i64,
+    pub term: String,
+    pub name: String,
+    pub location: Option<String>,
+    pub days: Vec<String>,
+    pub start_hr: i32,
+    pub start_min: i32,
+    pub end_hr: i32,
+    pub end_min: i32,
+    pub webreg_timestamp: Option<String>,
+    pub sync_state: String,
+    pub updated_at: String,
+}
+
+/// The user-editable fields of a custom event
+#[derive(Debug, Clone)]
+pub struct CustomEventFields {
+    pub name: String,
+    pub location: Option<String>,
+    pub days: Vec<String>,
+    pub start_hr: i32,
+    pub start_min: i32,
+    pub end_hr: i32,
+    pub end_min: i32,
+}
+
+const EVENT_COLUMNS: &str = "event_id, term, name, location, days, start_hr, start_min, \
+                             end_hr, end_min, webreg_timestamp, sync_state, updated_at";
+
+impl ScheduleDbManager {
+    /// Gets all of a session's custom events for a term, including ones waiting to be
+    /// deleted from WebReg
+    pub fn get_custom_events(&self, term: &str, session_token: &str) -> Result<Vec<CustomEvent>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {EVENT_COLUMNS} FROM custom_events
+             WHERE term = ? AND session_token = ?
+             ORDER BY event_id"
+        ))?;
+
+        let events = stmt.query_map((term, session_token), event_from_row)?;
+        events.collect()
+    }
+
+    /// Gets a single one of a session's custom events
+    pub fn get_custom_event(
+        &self,
+        term: &str,
+        session_token: &str,
+        event_id: i64,
+    ) -> Result<Option<CustomEvent>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            &format!(
+                "SELECT {EVENT_COLUMNS} FROM custom_events
+                 WHERE term = ? AND session_token = ? AND event_id = ?"
+            ),
+            (term, session_token, event_id),
+            event_from_row,
+        )
+        .optional()
+    }
+
+    /// Inserts a session's custom event, returning its ID. `webreg_timestamp` should be given
+    /// if the event came from WebReg, in which case it's considered synced
+    pub fn insert_custom_event(
+        &self,
+        term: &str,
+        session_token: &str,
+        fields: &CustomEventFields,
+        webreg_timestamp: Option<&str>,
+    ) -> Result<i64> {
+        let sync_state = if webreg_timestamp.is_some() {
+            SYNC_STATE_SYNCED
+        } else {
+            SYNC_STATE_PENDING_PUSH
+        };
+
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO custom_events (
+                term, session_token, name, location, days, start_hr, start_min, end_hr,
+                end_min, webreg_timestamp, sync_state, updated_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, datetime('now'))",
+            (
+                term,
+                session_token,
+                &fields.name,
+                &fields.location,
+                serde_json::to_string(&fields.days).unwrap(),
+                fields.start_hr,
+                fields.start_min,
+                fields.end_hr,
+                fields.end_min,
+                webreg_timestamp,
+                sync_state,
+            ),
+        )?;
+
+        Ok(db.last_insert_rowid())
+    }
+
+    /// Updates one of a session's custom events. If `sync_state` is `None`, the event is
+    /// marked as needing to be pushed to WebReg. Returns whether the event exists
+    pub fn update_custom_event(
+        &self,
+        session_token: &str,
+        event_id: i64,
+        fields: &CustomEventFields,
+        sync_state: Option<&str>,
+    ) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        let updated = db.execute(
+            "UPDATE custom_events
+             SET name = ?1, location = ?2, days = ?3, start_hr = ?4, start_min = ?5,
+                 end_hr = ?6, end_min = ?7, sync_state = ?8, updated_at = datetime('now')
+             WHERE event_id = ?9 AND session_token = ?10",
+            (
+                &fields.name,
+                &fields.location,
+                serde_json::to_string(&fields.days).unwrap(),
+                fields.start_hr,
+                fields.start_min,
+                fields.end_hr,
+                fields.end_min,
+                sync_state.unwrap_or(SYNC_STATE_PENDING_PUSH),
+                event_id,
+                session_token,
+            ),
+        )?;
+
+        Ok(updated > 0)
+    }
+
+    /// Records that one of a session's custom events now matches what's on WebReg
+    pub fn mark_custom_event_synced(
+        &self,
+        session_token: &str,
+        event_id: i64,
+        webreg_timestamp: &str,
+    ) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "UPDATE custom_events SET webreg_timestamp = ?1, sync_state = ?2
+             WHERE event_id = ?3 AND session_token = ?4",
+            (webreg_timestamp, SYNC_STATE_SYNCED, event_id, session_token),
+        )?;
+        Ok(())
+    }
+
+    /// Deletes one of a session's custom events locally. Events that exist on WebReg are
+    /// kept around, marked as pending deletion, until the next sync removes them there too.
+    /// Returns whether the event exists
+    pub fn delete_custom_event(
+        &self,
+        term: &str,
+        session_token: &str,
+        event_id: i64,
+    ) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        let deleted = db.execute(
+            "DELETE FROM custom_events
+             WHERE term = ?1 AND session_token = ?2 AND event_id = ?3
+                AND webreg_timestamp IS NULL",
+            (term, session_token, event_id),
+        )?;
+        if deleted > 0 {
+            return Ok(true);
+        }
+
+        let marked = db.execute(
+            "UPDATE custom_events SET sync_state = ?1, updated_at = datetime('now')
+             WHERE term = ?2 AND session_token = ?3 AND event_id = ?4",
+            (SYNC_STATE_PENDING_DELETE, term, session_token, event_id),
+        )?;
+        Ok(marked > 0)
+    }
+
+    /// Removes one of a session's custom events entirely, regardless of its sync state
+    pub fn purge_custom_event(&self, session_token: &str, event_id: i64) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "DELETE FROM custom_events WHERE event_id = ? AND session_token = ?",
+            (event_id, session_token),
+        )?;
+        Ok(())
+    }
+}
+
+/// Maps a row of `EVENT_COLUMNS` to a custom event
+fn event_from_row(row: &Row) -> Result<CustomEvent> {
+    let days: String = row.get(4)?;
+    Ok(CustomEvent {
+        event_id: row.get(0)?,
+        term: row.get(1)?,
+        name: row.get(2)?,
+        location: row.get(3)?,
+        days: serde_json::from_str(&days).unwrap_or_default(),
+        start_hr: row.get(5)?,
+        start_min: row.get(6)?,
+        end_hr: row.get(7)?,
+        end_min: row.get(8)?,
+        webreg_timestamp: row.get(9)?,
+        sync_state: row.get(10)?,
+        updated_at: row.get(11)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_per_session() {
+        let db = ScheduleDbManager::new(":memory:");
+        let fields = CustomEventFields {
+            name: "Work".to_owned(),
+            location: None,
+            days: vec!["M".to_owned(), "W".to_owned()],
+            start_hr: 9,
+            start_min: 0,
+            end_hr: 11,
+            end_min: 0,
+        };
+
+        let event_id = db.insert_custom_event("S124", "a", &fields, None).unwrap();
+        db.insert_custom_event("S124", "b", &fields, Some("2024-01-01 00:00:00"))
+            .unwrap();
+
+        let events = db.get_custom_events("S124", "a").unwrap();
+        assert_eq!(1, events.len());
+        assert_eq!(event_id, events[0].event_id);
+        assert_eq!(1, db.get_custom_events("S124", "b").unwrap().len());
+        assert!(db.get_custom_events("FA24", "a").unwrap().is_empty());
+
+        // Another session can't see, change, or delete the event
+        assert!(db
+            .get_custom_event("S124", "b", event_id)
+            .unwrap()
+            .is_none());
+        assert!(!db
+            .update_custom_event("b", event_id, &fields, None)
+            .unwrap());
+        assert!(!db.delete_custom_event("S124", "b", event_id).unwrap());
+        db.purge_custom_event("b", event_id).unwrap();
+        assert!(db
+            .get_custom_event("S124", "a", event_id)
+            .unwrap()
+            .is_some());
+
+        assert!(db.delete_custom_event("S124", "a", event_id).unwrap());
+        assert!(db.get_custom_events("S124", "a").unwrap().is_empty());
+    }
+}
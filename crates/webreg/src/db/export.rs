@@ -0,0 +1,111 @@
+//! Denormalized rows of a term's schedule data, read a page at a time so that a whole term
+//! can be exported without holding it in memory.
+
+use rusqlite::{Connection, Result};
+
+use super::ScheduleDbManager;
+
+/// A meeting along with its section and course. Sections without meetings have a single
+/// row whose meeting fields are all empty
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleExportRow {
+    pub section_id_pk: i64,
+    pub subj_code: String,
+    pub course_code: String,
+    pub section_id: String,
+    pub section_code: String,
+    pub section_start_date: Option<String>,
+    pub section_end_date: Option<String>,
+    pub total_seats: Option<i64>,
+    pub enrolled_ct: Option<i64>,
+    pub meeting_type: Option<String>,
+    pub meeting_days_type: Option<String>,
+    pub meeting_days: Option<String>,
+    pub start_hr: Option<i32>,
+    pub start_min: Option<i32>,
+    pub end_hr: Option<i32>,
+    pub end_min: Option<i32>,
+    pub building: Option<String>,
+    pub room: Option<String>,
+    pub instructors: Option<String>,
+    pub meeting_start_date: Option<String>,
+    pub meeting_end_date: Option<String>,
+}
+
+impl ScheduleDbManager {
+    /// Gets the rows of up to `max_sections` sections in a term, starting after the section
+    /// whose primary key is `after_section_pk`, ordered by section and then by meeting. Pass
+    /// the last row's `section_id_pk` to get the next page. Cancelled sections are left out
+    pub fn get_schedule_export_page(
+        &self,
+        term: &str,
+        after_section_pk: i64,
+        max_sections: usize,
+    ) -> Result<Vec<ScheduleExportRow>> {
+        let db = self.db.lock().unwrap();
+        query_export_rows(&db, term, after_section_pk, max_sections as i64)
+    }
+
+    /// Gets the rows of every section in a term at once, so that they all come from the
+    /// same state of the database
+    pub fn get_schedule_export(&self, term: &str) -> Result<Vec<ScheduleExportRow>> {
+        let db = self.db.lock().unwrap();
+        // SQLite treats a negative limit as no limit
+        query_export_rows(&db, term, 0, -1)
+    }
+}
+
+/// Gets the rows of up to `limit` sections in a term after `after_section_pk`
+fn query_export_rows(
+    db: &Connection,
+    term: &str,
+    after_section_pk: i64,
+    limit: i64,
+) -> Result<Vec<ScheduleExportRow>> {
+    let mut stmt = db.prepare(
+        "SELECT s.section_id_pk, c.subj_code, c.course_code, s.section_id, s.section_code,
+                    s.start_date, s.end_date, s.total_seats, s.enrolled_ct,
+                    m.meeting_type, m.meeting_days_type, m.meeting_days, m.start_hr,
+                    m.start_min, m.end_hr, m.end_min, m.building, m.room, m.instructors,
+                    m.start_date, m.end_date
+             FROM sections s
+             JOIN courses c ON s.course_id = c.course_id
+             LEFT JOIN meetings m ON m.section_id_pk = s.section_id_pk
+             WHERE s.section_id_pk IN (
+                 SELECT s2.section_id_pk FROM sections s2
+                 JOIN courses c2 ON s2.course_id = c2.course_id
+                 WHERE c2.term = ?1 AND s2.section_id_pk > ?2 AND s2.cancelled_at IS NULL
+                 ORDER BY s2.section_id_pk
+                 LIMIT ?3
+             )
+             ORDER BY s.section_id_pk, m.meeting_id",
+    )?;
+
+    let rows = stmt.query_map((term, after_section_pk, limit), |row| {
+        Ok(ScheduleExportRow {
+            section_id_pk: row.get(0)?,
+            subj_code: row.get(1)?,
+            course_code: row.get(2)?,
+            section_id: row.get(3)?,
+            section_code: row.get(4)?,
+            section_start_date: row.get(5)?,
+            section_end_date: row.get(6)?,
+            total_seats: row.get(7)?,
+            enrolled_ct: row.get(8)?,
+            meeting_type: row.get(9)?,
+            meeting_days_type: row.get(10)?,
+            meeting_days: row.get(11)?,
+            start_hr: row.get(12)?,
+            start_min: row.get(13)?,
+            end_hr: row.get(14)?,
+            end_min: row.get(15)?,
+            building: row.get(16)?,
+            room: row.get(17)?,
+            instructors: row.get(18)?,
+            meeting_start_date: row.get(19)?,
+            meeting_end_date: row.get(20)?,
+        })
+    })?;
+
+    rows.collect()
+}
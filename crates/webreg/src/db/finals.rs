@@ -0,0 +1,56 @@
+//! Queries about sections' exams, which are classified by their meeting type when they're
+//! stored (see [`crate::schedule::ExamKind`]).
+
+use rusqlite::{Connection, Result};
+
+use super::{meeting_from_row, DbMeeting, ScheduleDbManager};
+use crate::schedule::ExamKind;
+
+impl ScheduleDbManager {
+    /// Gets the exams of the given sections in a term, along with each exam's section ID and
+    /// its course's subject/course ID (e.g., `CSE 100`). Sections without exams are left out
+    pub fn get_section_exams(
+        &self,
+        term: &str,
+        section_ids: &[String],
+    ) -> Result<Vec<(String, String, DbMeeting)>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT m.meeting_id, m.section_id_pk, m.meeting_type, m.meeting_days_type,
+                    m.meeting_days, m.start_hr, m.start_min, m.end_hr, m.end_min,
+                    m.building, m.room, m.instructors, m.start_date, m.end_date,
+                    s.section_id, c.subj_course_id
+             FROM meetings m
+             JOIN sections s ON m.section_id_pk = s.section_id_pk
+             JOIN courses c ON s.course_id = c.course_id
+             WHERE c.term = ?1 AND s.section_id = ?2 AND s.cancelled_at IS NULL
+             AND m.exam_kind IS NOT NULL
+             ORDER BY m.meeting_days, m.start_hr, m.start_min",
+        )?;
+
+        let mut exams = vec![];
+        for section_id in section_ids {
+            let rows = stmt.query_map((term, section_id), |row| {
+                Ok((row.get(14)?, row.get(15)?, meeting_from_row(row)?))
+            })?;
+            for row in rows {
+                exams.push(row?);
+            }
+        }
+
+        Ok(exams)
+    }
+}
+
+/// Classifies the exams among meetings stored before exams were classified
+pub(super) fn backfill_exam_kinds(conn: &Connection) -> Result<()> {
+    for (meeting_type, kind) in [("FI", ExamKind::Final), ("MI", ExamKind::Midterm)] {
+        conn.execute(
+            "UPDATE meetings SET exam_kind = ?1
+             WHERE exam_kind IS NULL AND TRIM(meeting_type) = ?2",
+            (kind.as_str(), meeting_type),
+        )?;
+    }
+
+    Ok(())
+}
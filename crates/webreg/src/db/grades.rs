@@ -0,0 +1,112 @@
+//! Storage for historical grade distributions and course evaluations, which are imported
+//! from outside sources (see [`crate::grades`]) rather than scraped from WebReg.
+
+use std::collections::HashMap;
+
+use rusqlite::{Result, Row};
+use serde::Serialize;
+
+use super::{normalize_course_code, ScheduleDbManager};
+
+/// How students did in, and rated, one offering of a course
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GradeRecord {
+    /// The course's normalized code (e.g., `CSE 100`)
+    pub course: String,
+    pub term: String,
+    pub instructor: String,
+    pub enrolled: Option<i64>,
+    /// The average grade received, on a 4.0 scale
+    pub avg_gpa: Option<f32>,
+    /// The percent of students who recommend the instructor
+    pub recommend_instructor: Option<f32>,
+    /// The percent of students who recommend the class
+    pub recommend_class: Option<f32>,
+    /// The average number of hours studied per week
+    pub study_hours: Option<f32>,
+}
+
+fn grade_record_from_row(row: &Row) -> Result<GradeRecord> {
+    Ok(GradeRecord {
+        course: row.get(0)?,
+        term: row.get(1)?,
+        instructor: row.get(2)?,
+        enrolled: row.get(3)?,
+        avg_gpa: row.get(4)?,
+        recommend_instructor: row.get(5)?,
+        recommend_class: row.get(6)?,
+        study_hours: row.get(7)?,
+    })
+}
+
+const GRADE_COLUMNS: &str = "course, term, instructor, enrolled, avg_gpa, recommend_instructor,
+                             recommend_class, study_hours";
+
+impl ScheduleDbManager {
+    /// Saves grade records, replacing any existing record for the same course, term, and
+    /// instructor. The records are saved in one transaction
+    ///
+    /// Returns the number of records saved
+    pub fn upsert_grade_records(&self, records: &[GradeRecord]) -> Result<usize> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        {
+            let mut stmt = tx.prepare(&format!(
+                "INSERT OR REPLACE INTO grade_distributions ({GRADE_COLUMNS})
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"
+            ))?;
+            for r in records {
+                stmt.execute((
+                    normalize_course_code(&r.course),
+                    &r.term,
+                    &r.instructor,
+                    r.enrolled,
+                    r.avg_gpa,
+                    r.recommend_instructor,
+                    r.recommend_class,
+                    r.study_hours,
+                ))?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(records.len())
+    }
+
+    /// Gets every grade record for a course, newest term first
+    pub fn get_grade_records(&self, course: &str) -> Result<Vec<GradeRecord>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {GRADE_COLUMNS} FROM grade_distributions
+             WHERE course = ?
+             ORDER BY term DESC, instructor"
+        ))?;
+        let records = stmt.query_map([normalize_course_code(course)], grade_record_from_row)?;
+        records.collect()
+    }
+
+    /// Gets the grade records for each of several courses, keyed by the course's normalized
+    /// code. Courses without any records are left out
+    pub fn get_grade_records_for(
+        &self,
+        courses: &[String],
+    ) -> Result<HashMap<String, Vec<GradeRecord>>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {GRADE_COLUMNS} FROM grade_distributions WHERE course = ?"
+        ))?;
+
+        let mut by_course = HashMap::new();
+        for course in courses {
+            let course = normalize_course_code(course);
+            let records = stmt
+                .query_map([&course], grade_record_from_row)?
+                .collect::<Result<Vec<_>>>()?;
+            if !records.is_empty() {
+                by_course.insert(course, records);
+            }
+        }
+
+        Ok(by_course)
+    }
+}
@@ -0,0 +1,197 @@
+//! Normalized storage of the instructors teaching each meeting, so that sections can be
+//! looked up by instructor.
+
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+
+use super::{section_from_row, DbMeeting, DbSection, ScheduleDbManager};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstructorSummary {
+    pub name: String,
+    pub section_count: i64,
+    pub courses: Vec<String>,
+}
+
+impl ScheduleDbManager {
+    /// Gets every instructor teaching in a term, optionally only those whose name contains
+    /// `search` (case-insensitive), along with what they teach
+    pub fn get_instructors(
+        &self,
+        term: &str,
+        search: Option<&str>,
+    ) -> Result<Vec<InstructorSummary>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT i.name, COUNT(DISTINCT s.section_id_pk),
+                    GROUP_CONCAT(DISTINCT c.subj_course_id)
+             FROM instructors i
+             JOIN meeting_instructors mi ON mi.instructor_id = i.instructor_id
+             JOIN meetings m ON m.meeting_id = mi.meeting_id
+             JOIN sections s ON s.section_id_pk = m.section_id_pk
+             JOIN courses c ON c.course_id = s.course_id
+             WHERE c.term = ?1 AND s.cancelled_at IS NULL
+             AND (?2 IS NULL OR i.name LIKE '%' || ?2 || '%')
+             GROUP BY i.instructor_id
+             ORDER BY i.name",
+        )?;
+
+        let instructors = stmt.query_map((term, search), |row| {
+            let courses: Option<String> = row.get(2)?;
+            let mut courses: Vec<String> = courses
+                .unwrap_or_default()
+                .split(',')
+                .map(|c| c.trim().to_owned())
+                .filter(|c| !c.is_empty())
+                .collect();
+            courses.sort();
+
+            Ok(InstructorSummary {
+                name: row.get(0)?,
+                section_count: row.get(1)?,
+                courses,
+            })
+        })?;
+
+        instructors.collect()
+    }
+
+    /// Gets every section that an instructor teaches at least one meeting of in a term,
+    /// along with each section's subject/course ID and meetings
+    pub fn get_instructor_sections(
+        &self,
+        term: &str,
+        name: &str,
+    ) -> Result<Vec<(String, DbSection, Vec<DbMeeting>)>> {
+        let sections = {
+            let db = self.db.lock().unwrap();
+            let mut stmt = db.prepare(
+                "SELECT DISTINCT s.section_id_pk, s.course_id, s.section_id, s.section_code,
+                        s.start_date, s.end_date, s.cancelled_at, c.subj_course_id
+                 FROM sections s
+                 JOIN courses c ON c.course_id = s.course_id
+                 JOIN meetings m ON m.section_id_pk = s.section_id_pk
+                 JOIN meeting_instructors mi ON mi.meeting_id = m.meeting_id
+                 JOIN instructors i ON i.instructor_id = mi.instructor_id
+                 WHERE c.term = ?1 AND i.name = ?2 AND s.cancelled_at IS NULL
+                 ORDER BY c.subj_course_id, s.section_code",
+            )?;
+
+            let sections = stmt.query_map((term, name.trim()), |row| {
+                Ok((row.get::<_, String>(7)?, section_from_row(row)?))
+            })?;
+            sections.collect::<Result<Vec<_>>>()?
+        };
+
+        sections
+            .into_iter()
+            .map(|(subj_course_id, section)| {
+                let meetings = self.get_meetings_for_section_pk(section.section_id_pk)?;
+                Ok((subj_course_id, section, meetings))
+            })
+            .collect()
+    }
+}
+
+/// Records that the given instructors teach a meeting, adding any instructors that
+/// haven't been seen before
+pub(super) fn link_instructors(conn: &Connection, meeting_id: i64, names: &[String]) -> Result<()> {
+    for name in names.iter().map(|n| n.trim()).filter(|n| !n.is_empty()) {
+        conn.execute(
+            "INSERT OR IGNORE INTO instructors (name) VALUES (?)",
+            [name],
+        )?;
+        conn.execute(
+            "INSERT OR IGNORE INTO meeting_instructors (meeting_id, instructor_id)
+             SELECT ?1, instructor_id FROM instructors WHERE name = ?2",
+            (meeting_id, name),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Fills in the instructors of meetings stored before instructors were normalized, using
+/// each meeting's `instructors` JSON column
+pub(super) fn backfill_instructors(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT meeting_id, instructors FROM meetings
+         WHERE instructors IS NOT NULL AND instructors != '[]'
+           AND meeting_id NOT IN (SELECT meeting_id FROM meeting_instructors)",
+    )?;
+
+    let meetings = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    for (meeting_id, instructors) in meetings {
+        let names: Vec<String> = serde_json::from_str(&instructors).unwrap_or_default();
+        link_instructors(conn, meeting_id, &names)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::section;
+    use crate::schedule::MeetingDates;
+
+    #[test]
+    fn test_instructor_lookup() {
+        let db = ScheduleDbManager::new(":memory:");
+        let dates = MeetingDates::default();
+        db.insert_course_with_sections(
+            "FA24",
+            vec![
+                section("CSE 100", "123456", "CENTR", "Doe, Jane"),
+                section("CSE 101", "234567", "CENTR", "Smith, Sam"),
+            ],
+            None,
+            &dates,
+        )
+        .unwrap();
+        db.insert_course_with_sections(
+            "WI25",
+            vec![section("CSE 100", "123456", "WLH", "Doe, Jane")],
+            None,
+            &dates,
+        )
+        .unwrap();
+
+        // Meetings stored before instructors were normalized are linked by the backfill
+        {
+            let conn = db.db.lock().unwrap();
+            conn.execute("DELETE FROM meeting_instructors", []).unwrap();
+            backfill_instructors(&conn).unwrap();
+            backfill_instructors(&conn).unwrap();
+        }
+
+        let instructors = db.get_instructors("FA24", None).unwrap();
+        assert_eq!(2, instructors.len());
+        assert_eq!("Doe, Jane", instructors[0].name);
+        assert_eq!(1, instructors[0].section_count);
+        assert_eq!(vec!["CSE 100"], instructors[0].courses);
+        let search = db.get_instructors("FA24", Some("smith")).unwrap();
+        assert_eq!(1, search.len());
+        assert_eq!("Smith, Sam", search[0].name);
+
+        // Each term's section only has its own meetings, even though the IDs are the same
+        for (term, building) in [("FA24", "CENTR"), ("WI25", "WLH")] {
+            let sections = db.get_instructor_sections(term, " Doe, Jane ").unwrap();
+            assert_eq!(1, sections.len());
+            let (subj_course_id, section, meetings) = &sections[0];
+            assert_eq!("CSE 100", subj_course_id);
+            assert_eq!("123456", section.section_id);
+            assert_eq!(1, meetings.len());
+            assert_eq!(Some(building), meetings[0].building.as_deref());
+        }
+        assert!(db
+            .get_instructor_sections("WI25", "Smith, Sam")
+            .unwrap()
+            .is_empty());
+    }
+}
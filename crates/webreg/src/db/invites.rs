@@ -0,0 +1,152 @@
+//! Storage for invite codes (see [`crate::invites`]), the API keys registered with them, and
+//! the cookie servers that those keys were given.
+
+use rusqlite::{OptionalExtension, Result};
+use serde::Serialize;
+
+use super::ScheduleDbManager;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Invite {
+    pub invite_id: i64,
+    pub description: Option<String>,
+    pub max_uses: i64,
+    pub uses: i64,
+    pub expires_at: Option<String>,
+    pub revoked: bool,
+    pub created_at: String,
+}
+
+impl ScheduleDbManager {
+    /// Inserts an invite, returning its ID. `expires_at` is in SQLite's
+    /// `YYYY-MM-DD HH:MM:SS` format, in UTC
+    pub fn insert_invite(
+        &self,
+        code_hash: &str,
+        description: Option<&str>,
+        max_uses: i64,
+        expires_at: Option<&str>,
+    ) -> Result<i64> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO invites (code_hash, description, max_uses, expires_at, created_at)
+             VALUES (?1, ?2, ?3, ?4, datetime('now'))",
+            (code_hash, description, max_uses, expires_at),
+        )?;
+
+        Ok(db.last_insert_rowid())
+    }
+
+    /// Gets every invite, newest first
+    pub fn get_invites(&self) -> Result<Vec<Invite>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT invite_id, description, max_uses, uses, expires_at, revoked, created_at
+             FROM invites ORDER BY invite_id DESC",
+        )?;
+
+        let invites = stmt.query_map([], |row| {
+            Ok(Invite {
+                invite_id: row.get(0)?,
+                description: row.get(1)?,
+                max_uses: row.get(2)?,
+                uses: row.get(3)?,
+                expires_at: row.get(4)?,
+                revoked: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?;
+        invites.collect()
+    }
+
+    /// Revokes an invite, so that it can't be used anymore. Returns whether there was such
+    /// an invite that wasn't already revoked
+    pub fn revoke_invite(&self, invite_id: i64) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        let revoked = db.execute(
+            "UPDATE invites SET revoked = 1 WHERE invite_id = ? AND revoked = 0",
+            [invite_id],
+        )?;
+
+        Ok(revoked > 0)
+    }
+
+    /// Uses up one of an invite's uses, as long as it hasn't been revoked, used up, or
+    /// expired. Returns the invite's ID and description if it could be used
+    pub fn redeem_invite(&self, code_hash: &str) -> Result<Option<(i64, Option<String>)>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            "UPDATE invites SET uses = uses + 1
+             WHERE code_hash = ? AND revoked = 0 AND uses < max_uses
+               AND (expires_at IS NULL OR expires_at > datetime('now'))
+             RETURNING invite_id, description",
+            [code_hash],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+    }
+
+    /// Records that an API key was registered with an invite
+    pub fn record_invite_redemption(&self, invite_id: i64, key_prefix: &str) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO invite_redemptions (invite_id, key_prefix, redeemed_at)
+             VALUES (?1, ?2, datetime('now'))",
+            (invite_id, key_prefix),
+        )?;
+
+        Ok(())
+    }
+
+    /// Gives back a use of an invite that was redeemed by a registration that then failed,
+    /// forgetting the key that it was recorded for, if any
+    pub fn unredeem_invite(&self, invite_id: i64, key_prefix: &str) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        tx.execute(
+            "UPDATE invites SET uses = uses - 1 WHERE invite_id = ? AND uses > 0",
+            [invite_id],
+        )?;
+        tx.execute(
+            "DELETE FROM invite_redemptions WHERE invite_id = ?1 AND key_prefix = ?2",
+            (invite_id, key_prefix),
+        )?;
+        tx.commit()
+    }
+
+    /// Gives an API key a cookie server. Returns `false`, without changing anything, if the
+    /// server was already given to another key
+    pub fn assign_cookie_server(&self, key_prefix: &str, address: &str, port: i64) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        let assigned = db.execute(
+            "INSERT INTO cookie_server_slots (key_prefix, address, port, assigned_at)
+             VALUES (?1, ?2, ?3, datetime('now'))
+             ON CONFLICT DO NOTHING",
+            (key_prefix, address, port),
+        )?;
+
+        Ok(assigned > 0)
+    }
+
+    /// Gets the cookie server that each API key was given, as `(key_prefix, address, port)`
+    pub fn get_cookie_server_slots(&self) -> Result<Vec<(String, String, i64)>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT key_prefix, address, port FROM cookie_server_slots ORDER BY assigned_at",
+        )?;
+
+        let slots = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        slots.collect()
+    }
+
+    /// Takes an API key's cookie server back, so that it can be given to someone else
+    pub fn release_cookie_server(&self, key_prefix: &str) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "DELETE FROM cookie_server_slots WHERE key_prefix = ?",
+            [key_prefix],
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+//! Storage for leases, which let instances that share the database agree on which of them
+//! runs the background work. A lease is held until it expires, and its holder renews it
+//! before then; any instance may take it over once it has expired.
+
+use rusqlite::{OptionalExtension, Result};
+
+use super::ScheduleDbManager;
+
+impl ScheduleDbManager {
+    /// Takes or renews a lease, if it's free, expired, or already held by `holder`. This is
+    /// a single statement, so two instances can't both take the same lease
+    ///
+    /// Returns whether `holder` now holds the lease
+    pub fn try_acquire_lease(
+        &self,
+        name: &str,
+        holder: &str,
+        now: i64,
+        ttl_ms: i64,
+    ) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        let changed = db.execute(
+            "INSERT INTO leases (name, holder, expires_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at
+             WHERE leases.holder = excluded.holder OR leases.expires_at <= ?4",
+            (name, holder, now + ttl_ms, now),
+        )?;
+
+        Ok(changed == 1)
+    }
+
+    /// Gives up a lease, if `holder` holds it, so that another instance can take it over
+    /// without waiting for it to expire
+    pub fn release_lease(&self, name: &str, holder: &str) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "DELETE FROM leases WHERE name = ?1 AND holder = ?2",
+            (name, holder),
+        )?;
+
+        Ok(())
+    }
+
+    /// Gets the holder of a lease and when it expires, if anyone holds it
+    pub fn get_lease(&self, name: &str) -> Result<Option<(String, i64)>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            "SELECT holder, expires_at FROM leases WHERE name = ?",
+            [name],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+    }
+}
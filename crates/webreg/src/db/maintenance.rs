@@ -0,0 +1,127 @@
+//! Keeping the database responsive and compact. The database is opened in write-ahead
+//! logging mode, so that a long scrape's writes don't block readers (including other
+//! instances sharing the file), and waits for a lock instead of failing right away when
+//! another connection holds it. The log is folded back into the database periodically by
+//! the `db_checkpoint` job, and the admin API can vacuum and analyze it on demand.
+
+use std::time::Duration;
+
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+
+use super::ScheduleDbManager;
+
+/// How long a statement waits for another connection's lock before failing
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The result of a checkpoint of the write-ahead log
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub struct CheckpointStats {
+    /// Whether another connection kept the checkpoint from finishing
+    pub busy: bool,
+    /// The number of frames in the log before the checkpoint
+    pub log_frames: i64,
+    /// The number of frames that were written back to the database
+    pub checkpointed_frames: i64,
+}
+
+/// The size of the database before and after an operation, in bytes
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub struct SizeChange {
+    pub bytes_before: i64,
+    pub bytes_after: i64,
+}
+
+/// Opens connections in write-ahead logging mode, with a busy timeout. In-memory databases
+/// can't use the log, and keep their own journal mode
+pub(super) fn configure_connection(conn: &Connection) -> Result<()> {
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.query_row("PRAGMA journal_mode = WAL", [], |row| {
+        row.get::<_, String>(0)
+    })?;
+    // Safe in WAL mode: a crash can only lose the most recent commits, never corrupt
+    conn.execute_batch("PRAGMA synchronous = NORMAL")
+}
+
+/// Gets the size of a database, in bytes, not counting its write-ahead log
+fn database_size(conn: &Connection) -> Result<i64> {
+    conn.query_row(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        [],
+        |row| row.get(0),
+    )
+}
+
+impl ScheduleDbManager {
+    /// Gets the database's journal mode (e.g., `wal`)
+    pub fn journal_mode(&self) -> Result<String> {
+        let db = self.db.lock().unwrap();
+        db.query_row("PRAGMA journal_mode", [], |row| row.get(0))
+    }
+
+    /// Writes the write-ahead log back to the database and truncates it, so that the log
+    /// doesn't keep growing while readers hold old snapshots
+    pub fn checkpoint(&self) -> Result<CheckpointStats> {
+        let db = self.db.lock().unwrap();
+        db.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| {
+            Ok(CheckpointStats {
+                busy: row.get::<_, i64>(0)? != 0,
+                log_frames: row.get(1)?,
+                checkpointed_frames: row.get(2)?,
+            })
+        })
+    }
+
+    /// Rebuilds the database file without its free pages (e.g., from sections and seat
+    /// samples that were deleted). This holds the database for as long as it takes
+    pub fn vacuum(&self) -> Result<SizeChange> {
+        let db = self.db.lock().unwrap();
+        let bytes_before = database_size(&db)?;
+        db.execute_batch("VACUUM")?;
+        Ok(SizeChange {
+            bytes_before,
+            bytes_after: database_size(&db)?,
+        })
+    }
+
+    /// Updates the statistics that the query planner picks indexes with
+    pub fn analyze(&self) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute_batch("ANALYZE")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wal_maintenance() {
+        let dir = std::env::temp_dir().join(format!("webreg-wal-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("schedules.db");
+        let db = ScheduleDbManager::new(path.to_str().unwrap());
+        assert_eq!("wal", db.journal_mode().unwrap());
+
+        // Readers on other connections aren't blocked by an open write transaction
+        let writer = Connection::open(&path).unwrap();
+        writer
+            .execute_batch(
+                "BEGIN; INSERT INTO leases (name, holder, expires_at) VALUES ('a', 'b', 1);",
+            )
+            .unwrap();
+        assert_eq!(None, db.get_lease("a").unwrap());
+        writer.execute_batch("COMMIT").unwrap();
+        assert!(db.get_lease("a").unwrap().is_some());
+
+        let stats = db.checkpoint().unwrap();
+        assert!(!stats.busy);
+        assert_eq!(stats.log_frames, stats.checkpointed_frames);
+        db.analyze().unwrap();
+        let size = db.vacuum().unwrap();
+        assert!(size.bytes_after <= size.bytes_before);
+
+        drop((db, writer));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
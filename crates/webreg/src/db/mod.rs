@@ -1,13 +1,88 @@
 /// Database module for managing course schedule/meeting time data
-
+mod actions;
+#[cfg(feature = "auth")]
+mod api_keys;
+mod audit_trail;
+mod cart;
+mod catalog;
+mod course_plans;
+mod cross_listings;
+mod custom_requirements;
+mod data_versions;
+mod dependency_status;
+mod enroll_jobs;
+mod events;
+mod export;
+mod finals;
+mod grades;
+mod instructors;
+#[cfg(feature = "auth")]
+mod invites;
+mod leases;
+mod maintenance;
+mod offerings;
+mod pass_times;
+mod plans;
+mod rooms;
+mod scrape_jobs;
+mod search;
+mod seat_history;
+mod section_changes;
+mod section_notes;
+mod shares;
+mod status;
+mod sync;
 mod types;
+mod user_bundles;
+mod user_enrollments;
+#[cfg(feature = "auth")]
+mod vault;
 
+pub use actions::EnrollmentActionEntry;
+pub use audit_trail::TrailEntry;
+pub use cart::{CartItem, CartSection, NewCartItem};
+pub use catalog::CatalogCourse;
+pub use course_plans::{CoursePlan, PlannedQuarter};
+pub use custom_requirements::CustomRequirementSet;
+pub use dependency_status::StatusInterval;
+pub use enroll_jobs::{
+    EnrollJob, NewEnrollJob, WatchAlerts, ENROLL_JOB_ENROLLED, ENROLL_JOB_EXPIRED,
+    ENROLL_JOB_FAILED, ENROLL_JOB_NEEDS_SESSION, ENROLL_JOB_PENDING, EXPIRY_ADD_DEADLINE,
+    EXPIRY_ENROLLED_IN_COURSE, EXPIRY_TIME,
+};
+pub use events::{
+    CustomEvent, CustomEventFields, SYNC_STATE_PENDING_DELETE, SYNC_STATE_PENDING_PUSH,
+    SYNC_STATE_SYNCED,
+};
+pub use export::ScheduleExportRow;
+pub use grades::GradeRecord;
+pub use maintenance::{CheckpointStats, SizeChange, BUSY_TIMEOUT};
+pub use offerings::{normalize_course_code, CourseOffering};
+pub use pass_times::{PassTime, PASS_FIRST, PASS_SECOND};
+pub use plans::{PlanEntry, SavedPlan};
+pub use scrape_jobs::{SCRAPE_JOB_COMPLETED, SCRAPE_JOB_FAILED, SCRAPE_JOB_RUNNING};
+pub use search::TermSection;
+#[cfg(test)]
+pub(crate) use search::tests::term_section;
+pub use seat_history::SeatSample;
+pub use section_changes::SectionChangeEntry;
+pub use status::QueueDepths;
+pub use sync::SyncChange;
 pub use types::{DbCourse, DbMeeting, DbSection};
+pub use user_bundles::{
+    BundledCoursePlan, BundledOverride, BundledRequirementSet, BundledSavedPlan, ImportedBundle,
+    UserBundle,
+};
+pub use user_enrollments::{UserEnrollment, USER_ENROLLMENT_ENROLLED, USER_ENROLLMENT_WAITLISTED};
 
-use rusqlite::{Connection, Result};
+use rusqlite::backup::Progress;
+use rusqlite::{Connection, DatabaseName, OptionalExtension, Result};
+use std::path::Path;
 use std::sync::Mutex;
 use webweg::types::{CourseSection, MeetingDay};
 
+use crate::schedule::{DateRange, ExamKind, MeetingDates};
+
 const SCHEMA_SQL: &str = include_str!("../../../../sql/init_schedules.sql");
 
 pub struct ScheduleDbManager {
@@ -18,16 +93,76 @@ impl ScheduleDbManager {
     /// Creates a new ScheduleDbManager and initializes the database schema
     pub fn new(db_path: &str) -> Self {
         let conn = Connection::open(db_path).expect("Failed to open database");
+        maintenance::configure_connection(&conn).expect("Failed to configure database");
 
         // Initialize schema
         conn.execute_batch(SCHEMA_SQL)
             .expect("Failed to initialize database schema");
 
+        // Columns added after the initial schema, for databases created before them
+        for (table, column, decl) in [
+            ("sections", "start_date", "DATE"),
+            ("sections", "end_date", "DATE"),
+            ("meetings", "start_date", "DATE"),
+            ("meetings", "end_date", "DATE"),
+            ("sections", "total_seats", "INTEGER"),
+            ("sections", "enrolled_ct", "INTEGER"),
+            ("meetings", "room_id", "INTEGER"),
+            ("scrape_jobs", "dataset_hash", "TEXT"),
+            ("meetings", "exam_kind", "TEXT"),
+            ("section_notes", "enrollment_window", "TEXT"),
+            ("sections", "last_seen_at", "DATETIME"),
+            ("sections", "cancelled_at", "DATETIME"),
+            ("enroll_jobs", "expiry_reason", "VARCHAR(20)"),
+            ("enroll_jobs", "notify_channels", "TEXT"),
+            (
+                "enroll_jobs",
+                "alert_cooldown_mins",
+                "INTEGER NOT NULL DEFAULT 0",
+            ),
+            (
+                "enroll_jobs",
+                "alert_dedup_mins",
+                "INTEGER NOT NULL DEFAULT 0",
+            ),
+            (
+                "enroll_jobs",
+                "alert_min_seats",
+                "INTEGER NOT NULL DEFAULT 1",
+            ),
+            ("custom_events", "session_token", "TEXT"),
+        ] {
+            ensure_column(&conn, table, column, decl).expect("Failed to migrate database schema");
+        }
+
+        conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_meetings_room ON meetings(room_id);
+             CREATE INDEX IF NOT EXISTS idx_custom_events_session
+                ON custom_events(session_token, term);",
+        )
+        .expect("Failed to migrate database schema");
+        instructors::backfill_instructors(&conn).expect("Failed to migrate instructors");
+        rooms::backfill_rooms(&conn).expect("Failed to migrate rooms");
+        finals::backfill_exam_kinds(&conn).expect("Failed to classify exams");
+        offerings::backfill_course_offerings(&conn).expect("Failed to record course offerings");
+
         Self {
             db: Mutex::new(conn),
         }
     }
 
+    /// Writes a consistent copy of the database to a file, so that it can be replicated.
+    pub fn snapshot_to(&self, path: &Path) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.backup(DatabaseName::Main, path, None)
+    }
+
+    /// Replaces the contents of the database with a copy written by [`Self::snapshot_to`].
+    pub fn restore_from(&self, path: &Path) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        db.restore(DatabaseName::Main, path, None::<fn(Progress)>)
+    }
+
     /// Checks if a term already has data in the database
     pub fn term_has_data(&self, term: &str) -> bool {
         let db = self.db.lock().unwrap();
@@ -38,11 +173,15 @@ impl ScheduleDbManager {
         count > 0
     }
 
-    /// Inserts course data with all its sections and meetings
+    /// Inserts course data with all its sections and meetings. `date_range` is the term's
+    /// dates of instruction, if known, which are recorded on each section, and
+    /// `meeting_dates` holds the dates of meetings that only run for part of the term
     pub fn insert_course_with_sections(
         &self,
         term: &str,
         sections: Vec<CourseSection>,
+        date_range: Option<DateRange>,
+        meeting_dates: &MeetingDates,
     ) -> Result<()> {
         if sections.is_empty() {
             return Ok(());
@@ -72,13 +211,26 @@ impl ScheduleDbManager {
             |row| row.get(0),
         )?;
 
+        let start_date = date_range.map(|r| r.start.format("%Y-%m-%d").to_string());
+        let end_date = date_range.map(|r| r.end.format("%Y-%m-%d").to_string());
+
         // Insert sections and meetings
         for section in sections {
             // Insert section
             db.execute(
-                "INSERT OR IGNORE INTO sections (course_id, section_id, section_code, created_at)
-                 VALUES (?1, ?2, ?3, datetime('now'))",
-                (course_id, &section.section_id, &section.section_code),
+                "INSERT OR IGNORE INTO sections
+                    (course_id, section_id, section_code, start_date, end_date,
+                     total_seats, enrolled_ct, last_seen_at, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, datetime('now'), datetime('now'))",
+                (
+                    course_id,
+                    &section.section_id,
+                    &section.section_code,
+                    &start_date,
+                    &end_date,
+                    section.total_seats,
+                    section.enrolled_ct,
+                ),
             )?;
 
             let section_id_pk: i64 = db.query_row(
@@ -106,12 +258,16 @@ impl ScheduleDbManager {
                 let end_hr = Some(meeting.end_hr as i32);
                 let end_min = Some(meeting.end_min as i32);
 
+                let dates = meeting_dates.get(&section.section_id, &meeting.meeting_type);
+                let meeting_start_date = dates.map(|r| r.start.format("%Y-%m-%d").to_string());
+                let meeting_end_date = dates.map(|r| r.end.format("%Y-%m-%d").to_string());
+
                 db.execute(
                     "INSERT INTO meetings (
                         section_id_pk, meeting_type, meeting_days_type, meeting_days,
                         start_hr, start_min, end_hr, end_min,
-                        building, room, instructors, created_at
-                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, datetime('now'))",
+                        building, room, instructors, start_date, end_date, exam_kind, created_at
+                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, datetime('now'))",
                     (
                         section_id_pk,
                         &meeting.meeting_type,
@@ -124,70 +280,119 @@ impl ScheduleDbManager {
                         &meeting.building,
                         &meeting.room,
                         instructors_json,
+                        meeting_start_date,
+                        meeting_end_date,
+                        ExamKind::from_meeting_type(&meeting.meeting_type).map(|k| k.as_str()),
                     ),
                 )?;
+
+                let meeting_id = db.last_insert_rowid();
+                instructors::link_instructors(&db, meeting_id, &meeting.instructors)?;
+                rooms::link_room(&db, meeting_id, &meeting.building, &meeting.room)?;
             }
         }
 
-        Ok(())
+        data_versions::bump_data_version(&db, term)
+    }
+
+    /// Removes the given sections of a course, along with their meetings, so that they can
+    /// be inserted again without duplicating them. The course's other sections weren't in
+    /// its latest scrape, so they're kept but marked as cancelled, if they aren't already
+    pub fn clear_course_sections(
+        &self,
+        term: &str,
+        subj_course_id: &str,
+        section_ids: &[String],
+    ) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        let section_ids = serde_json::to_string(section_ids).unwrap();
+        let sections = "SELECT s.section_id_pk FROM sections s
+                        JOIN courses c ON s.course_id = c.course_id
+                        WHERE c.term = ?1 AND c.subj_course_id = ?2
+                        AND s.section_id IN (SELECT value FROM json_each(?3))";
+        tx.execute(
+            &format!(
+                "DELETE FROM meeting_instructors WHERE meeting_id IN
+                    (SELECT meeting_id FROM meetings WHERE section_id_pk IN ({sections}))"
+            ),
+            (term, subj_course_id, &section_ids),
+        )?;
+        tx.execute(
+            &format!("DELETE FROM meetings WHERE section_id_pk IN ({sections})"),
+            (term, subj_course_id, &section_ids),
+        )?;
+        tx.execute(
+            &format!("DELETE FROM sections WHERE section_id_pk IN ({sections})"),
+            (term, subj_course_id, &section_ids),
+        )?;
+        tx.execute(
+            "UPDATE sections SET cancelled_at = datetime('now')
+             WHERE cancelled_at IS NULL AND course_id IN
+                (SELECT course_id FROM courses WHERE term = ?1 AND subj_course_id = ?2)",
+            (term, subj_course_id),
+        )?;
+        data_versions::bump_data_version(&tx, term)?;
+        tx.commit()
+    }
+
+    /// Marks the sections of a term that no scrape has found since `since` (e.g., the start
+    /// of the term's latest full scrape) as cancelled. This catches the sections of courses
+    /// that disappeared altogether, which are never scraped again to be cleared
+    ///
+    /// Returns the number of sections that were marked
+    pub fn cancel_unseen_sections(&self, term: &str, since: &str) -> Result<usize> {
+        let db = self.db.lock().unwrap();
+        let cancelled = db.execute(
+            "UPDATE sections SET cancelled_at = datetime('now')
+             WHERE cancelled_at IS NULL
+             AND COALESCE(last_seen_at, created_at) < ?2
+             AND course_id IN (SELECT course_id FROM courses WHERE term = ?1)",
+            (term, since),
+        )?;
+        if cancelled > 0 {
+            data_versions::bump_data_version(&db, term)?;
+        }
+        Ok(cancelled)
     }
 
-    /// Gets all meetings for a specific section ID
-    pub fn get_meetings_for_section(&self, section_id: &str) -> Result<Vec<DbMeeting>> {
+    /// Gets the meetings of a section by its primary key, which (unlike its section ID)
+    /// belongs to a single term
+    pub fn get_meetings_for_section_pk(&self, section_id_pk: i64) -> Result<Vec<DbMeeting>> {
         let db = self.db.lock().unwrap();
         let mut stmt = db.prepare(
-            "SELECT m.meeting_id, m.section_id_pk, m.meeting_type, m.meeting_days_type,
-                    m.meeting_days, m.start_hr, m.start_min, m.end_hr, m.end_min,
-                    m.building, m.room, m.instructors
-             FROM meetings m
-             JOIN sections s ON m.section_id_pk = s.section_id_pk
-             WHERE s.section_id = ?",
+            "SELECT meeting_id, section_id_pk, meeting_type, meeting_days_type,
+                    meeting_days, start_hr, start_min, end_hr, end_min,
+                    building, room, instructors, start_date, end_date
+             FROM meetings
+             WHERE section_id_pk = ?",
         )?;
 
-        let meetings = stmt.query_map([section_id], |row| {
-            Ok(DbMeeting {
-                meeting_id: row.get(0)?,
-                section_id_pk: row.get(1)?,
-                meeting_type: row.get(2)?,
-                meeting_days_type: row.get(3)?,
-                meeting_days: row.get(4)?,
-                start_hr: row.get(5)?,
-                start_min: row.get(6)?,
-                end_hr: row.get(7)?,
-                end_min: row.get(8)?,
-                building: row.get(9)?,
-                room: row.get(10)?,
-                instructors: row.get(11)?,
-            })
-        })?;
+        let meetings = stmt.query_map([section_id_pk], meeting_from_row)?;
 
         meetings.collect()
     }
 
-    /// Gets all sections with their meetings for a specific term
+    /// Gets all sections with their meetings for a specific term. Cancelled sections are
+    /// left out unless `include_cancelled` is set
     pub fn get_all_sections_for_term(
         &self,
         term: &str,
+        include_cancelled: bool,
     ) -> Result<Vec<(DbSection, Vec<DbMeeting>)>> {
         let db = self.db.lock().unwrap();
 
         // Get all sections for the term
         let mut stmt = db.prepare(
-            "SELECT s.section_id_pk, s.course_id, s.section_id, s.section_code
+            "SELECT s.section_id_pk, s.course_id, s.section_id, s.section_code,
+                    s.start_date, s.end_date, s.cancelled_at
              FROM sections s
              JOIN courses c ON s.course_id = c.course_id
-             WHERE c.term = ?",
+             WHERE c.term = ?1 AND (?2 OR s.cancelled_at IS NULL)",
         )?;
 
         let sections: Vec<DbSection> = stmt
-            .query_map([term], |row| {
-                Ok(DbSection {
-                    section_id_pk: row.get(0)?,
-                    course_id: row.get(1)?,
-                    section_id: row.get(2)?,
-                    section_code: row.get(3)?,
-                })
-            })?
+            .query_map((term, include_cancelled), section_from_row)?
             .collect::<Result<Vec<_>>>()?;
 
         // For each section, get its meetings
@@ -196,28 +401,13 @@ impl ScheduleDbManager {
             let mut meeting_stmt = db.prepare(
                 "SELECT meeting_id, section_id_pk, meeting_type, meeting_days_type,
                         meeting_days, start_hr, start_min, end_hr, end_min,
-                        building, room, instructors
+                        building, room, instructors, start_date, end_date
                  FROM meetings
                  WHERE section_id_pk = ?",
             )?;
 
             let meetings: Vec<DbMeeting> = meeting_stmt
-                .query_map([section.section_id_pk], |row| {
-                    Ok(DbMeeting {
-                        meeting_id: row.get(0)?,
-                        section_id_pk: row.get(1)?,
-                        meeting_type: row.get(2)?,
-                        meeting_days_type: row.get(3)?,
-                        meeting_days: row.get(4)?,
-                        start_hr: row.get(5)?,
-                        start_min: row.get(6)?,
-                        end_hr: row.get(7)?,
-                        end_min: row.get(8)?,
-                        building: row.get(9)?,
-                        room: row.get(10)?,
-                        instructors: row.get(11)?,
-                    })
-                })?
+                .query_map([section.section_id_pk], meeting_from_row)?
                 .collect::<Result<Vec<_>>>()?;
 
             result.push((section, meetings));
@@ -225,4 +415,283 @@ impl ScheduleDbManager {
 
         Ok(result)
     }
+
+    /// Gets every meeting in a term along with its section's seat capacity and enrollment,
+    /// for computing how busy each building is
+    pub fn get_meeting_loads(&self, term: &str) -> Result<Vec<(DbMeeting, i64, i64)>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT m.meeting_id, m.section_id_pk, m.meeting_type, m.meeting_days_type,
+                    m.meeting_days, m.start_hr, m.start_min, m.end_hr, m.end_min,
+                    m.building, m.room, m.instructors, m.start_date, m.end_date,
+                    COALESCE(s.total_seats, 0), COALESCE(s.enrolled_ct, 0)
+             FROM meetings m
+             JOIN sections s ON m.section_id_pk = s.section_id_pk
+             JOIN courses c ON s.course_id = c.course_id
+             WHERE c.term = ? AND s.cancelled_at IS NULL",
+        )?;
+
+        let loads = stmt.query_map([term], |row| {
+            Ok((meeting_from_row(row)?, row.get(14)?, row.get(15)?))
+        })?;
+
+        loads.collect()
+    }
+
+    /// Gets a single section, along with its course's subject/course ID (e.g., `CSE 100`) and
+    /// its meetings, for a specific term. Cancelled sections aren't returned
+    pub fn get_section(
+        &self,
+        term: &str,
+        section_id: &str,
+    ) -> Result<Option<(String, DbSection, Vec<DbMeeting>)>> {
+        let section = {
+            let db = self.db.lock().unwrap();
+            db.query_row(
+                "SELECT s.section_id_pk, s.course_id, s.section_id, s.section_code,
+                        s.start_date, s.end_date, s.cancelled_at, c.subj_course_id
+                 FROM sections s
+                 JOIN courses c ON s.course_id = c.course_id
+                 WHERE c.term = ? AND s.section_id = ? AND s.cancelled_at IS NULL",
+                (term, section_id),
+                |row| Ok((row.get::<_, String>(7)?, section_from_row(row)?)),
+            )
+            .optional()?
+        };
+
+        let Some((subj_course_id, section)) = section else {
+            return Ok(None);
+        };
+
+        let meetings = self.get_meetings_for_section_pk(section.section_id_pk)?;
+        Ok(Some((subj_course_id, section, meetings)))
+    }
+}
+
+/// Maps a row of `section_id_pk, course_id, section_id, section_code, start_date, end_date,
+/// cancelled_at` to a section
+fn section_from_row(row: &rusqlite::Row) -> Result<DbSection> {
+    Ok(DbSection {
+        section_id_pk: row.get(0)?,
+        course_id: row.get(1)?,
+        section_id: row.get(2)?,
+        section_code: row.get(3)?,
+        start_date: row.get(4)?,
+        end_date: row.get(5)?,
+        cancelled_at: row.get(6)?,
+    })
+}
+
+/// Maps a row of `meeting_id, section_id_pk, meeting_type, meeting_days_type, meeting_days,
+/// start_hr, start_min, end_hr, end_min, building, room, instructors, start_date, end_date`
+/// to a meeting
+fn meeting_from_row(row: &rusqlite::Row) -> Result<DbMeeting> {
+    Ok(DbMeeting {
+        meeting_id: row.get(0)?,
+        section_id_pk: row.get(1)?,
+        meeting_type: row.get(2)?,
+        meeting_days_type: row.get(3)?,
+        meeting_days: row.get(4)?,
+        start_hr: row.get(5)?,
+        start_min: row.get(6)?,
+        end_hr: row.get(7)?,
+        end_min: row.get(8)?,
+        building: row.get(9)?,
+        room: row.get(10)?,
+        instructors: row.get(11)?,
+        start_date: row.get(12)?,
+        end_date: row.get(13)?,
+    })
+}
+
+/// Adds a column to a table if it doesn't already exist
+fn ensure_column(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>>>()?
+        .iter()
+        .any(|c| c == column);
+
+    if !exists {
+        conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {decl}"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use webweg::types::Meeting;
+
+    /// Creates a section of a course with a single lecture, taught by the given instructor.
+    pub(super) fn section(
+        subj_course_id: &str,
+        section_id: &str,
+        building: &str,
+        instructor: &str,
+    ) -> CourseSection {
+        CourseSection {
+            subj_course_id: subj_course_id.to_owned(),
+            section_id: section_id.to_owned(),
+            section_code: "A01".to_owned(),
+            all_instructors: vec![instructor.to_owned()],
+            available_seats: 10,
+            enrolled_ct: 90,
+            total_seats: 100,
+            waitlist_ct: 0,
+            meetings: vec![Meeting {
+                meeting_type: "LE".to_owned(),
+                meeting_days: MeetingDay::Repeated(vec!["M".to_owned(), "W".to_owned()]),
+                start_min: 0,
+                start_hr: 9,
+                end_min: 50,
+                end_hr: 9,
+                building: building.to_owned(),
+                room: "101".to_owned(),
+                instructors: vec![instructor.to_owned()],
+            }],
+            is_visible: true,
+        }
+    }
+
+    #[test]
+    fn test_section_in_two_terms() {
+        let db = ScheduleDbManager::new(":memory:");
+        let dates = MeetingDates::default();
+        db.insert_course_with_sections(
+            "FA24",
+            vec![section("CSE 100", "123456", "CENTR", "Doe, Jane")],
+            None,
+            &dates,
+        )
+        .unwrap();
+        // WebReg reuses section IDs between terms
+        db.insert_course_with_sections(
+            "WI25",
+            vec![section("CSE 100", "123456", "WLH", "Smith, Sam")],
+            None,
+            &dates,
+        )
+        .unwrap();
+
+        for (term, building) in [("FA24", "CENTR"), ("WI25", "WLH")] {
+            let (subj_course_id, section, meetings) =
+                db.get_section(term, "123456").unwrap().unwrap();
+            assert_eq!("CSE 100", subj_course_id);
+            assert_eq!("123456", section.section_id);
+            assert_eq!(1, meetings.len());
+            assert_eq!(Some(building), meetings[0].building.as_deref());
+        }
+        assert!(db.get_section("SP25", "123456").unwrap().is_none());
+    }
+
+    /// Gets the IDs of a term's sections, along with whether each is cancelled.
+    fn section_states(db: &ScheduleDbManager, term: &str) -> Vec<(String, bool)> {
+        let mut states: Vec<_> = db
+            .get_all_sections_for_term(term, true)
+            .unwrap()
+            .into_iter()
+            .map(|(s, _)| (s.section_id, s.cancelled_at.is_some()))
+            .collect();
+        states.sort();
+        states
+    }
+
+    #[test]
+    fn test_clear_course_sections() {
+        let db = ScheduleDbManager::new(":memory:");
+        let dates = MeetingDates::default();
+        let scrape = |term: &str, subj_course_id: &str, section_ids: &[&str]| {
+            let section_ids: Vec<String> = section_ids.iter().map(|id| id.to_string()).collect();
+            db.clear_course_sections(term, subj_course_id, &section_ids)
+                .unwrap();
+            let sections = section_ids
+                .iter()
+                .map(|id| section(subj_course_id, id, "CENTR", "Doe, Jane"))
+                .collect();
+            db.insert_course_with_sections(term, sections, None, &dates)
+                .unwrap();
+        };
+
+        scrape("FA24", "CSE 100", &["1", "2"]);
+        scrape("FA24", "CSE 101", &["3"]);
+        scrape("WI25", "CSE 100", &["2"]);
+
+        // Section 2 is gone from the next scrape of the course, so it's kept but cancelled,
+        // and section 1 is replaced rather than duplicated
+        scrape("FA24", "CSE 100", &["1"]);
+        assert_eq!(
+            vec![
+                ("1".to_owned(), false),
+                ("2".to_owned(), true),
+                ("3".to_owned(), false)
+            ],
+            section_states(&db, "FA24")
+        );
+        let (_, meetings) = db
+            .get_all_sections_for_term("FA24", false)
+            .unwrap()
+            .into_iter()
+            .find(|(s, _)| s.section_id == "1")
+            .unwrap();
+        assert_eq!(1, meetings.len());
+        // Cancelled sections are left out by default
+        assert_eq!(
+            2,
+            db.get_all_sections_for_term("FA24", false).unwrap().len()
+        );
+        assert!(db.get_section("FA24", "2").unwrap().is_none());
+        // The same section ID in another term isn't affected
+        assert_eq!(vec![("2".to_owned(), false)], section_states(&db, "WI25"));
+
+        // A cancelled section that comes back is no longer cancelled
+        scrape("FA24", "CSE 100", &["1", "2"]);
+        assert_eq!(
+            vec![
+                ("1".to_owned(), false),
+                ("2".to_owned(), false),
+                ("3".to_owned(), false)
+            ],
+            section_states(&db, "FA24")
+        );
+    }
+
+    #[test]
+    fn test_cancel_unseen_sections() {
+        let db = ScheduleDbManager::new(":memory:");
+        let dates = MeetingDates::default();
+        for term in ["FA24", "WI25"] {
+            db.insert_course_with_sections(
+                term,
+                vec![section("CSE 100", "1", "CENTR", "Doe, Jane")],
+                None,
+                &dates,
+            )
+            .unwrap();
+        }
+
+        // Every section was seen after a scrape that started in the past
+        assert_eq!(
+            0,
+            db.cancel_unseen_sections("FA24", "2000-01-01 00:00:00")
+                .unwrap()
+        );
+        assert_eq!(vec![("1".to_owned(), false)], section_states(&db, "FA24"));
+
+        // No section was seen after a scrape that started later, so each is cancelled once
+        assert_eq!(
+            1,
+            db.cancel_unseen_sections("FA24", "9999-01-01 00:00:00")
+                .unwrap()
+        );
+        assert_eq!(
+            0,
+            db.cancel_unseen_sections("FA24", "9999-01-01 00:00:00")
+                .unwrap()
+        );
+        assert_eq!(vec![("1".to_owned(), true)], section_states(&db, "FA24"));
+        assert_eq!(vec![("1".to_owned(), false)], section_states(&db, "WI25"));
+    }
 }
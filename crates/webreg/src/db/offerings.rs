@@ -0,0 +1,152 @@
+//! Queries about which terms courses have historically been offered in, and the table of
+//! offerings that's recorded from each scraped term (see [`crate::offerings`]).
+
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+
+use super::ScheduleDbManager;
+
+/// A term that a course was offered in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CourseOffering {
+    pub term: String,
+    pub section_count: i64,
+    pub total_seats: i64,
+    pub enrolled_ct: i64,
+}
+
+impl ScheduleDbManager {
+    /// Gets every term that each course has been offered in, keyed by the course's
+    /// normalized code (e.g., `CSE 100`)
+    pub fn get_offering_terms(&self) -> Result<HashMap<String, Vec<String>>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare("SELECT course_code, term FROM course_offerings")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut offerings: HashMap<String, Vec<String>> = HashMap::new();
+        for row in rows {
+            let (course_code, term) = row?;
+            offerings.entry(course_code).or_default().push(term);
+        }
+
+        Ok(offerings)
+    }
+
+    /// Gets every term that a course has been offered in, in no particular order
+    pub fn get_course_offerings(&self, course_code: &str) -> Result<Vec<CourseOffering>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT term, section_count, total_seats, enrolled_ct FROM course_offerings
+             WHERE course_code = ?",
+        )?;
+
+        let offerings = stmt.query_map([normalize_course_code(course_code)], |row| {
+            Ok(CourseOffering {
+                term: row.get(0)?,
+                section_count: row.get(1)?,
+                total_seats: row.get(2)?,
+                enrolled_ct: row.get(3)?,
+            })
+        })?;
+        offerings.collect()
+    }
+
+    /// Gets every term that any course has been recorded as offered in
+    pub fn get_offered_terms(&self) -> Result<HashSet<String>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare("SELECT DISTINCT term FROM course_offerings")?;
+        let terms = stmt.query_map([], |row| row.get(0))?;
+        terms.collect()
+    }
+
+    /// Records the courses offered in a term from its scraped sections, replacing what was
+    /// recorded for it before. Returns the number of courses recorded
+    pub fn record_course_offerings(&self, term: &str) -> Result<usize> {
+        let db = self.db.lock().unwrap();
+        record_term_offerings(&db, term)
+    }
+
+    /// Gets the normalized code of every course scraped for a term
+    pub fn get_term_course_codes(&self, term: &str) -> Result<HashSet<String>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare("SELECT DISTINCT subj_course_id FROM courses WHERE term = ?")?;
+        let codes = stmt.query_map([term], |row| row.get::<_, String>(0))?;
+        codes
+            .map(|code| code.map(|c| normalize_course_code(&c)))
+            .collect()
+    }
+}
+
+/// Records the courses offered in a term. Courses without any sections that weren't
+/// cancelled weren't really offered, so they're left out
+fn record_term_offerings(conn: &Connection, term: &str) -> Result<usize> {
+    let mut stmt = conn.prepare(
+        "SELECT c.subj_course_id, COUNT(*), COALESCE(SUM(s.total_seats), 0),
+                COALESCE(SUM(s.enrolled_ct), 0)
+         FROM courses c JOIN sections s ON s.course_id = c.course_id
+         WHERE c.term = ? AND s.cancelled_at IS NULL GROUP BY c.subj_course_id",
+    )?;
+    let rows = stmt
+        .query_map([term], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    // Codes that only differ in spacing or case are the same course
+    let mut offerings: HashMap<String, (i64, i64, i64)> = HashMap::new();
+    for (subj_course_id, sections, seats, enrolled) in rows {
+        let totals = offerings
+            .entry(normalize_course_code(&subj_course_id))
+            .or_default();
+        totals.0 += sections;
+        totals.1 += seats;
+        totals.2 += enrolled;
+    }
+
+    conn.execute("DELETE FROM course_offerings WHERE term = ?", [term])?;
+    let mut insert = conn.prepare(
+        "INSERT INTO course_offerings
+         (course_code, term, section_count, total_seats, enrolled_ct, recorded_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, datetime('now'))",
+    )?;
+    for (course_code, (sections, seats, enrolled)) in &offerings {
+        insert.execute((course_code, term, sections, seats, enrolled))?;
+    }
+
+    Ok(offerings.len())
+}
+
+/// Records the offerings of terms that were scraped before offerings were recorded
+pub(super) fn backfill_course_offerings(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT term FROM courses
+         WHERE term NOT IN (SELECT DISTINCT term FROM course_offerings)",
+    )?;
+    let terms = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>>>()?;
+
+    for term in terms {
+        record_term_offerings(conn, &term)?;
+    }
+
+    Ok(())
+}
+
+/// Normalizes a course code so that codes from different sources can be compared (e.g.,
+/// `cse  100` becomes `CSE 100`).
+pub fn normalize_course_code(code: &str) -> String {
+    code.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase()
+}
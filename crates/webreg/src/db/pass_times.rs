@@ -0,0 +1,130 @@
+//! Storage for each user's enrollment appointments (see [`crate::pass_times`])
+
+use std::collections::HashSet;
+
+use rusqlite::{Result, Row};
+use serde::Serialize;
+
+use super::ScheduleDbManager;
+
+/// The user's first pass, when they can enroll in a limited number of units
+pub const PASS_FIRST: &str = "first";
+/// The user's second pass, when they can enroll in the rest of their units
+pub const PASS_SECOND: &str = "second";
+
+/// When one of a user's passes opens
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PassTime {
+    /// The user that the pass belongs to, by the prefix of their API key. This is never sent
+    /// to clients.
+    #[serde(skip)]
+    pub key_prefix: String,
+    pub term: String,
+    /// `first` or `second`
+    pub pass: String,
+    /// When the pass opens, in local time (`YYYY-MM-DD HH:MM:SS`)
+    pub starts_at: String,
+    /// The fewest hours before the pass that a reminder has been sent at, if any
+    pub reminded_hours: Option<u64>,
+    /// When the pass was last fetched, if it has been
+    pub synced_at: Option<String>,
+}
+
+const PASS_TIME_COLUMNS: &str = "key_prefix, term, pass, starts_at, reminded_hours, synced_at";
+
+impl ScheduleDbManager {
+    /// Gets a user's passes in a term, in the order that they open
+    pub fn get_pass_times(&self, key_prefix: &str, term: &str) -> Result<Vec<PassTime>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {PASS_TIME_COLUMNS} FROM pass_times
+             WHERE key_prefix = ? AND term = ?
+             ORDER BY starts_at"
+        ))?;
+
+        let passes = stmt.query_map((key_prefix, term), pass_time_from_row)?;
+        passes.collect()
+    }
+
+    /// Gets every user's passes that open after the given time (`YYYY-MM-DD HH:MM:SS`, in
+    /// local time), in the order that they open
+    pub fn get_upcoming_pass_times(&self, after: &str) -> Result<Vec<PassTime>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {PASS_TIME_COLUMNS} FROM pass_times
+             WHERE starts_at > ?
+             ORDER BY starts_at"
+        ))?;
+
+        let passes = stmt.query_map([after], pass_time_from_row)?;
+        passes.collect()
+    }
+
+    /// Replaces a user's passes in a term with the given passes, in one transaction. A pass
+    /// whose time changed can be reminded about again
+    pub fn replace_pass_times(
+        &self,
+        key_prefix: &str,
+        term: &str,
+        passes: &[PassTime],
+    ) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        let current: HashSet<&str> = passes.iter().map(|p| p.pass.as_str()).collect();
+        let previous = tx
+            .prepare("SELECT pass FROM pass_times WHERE key_prefix = ? AND term = ?")?
+            .query_map((key_prefix, term), |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>>>()?;
+        for pass in previous.iter().filter(|p| !current.contains(p.as_str())) {
+            tx.execute(
+                "DELETE FROM pass_times WHERE key_prefix = ? AND term = ? AND pass = ?",
+                (key_prefix, term, pass),
+            )?;
+        }
+
+        for pass in passes {
+            tx.execute(
+                "INSERT INTO pass_times (key_prefix, term, pass, starts_at, synced_at)
+                VALUES (?1, ?2, ?3, ?4, datetime('now'))
+                ON CONFLICT (key_prefix, term, pass) DO UPDATE SET
+                    reminded_hours = CASE WHEN starts_at = excluded.starts_at
+                        THEN reminded_hours ELSE NULL END,
+                    starts_at = excluded.starts_at,
+                    synced_at = excluded.synced_at",
+                (key_prefix, term, &pass.pass, &pass.starts_at),
+            )?;
+        }
+
+        tx.commit()
+    }
+
+    /// Records that a reminder was sent for a pass the given number of hours before it opens
+    pub fn set_pass_time_reminded(
+        &self,
+        key_prefix: &str,
+        term: &str,
+        pass: &str,
+        hours: u64,
+    ) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "UPDATE pass_times SET reminded_hours = ?
+             WHERE key_prefix = ? AND term = ? AND pass = ?",
+            (hours, key_prefix, term, pass),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Maps a row of `PASS_TIME_COLUMNS` to a pass
+fn pass_time_from_row(row: &Row) -> Result<PassTime> {
+    Ok(PassTime {
+        key_prefix: row.get(0)?,
+        term: row.get(1)?,
+        pass: row.get(2)?,
+        starts_at: row.get(3)?,
+        reminded_hours: row.get(4)?,
+        synced_at: row.get(5)?,
+    })
+}
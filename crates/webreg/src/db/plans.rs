@@ -0,0 +1,147 @@
+//! Storage for the plans that students have saved, which can be synced into WebReg's
+//! planned schedules (see [`crate::plan_sync`])
+
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+
+use super::ScheduleDbManager;
+
+/// A section in a saved plan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanEntry {
+    pub subject_code: String,
+    pub course_code: String,
+    pub section_id: String,
+    pub section_code: String,
+    pub grading_option: Option<String>,
+    pub unit_count: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedPlan {
+    pub plan_id: i64,
+    pub term: String,
+    /// The session that the plan belongs to. This is never sent to clients.
+    #[serde(skip)]
+    pub session_token: String,
+    /// The name of the WebReg schedule that the plan is synced into
+    pub schedule_name: String,
+    pub entries: Vec<PlanEntry>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+const PLAN_COLUMNS: &str =
+    "plan_id, term, session_token, schedule_name, entries, created_at, updated_at";
+
+impl ScheduleDbManager {
+    /// Saves a session's plan, replacing the plan with the same schedule name if there is
+    /// one, returning the plan's ID
+    pub fn upsert_saved_plan(
+        &self,
+        term: &str,
+        session_token: &str,
+        schedule_name: &str,
+        entries: &[PlanEntry],
+    ) -> Result<i64> {
+        let db = self.db.lock().unwrap();
+        upsert_saved_plan(&db, term, session_token, schedule_name, entries)
+    }
+
+    /// Gets a session's saved plans for a term, by schedule name
+    pub fn get_saved_plans(&self, term: &str, session_token: &str) -> Result<Vec<SavedPlan>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {PLAN_COLUMNS} FROM saved_plans
+             WHERE term = ? AND session_token = ?
+             ORDER BY schedule_name"
+        ))?;
+
+        let plans = stmt.query_map((term, session_token), saved_plan_from_row)?;
+        plans.collect()
+    }
+
+    /// Gets a session's saved plans in every term, by term and schedule name
+    pub fn get_session_saved_plans(&self, session_token: &str) -> Result<Vec<SavedPlan>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {PLAN_COLUMNS} FROM saved_plans
+             WHERE session_token = ?
+             ORDER BY term, schedule_name"
+        ))?;
+
+        let plans = stmt.query_map([session_token], saved_plan_from_row)?;
+        plans.collect()
+    }
+
+    /// Gets one of a session's saved plans
+    pub fn get_saved_plan(
+        &self,
+        term: &str,
+        session_token: &str,
+        plan_id: i64,
+    ) -> Result<Option<SavedPlan>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            &format!(
+                "SELECT {PLAN_COLUMNS} FROM saved_plans
+                 WHERE term = ? AND session_token = ? AND plan_id = ?"
+            ),
+            (term, session_token, plan_id),
+            saved_plan_from_row,
+        )
+        .optional()
+    }
+
+    /// Removes one of a session's saved plans. Returns whether there was such a plan
+    pub fn delete_saved_plan(&self, term: &str, session_token: &str, plan_id: i64) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        let deleted = db.execute(
+            "DELETE FROM saved_plans WHERE term = ? AND session_token = ? AND plan_id = ?",
+            (term, session_token, plan_id),
+        )?;
+
+        Ok(deleted > 0)
+    }
+}
+
+/// Saves a session's plan, replacing the plan with the same schedule name if there is one,
+/// returning the plan's ID
+pub(super) fn upsert_saved_plan(
+    conn: &Connection,
+    term: &str,
+    session_token: &str,
+    schedule_name: &str,
+    entries: &[PlanEntry],
+) -> Result<i64> {
+    conn.query_row(
+        "INSERT INTO saved_plans (
+            term, session_token, schedule_name, entries, created_at, updated_at
+        ) VALUES (?1, ?2, ?3, ?4, datetime('now'), datetime('now'))
+        ON CONFLICT (term, session_token, schedule_name) DO UPDATE SET
+            entries = excluded.entries,
+            updated_at = excluded.updated_at
+        RETURNING plan_id",
+        (
+            term,
+            session_token,
+            schedule_name,
+            serde_json::to_string(entries).unwrap(),
+        ),
+        |row| row.get(0),
+    )
+}
+
+/// Maps a row of `PLAN_COLUMNS` to a saved plan
+fn saved_plan_from_row(row: &Row) -> Result<SavedPlan> {
+    let entries: String = row.get(4)?;
+    Ok(SavedPlan {
+        plan_id: row.get(0)?,
+        term: row.get(1)?,
+        session_token: row.get(2)?,
+        schedule_name: row.get(3)?,
+        entries: serde_json::from_str(&entries).unwrap_or_default(),
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
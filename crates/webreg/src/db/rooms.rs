@@ -0,0 +1,105 @@
+//! Normalized storage of the rooms that meetings take place in, so that rooms can be looked
+//! up by building and checked for availability.
+
+use rusqlite::{Connection, Result};
+
+use super::{meeting_from_row, DbMeeting, ScheduleDbManager};
+
+/// A meeting, along with the room it's in and the section it belongs to
+#[derive(Debug, Clone)]
+pub struct RoomMeeting {
+    pub building: String,
+    pub room: String,
+    pub subj_course_id: String,
+    pub section_code: String,
+    pub meeting: DbMeeting,
+}
+
+impl ScheduleDbManager {
+    /// Gets every meeting in a term that takes place in a known room, optionally only those
+    /// in the given building (case-insensitive)
+    pub fn get_room_meetings(
+        &self,
+        term: &str,
+        building: Option<&str>,
+    ) -> Result<Vec<RoomMeeting>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT m.meeting_id, m.section_id_pk, m.meeting_type, m.meeting_days_type,
+                    m.meeting_days, m.start_hr, m.start_min, m.end_hr, m.end_min,
+                    m.building, m.room, m.instructors, m.start_date, m.end_date,
+                    r.building, r.room, c.subj_course_id, s.section_code
+             FROM meetings m
+             JOIN rooms r ON m.room_id = r.room_id
+             JOIN sections s ON m.section_id_pk = s.section_id_pk
+             JOIN courses c ON s.course_id = c.course_id
+             WHERE c.term = ?1 AND s.cancelled_at IS NULL
+             AND (?2 IS NULL OR r.building = UPPER(?2))
+             ORDER BY r.building, r.room",
+        )?;
+
+        let meetings = stmt.query_map((term, building), |row| {
+            Ok(RoomMeeting {
+                meeting: meeting_from_row(row)?,
+                building: row.get(14)?,
+                room: row.get(15)?,
+                subj_course_id: row.get(16)?,
+                section_code: row.get(17)?,
+            })
+        })?;
+
+        meetings.collect()
+    }
+}
+
+/// Records the room that a meeting takes place in, adding the room if it hasn't been seen
+/// before. Meetings without a real room (e.g., `TBA` or online meetings) are left alone
+pub(super) fn link_room(
+    conn: &Connection,
+    meeting_id: i64,
+    building: &str,
+    room: &str,
+) -> Result<()> {
+    let (building, room) = (building.trim().to_uppercase(), room.trim().to_uppercase());
+    if building.is_empty() || room.is_empty() || building == "TBA" || room == "TBA" {
+        return Ok(());
+    }
+
+    conn.execute(
+        "INSERT OR IGNORE INTO rooms (building, room) VALUES (?1, ?2)",
+        (&building, &room),
+    )?;
+    conn.execute(
+        "UPDATE meetings SET room_id = (SELECT room_id FROM rooms WHERE building = ?1 AND room = ?2)
+         WHERE meeting_id = ?3",
+        (&building, &room, meeting_id),
+    )?;
+
+    Ok(())
+}
+
+/// Fills in the rooms of meetings stored before rooms were normalized, using each
+/// meeting's `building` and `room` columns
+pub(super) fn backfill_rooms(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT meeting_id, building, room FROM meetings
+         WHERE room_id IS NULL AND building IS NOT NULL AND room IS NOT NULL
+           AND TRIM(building) NOT IN ('', 'TBA') AND TRIM(room) NOT IN ('', 'TBA')",
+    )?;
+
+    let meetings = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    for (meeting_id, building, room) in meetings {
+        link_room(conn, meeting_id, &building, &room)?;
+    }
+
+    Ok(())
+}
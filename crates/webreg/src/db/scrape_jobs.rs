@@ -0,0 +1,187 @@
+//! Storage for full-term scrapes, along with the departments that each one walks. A
+//! department is only marked as done once all of its courses are stored, so a scrape that
+//! was interrupted can pick up from the first department that isn't done.
+
+use rusqlite::{OptionalExtension, Result, Row};
+use serde::Serialize;
+
+use super::ScheduleDbManager;
+
+/// The scrape is walking the term's departments (or was, before the scraper stopped)
+pub const SCRAPE_JOB_RUNNING: &str = "running";
+/// Every department was scraped
+pub const SCRAPE_JOB_COMPLETED: &str = "completed";
+/// The scrape stopped early (e.g., WebReg kept failing); it can be resumed
+pub const SCRAPE_JOB_FAILED: &str = "failed";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScrapeJob {
+    pub job_id: i64,
+    pub term: String,
+    pub status: String,
+    pub departments_total: i64,
+    pub departments_done: i64,
+    pub courses_scraped: i64,
+    pub courses_failed: i64,
+    pub last_error: Option<String>,
+    pub started_at: String,
+    pub updated_at: String,
+    pub completed_at: Option<String>,
+    /// The SHA-256 hash of the term's canonical export, recorded when the scrape completed
+    pub dataset_hash: Option<String>,
+}
+
+const JOB_COLUMNS: &str = "j.job_id, j.term, j.status,
+    (SELECT COUNT(*) FROM scrape_job_departments d WHERE d.job_id = j.job_id),
+    (SELECT COUNT(*) FROM scrape_job_departments d WHERE d.job_id = j.job_id AND d.done = 1),
+    j.courses_scraped, j.courses_failed, j.last_error, j.started_at, j.updated_at,
+    j.completed_at, j.dataset_hash";
+
+impl ScheduleDbManager {
+    /// Inserts a running scrape of a term, returning its ID
+    pub fn insert_scrape_job(&self, term: &str) -> Result<i64> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO scrape_jobs (term, status, started_at, updated_at)
+             VALUES (?1, ?2, datetime('now'), datetime('now'))",
+            (term, SCRAPE_JOB_RUNNING),
+        )?;
+
+        Ok(db.last_insert_rowid())
+    }
+
+    /// Gets a scrape by its ID
+    pub fn get_scrape_job(&self, job_id: i64) -> Result<Option<ScrapeJob>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            &format!("SELECT {JOB_COLUMNS} FROM scrape_jobs j WHERE j.job_id = ?"),
+            [job_id],
+            job_from_row,
+        )
+        .optional()
+    }
+
+    /// Gets the most recent scrape of a term
+    pub fn get_latest_scrape_job(&self, term: &str) -> Result<Option<ScrapeJob>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            &format!(
+                "SELECT {JOB_COLUMNS} FROM scrape_jobs j
+                 WHERE j.term = ? ORDER BY j.job_id DESC LIMIT 1"
+            ),
+            [term],
+            job_from_row,
+        )
+        .optional()
+    }
+
+    /// Gets every scrape that was still running when the scraper last stopped
+    pub fn get_running_scrape_jobs(&self) -> Result<Vec<ScrapeJob>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {JOB_COLUMNS} FROM scrape_jobs j WHERE j.status = ? ORDER BY j.job_id"
+        ))?;
+
+        let jobs = stmt.query_map([SCRAPE_JOB_RUNNING], job_from_row)?;
+        jobs.collect()
+    }
+
+    /// Records the departments that a scrape walks. Departments that were already recorded
+    /// keep their progress
+    pub fn add_scrape_departments(&self, job_id: i64, dept_codes: &[String]) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        for dept_code in dept_codes {
+            tx.execute(
+                "INSERT OR IGNORE INTO scrape_job_departments (job_id, dept_code) VALUES (?1, ?2)",
+                (job_id, dept_code),
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Gets the departments that a scrape hasn't finished yet, in order
+    pub fn get_pending_scrape_departments(&self, job_id: i64) -> Result<Vec<String>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT dept_code FROM scrape_job_departments
+             WHERE job_id = ? AND done = 0 ORDER BY dept_code",
+        )?;
+
+        let depts = stmt.query_map([job_id], |row| row.get(0))?;
+        depts.collect()
+    }
+
+    /// Marks a department as done, adding its counts to the scrape's
+    pub fn complete_scrape_department(
+        &self,
+        job_id: i64,
+        dept_code: &str,
+        courses_scraped: i64,
+        courses_failed: i64,
+    ) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        tx.execute(
+            "UPDATE scrape_job_departments SET done = 1, courses = ?1
+             WHERE job_id = ?2 AND dept_code = ?3",
+            (courses_scraped, job_id, dept_code),
+        )?;
+        tx.execute(
+            "UPDATE scrape_jobs
+             SET courses_scraped = courses_scraped + ?1, courses_failed = courses_failed + ?2,
+                 updated_at = datetime('now')
+             WHERE job_id = ?3",
+            (courses_scraped, courses_failed, job_id),
+        )?;
+        tx.commit()
+    }
+
+    /// Sets a scrape's status, along with the error that stopped it, if any
+    pub fn set_scrape_job_status(
+        &self,
+        job_id: i64,
+        status: &str,
+        last_error: Option<&str>,
+    ) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "UPDATE scrape_jobs
+             SET status = ?1, last_error = ?2, updated_at = datetime('now'),
+                 completed_at = CASE WHEN ?1 = ?3 THEN datetime('now') END
+             WHERE job_id = ?4",
+            (status, last_error, SCRAPE_JOB_COMPLETED, job_id),
+        )?;
+
+        Ok(())
+    }
+
+    /// Records the hash of the data that a scrape produced
+    pub fn set_scrape_job_dataset_hash(&self, job_id: i64, dataset_hash: &str) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "UPDATE scrape_jobs SET dataset_hash = ?1 WHERE job_id = ?2",
+            (dataset_hash, job_id),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Maps a row of `JOB_COLUMNS` to a scrape
+fn job_from_row(row: &Row) -> Result<ScrapeJob> {
+    Ok(ScrapeJob {
+        job_id: row.get(0)?,
+        term: row.get(1)?,
+        status: row.get(2)?,
+        departments_total: row.get(3)?,
+        departments_done: row.get(4)?,
+        courses_scraped: row.get(5)?,
+        courses_failed: row.get(6)?,
+        last_error: row.get(7)?,
+        started_at: row.get(8)?,
+        updated_at: row.get(9)?,
+        completed_at: row.get(10)?,
+        dataset_hash: row.get(11)?,
+    })
+}
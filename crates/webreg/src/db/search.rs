@@ -0,0 +1,134 @@
+//! Queries that back course search, which combines WebReg's search results with the
+//! sections and meetings stored for a term.
+
+use std::collections::HashMap;
+
+use rusqlite::Result;
+
+use super::{meeting_from_row, section_from_row, DbMeeting, DbSection, ScheduleDbManager};
+
+/// A section, along with its course, how full it was when it was scraped, and its meetings
+#[derive(Debug, Clone)]
+pub struct TermSection {
+    pub subj_course_id: String,
+    pub section: DbSection,
+    pub total_seats: i64,
+    pub enrolled_ct: i64,
+    pub meetings: Vec<DbMeeting>,
+}
+
+impl ScheduleDbManager {
+    /// Gets every section in a term with its seat counts and meetings, keyed by the
+    /// normalized subject/course ID of each section's course (e.g., `CSE 100`)
+    pub fn get_term_sections(&self, term: &str) -> Result<HashMap<String, Vec<TermSection>>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT s.section_id_pk, s.course_id, s.section_id, s.section_code,
+                    s.start_date, s.end_date, s.cancelled_at, c.subj_course_id,
+                    COALESCE(s.total_seats, 0), COALESCE(s.enrolled_ct, 0)
+             FROM sections s
+             JOIN courses c ON s.course_id = c.course_id
+             WHERE c.term = ? AND s.cancelled_at IS NULL
+             ORDER BY c.subj_course_id, s.section_code",
+        )?;
+
+        let sections = stmt
+            .query_map([term], |row| {
+                Ok(TermSection {
+                    section: section_from_row(row)?,
+                    subj_course_id: row.get(7)?,
+                    total_seats: row.get(8)?,
+                    enrolled_ct: row.get(9)?,
+                    meetings: vec![],
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut stmt = db.prepare(
+            "SELECT m.meeting_id, m.section_id_pk, m.meeting_type, m.meeting_days_type,
+                    m.meeting_days, m.start_hr, m.start_min, m.end_hr, m.end_min,
+                    m.building, m.room, m.instructors, m.start_date, m.end_date
+             FROM meetings m
+             JOIN sections s ON m.section_id_pk = s.section_id_pk
+             JOIN courses c ON s.course_id = c.course_id
+             WHERE c.term = ? AND s.cancelled_at IS NULL",
+        )?;
+
+        let mut meetings: HashMap<i64, Vec<DbMeeting>> = HashMap::new();
+        for meeting in stmt.query_map([term], meeting_from_row)? {
+            let meeting = meeting?;
+            meetings
+                .entry(meeting.section_id_pk)
+                .or_default()
+                .push(meeting);
+        }
+
+        let mut by_course: HashMap<String, Vec<TermSection>> = HashMap::new();
+        for mut section in sections {
+            section.meetings = meetings
+                .remove(&section.section.section_id_pk)
+                .unwrap_or_default();
+            by_course
+                .entry(super::normalize_course_code(&section.subj_course_id))
+                .or_default()
+                .push(section);
+        }
+
+        Ok(by_course)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Creates a section for tests, with a single lecture in CENTR 105 that's an hour and
+    /// twenty minutes long, and 30 seats
+    ///
+    /// # Parameters
+    /// - `subj_course_id`: The section's course (e.g., `CSE 100`).
+    /// - `section_id`: The section's ID, which is also used as its code.
+    /// - `days`: The lecture's days, as a JSON array (e.g., `["Tu","Th"]`).
+    /// - `start_hr`: The hour that the lecture starts at.
+    /// - `instructor`: The lecture's instructor.
+    /// - `enrolled_ct`: How many seats are taken.
+    pub fn term_section(
+        subj_course_id: &str,
+        section_id: &str,
+        days: &str,
+        start_hr: i32,
+        instructor: &str,
+        enrolled_ct: i64,
+    ) -> TermSection {
+        TermSection {
+            subj_course_id: subj_course_id.to_owned(),
+            section: DbSection {
+                section_id_pk: 0,
+                course_id: 0,
+                section_id: section_id.to_owned(),
+                section_code: section_id.to_owned(),
+                start_date: None,
+                end_date: None,
+                cancelled_at: None,
+            },
+            total_seats: 30,
+            enrolled_ct,
+            meetings: vec![DbMeeting {
+                meeting_id: 0,
+                section_id_pk: 0,
+                meeting_type: Some("LE".to_owned()),
+                meeting_days_type: "repeated".to_owned(),
+                meeting_days: Some(days.to_owned()),
+                start_hr: Some(start_hr),
+                start_min: Some(0),
+                end_hr: Some(start_hr + 1),
+                end_min: Some(20),
+                building: Some("CENTR".to_owned()),
+                room: Some("105".to_owned()),
+                instructors: Some(format!(r#"["{instructor}"]"#)),
+                start_date: None,
+                end_date: None,
+            }],
+        }
+    }
+}
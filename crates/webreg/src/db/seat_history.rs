@@ -0,0 +1,71 @@
+//! Storage for the history of each tracked section's seats, which the tracker appends to
+//! whenever a section's seats change (see [`crate::fill_rate`])
+
+use rusqlite::Result;
+
+use super::ScheduleDbManager;
+
+/// A section's seats at some point in time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeatSample {
+    pub subj_course_id: String,
+    pub section_id: String,
+    pub available_seats: i64,
+    pub waitlist_ct: i64,
+    pub total_seats: i64,
+    /// When the seats were seen, in milliseconds since the epoch
+    pub recorded_at: i64,
+}
+
+impl ScheduleDbManager {
+    /// Appends samples to a term's seat history
+    pub fn record_seat_history(&self, term: &str, samples: &[SeatSample]) -> Result<()> {
+        if samples.is_empty() {
+            return Ok(());
+        }
+
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO seat_history
+                 (term, subj_course_id, section_id, available_seats, waitlist_ct, total_seats, recorded_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for s in samples {
+                stmt.execute((
+                    term,
+                    &s.subj_course_id,
+                    &s.section_id,
+                    s.available_seats,
+                    s.waitlist_ct,
+                    s.total_seats,
+                    s.recorded_at,
+                ))?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Gets a term's seat history, ordered by course, then section, then time
+    pub fn get_seat_history(&self, term: &str) -> Result<Vec<SeatSample>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT subj_course_id, section_id, available_seats, waitlist_ct, total_seats, recorded_at
+             FROM seat_history WHERE term = ?
+             ORDER BY subj_course_id, section_id, recorded_at",
+        )?;
+
+        let samples = stmt.query_map([term], |row| {
+            Ok(SeatSample {
+                subj_course_id: row.get(0)?,
+                section_id: row.get(1)?,
+                available_seats: row.get(2)?,
+                waitlist_ct: row.get(3)?,
+                total_seats: row.get(4)?,
+                recorded_at: row.get(5)?,
+            })
+        })?;
+        samples.collect()
+    }
+}
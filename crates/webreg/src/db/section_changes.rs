@@ -0,0 +1,133 @@
+//! Storage for the changes to sections between scrapes (see [`crate::changes`])
+
+use rusqlite::Result;
+use serde::Serialize;
+
+use super::ScheduleDbManager;
+use crate::changes::{ChangeKind, SectionChange, SectionSnapshot};
+
+/// A recorded change to a section
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionChangeEntry {
+    pub subj_course_id: String,
+    #[serde(flatten)]
+    pub change: SectionChange,
+    /// When the change was seen, in milliseconds since the epoch
+    pub changed_at: i64,
+}
+
+impl ScheduleDbManager {
+    /// Gets a course's stored sections, reduced to the fields whose changes are tracked
+    pub fn get_course_snapshots(
+        &self,
+        term: &str,
+        subj_course_id: &str,
+    ) -> Result<Vec<SectionSnapshot>> {
+        let db = self.db.lock().unwrap();
+        let mut section_stmt = db.prepare(
+            "SELECT s.section_id_pk, s.course_id, s.section_id, s.section_code,
+                    s.start_date, s.end_date, s.cancelled_at
+             FROM sections s
+             JOIN courses c ON s.course_id = c.course_id
+             WHERE c.term = ?1 AND c.subj_course_id = ?2 AND s.cancelled_at IS NULL
+             ORDER BY s.section_id_pk",
+        )?;
+        let sections = section_stmt
+            .query_map((term, subj_course_id), super::section_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut meeting_stmt = db.prepare(
+            "SELECT meeting_id, section_id_pk, meeting_type, meeting_days_type,
+                    meeting_days, start_hr, start_min, end_hr, end_min,
+                    building, room, instructors, start_date, end_date
+             FROM meetings
+             WHERE section_id_pk = ?
+             ORDER BY meeting_id",
+        )?;
+        sections
+            .iter()
+            .map(|section| {
+                let meetings = meeting_stmt
+                    .query_map([section.section_id_pk], super::meeting_from_row)?
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(SectionSnapshot::from_db(section, &meetings))
+            })
+            .collect()
+    }
+
+    /// Records the changes to a course's sections that a scrape found
+    pub fn record_section_changes(
+        &self,
+        term: &str,
+        subj_course_id: &str,
+        changes: &[SectionChange],
+        changed_at: i64,
+    ) -> Result<()> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        let subj_code = subj_course_id
+            .split_whitespace()
+            .next()
+            .unwrap_or(subj_course_id);
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO section_changes
+                 (term, subj_code, subj_course_id, section_id, section_code, kind, fields, changed_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for change in changes {
+                stmt.execute((
+                    term,
+                    subj_code,
+                    subj_course_id,
+                    &change.section_id,
+                    &change.section_code,
+                    change.kind.as_str(),
+                    serde_json::to_string(&change.fields).unwrap(),
+                    changed_at,
+                ))?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Gets up to `limit` of a term's section changes seen after the given time, oldest
+    /// first, optionally only for one subject (e.g., `CSE`)
+    pub fn get_section_changes(
+        &self,
+        term: &str,
+        since: i64,
+        subject: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<SectionChangeEntry>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT subj_course_id, section_id, section_code, kind, fields, changed_at
+             FROM section_changes
+             WHERE term = ?1 AND changed_at > ?2 AND (?3 IS NULL OR subj_code = ?3 COLLATE NOCASE)
+             ORDER BY changed_at, change_id
+             LIMIT ?4",
+        )?;
+
+        let entries = stmt.query_map((term, since, subject, limit as i64), |row| {
+            let kind: String = row.get(3)?;
+            let fields: String = row.get(4)?;
+            Ok(SectionChangeEntry {
+                subj_course_id: row.get(0)?,
+                change: SectionChange {
+                    kind: ChangeKind::parse(&kind).unwrap_or(ChangeKind::Modified),
+                    section_id: row.get(1)?,
+                    section_code: row.get(2)?,
+                    fields: serde_json::from_str(&fields).unwrap_or_default(),
+                },
+                changed_at: row.get(5)?,
+            })
+        })?;
+
+        entries.collect()
+    }
+}
@@ -0,0 +1,90 @@
+//! Storage for sections' notes, enrollment restrictions, and enrollment windows (see
+//! [`crate::restrictions`])
+
+use std::collections::HashMap;
+
+use rusqlite::{OptionalExtension, Result, Row};
+
+use super::data_versions::bump_data_version;
+use super::ScheduleDbManager;
+use crate::restrictions::SectionNotes;
+
+impl ScheduleDbManager {
+    /// Replaces the notes of a course's sections with the ones from its latest scrape.
+    /// Sections of the course that no longer have notes have theirs removed
+    pub fn replace_section_notes(
+        &self,
+        term: &str,
+        section_ids: &[String],
+        notes: &HashMap<String, SectionNotes>,
+    ) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        {
+            let mut delete_stmt =
+                tx.prepare("DELETE FROM section_notes WHERE term = ?1 AND section_id = ?2")?;
+            for section_id in section_ids {
+                delete_stmt.execute((term, section_id))?;
+            }
+
+            let mut insert_stmt = tx.prepare(
+                "INSERT OR REPLACE INTO section_notes
+                 (term, section_id, notes, restrictions, enrollment_window, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, datetime('now'))",
+            )?;
+            for (section_id, section_notes) in notes {
+                insert_stmt.execute((
+                    term,
+                    section_id,
+                    serde_json::to_string(&section_notes.notes).unwrap(),
+                    serde_json::to_string(&section_notes.restrictions).unwrap(),
+                    section_notes
+                        .window
+                        .as_ref()
+                        .map(|w| serde_json::to_string(w).unwrap()),
+                ))?;
+            }
+        }
+        bump_data_version(&tx, term)?;
+        tx.commit()
+    }
+
+    /// Gets the notes of every section in a term that has any, keyed by section ID
+    pub fn get_term_section_notes(&self, term: &str) -> Result<HashMap<String, SectionNotes>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT section_id, notes, restrictions, enrollment_window FROM section_notes
+             WHERE term = ?",
+        )?;
+
+        let notes = stmt.query_map([term], |row| {
+            Ok((row.get::<_, String>(0)?, section_notes_from_row(row)?))
+        })?;
+        notes.collect()
+    }
+
+    /// Gets a section's notes, if it has any
+    pub fn get_section_notes(&self, term: &str, section_id: &str) -> Result<Option<SectionNotes>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            "SELECT section_id, notes, restrictions, enrollment_window FROM section_notes
+             WHERE term = ? AND section_id = ?",
+            (term, section_id),
+            section_notes_from_row,
+        )
+        .optional()
+    }
+}
+
+/// Maps a row of `section_id, notes, restrictions, enrollment_window` to a section's notes
+fn section_notes_from_row(row: &Row) -> Result<SectionNotes> {
+    let notes: String = row.get(1)?;
+    let restrictions: String = row.get(2)?;
+    Ok(SectionNotes {
+        notes: serde_json::from_str(&notes).unwrap_or_default(),
+        restrictions: serde_json::from_str(&restrictions).unwrap_or_default(),
+        window: row
+            .get::<_, Option<String>>(3)?
+            .and_then(|w| serde_json::from_str(&w).ok()),
+    })
+}
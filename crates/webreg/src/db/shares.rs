@@ -0,0 +1,59 @@
+//! Storage for schedule snapshots that students have shared by link.
+
+use rusqlite::{OptionalExtension, Result};
+
+use super::ScheduleDbManager;
+
+#[derive(Debug, Clone)]
+pub struct SharedSchedule {
+    pub term: String,
+    pub schedule_name: String,
+    pub snapshot: String, // JSON string
+    pub created_at: String,
+    pub expires_at: String,
+}
+
+impl ScheduleDbManager {
+    /// Stores a schedule snapshot, returning its ID. Snapshots that have already expired
+    /// are removed at the same time
+    pub fn insert_shared_schedule(
+        &self,
+        term: &str,
+        schedule_name: &str,
+        snapshot: &str,
+        expires_at: &str,
+    ) -> Result<i64> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "DELETE FROM shared_schedules WHERE expires_at <= datetime('now')",
+            [],
+        )?;
+        db.execute(
+            "INSERT INTO shared_schedules (term, schedule_name, snapshot, created_at, expires_at)
+             VALUES (?1, ?2, ?3, datetime('now'), ?4)",
+            (term, schedule_name, snapshot, expires_at),
+        )?;
+
+        Ok(db.last_insert_rowid())
+    }
+
+    /// Gets a schedule snapshot
+    pub fn get_shared_schedule(&self, share_id: i64) -> Result<Option<SharedSchedule>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            "SELECT term, schedule_name, snapshot, created_at, expires_at
+             FROM shared_schedules WHERE share_id = ?",
+            [share_id],
+            |row| {
+                Ok(SharedSchedule {
+                    term: row.get(0)?,
+                    schedule_name: row.get(1)?,
+                    snapshot: row.get(2)?,
+                    created_at: row.get(3)?,
+                    expires_at: row.get(4)?,
+                })
+            },
+        )
+        .optional()
+    }
+}
@@ -0,0 +1,169 @@
+//! Counts for the admin status endpoint
+
+use std::collections::BTreeMap;
+
+use rusqlite::Result;
+use serde::Serialize;
+
+use super::{ScheduleDbManager, ENROLL_JOB_PENDING, SCRAPE_JOB_RUNNING, SYNC_STATE_SYNCED};
+
+/// The amount of background work waiting to be done
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueDepths {
+    /// Enrollment jobs that are waiting for their start time or a seat
+    pub pending_enroll_jobs: i64,
+    /// Full-term scrapes that haven't finished
+    pub running_scrape_jobs: i64,
+    /// Departments that running scrapes haven't gotten to yet
+    pub pending_scrape_departments: i64,
+    /// Custom events that haven't been synced to WebReg yet
+    pub unsynced_custom_events: i64,
+}
+
+impl ScheduleDbManager {
+    /// Gets the number of rows in each table, by table name
+    pub fn get_table_row_counts(&self) -> Result<BTreeMap<String, i64>> {
+        let db = self.db.lock().unwrap();
+        let tables = db
+            .prepare(
+                "SELECT name FROM sqlite_master
+                 WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+            )?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut counts = BTreeMap::new();
+        for table in tables {
+            let count = db.query_row(&format!("SELECT COUNT(*) FROM \"{table}\""), [], |row| {
+                row.get(0)
+            })?;
+            counts.insert(table, count);
+        }
+
+        Ok(counts)
+    }
+
+    /// Gets the amount of background work waiting to be done
+    pub fn get_queue_depths(&self) -> Result<QueueDepths> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            "SELECT
+                (SELECT COUNT(*) FROM enroll_jobs WHERE status = ?1),
+                (SELECT COUNT(*) FROM scrape_jobs WHERE status = ?2),
+                (SELECT COUNT(*) FROM scrape_job_departments d
+                 JOIN scrape_jobs j ON d.job_id = j.job_id
+                 WHERE j.status = ?2 AND d.done = 0),
+                (SELECT COUNT(*) FROM custom_events WHERE sync_state != ?3)",
+            (ENROLL_JOB_PENDING, SCRAPE_JOB_RUNNING, SYNC_STATE_SYNCED),
+            |row| {
+                Ok(QueueDepths {
+                    pending_enroll_jobs: row.get(0)?,
+                    running_scrape_jobs: row.get(1)?,
+                    pending_scrape_departments: row.get(2)?,
+                    unsynced_custom_events: row.get(3)?,
+                })
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{CustomEventFields, NewEnrollJob, WatchAlerts, SCRAPE_JOB_COMPLETED};
+
+    fn enroll_job(db: &ScheduleDbManager, section_id: &str) -> i64 {
+        db.insert_enroll_job(&NewEnrollJob {
+            term: "FA24",
+            session_token: "a",
+            section_id,
+            subject_code: "CSE",
+            course_code: "100",
+            grading_option: None,
+            unit_count: None,
+            run_at: None,
+            expires_at: None,
+            notify_channels: &[],
+            alerts: WatchAlerts::default(),
+        })
+        .unwrap()
+    }
+
+    fn custom_event(db: &ScheduleDbManager, webreg_timestamp: Option<&str>) {
+        let fields = CustomEventFields {
+            name: "Work".to_owned(),
+            location: None,
+            days: vec!["M".to_owned()],
+            start_hr: 9,
+            start_min: 0,
+            end_hr: 11,
+            end_min: 0,
+        };
+        db.insert_custom_event("FA24", "a", &fields, webreg_timestamp)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_queue_depths_empty() {
+        let db = ScheduleDbManager::new(":memory:");
+        let queues = db.get_queue_depths().unwrap();
+        assert_eq!(0, queues.pending_enroll_jobs);
+        assert_eq!(0, queues.running_scrape_jobs);
+        assert_eq!(0, queues.pending_scrape_departments);
+        assert_eq!(0, queues.unsynced_custom_events);
+    }
+
+    #[test]
+    fn test_pending_enroll_jobs() {
+        let db = ScheduleDbManager::new(":memory:");
+        enroll_job(&db, "1");
+        let cancelled = enroll_job(&db, "2");
+        assert!(db.cancel_enroll_job("FA24", "a", cancelled).unwrap());
+
+        assert_eq!(1, db.get_queue_depths().unwrap().pending_enroll_jobs);
+    }
+
+    #[test]
+    fn test_pending_scrape_departments() {
+        let db = ScheduleDbManager::new(":memory:");
+        let running = db.insert_scrape_job("FA24").unwrap();
+        db.add_scrape_departments(running, &["CSE".to_owned(), "MATH".to_owned()])
+            .unwrap();
+        db.complete_scrape_department(running, "CSE", 10, 0)
+            .unwrap();
+
+        // A finished scrape's leftover departments aren't waiting on anything
+        let completed = db.insert_scrape_job("WI25").unwrap();
+        db.add_scrape_departments(completed, &["CSE".to_owned()])
+            .unwrap();
+        db.set_scrape_job_status(completed, SCRAPE_JOB_COMPLETED, None)
+            .unwrap();
+
+        let queues = db.get_queue_depths().unwrap();
+        assert_eq!(1, queues.running_scrape_jobs);
+        assert_eq!(1, queues.pending_scrape_departments);
+    }
+
+    #[test]
+    fn test_unsynced_custom_events() {
+        let db = ScheduleDbManager::new(":memory:");
+        custom_event(&db, Some("2024-01-01 00:00:00"));
+        custom_event(&db, None);
+
+        assert_eq!(1, db.get_queue_depths().unwrap().unsynced_custom_events);
+    }
+
+    #[test]
+    fn test_table_row_counts() {
+        let db = ScheduleDbManager::new(":memory:");
+        enroll_job(&db, "1");
+        enroll_job(&db, "2");
+        custom_event(&db, None);
+
+        let counts = db.get_table_row_counts().unwrap();
+        assert_eq!(Some(&2), counts.get("enroll_jobs"));
+        assert_eq!(Some(&1), counts.get("custom_events"));
+        assert_eq!(Some(&0), counts.get("scrape_jobs"));
+        assert!(counts.keys().all(|table| !table.starts_with("sqlite_")));
+    }
+}
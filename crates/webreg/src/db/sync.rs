@@ -0,0 +1,63 @@
+//! Storage for the change sequence that clients sync against (see [`crate::sync`])
+
+use rusqlite::Result;
+
+use super::ScheduleDbManager;
+
+/// A change to an entity that clients sync
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncChange {
+    pub seq: i64,
+    pub kind: String,
+    pub entity_key: String,
+}
+
+impl ScheduleDbManager {
+    /// Records that entities changed, moving each to the end of the change sequence
+    pub fn record_changes(&self, kind: &str, entity_keys: &[String]) -> Result<()> {
+        if entity_keys.is_empty() {
+            return Ok(());
+        }
+
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO sync_changes (kind, entity_key, changed_at)
+                 VALUES (?1, ?2, datetime('now'))",
+            )?;
+            for key in entity_keys {
+                stmt.execute((kind, key))?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Gets up to `limit` of the changes after the given sequence number, oldest first
+    pub fn get_changes_since(&self, seq: i64, limit: usize) -> Result<Vec<SyncChange>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT seq, kind, entity_key FROM sync_changes
+             WHERE seq > ? ORDER BY seq LIMIT ?",
+        )?;
+
+        let changes = stmt.query_map((seq, limit as i64), |row| {
+            Ok(SyncChange {
+                seq: row.get(0)?,
+                kind: row.get(1)?,
+                entity_key: row.get(2)?,
+            })
+        })?;
+        changes.collect()
+    }
+
+    /// Gets the sequence number of the latest change, or 0 if nothing has changed
+    pub fn get_latest_change_seq(&self) -> Result<i64> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            "SELECT COALESCE(MAX(seq), 0) FROM sync_changes",
+            [],
+            |row| row.get(0),
+        )
+    }
+}
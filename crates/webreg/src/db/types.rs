@@ -15,6 +15,10 @@ pub struct DbSection {
     pub course_id: i64,
     pub section_id: String,
     pub section_code: String,
+    pub start_date: Option<String>, // YYYY-MM-DD
+    pub end_date: Option<String>,   // YYYY-MM-DD
+    /// When the section was found to be cancelled, if it was
+    pub cancelled_at: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -31,4 +35,6 @@ pub struct DbMeeting {
     pub building: Option<String>,
     pub room: Option<String>,
     pub instructors: Option<String>,  // JSON string
+    pub start_date: Option<String>, // YYYY-MM-DD
+    pub end_date: Option<String>,   // YYYY-MM-DD
 }
@@ -0,0 +1,287 @@
+//! Importing a bundle of a user's data (see `POST /users/:id/import`), which adds
+//! everything in the bundle or, if anything fails, none of it, and keeping track of which
+//! user registered each session, so that their data can be exported by their API key
+
+use rusqlite::Result;
+
+use super::audit_trail::record_trail_version;
+use super::cart::upsert_cart_item;
+use super::course_plans::insert_course_plan;
+use super::custom_requirements::upsert_custom_requirement_set;
+use super::enroll_jobs::insert_enroll_job;
+use super::plans::upsert_saved_plan;
+use super::{NewCartItem, NewEnrollJob, PlanEntry, PlannedQuarter, ScheduleDbManager};
+use crate::audit_trail::TrailKind;
+use crate::degree_audit::config::SubrequirementConfig;
+
+/// The reason recorded for the requirement overrides that a bundle adds
+const IMPORTED_OVERRIDE_REASON: &str = "Imported from a user bundle";
+
+/// A saved plan in a bundle
+#[derive(Debug, Clone)]
+pub struct BundledSavedPlan<'a> {
+    pub term: &'a str,
+    pub schedule_name: &'a str,
+    pub entries: Vec<PlanEntry>,
+}
+
+/// A course plan in a bundle
+#[derive(Debug, Clone)]
+pub struct BundledCoursePlan<'a> {
+    pub name: &'a str,
+    pub quarters: Vec<PlannedQuarter>,
+}
+
+/// A requirement set in a bundle
+#[derive(Debug, Clone)]
+pub struct BundledRequirementSet<'a> {
+    pub name: &'a str,
+    pub subrequirements: &'a [SubrequirementConfig],
+}
+
+/// A requirement override in a bundle, as it's stored in the audit trail (see
+/// [`crate::audit_trail`])
+#[derive(Debug, Clone)]
+pub struct BundledOverride {
+    /// The key that the override's versions are stored under, for the importing user
+    pub entity_key: String,
+    /// The override as JSON
+    pub content: String,
+}
+
+/// What to add for a user. Requirement sets and overrides are kept for the user's API key,
+/// and everything else is added to one of the user's sessions; the enrollment jobs and cart
+/// items should belong to that session
+#[derive(Debug, Clone)]
+pub struct UserBundle<'a> {
+    pub key_prefix: &'a str,
+    pub session_token: &'a str,
+    pub enroll_jobs: Vec<NewEnrollJob<'a>>,
+    pub saved_plans: Vec<BundledSavedPlan<'a>>,
+    pub cart: Vec<NewCartItem<'a>>,
+    pub course_plans: Vec<BundledCoursePlan<'a>>,
+    pub custom_requirements: Vec<BundledRequirementSet<'a>>,
+    pub requirement_overrides: Vec<BundledOverride>,
+}
+
+/// The IDs of what a bundle added, in the bundle's order
+#[derive(Debug, Clone, Default)]
+pub struct ImportedBundle {
+    pub enroll_job_ids: Vec<i64>,
+    pub saved_plan_ids: Vec<i64>,
+    pub cart_item_ids: Vec<i64>,
+    pub course_plan_ids: Vec<i64>,
+    pub custom_requirement_ids: Vec<i64>,
+    /// The audit trail entries of the requirement overrides
+    pub requirement_override_ids: Vec<i64>,
+}
+
+impl ScheduleDbManager {
+    /// Records that a session was registered with a user's API key
+    pub fn record_session_owner(&self, session_token: &str, key_prefix: &str) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO session_owners (session_token, key_prefix, created_at)
+             VALUES (?, ?, datetime('now'))
+             ON CONFLICT (session_token) DO NOTHING",
+            (session_token, key_prefix),
+        )?;
+
+        Ok(())
+    }
+
+    /// Gets the tokens of every session that a user has registered, oldest first, including
+    /// the ones that no longer exist
+    pub fn get_owned_session_tokens(&self, key_prefix: &str) -> Result<Vec<String>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT session_token FROM session_owners
+             WHERE key_prefix = ?
+             ORDER BY created_at, rowid",
+        )?;
+
+        let tokens = stmt.query_map([key_prefix], |row| row.get(0))?;
+        tokens.collect()
+    }
+
+    /// Adds a bundle for a user in one transaction. Enrollment jobs and course plans are
+    /// inserted, while saved plans, cart items, and requirement sets replace the ones with
+    /// the same schedule name, course, or name. Requirement overrides are recorded as new
+    /// versions, by the user. If anything fails (e.g., the session already
+    /// has a course plan with a name in the bundle), nothing is added
+    pub fn import_user_bundle(&self, bundle: &UserBundle) -> Result<ImportedBundle> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        let mut imported = ImportedBundle::default();
+        for job in &bundle.enroll_jobs {
+            imported.enroll_job_ids.push(insert_enroll_job(&tx, job)?);
+        }
+
+        for plan in &bundle.saved_plans {
+            imported.saved_plan_ids.push(upsert_saved_plan(
+                &tx,
+                plan.term,
+                bundle.session_token,
+                plan.schedule_name,
+                &plan.entries,
+            )?);
+        }
+
+        for item in &bundle.cart {
+            imported.cart_item_ids.push(upsert_cart_item(&tx, item)?);
+        }
+
+        for plan in &bundle.course_plans {
+            imported.course_plan_ids.push(insert_course_plan(
+                &tx,
+                bundle.session_token,
+                plan.name,
+                &plan.quarters,
+            )?);
+        }
+
+        for set in &bundle.custom_requirements {
+            imported
+                .custom_requirement_ids
+                .push(upsert_custom_requirement_set(
+                    &tx,
+                    bundle.key_prefix,
+                    set.name,
+                    set.subrequirements,
+                )?);
+        }
+
+        for o in &bundle.requirement_overrides {
+            let entry = record_trail_version(
+                &tx,
+                TrailKind::Override.as_str(),
+                &o.entity_key,
+                Some(&o.content),
+                bundle.key_prefix,
+                IMPORTED_OVERRIDE_REASON,
+            )?;
+            imported.requirement_override_ids.push(entry.entry_id);
+        }
+
+        tx.commit()?;
+        Ok(imported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::WatchAlerts;
+
+    fn bundle<'a>(plan_names: &[&'a str]) -> UserBundle<'a> {
+        UserBundle {
+            key_prefix: "key",
+            session_token: "a",
+            enroll_jobs: vec![NewEnrollJob {
+                term: "FA24",
+                session_token: "a",
+                section_id: "123456",
+                subject_code: "CSE",
+                course_code: "100",
+                grading_option: None,
+                unit_count: Some(4),
+                run_at: None,
+                expires_at: None,
+                notify_channels: &[],
+                alerts: WatchAlerts::default(),
+            }],
+            saved_plans: vec![BundledSavedPlan {
+                term: "FA24",
+                schedule_name: "Main",
+                entries: vec![],
+            }],
+            cart: vec![NewCartItem {
+                term: "FA24",
+                session_token: "a",
+                subject_code: "CSE",
+                course_code: "100",
+                sections: &[],
+                notes: None,
+                priority: 0,
+                grading_option: None,
+                unit_count: Some(4),
+            }],
+            course_plans: plan_names
+                .iter()
+                .map(|name| BundledCoursePlan {
+                    name,
+                    quarters: vec![],
+                })
+                .collect(),
+            custom_requirements: vec![BundledRequirementSet {
+                name: "Pre-med",
+                subrequirements: &[],
+            }],
+            requirement_overrides: vec![BundledOverride {
+                entity_key: "key/writing".to_owned(),
+                content: r#"{"requirement":"Writing","status":"Complete","kind":"petition","user":"key"}"#
+                    .to_owned(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_import_bundle() {
+        let db = ScheduleDbManager::new(":memory:");
+        let imported = db.import_user_bundle(&bundle(&["Four years"])).unwrap();
+
+        let jobs = db.get_enroll_jobs("FA24", "a").unwrap();
+        assert_eq!(vec![jobs[0].job_id], imported.enroll_job_ids);
+        let plans = db.get_saved_plans("FA24", "a").unwrap();
+        assert_eq!(vec![plans[0].plan_id], imported.saved_plan_ids);
+        let cart = db.get_cart("FA24", "a").unwrap();
+        assert_eq!(vec![cart[0].item_id], imported.cart_item_ids);
+        let course_plans = db.get_course_plans("a").unwrap();
+        assert_eq!(vec![course_plans[0].plan_id], imported.course_plan_ids);
+        let sets = db.get_custom_requirement_sets("key").unwrap();
+        assert_eq!(
+            vec![sets[0].requirement_id],
+            imported.custom_requirement_ids
+        );
+        let overrides = db
+            .get_current_trail_versions(TrailKind::Override.as_str())
+            .unwrap();
+        assert_eq!(
+            vec![overrides[0].entry_id],
+            imported.requirement_override_ids
+        );
+        assert_eq!("key", overrides[0].author);
+    }
+
+    #[test]
+    fn test_failed_import_rolls_back() {
+        let db = ScheduleDbManager::new(":memory:");
+        // The second course plan can't be inserted, after everything else has been
+        assert!(db
+            .import_user_bundle(&bundle(&["Four years", "Four years"]))
+            .is_err());
+
+        assert!(db.get_enroll_jobs("FA24", "a").unwrap().is_empty());
+        assert!(db.get_saved_plans("FA24", "a").unwrap().is_empty());
+        assert!(db.get_cart("FA24", "a").unwrap().is_empty());
+        assert!(db.get_course_plans("a").unwrap().is_empty());
+        assert!(db.get_custom_requirement_sets("key").unwrap().is_empty());
+        assert!(db
+            .get_current_trail_versions(TrailKind::Override.as_str())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_owned_sessions() {
+        let db = ScheduleDbManager::new(":memory:");
+        db.record_session_owner("a", "key").unwrap();
+        db.record_session_owner("b", "other").unwrap();
+        db.record_session_owner("c", "key").unwrap();
+        // A session keeps the key that registered it
+        db.record_session_owner("a", "other").unwrap();
+
+        assert_eq!(vec!["a", "c"], db.get_owned_session_tokens("key").unwrap());
+        assert_eq!(vec!["b"], db.get_owned_session_tokens("other").unwrap());
+    }
+}
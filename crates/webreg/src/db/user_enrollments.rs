@@ -0,0 +1,160 @@
+//! Storage for the mirror of each user's WebReg schedule (see [`crate::schedule_mirror`])
+
+use std::collections::HashSet;
+
+use rusqlite::{Result, Row};
+use serde::Serialize;
+
+use super::ScheduleDbManager;
+
+/// The user is enrolled in the section
+pub const USER_ENROLLMENT_ENROLLED: &str = "enrolled";
+/// The user is on the section's waitlist
+pub const USER_ENROLLMENT_WAITLISTED: &str = "waitlisted";
+
+/// A section that a user is enrolled or waitlisted in
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UserEnrollment {
+    pub term: String,
+    pub section_id: String,
+    pub subject_code: String,
+    pub course_code: String,
+    pub section_code: String,
+    /// `enrolled` or `waitlisted`
+    pub status: String,
+    pub waitlist_pos: Option<i64>,
+    pub units: f32,
+    pub grade_option: String,
+    /// When the section was first mirrored, if it has been
+    pub first_seen_at: Option<String>,
+    /// When the section was last mirrored, if it has been
+    pub synced_at: Option<String>,
+}
+
+const USER_ENROLLMENT_COLUMNS: &str = "term, section_id, subject_code, course_code, \
+                                       section_code, status, waitlist_pos, units, \
+                                       grade_option, first_seen_at, synced_at";
+
+impl ScheduleDbManager {
+    /// Gets the mirrored sections of a user's schedule in a term, by course
+    pub fn get_user_enrollments(
+        &self,
+        key_prefix: &str,
+        term: &str,
+    ) -> Result<Vec<UserEnrollment>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(&format!(
+            "SELECT {USER_ENROLLMENT_COLUMNS} FROM user_enrollments
+             WHERE key_prefix = ? AND term = ?
+             ORDER BY subject_code, course_code, section_code"
+        ))?;
+
+        let sections = stmt.query_map((key_prefix, term), user_enrollment_from_row)?;
+        sections.collect()
+    }
+
+    /// Gets the courses (e.g., `CSE 100`) that each user is enrolled in, one list per user
+    /// and term, without saying whose they are. Waitlisted sections are left out
+    pub fn get_enrolled_schedules(&self, term: Option<&str>) -> Result<Vec<Vec<String>>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT key_prefix, term, subject_code || ' ' || course_code FROM user_enrollments
+             WHERE status = ?1 AND (?2 IS NULL OR term = ?2)
+             ORDER BY key_prefix, term",
+        )?;
+
+        let mut schedules: Vec<Vec<String>> = vec![];
+        let mut current = None;
+        let rows = stmt.query_map((USER_ENROLLMENT_ENROLLED, term), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (key_prefix, term, course) = row?;
+            let schedule = Some((key_prefix, term));
+            if schedule != current {
+                schedules.push(vec![]);
+                current = schedule;
+            }
+            schedules.last_mut().unwrap().push(course);
+        }
+
+        Ok(schedules)
+    }
+
+    /// Replaces the mirror of a user's schedule in a term with the given sections, in one
+    /// transaction. Sections that were already mirrored keep the time they were first seen
+    pub fn replace_user_enrollments(
+        &self,
+        key_prefix: &str,
+        term: &str,
+        sections: &[UserEnrollment],
+    ) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        let current: HashSet<&str> = sections.iter().map(|s| s.section_id.as_str()).collect();
+        let previous = tx
+            .prepare("SELECT section_id FROM user_enrollments WHERE key_prefix = ? AND term = ?")?
+            .query_map((key_prefix, term), |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>>>()?;
+        for section_id in previous.iter().filter(|id| !current.contains(id.as_str())) {
+            tx.execute(
+                "DELETE FROM user_enrollments
+                 WHERE key_prefix = ? AND term = ? AND section_id = ?",
+                (key_prefix, term, section_id),
+            )?;
+        }
+
+        for section in sections {
+            tx.execute(
+                "INSERT INTO user_enrollments (
+                    key_prefix, term, section_id, subject_code, course_code, section_code,
+                    status, waitlist_pos, units, grade_option, first_seen_at, synced_at
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, datetime('now'), datetime('now'))
+                ON CONFLICT (key_prefix, term, section_id) DO UPDATE SET
+                    subject_code = excluded.subject_code,
+                    course_code = excluded.course_code,
+                    section_code = excluded.section_code,
+                    status = excluded.status,
+                    waitlist_pos = excluded.waitlist_pos,
+                    units = excluded.units,
+                    grade_option = excluded.grade_option,
+                    synced_at = excluded.synced_at",
+                (
+                    key_prefix,
+                    term,
+                    &section.section_id,
+                    &section.subject_code,
+                    &section.course_code,
+                    &section.section_code,
+                    &section.status,
+                    section.waitlist_pos,
+                    section.units,
+                    &section.grade_option,
+                ),
+            )?;
+        }
+
+        tx.commit()
+    }
+}
+
+/// Maps a row of `USER_ENROLLMENT_COLUMNS` to a mirrored section
+fn user_enrollment_from_row(row: &Row) -> Result<UserEnrollment> {
+    Ok(UserEnrollment {
+        term: row.get(0)?,
+        section_id: row.get(1)?,
+        subject_code: row.get(2)?,
+        course_code: row.get(3)?,
+        section_code: row.get(4)?,
+        status: row.get(5)?,
+        waitlist_pos: row.get(6)?,
+        units: row.get(7)?,
+        grade_option: row.get(8)?,
+        first_seen_at: row.get(9)?,
+        synced_at: row.get(10)?,
+    })
+}
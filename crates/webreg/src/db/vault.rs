@@ -0,0 +1,105 @@
+//! Storage for users' sealed WebReg credentials (see [`crate::vault`]), along with whether
+//! each user's cookie server has the latest version of them.
+
+use rusqlite::{OptionalExtension, Result};
+use serde::Serialize;
+
+use super::ScheduleDbManager;
+
+/// A user's entry in the vault, without the credentials themselves.
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultEntry {
+    pub username: String,
+    pub version: i64,
+    pub updated_at: String,
+    pub synced_version: Option<i64>,
+    pub synced_at: Option<String>,
+    pub sync_error: Option<String>,
+}
+
+impl ScheduleDbManager {
+    /// Stores a user's sealed credentials in place of any they had before, returning the
+    /// new version of them
+    pub fn put_vault_credentials(
+        &self,
+        key_prefix: &str,
+        sealed: &[u8],
+        username: &str,
+    ) -> Result<i64> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            "INSERT INTO credential_vault (key_prefix, sealed, username, version, updated_at)
+             VALUES (?1, ?2, ?3, 1, datetime('now'))
+             ON CONFLICT(key_prefix) DO UPDATE
+             SET sealed = excluded.sealed, username = excluded.username,
+                 version = version + 1, updated_at = excluded.updated_at
+             RETURNING version",
+            (key_prefix, sealed, username),
+            |row| row.get(0),
+        )
+    }
+
+    /// Gets a user's sealed credentials, along with their version
+    pub fn get_vault_credentials(&self, key_prefix: &str) -> Result<Option<(Vec<u8>, i64)>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            "SELECT sealed, version FROM credential_vault WHERE key_prefix = ?",
+            [key_prefix],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+    }
+
+    /// Gets a user's entry in the vault
+    pub fn get_vault_entry(&self, key_prefix: &str) -> Result<Option<VaultEntry>> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            "SELECT username, version, updated_at, synced_version, synced_at, sync_error
+             FROM credential_vault WHERE key_prefix = ?",
+            [key_prefix],
+            |row| {
+                Ok(VaultEntry {
+                    username: row.get(0)?,
+                    version: row.get(1)?,
+                    updated_at: row.get(2)?,
+                    synced_version: row.get(3)?,
+                    synced_at: row.get(4)?,
+                    sync_error: row.get(5)?,
+                })
+            },
+        )
+        .optional()
+    }
+
+    /// Deletes a user's credentials. Returns whether they had any
+    pub fn delete_vault_credentials(&self, key_prefix: &str) -> Result<bool> {
+        let db = self.db.lock().unwrap();
+        let deleted = db.execute(
+            "DELETE FROM credential_vault WHERE key_prefix = ?",
+            [key_prefix],
+        )?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Records the result of sending a version of a user's credentials to their cookie
+    /// server. Nothing is recorded if a newer version was stored in the meantime
+    pub fn record_vault_sync(
+        &self,
+        key_prefix: &str,
+        version: i64,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "UPDATE credential_vault
+             SET synced_version = CASE WHEN ?3 IS NULL THEN ?2 ELSE synced_version END,
+                 synced_at = CASE WHEN ?3 IS NULL THEN datetime('now') ELSE synced_at END,
+                 sync_error = ?3
+             WHERE key_prefix = ?1 AND version = ?2",
+            (key_prefix, version, error),
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+//! Stripping a student's identity from their audit, so that it can be shared.
+//!
+//! With `?anonymize=true`, the degree audit endpoints leave out the student's name and PID,
+//! and replace the audit's ID with a hash of it. The hash is stable, so that an audit that
+//! was shared more than once (e.g., its progress and its graduation plan) can still be
+//! matched up by whoever it was shared with, without revealing the ID that DARS gave it.
+//! The student's major and college are kept, since they're what make the audit useful to
+//! a peer advisor.
+
+use sha2::{Digest, Sha256};
+
+use super::types::DegreeAudit;
+
+/// The prefix of an anonymized audit ID, so that it can't be mistaken for one from DARS.
+pub const ANONYMOUS_ID_PREFIX: &str = "anon-";
+
+/// The number of hex digits of the hash that an anonymized audit ID keeps.
+const ANONYMOUS_ID_DIGITS: usize = 16;
+
+/// Gets the anonymized ID of an audit (e.g., `anon-3f2c9a...`).
+///
+/// # Parameters
+/// - `audit_id`: The ID that DARS gave the audit.
+pub fn anonymous_audit_id(audit_id: &str) -> String {
+    let hash = format!("{:x}", Sha256::digest(audit_id.as_bytes()));
+    format!("{ANONYMOUS_ID_PREFIX}{}", &hash[..ANONYMOUS_ID_DIGITS])
+}
+
+/// Removes the student's identity from an audit (see the module's documentation). Anything
+/// computed from the audit afterward, like its progress, is anonymized too.
+///
+/// # Parameters
+/// - `audit`: The audit.
+pub fn anonymize_audit(audit: &mut DegreeAudit) {
+    audit.student_info.name = None;
+    audit.student_info.student_id = None;
+    if !audit.audit_id.starts_with(ANONYMOUS_ID_PREFIX) {
+        audit.audit_id = anonymous_audit_id(&audit.audit_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::degree_audit::StudentInfo;
+
+    #[test]
+    fn test_anonymize_audit() {
+        let mut audit = DegreeAudit {
+            audit_id: "JOB12345".to_owned(),
+            student_info: StudentInfo {
+                student_id: Some("A12345678".to_owned()),
+                name: Some("Jane Doe".to_owned()),
+                major: Some("CS26".to_owned()),
+                college: Some("Revelle".to_owned()),
+            },
+            requirements: vec![],
+            scraped_at: "2024-10-01T00:00:00Z".to_owned(),
+            parse_warnings: vec![],
+            report_version: None,
+        };
+
+        anonymize_audit(&mut audit);
+        assert_eq!(None, audit.student_info.name);
+        assert_eq!(None, audit.student_info.student_id);
+        assert_eq!(Some("CS26"), audit.student_info.major.as_deref());
+        assert_eq!(anonymous_audit_id("JOB12345"), audit.audit_id);
+        assert_eq!(
+            ANONYMOUS_ID_PREFIX.len() + ANONYMOUS_ID_DIGITS,
+            audit.audit_id.len()
+        );
+
+        // Anonymizing twice doesn't hash the hash
+        anonymize_audit(&mut audit);
+        assert_eq!(anonymous_audit_id("JOB12345"), audit.audit_id);
+        assert_ne!(anonymous_audit_id("JOB12346"), audit.audit_id);
+    }
+}
@@ -0,0 +1,258 @@
+//! Archival storage of the raw HTML of fetched degree audits.
+//!
+//! Consecutive audits of the same student are usually byte-identical, and each one can be
+//! several megabytes. Pages are therefore stored once per distinct content, keyed by their
+//! SHA-256 hash, and each fetched audit is recorded as a snapshot that refers to its page by
+//! hash. Snapshots past the retention period are removed periodically (except for the
+//! newest one of each session), along with any pages that no snapshot refers to anymore.
+//!
+//! With a payload store configured (see [`crate::payload_store`]), pages are kept there
+//! instead, under `dars/<hash>.html`, and only the snapshots are kept in the database. The
+//! store removes pages past its own retention period.
+
+use super::cache::{hex, SessionKey};
+use super::types::DegreeAuditResponse;
+use crate::payload_store::{dars_payload_key, PayloadArchive};
+use rusqlite::{Connection, Result};
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Default number of days that snapshots are kept for.
+pub const DEFAULT_ARCHIVE_RETENTION_DAYS: u64 = 90;
+
+/// What was removed by a garbage collection run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcStats {
+    pub snapshots_removed: usize,
+    pub blobs_removed: usize,
+    /// The total size of the removed pages, in bytes
+    pub bytes_freed: i64,
+}
+
+/// The number of snapshots and distinct pages in the archive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArchiveStats {
+    pub snapshots: i64,
+    pub blobs: i64,
+    /// The total size of the stored pages, in bytes
+    pub bytes: i64,
+}
+
+/// Content-addressed storage for raw degree audit pages.
+pub struct AuditArchive {
+    db: Mutex<Connection>,
+    retention: Duration,
+    /// Where pages are kept, if not in the database
+    payloads: Option<Arc<PayloadArchive>>,
+}
+
+impl AuditArchive {
+    /// Opens (or creates) the archive database at the given path.
+    ///
+    /// Falls back to an in-memory database if the file can't be opened.
+    pub fn open(path: &str, retention: Duration) -> Self {
+        let conn = Connection::open(path).unwrap_or_else(|e| {
+            warn!(
+                "Failed to open audit archive database {}: {}. Using memory.",
+                path, e
+            );
+            Connection::open_in_memory().expect("Failed to open in-memory database")
+        });
+
+        Self::from_connection(conn, retention)
+    }
+
+    fn from_connection(conn: Connection, retention: Duration) -> Self {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS audit_blobs (
+                hash TEXT PRIMARY KEY,
+                html TEXT NOT NULL,
+                size INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS audit_snapshots (
+                snapshot_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_key TEXT NOT NULL,
+                audit_id TEXT NOT NULL,
+                url TEXT NOT NULL,
+                scraped_at TEXT NOT NULL,
+                content_hash TEXT NOT NULL,
+                archived_at INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_audit_snapshots_hash
+                ON audit_snapshots(content_hash);",
+        )
+        .expect("Failed to initialize audit archive schema");
+
+        Self {
+            db: Mutex::new(conn),
+            retention,
+            payloads: None,
+        }
+    }
+
+    /// Keeps pages in a payload store instead of the database.
+    pub fn with_payloads(mut self, payloads: Arc<PayloadArchive>) -> Self {
+        self.payloads = Some(payloads);
+        self
+    }
+
+    /// Records a fetched audit, storing its page only if an identical page isn't already
+    /// stored.
+    ///
+    /// # Returns
+    /// The hash of the page.
+    pub fn store(&self, key: &SessionKey, raw: &DegreeAuditResponse) -> Result<String> {
+        self.store_at(key, raw, unix_now())
+    }
+
+    fn store_at(&self, key: &SessionKey, raw: &DegreeAuditResponse, now: u64) -> Result<String> {
+        let hash = hex::encode(&Sha256::digest(raw.html.as_bytes()));
+        if let Some(payloads) = &self.payloads {
+            payloads.archive(dars_payload_key(&hash), raw.html.clone().into_bytes());
+        }
+
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        if self.payloads.is_none() {
+            tx.execute(
+                "INSERT OR IGNORE INTO audit_blobs (hash, html, size) VALUES (?1, ?2, ?3)",
+                (&hash, &raw.html, raw.html.len() as i64),
+            )?;
+        }
+        tx.execute(
+            "INSERT INTO audit_snapshots
+                (session_key, audit_id, url, scraped_at, content_hash, archived_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            (
+                key.as_str(),
+                &raw.audit_id,
+                &raw.url,
+                &raw.scraped_at,
+                &hash,
+                now as i64,
+            ),
+        )?;
+        tx.commit()?;
+
+        Ok(hash)
+    }
+
+    /// Returns the number of snapshots and pages in the archive.
+    pub fn stats(&self) -> Result<ArchiveStats> {
+        let db = self.db.lock().unwrap();
+        db.query_row(
+            "SELECT (SELECT COUNT(*) FROM audit_snapshots), COUNT(*), COALESCE(SUM(size), 0)
+             FROM audit_blobs",
+            [],
+            |row| {
+                Ok(ArchiveStats {
+                    snapshots: row.get(0)?,
+                    blobs: row.get(1)?,
+                    bytes: row.get(2)?,
+                })
+            },
+        )
+    }
+
+    /// Removes snapshots older than the retention period (keeping the newest snapshot of
+    /// each session), and then every page that no snapshot refers to.
+    pub fn collect_garbage(&self) -> Result<GcStats> {
+        self.collect_garbage_at(unix_now())
+    }
+
+    fn collect_garbage_at(&self, now: u64) -> Result<GcStats> {
+        let cutoff = now.saturating_sub(self.retention.as_secs()) as i64;
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction()?;
+        let snapshots_removed = tx.execute(
+            "DELETE FROM audit_snapshots
+             WHERE archived_at < ?1
+               AND snapshot_id NOT IN (
+                   SELECT MAX(snapshot_id) FROM audit_snapshots GROUP BY session_key
+               )",
+            [cutoff],
+        )?;
+
+        let unreferenced = "FROM audit_blobs
+             WHERE hash NOT IN (SELECT content_hash FROM audit_snapshots)";
+        let bytes_freed: i64 = tx.query_row(
+            &format!("SELECT COALESCE(SUM(size), 0) {unreferenced}"),
+            [],
+            |row| row.get(0),
+        )?;
+        let blobs_removed = tx.execute(&format!("DELETE {unreferenced}"), [])?;
+        tx.commit()?;
+
+        Ok(GcStats {
+            snapshots_removed,
+            blobs_removed,
+            bytes_freed,
+        })
+    }
+}
+
+/// The current time, in seconds since the Unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(audit_id: &str, html: &str) -> DegreeAuditResponse {
+        DegreeAuditResponse {
+            audit_id: audit_id.to_string(),
+            scraped_at: "2024-01-01T00:00:00Z".to_string(),
+            url: String::new(),
+            html: html.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_dedup_and_gc() {
+        let day = 24 * 60 * 60;
+        let conn = Connection::open_in_memory().unwrap();
+        let archive = AuditArchive::from_connection(conn, Duration::from_secs(30 * day));
+        let key = SessionKey::from_cookie("session123");
+
+        let first = archive
+            .store_at(&key, &raw("a1", "<p>same</p>"), 0)
+            .unwrap();
+        let second = archive
+            .store_at(&key, &raw("a2", "<p>same</p>"), day)
+            .unwrap();
+        archive
+            .store_at(&key, &raw("a3", "<p>new</p>"), 40 * day)
+            .unwrap();
+        assert_eq!(first, second);
+        assert_eq!(
+            ArchiveStats {
+                snapshots: 3,
+                blobs: 2,
+                bytes: 21
+            },
+            archive.stats().unwrap()
+        );
+
+        // Both snapshots of the old page are past the retention period
+        let stats = archive.collect_garbage_at(40 * day).unwrap();
+        assert_eq!(
+            GcStats {
+                snapshots_removed: 2,
+                blobs_removed: 1,
+                bytes_freed: 11
+            },
+            stats
+        );
+
+        // The newest snapshot of a session is always kept
+        let stats = archive.collect_garbage_at(100 * day).unwrap();
+        assert_eq!(GcStats::default(), stats);
+    }
+}
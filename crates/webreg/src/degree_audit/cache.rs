@@ -1,10 +1,38 @@
 //! TTL-based caching for degree audit results.
-
-use super::types::DegreeAudit;
+//!
+//! The cache can optionally be backed by a directory of JSON files (one per session), so
+//! that audits survive restarts instead of each needing another slow Puppeteer round-trip.
+
+use super::archive::AuditArchive;
+use super::quota::{AuditQuota, DEFAULT_DAILY_AUDIT_QUOTA};
+use super::ttl::AuditTtlPolicy;
+use super::types::{DegreeAudit, DegreeAuditResponse};
+use super::wait::{PendingAudits, WaitEstimator};
 use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+pub use webreg_types::degree_audit::AuditFetch;
+
+/// How long an expired audit is kept so that it can still be served, marked as stale, while
+/// a fresh one is fetched in the background.
+pub const MAX_STALENESS: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How cached audits are used when fetching a degree audit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Only unexpired audits are returned from the cache; otherwise, wait for a fresh audit.
+    #[default]
+    FreshOnly,
+    /// An expired audit is returned immediately, while a fresh one is fetched in the
+    /// background.
+    StaleWhileRevalidate,
+}
 
 /// A session key derived from cookies, used for cache lookups and locking.
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
@@ -23,6 +51,11 @@ impl SessionKey {
         Self(hash)
     }
 
+    /// Creates a session key from a hash previously returned by [`SessionKey::as_str`].
+    pub(crate) fn from_hash(hash: String) -> Self {
+        Self(hash)
+    }
+
     /// Creates a session key from a JSESSIONID cookie specifically.
     pub fn from_jsessionid(jsessionid: &str) -> Self {
         Self::from_cookie(jsessionid)
@@ -52,12 +85,102 @@ struct CachedAudit {
     ttl: Duration,
 }
 
+/// A cached audit as written to disk.
+#[derive(Serialize, Deserialize)]
+struct StoredAudit {
+    /// When the audit was cached, in seconds since the Unix epoch
+    cached_at: u64,
+    /// TTL for this entry, in seconds
+    ttl_secs: u64,
+    result: DegreeAudit,
+}
+
+/// Writes cached audits through to a directory, one JSON file per session.
+///
+/// Failures are logged rather than returned, since the in-memory cache still works.
+struct DiskStore {
+    dir: PathBuf,
+}
+
+impl DiskStore {
+    fn path(&self, key: &SessionKey) -> PathBuf {
+        self.dir.join(format!("{}.json", key.as_str()))
+    }
+
+    fn write(&self, key: &SessionKey, entry: &CachedAudit) {
+        let age = entry.cached_at.elapsed().as_secs();
+        let stored = StoredAudit {
+            cached_at: unix_now().saturating_sub(age),
+            ttl_secs: entry.ttl.as_secs(),
+            result: entry.result.clone(),
+        };
+
+        let written = serde_json::to_vec(&stored)
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(self.path(key), json).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            warn!("Failed to write cached audit for {}: {}", key, e);
+        }
+    }
+
+    fn remove(&self, key: &SessionKey) {
+        let path = self.path(key);
+        if path.exists() {
+            if let Err(e) = std::fs::remove_file(path) {
+                warn!("Failed to remove cached audit for {}: {}", key, e);
+            }
+        }
+    }
+
+    fn clear(&self) {
+        for (key, _) in self.load() {
+            self.remove(&key);
+        }
+    }
+
+    /// Reads every stored audit, skipping (and removing) any that can't be read.
+    fn load(&self) -> Vec<(SessionKey, StoredAudit)> {
+        let Ok(dir) = std::fs::read_dir(&self.dir) else {
+            return vec![];
+        };
+
+        dir.filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let key = SessionKey(path.file_stem()?.to_str()?.to_owned());
+                let stored = std::fs::read(&path)
+                    .ok()
+                    .and_then(|json| serde_json::from_slice::<StoredAudit>(&json).ok());
+                if stored.is_none() {
+                    warn!("Removing unreadable cached audit {}", path.display());
+                    let _ = std::fs::remove_file(&path);
+                }
+
+                Some((key, stored?))
+            })
+            .collect()
+    }
+}
+
+/// The current time, in seconds since the Unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
 /// Thread-safe cache for degree audit results.
 ///
 /// Uses DashMap for concurrent access without external locking.
 pub struct AuditCache {
     entries: DashMap<SessionKey, CachedAudit>,
     default_ttl: Duration,
+    /// Where entries are written through to, if the cache is disk-backed
+    disk: Option<DiskStore>,
+    /// Decides the TTL of inserted entries from the term calendar, instead of using
+    /// `default_ttl`
+    ttl_policy: Option<AuditTtlPolicy>,
 }
 
 impl AuditCache {
@@ -66,6 +189,52 @@ impl AuditCache {
         Self {
             entries: DashMap::new(),
             default_ttl,
+            disk: None,
+            ttl_policy: None,
+        }
+    }
+
+    /// Creates a disk-backed cache with the specified default TTL.
+    ///
+    /// Audits already in `dir` that can still be served are loaded, and every insert is
+    /// written through to `dir`, so cached audits survive restarts with the same TTL
+    /// semantics.
+    pub fn with_disk(default_ttl: Duration, dir: impl AsRef<Path>) -> Self {
+        let dir = dir.as_ref().to_path_buf();
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            warn!(
+                "Failed to create audit cache directory {}: {}",
+                dir.display(),
+                e
+            );
+        }
+
+        let disk = DiskStore { dir };
+        let entries = DashMap::new();
+        let now = unix_now();
+        for (key, stored) in disk.load() {
+            let age = Duration::from_secs(now.saturating_sub(stored.cached_at));
+            let ttl = Duration::from_secs(stored.ttl_secs);
+            match Instant::now().checked_sub(age) {
+                Some(cached_at) if age < ttl + MAX_STALENESS => {
+                    entries.insert(
+                        key,
+                        CachedAudit {
+                            result: stored.result,
+                            cached_at,
+                            ttl,
+                        },
+                    );
+                }
+                _ => disk.remove(&key),
+            }
+        }
+
+        Self {
+            entries,
+            default_ttl,
+            disk: Some(disk),
+            ttl_policy: None,
         }
     }
 
@@ -76,43 +245,67 @@ impl AuditCache {
 
     /// Gets a cached audit if it exists and hasn't expired.
     pub fn get(&self, key: &SessionKey) -> Option<DegreeAudit> {
-        self.entries.get(key).and_then(|entry| {
-            if entry.cached_at.elapsed() < entry.ttl {
-                Some(entry.result.clone())
-            } else {
-                // Entry expired, remove it
-                drop(entry);
-                self.entries.remove(key);
-                None
-            }
-        })
+        match self.get_allowing_stale(key) {
+            Some((audit, false)) => Some(audit),
+            _ => None,
+        }
+    }
+
+    /// Gets a cached audit even if it has expired, along with whether it has expired.
+    /// Entries that expired more than [`MAX_STALENESS`] ago are removed instead.
+    pub fn get_allowing_stale(&self, key: &SessionKey) -> Option<(DegreeAudit, bool)> {
+        let entry = self.entries.get(key)?;
+        let age = entry.cached_at.elapsed();
+        if age < entry.ttl {
+            Some((entry.result.clone(), false))
+        } else if age < entry.ttl + MAX_STALENESS {
+            Some((entry.result.clone(), true))
+        } else {
+            drop(entry);
+            self.invalidate(key);
+            None
+        }
     }
 
-    /// Inserts an audit result into the cache with the default TTL.
+    /// Inserts an audit result into the cache with the TTL given by the calendar policy,
+    /// or the default TTL if there is no policy.
     pub fn insert(&self, key: SessionKey, result: DegreeAudit) {
-        self.insert_with_ttl(key, result, self.default_ttl);
+        let ttl = match &self.ttl_policy {
+            Some(policy) => policy.current_ttl(),
+            None => self.default_ttl,
+        };
+        self.insert_with_ttl(key, result, ttl);
     }
 
     /// Inserts an audit result with a custom TTL.
     pub fn insert_with_ttl(&self, key: SessionKey, result: DegreeAudit, ttl: Duration) {
-        self.entries.insert(
-            key,
-            CachedAudit {
-                result,
-                cached_at: Instant::now(),
-                ttl,
-            },
-        );
+        let entry = CachedAudit {
+            result,
+            cached_at: Instant::now(),
+            ttl,
+        };
+
+        if let Some(disk) = &self.disk {
+            disk.write(&key, &entry);
+        }
+
+        self.entries.insert(key, entry);
     }
 
     /// Invalidates (removes) a cached entry.
     pub fn invalidate(&self, key: &SessionKey) {
         self.entries.remove(key);
+        if let Some(disk) = &self.disk {
+            disk.remove(key);
+        }
     }
 
     /// Clears all entries from the cache.
     pub fn clear(&self) {
         self.entries.clear();
+        if let Some(disk) = &self.disk {
+            disk.clear();
+        }
     }
 
     /// Returns the number of entries in the cache (including expired ones).
@@ -125,12 +318,20 @@ impl AuditCache {
         self.entries.is_empty()
     }
 
-    /// Removes expired entries from the cache.
+    /// Removes entries that are too old to be served, even as stale, from the cache.
     ///
     /// Call this periodically if you want proactive cleanup.
     pub fn cleanup_expired(&self) {
-        self.entries
-            .retain(|_, entry| entry.cached_at.elapsed() < entry.ttl);
+        let expired: Vec<SessionKey> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.cached_at.elapsed() >= entry.ttl + MAX_STALENESS)
+            .map(|entry| entry.key().clone())
+            .collect();
+
+        for key in expired {
+            self.invalidate(&key);
+        }
     }
 
     /// Gets cache statistics.
@@ -247,6 +448,28 @@ impl CircuitBreaker {
         self.failure_count
             .load(std::sync::atomic::Ordering::Relaxed)
     }
+
+    /// Returns the breaker's state, for the admin API.
+    pub fn status(&self) -> CircuitBreakerStatus {
+        let open = self.is_open();
+        let last_failure = self.last_failure.lock().ok().and_then(|guard| *guard);
+        CircuitBreakerStatus {
+            open,
+            failure_count: self.failure_count(),
+            threshold: self.threshold,
+            secs_since_last_failure: last_failure.map(|last| last.elapsed().as_secs()),
+        }
+    }
+}
+
+/// The state of a circuit breaker, as seen by the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitBreakerStatus {
+    pub open: bool,
+    pub failure_count: u32,
+    /// The number of failures in a row that open the breaker.
+    pub threshold: u32,
+    pub secs_since_last_failure: Option<u64>,
 }
 
 impl Default for CircuitBreaker {
@@ -256,18 +479,28 @@ impl Default for CircuitBreaker {
 }
 
 /// Helper module for hex encoding (avoiding extra dependency).
-mod hex {
+pub(crate) mod hex {
     pub fn encode(bytes: &[u8]) -> String {
         bytes.iter().map(|b| format!("{:02x}", b)).collect()
     }
 }
 
-/// Shared state wrapper combining cache and circuit breaker.
+/// Shared state wrapper combining cache, circuit breaker, and daily quota.
 pub struct AuditCacheState {
     pub cache: AuditCache,
     pub circuit_breaker: CircuitBreaker,
+    /// Daily per-session limit on the number of audits triggered
+    pub quota: AuditQuota,
+    /// The number of audit blocks that have been left out because they couldn't be parsed
+    pub dropped_blocks: AtomicUsize,
     /// Per-session locks to prevent concurrent operations
     pub session_locks: DashMap<SessionKey, Arc<tokio::sync::Mutex<()>>>,
+    /// Where the raw pages of fetched audits are archived, if archiving is enabled
+    pub archive: Option<AuditArchive>,
+    /// How long recent audits took, for estimating how long the next will take
+    pub wait_estimator: WaitEstimator,
+    /// The audits that are being generated in the background
+    pub pending: PendingAudits,
 }
 
 impl AuditCacheState {
@@ -276,7 +509,12 @@ impl AuditCacheState {
         Self {
             cache: AuditCache::with_default_ttl(),
             circuit_breaker: CircuitBreaker::with_defaults(),
+            quota: AuditQuota::in_memory(DEFAULT_DAILY_AUDIT_QUOTA),
+            dropped_blocks: AtomicUsize::new(0),
             session_locks: DashMap::new(),
+            archive: None,
+            wait_estimator: WaitEstimator::new(),
+            pending: PendingAudits::default(),
         }
     }
 
@@ -285,10 +523,61 @@ impl AuditCacheState {
         Self {
             cache: AuditCache::new(ttl),
             circuit_breaker: CircuitBreaker::with_defaults(),
+            quota: AuditQuota::in_memory(DEFAULT_DAILY_AUDIT_QUOTA),
+            dropped_blocks: AtomicUsize::new(0),
+            session_locks: DashMap::new(),
+            archive: None,
+            wait_estimator: WaitEstimator::new(),
+            pending: PendingAudits::default(),
+        }
+    }
+
+    /// Creates a new cache state whose cache is backed by the given directory.
+    pub fn with_disk_cache(dir: impl AsRef<Path>) -> Self {
+        Self {
+            cache: AuditCache::with_disk(Duration::from_secs(5 * 60), dir),
+            circuit_breaker: CircuitBreaker::with_defaults(),
+            quota: AuditQuota::in_memory(DEFAULT_DAILY_AUDIT_QUOTA),
+            dropped_blocks: AtomicUsize::new(0),
             session_locks: DashMap::new(),
+            archive: None,
+            wait_estimator: WaitEstimator::new(),
+            pending: PendingAudits::default(),
+        }
+    }
+
+    /// Replaces the quota tracker (e.g., with a persistent one).
+    pub fn with_quota(mut self, quota: AuditQuota) -> Self {
+        self.quota = quota;
+        self
+    }
+
+    /// Sets the calendar policy used to decide how long inserted audits are cached for.
+    pub fn with_ttl_policy(mut self, policy: AuditTtlPolicy) -> Self {
+        self.cache.ttl_policy = Some(policy);
+        self
+    }
+
+    /// Enables archiving the raw pages of fetched audits.
+    pub fn with_archive(mut self, archive: AuditArchive) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Archives the raw page of a freshly fetched audit, if archiving is enabled. Failures
+    /// are logged rather than returned, since the audit itself is still usable.
+    pub fn archive_raw(&self, key: &SessionKey, raw: &DegreeAuditResponse) {
+        if let Some(Err(e)) = self.archive.as_ref().map(|a| a.store(key, raw)) {
+            warn!("Failed to archive degree audit for {}: {}", key, e);
         }
     }
 
+    /// Counts the blocks that were left out of a freshly parsed audit.
+    pub fn record_parse_warnings(&self, audit: &DegreeAudit) {
+        self.dropped_blocks
+            .fetch_add(audit.parse_warnings.len(), Ordering::Relaxed);
+    }
+
     /// Gets or creates a lock for the given session.
     pub fn get_session_lock(&self, key: &SessionKey) -> Arc<tokio::sync::Mutex<()>> {
         self.session_locks
@@ -307,6 +596,7 @@ impl Default for AuditCacheState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::degree_audit::tests::audit;
 
     #[test]
     fn test_session_key_hashing() {
@@ -318,6 +608,36 @@ mod tests {
         assert_ne!(key1, key3);
     }
 
+    #[test]
+    fn test_disk_cache_survives_restart() {
+        let dir = std::env::temp_dir().join(format!("audit_cache_test_{}", std::process::id()));
+        let key = SessionKey::from_cookie("session123");
+        let expired = SessionKey::from_cookie("session456");
+
+        let cache = AuditCache::with_disk(Duration::from_secs(60), &dir);
+        cache.insert(key.clone(), audit("a1"));
+        cache.insert_with_ttl(expired.clone(), audit("a2"), Duration::ZERO);
+
+        let reloaded = AuditCache::with_disk(Duration::from_secs(60), &dir);
+        assert_eq!(
+            Some("a1".to_string()),
+            reloaded.get(&key).map(|a| a.audit_id)
+        );
+        // Expired audits are kept, so they can still be served as stale
+        assert!(reloaded.get(&expired).is_none());
+        assert_eq!(
+            Some(("a2".to_string(), true)),
+            reloaded
+                .get_allowing_stale(&expired)
+                .map(|(a, stale)| (a.audit_id, stale))
+        );
+        assert_eq!(2, reloaded.len());
+
+        reloaded.clear();
+        assert!(AuditCache::with_disk(Duration::from_secs(60), &dir).is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_circuit_breaker_threshold() {
         let cb = CircuitBreaker::new(3, Duration::from_secs(1));
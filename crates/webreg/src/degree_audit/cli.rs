@@ -0,0 +1,302 @@
+//! Offline parsing of saved degree audit pages, for the `webreg-audit` binary.
+//!
+//! Running `webreg-audit parse <file.html> [--json|--summary]` runs the full parser and
+//! progress processor on a degree audit page saved from DARS, so that students can debug
+//! their own audits and report parser gaps with the page as a fixture. Running
+//! `webreg-audit parse --replay <dir>` instead runs the whole audit flow against pages
+//! recorded with `auditFixtureDir` (see [`super::fixtures`]).
+
+use super::cache::{AuditCacheState, CachePolicy};
+use super::client::DegreeAuditConfig;
+use super::config::RequirementsConfig;
+use super::fixtures::FixtureMode;
+use super::types::DegreeAudit;
+use super::{
+    parse_degree_audit_html, DegreeAuditClient, DegreeAuditResponse, DegreeProgressProcessor,
+};
+use crate::ingest;
+use clap::{Parser, Subcommand};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+/// The arguments of `webreg-audit`.
+#[derive(Parser, Debug)]
+#[command(
+    name = "webreg-audit",
+    author,
+    version,
+    about = "Parses degree audit pages saved from DARS, without a server."
+)]
+pub struct CliArg {
+    #[command(subcommand)]
+    pub command: CliSubCmd,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CliSubCmd {
+    /// Runs the parser and progress processor on a saved degree audit page.
+    #[clap(name = "parse")]
+    Parse {
+        /// The degree audit page, saved from DARS as an HTML file.
+        #[clap(
+            name = "file",
+            value_name = "FILE",
+            required_unless_present = "replay",
+            conflicts_with = "replay"
+        )]
+        file: Option<String>,
+        /// A directory of DARS pages recorded with `auditFixtureDir`, to replay through the
+        /// whole audit flow instead.
+        #[clap(name = "replay", long, value_name = "DIR")]
+        replay: Option<String>,
+        /// Prints the parsed audit and computed progress as JSON.
+        #[clap(long, overrides_with = "summary")]
+        json: bool,
+        /// Prints a short, human-readable overview. This is the default.
+        #[clap(long, overrides_with = "json")]
+        summary: bool,
+    },
+}
+
+/// How the parsed audit is printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// The parsed audit and computed progress, as JSON
+    Json,
+    /// A short, human-readable overview
+    Summary,
+}
+
+/// Where the audit comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AuditSource<'a> {
+    /// A saved audit page
+    File(&'a str),
+    /// A directory of recorded DARS pages, replayed through the audit flow
+    Replay(&'a str),
+}
+
+impl CliSubCmd {
+    /// Where the audit comes from and how it's printed.
+    fn source_and_format(&self) -> (AuditSource<'_>, OutputFormat) {
+        match self {
+            CliSubCmd::Parse {
+                file, replay, json, ..
+            } => {
+                // Clap makes sure that exactly one of the two is given
+                let source = match (file, replay) {
+                    (_, Some(dir)) => AuditSource::Replay(dir),
+                    (Some(path), None) => AuditSource::File(path),
+                    (None, None) => unreachable!("clap requires a file or --replay"),
+                };
+                let format = if *json {
+                    OutputFormat::Json
+                } else {
+                    OutputFormat::Summary
+                };
+                (source, format)
+            }
+        }
+    }
+}
+
+/// Parses a saved audit page.
+fn parse_file(path: &str) -> Result<DegreeAudit, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Unable to read {path}: {e}"))?;
+    let html = ingest::decode(&bytes).map_err(|e| format!("Unable to read {path}: {e}"))?;
+
+    let raw_audit = DegreeAuditResponse {
+        audit_id: "offline".to_string(),
+        scraped_at: chrono::Utc::now().to_rfc3339(),
+        url: path.to_string(),
+        html,
+    };
+
+    parse_degree_audit_html(&raw_audit)
+        .map_err(|e| format!("Unable to parse the degree audit: {e}"))
+}
+
+/// Runs the audit flow against recorded DARS pages.
+async fn replay(dir: &str) -> Result<DegreeAudit, String> {
+    let config = DegreeAuditConfig {
+        fixtures: Some(FixtureMode::Replay(PathBuf::from(dir))),
+        ..Default::default()
+    };
+    let client = DegreeAuditClient::with_config(config, Arc::new(AuditCacheState::new()))
+        .map_err(|e| e.to_string())?;
+
+    client
+        .get_or_create_audit("replay", true, CachePolicy::FreshOnly)
+        .await
+        .map(|fetch| fetch.audit)
+        .map_err(|e| format!("Unable to replay the degree audit: {e}"))
+}
+
+/// Runs `webreg-audit`.
+///
+/// # Parameters
+/// - `args`: The parsed arguments.
+///
+/// # Returns
+/// The exit code.
+pub async fn run(args: CliArg) -> ExitCode {
+    let (source, format) = args.command.source_and_format();
+
+    let audit = match source {
+        AuditSource::File(path) => parse_file(path),
+        AuditSource::Replay(dir) => replay(dir).await,
+    };
+    let audit = match audit {
+        Ok(audit) => audit,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let requirements_config =
+        RequirementsConfig::load_from_directory(Path::new("requirements_config"))
+            .unwrap_or_default();
+    let progress =
+        match DegreeProgressProcessor::new(requirements_config).compute_degree_progress(&audit) {
+            Ok(progress) => progress,
+            Err(e) => {
+                eprintln!("Unable to compute degree progress: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+
+    match format {
+        OutputFormat::Json => {
+            let output = json!({ "audit": audit, "progress": progress });
+            println!("{}", serde_json::to_string_pretty(&output).unwrap());
+        }
+        OutputFormat::Summary => {
+            println!(
+                "Student: {} ({})",
+                audit.student_info.name.as_deref().unwrap_or("unknown"),
+                audit
+                    .student_info
+                    .major
+                    .as_deref()
+                    .unwrap_or("unknown major")
+            );
+            println!(
+                "Units: {:.1} completed ({:.1} transfer), {:.1} remaining",
+                progress.total_units_completed,
+                progress.transfer_units,
+                progress.total_units_remaining
+            );
+            println!(
+                "Upper-division units: {:.1}/{:.1}, units in residence: {:.1}/{:.1}",
+                progress.upper_division.completed,
+                progress.upper_division.required,
+                progress.residency.completed,
+                progress.residency.required
+            );
+
+            println!("Requirements ({}):", progress.requirements_summary.len());
+            for req in &progress.requirements_summary {
+                println!(
+                    "  [{:?}] {} - {:.1}/{:.1} units, {}/{} subrequirements",
+                    req.status,
+                    req.name,
+                    req.units_completed,
+                    req.units_required,
+                    req.completed_subrequirements,
+                    req.subrequirements_count
+                );
+            }
+
+            if let Some(version) = audit.report_version {
+                println!("Report version: {version:?}");
+            }
+            if !audit.parse_warnings.is_empty() {
+                println!("Parse warnings ({}):", audit.parse_warnings.len());
+                for warning in &audit.parse_warnings {
+                    println!(
+                        "  {} {}: {}",
+                        warning.kind,
+                        warning.block_id.as_deref().unwrap_or("(no id)"),
+                        warning.message
+                    );
+                }
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::error::ErrorKind;
+    use clap::CommandFactory;
+
+    fn parse(args: &[&str]) -> Result<CliArg, ErrorKind> {
+        CliArg::try_parse_from(std::iter::once("webreg-audit").chain(args.iter().copied()))
+            .map_err(|e| e.kind())
+    }
+
+    fn source_and_format(args: &[&str]) -> (String, OutputFormat) {
+        let args = parse(args).unwrap();
+        let (source, format) = args.command.source_and_format();
+        (format!("{source:?}"), format)
+    }
+
+    #[test]
+    fn test_parse_args() {
+        CliArg::command().debug_assert();
+
+        assert_eq!(
+            ("File(\"audit.html\")".to_owned(), OutputFormat::Summary),
+            source_and_format(&["parse", "audit.html"])
+        );
+        assert_eq!(
+            ("File(\"audit.html\")".to_owned(), OutputFormat::Json),
+            source_and_format(&["parse", "--json", "audit.html"])
+        );
+        assert_eq!(
+            ("Replay(\"capture\")".to_owned(), OutputFormat::Summary),
+            source_and_format(&["parse", "--replay", "capture"])
+        );
+        // The last of the two formats wins
+        assert_eq!(
+            ("File(\"a.html\")".to_owned(), OutputFormat::Summary),
+            source_and_format(&["parse", "--json", "--summary", "a.html"])
+        );
+
+        assert_eq!(Some(ErrorKind::DisplayHelp), parse(&["--help"]).err());
+        assert_eq!(
+            Some(ErrorKind::DisplayHelp),
+            parse(&["parse", "--help"]).err()
+        );
+        assert_eq!(
+            Some(ErrorKind::UnknownArgument),
+            parse(&["parse", "--verbose", "a.html"]).err()
+        );
+        assert_eq!(
+            Some(ErrorKind::MissingRequiredArgument),
+            parse(&["parse"]).err()
+        );
+        assert_eq!(
+            Some(ErrorKind::UnknownArgument),
+            parse(&["parse", "a.html", "b.html"]).err()
+        );
+        assert_eq!(
+            Some(ErrorKind::ArgumentConflict),
+            parse(&["parse", "a.html", "--replay", "c"]).err()
+        );
+        assert_eq!(
+            Some(ErrorKind::InvalidValue),
+            parse(&["parse", "--replay"]).err()
+        );
+        assert_eq!(
+            Some(ErrorKind::InvalidSubcommand),
+            parse(&["fetch", "audit.html"]).err()
+        );
+    }
+}
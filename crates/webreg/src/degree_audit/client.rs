@@ -7,12 +7,15 @@
 //! 4. Poll until job completes
 //! 5. Fetch read.html?id=... to get the audit HTML
 
-use super::cache::{AuditCacheState, SessionKey};
+use super::cache::{AuditCacheState, AuditFetch, CachePolicy, SessionKey};
 use super::error::DegreeAuditError;
+use super::fixtures::{FixtureMode, FixturePage, FlowFixtures};
 use super::job::{parse_newest_job, page_indicates_processing, AuditJob};
+use super::poll::{parse_poll_hint, PollStrategy, PollStrategyKind};
 use super::types::DegreeAudit;
 use super::{parse_degree_audit_html, DegreeAuditResponse};
-use rand::Rng;
+use crate::ingest;
+use crate::request_log::generate_request_id;
 use reqwest::header::{COOKIE, LOCATION};
 use reqwest::redirect::Policy;
 use reqwest::{Client, StatusCode};
@@ -36,12 +39,16 @@ pub struct DegreeAuditConfig {
     pub base_url: String,
     /// Maximum number of poll attempts
     pub max_poll_attempts: u32,
-    /// Base delay between polls (will use exponential backoff)
-    pub poll_interval_base: Duration,
-    /// Maximum total time to wait for job completion
+    /// How long to wait between polls (see [`super::poll`])
+    pub poll_strategy: Arc<dyn PollStrategy>,
+    /// Maximum total time to wait for job completion. Requests can ask to wait for less,
+    /// but not for more
     pub max_poll_timeout: Duration,
     /// User agent string
     pub user_agent: String,
+    /// Whether DARS pages are recorded to, or replayed from, disk (see
+    /// [`super::fixtures`])
+    pub fixtures: Option<FixtureMode>,
 }
 
 impl Default for DegreeAuditConfig {
@@ -49,14 +56,35 @@ impl Default for DegreeAuditConfig {
         Self {
             base_url: DARS_BASE_URL.to_string(),
             max_poll_attempts: 30,
-            poll_interval_base: Duration::from_millis(500),
+            poll_strategy: PollStrategyKind::default().build(Duration::from_millis(500)),
             max_poll_timeout: Duration::from_secs(120),
             user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36".to_string(),
+            fixtures: None,
         }
     }
 }
 
+impl DegreeAuditConfig {
+    /// Gets how long to wait for an audit, given how long a request asked to wait for.
+    ///
+    /// # Parameters
+    /// - `requested`: How long the request asked to wait for, if it did.
+    ///
+    /// # Returns
+    /// The requested time, clamped to between one second and `max_poll_timeout`, or
+    /// `max_poll_timeout` if nothing was requested.
+    pub fn poll_timeout(&self, requested: Option<Duration>) -> Duration {
+        requested.map_or(self.max_poll_timeout, |requested| {
+            requested.clamp(
+                Duration::from_secs(1).min(self.max_poll_timeout),
+                self.max_poll_timeout,
+            )
+        })
+    }
+}
+
 /// Client for fetching degree audits from UCSD's DARS system.
+#[derive(Clone)]
 pub struct DegreeAuditClient {
     /// HTTP client configured for manual redirects on create
     client_no_redirect: Client,
@@ -109,23 +137,48 @@ impl DegreeAuditClient {
         })
     }
 
-    /// Fetches the degree audit, using cache if available.
+    /// Gets how long to wait for an audit, given how long a request asked to wait for (see
+    /// [`DegreeAuditConfig::poll_timeout`]).
+    pub fn poll_timeout(&self, requested: Option<Duration>) -> Duration {
+        self.config.poll_timeout(requested)
+    }
+
+    /// Fetches the degree audit, using cache if available, waiting for up to the
+    /// configured `max_poll_timeout` for a new audit to be generated.
     ///
     /// This is the main entry point for getting a degree audit.
     ///
     /// # Arguments
     /// * `cookies` - The authentication cookies (from Puppeteer auth server)
     /// * `force_refresh` - If true, bypass cache and fetch fresh data
+    /// * `policy` - Whether an expired cached audit may be returned while a fresh one is
+    ///   fetched in the background
     ///
     /// # Returns
-    /// * `Ok(DegreeAudit)` - The parsed degree audit
+    /// * `Ok(AuditFetch)` - The parsed degree audit, and whether it's stale
     /// * `Err(DegreeAuditError)` - If the operation fails
     pub async fn get_or_create_audit(
         &self,
         cookies: &str,
         force_refresh: bool,
-    ) -> Result<DegreeAudit, DegreeAuditError> {
-        let correlation_id = generate_correlation_id();
+        policy: CachePolicy,
+    ) -> Result<AuditFetch, DegreeAuditError> {
+        self.get_or_create_audit_within(cookies, force_refresh, policy, None)
+            .await
+    }
+
+    /// Fetches the degree audit like [`Self::get_or_create_audit`], but waits for only as
+    /// long as the caller asked for a new audit to be generated, up to the configured
+    /// `max_poll_timeout`.
+    pub async fn get_or_create_audit_within(
+        &self,
+        cookies: &str,
+        force_refresh: bool,
+        policy: CachePolicy,
+        timeout: Option<Duration>,
+    ) -> Result<AuditFetch, DegreeAuditError> {
+        let timeout = self.poll_timeout(timeout);
+        let correlation_id = generate_request_id();
         let session_key = SessionKey::from_cookie(cookies);
 
         info!(
@@ -145,12 +198,26 @@ impl DegreeAuditClient {
 
         // Check cache first (unless force_refresh)
         if !force_refresh {
-            if let Some(cached) = self.cache_state.cache.get(&session_key) {
-                info!(
-                    correlation_id = %correlation_id,
-                    "Returning cached degree audit"
-                );
-                return Ok(cached);
+            match self.cache_state.cache.get_allowing_stale(&session_key) {
+                Some((audit, false)) => {
+                    info!(
+                        correlation_id = %correlation_id,
+                        "Returning cached degree audit"
+                    );
+                    return Ok(AuditFetch {
+                        audit,
+                        stale: false,
+                    });
+                }
+                Some((audit, true)) if policy == CachePolicy::StaleWhileRevalidate => {
+                    info!(
+                        correlation_id = %correlation_id,
+                        "Returning stale degree audit and refreshing in the background"
+                    );
+                    self.spawn_refresh(cookies.to_owned(), session_key, correlation_id);
+                    return Ok(AuditFetch { audit, stale: true });
+                }
+                _ => {}
             }
         }
 
@@ -165,17 +232,69 @@ impl DegreeAuditClient {
                     correlation_id = %correlation_id,
                     "Returning cached degree audit (post-lock)"
                 );
-                return Ok(cached);
+                return Ok(AuditFetch {
+                    audit: cached,
+                    stale: false,
+                });
             }
         }
 
+        self.fetch_and_cache(cookies, session_key, &correlation_id, timeout)
+            .await
+            .map(|audit| AuditFetch {
+                audit,
+                stale: false,
+            })
+    }
+
+    /// Refreshes a session's cached audit in the background. Nothing is done if the
+    /// session's audit is already being fetched.
+    fn spawn_refresh(&self, cookies: String, session_key: SessionKey, correlation_id: String) {
+        let client = self.clone();
+        tokio::spawn(async move {
+            let lock = client.cache_state.get_session_lock(&session_key);
+            let Ok(_guard) = lock.try_lock() else {
+                debug!(
+                    correlation_id = %correlation_id,
+                    "Degree audit is already being fetched, skipping background refresh"
+                );
+                return;
+            };
+
+            // Failures are already logged, and the stale audit stays cached until it's
+            // too old to serve
+            let _ = client
+                .fetch_and_cache(
+                    &cookies,
+                    session_key,
+                    &correlation_id,
+                    client.config.max_poll_timeout,
+                )
+                .await;
+        });
+    }
+
+    /// Runs the audit flow and caches the result. The caller must hold the session's lock.
+    async fn fetch_and_cache(
+        &self,
+        cookies: &str,
+        session_key: SessionKey,
+        correlation_id: &str,
+        timeout: Duration,
+    ) -> Result<DegreeAudit, DegreeAuditError> {
+        // Every audit triggered counts against the session's daily quota
+        self.cache_state.quota.try_consume(&session_key)?;
+
         // Execute the full audit flow
         let start = Instant::now();
-        let result = self.execute_audit_flow(cookies, &correlation_id).await;
+        let result = self
+            .execute_audit_flow(cookies, &session_key, correlation_id, timeout)
+            .await;
 
         match &result {
             Ok(audit) => {
                 self.cache_state.circuit_breaker.record_success();
+                self.cache_state.record_parse_warnings(audit);
                 self.cache_state.cache.insert(session_key, audit.clone());
                 info!(
                     correlation_id = %correlation_id,
@@ -203,14 +322,20 @@ impl DegreeAuditClient {
     async fn execute_audit_flow(
         &self,
         cookies: &str,
+        session_key: &SessionKey,
         correlation_id: &str,
+        timeout: Duration,
     ) -> Result<DegreeAudit, DegreeAuditError> {
+        let mut fixtures = FlowFixtures::start(self.config.fixtures.as_ref());
+
         // Step 1: Trigger audit creation
-        let list_url = self.trigger_create(cookies, correlation_id).await?;
+        let list_url = self
+            .trigger_create(cookies, correlation_id, &mut fixtures)
+            .await?;
 
         // Step 2: Discover job from list page
-        let job = self
-            .fetch_list_and_discover(&list_url, cookies, correlation_id)
+        let (job, poll_hint) = self
+            .fetch_list_and_discover(&list_url, cookies, correlation_id, &mut fixtures)
             .await?;
 
         // Step 3: Poll until ready (if not already complete)
@@ -222,12 +347,20 @@ impl DegreeAuditClient {
             );
             job.job_id
         } else {
-            self.poll_until_ready(job, cookies, correlation_id).await?
+            self.poll_until_ready(
+                job,
+                poll_hint,
+                timeout,
+                cookies,
+                correlation_id,
+                &mut fixtures,
+            )
+            .await?
         };
 
         // Step 4: Fetch the audit HTML
         let html = self
-            .fetch_audit_html(&ready_job_id, cookies, correlation_id)
+            .fetch_audit_html(&ready_job_id, cookies, correlation_id, &mut fixtures)
             .await?;
 
         // Step 5: Parse the HTML
@@ -237,6 +370,7 @@ impl DegreeAuditClient {
             url: format!("{}{}?id={}", self.config.base_url, READ_PATH, ready_job_id),
             html,
         };
+        self.cache_state.archive_raw(session_key, &raw_response);
 
         parse_degree_audit_html(&raw_response).map_err(|e| DegreeAuditError::ParseError {
             message: e.to_string(),
@@ -250,7 +384,12 @@ impl DegreeAuditClient {
         &self,
         cookies: &str,
         correlation_id: &str,
+        fixtures: &mut FlowFixtures,
     ) -> Result<String, DegreeAuditError> {
+        if let Some(list_url) = fixtures.replay(FixturePage::Create)? {
+            return Ok(list_url);
+        }
+
         let url = format!("{}{}", self.config.base_url, CREATE_PATH);
         info!(
             correlation_id = %correlation_id,
@@ -305,6 +444,7 @@ impl DegreeAuditClient {
                     format!("{}/{}", self.config.base_url, location)
                 };
 
+                fixtures.record(FixturePage::Create, &absolute_url);
                 Ok(absolute_url)
             }
             StatusCode::OK => {
@@ -313,7 +453,9 @@ impl DegreeAuditClient {
                     correlation_id = %correlation_id,
                     "Create returned 200 instead of redirect, using list.html directly"
                 );
-                Ok(format!("{}{}?autoPoll=true", self.config.base_url, LIST_PATH))
+                let list_url = format!("{}{}?autoPoll=true", self.config.base_url, LIST_PATH);
+                fixtures.record(FixturePage::Create, &list_url);
+                Ok(list_url)
             }
             status => Err(DegreeAuditError::UnexpectedResponse {
                 message: format!(
@@ -324,35 +466,44 @@ impl DegreeAuditClient {
         }
     }
 
-    /// Step 2: Fetches list.html and discovers the newest job.
+    /// Step 2: Fetches list.html and discovers the newest job, along with the interval
+    /// that the page asks to be polled at, if it says.
     async fn fetch_list_and_discover(
         &self,
         list_url: &str,
         cookies: &str,
         correlation_id: &str,
-    ) -> Result<AuditJob, DegreeAuditError> {
+        fixtures: &mut FlowFixtures,
+    ) -> Result<(AuditJob, Option<Duration>), DegreeAuditError> {
         info!(
             correlation_id = %correlation_id,
             url = %list_url,
             "Fetching job list"
         );
 
-        let response = self
-            .client_with_redirect
-            .get(list_url)
-            .header(COOKIE, cookies)
-            .send()
-            .await?;
-
-        self.check_session_valid(&response, correlation_id)?;
-
-        if !response.status().is_success() {
-            return Err(DegreeAuditError::UnexpectedResponse {
-                message: format!("list.html returned status {}", response.status()),
-            });
-        }
+        let html = match fixtures.replay(FixturePage::List)? {
+            Some(html) => html,
+            None => {
+                let response = self
+                    .client_with_redirect
+                    .get(list_url)
+                    .header(COOKIE, cookies)
+                    .send()
+                    .await?;
+
+                self.check_session_valid(&response, correlation_id)?;
+
+                if !response.status().is_success() {
+                    return Err(DegreeAuditError::UnexpectedResponse {
+                        message: format!("list.html returned status {}", response.status()),
+                    });
+                }
 
-        let html = response.text().await?;
+                let html = read_page(response).await?;
+                fixtures.record(FixturePage::List, &html);
+                html
+            }
+        };
 
         // Check if page indicates processing
         if page_indicates_processing(&html) {
@@ -370,19 +521,23 @@ impl DegreeAuditClient {
             "Discovered job from list"
         );
 
-        Ok(job)
+        Ok((job, parse_poll_hint(&html)))
     }
 
-    /// Step 3: Polls until the job is ready.
+    /// Step 3: Polls until the job is ready, for up to `timeout`.
     async fn poll_until_ready(
         &self,
         initial_job: AuditJob,
+        initial_hint: Option<Duration>,
+        timeout: Duration,
         cookies: &str,
         correlation_id: &str,
+        fixtures: &mut FlowFixtures,
     ) -> Result<String, DegreeAuditError> {
         let start = Instant::now();
         let mut attempts = 0u32;
         let mut current_job = initial_job;
+        let mut poll_hint = initial_hint;
 
         info!(
             correlation_id = %correlation_id,
@@ -419,49 +574,40 @@ impl DegreeAuditClient {
                 });
             }
 
-            if start.elapsed() > self.config.max_poll_timeout {
+            if start.elapsed() > timeout {
                 return Err(DegreeAuditError::PollTimeout {
                     attempts,
                     elapsed_secs: start.elapsed().as_secs_f64(),
                 });
             }
 
-            // Calculate delay with exponential backoff and jitter
-            let delay = self.calculate_poll_delay(attempts);
+            let delay = self.config.poll_strategy.next_delay(attempts, poll_hint);
             debug!(
                 correlation_id = %correlation_id,
                 attempt = attempts,
                 delay_ms = delay.as_millis() as u64,
                 "Waiting before next poll"
             );
-            tokio::time::sleep(delay).await;
+            // Replayed pages don't need to be waited for
+            if !fixtures.is_replay() {
+                tokio::time::sleep(delay).await;
+            }
 
             // Re-fetch list page
             let list_url = format!("{}{}?autoPoll=true", self.config.base_url, LIST_PATH);
-            current_job = self
-                .fetch_list_and_discover(&list_url, cookies, correlation_id)
+            (current_job, poll_hint) = self
+                .fetch_list_and_discover(&list_url, cookies, correlation_id, fixtures)
                 .await?;
         }
     }
 
-    /// Calculates poll delay with exponential backoff and jitter.
-    fn calculate_poll_delay(&self, attempt: u32) -> Duration {
-        let base = self.config.poll_interval_base.as_millis() as u64;
-        // Exponential backoff: base * 2^min(attempt-1, 5)
-        let exponential = base * 2u64.pow(attempt.saturating_sub(1).min(5));
-        // Cap at 10 seconds
-        let capped = exponential.min(10_000);
-        // Add jitter: 0-20% of the delay
-        let jitter = rand::thread_rng().gen_range(0..=(capped / 5));
-        Duration::from_millis(capped + jitter)
-    }
-
     /// Step 4: Fetches the completed audit HTML from read.html.
     async fn fetch_audit_html(
         &self,
         job_id: &str,
         cookies: &str,
         correlation_id: &str,
+        fixtures: &mut FlowFixtures,
     ) -> Result<String, DegreeAuditError> {
         // URL-encode the job ID for the query parameter
         let encoded_job_id = urlencoding::encode(job_id);
@@ -476,22 +622,29 @@ impl DegreeAuditClient {
             "Fetching audit report"
         );
 
-        let response = self
-            .client_with_redirect
-            .get(&url)
-            .header(COOKIE, cookies)
-            .send()
-            .await?;
-
-        self.check_session_valid(&response, correlation_id)?;
-
-        if !response.status().is_success() {
-            return Err(DegreeAuditError::UnexpectedResponse {
-                message: format!("read.html returned status {}", response.status()),
-            });
-        }
+        let html = match fixtures.replay(FixturePage::Read)? {
+            Some(html) => html,
+            None => {
+                let response = self
+                    .client_with_redirect
+                    .get(&url)
+                    .header(COOKIE, cookies)
+                    .send()
+                    .await?;
+
+                self.check_session_valid(&response, correlation_id)?;
+
+                if !response.status().is_success() {
+                    return Err(DegreeAuditError::UnexpectedResponse {
+                        message: format!("read.html returned status {}", response.status()),
+                    });
+                }
 
-        let html = response.text().await?;
+                let html = read_page(response).await?;
+                fixtures.record(FixturePage::Read, &html);
+                html
+            }
+        };
 
         // Basic validation that we got an audit page
         if html.len() < 1000 {
@@ -576,20 +729,20 @@ mod urlencoding {
     }
 }
 
-/// Generates a unique correlation ID for request tracing.
-fn generate_correlation_id() -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_micros();
-    let random: u32 = rand::thread_rng().gen();
-    format!("{:x}-{:08x}", timestamp & 0xFFFFFFFF, random)
+/// Reads a DARS page, rejecting it early if it's too large to parse.
+async fn read_page(response: reqwest::Response) -> Result<String, DegreeAuditError> {
+    if let Some(len) = response.content_length() {
+        ingest::check_size(len as usize)?;
+    }
+
+    Ok(ingest::decode(&response.bytes().await?)?)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::degree_audit::quota::AuditQuota;
+    use crate::degree_audit::tests::audit;
 
     #[test]
     fn test_url_encoding() {
@@ -604,12 +757,71 @@ mod tests {
         let cache_state = Arc::new(AuditCacheState::new());
         let client = DegreeAuditClient::new(cache_state).unwrap();
 
-        let d1 = client.calculate_poll_delay(1);
-        let d2 = client.calculate_poll_delay(2);
-        let d3 = client.calculate_poll_delay(3);
+        let d1 = client.config.poll_strategy.next_delay(1, None);
+        let d2 = client.config.poll_strategy.next_delay(2, None);
+        let d3 = client.config.poll_strategy.next_delay(3, None);
 
         // Each should be roughly double (with jitter)
         assert!(d2 > d1);
         assert!(d3 > d2);
     }
+
+    #[tokio::test]
+    async fn test_stale_while_revalidate() {
+        // With no quota, every fetch fails before reaching DARS
+        let cache_state = Arc::new(AuditCacheState::new().with_quota(AuditQuota::in_memory(0)));
+        let client = DegreeAuditClient::new(cache_state.clone()).unwrap();
+        let key = SessionKey::from_cookie("session123");
+        cache_state
+            .cache
+            .insert_with_ttl(key.clone(), audit("a1"), Duration::ZERO);
+
+        // Only callers that allow it get the stale audit; the rest wait for a fresh one
+        let fetch =
+            |policy, force_refresh| client.get_or_create_audit("session123", force_refresh, policy);
+        assert!(matches!(
+            fetch(CachePolicy::FreshOnly, false).await,
+            Err(DegreeAuditError::AuditQuotaExceeded { .. })
+        ));
+        let stale = fetch(CachePolicy::StaleWhileRevalidate, false)
+            .await
+            .unwrap();
+        assert_eq!(("a1", true), (stale.audit.audit_id.as_str(), stale.stale));
+        assert!(fetch(CachePolicy::StaleWhileRevalidate, true)
+            .await
+            .is_err());
+
+        // A failed refresh leaves the stale audit to be served
+        tokio::task::yield_now().await;
+        assert!(cache_state.cache.get_allowing_stale(&key).is_some());
+
+        cache_state.cache.insert(key, audit("a2"));
+        let fresh = fetch(CachePolicy::StaleWhileRevalidate, false)
+            .await
+            .unwrap();
+        assert_eq!(("a2", false), (fresh.audit.audit_id.as_str(), fresh.stale));
+    }
+
+    #[tokio::test]
+    async fn test_background_refresh_skipped_while_fetching() {
+        let cache_state = Arc::new(AuditCacheState::new().with_quota(AuditQuota::in_memory(1)));
+        let client = DegreeAuditClient::new(cache_state.clone()).unwrap();
+        let key = SessionKey::from_cookie("session123");
+        cache_state
+            .cache
+            .insert_with_ttl(key.clone(), audit("a1"), Duration::ZERO);
+
+        // The session's audit is already being fetched, so the refresh doesn't start another
+        let lock = cache_state.get_session_lock(&key);
+        let _guard = lock.lock().await;
+        let stale = client
+            .get_or_create_audit("session123", false, CachePolicy::StaleWhileRevalidate)
+            .await
+            .unwrap();
+        assert!(stale.stale);
+        for _ in 0..3 {
+            tokio::task::yield_now().await;
+        }
+        assert!(cache_state.quota.usage_today().is_empty());
+    }
 }
@@ -0,0 +1,271 @@
+//! Requirement gap reports across a cohort of students in the same major.
+//!
+//! Departments planning which courses to offer want to know which subrequirements their
+//! students are still missing, and which courses would help the most students finish one.
+
+use super::types::{DegreeAudit, RequirementStatus};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A subrequirement that some students in the cohort haven't completed.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SubrequirementGap {
+    pub requirement: String,
+    pub subrequirement: String,
+    /// The number of students who haven't completed the subrequirement
+    pub incomplete_students: usize,
+    /// The share of the cohort that hasn't completed the subrequirement, from 0 to 1
+    pub incomplete_share: f32,
+}
+
+/// A course that would count towards an incomplete subrequirement for some students.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct CourseDemand {
+    /// The course (e.g., `MATH 170A`)
+    pub course: String,
+    /// The number of students for whom the course counts towards a subrequirement they
+    /// haven't completed
+    pub students_unblocked: usize,
+}
+
+/// Which subrequirements a cohort is missing and which courses would help the most.
+#[derive(Debug, Clone, Serialize)]
+pub struct GapReport {
+    pub major: String,
+    /// The number of students in the cohort
+    pub students: usize,
+    /// The most commonly incomplete subrequirements, most common first
+    pub subrequirements: Vec<SubrequirementGap>,
+    /// The courses that would unblock the most students, most students first
+    pub courses: Vec<CourseDemand>,
+}
+
+/// Builds a gap report for the students in one major.
+///
+/// # Parameters
+/// - `major`: The major code (e.g., `MA30`).
+/// - `audits`: The audits of every student that could be in the cohort. Students in other
+///   majors are ignored.
+/// - `limit`: The largest number of subrequirements and courses to include.
+///
+/// # Returns
+/// The report.
+pub fn build_gap_report(major: &str, audits: &[DegreeAudit], limit: usize) -> GapReport {
+    let cohort: Vec<_> = audits
+        .iter()
+        .filter(|a| {
+            a.student_info
+                .major
+                .as_deref()
+                .is_some_and(|m| m.trim().eq_ignore_ascii_case(major.trim()))
+        })
+        .collect();
+
+    let mut gaps: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    let mut demand: BTreeMap<String, usize> = BTreeMap::new();
+    for audit in &cohort {
+        let mut unblocked = BTreeSet::new();
+        for req in &audit.requirements {
+            for sub in &req.subrequirements {
+                if matches!(
+                    sub.status,
+                    RequirementStatus::Complete | RequirementStatus::NotApplicable
+                ) {
+                    continue;
+                }
+
+                *gaps.entry((&req.name, &sub.title)).or_default() += 1;
+
+                let taken: BTreeSet<_> = sub
+                    .completed_courses
+                    .iter()
+                    .map(|c| c.course_code.as_str())
+                    .collect();
+                let eligible = sub
+                    .eligible_courses
+                    .iter()
+                    .chain(sub.category_groups.iter().flat_map(|g| &g.courses));
+                unblocked.extend(
+                    eligible
+                        .map(|c| c.full_code.as_str())
+                        .filter(|c| !taken.contains(c)),
+                );
+            }
+        }
+
+        // Each student counts once per course, even if it fits several subrequirements
+        for course in unblocked {
+            *demand.entry(course.to_owned()).or_default() += 1;
+        }
+    }
+
+    let mut subrequirements: Vec<_> = gaps
+        .into_iter()
+        .map(|((requirement, subrequirement), count)| SubrequirementGap {
+            requirement: requirement.to_owned(),
+            subrequirement: subrequirement.to_owned(),
+            incomplete_students: count,
+            incomplete_share: count as f32 / cohort.len() as f32,
+        })
+        .collect();
+    // The maps are ordered by name, and sorting is stable, so ties stay in name order
+    subrequirements.sort_by_key(|s| Reverse(s.incomplete_students));
+    subrequirements.truncate(limit);
+
+    let mut courses: Vec<_> = demand
+        .into_iter()
+        .map(|(course, students_unblocked)| CourseDemand {
+            course,
+            students_unblocked,
+        })
+        .collect();
+    courses.sort_by_key(|c| Reverse(c.students_unblocked));
+    courses.truncate(limit);
+
+    GapReport {
+        major: major.trim().to_uppercase(),
+        students: cohort.len(),
+        subrequirements,
+        courses,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::degree_audit::types::{
+        CourseRequirement, CourseStatus, EligibleCourse, Requirement, StudentInfo, Subrequirement,
+    };
+
+    fn course(code: &str) -> EligibleCourse {
+        let (department, course_number) = code.split_once(' ').unwrap();
+        EligibleCourse {
+            department: department.to_string(),
+            course_number: course_number.to_string(),
+            full_code: code.to_string(),
+        }
+    }
+
+    fn subreq(
+        title: &str,
+        status: RequirementStatus,
+        eligible: &[&str],
+        taken: &[&str],
+    ) -> Subrequirement {
+        Subrequirement {
+            id: title.to_string(),
+            title: title.to_string(),
+            required_units: 8.0,
+            required_courses: None,
+            needs: None,
+            courses_needed: None,
+            units_completed: 0.0,
+            units_remaining: 8.0,
+            status,
+            eligible_courses: eligible.iter().map(|c| course(c)).collect(),
+            completed_courses: taken
+                .iter()
+                .map(|c| CourseRequirement {
+                    course_code: c.to_string(),
+                    title: None,
+                    units: Some(4.0),
+                    grade: Some("A".to_string()),
+                    term: None,
+                    status: CourseStatus::Completed,
+                    source: None,
+                })
+                .collect(),
+            category_groups: vec![],
+        }
+    }
+
+    fn audit(major: &str, subrequirements: Vec<Subrequirement>) -> DegreeAudit {
+        DegreeAudit {
+            audit_id: String::new(),
+            student_info: StudentInfo {
+                student_id: None,
+                name: None,
+                major: Some(major.to_string()),
+                college: None,
+            },
+            requirements: vec![Requirement {
+                category: "Major".to_string(),
+                name: "Upper Division".to_string(),
+                status: RequirementStatus::InProgress,
+                credits_required: None,
+                credits_completed: None,
+                courses: vec![],
+                subrequirements,
+            }],
+            scraped_at: String::new(),
+            parse_warnings: vec![],
+            report_version: None,
+        }
+    }
+
+    #[test]
+    fn test_gap_report() {
+        let audits = vec![
+            audit(
+                "MA30",
+                vec![
+                    subreq(
+                        "Analysis",
+                        RequirementStatus::InProgress,
+                        &["MATH 140A", "MATH 140B"],
+                        &["MATH 140A"],
+                    ),
+                    subreq(
+                        "Algebra",
+                        RequirementStatus::NotStarted,
+                        &["MATH 100A"],
+                        &[],
+                    ),
+                ],
+            ),
+            audit(
+                "ma30",
+                vec![
+                    subreq(
+                        "Analysis",
+                        RequirementStatus::NotStarted,
+                        &["MATH 140A", "MATH 140B"],
+                        &[],
+                    ),
+                    subreq(
+                        "Algebra",
+                        RequirementStatus::Complete,
+                        &["MATH 100A"],
+                        &["MATH 100A"],
+                    ),
+                ],
+            ),
+            audit(
+                "CS26",
+                vec![subreq(
+                    "Algebra",
+                    RequirementStatus::NotStarted,
+                    &["MATH 100A"],
+                    &[],
+                )],
+            ),
+        ];
+
+        let report = build_gap_report("MA30", &audits, 10);
+        assert_eq!(2, report.students);
+        assert_eq!("Analysis", report.subrequirements[0].subrequirement);
+        assert_eq!(2, report.subrequirements[0].incomplete_students);
+        assert_eq!(0.5, report.subrequirements[1].incomplete_share);
+
+        let demand: Vec<_> = report
+            .courses
+            .iter()
+            .map(|c| (c.course.as_str(), c.students_unblocked))
+            .collect();
+        assert_eq!(
+            vec![("MATH 140B", 2), ("MATH 100A", 1), ("MATH 140A", 1)],
+            demand
+        );
+    }
+}
@@ -1,14 +1,28 @@
 /// Configuration system for college and major requirements
+use super::grades::GradeScale;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
+/// The units that every UCSD student needs to graduate, unless their major or college
+/// says otherwise
+pub const DEFAULT_TOTAL_UNITS: f32 = 180.0;
+/// The upper-division units that every UCSD student needs to graduate
+pub const DEFAULT_UPPER_DIVISION_UNITS: f32 = 60.0;
+/// The units that every UCSD student needs to earn in residence (i.e., not from transfer
+/// or exam credit)
+pub const DEFAULT_RESIDENCY_UNITS: f32 = 36.0;
+
 /// Top-level requirements configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequirementsConfig {
     pub colleges: HashMap<String, CollegeRequirements>,
     pub majors: HashMap<String, MajorRequirements>,
+    /// The grade scale, which is set from the scraper's configuration rather than the
+    /// requirements directory
+    #[serde(skip)]
+    pub grade_scale: GradeScale,
 }
 
 /// College-specific requirements (e.g., Warren, Revelle, etc.)
@@ -16,6 +30,8 @@ pub struct RequirementsConfig {
 pub struct CollegeRequirements {
     pub college_code: String,
     pub college_name: String,
+    #[serde(default)]
+    pub units: UnitRequirements,
     pub requirements: Vec<RequirementCategory>,
 }
 
@@ -24,9 +40,32 @@ pub struct CollegeRequirements {
 pub struct MajorRequirements {
     pub major_code: String,
     pub major_name: String,
+    #[serde(default)]
+    pub units: UnitRequirements,
     pub requirements: Vec<RequirementCategory>,
 }
 
+/// Unit minimums that a major or college sets. Minimums that are left out fall back to
+/// the university's (or the other's, if it sets them)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UnitRequirements {
+    #[serde(default)]
+    pub total_units: Option<f32>,
+    #[serde(default)]
+    pub upper_division_units: Option<f32>,
+    #[serde(default)]
+    pub residency_units: Option<f32>,
+}
+
+/// The unit minimums that apply to a student, after combining their major's and
+/// college's with the university's
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnitMinimums {
+    pub total_units: f32,
+    pub upper_division_units: f32,
+    pub residency_units: f32,
+}
+
 /// Category of requirements (e.g., "Lower Division", "Upper Division")
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequirementCategory {
@@ -91,7 +130,11 @@ impl RequirementsConfig {
             }
         }
 
-        Ok(RequirementsConfig { colleges, majors })
+        Ok(RequirementsConfig {
+            colleges,
+            majors,
+            grade_scale: GradeScale::default(),
+        })
     }
 
     /// Creates an empty configuration
@@ -99,6 +142,7 @@ impl RequirementsConfig {
         RequirementsConfig {
             colleges: HashMap::new(),
             majors: HashMap::new(),
+            grade_scale: GradeScale::default(),
         }
     }
 
@@ -111,6 +155,45 @@ impl RequirementsConfig {
     pub fn get_major(&self, major_code: &str) -> Option<&MajorRequirements> {
         self.majors.get(major_code)
     }
+
+    /// Gets the unit minimums for a student's program. Where both the major and the college
+    /// set a minimum, the stricter one applies
+    ///
+    /// # Arguments
+    /// * `major_code` - The student's major, if known
+    /// * `college_code` - The student's college, if known
+    pub fn unit_minimums(
+        &self,
+        major_code: Option<&str>,
+        college_code: Option<&str>,
+    ) -> UnitMinimums {
+        let sources: Vec<&UnitRequirements> = [
+            major_code.and_then(|c| self.get_major(c)).map(|m| &m.units),
+            college_code
+                .and_then(|c| self.get_college(c))
+                .map(|c| &c.units),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        let strictest = |field: fn(&UnitRequirements) -> Option<f32>, default: f32| {
+            sources
+                .iter()
+                .filter_map(|units| field(units))
+                .reduce(f32::max)
+                .unwrap_or(default)
+        };
+
+        UnitMinimums {
+            total_units: strictest(|u| u.total_units, DEFAULT_TOTAL_UNITS),
+            upper_division_units: strictest(
+                |u| u.upper_division_units,
+                DEFAULT_UPPER_DIVISION_UNITS,
+            ),
+            residency_units: strictest(|u| u.residency_units, DEFAULT_RESIDENCY_UNITS),
+        }
+    }
 }
 
 impl Default for RequirementsConfig {
@@ -0,0 +1,298 @@
+//! Elective suggestions based on the courses a student has already completed.
+//!
+//! When enough other registered students have audits cached, electives are suggested by
+//! collaborative filtering: the students whose completed courses overlap the most with this
+//! student's are found, and the courses they completed that this student hasn't are ranked
+//! by how similar the students who took them are. Only courses taken by several similar
+//! students are suggested, so no suggestion can be traced back to one student.
+//!
+//! Otherwise, upper-division courses from the catalog are suggested from the departments of
+//! the student's completed upper-division work, and from departments that those courses are
+//! cross-listed with.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use super::processor::is_upper_division;
+use super::types::{CourseStatus, DegreeAudit};
+use super::GradeScale;
+use crate::db::{normalize_course_code, CatalogCourse};
+
+/// The fewest similar students needed to suggest electives from what they've taken.
+pub const MIN_SIMILAR_STUDENTS: usize = 5;
+/// The fewest similar students who must have taken a course for it to be suggested.
+const MIN_COURSE_SUPPORT: usize = 3;
+/// The most similar students that suggestions are drawn from.
+const MAX_NEIGHBORS: usize = 25;
+/// How much a department that the student's courses are cross-listed with counts, compared
+/// to the department of the courses themselves.
+const CROSS_LISTED_WEIGHT: f32 = 0.5;
+
+/// How a suggestion was made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionMethod {
+    SimilarStudents,
+    DepartmentAdjacency,
+}
+
+/// A suggested elective.
+#[derive(Debug, Clone, Serialize)]
+pub struct ElectiveSuggestion {
+    /// The course (e.g., `CSE 152A`)
+    pub course: String,
+    /// How strongly the course is suggested; only comparable between suggestions made the
+    /// same way
+    pub score: f32,
+    /// The number of similar students who took the course, for suggestions from similar
+    /// students
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similar_students: Option<usize>,
+    /// The department that the course was suggested from, for suggestions by department
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub department: Option<String>,
+}
+
+/// Electives suggested for a student.
+#[derive(Debug, Clone, Serialize)]
+pub struct ElectiveSuggestions {
+    pub method: SuggestionMethod,
+    /// The number of other students whose completed courses overlap with this student's
+    pub similar_students: usize,
+    pub suggestions: Vec<ElectiveSuggestion>,
+}
+
+/// Gets the courses that a student has completed with a passing grade (or transferred in),
+/// and every course they've taken, are taking, or plan to take.
+fn course_history(audit: &DegreeAudit, scale: &GradeScale) -> (BTreeSet<String>, BTreeSet<String>) {
+    let courses = audit.requirements.iter().flat_map(|r| {
+        r.courses
+            .iter()
+            .chain(r.subrequirements.iter().flat_map(|s| &s.completed_courses))
+    });
+
+    let (mut completed, mut seen) = (BTreeSet::new(), BTreeSet::new());
+    for c in courses {
+        let code = normalize_course_code(&c.course_code);
+        let passed = match (&c.status, &c.grade) {
+            (CourseStatus::Transfer, _) => true,
+            (_, Some(grade)) => scale.is_passing_grade(grade),
+            _ => false,
+        };
+        if passed {
+            completed.insert(code.clone());
+        }
+        seen.insert(code);
+    }
+
+    (completed, seen)
+}
+
+/// Suggests electives for a student.
+///
+/// # Parameters
+/// - `student`: The student's audit.
+/// - `others`: Other students' audits. The student's own audit is skipped if it's included.
+/// - `catalog`: The courses in the catalog, used when there aren't enough similar students.
+/// - `limit`: The most suggestions to make.
+/// - `scale`: The grade scale that decides which courses were passed.
+///
+/// # Returns
+/// The suggestions, strongest first.
+pub fn suggest_electives(
+    student: &DegreeAudit,
+    others: &[DegreeAudit],
+    catalog: &[CatalogCourse],
+    limit: usize,
+    scale: &GradeScale,
+) -> ElectiveSuggestions {
+    let (completed, seen) = course_history(student, scale);
+
+    // Jaccard similarity of completed courses, most similar first
+    let mut neighbors: Vec<(f32, BTreeSet<String>)> = others
+        .iter()
+        .filter(|a| a.audit_id.is_empty() || a.audit_id != student.audit_id)
+        .filter_map(|a| {
+            let (theirs, _) = course_history(a, scale);
+            let shared = completed.intersection(&theirs).count();
+            let union = completed.union(&theirs).count();
+            (shared > 0).then(|| (shared as f32 / union as f32, theirs))
+        })
+        .collect();
+    neighbors.sort_by(|a, b| b.0.total_cmp(&a.0));
+    neighbors.truncate(MAX_NEIGHBORS);
+
+    let mut suggestions = if neighbors.len() >= MIN_SIMILAR_STUDENTS {
+        let mut scores: BTreeMap<&str, (f32, usize)> = BTreeMap::new();
+        for (similarity, theirs) in &neighbors {
+            for course in theirs.iter().filter(|c| !seen.contains(*c)) {
+                let entry = scores.entry(course).or_default();
+                entry.0 += similarity;
+                entry.1 += 1;
+            }
+        }
+
+        scores
+            .into_iter()
+            .filter(|(_, (_, support))| *support >= MIN_COURSE_SUPPORT)
+            .map(|(course, (score, support))| ElectiveSuggestion {
+                course: course.to_owned(),
+                score,
+                similar_students: Some(support),
+                department: None,
+            })
+            .collect::<Vec<_>>()
+    } else {
+        vec![]
+    };
+
+    let method = if suggestions.is_empty() {
+        suggestions = by_department(&completed, &seen, catalog);
+        SuggestionMethod::DepartmentAdjacency
+    } else {
+        SuggestionMethod::SimilarStudents
+    };
+
+    // The suggestions are in course order, and sorting is stable, so ties stay that way
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    suggestions.truncate(limit);
+    ElectiveSuggestions {
+        method,
+        similar_students: neighbors.len(),
+        suggestions,
+    }
+}
+
+/// Suggests upper-division courses from the departments of the student's completed
+/// upper-division courses, weighted by how many of them are in each department, and from
+/// departments that those courses are cross-listed with.
+fn by_department(
+    completed: &BTreeSet<String>,
+    seen: &BTreeSet<String>,
+    catalog: &[CatalogCourse],
+) -> Vec<ElectiveSuggestion> {
+    let department = |code: &str| code.split(' ').next().unwrap_or_default().to_owned();
+
+    let mut weights: BTreeMap<String, f32> = BTreeMap::new();
+    for code in completed.iter().filter(|c| is_upper_division(c)) {
+        *weights.entry(department(code)).or_default() += 1.0;
+
+        let cross_listings = catalog
+            .iter()
+            .filter(|c| &c.code() == code)
+            .flat_map(|c| &c.cross_listings);
+        for listed in cross_listings {
+            *weights.entry(department(listed)).or_default() += CROSS_LISTED_WEIGHT;
+        }
+    }
+
+    catalog
+        .iter()
+        .filter(|c| is_upper_division(&c.code()) && !seen.contains(&c.code()))
+        .filter_map(|c| {
+            let score = *weights.get(&c.subject)?;
+            Some(ElectiveSuggestion {
+                course: c.code(),
+                score,
+                similar_students: None,
+                department: Some(c.subject.clone()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::degree_audit::types::{
+        CourseRequirement, Requirement, RequirementStatus, StudentInfo,
+    };
+
+    fn audit(id: &str, courses: &[&str]) -> DegreeAudit {
+        DegreeAudit {
+            audit_id: id.to_string(),
+            student_info: StudentInfo {
+                student_id: None,
+                name: None,
+                major: None,
+                college: None,
+            },
+            requirements: vec![Requirement {
+                category: "Major".to_string(),
+                name: "Major".to_string(),
+                status: RequirementStatus::InProgress,
+                credits_required: None,
+                credits_completed: None,
+                courses: courses
+                    .iter()
+                    .map(|c| CourseRequirement {
+                        course_code: c.to_string(),
+                        title: None,
+                        units: Some(4.0),
+                        grade: Some("A".to_string()),
+                        term: None,
+                        status: CourseStatus::Completed,
+                        source: None,
+                    })
+                    .collect(),
+                subrequirements: vec![],
+            }],
+            scraped_at: String::new(),
+            parse_warnings: vec![],
+            report_version: None,
+        }
+    }
+
+    fn catalog_course(code: &str, cross_listings: &[&str]) -> CatalogCourse {
+        let (subject, number) = code.split_once(' ').unwrap();
+        CatalogCourse {
+            subject: subject.to_string(),
+            number: number.to_string(),
+            title: String::new(),
+            description: String::new(),
+            min_units: Some(4.0),
+            max_units: Some(4.0),
+            prerequisites: None,
+            cross_listings: cross_listings.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_suggest_electives() {
+        let student = audit("me", &["CSE 100", "CSE 101", "MATH 170A"]);
+        let catalog = vec![
+            catalog_course("CSE 100", &["MATH 176"]),
+            catalog_course("CSE 151A", &[]),
+            catalog_course("MATH 180A", &[]),
+            catalog_course("MUS 101", &[]),
+            catalog_course("CSE 11", &[]),
+        ];
+
+        // Without enough similar students, courses are suggested by department
+        let suggestions = suggest_electives(&student, &[], &catalog, 10, &GradeScale::default());
+        assert_eq!(SuggestionMethod::DepartmentAdjacency, suggestions.method);
+        let courses: Vec<_> = suggestions
+            .suggestions
+            .iter()
+            .map(|s| s.course.as_str())
+            .collect();
+        assert_eq!(vec!["CSE 151A", "MATH 180A"], courses);
+        assert_eq!(2.0, suggestions.suggestions[0].score);
+        assert_eq!(1.5, suggestions.suggestions[1].score);
+
+        // With them, by what they took; a course only one of them took isn't suggested
+        let mut others: Vec<_> = (0..5)
+            .map(|i| audit(&i.to_string(), &["CSE 100", "CSE 101", "CSE 152A"]))
+            .collect();
+        others.push(audit("x", &["CSE 100", "CSE 167"]));
+        others.push(audit("me", &["CSE 100", "CSE 101", "MATH 170A"]));
+        let suggestions =
+            suggest_electives(&student, &others, &catalog, 10, &GradeScale::default());
+        assert_eq!(SuggestionMethod::SimilarStudents, suggestions.method);
+        assert_eq!(6, suggestions.similar_students);
+        assert_eq!(1, suggestions.suggestions.len());
+        assert_eq!("CSE 152A", suggestions.suggestions[0].course);
+        assert_eq!(Some(5), suggestions.suggestions[0].similar_students);
+    }
+}
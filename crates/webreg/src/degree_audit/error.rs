@@ -1,5 +1,7 @@
 //! Error types for the degree audit subsystem.
 
+use crate::error::WebregError;
+use crate::ingest::IngestError;
 use thiserror::Error;
 
 /// Errors that can occur during degree audit operations.
@@ -49,6 +51,19 @@ pub enum DegreeAuditError {
     #[error("Audit operation already in progress for this session")]
     OperationInProgress,
 
+    /// The session has triggered its daily quota of audits
+    #[error("Daily audit quota exceeded ({used} of {limit} used)")]
+    AuditQuotaExceeded { used: u32, limit: u32 },
+
+    /// A recorded page couldn't be replayed
+    #[error("Fixture error: {message}")]
+    Fixture { message: String },
+
+    /// The caller isn't allowed the audit that they asked for (e.g., they don't have a
+    /// cookie server of their own in multi-user mode)
+    #[error("Access denied: {message}")]
+    AccessDenied { message: String },
+
     /// Cookie fetch from auth server failed
     #[error("Failed to fetch cookies from auth server: {message}")]
     CookieFetchError { message: String },
@@ -65,12 +80,7 @@ impl DegreeAuditError {
 
     /// Returns true if this error is potentially transient and retryable.
     pub fn is_retryable(&self) -> bool {
-        matches!(
-            self,
-            DegreeAuditError::Network { .. }
-                | DegreeAuditError::PollTimeout { .. }
-                | DegreeAuditError::UnexpectedResponse { .. }
-        )
+        WebregError::from(self).is_retryable()
     }
 }
 
@@ -90,6 +100,14 @@ impl From<url::ParseError> for DegreeAuditError {
     }
 }
 
+impl From<IngestError> for DegreeAuditError {
+    fn from(err: IngestError) -> Self {
+        DegreeAuditError::ParseError {
+            message: err.to_string(),
+        }
+    }
+}
+
 impl From<std::io::Error> for DegreeAuditError {
     fn from(err: std::io::Error) -> Self {
         DegreeAuditError::Network {
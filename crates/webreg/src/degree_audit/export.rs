@@ -0,0 +1,405 @@
+//! A spreadsheet export of a degree audit, in the layout that advisors ask students to
+//! bring: a summary sheet, a sheet of every course taken, and one sheet per requirement
+//! category. Totals are written as values rather than formulas, so they read the same in
+//! any spreadsheet program.
+
+use std::collections::HashSet;
+
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+
+use crate::degree_audit::{
+    CourseRequirement, CourseStatus, DegreeAudit, DegreeProgress, RequirementStatus,
+};
+
+/// The longest sheet name that Excel allows.
+const MAX_SHEET_NAME_LEN: usize = 31;
+/// Characters that Excel doesn't allow in sheet names.
+const INVALID_SHEET_NAME_CHARS: [char; 7] = ['[', ']', ':', '*', '?', '/', '\\'];
+
+const SUMMARY_SHEET: &str = "Summary";
+const COURSES_SHEET: &str = "Completed Courses";
+
+/// The columns of a sheet that lists courses.
+const COURSE_COLUMNS: [(&str, f64); 7] = [
+    ("Course", 12.0),
+    ("Title", 36.0),
+    ("Term", 10.0),
+    ("Grade", 8.0),
+    ("Units", 8.0),
+    ("Status", 12.0),
+    ("Source", 24.0),
+];
+
+/// Renders the degree audit, along with the progress computed from it, as an XLSX
+/// workbook.
+///
+/// # Parameters
+/// - `audit`: The audit.
+/// - `progress`: The progress computed from the audit.
+///
+/// # Returns
+/// The workbook.
+pub fn render_workbook(
+    audit: &DegreeAudit,
+    progress: &DegreeProgress,
+) -> Result<Vec<u8>, XlsxError> {
+    let bold = Format::new().set_bold();
+    let units = Format::new().set_num_format("0.0");
+    let mut names = SheetNames::default();
+    let mut workbook = Workbook::new();
+
+    workbook.push_worksheet(summary_sheet(audit, progress, &mut names, &bold, &units)?);
+
+    // Every course taken, once each, in the order the audit lists them
+    let mut seen = HashSet::new();
+    let taken: Vec<&CourseRequirement> = audit
+        .requirements
+        .iter()
+        .flat_map(|r| {
+            r.courses
+                .iter()
+                .chain(r.subrequirements.iter().flat_map(|s| &s.completed_courses))
+        })
+        .filter(|c| {
+            matches!(
+                c.status,
+                CourseStatus::Completed | CourseStatus::InProgress | CourseStatus::Transfer
+            )
+        })
+        .filter(|c| seen.insert((c.course_code.clone(), c.term.clone())))
+        .collect();
+    let mut sheet = Worksheet::new();
+    sheet.set_name(names.unique(COURSES_SHEET))?;
+    write_course_header(&mut sheet, 0, &bold)?;
+    for (row, course) in taken.iter().enumerate() {
+        write_course(&mut sheet, row as u32 + 1, course, &units)?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    workbook.push_worksheet(sheet);
+
+    // One sheet per category, in the order the categories first appear
+    let mut categories: Vec<&str> = vec![];
+    for req in &audit.requirements {
+        if !categories.contains(&req.category.as_str()) {
+            categories.push(&req.category);
+        }
+    }
+    for category in categories {
+        let mut sheet = Worksheet::new();
+        sheet.set_name(names.unique(category))?;
+        let mut row = 0;
+        for req in audit.requirements.iter().filter(|r| r.category == category) {
+            let mut title = format!("{} ({})", req.name, requirement_status(&req.status));
+            if let (Some(done), Some(required)) = (req.credits_completed, req.credits_required) {
+                title.push_str(&format!(" - {done:.1}/{required:.1} units"));
+            }
+            sheet.write_string_with_format(row, 0, title, &bold)?;
+            row += 1;
+
+            if !req.courses.is_empty() {
+                write_course_header(&mut sheet, row, &bold)?;
+                row += 1;
+                for course in &req.courses {
+                    write_course(&mut sheet, row, course, &units)?;
+                    row += 1;
+                }
+            }
+
+            for sub in &req.subrequirements {
+                let mut title = format!(
+                    "{} ({}) - {:.1}/{:.1} units",
+                    sub.title,
+                    requirement_status(&sub.status),
+                    sub.units_completed,
+                    sub.required_units
+                );
+                if let Some(needs) = &sub.needs {
+                    title.push_str(&format!(", {}", needs.trim()));
+                }
+                sheet.write_string(row, 0, title)?;
+                row += 1;
+
+                if !sub.completed_courses.is_empty() {
+                    write_course_header(&mut sheet, row, &bold)?;
+                    row += 1;
+                    for course in &sub.completed_courses {
+                        write_course(&mut sheet, row, course, &units)?;
+                        row += 1;
+                    }
+                }
+            }
+
+            row += 1;
+        }
+        set_course_widths(&mut sheet)?;
+        workbook.push_worksheet(sheet);
+    }
+
+    workbook.save_to_buffer()
+}
+
+/// Lays out the summary sheet: who the audit is for, the overall totals, and each
+/// category's totals.
+fn summary_sheet(
+    audit: &DegreeAudit,
+    progress: &DegreeProgress,
+    names: &mut SheetNames,
+    bold: &Format,
+    units: &Format,
+) -> Result<Worksheet, XlsxError> {
+    let mut sheet = Worksheet::new();
+    sheet.set_name(names.unique(SUMMARY_SHEET))?;
+    sheet.set_column_width(0, 28)?;
+    for col in 1..=4 {
+        sheet.set_column_width(col, 14)?;
+    }
+
+    let student = &audit.student_info;
+    let mut row = 0;
+    for (label, value) in [
+        ("Name", &student.name),
+        ("Student ID", &student.student_id),
+        ("Major", &student.major),
+        ("College", &student.college),
+        ("Audit", &Some(audit.audit_id.clone())),
+        ("Fetched", &Some(audit.scraped_at.clone())),
+    ] {
+        if let Some(value) = value {
+            sheet.write_string_with_format(row, 0, label, bold)?;
+            sheet.write_string(row, 1, value)?;
+            row += 1;
+        }
+    }
+
+    row += 1;
+    sheet.write_string_with_format(row, 0, "Units", bold)?;
+    for (col, label) in ["Required", "Completed", "Remaining"].iter().enumerate() {
+        sheet.write_string_with_format(row, col as u16 + 1, *label, bold)?;
+    }
+    row += 1;
+    for (label, required, completed, remaining) in [
+        (
+            "Total",
+            progress.total_units_required,
+            progress.total_units_completed,
+            progress.total_units_remaining,
+        ),
+        (
+            "Upper-division",
+            progress.upper_division.required,
+            progress.upper_division.completed,
+            progress.upper_division.remaining,
+        ),
+        (
+            "In residence",
+            progress.residency.required,
+            progress.residency.completed,
+            progress.residency.remaining,
+        ),
+    ] {
+        sheet.write_string(row, 0, label)?;
+        for (col, value) in [required, completed, remaining].into_iter().enumerate() {
+            sheet.write_number_with_format(row, col as u16 + 1, value, units)?;
+        }
+        row += 1;
+    }
+    sheet.write_string(row, 0, "Transfer and exam credit")?;
+    sheet.write_number_with_format(row, 2, progress.transfer_units, units)?;
+    row += 1;
+    if let Some(gpa) = progress.gpa {
+        sheet.write_string(row, 0, "GPA")?;
+        sheet.write_number_with_format(row, 1, gpa, &Format::new().set_num_format("0.00"))?;
+        row += 1;
+    }
+
+    row += 1;
+    for (col, label) in [
+        "Category",
+        "Requirements",
+        "Complete",
+        "Units Required",
+        "Units Completed",
+    ]
+    .iter()
+    .enumerate()
+    {
+        sheet.write_string_with_format(row, col as u16, *label, bold)?;
+    }
+    row += 1;
+
+    let mut categories: Vec<(&str, usize, usize, f32, f32)> = vec![];
+    for req in &progress.requirements_summary {
+        let complete = matches!(req.status, RequirementStatus::Complete) as usize;
+        match categories.iter_mut().find(|c| c.0 == req.category) {
+            Some(c) => {
+                c.1 += 1;
+                c.2 += complete;
+                c.3 += req.units_required;
+                c.4 += req.units_completed;
+            }
+            None => categories.push((
+                &req.category,
+                1,
+                complete,
+                req.units_required,
+                req.units_completed,
+            )),
+        }
+    }
+    for (category, count, complete, required, completed) in categories {
+        sheet.write_string(row, 0, category)?;
+        sheet.write_number(row, 1, count as f64)?;
+        sheet.write_number(row, 2, complete as f64)?;
+        sheet.write_number_with_format(row, 3, required, units)?;
+        sheet.write_number_with_format(row, 4, completed, units)?;
+        row += 1;
+    }
+
+    if !progress.unknown_grades.is_empty() || !audit.parse_warnings.is_empty() {
+        row += 1;
+        sheet.write_string_with_format(row, 0, "Notes", bold)?;
+        row += 1;
+        for unknown in &progress.unknown_grades {
+            sheet.write_string(
+                row,
+                0,
+                format!(
+                    "{} has grade {}, which isn't on the grade scale and wasn't counted.",
+                    unknown.course_code, unknown.grade
+                ),
+            )?;
+            row += 1;
+        }
+        if !audit.parse_warnings.is_empty() {
+            sheet.write_string(
+                row,
+                0,
+                format!(
+                    "{} part(s) of the audit couldn't be read and aren't included.",
+                    audit.parse_warnings.len()
+                ),
+            )?;
+        }
+    }
+
+    Ok(sheet)
+}
+
+/// Writes the header of a list of courses.
+fn write_course_header(sheet: &mut Worksheet, row: u32, bold: &Format) -> Result<(), XlsxError> {
+    for (col, (label, _)) in COURSE_COLUMNS.iter().enumerate() {
+        sheet.write_string_with_format(row, col as u16, *label, bold)?;
+    }
+
+    set_course_widths(sheet)
+}
+
+/// Sizes the columns of a list of courses.
+fn set_course_widths(sheet: &mut Worksheet) -> Result<(), XlsxError> {
+    for (col, (_, width)) in COURSE_COLUMNS.iter().enumerate() {
+        sheet.set_column_width(col as u16, *width)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a course as a row of a list of courses.
+fn write_course(
+    sheet: &mut Worksheet,
+    row: u32,
+    course: &CourseRequirement,
+    units: &Format,
+) -> Result<(), XlsxError> {
+    sheet.write_string(row, 0, &course.course_code)?;
+    for (col, value) in [(1, &course.title), (2, &course.term), (3, &course.grade)] {
+        if let Some(value) = value {
+            sheet.write_string(row, col, value)?;
+        }
+    }
+    if let Some(course_units) = course.units {
+        sheet.write_number_with_format(row, 4, course_units, units)?;
+    }
+    let status = match course.status {
+        CourseStatus::Completed => "Completed",
+        CourseStatus::InProgress => "In progress",
+        CourseStatus::Planned => "Planned",
+        CourseStatus::Required => "Needed",
+        CourseStatus::Transfer => "Transfer",
+    };
+    sheet.write_string(row, 5, status)?;
+    if let Some(source) = &course.source {
+        sheet.write_string(row, 6, source)?;
+    }
+
+    Ok(())
+}
+
+/// Describes a requirement's status.
+fn requirement_status(status: &RequirementStatus) -> &'static str {
+    match status {
+        RequirementStatus::Complete => "Complete",
+        RequirementStatus::InProgress => "In progress",
+        RequirementStatus::NotStarted => "Not started",
+        RequirementStatus::NotApplicable => "N/A",
+    }
+}
+
+/// Gives each sheet a name that Excel accepts and that no other sheet has.
+#[derive(Default)]
+struct SheetNames {
+    used: HashSet<String>,
+}
+
+impl SheetNames {
+    /// Makes a name valid and unique. Excel compares sheet names without regard to case.
+    fn unique(&mut self, name: &str) -> String {
+        let cleaned: String = name
+            .chars()
+            .map(|c| {
+                if INVALID_SHEET_NAME_CHARS.contains(&c) {
+                    ' '
+                } else {
+                    c
+                }
+            })
+            .collect();
+        let cleaned = cleaned.trim().trim_matches('\'').trim();
+        let base = if cleaned.is_empty() { "Sheet" } else { cleaned };
+
+        let mut suffix = 1;
+        loop {
+            let tail = if suffix == 1 {
+                String::new()
+            } else {
+                format!(" ({suffix})")
+            };
+            let head: String = base.chars().take(MAX_SHEET_NAME_LEN - tail.len()).collect();
+            let candidate = format!("{}{tail}", head.trim_end());
+            if self.used.insert(candidate.to_lowercase()) {
+                return candidate;
+            }
+            suffix += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sheet_names() {
+        let mut names = SheetNames::default();
+        assert_eq!("Summary", names.unique(SUMMARY_SHEET));
+        assert_eq!("summary (2)", names.unique("summary"));
+        assert_eq!("Major  Minor", names.unique("Major [Minor]"));
+        assert_eq!("Sheet", names.unique("'//'"));
+
+        let long = "General Education Requirements For Transfer Students";
+        let first = names.unique(long);
+        let second = names.unique(long);
+        assert_eq!("General Education Requirements", first);
+        assert!(second.ends_with(" (2)") && second.chars().count() <= MAX_SHEET_NAME_LEN);
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,204 @@
+//! Recording and replaying the pages that DARS serves, so that the parser can be tested
+//! against real audits without a student's session.
+//!
+//! When recording is on (`auditFixtureDir`), each audit flow saves what DARS returned for
+//! each step to its own directory: where `create.html` redirected to (`create.txt`), each
+//! `list.html` page in the order it was polled (`list_1.html`, `list_2.html`, ...), and
+//! the audit itself (`read.html`). The student's name, PIDs, and email addresses are
+//! scrubbed from every page before it's written. A [`DegreeAuditClient`] in replay mode
+//! serves a recorded directory back instead of making requests, so a capture can be run
+//! through the whole flow with `webreg-audit parse --replay <dir>`.
+//!
+//! [`DegreeAuditClient`]: super::DegreeAuditClient
+
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use rand::Rng;
+use regex::Regex;
+use scraper::{Html, Selector};
+use tracing::{info, warn};
+
+use super::error::DegreeAuditError;
+
+/// What a scrubbed name is replaced with.
+const SCRUBBED_NAME: &str = "Student Name";
+/// What a scrubbed PID is replaced with.
+const SCRUBBED_PID: &str = "A00000000";
+/// What a scrubbed email address is replaced with.
+const SCRUBBED_EMAIL: &str = "student@example.edu";
+
+static NAME_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("#headerInfo span.float-right").unwrap());
+static PID_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b[AaUu]\d{8}\b").unwrap());
+static EMAIL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap());
+
+/// Whether DARS pages are recorded to, or replayed from, disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FixtureMode {
+    /// Save each audit flow's pages to a new directory under this one.
+    Record(PathBuf),
+    /// Serve the pages saved in this directory instead of making requests.
+    Replay(PathBuf),
+}
+
+/// A step of the audit flow whose response is recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixturePage {
+    /// Where `create.html` redirected to.
+    Create,
+    /// A `list.html` page.
+    List,
+    /// The audit itself, from `read.html`.
+    Read,
+}
+
+/// Removes the student's name, PIDs, and email addresses from a DARS page.
+///
+/// # Parameters
+/// - `html`: The page.
+///
+/// # Returns
+/// The scrubbed page.
+pub fn scrub_pii(html: &str) -> String {
+    let name = Html::parse_document(html)
+        .select(&NAME_SELECTOR)
+        .next()
+        .map(|el| el.text().collect::<String>().trim().to_owned())
+        .filter(|name| !name.is_empty());
+
+    let mut scrubbed = match name {
+        Some(name) => html.replace(&name, SCRUBBED_NAME),
+        None => html.to_owned(),
+    };
+    scrubbed = PID_REGEX.replace_all(&scrubbed, SCRUBBED_PID).into_owned();
+    EMAIL_REGEX
+        .replace_all(&scrubbed, SCRUBBED_EMAIL)
+        .into_owned()
+}
+
+/// The fixtures for one run of the audit flow.
+pub(super) enum FlowFixtures {
+    None,
+    Record { dir: PathBuf, lists: usize },
+    Replay { dir: PathBuf, lists: usize },
+}
+
+impl FlowFixtures {
+    /// Starts the fixtures for a run of the audit flow. When recording, a new directory is
+    /// made for the run.
+    pub(super) fn start(mode: Option<&FixtureMode>) -> Self {
+        match mode {
+            None => Self::None,
+            Some(FixtureMode::Replay(dir)) => Self::Replay {
+                dir: dir.clone(),
+                lists: 0,
+            },
+            Some(FixtureMode::Record(root)) => {
+                let dir = root.join(format!(
+                    "{}-{:06x}",
+                    chrono::Utc::now().format("%Y%m%dT%H%M%S"),
+                    rand::thread_rng().gen::<u32>() & 0xFF_FFFF
+                ));
+                match std::fs::create_dir_all(&dir) {
+                    Ok(()) => {
+                        info!("Recording degree audit pages to {}.", dir.display());
+                        Self::Record { dir, lists: 0 }
+                    }
+                    Err(e) => {
+                        warn!("Failed to create {}: {e}", dir.display());
+                        Self::None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether pages are being replayed, in which case nothing should be requested.
+    pub(super) fn is_replay(&self) -> bool {
+        matches!(self, Self::Replay { .. })
+    }
+
+    /// Gets the file that a page is saved to, counting `list.html` pages as they're seen.
+    fn next_file(dir: &Path, lists: &mut usize, page: FixturePage) -> PathBuf {
+        match page {
+            FixturePage::Create => dir.join("create.txt"),
+            FixturePage::List => {
+                *lists += 1;
+                dir.join(format!("list_{lists}.html"))
+            }
+            FixturePage::Read => dir.join("read.html"),
+        }
+    }
+
+    /// Saves a page, scrubbed, if recording. Failures are only logged.
+    pub(super) fn record(&mut self, page: FixturePage, content: &str) {
+        let Self::Record { dir, lists } = self else {
+            return;
+        };
+
+        let path = Self::next_file(dir, lists, page);
+        if let Err(e) = std::fs::write(&path, scrub_pii(content)) {
+            warn!("Failed to record {}: {e}", path.display());
+        }
+    }
+
+    /// Gets a recorded page, if replaying. Once every recorded `list.html` page has been
+    /// served, the last one is served again.
+    ///
+    /// # Returns
+    /// The page, or nothing if not replaying.
+    pub(super) fn replay(&mut self, page: FixturePage) -> Result<Option<String>, DegreeAuditError> {
+        let Self::Replay { dir, lists } = self else {
+            return Ok(None);
+        };
+
+        let mut path = Self::next_file(dir, lists, page);
+        if page == FixturePage::List && !path.exists() && *lists > 1 {
+            *lists -= 1;
+            path = dir.join(format!("list_{lists}.html"));
+        }
+
+        std::fs::read_to_string(&path)
+            .map(Some)
+            .map_err(|e| DegreeAuditError::Fixture {
+                message: format!("failed to read {}: {e}", path.display()),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_replay() {
+        let html = r#"<div id="headerInfo"><span class="float-right"> Jane Doe </span></div>
+            <p>Jane Doe (A12345678, jdoe@ucsd.edu) took MATH 20A</p>"#;
+        let scrubbed = scrub_pii(html);
+        assert!(!scrubbed.contains("Jane") && !scrubbed.contains("12345678"));
+        assert!(!scrubbed.contains("jdoe"));
+        assert!(scrubbed.contains("Student Name (A00000000, student@example.edu) took MATH 20A"));
+
+        let root = std::env::temp_dir().join(format!("webreg-fixtures-{}", std::process::id()));
+        let mut recording = FlowFixtures::start(Some(&FixtureMode::Record(root.clone())));
+        recording.record(FixturePage::Create, "list.html?autoPoll=true");
+        recording.record(FixturePage::List, "first");
+        recording.record(FixturePage::List, "second");
+        recording.record(FixturePage::Read, html);
+        let FlowFixtures::Record { dir, .. } = recording else {
+            panic!("not recording");
+        };
+
+        let mut replay = FlowFixtures::start(Some(&FixtureMode::Replay(dir)));
+        let mut next = |page| replay.replay(page).unwrap().unwrap();
+        assert_eq!("list.html?autoPoll=true", next(FixturePage::Create));
+        assert_eq!("first", next(FixturePage::List));
+        assert_eq!("second", next(FixturePage::List));
+        assert_eq!("second", next(FixturePage::List));
+        assert_eq!(scrubbed, next(FixturePage::Read));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+//! Grade scales, which map the grades on a degree audit to grade points and decide which of
+//! them count as passing.
+//!
+//! The default scale is UCSD's, where an A+ is worth 4.0 points and a C- or better passes
+//! a major requirement. Other institutions' policies differ (e.g., an A+ worth 4.3), so the
+//! scale can be overridden in the configuration, either directly or through a named
+//! institution profile. Grades that aren't on the scale are reported rather than silently
+//! treated as non-passing.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// The letter grades and their points on UCSD's scale.
+const DEFAULT_POINTS: [(&str, f32); 13] = [
+    ("A+", 4.0),
+    ("A", 4.0),
+    ("A-", 3.7),
+    ("B+", 3.3),
+    ("B", 3.0),
+    ("B-", 2.7),
+    ("C+", 2.3),
+    ("C", 2.0),
+    ("C-", 1.7),
+    ("D+", 1.3),
+    ("D", 1.0),
+    ("D-", 0.7),
+    ("F", 0.0),
+];
+/// The lowest letter grade that passes a major requirement, by default.
+const DEFAULT_MINIMUM_PASSING: &str = "C-";
+/// Grades that pass without counting towards the GPA, by default (pass, transfer pass,
+/// and satisfactory).
+const DEFAULT_PASS_GRADES: [&str; 3] = ["P", "TP", "S"];
+/// Grades that earn no credit and don't count towards the GPA, by default (no pass,
+/// unsatisfactory, withdrawn, incomplete, in progress, and not reported).
+const DEFAULT_NO_CREDIT_GRADES: [&str; 6] = ["NP", "U", "W", "I", "IP", "NR"];
+
+/// Changes to a grade scale, as given in the configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GradeScaleOverrides {
+    /// The points that an A+ is worth.
+    #[serde(default)]
+    pub a_plus_points: Option<f32>,
+    /// Letter grades to add to the scale, or to change the points of.
+    #[serde(default)]
+    pub points: HashMap<String, f32>,
+    /// The lowest letter grade that passes a major requirement.
+    #[serde(default)]
+    pub minimum_passing: Option<String>,
+    /// Grades to add that pass without counting towards the GPA.
+    #[serde(default)]
+    pub pass_grades: Vec<String>,
+    /// Grades to add that earn no credit and don't count towards the GPA.
+    #[serde(default)]
+    pub no_credit_grades: Vec<String>,
+}
+
+/// What a grade means on a scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GradeKind {
+    /// A letter grade worth this many points.
+    Graded(f32),
+    /// A grade that passes without counting towards the GPA.
+    Pass,
+    /// A grade that earns no credit and doesn't count towards the GPA.
+    NoCredit,
+    /// A grade that isn't on the scale.
+    Unknown,
+}
+
+/// Maps grades to grade points and decides which of them pass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradeScale {
+    points: BTreeMap<String, f32>,
+    minimum_passing_points: f32,
+    pass_grades: BTreeSet<String>,
+    no_credit_grades: BTreeSet<String>,
+}
+
+/// Normalizes a grade for lookup (e.g., ` a- ` becomes `A-`).
+fn normalize_grade(grade: &str) -> String {
+    grade.trim().to_uppercase()
+}
+
+impl Default for GradeScale {
+    fn default() -> Self {
+        let points: BTreeMap<String, f32> = DEFAULT_POINTS
+            .iter()
+            .map(|(grade, points)| (grade.to_string(), *points))
+            .collect();
+        Self {
+            minimum_passing_points: points[DEFAULT_MINIMUM_PASSING],
+            points,
+            pass_grades: DEFAULT_PASS_GRADES.iter().map(|g| g.to_string()).collect(),
+            no_credit_grades: DEFAULT_NO_CREDIT_GRADES
+                .iter()
+                .map(|g| g.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl GradeScale {
+    /// Creates a grade scale from the configuration.
+    ///
+    /// # Parameters
+    /// - `profile`: The name of the institution profile to start from, if any.
+    /// - `profiles`: The configured institution profiles, each applied on top of UCSD's
+    ///   scale.
+    /// - `overrides`: Changes applied on top of the profile.
+    ///
+    /// # Returns
+    /// The grade scale. A profile that isn't configured, or a minimum passing grade that
+    /// isn't a letter grade on the scale, is logged and ignored.
+    pub fn from_config(
+        profile: Option<&str>,
+        profiles: &HashMap<String, GradeScaleOverrides>,
+        overrides: &GradeScaleOverrides,
+    ) -> Self {
+        let mut scale = Self::default();
+        if let Some(name) = profile {
+            match profiles.get(name) {
+                Some(profile) => scale.apply(profile),
+                None => warn!("Unknown grade scale profile '{name}'; using the default scale."),
+            }
+        }
+
+        scale.apply(overrides);
+        scale
+    }
+
+    /// Applies changes to this scale.
+    fn apply(&mut self, overrides: &GradeScaleOverrides) {
+        if let Some(points) = overrides.a_plus_points {
+            self.points.insert("A+".to_owned(), points);
+        }
+
+        for (grade, points) in &overrides.points {
+            self.points.insert(normalize_grade(grade), *points);
+        }
+
+        self.pass_grades
+            .extend(overrides.pass_grades.iter().map(|g| normalize_grade(g)));
+        self.no_credit_grades.extend(
+            overrides
+                .no_credit_grades
+                .iter()
+                .map(|g| normalize_grade(g)),
+        );
+
+        if let Some(ref minimum) = overrides.minimum_passing {
+            match self.points.get(&normalize_grade(minimum)) {
+                Some(points) => self.minimum_passing_points = *points,
+                None => warn!("Minimum passing grade '{minimum}' isn't on the grade scale."),
+            }
+        }
+    }
+
+    /// Gets what a grade means on this scale.
+    pub fn classify(&self, grade: &str) -> GradeKind {
+        let grade = normalize_grade(grade);
+        if let Some(points) = self.points.get(&grade) {
+            GradeKind::Graded(*points)
+        } else if self.pass_grades.contains(&grade) {
+            GradeKind::Pass
+        } else if self.no_credit_grades.contains(&grade) {
+            GradeKind::NoCredit
+        } else {
+            GradeKind::Unknown
+        }
+    }
+
+    /// Gets the points that a grade is worth, if it counts towards the GPA.
+    pub fn points(&self, grade: &str) -> Option<f32> {
+        match self.classify(grade) {
+            GradeKind::Graded(points) => Some(points),
+            _ => None,
+        }
+    }
+
+    /// Checks if a grade is passing for major requirements (C- or higher, by default).
+    /// Grades that aren't on the scale don't pass; see [`Self::is_known`].
+    pub fn is_passing_grade(&self, grade: &str) -> bool {
+        match self.classify(grade) {
+            GradeKind::Graded(points) => points >= self.minimum_passing_points,
+            GradeKind::Pass => true,
+            GradeKind::NoCredit | GradeKind::Unknown => false,
+        }
+    }
+
+    /// Checks if a grade is on this scale.
+    pub fn is_known(&self, grade: &str) -> bool {
+        self.classify(grade) != GradeKind::Unknown
+    }
+
+    /// Calculates units earned based on grade
+    pub fn units_earned(&self, grade: &str, course_units: f32) -> f32 {
+        if self.is_passing_grade(grade) {
+            course_units
+        } else {
+            0.0
+        }
+    }
+
+    /// Computes the unit-weighted GPA of some graded courses.
+    ///
+    /// # Parameters
+    /// - `courses`: Each course's grade and units. Grades that don't count towards the GPA
+    ///   are skipped.
+    ///
+    /// # Returns
+    /// The GPA, or nothing if none of the courses count towards it.
+    pub fn gpa<'a>(&self, courses: impl IntoIterator<Item = (&'a str, f32)>) -> Option<f32> {
+        let (points, units) = courses
+            .into_iter()
+            .filter_map(|(grade, units)| Some((self.points(grade)? * units, units)))
+            .fold((0.0, 0.0), |(p, u), (points, units)| {
+                (p + points, u + units)
+            });
+        (units > 0.0).then(|| points / units)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grade_scale() {
+        let scale = GradeScale::default();
+        assert!(scale.is_passing_grade("C-"));
+        assert!(scale.is_passing_grade(" tp "));
+        assert!(!scale.is_passing_grade("D+"));
+        assert_eq!(GradeKind::NoCredit, scale.classify("W"));
+        assert!(!scale.is_known("Q"));
+        assert_eq!(Some(4.0), scale.gpa([("A+", 4.0), ("A", 2.0), ("P", 4.0)]));
+        assert_eq!(None, scale.gpa([("P", 4.0), ("Q", 4.0)]));
+
+        // A profile changes the scale, and the overrides change the profile
+        let profiles = HashMap::from([(
+            "plus".to_owned(),
+            GradeScaleOverrides {
+                a_plus_points: Some(4.3),
+                minimum_passing: Some("D".to_owned()),
+                ..Default::default()
+            },
+        )]);
+        let overrides = GradeScaleOverrides {
+            pass_grades: vec!["cr".to_owned()],
+            minimum_passing: Some("Z".to_owned()),
+            ..Default::default()
+        };
+        let scale = GradeScale::from_config(Some("plus"), &profiles, &overrides);
+        assert_eq!(Some(4.3), scale.points("A+"));
+        assert!(scale.is_passing_grade("D"));
+        assert!(scale.is_passing_grade("CR"));
+        assert!(!scale.is_passing_grade("D-"));
+
+        // An unknown profile falls back to the default scale
+        let scale = GradeScale::from_config(Some("nope"), &profiles, &Default::default());
+        assert_eq!(GradeScale::default(), scale);
+    }
+}
@@ -7,26 +7,58 @@
 //! - Processing requirements and generating recommendations
 
 // Core modules
+pub mod anonymize;
+pub mod archive;
 pub mod cache;
+pub mod cli;
 pub mod client;
+pub mod cohort;
 pub mod config;
+pub mod electives;
 pub mod error;
+pub mod export;
+pub mod fixtures;
+pub mod grades;
 pub mod job;
+pub mod owner;
+pub mod planner;
+pub mod poll;
 pub mod processor;
+pub mod quota;
+pub mod report;
+pub mod ttl;
 mod types;
+pub mod version;
+pub mod wait;
 
 // Re-exports for convenience
-pub use cache::AuditCacheState;
+pub use archive::{AuditArchive, DEFAULT_ARCHIVE_RETENTION_DAYS};
+pub use cache::{AuditCacheState, AuditFetch, CachePolicy};
 pub use client::DegreeAuditClient;
+pub use cohort::build_gap_report;
+pub use electives::{suggest_electives, ElectiveSuggestions};
 pub use error::DegreeAuditError;
+pub use export::render_workbook;
+pub use fixtures::{scrub_pii, FixtureMode};
+pub use grades::{GradeKind, GradeScale, GradeScaleOverrides};
+pub use owner::AuditOwner;
+pub use planner::{build_graduation_plan, PlanInputs};
+pub use poll::{PollStrategy, PollStrategyKind};
 pub use processor::*;
+pub use quota::{AuditQuota, DEFAULT_DAILY_AUDIT_QUOTA};
+pub use report::render_report;
+pub use ttl::{AuditTtlPolicy, DEFAULT_AUDIT_TTL, DEFAULT_GRADE_POSTING_TTL};
 pub use types::*;
+pub use version::{detect_version, ReportVersion, SelectorProfile};
+pub use wait::{PendingAudits, WaitEstimator};
 
+use crate::ingest;
 use crate::types::WrapperState;
 use regex::Regex;
 use scraper::{Html, Selector};
 use std::sync::Arc;
-use tracing::info;
+use std::time::Duration;
+use tracing::{info, warn};
 
 /// Fetches degree audit data from the webregautoin server
 ///
@@ -35,25 +67,43 @@ use tracing::info;
 ///
 /// # Arguments
 /// * `state` - The wrapper state containing cookie server configuration
+/// * `fresh` - Whether to have DARS run a new audit instead of returning the most recent one
+/// * `timeout` - How long to wait for the server to respond
 ///
 /// # Returns
 /// * `Ok(DegreeAuditResponse)` - Raw degree audit data including HTML
 /// * `Err` - If the request fails or the response is invalid
 pub async fn fetch_degree_audit(
     state: &Arc<WrapperState>,
+    fresh: bool,
+    timeout: Duration,
 ) -> Result<DegreeAuditResponse, Box<dyn std::error::Error>> {
-    let address = format!(
-        "{}:{}",
-        state.cookie_server.address, state.cookie_server.port
-    );
+    let Some(address) = state.cookie_server_address() else {
+        return Err("Degree audits need a cookie server, and none is configured".into());
+    };
 
-    info!("Requesting degree audit data from webregautoin server (http://{address}/degree_audit)");
+    fetch_degree_audit_from(state, &address, fresh, timeout).await
+}
 
-    let response = state
+/// Fetches degree audit data from a specific webregautoin server, like a user's own in
+/// multi-user mode (see [`owner`]). See [`fetch_degree_audit`].
+pub async fn fetch_degree_audit_from(
+    state: &Arc<WrapperState>,
+    address: &str,
+    fresh: bool,
+    timeout: Duration,
+) -> Result<DegreeAuditResponse, Box<dyn std::error::Error>> {
+    info!("Requesting degree audit data from webregautoin server (http://{address}/degree_audit, fresh={fresh})");
+
+    let mut request = state
         .client
         .get(format!("http://{address}/degree_audit"))
-        .send()
-        .await?;
+        .timeout(timeout);
+    if fresh {
+        request = request.query(&[("fresh", "true")]);
+    }
+
+    let response = request.send().await?;
 
     if !response.status().is_success() {
         let status = response.status();
@@ -61,7 +111,11 @@ pub async fn fetch_degree_audit(
         return Err(format!("Degree audit request failed with status {}: {}", status, error_text).into());
     }
 
-    let text = response.text().await?;
+    if let Some(len) = response.content_length() {
+        ingest::check_size(len as usize)?;
+    }
+
+    let text = ingest::decode(&response.bytes().await?)?;
     let audit_data: DegreeAuditResponse = serde_json::from_str(&text)?;
 
     info!("Successfully received degree audit data (audit ID: {})", audit_data.audit_id);
@@ -85,21 +139,52 @@ pub fn parse_degree_audit_html(
 ) -> Result<DegreeAudit, Box<dyn std::error::Error>> {
     info!("Parsing degree audit HTML");
 
-    let document = Html::parse_document(&raw_audit.html);
+    let html = ingest::sanitize_html(&raw_audit.html)?;
+    let document = Html::parse_document(&html);
 
     // Parse student info
     let student_info = parse_student_info(&document)?;
 
-    // Parse requirements
-    let requirements = parse_requirements(&document)?;
+    // Parse requirements with the detected version's selectors, keeping track of any
+    // blocks that had to be left out
+    let mut parse_warnings = Vec::new();
+    let report_version = detect_version(&document).unwrap_or_else(|| {
+        warn!(
+            "Couldn't detect the DARS report version; parsing it as {:?}",
+            ReportVersion::FALLBACK
+        );
+        parse_warnings.push(ParseWarning {
+            kind: "report_version".to_string(),
+            block_id: None,
+            message: format!(
+                "no known DARS report version was detected, so it was parsed as {:?}",
+                ReportVersion::FALLBACK
+            ),
+        });
+        ReportVersion::FALLBACK
+    });
+    let profile = SelectorProfile::of(report_version);
+    let requirements = parse_requirements(&document, profile, &mut parse_warnings)?;
 
-    info!("Parsed {} requirements from degree audit", requirements.len());
+    info!(
+        "Parsed {} requirements from degree audit ({:?} report)",
+        requirements.len(),
+        report_version
+    );
+    if !parse_warnings.is_empty() {
+        warn!(
+            "Left {} unparseable blocks out of degree audit",
+            parse_warnings.len()
+        );
+    }
 
     Ok(DegreeAudit {
         audit_id: raw_audit.audit_id.clone(),
         student_info,
         requirements,
         scraped_at: raw_audit.scraped_at.clone(),
+        parse_warnings,
+        report_version: Some(report_version),
     })
 }
 
@@ -140,30 +225,55 @@ fn parse_student_info(document: &Html) -> Result<StudentInfo, Box<dyn std::error
     })
 }
 
-/// Parses all requirements from the degree audit
-fn parse_requirements(document: &Html) -> Result<Vec<Requirement>, Box<dyn std::error::Error>> {
+/// Parses all requirements from the degree audit. Requirements that can't be parsed are
+/// left out, with a warning added for each
+fn parse_requirements(
+    document: &Html,
+    profile: &SelectorProfile,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<Vec<Requirement>, Box<dyn std::error::Error>> {
     let mut requirements = Vec::new();
 
-    // Select all requirement divs
-    let req_selector = Selector::parse("div.requirement").unwrap();
-
-    for req_element in document.select(&req_selector) {
-        if let Ok(requirement) = parse_single_requirement(&req_element) {
-            requirements.push(requirement);
+    for req_element in document.select(&profile.requirement) {
+        match parse_single_requirement(&req_element, profile, warnings) {
+            Ok(requirement) => requirements.push(requirement),
+            Err(e) => warnings.push(parse_warning("requirement", &req_element, e.as_ref())),
         }
     }
 
     Ok(requirements)
 }
 
+/// Creates a warning for a block that couldn't be parsed
+fn parse_warning(
+    kind: &str,
+    elem: &scraper::ElementRef,
+    error: &dyn std::error::Error,
+) -> ParseWarning {
+    let block_id = elem.value().attr("id").map(str::to_string);
+    warn!(
+        "Failed to parse {} {}: {}",
+        kind,
+        block_id.as_deref().unwrap_or("(no id)"),
+        error
+    );
+
+    ParseWarning {
+        kind: kind.to_string(),
+        block_id,
+        message: error.to_string(),
+    }
+}
+
 /// Parses a single requirement element
 fn parse_single_requirement(
     req_element: &scraper::ElementRef,
+    profile: &SelectorProfile,
+    warnings: &mut Vec<ParseWarning>,
 ) -> Result<Requirement, Box<dyn std::error::Error>> {
     // Extract requirement title
-    let title_selector = Selector::parse(".reqTitle").unwrap();
     let title = req_element
-        .select(&title_selector)
+        .select(&profile.requirement_title)
         .next()
         .map(|el| el.text().collect::<String>().trim().to_string())
         .unwrap_or_default();
@@ -191,12 +301,12 @@ fn parse_single_requirement(
     // Extract required hours from attribute
     let credits_required = req_element
         .value()
-        .attr("rqdHours")
+        .attr("rqdhours")
         .and_then(|s| s.parse::<f32>().ok())
         .filter(|&h| h > 0.0);
 
     // Parse completed courses from subrequirements
-    let courses = parse_courses_from_requirement(req_element)?;
+    let courses = parse_courses_from_requirement(req_element, profile)?;
 
     // Try to get earned units from requirementTotals table first
     // This is more accurate than calculating from courses
@@ -210,7 +320,7 @@ fn parse_single_requirement(
     });
 
     // Parse subrequirements
-    let subrequirements = parse_subrequirements(req_element)?;
+    let subrequirements = parse_subrequirements(req_element, profile, warnings)?;
 
     Ok(Requirement {
         category,
@@ -226,24 +336,66 @@ fn parse_single_requirement(
 /// Parses all completed courses from a requirement's subrequirements
 fn parse_courses_from_requirement(
     req_element: &scraper::ElementRef,
+    profile: &SelectorProfile,
 ) -> Result<Vec<CourseRequirement>, Box<dyn std::error::Error>> {
     let mut courses = Vec::new();
 
     // Select all completed course tables
-    let table_selector = Selector::parse("table.completedCourses").unwrap();
-    let row_selector = Selector::parse("tr.takenCourse").unwrap();
-
-    for table in req_element.select(&table_selector) {
-        for row in table.select(&row_selector) {
+    for table in req_element.select(&profile.completed_courses) {
+        for row in table.select(&profile.taken_course) {
             if let Ok(course) = parse_course_row(&row) {
                 courses.push(course);
             }
         }
     }
 
+    courses.extend(parse_transfer_courses(req_element));
     Ok(courses)
 }
 
+/// Parses transfer courses and AP/IB exam credit, which DARS lists in their own tables
+/// instead of alongside courses taken here
+///
+/// The HTML structure is:
+/// ```html
+/// <table class="transferCourses">
+///   <tr class="transferCourse">
+///     <td class="term">FA22</td>
+///     <td class="institution">SAN DIEGO MESA COLLEGE</td>
+///     <td class="course">MATH 20A</td>
+///     <td class="credit">4.0</td>
+///     <td class="grade">TP</td>
+///   </tr>
+/// </table>
+/// <table class="examCredits">
+///   <tr class="examCredit">
+///     <td class="exam">AP CALCULUS BC</td>
+///     <td class="course">MATH 20A</td>
+///     <td class="credit">4.0</td>
+///     <td class="grade">5</td>
+///   </tr>
+/// </table>
+/// ```
+fn parse_transfer_courses(elem: &scraper::ElementRef) -> Vec<CourseRequirement> {
+    let row_selector =
+        Selector::parse("table.transferCourses tr.transferCourse, table.examCredits tr.examCredit")
+            .unwrap();
+    let source_selector = Selector::parse("td.institution, td.exam").unwrap();
+
+    elem.select(&row_selector)
+        .filter_map(|row| {
+            let mut course = parse_course_row(&row).ok()?;
+            course.source = row
+                .select(&source_selector)
+                .next()
+                .map(|el| el.text().collect::<String>().trim().to_string())
+                .filter(|s| !s.is_empty());
+            course.status = CourseStatus::Transfer;
+            Some(course)
+        })
+        .collect()
+}
+
 /// Parses a single course row from a completed courses table
 fn parse_course_row(
     row: &scraper::ElementRef,
@@ -299,20 +451,23 @@ fn parse_course_row(
         grade,
         term,
         status,
+        source: None,
     })
 }
 
-/// Parses all subrequirements from a requirement element
+/// Parses all subrequirements from a requirement element. Subrequirements that can't be
+/// parsed are left out, with a warning added for each
 fn parse_subrequirements(
     req_element: &scraper::ElementRef,
+    profile: &SelectorProfile,
+    warnings: &mut Vec<ParseWarning>,
 ) -> Result<Vec<Subrequirement>, Box<dyn std::error::Error>> {
     let mut subrequirements = Vec::new();
 
-    let subreq_selector = Selector::parse("div.subrequirement").unwrap();
-
-    for subreq_elem in req_element.select(&subreq_selector) {
-        if let Ok(subreq) = parse_single_subrequirement(&subreq_elem) {
-            subrequirements.push(subreq);
+    for subreq_elem in req_element.select(&profile.subrequirement) {
+        match parse_single_subrequirement(&subreq_elem, profile) {
+            Ok(subreq) => subrequirements.push(subreq),
+            Err(e) => warnings.push(parse_warning("subrequirement", &subreq_elem, e.as_ref())),
         }
     }
 
@@ -322,6 +477,7 @@ fn parse_subrequirements(
 /// Parses a single subrequirement div
 fn parse_single_subrequirement(
     subreq_elem: &scraper::ElementRef,
+    profile: &SelectorProfile,
 ) -> Result<Subrequirement, Box<dyn std::error::Error>> {
     // Extract id attribute
     let id = subreq_elem
@@ -331,9 +487,8 @@ fn parse_single_subrequirement(
         .to_string();
 
     // Extract title
-    let title_selector = Selector::parse(".subreqTitle").unwrap();
     let title = subreq_elem
-        .select(&title_selector)
+        .select(&profile.subrequirement_title)
         .next()
         .map(|el| el.text().collect::<String>().trim().to_string())
         .unwrap_or_default();
@@ -345,6 +500,16 @@ fn parse_single_subrequirement(
         .and_then(|s| s.parse::<f32>().ok())
         .unwrap_or(0.0);
 
+    // Extract required number of courses from rqdcourses attribute
+    let required_courses = subreq_elem
+        .value()
+        .attr("rqdcourses")
+        .and_then(|s| s.trim().parse::<u32>().ok())
+        .filter(|&c| c > 0);
+
+    // Parse what's still needed (e.g., "NEEDS: 8.00 UNITS 2 COURSES")
+    let (needs, courses_needed) = parse_subreq_needs(subreq_elem);
+
     // Parse status from class attribute
     let class_attr = subreq_elem.value().attr("class").unwrap_or("");
     let status = if class_attr.contains("Status_OK") {
@@ -364,18 +529,20 @@ fn parse_single_subrequirement(
     let category_groups = parse_course_categories(&subreq_elem)?;
 
     // Parse completed courses from completedCourses table
-    let completed_courses = parse_completed_courses_in_subreq(&subreq_elem)?;
+    let completed_courses = parse_completed_courses_in_subreq(subreq_elem, profile)?;
 
     // Try to get earned units from subrequirementTotals table first
     // This is more accurate than calculating from courses since some subrequirements
     // show totals without listing individual courses
     let units_completed = parse_subreq_earned_units(&subreq_elem).unwrap_or_else(|| {
-        // Fallback: Calculate completed units from courses (only count passing grades)
+        // Fallback: Calculate completed units from courses (only count passing grades).
+        // Parsing doesn't know the configured grade scale, so UCSD's is used
+        let scale = GradeScale::default();
         completed_courses
             .iter()
             .filter_map(|c| {
                 if let (Some(grade), Some(units)) = (&c.grade, c.units) {
-                    if GradeValidator::is_passing_grade(grade) {
+                    if scale.is_passing_grade(grade) {
                         Some(units)
                     } else {
                         None
@@ -393,6 +560,9 @@ fn parse_single_subrequirement(
         id,
         title,
         required_units,
+        required_courses,
+        needs,
+        courses_needed,
         units_completed,
         units_remaining,
         status,
@@ -402,6 +572,41 @@ fn parse_single_subrequirement(
     })
 }
 
+/// Parses what a subrequirement still needs from the subreqNeeds table
+///
+/// The HTML structure is:
+/// ```html
+/// <table class="subreqNeeds">
+///   <tr>
+///     <td class="count">NEEDS:</td>
+///     <td class="hours"><span class="hours number">8.00</span> UNITS</td>
+///     <td class="count"><span class="count number">2</span> COURSES</td>
+///   </tr>
+/// </table>
+/// ```
+///
+/// Returns the full NEEDS text and the number of courses needed, if given
+fn parse_subreq_needs(subreq_elem: &scraper::ElementRef) -> (Option<String>, Option<u32>) {
+    let needs_selector = Selector::parse("table.subreqNeeds").unwrap();
+    let count_selector = Selector::parse("span.count.number").unwrap();
+
+    let Some(table) = subreq_elem.select(&needs_selector).next() else {
+        return (None, None);
+    };
+
+    let needs = table
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let courses_needed = table
+        .select(&count_selector)
+        .next()
+        .and_then(|el| el.text().collect::<String>().trim().parse::<u32>().ok());
+
+    (Some(needs).filter(|n| !n.is_empty()), courses_needed)
+}
+
 /// Parses eligible courses from selectcourses table
 fn parse_eligible_courses(
     subreq_elem: &scraper::ElementRef,
@@ -515,20 +720,19 @@ fn parse_course_categories(
 /// Parses completed courses within a subrequirement
 fn parse_completed_courses_in_subreq(
     subreq_elem: &scraper::ElementRef,
+    profile: &SelectorProfile,
 ) -> Result<Vec<CourseRequirement>, Box<dyn std::error::Error>> {
     let mut courses = Vec::new();
 
-    let table_selector = Selector::parse("table.completedCourses").unwrap();
-    let row_selector = Selector::parse("tr.takenCourse").unwrap();
-
-    for table in subreq_elem.select(&table_selector) {
-        for row in table.select(&row_selector) {
+    for table in subreq_elem.select(&profile.completed_courses) {
+        for row in table.select(&profile.taken_course) {
             if let Ok(course) = parse_course_row(&row) {
                 courses.push(course);
             }
         }
     }
 
+    courses.extend(parse_transfer_courses(subreq_elem));
     Ok(courses)
 }
 
@@ -588,20 +792,144 @@ fn parse_req_earned_units(req_elem: &scraper::ElementRef) -> Option<f32> {
     None
 }
 
-/// Fetches and parses degree audit data in one step
-///
-/// Convenience function that combines fetch and parse operations.
-///
-/// # Arguments
-/// * `state` - The wrapper state
-///
-/// # Returns
-/// * `Ok(DegreeAudit)` - Fully parsed degree audit data
-/// * `Err` - If fetch or parse fails
-pub async fn get_degree_audit(
-    state: &Arc<WrapperState>,
-) -> Result<DegreeAudit, Box<dyn std::error::Error>> {
-    let raw_audit = fetch_degree_audit(state).await?;
-    let parsed_audit = parse_degree_audit_html(&raw_audit)?;
-    Ok(parsed_audit)
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Creates an empty audit for tests, with nothing about the student
+    ///
+    /// # Parameters
+    /// - `audit_id`: The audit's ID.
+    pub fn audit(audit_id: &str) -> DegreeAudit {
+        DegreeAudit {
+            audit_id: audit_id.to_owned(),
+            student_info: StudentInfo {
+                student_id: None,
+                name: None,
+                major: None,
+                college: None,
+            },
+            requirements: vec![],
+            scraped_at: "2024-01-01T00:00:00Z".to_owned(),
+            parse_warnings: vec![],
+            report_version: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_transfer_courses() {
+        let html = Html::parse_fragment(
+            r#"<div class="requirement">
+                <table class="completedCourses">
+                    <tr class="takenCourse">
+                        <td class="term">FA23</td><td class="course">CSE 12</td>
+                        <td class="credit">4.0</td><td class="grade">A</td>
+                    </tr>
+                </table>
+                <table class="transferCourses">
+                    <tr class="transferCourse">
+                        <td class="term">SU22</td><td class="institution">MESA COLLEGE</td>
+                        <td class="course">MATH 20A</td><td class="credit">4.0</td>
+                        <td class="grade">TP</td>
+                    </tr>
+                </table>
+                <table class="examCredits">
+                    <tr class="examCredit">
+                        <td class="exam">AP CALCULUS BC</td><td class="course">MATH 20B</td>
+                        <td class="credit">4.0</td><td class="grade">5</td>
+                    </tr>
+                </table>
+            </div>"#,
+        );
+
+        let courses = parse_courses_from_requirement(
+            &html.root_element(),
+            SelectorProfile::of(ReportVersion::Classic),
+        )
+        .unwrap();
+        assert_eq!(3, courses.len());
+        assert!(matches!(courses[0].status, CourseStatus::Completed));
+        assert!(courses[0].source.is_none());
+        assert!(matches!(courses[1].status, CourseStatus::Transfer));
+        assert_eq!(Some("MESA COLLEGE"), courses[1].source.as_deref());
+        assert_eq!("MATH 20B", courses[2].course_code);
+        assert_eq!(Some("AP CALCULUS BC"), courses[2].source.as_deref());
+    }
+
+    #[test]
+    fn test_parse_subrequirement() {
+        let html = Html::parse_fragment(
+            r#"<div class="subrequirement Status_NO" id="sr1" rqdhours="8" rqdcourses="2">
+                <span class="subreqTitle">Upper-Division Electives</span>
+                <table class="subreqNeeds">
+                    <tr>
+                        <td class="count">NEEDS:</td>
+                        <td class="hours"><span class="hours number">8.00</span> UNITS</td>
+                        <td class="count"><span class="count number">2</span> COURSES</td>
+                    </tr>
+                </table>
+                <table class="selectcourses">
+                    <tr><td class="fromcourselist">
+                        <span class="course" department="CSE" number="101">
+                            <span class="number">CSE 101</span>
+                        </span>
+                    </td></tr>
+                </table>
+            </div>"#,
+        );
+        let selector = Selector::parse("div.subrequirement").unwrap();
+        let elem = html.select(&selector).next().unwrap();
+
+        let subreq =
+            parse_single_subrequirement(&elem, SelectorProfile::of(ReportVersion::Classic))
+                .unwrap();
+        assert_eq!("sr1", subreq.id);
+        assert_eq!("Upper-Division Electives", subreq.title);
+        assert_eq!(8.0, subreq.required_units);
+        assert_eq!(Some(2), subreq.required_courses);
+        assert_eq!(Some("NEEDS: 8.00 UNITS 2 COURSES"), subreq.needs.as_deref());
+        assert_eq!(Some(2), subreq.courses_needed);
+        assert_eq!("CSE 101", subreq.eligible_courses[0].full_code);
+    }
+
+    #[test]
+    fn test_parse_warnings() {
+        // A page without any known version's markers is still parsed, with a warning
+        let raw = DegreeAuditResponse {
+            audit_id: "a1".to_string(),
+            scraped_at: "2024-01-01T00:00:00Z".to_string(),
+            url: "https://example.com/read.html?id=a1".to_string(),
+            html: "<html><body><p>Your audit could not be run.</p></body></html>".to_string(),
+        };
+        let mut audit = parse_degree_audit_html(&raw).unwrap();
+        assert_eq!(1, audit.parse_warnings.len());
+        assert_eq!("report_version", audit.parse_warnings[0].kind);
+        assert_eq!(Some(ReportVersion::FALLBACK), audit.report_version);
+
+        // Blocks that fail are identified by their ID
+        let html = Html::parse_fragment(r#"<div class="subrequirement" id="sr2"></div>"#);
+        let selector = Selector::parse("div.subrequirement").unwrap();
+        let elem = html.select(&selector).next().unwrap();
+        let error: Box<dyn std::error::Error> = "missing units".into();
+        let warning = parse_warning("subrequirement", &elem, error.as_ref());
+        assert_eq!("subrequirement", warning.kind);
+        assert_eq!(Some("sr2"), warning.block_id.as_deref());
+        assert_eq!("missing units", warning.message);
+
+        let cache_state = AuditCacheState::new();
+        audit.parse_warnings.push(warning);
+        cache_state.record_parse_warnings(&audit);
+        assert_eq!(
+            2,
+            cache_state
+                .dropped_blocks
+                .load(std::sync::atomic::Ordering::Relaxed)
+        );
+
+        // Audits cached before warnings were recorded have none
+        let mut cached = serde_json::to_value(&audit).unwrap();
+        cached.as_object_mut().unwrap().remove("parse_warnings");
+        let cached: DegreeAudit = serde_json::from_value(cached).unwrap();
+        assert!(cached.parse_warnings.is_empty());
+    }
 }
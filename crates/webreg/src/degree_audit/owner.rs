@@ -0,0 +1,161 @@
+//! Deciding whose degree audit a request is for.
+//!
+//! With only the tracker's cookie server, every audit is that of the one student it logs in
+//! as, so every caller shares it. Once users are assigned cookie servers of their own
+//! (`userCookieServers`, keyed by the prefix of their API key, or given out from
+//! `registration.cookieServers` as they register), the server is in multi-user mode: each
+//! request's audit is fetched through the caller's cookie server, and is cached, counted
+//! against the quota, and invalidated under a session key derived from the caller's API
+//! key, so that one user's audit is never served to another. Callers without a cookie
+//! server of their own are turned away, rather than being given the shared audit.
+
+use std::collections::HashMap;
+
+use super::cache::SessionKey;
+use super::error::DegreeAuditError;
+use crate::types::AddressPortInfo;
+
+/// Whose audit a request is for, and where it's fetched from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditOwner {
+    /// The prefix of the caller's API key, in multi-user mode.
+    user: Option<String>,
+    /// The address of the cookie server that fetches the audit (e.g., `127.0.0.1:3000`).
+    cookie_server: String,
+}
+
+impl AuditOwner {
+    /// Works out whose audit a request is for.
+    ///
+    /// # Parameters
+    /// - `key_prefix`: The prefix of the caller's API key, if the request carried one.
+    /// - `shared_server`: The address of the tracker's cookie server, if there is one.
+    /// - `user_servers`: The cookie server of each user, keyed by the prefix of their API
+    ///   key, if the server is in multi-user mode.
+    ///
+    /// # Returns
+    /// The owner, or why the caller can't have an audit.
+    pub fn resolve(
+        key_prefix: Option<&str>,
+        shared_server: Option<String>,
+        user_servers: Option<&HashMap<String, AddressPortInfo>>,
+    ) -> Result<Self, DegreeAuditError> {
+        let Some(user_servers) = user_servers else {
+            return match shared_server {
+                Some(cookie_server) => Ok(Self {
+                    user: None,
+                    cookie_server,
+                }),
+                None => Err(DegreeAuditError::Network {
+                    message: "Degree audits need a cookie server, and none is configured"
+                        .to_owned(),
+                }),
+            };
+        };
+
+        let Some(prefix) = key_prefix else {
+            return Err(DegreeAuditError::AccessDenied {
+                message: "degree audits need an API key in multi-user mode".to_owned(),
+            });
+        };
+
+        match user_servers.get(prefix) {
+            Some(server) => Ok(Self {
+                user: Some(prefix.to_owned()),
+                cookie_server: format!("{}:{}", server.address, server.port),
+            }),
+            None => Err(DegreeAuditError::AccessDenied {
+                message: format!("no cookie server is assigned to key '{prefix}'"),
+            }),
+        }
+    }
+
+    /// The prefix of the caller's API key, in multi-user mode.
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// The address of the cookie server that fetches the audit.
+    pub fn cookie_server(&self) -> &str {
+        &self.cookie_server
+    }
+
+    /// The session that the audit is cached and counted against the quota under. The shared
+    /// audit keeps the key that's derived from the cookie server's address, while each
+    /// user's is derived from their API key, so that users who happen to share a cookie
+    /// server still don't share audits.
+    pub fn session_key(&self) -> SessionKey {
+        match &self.user {
+            Some(prefix) => SessionKey::from_cookie(&format!("user:{prefix}")),
+            None => SessionKey::from_cookie(&self.cookie_server),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::degree_audit::cache::AuditCache;
+    use crate::degree_audit::quota::AuditQuota;
+    use crate::degree_audit::tests::audit;
+    use std::time::Duration;
+
+    fn server(port: i64) -> AddressPortInfo {
+        AddressPortInfo {
+            address: "127.0.0.1".to_owned(),
+            port,
+        }
+    }
+
+    #[test]
+    fn test_cross_user_isolation() {
+        let shared = Some("127.0.0.1:3000".to_owned());
+
+        // With a single cookie server, every caller shares its audit
+        let single = AuditOwner::resolve(Some("alice"), shared.clone(), None).unwrap();
+        assert_eq!(
+            single,
+            AuditOwner::resolve(None, shared.clone(), None).unwrap()
+        );
+        assert_eq!(
+            SessionKey::from_cookie("127.0.0.1:3000"),
+            single.session_key()
+        );
+
+        // With users' own cookie servers, each caller gets theirs, and callers without one
+        // are turned away instead of getting the shared audit
+        let users = HashMap::from([
+            ("alice".to_owned(), server(3001)),
+            ("bob".to_owned(), server(3001)),
+        ]);
+        let alice = AuditOwner::resolve(Some("alice"), shared.clone(), Some(&users)).unwrap();
+        let bob = AuditOwner::resolve(Some("bob"), shared.clone(), Some(&users)).unwrap();
+        assert_eq!("127.0.0.1:3001", alice.cookie_server());
+        assert_eq!(Some("alice"), alice.user());
+        for denied in [Some("mallory"), None] {
+            assert!(matches!(
+                AuditOwner::resolve(denied, shared.clone(), Some(&users)),
+                Err(DegreeAuditError::AccessDenied { .. })
+            ));
+        }
+
+        // Users sharing a cookie server still don't share cached audits or quotas
+        assert_ne!(alice.session_key(), bob.session_key());
+        assert_ne!(single.session_key(), alice.session_key());
+        let cache = AuditCache::new(Duration::from_secs(60));
+        cache.insert(alice.session_key(), audit("alice-audit"));
+        assert_eq!(
+            Some("alice-audit".to_owned()),
+            cache.get(&alice.session_key()).map(|a| a.audit_id)
+        );
+        assert!(cache.get(&bob.session_key()).is_none());
+        assert!(cache.get_allowing_stale(&bob.session_key()).is_none());
+        cache.invalidate(&bob.session_key());
+        assert!(cache.get(&alice.session_key()).is_some());
+
+        let quota = AuditQuota::open(":memory:", 1);
+        quota.try_consume(&alice.session_key()).unwrap();
+        assert!(quota.try_consume(&alice.session_key()).is_err());
+        assert!(quota.try_consume(&bob.session_key()).is_ok());
+    }
+}
@@ -0,0 +1,394 @@
+//! Quarter-by-quarter graduation planning.
+//!
+//! Lays out the courses still needed for a degree over the coming quarters, taking
+//! prerequisites, a per-quarter unit cap, and the quarters each course is usually offered
+//! in into account. Courses that are only offered in one quarter of the year are called
+//! out, since putting them off delays them by a year.
+
+use super::types::NextCourseRecommendation;
+use crate::offerings::{only_season, season, season_name};
+use std::collections::{HashMap, HashSet};
+
+pub use webreg_types::degree_audit::{
+    GraduationPlan, PlanWarning, PlannedCourse, PlannedTerm, UnmetNeed,
+};
+
+/// Units assumed for a course, since the degree audit doesn't list units for courses
+/// that haven't been taken yet.
+pub const DEFAULT_COURSE_UNITS: f32 = 4.0;
+
+/// The number of quarters in a row without any progress after which planning gives up,
+/// i.e., a full academic year.
+const MAX_IDLE_TERMS: usize = 3;
+
+/// Everything the planner needs to know besides the remaining requirements.
+#[derive(Debug, Clone)]
+pub struct PlanInputs {
+    /// Courses the student has already completed (e.g., `CSE 12`).
+    pub completed: HashSet<String>,
+    /// Prerequisites for each course. Each inner list is a group of alternatives, of
+    /// which at least one must be taken; every group must be satisfied. Courses without
+    /// an entry are assumed to have no prerequisites.
+    pub prerequisites: HashMap<String, Vec<Vec<String>>>,
+    /// Terms that each course has been offered in before. Courses without an entry are
+    /// assumed to be offered every quarter.
+    pub offerings: HashMap<String, Vec<String>>,
+    /// The maximum number of units to plan for a single quarter.
+    pub units_per_term: f32,
+    /// The first quarter to plan (e.g., `FA24`).
+    pub start_term: String,
+    /// The maximum number of quarters to plan.
+    pub max_terms: usize,
+}
+
+/// A subrequirement being planned, along with how many more courses it needs.
+struct Need<'a> {
+    recommendation: &'a NextCourseRecommendation,
+    remaining: usize,
+    /// Whether every eligible course has to be taken, i.e., there are no alternatives.
+    required: bool,
+}
+
+/// Builds a quarter-by-quarter plan for the given remaining requirements.
+///
+/// Quarters are filled greedily, in order. Within a quarter, higher priority requirements
+/// are considered first, and a course is only placed if it's usually offered that quarter,
+/// all of its prerequisites are completed or planned for an earlier quarter, and it fits
+/// under the unit cap. Only regular quarters are planned.
+///
+/// # Arguments
+/// * `recommendations` - The remaining requirements, as computed by
+///   `DegreeProgressProcessor`
+/// * `inputs` - Completed courses, prerequisites, offering history, and limits
+///
+/// # Returns
+/// * The plan, along with any requirements that couldn't be planned
+pub fn build_graduation_plan(
+    recommendations: &[NextCourseRecommendation],
+    inputs: &PlanInputs,
+) -> GraduationPlan {
+    let mut needs: Vec<Need> = recommendations
+        .iter()
+        .map(|r| {
+            let remaining = (r.units_needed / DEFAULT_COURSE_UNITS).ceil().max(0.0) as usize;
+            Need {
+                recommendation: r,
+                remaining,
+                required: r.eligible_courses.len() <= remaining,
+            }
+        })
+        .filter(|n| n.remaining > 0)
+        .collect();
+    needs.sort_by_key(|n| n.recommendation.priority);
+
+    // Seasons each course has been offered in.
+    let seasons: HashMap<&str, HashSet<&str>> = inputs
+        .offerings
+        .iter()
+        .map(|(code, terms)| (code.as_str(), terms.iter().map(|t| season(t)).collect()))
+        .collect();
+
+    let mut taken: HashSet<String> = inputs.completed.clone();
+    let mut terms = vec![];
+    let mut term = inputs.start_term.to_uppercase();
+    let mut idle = 0;
+    while terms.len() < inputs.max_terms && needs.iter().any(|n| n.remaining > 0) {
+        let mut planned = PlannedTerm {
+            term: term.clone(),
+            units: 0.0,
+            courses: vec![],
+        };
+
+        for need in needs.iter_mut() {
+            for course in &need.recommendation.eligible_courses {
+                if need.remaining == 0
+                    || planned.units + DEFAULT_COURSE_UNITS > inputs.units_per_term
+                {
+                    break;
+                }
+
+                let code = course.full_code.as_str();
+                if taken.contains(code) || planned.courses.iter().any(|c| c.course_code == code) {
+                    continue;
+                }
+
+                let offered = match seasons.get(code) {
+                    Some(s) => s.contains(season(&term)),
+                    None => true,
+                };
+
+                if !offered || !prerequisites_met(code, &inputs.prerequisites, &taken) {
+                    continue;
+                }
+
+                planned.units += DEFAULT_COURSE_UNITS;
+                planned.courses.push(PlannedCourse {
+                    course_code: code.to_owned(),
+                    subrequirement_title: need.recommendation.subrequirement_title.clone(),
+                    units: DEFAULT_COURSE_UNITS,
+                });
+                need.remaining -= 1;
+            }
+        }
+
+        // Courses only count as taken for later quarters.
+        taken.extend(planned.courses.iter().map(|c| c.course_code.clone()));
+        idle = if planned.courses.is_empty() {
+            idle + 1
+        } else {
+            0
+        };
+        terms.push(planned);
+        if idle >= MAX_IDLE_TERMS {
+            break;
+        }
+
+        term = next_regular_term(&term);
+    }
+
+    // Drop trailing quarters where nothing could be planned.
+    while terms.last().is_some_and(|t| t.courses.is_empty()) {
+        terms.pop();
+    }
+
+    let unmet: Vec<UnmetNeed> = needs
+        .iter()
+        .filter(|n| n.remaining > 0)
+        .map(|n| UnmetNeed {
+            subrequirement_title: n.recommendation.subrequirement_title.clone(),
+            courses_remaining: n.remaining,
+            blocked_by_prerequisites: n
+                .recommendation
+                .eligible_courses
+                .iter()
+                .filter(|c| !taken.contains(&c.full_code))
+                .filter(|c| !prerequisites_met(&c.full_code, &inputs.prerequisites, &taken))
+                .map(|c| c.full_code.clone())
+                .collect(),
+        })
+        .collect();
+
+    let warnings = once_a_year_warnings(&needs, &terms, &taken, inputs);
+    GraduationPlan {
+        complete: unmet.is_empty(),
+        terms,
+        unmet,
+        warnings,
+    }
+}
+
+/// Finds the planned courses, and the required courses that couldn't be planned, that are
+/// only offered in one quarter of the year.
+fn once_a_year_warnings(
+    needs: &[Need],
+    terms: &[PlannedTerm],
+    taken: &HashSet<String>,
+    inputs: &PlanInputs,
+) -> Vec<PlanWarning> {
+    let scraped: HashSet<&str> = inputs
+        .offerings
+        .values()
+        .flatten()
+        .map(String::as_str)
+        .collect();
+    let only_in = |code: &str| {
+        let offered = inputs.offerings.get(code)?;
+        only_season(offered.iter().map(String::as_str), scraped.iter().copied())
+    };
+
+    let planned = terms.iter().flat_map(|t| {
+        t.courses.iter().filter_map(|c| {
+            let season = season_name(only_in(&c.course_code)?);
+            Some(PlanWarning {
+                course_code: c.course_code.clone(),
+                subrequirement_title: c.subrequirement_title.clone(),
+                only_offered_in: season.to_owned(),
+                planned_term: Some(t.term.clone()),
+                message: format!(
+                    "{} is only offered in the {season}, so if it isn't taken in {}, it \
+                     can't be taken until the next {season}.",
+                    c.course_code, t.term
+                ),
+            })
+        })
+    });
+
+    let unplanned = needs
+        .iter()
+        .filter(|n| n.required && n.remaining > 0)
+        .flat_map(|n| {
+            n.recommendation
+                .eligible_courses
+                .iter()
+                .filter(|c| !taken.contains(&c.full_code))
+                .filter_map(|c| {
+                    let season = season_name(only_in(&c.full_code)?);
+                    Some(PlanWarning {
+                        course_code: c.full_code.clone(),
+                        subrequirement_title: n.recommendation.subrequirement_title.clone(),
+                        only_offered_in: season.to_owned(),
+                        planned_term: None,
+                        message: format!(
+                            "{} is required but is only offered in the {season}, and \
+                             couldn't be planned.",
+                            c.full_code
+                        ),
+                    })
+                })
+        });
+
+    planned.chain(unplanned).collect()
+}
+
+/// Whether every prerequisite group of a course has at least one course that's been taken.
+fn prerequisites_met(
+    course: &str,
+    prerequisites: &HashMap<String, Vec<Vec<String>>>,
+    taken: &HashSet<String>,
+) -> bool {
+    match prerequisites.get(course) {
+        Some(groups) => groups
+            .iter()
+            .all(|group| group.is_empty() || group.iter().any(|c| taken.contains(c))),
+        None => true,
+    }
+}
+
+/// Gets the regular quarter after the given one (e.g., `SP25` is followed by `FA25`).
+/// Summer terms are followed by the fall quarter of the same year.
+///
+/// # Arguments
+/// * `term` - The term code
+///
+/// # Returns
+/// * The next regular quarter's term code
+pub fn next_regular_term(term: &str) -> String {
+    let year: u32 = term.get(2..4).and_then(|y| y.parse().ok()).unwrap_or(0);
+    match season(term) {
+        "FA" => format!("WI{:02}", (year + 1) % 100),
+        "WI" => format!("SP{year:02}"),
+        _ => format!("FA{year:02}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::degree_audit::EligibleCourse;
+
+    fn course(code: &str) -> EligibleCourse {
+        let (department, course_number) = code.split_once(' ').unwrap();
+        EligibleCourse {
+            department: department.to_string(),
+            course_number: course_number.to_string(),
+            full_code: code.to_string(),
+        }
+    }
+
+    fn recommendation(
+        title: &str,
+        priority: u32,
+        units: f32,
+        codes: &[&str],
+    ) -> NextCourseRecommendation {
+        NextCourseRecommendation {
+            subrequirement_title: title.to_string(),
+            priority,
+            eligible_courses: codes.iter().map(|c| course(c)).collect(),
+            units_needed: units,
+        }
+    }
+
+    fn inputs() -> PlanInputs {
+        PlanInputs {
+            completed: HashSet::from(["CSE 12".to_string()]),
+            prerequisites: HashMap::new(),
+            offerings: HashMap::new(),
+            units_per_term: 8.0,
+            start_term: "FA24".to_string(),
+            max_terms: 12,
+        }
+    }
+
+    #[test]
+    fn test_next_regular_term() {
+        assert_eq!("WI25", next_regular_term("FA24"));
+        assert_eq!("SP25", next_regular_term("WI25"));
+        assert_eq!("FA25", next_regular_term("SP25"));
+        assert_eq!("FA25", next_regular_term("S125"));
+        assert_eq!("WI00", next_regular_term("FA99"));
+    }
+
+    #[test]
+    fn test_prerequisites_and_offerings() {
+        let recs = [recommendation(
+            "Upper Division",
+            1,
+            12.0,
+            &["CSE 101", "CSE 100", "CSE 110"],
+        )];
+
+        let mut inputs = inputs();
+        inputs
+            .prerequisites
+            .insert("CSE 101".to_string(), vec![vec!["CSE 100".to_string()]]);
+        inputs.offerings.insert(
+            "CSE 110".to_string(),
+            vec!["SP23".to_string(), "SP24".to_string()],
+        );
+
+        let plan = build_graduation_plan(&recs, &inputs);
+        assert!(plan.complete);
+
+        let codes: Vec<Vec<&str>> = plan
+            .terms
+            .iter()
+            .map(|t| t.courses.iter().map(|c| c.course_code.as_str()).collect())
+            .collect();
+        assert_eq!(
+            vec![vec!["CSE 100"], vec!["CSE 101"], vec!["CSE 110"]],
+            codes
+        );
+        assert_eq!(
+            vec!["FA24", "WI25", "SP25"],
+            plan.terms
+                .iter()
+                .map(|t| t.term.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        // Neither fall nor winter has been scraped, so it's unknown whether CSE 110 is only
+        // offered in the spring
+        assert!(plan.warnings.is_empty());
+        inputs.offerings.insert(
+            "CSE 100".to_string(),
+            vec!["FA23".to_string(), "SP24".to_string()],
+        );
+        let plan = build_graduation_plan(&recs, &inputs);
+        assert_eq!(1, plan.warnings.len());
+        assert_eq!("CSE 110", plan.warnings[0].course_code);
+        assert_eq!("spring", plan.warnings[0].only_offered_in);
+        assert_eq!(Some("SP25"), plan.warnings[0].planned_term.as_deref());
+    }
+
+    #[test]
+    fn test_unit_cap_and_unmet() {
+        let recs = [
+            recommendation("Lower Division", 1, 8.0, &["MATH 18", "MATH 20C"]),
+            recommendation("Electives", 2, 8.0, &["CSE 150", "CSE 151"]),
+        ];
+
+        let mut inputs = inputs();
+        inputs
+            .prerequisites
+            .insert("CSE 151".to_string(), vec![vec!["CSE 999".to_string()]]);
+
+        let plan = build_graduation_plan(&recs, &inputs);
+        assert!(!plan.complete);
+        assert!(plan.terms.iter().all(|t| t.units <= 8.0));
+        assert_eq!(2, plan.terms.len());
+        assert_eq!(1, plan.unmet.len());
+        assert_eq!(1, plan.unmet[0].courses_remaining);
+        assert_eq!(vec!["CSE 151"], plan.unmet[0].blocked_by_prerequisites);
+    }
+}
@@ -0,0 +1,183 @@
+//! Strategies for how long to wait between polls of list.html while DARS runs an audit.
+//!
+//! While an audit is running, DARS serves list.html with `autoPoll=true` and reloads it on
+//! its own schedule, which the page sometimes states (e.g., `autoPollInterval: 5000`, or a
+//! `<meta http-equiv="refresh">`). The [`Adaptive`](PollStrategyKind::Adaptive) strategy
+//! follows that hint when there is one, so that we poll no more often than DARS itself does.
+
+use rand::Rng;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+/// The shortest delay that a hint from DARS is followed with.
+const MIN_HINT_DELAY: Duration = Duration::from_millis(250);
+
+/// The longest delay between polls, whatever the strategy.
+const MAX_POLL_DELAY: Duration = Duration::from_secs(10);
+
+static AUTO_POLL_INTERVAL_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)autoPoll(?:Interval|Delay)["']?\s*[:=]\s*["']?(\d+)"#).unwrap()
+});
+static META_REFRESH_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)<meta[^>]+http-equiv=["']?refresh["']?[^>]+content=["']?(\d+)"#).unwrap()
+});
+
+/// Decides how long to wait before each poll.
+pub trait PollStrategy: Debug + Send + Sync {
+    /// Gets the delay before a poll.
+    ///
+    /// # Parameters
+    /// - `attempt`: The poll that's about to be made, starting at 1.
+    /// - `hint`: The interval that the last list page asked to be polled at, if it said.
+    ///
+    /// # Returns
+    /// The delay.
+    fn next_delay(&self, attempt: u32, hint: Option<Duration>) -> Duration;
+}
+
+/// Polls at the same interval every time.
+#[derive(Debug, Clone)]
+pub struct FixedPoll {
+    pub interval: Duration,
+}
+
+impl PollStrategy for FixedPoll {
+    fn next_delay(&self, _attempt: u32, _hint: Option<Duration>) -> Duration {
+        self.interval
+    }
+}
+
+/// Doubles the delay after each poll, up to a cap, with up to 20% jitter.
+#[derive(Debug, Clone)]
+pub struct ExponentialPoll {
+    pub base: Duration,
+    pub cap: Duration,
+}
+
+impl Default for ExponentialPoll {
+    fn default() -> Self {
+        Self {
+            base: Duration::from_millis(500),
+            cap: MAX_POLL_DELAY,
+        }
+    }
+}
+
+impl PollStrategy for ExponentialPoll {
+    fn next_delay(&self, attempt: u32, _hint: Option<Duration>) -> Duration {
+        let base = self.base.as_millis() as u64;
+        // Exponential backoff: base * 2^min(attempt-1, 5)
+        let exponential = base * 2u64.pow(attempt.saturating_sub(1).min(5));
+        let capped = exponential.min(self.cap.as_millis() as u64);
+        let jitter = rand::thread_rng().gen_range(0..=(capped / 5));
+        Duration::from_millis(capped + jitter)
+    }
+}
+
+/// Polls at the interval that the list page asks for, falling back to another strategy
+/// when the page doesn't say.
+#[derive(Debug, Clone, Default)]
+pub struct AdaptivePoll {
+    pub fallback: ExponentialPoll,
+}
+
+impl PollStrategy for AdaptivePoll {
+    fn next_delay(&self, attempt: u32, hint: Option<Duration>) -> Duration {
+        match hint {
+            Some(hint) => hint.clamp(MIN_HINT_DELAY, MAX_POLL_DELAY),
+            None => self.fallback.next_delay(attempt, None),
+        }
+    }
+}
+
+/// The poll strategies that can be configured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PollStrategyKind {
+    Fixed,
+    #[default]
+    Exponential,
+    Adaptive,
+}
+
+impl PollStrategyKind {
+    /// Creates the strategy.
+    ///
+    /// # Parameters
+    /// - `interval`: The interval that `Fixed` polls at, and the delay that `Exponential`
+    ///   starts from.
+    ///
+    /// # Returns
+    /// The strategy.
+    pub fn build(self, interval: Duration) -> Arc<dyn PollStrategy> {
+        let exponential = ExponentialPoll {
+            base: interval,
+            ..Default::default()
+        };
+        match self {
+            PollStrategyKind::Fixed => Arc::new(FixedPoll { interval }),
+            PollStrategyKind::Exponential => Arc::new(exponential),
+            PollStrategyKind::Adaptive => Arc::new(AdaptivePoll {
+                fallback: exponential,
+            }),
+        }
+    }
+}
+
+/// Finds the interval that a list page asks to be polled at, either from an
+/// `autoPollInterval` (in milliseconds) in its scripts or from a meta refresh (in seconds).
+///
+/// # Parameters
+/// - `html`: The list page.
+///
+/// # Returns
+/// The interval, if the page gives one.
+pub fn parse_poll_hint(html: &str) -> Option<Duration> {
+    if let Some(ms) = AUTO_POLL_INTERVAL_REGEX
+        .captures(html)
+        .and_then(|c| c[1].parse().ok())
+    {
+        return Some(Duration::from_millis(ms));
+    }
+
+    META_REFRESH_REGEX
+        .captures(html)
+        .and_then(|c| c[1].parse().ok())
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_strategies() {
+        let script = r#"<script>var opts = { autoPollInterval: 3000 };</script>"#;
+        let meta = r#"<meta http-equiv="refresh" content="5; url=list.html?autoPoll=true">"#;
+        assert_eq!(Some(Duration::from_secs(3)), parse_poll_hint(script));
+        assert_eq!(Some(Duration::from_secs(5)), parse_poll_hint(meta));
+        assert_eq!(None, parse_poll_hint("<a href='list.html?autoPoll=true'>"));
+
+        let interval = Duration::from_millis(500);
+        let fixed = PollStrategyKind::Fixed.build(interval);
+        assert_eq!(interval, fixed.next_delay(4, Some(Duration::from_secs(3))));
+
+        let exponential = PollStrategyKind::Exponential.build(interval);
+        assert!(exponential.next_delay(3, None) > exponential.next_delay(1, None));
+        assert!(exponential.next_delay(30, None) <= MAX_POLL_DELAY + MAX_POLL_DELAY / 5);
+
+        let adaptive = PollStrategyKind::Adaptive.build(interval);
+        assert_eq!(
+            Duration::from_secs(3),
+            adaptive.next_delay(1, parse_poll_hint(script))
+        );
+        assert_eq!(
+            MAX_POLL_DELAY,
+            adaptive.next_delay(1, Some(Duration::from_secs(60)))
+        );
+        assert!(adaptive.next_delay(1, None) < Duration::from_secs(1));
+    }
+}
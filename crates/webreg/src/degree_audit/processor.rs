@@ -1,11 +1,15 @@
 /// Degree progress processing and analysis
-use super::config::RequirementsConfig;
+use super::config::{RequirementsConfig, SubrequirementConfig};
 use super::types::*;
+use crate::cross_listings::CrossListings;
 use std::collections::HashSet;
+use tracing::warn;
 
 /// Processes degree audit data to compute progress and recommendations
 pub struct DegreeProgressProcessor {
     requirements_config: RequirementsConfig,
+    /// Courses that are cross-listed, so that taking any listing counts as taking the others
+    cross_listings: CrossListings,
 }
 
 impl DegreeProgressProcessor {
@@ -13,9 +17,17 @@ impl DegreeProgressProcessor {
     pub fn new(requirements_config: RequirementsConfig) -> Self {
         Self {
             requirements_config,
+            cross_listings: CrossListings::default(),
         }
     }
 
+    /// Treats the listings of each cross-listed course as the same course when matching
+    /// courses, so that e.g. taking `MATH 176` satisfies a requirement that lists `CSE 100`
+    pub fn with_cross_listings(mut self, cross_listings: CrossListings) -> Self {
+        self.cross_listings = cross_listings;
+        self
+    }
+
     /// Computes comprehensive degree progress from parsed audit
     ///
     /// # Arguments
@@ -28,14 +40,19 @@ impl DegreeProgressProcessor {
         &self,
         audit: &DegreeAudit,
     ) -> Result<DegreeProgress, Box<dyn std::error::Error>> {
-        // Calculate total units completed (only count passing grades)
-        let total_units_completed: f32 = audit
+        let scale = &self.requirements_config.grade_scale;
+
+        // Calculate total units completed (only count passing grades). Transfer and exam
+        // credit is only listed once it's been accepted, so it always counts
+        let earned: Vec<(&CourseRequirement, f32)> = audit
             .requirements
             .iter()
             .flat_map(|r| &r.courses)
             .filter_map(|c| {
-                if let Some(ref grade) = c.grade {
-                    if GradeValidator::is_passing_grade(grade) {
+                if matches!(c.status, CourseStatus::Transfer) {
+                    c.units
+                } else if let Some(ref grade) = c.grade {
+                    if scale.is_passing_grade(grade) {
                         c.units
                     } else {
                         None
@@ -43,13 +60,61 @@ impl DegreeProgressProcessor {
                 } else {
                     c.units
                 }
+                .map(|units| (c, units))
             })
+            .collect();
+        let total_units_completed: f32 = earned.iter().map(|(_, units)| units).sum();
+
+        let transfer_units: f32 = earned
+            .iter()
+            .filter(|(c, _)| matches!(c.status, CourseStatus::Transfer))
+            .map(|(_, units)| units)
+            .sum();
+        let upper_division_units: f32 = earned
+            .iter()
+            .filter(|(c, _)| is_upper_division(&c.course_code))
+            .map(|(_, units)| units)
             .sum();
 
-        // Standard UCSD requirement (can be customized based on major)
-        let total_units_required = 180.0;
+        // The minimums depend on the student's major and college
+        let minimums = self.requirements_config.unit_minimums(
+            audit.student_info.major.as_deref(),
+            audit.student_info.college.as_deref(),
+        );
+        let total_units_required = minimums.total_units;
         let total_units_remaining = (total_units_required - total_units_completed).max(0.0);
 
+        // Grades that aren't on the scale don't count as passing, so they're surfaced in
+        // case the scale is missing them
+        let unknown_grades: Vec<UnknownGrade> = audit
+            .requirements
+            .iter()
+            .flat_map(|r| &r.courses)
+            .filter(|c| !matches!(c.status, CourseStatus::Transfer))
+            .filter_map(|c| {
+                let grade = c.grade.as_ref().filter(|g| !scale.is_known(g))?;
+                Some(UnknownGrade {
+                    course_code: c.course_code.clone(),
+                    grade: grade.clone(),
+                })
+            })
+            .collect();
+        for unknown in &unknown_grades {
+            warn!(
+                "[{}] Grade '{}' in {} isn't on the grade scale; not counting it as passing.",
+                audit.audit_id, unknown.grade, unknown.course_code
+            );
+        }
+
+        let gpa = scale.gpa(
+            audit
+                .requirements
+                .iter()
+                .flat_map(|r| &r.courses)
+                .filter(|c| !matches!(c.status, CourseStatus::Transfer))
+                .filter_map(|c| Some((c.grade.as_deref()?, c.units?))),
+        );
+
         // Build requirement summaries
         let requirements_summary = self.build_requirement_summaries(&audit.requirements);
 
@@ -65,8 +130,17 @@ impl DegreeProgressProcessor {
             total_units_required,
             total_units_completed,
             total_units_remaining,
+            transfer_units,
+            upper_division: UnitProgress::new(minimums.upper_division_units, upper_division_units),
+            residency: UnitProgress::new(
+                minimums.residency_units,
+                total_units_completed - transfer_units,
+            ),
             requirements_summary,
             next_courses_to_take,
+            gpa,
+            unknown_grades,
+            custom_requirements: vec![],
         })
     }
 
@@ -109,18 +183,22 @@ impl DegreeProgressProcessor {
     ) -> Result<Vec<NextCourseRecommendation>, Box<dyn std::error::Error>> {
         let mut recommendations = Vec::new();
 
-        // Build set of completed course codes for filtering
+        // Build set of completed course codes for filtering. Cross-listed courses are
+        // compared by their canonical code, so that no other listing of a taken course is
+        // recommended
         let completed_courses: HashSet<String> = requirements
             .iter()
             .flat_map(|r| &r.courses)
             .filter(|c| {
-                if let Some(ref grade) = c.grade {
-                    GradeValidator::is_passing_grade(grade)
+                if matches!(c.status, CourseStatus::Transfer) {
+                    true
+                } else if let Some(ref grade) = c.grade {
+                    self.requirements_config.grade_scale.is_passing_grade(grade)
                 } else {
                     false
                 }
             })
-            .map(|c| c.course_code.clone())
+            .map(|c| self.cross_listings.canonical(&c.course_code))
             .collect();
 
         // Collect recommendations from incomplete subrequirements
@@ -142,12 +220,17 @@ impl DegreeProgressProcessor {
                 let available_courses: Vec<EligibleCourse> = subreq
                     .eligible_courses
                     .iter()
-                    .filter(|course| !completed_courses.contains(&course.full_code))
+                    .filter(|course| {
+                        !completed_courses
+                            .contains(&self.cross_listings.canonical(&course.full_code))
+                    })
                     .cloned()
                     .collect();
 
-                // Only add if there are available courses and units remaining
-                if !available_courses.is_empty() && subreq.units_remaining > 0.0 {
+                // Only add if there are available courses and units or courses remaining
+                let still_needed =
+                    subreq.units_remaining > 0.0 || subreq.courses_needed.unwrap_or(0) > 0;
+                if !available_courses.is_empty() && still_needed {
                     recommendations.push(NextCourseRecommendation {
                         subrequirement_title: subreq.title.clone(),
                         priority,
@@ -178,17 +261,18 @@ impl DegreeProgressProcessor {
     pub fn match_courses_to_subrequirement(
         &self,
         completed_courses: &[CourseRequirement],
-        subreq_config: &super::config::SubrequirementConfig,
+        subreq_config: &SubrequirementConfig,
     ) -> Vec<CourseRequirement> {
         completed_courses
             .iter()
             .filter(|course| {
-                // Check if course is in eligible_courses list
+                // Check if course, or another listing of it, is in eligible_courses list
                 if !subreq_config.eligible_courses.is_empty() {
-                    return subreq_config
-                        .eligible_courses
-                        .iter()
-                        .any(|eligible| course.course_code.contains(eligible));
+                    let listings = self.cross_listings.also_offered_as(&course.course_code);
+                    return subreq_config.eligible_courses.iter().any(|eligible| {
+                        course.course_code.contains(eligible)
+                            || listings.iter().any(|l| l.contains(eligible))
+                    });
                 }
 
                 // Check if course is in specified departments
@@ -206,8 +290,348 @@ impl DegreeProgressProcessor {
             .collect()
     }
 
+    /// Computes progress towards a requirement set that the student defined themselves, from
+    /// the courses on their audit. A course that's listed under several of the audit's
+    /// requirements is counted once, and counts towards at most one of the set's
+    /// subrequirements: the first one that it matches and that still needs units, as on an
+    /// official audit. Courses that the student is taking now are listed, but not counted.
+    ///
+    /// # Arguments
+    /// * `audit` - Parsed degree audit data
+    /// * `requirement_id` - The ID of the requirement set
+    /// * `name` - The name of the requirement set
+    /// * `subrequirements` - The requirement set's subrequirements
+    ///
+    /// # Returns
+    /// * The progress towards each subrequirement, and the set as a whole
+    pub fn compute_custom_progress(
+        &self,
+        audit: &DegreeAudit,
+        requirement_id: i64,
+        name: &str,
+        subrequirements: &[SubrequirementConfig],
+    ) -> CustomRequirementProgress {
+        let scale = &self.requirements_config.grade_scale;
+
+        let mut seen = HashSet::new();
+        let (mut completed, mut in_progress) = (vec![], vec![]);
+        for course in audit.requirements.iter().flat_map(|r| &r.courses) {
+            if !seen.insert(self.cross_listings.canonical(&course.course_code)) {
+                continue;
+            }
+
+            match (&course.status, &course.grade) {
+                (CourseStatus::Transfer, _) => completed.push(course.clone()),
+                (_, Some(grade)) if scale.is_passing_grade(grade) => completed.push(course.clone()),
+                (CourseStatus::Completed, None) => completed.push(course.clone()),
+                (CourseStatus::InProgress, None) => in_progress.push(course.clone()),
+                _ => {}
+            }
+        }
+
+        let mut used = HashSet::new();
+        let subrequirements: Vec<CustomSubrequirementProgress> = subrequirements
+            .iter()
+            .map(|config| {
+                let mut units_completed = 0.0;
+                let mut courses_completed = vec![];
+                for course in self.match_courses_to_subrequirement(&completed, config) {
+                    if units_completed >= config.required_units {
+                        break;
+                    }
+                    if !matches_level(&course.course_code, &config.level_filters)
+                        || !used.insert(course.course_code.clone())
+                    {
+                        continue;
+                    }
+
+                    units_completed += course.units.unwrap_or(0.0);
+                    courses_completed.push(course.course_code);
+                }
+
+                let courses_in_progress: Vec<String> = self
+                    .match_courses_to_subrequirement(&in_progress, config)
+                    .into_iter()
+                    .filter(|c| matches_level(&c.course_code, &config.level_filters))
+                    .map(|c| c.course_code)
+                    .collect();
+
+                let units_remaining = (config.required_units - units_completed).max(0.0);
+                CustomSubrequirementProgress {
+                    title: config.title.clone(),
+                    status: custom_status(
+                        units_remaining,
+                        units_completed > 0.0 || !courses_in_progress.is_empty(),
+                    ),
+                    units_required: config.required_units,
+                    units_completed: units_completed.min(config.required_units),
+                    units_remaining,
+                    courses_completed,
+                    courses_in_progress,
+                }
+            })
+            .collect();
+
+        let units_required: f32 = subrequirements.iter().map(|s| s.units_required).sum();
+        let units_completed: f32 = subrequirements.iter().map(|s| s.units_completed).sum();
+        let units_remaining = (units_required - units_completed).max(0.0);
+        let started = subrequirements
+            .iter()
+            .any(|s| !matches!(s.status, RequirementStatus::NotStarted));
+        CustomRequirementProgress {
+            requirement_id,
+            name: name.to_owned(),
+            status: custom_status(units_remaining, started),
+            units_required,
+            units_completed,
+            units_remaining,
+            subrequirements,
+        }
+    }
+
     /// Gets the requirements configuration
     pub fn config(&self) -> &RequirementsConfig {
         &self.requirements_config
     }
 }
+
+/// Whether a course (e.g., `MATH 170A`) is upper-division, i.e., numbered 100 to 199
+pub(super) fn is_upper_division(course_code: &str) -> bool {
+    let number = course_code.split_whitespace().nth(1).unwrap_or("");
+    let digits = number
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(number.len());
+    matches!(number[..digits].parse::<u32>(), Ok(100..=199))
+}
+
+/// Whether a course (e.g., `CSE 100`) is at one of the given levels: `l` (lower-division,
+/// numbered below 100), `u` (upper-division), or `g` (graduate, numbered 200 and up). A
+/// course matches if no levels are given
+fn matches_level(course_code: &str, level_filters: &[String]) -> bool {
+    if level_filters.is_empty() {
+        return true;
+    }
+
+    let number = course_code.split_whitespace().nth(1).unwrap_or("");
+    let digits = number
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(number.len());
+    let level = match number[..digits].parse::<u32>() {
+        Ok(0..=99) => "l",
+        Ok(100..=199) => "u",
+        Ok(_) => "g",
+        Err(_) => return false,
+    };
+    level_filters.iter().any(|f| f.eq_ignore_ascii_case(level))
+}
+
+/// The status of a custom requirement, or one of its subrequirements
+fn custom_status(units_remaining: f32, started: bool) -> RequirementStatus {
+    if units_remaining <= 0.0 {
+        RequirementStatus::Complete
+    } else if started {
+        RequirementStatus::InProgress
+    } else {
+        RequirementStatus::NotStarted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::degree_audit::config::{
+        MajorRequirements, SubrequirementConfig, UnitRequirements, DEFAULT_RESIDENCY_UNITS,
+    };
+
+    fn course(code: &str, units: f32, status: CourseStatus) -> CourseRequirement {
+        CourseRequirement {
+            course_code: code.to_owned(),
+            title: None,
+            units: Some(units),
+            grade: Some("A".to_owned()),
+            term: None,
+            status,
+            source: None,
+        }
+    }
+
+    #[test]
+    fn test_unit_minimums_from_program() {
+        let mut config = RequirementsConfig::empty();
+        config.majors.insert(
+            "MA30".to_owned(),
+            MajorRequirements {
+                major_code: "MA30".to_owned(),
+                major_name: "Mathematics-Computer Science".to_owned(),
+                units: UnitRequirements {
+                    total_units: Some(184.0),
+                    upper_division_units: Some(72.0),
+                    residency_units: None,
+                },
+                requirements: vec![],
+            },
+        );
+
+        let audit = DegreeAudit {
+            audit_id: "audit".to_owned(),
+            student_info: StudentInfo {
+                student_id: None,
+                name: None,
+                major: Some("MA30".to_owned()),
+                college: None,
+            },
+            requirements: vec![Requirement {
+                category: "Major".to_owned(),
+                name: "Major".to_owned(),
+                status: RequirementStatus::InProgress,
+                credits_required: None,
+                credits_completed: None,
+                courses: vec![
+                    course("MATH 20A", 4.0, CourseStatus::Transfer),
+                    course("MATH 170A", 4.0, CourseStatus::Completed),
+                    course("CSE 8B", 4.0, CourseStatus::Completed),
+                ],
+                subrequirements: vec![],
+            }],
+            scraped_at: String::new(),
+            parse_warnings: vec![],
+            report_version: None,
+        };
+
+        let progress = DegreeProgressProcessor::new(config)
+            .compute_degree_progress(&audit)
+            .unwrap();
+        assert_eq!(184.0, progress.total_units_required);
+        assert_eq!(172.0, progress.total_units_remaining);
+        assert_eq!(
+            (72.0, 4.0, 68.0),
+            (
+                progress.upper_division.required,
+                progress.upper_division.completed,
+                progress.upper_division.remaining
+            )
+        );
+        // The major doesn't set a residency minimum, so the university's applies
+        assert_eq!(
+            (DEFAULT_RESIDENCY_UNITS, 8.0),
+            (progress.residency.required, progress.residency.completed)
+        );
+    }
+
+    #[test]
+    fn test_cross_listed_courses_match() {
+        let subreq = SubrequirementConfig {
+            title: "Data Structures".to_owned(),
+            required_units: 4.0,
+            eligible_courses: vec!["CSE 100".to_owned()],
+            departments: vec![],
+            level_filters: vec![],
+        };
+        let taken = [course("MATH 176", 4.0, CourseStatus::Completed)];
+
+        let processor = DegreeProgressProcessor::new(RequirementsConfig::empty());
+        assert!(processor
+            .match_courses_to_subrequirement(&taken, &subreq)
+            .is_empty());
+
+        let processor =
+            processor.with_cross_listings(CrossListings::from_pairs([("CSE 100", "MATH 176")]));
+        assert_eq!(
+            1,
+            processor
+                .match_courses_to_subrequirement(&taken, &subreq)
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_custom_requirement_progress() {
+        let subreq =
+            |title: &str, units: f32, courses: &[&str], depts: &[&str], levels: &[&str]| {
+                SubrequirementConfig {
+                    title: title.to_owned(),
+                    required_units: units,
+                    eligible_courses: courses.iter().map(|c| c.to_string()).collect(),
+                    departments: depts.iter().map(|d| d.to_string()).collect(),
+                    level_filters: levels.iter().map(|l| l.to_string()).collect(),
+                }
+            };
+        let set = [
+            subreq("General Chemistry", 8.0, &["CHEM 6A", "CHEM 6B"], &[], &[]),
+            subreq("Lower-Division Biology", 4.0, &[], &["BILD"], &["l"]),
+            subreq(
+                "Upper-Division Biology",
+                4.0,
+                &[],
+                &["BILD", "BIBC"],
+                &["u"],
+            ),
+        ];
+
+        let mut taking = course("CHEM 6B", 4.0, CourseStatus::InProgress);
+        taking.grade = None;
+        let requirement = |courses| Requirement {
+            category: "GE".to_owned(),
+            name: "GE".to_owned(),
+            status: RequirementStatus::InProgress,
+            credits_required: None,
+            credits_completed: None,
+            courses,
+            subrequirements: vec![],
+        };
+        let audit = DegreeAudit {
+            audit_id: "audit".to_owned(),
+            student_info: StudentInfo {
+                student_id: None,
+                name: None,
+                major: None,
+                college: None,
+            },
+            // CHEM 6A is listed under two requirements, but only counts once
+            requirements: vec![
+                requirement(vec![
+                    course("CHEM 6A", 4.0, CourseStatus::Completed),
+                    course("BILD 1", 4.0, CourseStatus::Completed),
+                    taking,
+                ]),
+                requirement(vec![
+                    course("CHEM 6A", 4.0, CourseStatus::Completed),
+                    course("BIBC 102", 4.0, CourseStatus::Completed),
+                ]),
+            ],
+            scraped_at: String::new(),
+            parse_warnings: vec![],
+            report_version: None,
+        };
+
+        let progress = DegreeProgressProcessor::new(RequirementsConfig::empty())
+            .compute_custom_progress(&audit, 1, "Pre-med", &set);
+        assert_eq!(
+            (16.0, 12.0, 4.0),
+            (
+                progress.units_required,
+                progress.units_completed,
+                progress.units_remaining
+            )
+        );
+        assert!(matches!(progress.status, RequirementStatus::InProgress));
+
+        let chemistry = &progress.subrequirements[0];
+        assert_eq!(vec!["CHEM 6A"], chemistry.courses_completed);
+        assert_eq!(vec!["CHEM 6B"], chemistry.courses_in_progress);
+        assert!(matches!(chemistry.status, RequirementStatus::InProgress));
+        assert_eq!(
+            vec!["BILD 1"],
+            progress.subrequirements[1].courses_completed
+        );
+        // BILD 1 is lower-division, so it doesn't count towards the upper-division biology
+        assert_eq!(
+            vec!["BIBC 102"],
+            progress.subrequirements[2].courses_completed
+        );
+        assert!(matches!(
+            progress.subrequirements[2].status,
+            RequirementStatus::Complete
+        ));
+    }
+}
@@ -0,0 +1,167 @@
+//! Soft daily quota on the number of audits run per session.
+//!
+//! DARS limits how many audits a student can run, so we stop triggering new audits for a
+//! session once it reaches a configurable number per day, well before DARS itself starts
+//! refusing. Counts are kept in SQLite so that restarting doesn't reset them.
+
+use super::cache::SessionKey;
+use super::error::DegreeAuditError;
+use chrono::Local;
+use rusqlite::{Connection, OptionalExtension};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Default number of audits a session may trigger per day.
+pub const DEFAULT_DAILY_AUDIT_QUOTA: u32 = 10;
+
+/// A session's usage of its quota for today.
+#[derive(Debug, Clone)]
+pub struct QuotaUsage {
+    /// The session, truncated for privacy
+    pub session: String,
+    pub used: u32,
+    pub remaining: u32,
+}
+
+/// Tracks how many audits each session has triggered per day.
+pub struct AuditQuota {
+    db: Mutex<Connection>,
+    daily_limit: u32,
+}
+
+impl AuditQuota {
+    /// Opens (or creates) the quota database at the given path.
+    ///
+    /// Falls back to an in-memory database if the file can't be opened.
+    pub fn open(path: &str, daily_limit: u32) -> Self {
+        let conn = Connection::open(path).unwrap_or_else(|e| {
+            warn!(
+                "Failed to open audit quota database {}: {}. Using memory.",
+                path, e
+            );
+            Connection::open_in_memory().expect("Failed to open in-memory database")
+        });
+
+        Self::from_connection(conn, daily_limit)
+    }
+
+    /// Creates a quota tracker that isn't persisted.
+    pub fn in_memory(daily_limit: u32) -> Self {
+        let conn = Connection::open_in_memory().expect("Failed to open in-memory database");
+        Self::from_connection(conn, daily_limit)
+    }
+
+    fn from_connection(conn: Connection, daily_limit: u32) -> Self {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS audit_quota (
+                session_key TEXT NOT NULL,
+                day DATE NOT NULL,
+                count INTEGER NOT NULL,
+                PRIMARY KEY (session_key, day)
+            )",
+        )
+        .expect("Failed to initialize audit quota schema");
+
+        // Only today's counts matter
+        if let Err(e) = conn.execute("DELETE FROM audit_quota WHERE day < ?", [today()]) {
+            warn!("Failed to prune old audit quota counts: {}", e);
+        }
+
+        Self {
+            db: Mutex::new(conn),
+            daily_limit,
+        }
+    }
+
+    /// Returns the number of audits each session may trigger per day.
+    pub fn daily_limit(&self) -> u32 {
+        self.daily_limit
+    }
+
+    /// Records that the session is about to trigger an audit.
+    ///
+    /// Returns `AuditQuotaExceeded` (without recording anything) if the session has already
+    /// used up today's quota.
+    pub fn try_consume(&self, key: &SessionKey) -> Result<(), DegreeAuditError> {
+        let db = self.db.lock().unwrap();
+        let day = today();
+        let used: u32 = db
+            .query_row(
+                "SELECT count FROM audit_quota WHERE session_key = ?1 AND day = ?2",
+                (key.as_str(), &day),
+                |row| row.get(0),
+            )
+            .optional()
+            .ok()
+            .flatten()
+            .unwrap_or(0);
+
+        if used >= self.daily_limit {
+            return Err(DegreeAuditError::AuditQuotaExceeded {
+                used,
+                limit: self.daily_limit,
+            });
+        }
+
+        if let Err(e) = db.execute(
+            "INSERT INTO audit_quota (session_key, day, count) VALUES (?1, ?2, 1)
+             ON CONFLICT(session_key, day) DO UPDATE SET count = count + 1",
+            (key.as_str(), &day),
+        ) {
+            warn!("Failed to record audit for {}: {}", key, e);
+        }
+
+        Ok(())
+    }
+
+    /// Returns today's usage for every session that has triggered an audit today.
+    pub fn usage_today(&self) -> Vec<QuotaUsage> {
+        let db = self.db.lock().unwrap();
+        let Ok(mut stmt) = db.prepare(
+            "SELECT session_key, count FROM audit_quota WHERE day = ? ORDER BY count DESC",
+        ) else {
+            return vec![];
+        };
+
+        stmt.query_map([today()], |row| {
+            let key: String = row.get(0)?;
+            let used: u32 = row.get(1)?;
+            Ok(QuotaUsage {
+                session: SessionKey::from_hash(key).to_string(),
+                used,
+                remaining: self.daily_limit.saturating_sub(used),
+            })
+        })
+        .map(|rows| rows.filter_map(Result::ok).collect())
+        .unwrap_or_default()
+    }
+}
+
+/// Today's date, in `YYYY-MM-DD` format.
+fn today() -> String {
+    Local::now().format("%Y-%m-%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quota_is_per_session() {
+        let quota = AuditQuota::in_memory(2);
+        let a = SessionKey::from_cookie("session123");
+        let b = SessionKey::from_cookie("session456");
+
+        assert!(quota.try_consume(&a).is_ok());
+        assert!(quota.try_consume(&a).is_ok());
+        assert!(matches!(
+            quota.try_consume(&a),
+            Err(DegreeAuditError::AuditQuotaExceeded { used: 2, limit: 2 })
+        ));
+        assert!(quota.try_consume(&b).is_ok());
+
+        let usage = quota.usage_today();
+        assert_eq!(2, usage.len());
+        assert_eq!((2, 0), (usage[0].used, usage[0].remaining));
+    }
+}
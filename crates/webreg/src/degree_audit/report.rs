@@ -0,0 +1,477 @@
+//! A printable report of a degree audit, for students to bring to advising appointments.
+
+use chrono::Local;
+use printpdf::{
+    BuiltinFont, Color, IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference,
+    Rect, Rgb,
+};
+
+use crate::degree_audit::{
+    CourseRequirement, CourseStatus, DegreeAudit, DegreeProgress, RequirementStatus,
+};
+
+/// The page size (US Letter), in millimeters.
+const PAGE_WIDTH: f32 = 215.9;
+const PAGE_HEIGHT: f32 = 279.4;
+/// The space around each page's content, in millimeters.
+const MARGIN: f32 = 18.0;
+/// The space between lines, as a multiple of the font size.
+const LINE_SPACING: f32 = 1.35;
+/// The approximate width of an average Helvetica character, as a multiple of the font
+/// size. Used to wrap lines, since the built-in fonts don't come with their metrics.
+const CHAR_WIDTH: f32 = 0.5;
+/// The most eligible courses listed for each recommendation.
+const MAX_LISTED_COURSES: usize = 12;
+
+const TITLE_SIZE: f32 = 18.0;
+const HEADING_SIZE: f32 = 13.0;
+const BODY_SIZE: f32 = 9.5;
+
+/// Points per millimeter.
+const PT_PER_MM: f32 = 72.0 / 25.4;
+
+/// A report being laid out, top to bottom, over as many pages as it needs.
+struct Report {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    /// The baseline of the next line, from the bottom of the page, in millimeters.
+    y: f32,
+    pages: usize,
+}
+
+impl Report {
+    fn new(title: &str) -> Result<Self, printpdf::Error> {
+        let (doc, page, layer) =
+            PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Content");
+        let regular = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+        let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+        let layer = doc.get_page(page).get_layer(layer);
+
+        Ok(Self {
+            doc,
+            layer,
+            regular,
+            bold,
+            y: PAGE_HEIGHT - MARGIN,
+            pages: 1,
+        })
+    }
+
+    /// Makes sure that there's room for the given height on the page, starting a new page
+    /// if there isn't.
+    fn reserve(&mut self, height: f32) {
+        if self.y - height >= MARGIN {
+            return;
+        }
+
+        let (page, layer) = self
+            .doc
+            .add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Content");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = PAGE_HEIGHT - MARGIN;
+        self.pages += 1;
+    }
+
+    /// Writes text, wrapping it to fit the page.
+    ///
+    /// # Parameters
+    /// - `text`: The text.
+    /// - `size`: The font size, in points.
+    /// - `bold`: Whether to use the bold font.
+    /// - `indent`: How far to indent the text, in millimeters.
+    fn text(&mut self, text: &str, size: f32, bold: bool, indent: f32) {
+        let line_height = size * LINE_SPACING / PT_PER_MM;
+        let width = PAGE_WIDTH - 2.0 * MARGIN - indent;
+        let max_chars = (width * PT_PER_MM / (size * CHAR_WIDTH)) as usize;
+
+        for line in wrap(text, max_chars) {
+            self.reserve(line_height);
+            self.y -= line_height;
+            let font = if bold { &self.bold } else { &self.regular };
+            self.layer
+                .use_text(line, size, Mm(MARGIN + indent), Mm(self.y), font);
+        }
+    }
+
+    /// Adds vertical space.
+    fn gap(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    /// Writes a section heading, keeping it on the same page as the line after it.
+    fn heading(&mut self, text: &str) {
+        self.gap(3.0);
+        self.reserve((HEADING_SIZE + 2.0 * BODY_SIZE) * LINE_SPACING / PT_PER_MM);
+        self.text(text, HEADING_SIZE, true, 0.0);
+        self.gap(1.0);
+    }
+
+    /// Draws a bar showing how much of something is done.
+    fn progress_bar(&mut self, fraction: f32) {
+        let height = 5.0;
+        self.reserve(height + 2.0);
+        self.y -= height + 2.0;
+
+        let (left, right) = (MARGIN, PAGE_WIDTH - MARGIN);
+        let filled = left + (right - left) * fraction.clamp(0.0, 1.0);
+        self.layer
+            .set_fill_color(Color::Rgb(Rgb::new(0.9, 0.9, 0.9, None)));
+        self.layer.add_rect(Rect::new(
+            Mm(left),
+            Mm(self.y),
+            Mm(right),
+            Mm(self.y + height),
+        ));
+        self.layer
+            .set_fill_color(Color::Rgb(Rgb::new(0.06, 0.45, 0.27, None)));
+        self.layer.add_rect(Rect::new(
+            Mm(left),
+            Mm(self.y),
+            Mm(filled),
+            Mm(self.y + height),
+        ));
+        self.layer
+            .set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+    }
+}
+
+/// Renders a degree audit, along with the progress computed from it, as a PDF.
+///
+/// The report lists the student's overall progress, each requirement along with its
+/// subrequirements and the courses applied to them, and the courses recommended next.
+///
+/// # Parameters
+/// - `audit`: The audit.
+/// - `progress`: The progress computed from the audit.
+///
+/// # Returns
+/// The contents of the `.pdf` file.
+pub fn render_report(
+    audit: &DegreeAudit,
+    progress: &DegreeProgress,
+) -> Result<Vec<u8>, printpdf::Error> {
+    layout(audit, progress)?.doc.save_to_bytes()
+}
+
+/// Lays out the report.
+fn layout(audit: &DegreeAudit, progress: &DegreeProgress) -> Result<Report, printpdf::Error> {
+    let mut report = Report::new("Degree Audit Report")?;
+    report.text("Degree Audit Report", TITLE_SIZE, true, 0.0);
+    report.gap(2.0);
+
+    let student = &audit.student_info;
+    for (label, value) in [
+        ("Name", &student.name),
+        ("Student ID", &student.student_id),
+        ("Major", &student.major),
+        ("College", &student.college),
+    ] {
+        if let Some(value) = value {
+            report.text(&format!("{label}: {value}"), BODY_SIZE, false, 0.0);
+        }
+    }
+    report.text(
+        &format!(
+            "Audit {} (fetched {}), printed {}",
+            audit.audit_id,
+            audit.scraped_at,
+            Local::now().format("%Y-%m-%d %H:%M")
+        ),
+        BODY_SIZE,
+        false,
+        0.0,
+    );
+
+    report.heading("Overall Progress");
+    let fraction = if progress.total_units_required > 0.0 {
+        progress.total_units_completed / progress.total_units_required
+    } else {
+        1.0
+    };
+    report.text(
+        &format!(
+            "{:.1} of {:.1} units completed ({:.0}%), {:.1} remaining. Includes {:.1} transfer units.",
+            progress.total_units_completed,
+            progress.total_units_required,
+            fraction * 100.0,
+            progress.total_units_remaining,
+            progress.transfer_units
+        ),
+        BODY_SIZE,
+        false,
+        0.0,
+    );
+    report.progress_bar(fraction);
+    report.text(
+        &format!(
+            "Upper-division: {:.1} of {:.1} units. In residence: {:.1} of {:.1} units.",
+            progress.upper_division.completed,
+            progress.upper_division.required,
+            progress.residency.completed,
+            progress.residency.required
+        ),
+        BODY_SIZE,
+        false,
+        0.0,
+    );
+
+    report.heading("Requirements");
+    for req in &audit.requirements {
+        let units = match (req.credits_completed, req.credits_required) {
+            (Some(done), Some(required)) => format!(" - {done:.1}/{required:.1} units"),
+            _ => String::new(),
+        };
+        report.gap(1.5);
+        report.text(
+            &format!(
+                "[{}] {}: {}{units}",
+                requirement_status(&req.status),
+                req.category,
+                req.name
+            ),
+            BODY_SIZE,
+            true,
+            0.0,
+        );
+
+        for course in &req.courses {
+            report.text(&course_line(course), BODY_SIZE, false, 5.0);
+        }
+
+        for sub in &req.subrequirements {
+            let mut line = format!(
+                "[{}] {} - {:.1}/{:.1} units",
+                requirement_status(&sub.status),
+                sub.title,
+                sub.units_completed,
+                sub.required_units
+            );
+            if let Some(needs) = &sub.needs {
+                line.push_str(&format!(" ({})", needs.trim()));
+            }
+            report.text(&line, BODY_SIZE, false, 5.0);
+
+            for course in &sub.completed_courses {
+                report.text(&course_line(course), BODY_SIZE, false, 10.0);
+            }
+        }
+    }
+
+    if !progress.next_courses_to_take.is_empty() {
+        report.heading("Recommended Next Courses");
+        for rec in &progress.next_courses_to_take {
+            report.text(
+                &format!(
+                    "{}. {} ({:.1} units needed)",
+                    rec.priority, rec.subrequirement_title, rec.units_needed
+                ),
+                BODY_SIZE,
+                true,
+                0.0,
+            );
+
+            let mut courses: Vec<_> = rec
+                .eligible_courses
+                .iter()
+                .take(MAX_LISTED_COURSES)
+                .map(|c| c.full_code.as_str())
+                .collect();
+            if rec.eligible_courses.len() > MAX_LISTED_COURSES {
+                courses.push("...");
+            }
+            if !courses.is_empty() {
+                report.text(&courses.join(", "), BODY_SIZE, false, 5.0);
+            }
+        }
+    }
+
+    if !audit.parse_warnings.is_empty() {
+        report.heading("Notes");
+        report.text(
+            &format!(
+                "{} part(s) of the audit couldn't be read and aren't shown. Check the audit \
+                 on the official site before relying on this report.",
+                audit.parse_warnings.len()
+            ),
+            BODY_SIZE,
+            false,
+            0.0,
+        );
+    }
+
+    Ok(report)
+}
+
+/// Describes a course that applies to a requirement.
+fn course_line(course: &CourseRequirement) -> String {
+    let mut parts = vec![course.course_code.clone()];
+    if let Some(title) = &course.title {
+        parts.push(title.clone());
+    }
+    if let Some(term) = &course.term {
+        parts.push(term.clone());
+    }
+    if let Some(grade) = &course.grade {
+        parts.push(format!("grade {grade}"));
+    }
+    if let Some(units) = course.units {
+        parts.push(format!("{units:.1} units"));
+    }
+
+    let status = match course.status {
+        CourseStatus::Completed => "Done",
+        CourseStatus::InProgress => "In progress",
+        CourseStatus::Planned => "Planned",
+        CourseStatus::Required => "Needed",
+        CourseStatus::Transfer => "Transfer",
+    };
+    let mut line = format!("{status}: {}", parts.join(" - "));
+    if let Some(source) = &course.source {
+        line.push_str(&format!(" (from {source})"));
+    }
+
+    line
+}
+
+fn requirement_status(status: &RequirementStatus) -> &'static str {
+    match status {
+        RequirementStatus::Complete => "OK",
+        RequirementStatus::InProgress => "IP",
+        RequirementStatus::NotStarted => "NO",
+        RequirementStatus::NotApplicable => "N/A",
+    }
+}
+
+/// Splits text into lines of at most `max_chars` characters, breaking between words where
+/// possible.
+fn wrap(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut lines = vec![];
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        let mut word = word;
+        // Words that are too long for a line of their own are split up
+        while word.chars().count() > max_chars {
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            let split = word
+                .char_indices()
+                .nth(max_chars)
+                .map_or(word.len(), |(i, _)| i);
+            lines.push(word[..split].to_owned());
+            word = &word[split..];
+        }
+
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > max_chars {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::degree_audit::{
+        EligibleCourse, NextCourseRecommendation, Requirement, StudentInfo, Subrequirement,
+        UnitProgress,
+    };
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(vec!["a bc", "def"], wrap("a bc  def", 4));
+        assert_eq!(vec!["abcd", "ef g"], wrap("abcdef g", 4));
+        assert_eq!(vec![""], wrap("", 4));
+    }
+
+    #[test]
+    fn test_report_spans_pages() {
+        let course = CourseRequirement {
+            course_code: "CSE 100".to_owned(),
+            title: Some("ADVANCED DATA STRUCTURES".to_owned()),
+            units: Some(4.0),
+            grade: Some("A".to_owned()),
+            term: Some("FA23".to_owned()),
+            status: CourseStatus::Completed,
+            source: None,
+        };
+        let subrequirement = Subrequirement {
+            id: "sub".to_owned(),
+            title: "Upper Division".to_owned(),
+            required_units: 16.0,
+            required_courses: None,
+            needs: Some("NEEDS: 12.00 UNITS".to_owned()),
+            courses_needed: None,
+            units_completed: 4.0,
+            units_remaining: 12.0,
+            status: RequirementStatus::InProgress,
+            eligible_courses: vec![],
+            completed_courses: vec![course.clone(); 3],
+            category_groups: vec![],
+        };
+        let audit = DegreeAudit {
+            audit_id: "audit".to_owned(),
+            student_info: StudentInfo {
+                student_id: Some("A12345678".to_owned()),
+                name: Some("Triton, King".to_owned()),
+                major: Some("CS26".to_owned()),
+                college: None,
+            },
+            requirements: (0..20)
+                .map(|i| Requirement {
+                    category: "MAJOR".to_owned(),
+                    name: format!("Requirement {i}"),
+                    status: RequirementStatus::InProgress,
+                    credits_required: Some(16.0),
+                    credits_completed: Some(4.0),
+                    courses: vec![course.clone()],
+                    subrequirements: vec![subrequirement.clone(); 2],
+                })
+                .collect(),
+            scraped_at: "2024-10-01T00:00:00Z".to_owned(),
+            parse_warnings: vec![],
+            report_version: None,
+        };
+        let progress = DegreeProgress {
+            audit_id: "audit".to_owned(),
+            student_info: audit.student_info.clone(),
+            total_units_required: 180.0,
+            total_units_completed: 90.0,
+            total_units_remaining: 90.0,
+            transfer_units: 0.0,
+            upper_division: UnitProgress::new(60.0, 24.0),
+            residency: UnitProgress::new(36.0, 90.0),
+            requirements_summary: vec![],
+            next_courses_to_take: vec![NextCourseRecommendation {
+                subrequirement_title: "Upper Division".to_owned(),
+                priority: 1,
+                eligible_courses: vec![EligibleCourse {
+                    department: "CSE".to_owned(),
+                    course_number: "101".to_owned(),
+                    full_code: "CSE 101".to_owned(),
+                }],
+                units_needed: 12.0,
+            }],
+            gpa: None,
+            unknown_grades: vec![],
+            custom_requirements: vec![],
+        };
+
+        assert!(layout(&audit, &progress).unwrap().pages > 1);
+        let pdf = render_report(&audit, &progress).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+    }
+}
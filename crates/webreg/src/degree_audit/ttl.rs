@@ -0,0 +1,107 @@
+//! Cache TTLs that follow the academic calendar.
+//!
+//! Audits rarely change in the middle of a quarter, but change often once grades start
+//! posting after the last day of instruction. Audits are therefore cached for hours most
+//! of the time, and for only a few minutes while grades are being posted.
+
+use crate::schedule::DateRange;
+use chrono::{Days, Local, NaiveDate};
+use std::time::Duration;
+
+/// Default TTL for audits outside of grade-posting weeks.
+pub const DEFAULT_AUDIT_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Default TTL for audits while grades are being posted.
+pub const DEFAULT_GRADE_POSTING_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// The number of days after a term's last day of instruction during which grades are
+/// considered to be posting. This covers finals week and the grade submission deadline.
+const GRADE_POSTING_DAYS: u64 = 14;
+
+/// Where a day falls in the academic calendar, as far as audits are concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalendarPhase {
+    /// Grades are unlikely to change.
+    Normal,
+    /// Grades for a term that just ended are being posted.
+    GradePosting,
+}
+
+/// Decides how long an audit is cached for based on the term calendar.
+#[derive(Debug, Clone)]
+pub struct AuditTtlPolicy {
+    /// The instruction dates of every known term
+    terms: Vec<DateRange>,
+    normal_ttl: Duration,
+    grade_posting_ttl: Duration,
+}
+
+impl AuditTtlPolicy {
+    /// Creates a policy for the given terms.
+    ///
+    /// # Parameters
+    /// - `terms`: The instruction dates of each term.
+    /// - `normal_ttl`: The TTL outside of grade-posting weeks.
+    /// - `grade_posting_ttl`: The TTL while grades are being posted.
+    pub fn new(terms: Vec<DateRange>, normal_ttl: Duration, grade_posting_ttl: Duration) -> Self {
+        Self {
+            terms,
+            normal_ttl,
+            grade_posting_ttl,
+        }
+    }
+
+    /// Gets the calendar phase of the given day.
+    pub fn phase(&self, day: NaiveDate) -> CalendarPhase {
+        let posting = self.terms.iter().any(|term| {
+            let posting_end = term
+                .end
+                .checked_add_days(Days::new(GRADE_POSTING_DAYS))
+                .unwrap_or(NaiveDate::MAX);
+            term.end < day && day <= posting_end
+        });
+
+        if posting {
+            CalendarPhase::GradePosting
+        } else {
+            CalendarPhase::Normal
+        }
+    }
+
+    /// Gets the TTL for audits cached on the given day.
+    pub fn ttl_on(&self, day: NaiveDate) -> Duration {
+        match self.phase(day) {
+            CalendarPhase::Normal => self.normal_ttl,
+            CalendarPhase::GradePosting => self.grade_posting_ttl,
+        }
+    }
+
+    /// Gets the TTL for audits cached right now.
+    pub fn current_ttl(&self) -> Duration {
+        self.ttl_on(Local::now().date_naive())
+    }
+}
+
+impl Default for AuditTtlPolicy {
+    fn default() -> Self {
+        Self::new(vec![], DEFAULT_AUDIT_TTL, DEFAULT_GRADE_POSTING_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grade_posting_window() {
+        let fall = DateRange::parse("2024-09-26", "2024-12-06").unwrap();
+        let policy = AuditTtlPolicy::new(vec![fall], DEFAULT_AUDIT_TTL, DEFAULT_GRADE_POSTING_TTL);
+        let day = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+
+        assert_eq!(CalendarPhase::Normal, policy.phase(day("2024-10-15")));
+        assert_eq!(CalendarPhase::Normal, policy.phase(day("2024-12-06")));
+        assert_eq!(CalendarPhase::GradePosting, policy.phase(day("2024-12-07")));
+        assert_eq!(DEFAULT_GRADE_POSTING_TTL, policy.ttl_on(day("2024-12-20")));
+        assert_eq!(DEFAULT_AUDIT_TTL, policy.ttl_on(day("2024-12-21")));
+    }
+}
@@ -0,0 +1,137 @@
+//! Detecting which version of the DARS report an audit was rendered by.
+//!
+//! DARS releases occasionally restructure the report, renaming the classes of the blocks
+//! that the parser looks for. Each known version has a [`SelectorProfile`] with the
+//! selectors for those blocks, and a version is detected by looking for its markers in
+//! the page. Supporting a new version means adding a variant, its profile, and its markers
+//! here; the rest of the parser is shared.
+
+use std::sync::LazyLock;
+
+use scraper::{Html, Selector};
+
+pub use webreg_types::degree_audit::ReportVersion;
+
+/// The selectors for the blocks of a version of the report.
+pub struct SelectorProfile {
+    /// A requirement.
+    pub requirement: Selector,
+    /// A requirement's title, within the requirement.
+    pub requirement_title: Selector,
+    /// A subrequirement, within a requirement.
+    pub subrequirement: Selector,
+    /// A subrequirement's title, within the subrequirement.
+    pub subrequirement_title: Selector,
+    /// A table of completed courses.
+    pub completed_courses: Selector,
+    /// A course in a table of completed courses.
+    pub taken_course: Selector,
+}
+
+impl SelectorProfile {
+    fn new(selectors: [&str; 6]) -> Self {
+        let [requirement, requirement_title, subrequirement, subrequirement_title, completed_courses, taken_course] =
+            selectors.map(|s| Selector::parse(s).unwrap());
+        Self {
+            requirement,
+            requirement_title,
+            subrequirement,
+            subrequirement_title,
+            completed_courses,
+            taken_course,
+        }
+    }
+}
+
+static CLASSIC_PROFILE: LazyLock<SelectorProfile> = LazyLock::new(|| {
+    SelectorProfile::new([
+        "div.requirement",
+        ".reqTitle",
+        "div.subrequirement",
+        ".subreqTitle",
+        "table.completedCourses",
+        "tr.takenCourse",
+    ])
+});
+static SELF_SERVICE_PROFILE: LazyLock<SelectorProfile> = LazyLock::new(|| {
+    SelectorProfile::new([
+        "div.requirement-block",
+        ".requirement-title",
+        "div.subrequirement-block",
+        ".subrequirement-title",
+        "table.completed-courses",
+        "tr.taken-course",
+    ])
+});
+
+/// The markers of each version, checked in order. A version's markers are checked before
+/// those of the versions it was derived from.
+static VERSION_MARKERS: LazyLock<Vec<(ReportVersion, Selector)>> = LazyLock::new(|| {
+    [
+        (
+            ReportVersion::SelfService,
+            "div.requirement-block, div.subrequirement-block",
+        ),
+        (
+            ReportVersion::Classic,
+            "div.requirement, div.subrequirement, .reqTitle",
+        ),
+    ]
+    .into_iter()
+    .map(|(version, markers)| (version, Selector::parse(markers).unwrap()))
+    .collect()
+});
+
+impl SelectorProfile {
+    /// Gets the selectors for a version's blocks.
+    pub fn of(version: ReportVersion) -> &'static Self {
+        match version {
+            ReportVersion::Classic => &CLASSIC_PROFILE,
+            ReportVersion::SelfService => &SELF_SERVICE_PROFILE,
+        }
+    }
+}
+
+/// Detects which version of the report a page is.
+///
+/// # Parameters
+/// - `document`: The page.
+///
+/// # Returns
+/// The version, or nothing if the page has none of the known versions' markers (e.g., if
+/// DARS was restructured again).
+pub fn detect_version(document: &Html) -> Option<ReportVersion> {
+    VERSION_MARKERS
+        .iter()
+        .find(|(_, markers)| document.select(markers).next().is_some())
+        .map(|(version, _)| *version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_version() {
+        let classic = Html::parse_document(
+            r#"<div class="requirement Status_OK"><span class="reqTitle">GE</span></div>"#,
+        );
+        assert_eq!(Some(ReportVersion::Classic), detect_version(&classic));
+
+        let self_service = Html::parse_document(
+            r#"<div class="requirement-block Status_IP">
+                <span class="requirement-title">Major</span>
+            </div>"#,
+        );
+        let version = detect_version(&self_service).unwrap();
+        assert_eq!(ReportVersion::SelfService, version);
+        let title = self_service
+            .select(&SelectorProfile::of(version).requirement_title)
+            .next()
+            .unwrap();
+        assert_eq!("Major", title.text().collect::<String>());
+
+        let unknown = Html::parse_document("<p>Your audit could not be run.</p>");
+        assert_eq!(None, detect_version(&unknown));
+    }
+}
@@ -0,0 +1,232 @@
+//! Estimating how long DARS takes to generate an audit, and keeping track of audits that
+//! are being generated in the background.
+//!
+//! DARS can take anywhere from a few seconds to a few minutes to run an audit, depending on
+//! how busy it is, which mostly depends on the time of day. So the time that each audit took
+//! is recorded by the hour of the day that it was started in, and the wait for a new audit is
+//! estimated from the audits started in the same hour. This lets clients that ask for an
+//! audit in the background (see `/degree_audit?async=true`) show how long it'll be.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::Serialize;
+
+use super::cache::SessionKey;
+
+/// The wait that's estimated before any audits have been timed.
+pub const DEFAULT_AUDIT_WAIT: Duration = Duration::from_secs(30);
+/// The number of recent durations kept for each hour of the day.
+const MAX_SAMPLES_PER_HOUR: usize = 50;
+/// The fewest durations in an hour of the day that its estimate is based on. Hours with
+/// fewer are estimated from every hour's durations instead.
+const MIN_SAMPLES_PER_HOUR: usize = 3;
+/// How long the outcome of a background audit is kept for after it finishes.
+const FINISHED_AUDIT_RETENTION: Duration = Duration::from_secs(60 * 60);
+/// The length of the handles given to background audits.
+const HANDLE_LENGTH: usize = 24;
+
+/// The recent durations of audits, by the hour of the day that they were started in.
+pub struct WaitEstimator {
+    samples: Mutex<Vec<VecDeque<Duration>>>,
+}
+
+impl Default for WaitEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WaitEstimator {
+    /// Creates an estimator with no durations recorded.
+    pub fn new() -> Self {
+        Self {
+            samples: Mutex::new(vec![VecDeque::new(); 24]),
+        }
+    }
+
+    /// Records how long an audit took.
+    ///
+    /// # Parameters
+    /// - `hour`: The hour of the day (`0` to `23`, in local time) that the audit was started
+    ///   in.
+    /// - `duration`: How long the audit took.
+    pub fn record(&self, hour: u32, duration: Duration) {
+        let mut samples = self.samples.lock().unwrap();
+        let hour = &mut samples[hour as usize % 24];
+        if hour.len() == MAX_SAMPLES_PER_HOUR {
+            hour.pop_front();
+        }
+        hour.push_back(duration);
+    }
+
+    /// Estimates how long an audit will take.
+    ///
+    /// # Parameters
+    /// - `hour`: The hour of the day (`0` to `23`, in local time) that the audit is started
+    ///   in.
+    ///
+    /// # Returns
+    /// The median duration of the audits started in the same hour, or of every audit if too
+    /// few were, or [`DEFAULT_AUDIT_WAIT`] if none have been timed.
+    pub fn estimate(&self, hour: u32) -> Duration {
+        let samples = self.samples.lock().unwrap();
+        let in_hour = &samples[hour as usize % 24];
+        let mut durations: Vec<Duration> = if in_hour.len() >= MIN_SAMPLES_PER_HOUR {
+            in_hour.iter().copied().collect()
+        } else {
+            samples.iter().flatten().copied().collect()
+        };
+
+        if durations.is_empty() {
+            return DEFAULT_AUDIT_WAIT;
+        }
+        durations.sort();
+        durations[durations.len() / 2]
+    }
+}
+
+/// How a background audit is going.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PendingAuditStatus {
+    Pending,
+    /// The audit is cached, and can be fetched with `/degree_audit`.
+    Completed,
+    Failed {
+        message: String,
+    },
+}
+
+/// An audit that's being, or was, generated in the background.
+struct PendingAudit {
+    /// The session that asked for the audit, which is the only one that can see it.
+    owner: SessionKey,
+    started: Instant,
+    estimate: Duration,
+    status: PendingAuditStatus,
+    finished: Option<Instant>,
+}
+
+/// A background audit, as seen by the session that asked for it.
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingAuditView {
+    #[serde(flatten)]
+    pub status: PendingAuditStatus,
+    pub elapsed_seconds: u64,
+    /// How much longer the audit is expected to take, if it's still pending. An audit that's
+    /// taking longer than expected is always expected to take at least another second.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_wait_seconds: Option<u64>,
+}
+
+/// The audits that are being, or were recently, generated in the background, by handle.
+#[derive(Default)]
+pub struct PendingAudits {
+    audits: DashMap<String, PendingAudit>,
+}
+
+impl PendingAudits {
+    /// Starts keeping track of a background audit. Audits that finished a while ago are
+    /// forgotten.
+    ///
+    /// # Parameters
+    /// - `owner`: The session that asked for the audit.
+    /// - `estimate`: How long the audit is expected to take.
+    ///
+    /// # Returns
+    /// The audit's handle.
+    pub fn start(&self, owner: SessionKey, estimate: Duration) -> String {
+        self.audits.retain(|_, audit| {
+            audit
+                .finished
+                .is_none_or(|finished| finished.elapsed() < FINISHED_AUDIT_RETENTION)
+        });
+
+        let handle: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(HANDLE_LENGTH)
+            .map(char::from)
+            .collect();
+        self.audits.insert(
+            handle.clone(),
+            PendingAudit {
+                owner,
+                started: Instant::now(),
+                estimate,
+                status: PendingAuditStatus::Pending,
+                finished: None,
+            },
+        );
+        handle
+    }
+
+    /// Records how a background audit went.
+    ///
+    /// # Parameters
+    /// - `handle`: The audit's handle.
+    /// - `result`: Nothing if the audit was cached, or why it couldn't be fetched.
+    pub fn finish(&self, handle: &str, result: Result<(), String>) {
+        if let Some(mut audit) = self.audits.get_mut(handle) {
+            audit.status = match result {
+                Ok(()) => PendingAuditStatus::Completed,
+                Err(message) => PendingAuditStatus::Failed { message },
+            };
+            audit.finished = Some(Instant::now());
+        }
+    }
+
+    /// Gets how a background audit is going.
+    ///
+    /// # Parameters
+    /// - `handle`: The audit's handle.
+    /// - `owner`: The session asking.
+    ///
+    /// # Returns
+    /// The audit, or `None` if there's no such audit, or it belongs to another session.
+    pub fn get(&self, handle: &str, owner: &SessionKey) -> Option<PendingAuditView> {
+        let audit = self.audits.get(handle)?;
+        if &audit.owner != owner {
+            return None;
+        }
+
+        let elapsed = audit
+            .finished
+            .unwrap_or_else(Instant::now)
+            .duration_since(audit.started);
+        let estimated_wait_seconds = (audit.status == PendingAuditStatus::Pending)
+            .then(|| audit.estimate.saturating_sub(elapsed).as_secs().max(1));
+        Some(PendingAuditView {
+            status: audit.status.clone(),
+            elapsed_seconds: elapsed.as_secs(),
+            estimated_wait_seconds,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait_estimate() {
+        let estimator = WaitEstimator::new();
+        assert_eq!(DEFAULT_AUDIT_WAIT, estimator.estimate(9));
+
+        // Too few audits at 9 AM, so every audit is used
+        estimator.record(9, Duration::from_secs(10));
+        estimator.record(14, Duration::from_secs(60));
+        estimator.record(14, Duration::from_secs(90));
+        estimator.record(14, Duration::from_secs(120));
+        assert_eq!(Duration::from_secs(90), estimator.estimate(9));
+
+        estimator.record(9, Duration::from_secs(20));
+        estimator.record(9, Duration::from_secs(12));
+        assert_eq!(Duration::from_secs(12), estimator.estimate(9));
+        assert_eq!(Duration::from_secs(90), estimator.estimate(14));
+    }
+}
@@ -0,0 +1,219 @@
+//! Runtime validation of WebReg JSON responses against the schemas webweg expects.
+//!
+//! UCSD occasionally changes the shape of WebReg's responses without notice. Rather
+//! than waiting for users to report broken data, responses that pass through the
+//! wrapper in raw form are checked against the expected field sets, and any unknown
+//! or missing fields are counted and logged.
+
+mod schema;
+
+pub use schema::ResponseKind;
+
+use chrono::Utc;
+use dashmap::DashMap;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use tracing::warn;
+
+/// Drift statistics collected for a single response kind.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DriftStats {
+    /// The number of responses that were checked.
+    pub responses_checked: usize,
+    /// The number of responses that were not valid JSON, or not objects/arrays of objects.
+    pub malformed_responses: usize,
+    /// Fields that appeared in a response but are not part of the expected schema,
+    /// mapped to the number of objects they appeared in.
+    pub unknown_fields: BTreeMap<String, usize>,
+    /// Required fields that were absent, mapped to the number of objects missing them.
+    pub missing_fields: BTreeMap<String, usize>,
+    /// When the last response of this kind was checked, in RFC 3339 format.
+    pub last_checked: Option<String>,
+    /// When drift was last observed for this kind, in RFC 3339 format.
+    pub last_drift: Option<String>,
+}
+
+impl DriftStats {
+    /// Whether any drift has been observed.
+    pub fn has_drift(&self) -> bool {
+        self.malformed_responses > 0
+            || !self.unknown_fields.is_empty()
+            || !self.missing_fields.is_empty()
+    }
+}
+
+/// The result of validating a single response.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ValidationResult {
+    /// Whether the response could not be interpreted at all.
+    pub malformed: bool,
+    /// Unknown fields, with the number of objects each appeared in.
+    pub unknown_fields: BTreeMap<String, usize>,
+    /// Missing required fields, with the number of objects each was missing from.
+    pub missing_fields: BTreeMap<String, usize>,
+}
+
+/// Validates a raw WebReg response body against the schema for `kind`.
+///
+/// # Parameters
+/// - `kind`: The kind of response.
+/// - `body`: The raw response body.
+///
+/// # Returns
+/// The fields that did not match the expected schema.
+pub fn validate_response(kind: ResponseKind, body: &str) -> ValidationResult {
+    let mut result = ValidationResult::default();
+    let objects = match serde_json::from_str::<Value>(body) {
+        Ok(Value::Array(arr)) => arr,
+        Ok(obj @ Value::Object(_)) => vec![obj],
+        _ => {
+            result.malformed = true;
+            return result;
+        }
+    };
+
+    for obj in objects {
+        let Value::Object(map) = obj else {
+            result.malformed = true;
+            continue;
+        };
+
+        for key in map.keys() {
+            if !kind.is_known_field(key) {
+                *result.unknown_fields.entry(key.to_owned()).or_default() += 1;
+            }
+        }
+
+        for field in kind.required_fields() {
+            if !map.contains_key(*field) {
+                *result.missing_fields.entry(field.to_string()).or_default() += 1;
+            }
+        }
+    }
+
+    result
+}
+
+/// Tracks schema drift across all response kinds.
+#[derive(Default)]
+pub struct DriftTracker {
+    stats: DashMap<ResponseKind, DriftStats>,
+}
+
+impl DriftTracker {
+    /// Creates a new, empty drift tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validates a raw response body and records the outcome.
+    ///
+    /// # Parameters
+    /// - `kind`: The kind of response.
+    /// - `body`: The raw response body.
+    pub fn observe(&self, kind: ResponseKind, body: &str) {
+        let result = validate_response(kind, body);
+        let now = Utc::now().to_rfc3339();
+        let mut entry = self.stats.entry(kind).or_default();
+        entry.responses_checked += 1;
+        entry.last_checked = Some(now.clone());
+
+        if result.malformed {
+            entry.malformed_responses += 1;
+            entry.last_drift = Some(now.clone());
+            warn!("Upstream drift: {kind:?} response could not be interpreted.");
+        }
+
+        let seen_unknown: HashSet<String> = entry.unknown_fields.keys().cloned().collect();
+        for (field, count) in result.unknown_fields {
+            if !seen_unknown.contains(&field) {
+                warn!("Upstream drift: new unknown field '{field}' in {kind:?} response.");
+            }
+
+            *entry.unknown_fields.entry(field).or_default() += count;
+            entry.last_drift = Some(now.clone());
+        }
+
+        let seen_missing: HashSet<String> = entry.missing_fields.keys().cloned().collect();
+        for (field, count) in result.missing_fields {
+            if !seen_missing.contains(&field) {
+                warn!("Upstream drift: required field '{field}' missing from {kind:?} response.");
+            }
+
+            *entry.missing_fields.entry(field).or_default() += count;
+            entry.last_drift = Some(now.clone());
+        }
+    }
+
+    /// Records the outcome of a raw webweg request, ignoring failed requests.
+    ///
+    /// # Parameters
+    /// - `kind`: The kind of response.
+    /// - `result`: The result of the raw request.
+    pub fn observe_result(&self, kind: ResponseKind, result: &webweg::types::Result<String>) {
+        if let Ok(body) = result {
+            self.observe(kind, body);
+        }
+    }
+
+    /// Gets a snapshot of the drift statistics for every response kind.
+    ///
+    /// # Returns
+    /// The statistics, keyed by response kind.
+    pub fn report(&self) -> BTreeMap<String, DriftStats> {
+        ResponseKind::ALL
+            .iter()
+            .map(|kind| {
+                let stats = self.stats.get(kind).map(|s| s.clone()).unwrap_or_default();
+                (kind.as_str().to_owned(), stats)
+            })
+            .collect()
+    }
+
+    /// Whether drift has been observed for any response kind.
+    pub fn has_drift(&self) -> bool {
+        self.stats.iter().any(|s| s.has_drift())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_response_has_no_drift() {
+        let body = r#"[{"SUBJ_CODE": "CSE", "CRSE_CODE": "100", "CRSE_TITLE": "Adv Data Struct",
+            "UNIT_FROM": 4, "UNIT_TO": 4, "UNIT_INC": 1}]"#;
+        let result = validate_response(ResponseKind::Search, body);
+        assert_eq!(ValidationResult::default(), result);
+    }
+
+    #[test]
+    fn test_unknown_and_missing_fields() {
+        let body = r#"[{"SUBJ_CODE": "CSE", "CRSE_CODE": "100", "CRSE_TITLE": "Adv Data Struct",
+            "UNIT_FROM": 4, "NEW_FIELD": true}]"#;
+        let result = validate_response(ResponseKind::Search, body);
+        assert!(!result.malformed);
+        assert_eq!(Some(&1), result.unknown_fields.get("NEW_FIELD"));
+        assert_eq!(Some(&1), result.missing_fields.get("UNIT_TO"));
+    }
+
+    #[test]
+    fn test_malformed_response() {
+        let result = validate_response(ResponseKind::Schedule, "<html>login</html>");
+        assert!(result.malformed);
+    }
+
+    #[test]
+    fn test_tracker_accumulates() {
+        let tracker = DriftTracker::new();
+        tracker.observe(ResponseKind::Prerequisites, r#"[{"SUBJECT_CODE": "MATH"}]"#);
+        tracker.observe(ResponseKind::Prerequisites, r#"[{"SUBJECT_CODE": "MATH"}]"#);
+        let report = tracker.report();
+        let stats = &report["prerequisites"];
+        assert_eq!(2, stats.responses_checked);
+        assert_eq!(Some(&2), stats.missing_fields.get("COURSE_CODE"));
+        assert!(tracker.has_drift());
+    }
+}
@@ -0,0 +1,127 @@
+//! Expected field sets for the WebReg JSON responses that webweg parses.
+//!
+//! These mirror the fields webweg's raw types deserialize. "Required" fields are the
+//! ones the parser actually depends on; "optional" fields are known to appear in some
+//! responses but are not needed. Anything outside both lists is reported as unknown.
+
+/// The kind of WebReg response being validated.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum ResponseKind {
+    /// Results from the course search endpoint.
+    Search,
+    /// Section/meeting data from the course info or enrollment count endpoints.
+    CourseInfo,
+    /// Prerequisite listings for a course.
+    Prerequisites,
+    /// The user's schedule.
+    Schedule,
+}
+
+impl ResponseKind {
+    /// All response kinds that are tracked.
+    pub const ALL: [ResponseKind; 4] = [
+        ResponseKind::Search,
+        ResponseKind::CourseInfo,
+        ResponseKind::Prerequisites,
+        ResponseKind::Schedule,
+    ];
+
+    /// The name of this kind, as used in reports.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResponseKind::Search => "search",
+            ResponseKind::CourseInfo => "course_info",
+            ResponseKind::Prerequisites => "prerequisites",
+            ResponseKind::Schedule => "schedule",
+        }
+    }
+
+    /// The fields that every object in this response must contain.
+    pub fn required_fields(&self) -> &'static [&'static str] {
+        match self {
+            ResponseKind::Search => &[
+                "SUBJ_CODE",
+                "CRSE_CODE",
+                "CRSE_TITLE",
+                "UNIT_FROM",
+                "UNIT_TO",
+            ],
+            ResponseKind::CourseInfo => &[
+                "DAY_CODE",
+                "SECT_CODE",
+                "SCTN_CPCTY_QTY",
+                "SCTN_ENRLT_QTY",
+                "AVAIL_SEAT",
+                "COUNT_ON_WAITLIST",
+                "BEGIN_HH_TIME",
+                "BEGIN_MM_TIME",
+                "END_HH_TIME",
+                "END_MM_TIME",
+                "FK_CDI_INSTR_TYPE",
+                "PERSON_FULL_NAME",
+                "SECTION_NUMBER",
+                "BLDG_CODE",
+                "ROOM_CODE",
+            ],
+            ResponseKind::Prerequisites => &["SUBJECT_CODE", "COURSE_CODE", "CRSE_TITLE", "TYPE"],
+            ResponseKind::Schedule => &[
+                "ENROLL_STATUS",
+                "SUBJ_CODE",
+                "CRSE_CODE",
+                "CRSE_TITLE",
+                "SECT_CODE",
+                "SECTION_NUMBER",
+                "DAY_CODE",
+                "BEGIN_HH_TIME",
+                "BEGIN_MM_TIME",
+                "END_HH_TIME",
+                "END_MM_TIME",
+                "FK_CDI_INSTR_TYPE",
+                "BLDG_CODE",
+                "ROOM_CODE",
+            ],
+        }
+    }
+
+    /// Fields that are known to appear but are not required.
+    pub fn optional_fields(&self) -> &'static [&'static str] {
+        match self {
+            ResponseKind::Search => &["UNIT_INC"],
+            ResponseKind::CourseInfo => &[
+                "STP_ENRLT_FLAG",
+                "FK_SPM_SPCL_MTG_CD",
+                "START_DATE",
+                "SECTION_START_DATE",
+                "SECTION_END_DATE",
+                "PRINT_FLAG",
+                "FK_SST_SCTN_STATCD",
+                "LONG_DESC",
+                "PRIMARY_INSTR_FLAG",
+            ],
+            ResponseKind::Prerequisites => &["PREREQ_SEQ_ID", "GRADE_SEQ_ID"],
+            ResponseKind::Schedule => &[
+                "SECTION_HEAD",
+                "PERSON_FULL_NAME",
+                "SCTN_CPCTY_QTY",
+                "SCTN_ENRLT_QTY",
+                "AVAIL_SEAT",
+                "GRADE_OPTION",
+                "SECT_CREDIT_HRS",
+                "WT_POS",
+                "COUNT_ON_WAITLIST",
+                "FK_SPM_SPCL_MTG_CD",
+                "START_DATE",
+                "SECTION_START_DATE",
+                "SECTION_END_DATE",
+                "PRIMARY_INSTR_FLAG",
+                "FK_SST_SCTN_STATCD",
+                "STP_ENRLT_FLAG",
+            ],
+        }
+    }
+
+    /// Whether `field` is part of the expected schema for this kind.
+    pub fn is_known_field(&self, field: &str) -> bool {
+        self.required_fields().contains(&field) || self.optional_fields().contains(&field)
+    }
+}
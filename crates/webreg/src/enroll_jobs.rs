@@ -0,0 +1,765 @@
+//! Enrollment jobs, which add a student to a section as soon as a seat opens, or at a given
+//! time (e.g., the start of their first pass).
+//!
+//! Jobs are stored in the schedule database and run periodically by the scheduler (see
+//! [`crate::scheduler`]). Each round looks at the jobs whose start time has passed, checks the seat counts of their
+//! sections, and tries to enroll the student in the sections that have open seats using
+//! their session's cookies. Every attempt, and how it went, is recorded with the job.
+//!
+//! A pending job is also called a watch, since it watches its section for an open seat.
+//! Watches expire on their own: at their expiry time, at the term's add deadline, or once
+//! the student is enrolled in another section of the watched course (found by checking each
+//! session's schedule). The expired job is recorded in the sync feed, which is how clients
+//! are notified of it. A job can also be given notification channels (see [`crate::notify`]),
+//! which are told once it enrolls the student, fails, or expires. The channels are also
+//! alerted whenever the tracker sees the section's seats open, throttled by the settings
+//! saved with the job (see [`crate::scraper::throttle`]), so that a flapping section doesn't
+//! flood them.
+//!
+//! Sessions are only kept in memory, so a job can outlive its session (e.g., when the
+//! server restarts). Such a job is paused rather than failed, and its channels are told, so
+//! that the student can register a new session and move the job onto it (see
+//! `POST /sessions/reattach`). A paused job still expires as usual.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+use webweg::types::{CourseSection, EnrollmentStatus, ScheduledSection};
+use webweg::wrapper::input_types::{AddType, EnrollWaitAdd};
+
+use crate::db::{
+    EnrollJob, ENROLL_JOB_ENROLLED, ENROLL_JOB_EXPIRED, ENROLL_JOB_FAILED,
+    ENROLL_JOB_NEEDS_SESSION, ENROLL_JOB_PENDING, EXPIRY_ADD_DEADLINE, EXPIRY_ENROLLED_IN_COURSE,
+    EXPIRY_TIME,
+};
+use crate::notify::{notify_all, Notification};
+use crate::receipts::{find_enrolled, record_change, EnrollmentAction};
+use crate::retry::Idempotency;
+use crate::scraper::live::SectionEvent;
+use crate::scraper::throttle::AlertThrottle;
+use crate::server::parse_grade_option_unit_count;
+use crate::sessions::SessionError;
+use crate::types::WrapperState;
+
+/// How often pending jobs are checked, if not configured.
+pub const DEFAULT_ENROLL_JOB_INTERVAL: Duration = Duration::from_secs(30);
+/// The number of failed attempts after which a job gives up.
+pub const MAX_ENROLL_ATTEMPTS: i64 = 10;
+/// How often watches are checked for expiry, if not configured.
+pub const DEFAULT_WATCH_EXPIRY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// The expiry time of a new job, which is no later than the term's add deadline.
+///
+/// # Parameters
+/// - `expires_at`: The expiry time that the student gave, in SQLite's format (UTC), if any.
+/// - `add_deadline`: The term's add deadline, in milliseconds since the epoch, if known.
+///
+/// # Returns
+/// The earlier of the two, in SQLite's format.
+pub fn job_expiry(expires_at: Option<String>, add_deadline: Option<i64>) -> Option<String> {
+    let deadline = add_deadline
+        .and_then(DateTime::<Utc>::from_timestamp_millis)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string());
+    // Both are in the same format, so they sort chronologically
+    match (expires_at, deadline) {
+        (Some(expires_at), Some(deadline)) => Some(expires_at.min(deadline)),
+        (expires_at, deadline) => expires_at.or(deadline),
+    }
+}
+
+/// Finds a section of a course that the student is enrolled in. Being waitlisted doesn't
+/// count, since the student would still want a seat.
+///
+/// # Parameters
+/// - `schedule`: The student's schedule.
+/// - `subject_code`: The course's subject (e.g., `CSE`).
+/// - `course_code`: The course's number (e.g., `100`).
+///
+/// # Returns
+/// The section, if there is one.
+pub fn enrolled_in_course<'a>(
+    schedule: &'a [ScheduledSection],
+    subject_code: &str,
+    course_code: &str,
+) -> Option<&'a ScheduledSection> {
+    schedule.iter().find(|s| {
+        s.subject_code
+            .trim()
+            .eq_ignore_ascii_case(subject_code.trim())
+            && s.course_code
+                .trim()
+                .eq_ignore_ascii_case(course_code.trim())
+            && matches!(s.enrolled_status, EnrollmentStatus::Enrolled)
+    })
+}
+
+/// Whether a section has an open seat.
+///
+/// # Parameters
+/// - `sections`: The sections of the section's course, as returned by WebReg.
+/// - `section_id`: The section.
+///
+/// # Returns
+/// `true` if the section has an open seat, or if it isn't listed (in which case WebReg is
+/// left to decide).
+pub fn seat_open(sections: &[CourseSection], section_id: &str) -> bool {
+    sections
+        .iter()
+        .find(|s| s.section_id == section_id)
+        .is_none_or(|s| s.available_seats > 0)
+}
+
+/// The status that a job should have after an attempt that didn't enroll the student.
+///
+/// # Parameters
+/// - `attempts`: The number of attempts that the job made before this one.
+///
+/// # Returns
+/// The status.
+pub fn status_after_failure(attempts: i64) -> &'static str {
+    if attempts + 1 >= MAX_ENROLL_ATTEMPTS {
+        ENROLL_JOB_FAILED
+    } else {
+        ENROLL_JOB_PENDING
+    }
+}
+
+/// Describes a job that finished as a notification.
+///
+/// # Parameters
+/// - `job`: The job.
+/// - `status`: The job's new status (`enrolled`, `failed`, `expired`, or `needs_session`).
+/// - `detail`: Why the job finished, if known (e.g., the error that made it fail). For an
+///   expired job, this is the reason that it expired.
+///
+/// # Returns
+/// The notification.
+pub fn job_notification(job: &EnrollJob, status: &str, detail: Option<&str>) -> Notification {
+    let course = format!("{} {}", job.subject_code.trim(), job.course_code.trim());
+    let title = match status {
+        ENROLL_JOB_ENROLLED => format!("Enrolled in {course}"),
+        ENROLL_JOB_EXPIRED => format!("Stopped watching {course}"),
+        ENROLL_JOB_NEEDS_SESSION => format!("Paused watching {course}"),
+        _ => format!("Couldn't enroll in {course}"),
+    };
+    let reason = match (status, detail) {
+        (ENROLL_JOB_EXPIRED, Some(EXPIRY_TIME)) => Some("The watch's expiry time passed."),
+        (ENROLL_JOB_EXPIRED, Some(EXPIRY_ADD_DEADLINE)) => Some("The term's add deadline passed."),
+        (ENROLL_JOB_EXPIRED, Some(EXPIRY_ENROLLED_IN_COURSE)) => {
+            Some("You're already enrolled in another section of the course.")
+        }
+        (ENROLL_JOB_EXPIRED, _) => None,
+        (ENROLL_JOB_NEEDS_SESSION, _) => {
+            Some("The watch's session no longer exists; move it onto a new session to resume it.")
+        }
+        (_, detail) => detail,
+    };
+
+    let mut message = format!("Section {} of {course}, in {}.", job.section_id, job.term);
+    if let Some(reason) = reason {
+        message.push(' ');
+        message.push_str(reason);
+    }
+
+    Notification {
+        title,
+        message,
+        payload: json!({
+            "job_id": job.job_id,
+            "term": job.term,
+            "section_id": job.section_id,
+            "subject_code": job.subject_code,
+            "course_code": job.course_code,
+            "status": status,
+            "detail": detail,
+        }),
+    }
+}
+
+/// Sends a finished (or paused) job's notification to its channels, if it has any.
+async fn notify_job(state: &WrapperState, job: &EnrollJob, status: &str, detail: Option<&str>) {
+    if job.notify_channels.is_empty() {
+        return;
+    }
+
+    notify_all(
+        state,
+        &state.student_notify_client,
+        &job.notify_channels,
+        &job_notification(job, status, detail),
+    )
+    .await;
+}
+
+/// Describes open seats in a watch's section as a notification.
+///
+/// # Parameters
+/// - `job`: The watch.
+/// - `event`: The event, which should be a [`SectionEvent::SeatsOpened`].
+///
+/// # Returns
+/// The notification, with the event as its payload.
+pub fn seat_alert(job: &EnrollJob, event: &SectionEvent) -> Notification {
+    let course = format!("{} {}", job.subject_code.trim(), job.course_code.trim());
+    let message = match event {
+        SectionEvent::SeatsOpened { message, .. } => message.clone(),
+        _ => format!("Section {} of {course}, in {}.", job.section_id, job.term),
+    };
+
+    let mut payload = json!(event);
+    payload["job_id"] = json!(job.job_id);
+    Notification {
+        title: format!("Seats open in {course}"),
+        message,
+        payload,
+    }
+}
+
+/// Finds the watches whose channels should be alerted about an event, and records the
+/// alerts in their throttles.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `event`: The event.
+/// - `now`: The current time.
+///
+/// # Returns
+/// The watches to alert.
+fn watches_to_alert(
+    state: &WrapperState,
+    event: &SectionEvent,
+    now: Instant,
+) -> rusqlite::Result<Vec<EnrollJob>> {
+    // The rest of a watch's life is told through `notify_job`
+    if !matches!(event, SectionEvent::SeatsOpened { .. }) {
+        return Ok(vec![]);
+    }
+
+    let jobs = state
+        .schedule_db
+        .get_pending_enroll_jobs_for_section(event.term(), event.section_id())?;
+    Ok(jobs
+        .into_iter()
+        .filter(|job| !job.notify_channels.is_empty())
+        .filter(|job| {
+            state
+                .watch_throttles
+                .entry(job.job_id)
+                .or_insert_with(|| AlertThrottle::new(job.alerts.settings()))
+                .admit(event, now)
+        })
+        .collect())
+}
+
+/// Alerts the channels of the watches whose sections' seats open, as the tracker sees
+/// them, until the live feed is closed.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+pub async fn run_watch_alerts(state: Arc<WrapperState>) {
+    let mut events = state.live_feed.subscribe();
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Watch alerts missed {skipped} event(s).");
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        let jobs = match watches_to_alert(&state, &event, Instant::now()) {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                warn!("[{}] Failed to load watches to alert: {e}", event.term());
+                continue;
+            }
+        };
+
+        for job in jobs {
+            let state = state.clone();
+            let notification = seat_alert(&job, &event);
+            tokio::spawn(async move {
+                notify_all(
+                    &state,
+                    &state.student_notify_client,
+                    &job.notify_channels,
+                    &notification,
+                )
+                .await;
+            });
+        }
+    }
+}
+
+/// Runs the enrollment jobs that are due.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+///
+/// # Returns
+/// The number of jobs whose sections had open seats, and were therefore attempted.
+pub async fn run_due_enroll_jobs(state: &WrapperState) -> rusqlite::Result<usize> {
+    let overdue = state
+        .schedule_db
+        .expire_overdue_enroll_jobs()
+        .inspect_err(|e| warn!("Failed to expire enrollment jobs: {e}"))?;
+    for job in overdue {
+        notify_job(state, &job, ENROLL_JOB_EXPIRED, Some(EXPIRY_TIME)).await;
+    }
+
+    let jobs = state
+        .schedule_db
+        .get_due_enroll_jobs()
+        .inspect_err(|e| warn!("Failed to load enrollment jobs: {e}"))?;
+
+    let mut attempted = 0;
+    // Seat counts are only fetched once per course each round
+    let mut seats: HashMap<(String, String, String), Option<Vec<CourseSection>>> = HashMap::new();
+    for job in jobs {
+        let key = (
+            job.term.clone(),
+            job.subject_code.clone(),
+            job.course_code.clone(),
+        );
+        if !seats.contains_key(&key) {
+            let sections = state
+                .wrapper
+                .req(job.term.as_str())
+                .parsed()
+                .get_enrollment_count(job.subject_code.trim(), job.course_code.trim())
+                .await
+                .inspect_err(|e| {
+                    warn!(
+                        "[{}] Failed to get seats for {} {}: {e}",
+                        job.term, job.subject_code, job.course_code
+                    )
+                })
+                .ok();
+            seats.insert(key.clone(), sections);
+        }
+
+        // Nothing is attempted if the seat counts couldn't be checked
+        if seats[&key]
+            .as_deref()
+            .is_some_and(|sections| seat_open(sections, &job.section_id))
+        {
+            run_job(state, &job).await;
+            attempted += 1;
+        }
+    }
+
+    Ok(attempted)
+}
+
+/// Expires the watches whose term's add deadline has passed, or whose student is already
+/// enrolled in the watched course.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+///
+/// # Returns
+/// The number of watches that expired.
+pub async fn expire_watches(state: &WrapperState) -> rusqlite::Result<usize> {
+    let jobs = state
+        .schedule_db
+        .get_pending_enroll_jobs()
+        .inspect_err(|e| warn!("Failed to load watches: {e}"))?;
+
+    // The throttles of watches that are no longer waiting won't be used again
+    let waiting: HashSet<_> = jobs.iter().map(|job| job.job_id).collect();
+    state
+        .watch_throttles
+        .retain(|job_id, _| waiting.contains(job_id));
+
+    let expire = |job: &EnrollJob, reason: &str| match state
+        .schedule_db
+        .expire_enroll_job(job.job_id, reason)
+    {
+        Ok(expired) => {
+            if expired {
+                info!("[{}] Watch {} expired ({reason}).", job.term, job.job_id);
+            }
+            expired
+        }
+        Err(e) => {
+            warn!("[{}] Failed to expire watch {}: {e}", job.term, job.job_id);
+            false
+        }
+    };
+
+    let now = Utc::now().timestamp_millis();
+    let mut expired = vec![];
+    // The rest are checked against their student's schedule, once per session and term
+    let mut by_schedule: HashMap<(String, String), Vec<EnrollJob>> = HashMap::new();
+    for job in jobs {
+        let past_deadline = state
+            .term(&job.term)
+            .and_then(|t| t.add_deadline)
+            .is_some_and(|deadline| deadline <= now);
+        if past_deadline {
+            if expire(&job, EXPIRY_ADD_DEADLINE) {
+                expired.push((job, EXPIRY_ADD_DEADLINE));
+            }
+        } else {
+            by_schedule
+                .entry((job.session_token.clone(), job.term.clone()))
+                .or_default()
+                .push(job);
+        }
+    }
+
+    for ((token, term), jobs) in by_schedule {
+        // Tried again next round
+        let Ok(cookies) = state.sessions.acquire_queued(&token) else {
+            continue;
+        };
+
+        let requester = state
+            .c_wrapper
+            .req(term.as_str())
+            .override_cookies(&cookies)
+            .parsed();
+        let schedule = match state
+            .webreg_retry
+            .run(Idempotency::Idempotent, || requester.get_schedule(None))
+            .await
+        {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                warn!("[{term}] Failed to get a schedule to reconcile watches with: {e}");
+                continue;
+            }
+        };
+
+        for job in jobs {
+            if enrolled_in_course(&schedule, &job.subject_code, &job.course_code).is_some()
+                && expire(&job, EXPIRY_ENROLLED_IN_COURSE)
+            {
+                expired.push((job, EXPIRY_ENROLLED_IN_COURSE));
+            }
+        }
+    }
+
+    for (job, reason) in &expired {
+        notify_job(state, job, ENROLL_JOB_EXPIRED, Some(reason)).await;
+    }
+    Ok(expired.len())
+}
+
+/// How an attempt to enroll the student in a job's section went.
+struct Attempt {
+    outcome: &'static str,
+    message: Option<String>,
+    action_id: Option<i64>,
+    /// The job's status after the attempt.
+    status: &'static str,
+}
+
+impl Attempt {
+    fn error(message: impl Into<String>, status: &'static str) -> Self {
+        Self {
+            outcome: "error",
+            message: Some(message.into()),
+            action_id: None,
+            status,
+        }
+    }
+}
+
+/// Tries to enroll the student in a job's section, records how it went, and notifies the
+/// job's channels if it finished.
+async fn run_job(state: &WrapperState, job: &EnrollJob) {
+    // Tried again next round
+    let Some(attempt) = attempt_job(state, job).await else {
+        return;
+    };
+
+    if let Err(e) = state.schedule_db.record_enroll_attempt(
+        job.job_id,
+        attempt.outcome,
+        attempt.message.as_deref(),
+        attempt.action_id,
+        attempt.status,
+    ) {
+        warn!(
+            "[{}] Failed to record attempt of job {}: {e}",
+            job.term, job.job_id
+        );
+    }
+
+    if attempt.status != ENROLL_JOB_PENDING {
+        notify_job(state, job, attempt.status, attempt.message.as_deref()).await;
+    }
+}
+
+/// Pauses a job whose session no longer exists, and notifies its channels.
+async fn pause_job(state: &WrapperState, job: &EnrollJob) {
+    match state.schedule_db.pause_enroll_job(job.job_id) {
+        Ok(true) => {
+            info!(
+                "[{}] Paused job {}, whose session no longer exists.",
+                job.term, job.job_id
+            );
+            notify_job(state, job, ENROLL_JOB_NEEDS_SESSION, None).await;
+        }
+        Ok(false) => {}
+        Err(e) => warn!("[{}] Failed to pause job {}: {e}", job.term, job.job_id),
+    }
+}
+
+/// Tries to enroll the student in a job's section.
+///
+/// # Returns
+/// How the attempt went, or `None` if it wasn't made (the job will be tried again next
+/// round, or was paused), in which case nothing is recorded.
+async fn attempt_job(state: &WrapperState, job: &EnrollJob) -> Option<Attempt> {
+    let term = job.term.as_str();
+    let cookies = match state.sessions.acquire_queued(&job.session_token) {
+        Ok(cookies) => cookies,
+        Err(SessionError::RateLimited(_)) => return None,
+        Err(SessionError::NotFound) => {
+            pause_job(state, job).await;
+            return None;
+        }
+    };
+
+    let requester = state
+        .c_wrapper
+        .req(term)
+        .override_cookies(&cookies)
+        .parsed();
+    let before = match requester.get_schedule(None).await {
+        Ok(before) => before,
+        Err(e) => {
+            return Some(Attempt::error(
+                e.to_string(),
+                status_after_failure(job.attempts),
+            ));
+        }
+    };
+
+    if find_enrolled(&before, &job.section_id).is_some() {
+        return Some(Attempt {
+            outcome: "enrolled",
+            message: Some("Already in the section.".to_owned()),
+            action_id: None,
+            status: ENROLL_JOB_ENROLLED,
+        });
+    }
+
+    let (grading_option, unit_count) =
+        parse_grade_option_unit_count(&job.grading_option, job.unit_count);
+    let mut add = EnrollWaitAdd::builder()
+        .with_section_id(job.section_id.as_str())
+        .with_grading_option(grading_option);
+    if let Some(u) = unit_count {
+        add = add.with_unit_count(u);
+    }
+
+    // Only enroll; a waitlist spot isn't what the student asked for
+    let webreg_success = match requester
+        .add_section(AddType::Enroll, add.try_build().unwrap(), true)
+        .await
+    {
+        Ok(b) => b,
+        Err(e) => {
+            return Some(Attempt::error(
+                e.to_string(),
+                status_after_failure(job.attempts),
+            ));
+        }
+    };
+
+    let after = requester.get_schedule(None).await.ok();
+    let receipt = record_change(
+        state,
+        term,
+        EnrollmentAction::Add,
+        &job.section_id,
+        webreg_success,
+        &before,
+        after.as_deref(),
+    );
+
+    if receipt.verified {
+        info!(
+            "[{term}] Job {} enrolled the student in section {}.",
+            job.job_id, job.section_id
+        );
+        Some(Attempt {
+            outcome: "enrolled",
+            message: None,
+            action_id: receipt.action_id,
+            status: ENROLL_JOB_ENROLLED,
+        })
+    } else {
+        Some(Attempt {
+            outcome: "unverified",
+            message: Some("The section isn't in the student's schedule.".to_owned()),
+            action_id: receipt.action_id,
+            status: status_after_failure(job.attempts),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{NewEnrollJob, WatchAlerts};
+    use crate::notify::NotifyChannel;
+    use crate::types::tests::state;
+
+    fn section(section_id: &str, available_seats: i64) -> CourseSection {
+        CourseSection {
+            subj_course_id: "CSE 100".to_string(),
+            section_id: section_id.to_string(),
+            section_code: "A01".to_string(),
+            all_instructors: vec![],
+            available_seats,
+            enrolled_ct: 100 - available_seats,
+            total_seats: 100,
+            waitlist_ct: 0,
+            meetings: vec![],
+            is_visible: true,
+        }
+    }
+
+    #[test]
+    fn test_seat_open_and_attempts() {
+        let sections = vec![section("1", 0), section("2", 3)];
+        assert!(!seat_open(&sections, "1"));
+        assert!(seat_open(&sections, "2"));
+        assert!(seat_open(&sections, "3"));
+
+        assert_eq!(ENROLL_JOB_PENDING, status_after_failure(0));
+        assert_eq!(
+            ENROLL_JOB_FAILED,
+            status_after_failure(MAX_ENROLL_ATTEMPTS - 1)
+        );
+    }
+
+    #[test]
+    fn test_job_expiry_is_capped_at_add_deadline() {
+        // 2024-10-11 23:59:00 UTC
+        let deadline = Some(1_728_691_140_000);
+        assert_eq!(None, job_expiry(None, None));
+        assert_eq!(
+            Some("2024-10-11 23:59:00".to_owned()),
+            job_expiry(None, deadline)
+        );
+        assert_eq!(
+            Some("2024-10-01 08:00:00".to_owned()),
+            job_expiry(Some("2024-10-01 08:00:00".to_owned()), deadline)
+        );
+        assert_eq!(
+            Some("2024-10-11 23:59:00".to_owned()),
+            job_expiry(Some("2024-12-01 08:00:00".to_owned()), deadline)
+        );
+        assert_eq!(
+            Some("2024-12-01 08:00:00".to_owned()),
+            job_expiry(Some("2024-12-01 08:00:00".to_owned()), None)
+        );
+    }
+
+    fn opened(section_id: &str, previous_seats: i64, available_seats: i64) -> SectionEvent {
+        SectionEvent::SeatsOpened {
+            term: "FA24".to_owned(),
+            subj_course_id: "CSE 100".to_owned(),
+            section_code: "A01".to_owned(),
+            section_id: section_id.to_owned(),
+            previous_seats,
+            available_seats,
+            total_seats: 100,
+            opens_to_non_majors: None,
+            message: format!("{available_seats} of 100 seats are open in CSE 100 A01."),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watch_alerts_are_throttled() {
+        let s = state("watch-alerts", &["FA24"], json!({}));
+        let notify = [NotifyChannel::Ntfy {
+            topic: "cse-100".to_owned(),
+            server: None,
+        }];
+        let insert = |notify_channels, alerts| {
+            s.schedule_db
+                .insert_enroll_job(&NewEnrollJob {
+                    term: "FA24",
+                    session_token: "a",
+                    section_id: "123456",
+                    subject_code: "CSE",
+                    course_code: "100",
+                    grading_option: None,
+                    unit_count: None,
+                    run_at: None,
+                    expires_at: None,
+                    notify_channels,
+                    alerts,
+                })
+                .unwrap()
+        };
+        let throttled = insert(
+            &notify,
+            WatchAlerts {
+                cooldown_mins: 10,
+                dedup_mins: 0,
+                min_seats: 2,
+            },
+        );
+        let every = insert(&notify, WatchAlerts::default());
+        // Without channels, there's no one to alert
+        insert(&[], WatchAlerts::default());
+
+        let alerted = |event: SectionEvent, now: Instant| -> Vec<i64> {
+            watches_to_alert(&s, &event, now)
+                .unwrap()
+                .iter()
+                .map(|job| job.job_id)
+                .collect()
+        };
+        let start = Instant::now();
+        let mins = |m: u64| start + Duration::from_secs(m * 60);
+
+        // One seat isn't enough for the throttled watch, and the other was already alerted
+        // about the section opening
+        assert_eq!(vec![every], alerted(opened("123456", 0, 1), start));
+        assert_eq!(vec![throttled], alerted(opened("123456", 1, 2), mins(1)));
+        // The section flaps, but the throttled watch is cooling down, and its throttle is
+        // kept between events
+        assert_eq!(vec![every], alerted(opened("123456", 0, 2), mins(5)));
+        assert_eq!(
+            vec![throttled, every],
+            alerted(opened("123456", 0, 2), mins(12))
+        );
+
+        // Other sections and kinds of events aren't alerted about
+        assert!(alerted(opened("999999", 0, 5), mins(30)).is_empty());
+        let waitlist = SectionEvent::WaitlistChanged {
+            term: "FA24".to_owned(),
+            subj_course_id: "CSE 100".to_owned(),
+            section_code: "A01".to_owned(),
+            section_id: "123456".to_owned(),
+            old_waitlist_ct: 0,
+            new_waitlist_ct: 1,
+        };
+        assert!(alerted(waitlist, mins(30)).is_empty());
+
+        // Nor are watches that are no longer waiting
+        assert!(s.schedule_db.cancel_enroll_job("FA24", "a", every).unwrap());
+        assert_eq!(vec![throttled], alerted(opened("123456", 0, 2), mins(30)));
+
+        let alert = seat_alert(
+            &s.schedule_db.get_enroll_job(throttled).unwrap().unwrap(),
+            &opened("123456", 0, 2),
+        );
+        assert_eq!("Seats open in CSE 100", alert.title);
+        assert_eq!(json!(throttled), alert.payload["job_id"]);
+    }
+}
@@ -0,0 +1,647 @@
+//! The errors that the API responds with, sorted into categories.
+//!
+//! Errors from the database, from requests made to other services, from WebReg, and from
+//! degree audits are all converted into a [`WebregError`], whose category decides both the
+//! HTTP status that the API responds with and whether the failed operation is worth
+//! retrying (see [`crate::retry`]). Handlers can replace an error's message with one that
+//! says what they were doing, but never decide the status of an error that they were given.
+//! They only pick a status themselves for problems with the request (e.g., an unknown ID or
+//! a limit that the caller reached) and for features that aren't enabled. Errors that the
+//! API can say more about also carry a specific code (e.g., `COOKIE_EXPIRED` rather than
+//! `UNAUTHORIZED`) for clients to act on.
+//!
+//! The context of an internal error is only logged, since it can describe the server's
+//! internals (e.g., the database's schema). So is the context of any error that's reported
+//! alongside other results (e.g., as one item of a batch) rather than as the response, which
+//! only carries its code and message (see [`WebregError::log_context`]).
+
+use std::borrow::Cow;
+
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde_json::Value;
+use thiserror::Error;
+use tracing::{error, warn};
+use webweg::types::{SectionIdNotFoundContext, WrapperError};
+
+use crate::degree_audit::DegreeAuditError;
+use crate::grades::GradeImportError;
+use crate::payload_store::PayloadStoreError;
+use crate::replication::ReplicationError;
+use crate::scraper::term_scrape::StartScrapeError;
+use crate::semantic::SemanticError;
+use crate::server::types::{error_response, ErrorCode};
+
+/// How long, in seconds, clients are told to wait before trying again when another service
+/// is unavailable. This is how long the degree audit circuit breaker stays open.
+const UNAVAILABLE_RETRY_AFTER_SECS: u64 = 30;
+
+/// An error, by category. Each carries a message for the caller and, optionally, more
+/// context about what went wrong and a more specific code than its category's (see
+/// [`ErrorCode`]).
+#[derive(Debug, Clone, Error)]
+pub enum WebregError {
+    /// Another service (WebReg, DARS, or the cookie server) failed or couldn't be reached.
+    #[error("{message}")]
+    Upstream {
+        message: Cow<'static, str>,
+        context: Option<String>,
+        code: Option<ErrorCode>,
+    },
+    /// The session or credentials aren't valid.
+    #[error("{message}")]
+    Auth {
+        message: Cow<'static, str>,
+        context: Option<String>,
+        code: Option<ErrorCode>,
+    },
+    /// The caller isn't allowed to do what they asked.
+    #[error("{message}")]
+    Forbidden {
+        message: Cow<'static, str>,
+        context: Option<String>,
+        code: Option<ErrorCode>,
+    },
+    /// The request itself was invalid.
+    #[error("{message}")]
+    BadRequest {
+        message: Cow<'static, str>,
+        context: Option<String>,
+        code: Option<ErrorCode>,
+    },
+    #[error("{message}")]
+    NotFound {
+        message: Cow<'static, str>,
+        context: Option<String>,
+        code: Option<ErrorCode>,
+    },
+    /// The request conflicts with something that already exists or is in progress.
+    #[error("{message}")]
+    Conflict {
+        message: Cow<'static, str>,
+        context: Option<String>,
+        code: Option<ErrorCode>,
+    },
+    /// Something went wrong on our end.
+    #[error("{message}")]
+    Internal {
+        message: Cow<'static, str>,
+        context: Option<String>,
+        code: Option<ErrorCode>,
+    },
+    /// The caller has made too many requests, and can make the request again later.
+    #[error("{message}")]
+    RateLimited {
+        message: Cow<'static, str>,
+        context: Option<String>,
+        code: Option<ErrorCode>,
+    },
+    /// Another service turned the request away without handling it, or is known to be
+    /// down, and the request can be made again later.
+    #[error("{message}")]
+    Unavailable {
+        message: Cow<'static, str>,
+        context: Option<String>,
+        code: Option<ErrorCode>,
+    },
+}
+
+impl WebregError {
+    fn parts(&self) -> (&Cow<'static, str>, &Option<String>, &Option<ErrorCode>) {
+        match self {
+            WebregError::Upstream {
+                message,
+                context,
+                code,
+            }
+            | WebregError::Auth {
+                message,
+                context,
+                code,
+            }
+            | WebregError::Forbidden {
+                message,
+                context,
+                code,
+            }
+            | WebregError::BadRequest {
+                message,
+                context,
+                code,
+            }
+            | WebregError::NotFound {
+                message,
+                context,
+                code,
+            }
+            | WebregError::Conflict {
+                message,
+                context,
+                code,
+            }
+            | WebregError::Internal {
+                message,
+                context,
+                code,
+            }
+            | WebregError::RateLimited {
+                message,
+                context,
+                code,
+            }
+            | WebregError::Unavailable {
+                message,
+                context,
+                code,
+            } => (message, context, code),
+        }
+    }
+
+    /// The status that the API responds with for this error.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            WebregError::Upstream { .. } => StatusCode::BAD_GATEWAY,
+            WebregError::Auth { .. } => StatusCode::UNAUTHORIZED,
+            WebregError::Forbidden { .. } => StatusCode::FORBIDDEN,
+            WebregError::BadRequest { .. } => StatusCode::BAD_REQUEST,
+            WebregError::NotFound { .. } => StatusCode::NOT_FOUND,
+            WebregError::Conflict { .. } => StatusCode::CONFLICT,
+            WebregError::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            WebregError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            WebregError::Unavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    /// Whether the operation might succeed if it's tried again later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            WebregError::Upstream { .. }
+                | WebregError::RateLimited { .. }
+                | WebregError::Unavailable { .. }
+        )
+    }
+
+    /// The error's message.
+    pub fn message(&self) -> &str {
+        self.parts().0
+    }
+
+    /// More context about the error, if there is any.
+    pub fn context(&self) -> Option<&str> {
+        self.parts().1.as_deref()
+    }
+
+    /// The error's code: its specific code if it has one, and otherwise its status's.
+    pub fn code(&self) -> ErrorCode {
+        self.parts()
+            .2
+            .unwrap_or_else(|| ErrorCode::from_status(self.status_code()))
+    }
+
+    /// Gives the error a more specific code than its category's, keeping its category.
+    pub fn with_code(mut self, new_code: ErrorCode) -> Self {
+        match &mut self {
+            WebregError::Upstream { code, .. }
+            | WebregError::Auth { code, .. }
+            | WebregError::Forbidden { code, .. }
+            | WebregError::BadRequest { code, .. }
+            | WebregError::NotFound { code, .. }
+            | WebregError::Conflict { code, .. }
+            | WebregError::Internal { code, .. }
+            | WebregError::RateLimited { code, .. }
+            | WebregError::Unavailable { code, .. } => *code = Some(new_code),
+        }
+
+        self
+    }
+
+    /// Replaces the error's message (e.g., with what the handler was doing), keeping its
+    /// category, context, and code.
+    pub fn with_message(mut self, new_message: impl Into<Cow<'static, str>>) -> Self {
+        match &mut self {
+            WebregError::Upstream { message, .. }
+            | WebregError::Auth { message, .. }
+            | WebregError::Forbidden { message, .. }
+            | WebregError::BadRequest { message, .. }
+            | WebregError::NotFound { message, .. }
+            | WebregError::Conflict { message, .. }
+            | WebregError::Internal { message, .. }
+            | WebregError::RateLimited { message, .. }
+            | WebregError::Unavailable { message, .. } => *message = new_message.into(),
+        }
+
+        self
+    }
+
+    /// Logs the error's context, for when the error is reported by only its code and
+    /// message (e.g., as one item of a batch) rather than as the response.
+    ///
+    /// # Returns
+    /// The error, so that its code and message can be reported.
+    pub fn log_context(&self) -> &Self {
+        if let Some(context) = self.context() {
+            match self {
+                WebregError::Internal { .. } => error!("{} ({context})", self.message()),
+                _ => warn!("{} ({context})", self.message()),
+            }
+        }
+
+        self
+    }
+
+    /// Creates an error for something that went wrong on our end.
+    ///
+    /// # Parameters
+    /// - `message`: What couldn't be done.
+    /// - `err`: What went wrong, which is logged rather than sent to the caller.
+    pub fn internal(message: impl Into<Cow<'static, str>>, err: impl std::fmt::Display) -> Self {
+        WebregError::Internal {
+            message: message.into(),
+            context: Some(err.to_string()),
+            code: None,
+        }
+    }
+}
+
+impl IntoResponse for WebregError {
+    fn into_response(self) -> Response {
+        let context = match &self {
+            WebregError::Internal { .. } => {
+                error!(
+                    "{} ({})",
+                    self.message(),
+                    self.context().unwrap_or("no context")
+                );
+                None
+            }
+            _ => self.context().map(Value::from),
+        };
+
+        let mut resp = error_response(self.status_code(), self.code(), self.message(), context);
+        if matches!(self, WebregError::Unavailable { .. }) {
+            resp.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(UNAVAILABLE_RETRY_AFTER_SECS));
+        }
+
+        resp
+    }
+}
+
+impl From<&WrapperError> for WebregError {
+    fn from(err: &WrapperError) -> Self {
+        match err {
+            WrapperError::RequestError(r) => WebregError::Upstream {
+                message: "A request to WebReg failed.".into(),
+                context: Some(r.to_string()),
+                code: None,
+            },
+            WrapperError::BadStatusCode(status, context) => {
+                let context = context.clone();
+                match status {
+                    429 | 503 => WebregError::Unavailable {
+                        message: "WebReg turned the request away.".into(),
+                        context,
+                        code: None,
+                    },
+                    401 | 403 => WebregError::Auth {
+                        message: "WebReg rejected the session.".into(),
+                        context,
+                        code: None,
+                    },
+                    404 => WebregError::NotFound {
+                        message: "WebReg couldn't find what was requested.".into(),
+                        context,
+                        code: None,
+                    },
+                    400..=499 => WebregError::BadRequest {
+                        message: format!("WebReg responded with status {status}.").into(),
+                        context,
+                        code: None,
+                    },
+                    _ => WebregError::Upstream {
+                        message: format!("WebReg responded with status {status}.").into(),
+                        context,
+                        code: None,
+                    },
+                }
+            }
+            WrapperError::InputError(input, value) => WebregError::BadRequest {
+                message: "A bad argument was passed in.".into(),
+                context: Some(format!("input={input}, bad arg value={value}")),
+                code: None,
+            },
+            WrapperError::WebRegError(w) => WebregError::BadRequest {
+                message: "WebReg returned an error regarding your request.".into(),
+                context: Some(w.clone()),
+                code: None,
+            },
+            WrapperError::SectionIdNotFound(section_id, place) => WebregError::NotFound {
+                message: match place {
+                    SectionIdNotFoundContext::Schedule => {
+                        "The section ID you specified wasn't found in your schedule."
+                    }
+                    SectionIdNotFoundContext::Catalog => {
+                        "The section ID you specified doesn't appear to be offered in the specified term."
+                    }
+                }
+                .into(),
+                context: Some(section_id.clone()),
+                code: Some(ErrorCode::SectionNotFound),
+            },
+            // WebReg responds with a login page instead of JSON when the session is invalid
+            WrapperError::SerdeError(s) => WebregError::Auth {
+                message: "WebReg's response wasn't JSON. It's possible your session is not valid."
+                    .into(),
+                context: Some(s.to_string()),
+                code: Some(ErrorCode::CookieExpired),
+            },
+            WrapperError::SessionNotValid => WebregError::Auth {
+                message: "Your session isn't valid. Try a different set of WebReg cookies.".into(),
+                context: None,
+                code: Some(ErrorCode::CookieExpired),
+            },
+            WrapperError::UrlParseError(e) => WebregError::Internal {
+                message: "An internal URL parsing error occurred.".into(),
+                context: Some(e.to_string()),
+                code: None,
+            },
+            WrapperError::WrapperParsingError(p) => WebregError::Internal {
+                message: "An error occurred when trying to convert the response JSON into an object."
+                    .into(),
+                context: Some(p.clone()),
+                code: None,
+            },
+            WrapperError::BadTimeError => WebregError::Internal {
+                message: "An error occurred when trying to parse a time unit.".into(),
+                context: None,
+                code: None,
+            },
+        }
+    }
+}
+
+impl From<WrapperError> for WebregError {
+    fn from(err: WrapperError) -> Self {
+        Self::from(&err)
+    }
+}
+
+impl From<rusqlite::Error> for WebregError {
+    fn from(err: rusqlite::Error) -> Self {
+        // SQLite's messages name tables and columns, so they're only logged
+        match err.sqlite_error_code() {
+            _ if matches!(err, rusqlite::Error::QueryReturnedNoRows) => WebregError::NotFound {
+                message: "Nothing was found.".into(),
+                context: None,
+                code: None,
+            },
+            Some(rusqlite::ErrorCode::ConstraintViolation) => {
+                error!("Database constraint violated: {err}");
+                WebregError::Conflict {
+                    message: "That already exists.".into(),
+                    context: None,
+                    code: None,
+                }
+            }
+            _ => {
+                error!("Database error: {err}");
+                WebregError::Internal {
+                    message: "Failed to access the database.".into(),
+                    context: None,
+                    code: None,
+                }
+            }
+        }
+    }
+}
+
+impl From<reqwest::Error> for WebregError {
+    fn from(err: reqwest::Error) -> Self {
+        let context = Some(err.to_string());
+        match err.status().map(|s| s.as_u16()) {
+            Some(429 | 503) => WebregError::Unavailable {
+                message: "The service turned the request away.".into(),
+                context,
+                code: None,
+            },
+            _ => WebregError::Upstream {
+                message: "A request to another service failed.".into(),
+                context,
+                code: None,
+            },
+        }
+    }
+}
+
+impl From<&DegreeAuditError> for WebregError {
+    fn from(err: &DegreeAuditError) -> Self {
+        let context = Some(err.to_string());
+        match err {
+            DegreeAuditError::Network { .. }
+            | DegreeAuditError::UnexpectedResponse { .. }
+            | DegreeAuditError::JobFailed { .. } => WebregError::Upstream {
+                message: "Failed to fetch degree audit".into(),
+                context,
+                code: None,
+            },
+            DegreeAuditError::PollTimeout { .. } => WebregError::Upstream {
+                message: "Audit generation timed out".into(),
+                context,
+                code: None,
+            },
+            DegreeAuditError::CircuitBreakerOpen => WebregError::Unavailable {
+                message: "Service temporarily unavailable due to repeated failures".into(),
+                context,
+                code: None,
+            },
+            DegreeAuditError::CookieFetchError { .. } => WebregError::Upstream {
+                message: "Failed to fetch authentication cookies".into(),
+                context,
+                code: None,
+            },
+            DegreeAuditError::SessionExpired { .. } => WebregError::Auth {
+                message: "Session expired - please re-authenticate".into(),
+                context,
+                code: Some(ErrorCode::CookieExpired),
+            },
+            DegreeAuditError::NoSession { .. } => WebregError::Auth {
+                message: "No active session".into(),
+                context,
+                code: Some(ErrorCode::CookieExpired),
+            },
+            DegreeAuditError::AccessDenied { .. } => WebregError::Forbidden {
+                message: "This API key can't access degree audits".into(),
+                context,
+                code: None,
+            },
+            DegreeAuditError::NoJobFound => WebregError::NotFound {
+                message: "DARS didn't list the audit that was requested".into(),
+                context,
+                code: None,
+            },
+            DegreeAuditError::OperationInProgress => WebregError::Conflict {
+                message: "A degree audit is already being fetched".into(),
+                context,
+                code: None,
+            },
+            DegreeAuditError::AuditQuotaExceeded { .. } => WebregError::RateLimited {
+                message: "Daily degree audit quota exceeded - try again tomorrow".into(),
+                context,
+                code: Some(ErrorCode::QuotaExceeded),
+            },
+            DegreeAuditError::ParseError { .. }
+            | DegreeAuditError::UrlError { .. }
+            | DegreeAuditError::Fixture { .. } => WebregError::Internal {
+                message: "Failed to fetch degree audit".into(),
+                context,
+                code: None,
+            },
+        }
+    }
+}
+
+impl From<DegreeAuditError> for WebregError {
+    fn from(err: DegreeAuditError) -> Self {
+        Self::from(&err)
+    }
+}
+
+impl From<GradeImportError> for WebregError {
+    fn from(err: GradeImportError) -> Self {
+        match err {
+            GradeImportError::Db(e) => {
+                WebregError::from(e).with_message("Failed to save the grade data.")
+            }
+            e => WebregError::BadRequest {
+                message: "The grade data couldn't be parsed.".into(),
+                context: Some(e.to_string()),
+                code: None,
+            },
+        }
+    }
+}
+
+impl From<ReplicationError> for WebregError {
+    fn from(err: ReplicationError) -> Self {
+        match err {
+            ReplicationError::Db(e) => WebregError::from(e),
+            e @ (ReplicationError::NotStandby | ReplicationError::Outdated { .. }) => {
+                WebregError::Conflict {
+                    message: "The snapshot was refused.".into(),
+                    context: Some(e.to_string()),
+                    code: None,
+                }
+            }
+            e @ (ReplicationError::Rejected(_) | ReplicationError::Http(_)) => {
+                WebregError::Upstream {
+                    message: "Failed to push the snapshot.".into(),
+                    context: Some(e.to_string()),
+                    code: None,
+                }
+            }
+            e @ ReplicationError::Io(_) => {
+                WebregError::internal("Failed to access the snapshot.", e)
+            }
+        }
+    }
+}
+
+impl From<StartScrapeError> for WebregError {
+    fn from(err: StartScrapeError) -> Self {
+        match err {
+            StartScrapeError::Db(e) => WebregError::from(e),
+            e @ StartScrapeError::AlreadyRunning(_) => WebregError::Conflict {
+                message: "A scrape of this term is already running.".into(),
+                context: Some(e.to_string()),
+                code: None,
+            },
+        }
+    }
+}
+
+impl From<PayloadStoreError> for WebregError {
+    fn from(err: PayloadStoreError) -> Self {
+        match err {
+            e @ PayloadStoreError::InvalidKey(_) => WebregError::BadRequest {
+                message: "This isn't a valid payload key.".into(),
+                context: Some(e.to_string()),
+                code: None,
+            },
+            e @ (PayloadStoreError::Config(_) | PayloadStoreError::Io(_)) => {
+                WebregError::internal("Failed to access the payload store.", e)
+            }
+            e @ (PayloadStoreError::Http(_) | PayloadStoreError::Status { .. }) => {
+                WebregError::Upstream {
+                    message: "A request to the object store failed.".into(),
+                    context: Some(e.to_string()),
+                    code: None,
+                }
+            }
+        }
+    }
+}
+
+impl From<SemanticError> for WebregError {
+    fn from(err: SemanticError) -> Self {
+        match err {
+            SemanticError::Http(e) => WebregError::from(e),
+            SemanticError::Db(e) => WebregError::from(e),
+            e @ SemanticError::Mismatch { .. } => WebregError::Upstream {
+                message: "The embedding API's response was unexpected.".into(),
+                context: Some(e.to_string()),
+                code: None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categories() {
+        // WebReg turning us away isn't the caller's fault, so it isn't a 429
+        let turned_away = WebregError::from(WrapperError::BadStatusCode(503, None));
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, turned_away.status_code());
+        assert!(turned_away.is_retryable());
+        let resp = turned_away.into_response();
+        assert_eq!("30", resp.headers()[RETRY_AFTER]);
+
+        let breaker = WebregError::from(DegreeAuditError::CircuitBreakerOpen);
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, breaker.status_code());
+
+        let down = WebregError::from(WrapperError::BadStatusCode(502, None));
+        assert!(matches!(down, WebregError::Upstream { .. }));
+        assert!(down.is_retryable());
+
+        let rejected = WebregError::from(WrapperError::WebRegError("full".to_owned()));
+        assert_eq!(StatusCode::BAD_REQUEST, rejected.status_code());
+        assert!(!rejected.is_retryable());
+
+        let missing = WebregError::from(rusqlite::Error::QueryReturnedNoRows)
+            .with_message("No share has that ID");
+        assert_eq!(StatusCode::NOT_FOUND, missing.status_code());
+        assert_eq!("No share has that ID", missing.message());
+
+        // SQLite's own message isn't passed on
+        let db = WebregError::from(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(1),
+            Some("no such table: secret_table".to_owned()),
+        ));
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, db.status_code());
+        assert_eq!(None, db.context());
+
+        assert!(DegreeAuditError::PollTimeout {
+            attempts: 3,
+            elapsed_secs: 1.0
+        }
+        .is_retryable());
+        assert!(!DegreeAuditError::NoSession {
+            message: String::new()
+        }
+        .is_retryable());
+    }
+}
@@ -0,0 +1,153 @@
+//! How quickly courses fill up, computed from the seat history that the tracker records.
+//!
+//! A section fills when it first has no seats left. Its fill time is measured from the
+//! term's first pass, if it's configured (`firstPassStart`), and otherwise from when the
+//! tracker first saw the section. A course's fill time is the median of its sections' fill
+//! times, so a course only has one if at least half of its sections filled; that way, one
+//! small section filling doesn't make the whole course look full.
+
+use serde::Serialize;
+
+use crate::db::SeatSample;
+
+/// Courses that fill within this many hours are flagged, so that students can prioritize
+/// them at their enrollment time.
+pub const FILLS_QUICKLY_HOURS: f64 = 24.0;
+
+const MS_PER_HOUR: f64 = 60.0 * 60.0 * 1000.0;
+
+/// How quickly a course filled.
+#[derive(Debug, Clone, Serialize)]
+pub struct CourseFillRate {
+    pub subj_course_id: String,
+    /// The number of the course's sections with seats that were tracked.
+    pub sections: usize,
+    /// The number of those sections that filled.
+    pub sections_filled: usize,
+    /// The median hours that the course's sections took to fill, if at least half of
+    /// them filled.
+    pub median_fill_hours: Option<f64>,
+    /// The hours that the quickest section took to fill, if any filled.
+    pub fastest_fill_hours: Option<f64>,
+    /// The share of the course's peak waitlist that was later cleared, if it ever had a
+    /// waitlist.
+    pub waitlist_resolution_rate: Option<f64>,
+    /// Whether the course fills within [`FILLS_QUICKLY_HOURS`].
+    pub fills_within_24h: bool,
+}
+
+/// Computes how quickly each course filled.
+///
+/// # Parameters
+/// - `samples`: The term's seat history, ordered by course, then section, then time (as
+///   returned by [`crate::db::ScheduleDbManager::get_seat_history`]).
+/// - `first_pass`: When the term's first pass started, in milliseconds since the epoch, if
+///   known.
+///
+/// # Returns
+/// Each course's fill rate, quickest to fill first. Courses that didn't fill are last.
+pub fn compute_fill_rates(samples: &[SeatSample], first_pass: Option<i64>) -> Vec<CourseFillRate> {
+    let mut rates = vec![];
+    for course in samples.chunk_by(|a, b| a.subj_course_id == b.subj_course_id) {
+        // Sections without any seats (e.g., lectures that students enroll in through
+        // their discussions) can't fill
+        let sections: Vec<&[SeatSample]> = course
+            .chunk_by(|a, b| a.section_id == b.section_id)
+            .filter(|s| s.iter().any(|sample| sample.total_seats > 0))
+            .collect();
+        if sections.is_empty() {
+            continue;
+        }
+
+        // Sections that never filled are treated as taking forever, so they sort last
+        let mut fill_hours: Vec<f64> = sections
+            .iter()
+            .map(|s| {
+                let start = first_pass.unwrap_or(s[0].recorded_at);
+                s.iter()
+                    .find(|sample| sample.total_seats > 0 && sample.available_seats <= 0)
+                    .map_or(f64::INFINITY, |full| {
+                        (full.recorded_at - start).max(0) as f64 / MS_PER_HOUR
+                    })
+            })
+            .collect();
+        fill_hours.sort_by(f64::total_cmp);
+        let finite = |hours: f64| hours.is_finite().then_some(hours);
+        let median_fill_hours = finite(fill_hours[(fill_hours.len() - 1) / 2]);
+
+        let (peak, cleared) = sections
+            .iter()
+            .map(|s| {
+                let peak = s.iter().map(|sample| sample.waitlist_ct).max().unwrap_or(0);
+                let last = s.last().map_or(0, |sample| sample.waitlist_ct);
+                (peak, (peak - last).max(0))
+            })
+            .fold((0, 0), |(p, c), (peak, cleared)| (p + peak, c + cleared));
+
+        rates.push(CourseFillRate {
+            subj_course_id: course[0].subj_course_id.clone(),
+            sections: sections.len(),
+            sections_filled: fill_hours.iter().filter(|h| h.is_finite()).count(),
+            median_fill_hours,
+            fastest_fill_hours: finite(fill_hours[0]),
+            waitlist_resolution_rate: (peak > 0).then(|| cleared as f64 / peak as f64),
+            fills_within_24h: median_fill_hours.is_some_and(|h| h <= FILLS_QUICKLY_HOURS),
+        });
+    }
+
+    rates.sort_by(|a, b| {
+        let hours = |r: &CourseFillRate| r.median_fill_hours.unwrap_or(f64::INFINITY);
+        hours(a).total_cmp(&hours(b))
+    });
+    rates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(course: &str, section: &str, available: i64, waitlist: i64, hour: i64) -> SeatSample {
+        SeatSample {
+            subj_course_id: course.to_owned(),
+            section_id: section.to_owned(),
+            available_seats: available,
+            waitlist_ct: waitlist,
+            total_seats: 30,
+            recorded_at: hour * MS_PER_HOUR as i64,
+        }
+    }
+
+    #[test]
+    fn test_compute_fill_rates() {
+        let samples = vec![
+            sample("CSE 100", "1", 10, 0, 0),
+            sample("CSE 100", "1", 0, 8, 6),
+            sample("CSE 100", "1", 0, 2, 100),
+            sample("CSE 100", "2", 5, 0, 0),
+            sample("CSE 100", "2", 0, 2, 12),
+            sample("CSE 100", "3", 20, 0, 0),
+            sample("MATH 20A", "4", 5, 0, 0),
+            sample("MATH 20A", "4", 0, 0, 48),
+            sample("MATH 20A", "5", 5, 0, 0),
+            sample("MATH 20A", "6", 5, 0, 0),
+        ];
+
+        let rates = compute_fill_rates(&samples, None);
+        assert_eq!("CSE 100", rates[0].subj_course_id);
+        assert_eq!((3, 2), (rates[0].sections, rates[0].sections_filled));
+        assert_eq!(Some(12.0), rates[0].median_fill_hours);
+        assert_eq!(Some(6.0), rates[0].fastest_fill_hours);
+        assert_eq!(Some(0.6), rates[0].waitlist_resolution_rate);
+        assert!(rates[0].fills_within_24h);
+
+        // Only one of three sections filled, so the course hasn't
+        assert_eq!(None, rates[1].median_fill_hours);
+        assert_eq!(None, rates[1].waitlist_resolution_rate);
+        assert!(!rates[1].fills_within_24h);
+
+        // Measured from the first pass, CSE 100 takes longer to fill
+        let rates = compute_fill_rates(&samples, Some(-24 * MS_PER_HOUR as i64));
+        assert_eq!(Some(36.0), rates[0].median_fill_hours);
+        assert!(!rates[0].fills_within_24h);
+    }
+}
@@ -0,0 +1,295 @@
+//! Historical grade distributions and course evaluations (e.g., from CAPE or SET), which help
+//! students choose between courses and sections.
+//!
+//! The data is imported as CSV, either uploaded through `POST /admin/grade_distributions` or
+//! fetched every so often from `gradeDistributionUrl`. The first line is a header naming
+//! each column; `course`, `term`, and `instructor` are required, and `enrolled`, `avg_gpa`,
+//! `recommend_instructor`, `recommend_class`, and `study_hours` are optional. Other columns
+//! are ignored. Importing a record replaces any existing record for the same course, term,
+//! and instructor.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::db::GradeRecord;
+use crate::types::WrapperState;
+
+/// How often grade data is fetched from `gradeDistributionUrl`.
+pub const GRADE_SYNC_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// The largest CSV file that will be imported, in bytes.
+pub const MAX_GRADE_CSV_SIZE: usize = 64 * 1024 * 1024;
+
+/// Why a CSV file of grade data couldn't be imported.
+#[derive(Debug, Error)]
+pub enum GradeImportError {
+    #[error("the file is missing the `{0}` column")]
+    MissingColumn(&'static str),
+    #[error("line {line}: `{value}` isn't a valid {column}")]
+    InvalidValue {
+        line: usize,
+        column: &'static str,
+        value: String,
+    },
+    #[error("line {0} is missing a course, term, or instructor")]
+    MissingKey(usize),
+    #[error("failed to save the grade data: {0}")]
+    Db(#[from] rusqlite::Error),
+    #[error("failed to fetch the grade data: {0}")]
+    Http(#[from] reqwest::Error),
+}
+
+/// How students have done in, and rated, a course (or one instructor's offerings of it).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GradeSummary {
+    /// The number of offerings that the summary covers.
+    pub offerings: usize,
+    /// The average grade received, on a 4.0 scale, weighted by enrollment.
+    pub avg_gpa: Option<f32>,
+    /// The percent of students who recommend the instructor, weighted by enrollment.
+    pub instructor_rating: Option<f32>,
+}
+
+/// Summarizes grade records. Offerings without an enrollment count are weighted as if one
+/// student was enrolled.
+///
+/// # Parameters
+/// - `records`: The records to summarize.
+///
+/// # Returns
+/// The summary, or nothing if there are no records.
+pub fn summarize(records: &[GradeRecord]) -> Option<GradeSummary> {
+    if records.is_empty() {
+        return None;
+    }
+
+    let weighted_avg = |value: fn(&GradeRecord) -> Option<f32>| {
+        let (sum, weight) = records
+            .iter()
+            .filter_map(|r| value(r).map(|v| (v, r.enrolled.unwrap_or(1).max(1) as f32)))
+            .fold((0.0, 0.0), |(sum, weight), (v, w)| {
+                (sum + v * w, weight + w)
+            });
+        (weight > 0.0).then(|| sum / weight)
+    };
+
+    Some(GradeSummary {
+        offerings: records.len(),
+        avg_gpa: weighted_avg(|r| r.avg_gpa),
+        instructor_rating: weighted_avg(|r| r.recommend_instructor),
+    })
+}
+
+/// Summarizes a course's grade records for each instructor.
+///
+/// # Parameters
+/// - `records`: The course's records.
+///
+/// # Returns
+/// Each instructor's summary, keyed by the instructor's name.
+pub fn summarize_by_instructor(records: &[GradeRecord]) -> BTreeMap<String, GradeSummary> {
+    let mut by_instructor: BTreeMap<&str, Vec<GradeRecord>> = BTreeMap::new();
+    for r in records {
+        by_instructor
+            .entry(r.instructor.as_str())
+            .or_default()
+            .push(r.clone());
+    }
+
+    by_instructor
+        .into_iter()
+        .filter_map(|(name, records)| summarize(&records).map(|s| (name.to_owned(), s)))
+        .collect()
+}
+
+/// Parses a CSV file of grade data (see the module documentation for its columns).
+///
+/// # Parameters
+/// - `text`: The file's contents.
+///
+/// # Returns
+/// The records, or why the file couldn't be parsed.
+pub fn parse_grade_csv(text: &str) -> Result<Vec<GradeRecord>, GradeImportError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let header = lines
+        .next()
+        .map(|(_, line)| split_csv_line(line))
+        .unwrap_or_default();
+    let column = |name: &'static str| {
+        header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name))
+    };
+    let required = |name| column(name).ok_or(GradeImportError::MissingColumn(name));
+    let (course_col, term_col, instructor_col) = (
+        required("course")?,
+        required("term")?,
+        required("instructor")?,
+    );
+
+    let mut records = vec![];
+    for (idx, line) in lines {
+        let line_no = idx + 1;
+        let fields = split_csv_line(line);
+        let field = |col: Option<usize>| {
+            col.and_then(|c| fields.get(c))
+                .map(|f| f.trim())
+                .filter(|f| !f.is_empty())
+        };
+        let number = |name: &'static str| -> Result<Option<f32>, GradeImportError> {
+            field(column(name))
+                // CAPE reports some values as, e.g., `3.52 (B+)` or `85.2 %`
+                .map(|f| {
+                    let value = f.split([' ', '%']).next().unwrap_or(f);
+                    value.parse().map_err(|_| GradeImportError::InvalidValue {
+                        line: line_no,
+                        column: name,
+                        value: f.to_owned(),
+                    })
+                })
+                .transpose()
+        };
+
+        let (Some(course), Some(term), Some(instructor)) = (
+            field(Some(course_col)),
+            field(Some(term_col)),
+            field(Some(instructor_col)),
+        ) else {
+            return Err(GradeImportError::MissingKey(line_no));
+        };
+
+        records.push(GradeRecord {
+            course: crate::db::normalize_course_code(course),
+            term: term.to_uppercase(),
+            instructor: instructor.to_owned(),
+            enrolled: number("enrolled")?.map(|n| n as i64),
+            avg_gpa: number("avg_gpa")?,
+            recommend_instructor: number("recommend_instructor")?,
+            recommend_class: number("recommend_class")?,
+            study_hours: number("study_hours")?,
+        });
+    }
+
+    Ok(records)
+}
+
+/// Splits a line of CSV into its fields. Fields may be quoted, with quotes inside them
+/// doubled.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+
+    fields.push(field);
+    fields
+}
+
+/// Parses a CSV file of grade data and saves it to the schedule database.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `text`: The file's contents.
+///
+/// # Returns
+/// The number of records imported, or why the file couldn't be imported.
+pub fn import_grade_csv(state: &WrapperState, text: &str) -> Result<usize, GradeImportError> {
+    let records = parse_grade_csv(text)?;
+    Ok(state.schedule_db.upsert_grade_records(&records)?)
+}
+
+/// Fetches grade data from `gradeDistributionUrl` once a day, if it's set.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+pub async fn run_grade_sync(state: Arc<WrapperState>) {
+    let Some(url) = &state.grade_distribution_url else {
+        return;
+    };
+
+    let mut interval = tokio::time::interval(GRADE_SYNC_INTERVAL);
+    loop {
+        interval.tick().await;
+        if !state.leader.is_leader() {
+            continue;
+        }
+
+        let imported = match fetch_grade_csv(&state, url).await {
+            Ok(text) => import_grade_csv(&state, &text),
+            Err(e) => Err(e),
+        };
+        match imported {
+            Ok(count) => info!("Imported {count} grade distribution record(s) from {url}."),
+            Err(e) => warn!("Failed to import grade distributions from {url}: {e}"),
+        }
+    }
+}
+
+/// Fetches a CSV file of grade data.
+async fn fetch_grade_csv(state: &WrapperState, url: &str) -> Result<String, GradeImportError> {
+    Ok(state
+        .client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_summarize() {
+        let csv = "Instructor,Course,Term,Enrolled,Avg_GPA,Recommend_Instructor\n\
+                   \"Doe, Jane\",cse  100,fa23,100,3.50 (B+),90.0 %\n\
+                   \"Doe, Jane\",CSE 100,WI24,300,3.10,70\n\
+                   Smith,CSE 100,SP24,,,\n";
+        let records = parse_grade_csv(csv).unwrap();
+        assert_eq!(3, records.len());
+        assert_eq!("CSE 100", records[0].course);
+        assert_eq!("FA23", records[0].term);
+        assert_eq!("Doe, Jane", records[0].instructor);
+        assert_eq!(Some(3.5), records[0].avg_gpa);
+        assert_eq!(None, records[2].enrolled);
+
+        // Weighted by enrollment, and offerings without data don't count
+        let summary = summarize(&records).unwrap();
+        assert_eq!(3, summary.offerings);
+        assert!((summary.avg_gpa.unwrap() - 3.2).abs() < 1e-4);
+        assert!((summary.instructor_rating.unwrap() - 75.0).abs() < 1e-4);
+
+        let by_instructor = summarize_by_instructor(&records);
+        assert_eq!(2, by_instructor["Doe, Jane"].offerings);
+        assert_eq!(None, by_instructor["Smith"].avg_gpa);
+
+        assert!(matches!(
+            parse_grade_csv("course,term\nCSE 100,FA23"),
+            Err(GradeImportError::MissingColumn("instructor"))
+        ));
+        assert!(matches!(
+            parse_grade_csv("course,term,instructor,avg_gpa\nCSE 100,FA23,Doe,A"),
+            Err(GradeImportError::InvalidValue { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,286 @@
+//! Hooks that are fired after a student's enrollment changes.
+//!
+//! Whenever an add or drop made through the API is verified (see [`crate::receipts`]), an
+//! [`EnrollmentEvent`] is published on the [`EnrollmentBus`]. Each configured hook picks up
+//! the events that pass its filters and posts them as JSON to a webhook, sends them to its
+//! notification channels (see [`crate::notify`]), or runs a command with the event's JSON
+//! in the `WEBREG_EVENT` environment variable. This makes it possible to, e.g., update a
+//! spreadsheet, post to a Discord channel, or sync a calendar.
+
+use std::process::Command;
+use std::sync::Arc;
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+use crate::db::normalize_course_code;
+use crate::notify::{notify_all, Notification};
+use crate::receipts::{EnrollmentAction, EnrollmentReceipt};
+use crate::types::{ConfigHook, WrapperState};
+
+/// The number of events that can be buffered before slow hooks start missing events.
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+/// The environment variable that a hook's command receives the event in.
+const EVENT_ENV_VAR: &str = "WEBREG_EVENT";
+
+/// A verified change to a student's enrollment.
+#[derive(Debug, Clone, Serialize)]
+pub struct EnrollmentEvent {
+    pub term: String,
+    pub action: EnrollmentAction,
+    pub section_id: String,
+    /// The course (e.g., `CSE 100`), if known.
+    pub subj_course_id: Option<String>,
+    /// The section code (e.g., `A01`), if known.
+    pub section_code: Option<String>,
+    /// The ID of the change in the action log, if it was recorded.
+    pub action_id: Option<i64>,
+    /// When the change was made, in RFC 3339 format.
+    pub timestamp: String,
+}
+
+impl EnrollmentEvent {
+    /// Describes the event as a notification.
+    ///
+    /// # Returns
+    /// The notification, with the event as its payload.
+    pub fn notification(&self) -> Notification {
+        let section = match (&self.subj_course_id, &self.section_code) {
+            (Some(course), Some(code)) => format!("{course} ({code})"),
+            (Some(course), None) => course.clone(),
+            _ => format!("section {}", self.section_id),
+        };
+        let verb = match self.action {
+            EnrollmentAction::Add => "Added",
+            EnrollmentAction::Drop => "Dropped",
+        };
+
+        Notification {
+            title: format!("{verb} {section}"),
+            message: format!(
+                "{verb} {section}, section ID {}, in {} at {}.",
+                self.section_id, self.term, self.timestamp
+            ),
+            payload: serde_json::to_value(self).unwrap(),
+        }
+    }
+
+    /// Creates an event from the receipt for an enrollment change.
+    ///
+    /// # Parameters
+    /// - `term`: The term that the change was made in.
+    /// - `receipt`: The receipt.
+    ///
+    /// # Returns
+    /// The event.
+    pub fn from_receipt(term: &str, receipt: &EnrollmentReceipt) -> Self {
+        let section = receipt.after.as_ref().or(receipt.before.as_ref());
+        Self {
+            term: term.to_owned(),
+            action: receipt.action,
+            section_id: receipt.section_id.clone(),
+            subj_course_id: section
+                .map(|s| format!("{} {}", s.subject_code.trim(), s.course_code.trim())),
+            section_code: section.map(|s| s.section_code.trim().to_owned()),
+            action_id: receipt.action_id,
+            timestamp: receipt.timestamp.clone(),
+        }
+    }
+}
+
+/// Publishes enrollment events to the hooks.
+pub struct EnrollmentBus {
+    sender: broadcast::Sender<EnrollmentEvent>,
+}
+
+impl Default for EnrollmentBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnrollmentBus {
+    /// Creates a new bus with no subscribers.
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// Subscribes to all future events.
+    ///
+    /// # Returns
+    /// The receiver for the events.
+    pub fn subscribe(&self) -> broadcast::Receiver<EnrollmentEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Publishes an event. Nothing happens if no hooks are listening.
+    ///
+    /// # Parameters
+    /// - `event`: The event.
+    pub fn publish(&self, event: EnrollmentEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// The number of events that the slowest hook hasn't handled yet.
+    pub fn queued(&self) -> usize {
+        self.sender.len()
+    }
+}
+
+/// Whether a hook should be fired for an event.
+///
+/// # Parameters
+/// - `hook`: The hook.
+/// - `event`: The event.
+///
+/// # Returns
+/// `true` if the event passes all of the hook's filters. Filters that are empty let every
+/// event through.
+pub fn hook_matches(hook: &ConfigHook, event: &EnrollmentEvent) -> bool {
+    let action_ok = hook.actions.is_empty()
+        || hook
+            .actions
+            .iter()
+            .any(|a| a.eq_ignore_ascii_case(event.action.as_str()));
+    let term_ok = hook.terms.is_empty() || hook.terms.iter().any(|t| t == &event.term);
+    let course_ok = hook.courses.is_empty()
+        || event.subj_course_id.as_deref().is_some_and(|course| {
+            let course = normalize_course_code(course);
+            hook.courses
+                .iter()
+                .any(|c| normalize_course_code(c) == course)
+        });
+
+    action_ok && term_ok && course_ok
+}
+
+/// Fires the configured hooks for every enrollment event until the bus is closed. This
+/// does nothing if no hooks are configured.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+pub async fn run_hooks(state: Arc<WrapperState>) {
+    if state.enrollment_hooks.is_empty() {
+        return;
+    }
+
+    info!(
+        "Running {} post-enrollment hook(s).",
+        state.enrollment_hooks.len()
+    );
+    let mut events = state.enrollment_bus.subscribe();
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Post-enrollment hooks missed {skipped} event(s).");
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        for hook in state
+            .enrollment_hooks
+            .iter()
+            .filter(|h| hook_matches(h, &event))
+        {
+            tokio::spawn(fire_hook(state.clone(), hook.clone(), event.clone()));
+        }
+    }
+}
+
+/// Fires a single hook for an event.
+async fn fire_hook(state: Arc<WrapperState>, hook: ConfigHook, event: EnrollmentEvent) {
+    if let Some(url) = &hook.webhook_url {
+        match state.client.post(url).json(&event).send().await {
+            Ok(resp) if resp.status().is_success() => {
+                info!("[{}] Sent {} event.", hook.name, event.action.as_str());
+            }
+            Ok(resp) => warn!("[{}] Webhook returned {}.", hook.name, resp.status()),
+            Err(e) => warn!("[{}] Webhook failed: {e}", hook.name),
+        }
+    }
+
+    if !hook.channels.is_empty() {
+        let sent = notify_all(
+            &state,
+            &state.notify_client,
+            &hook.channels,
+            &event.notification(),
+        )
+        .await;
+        info!(
+            "[{}] Sent {} event to {sent}/{} channel(s).",
+            hook.name,
+            event.action.as_str(),
+            hook.channels.len()
+        );
+    }
+
+    if let Some(command) = hook.command {
+        let payload = serde_json::to_string(&event).unwrap();
+        let result = tokio::task::spawn_blocking(move || {
+            Command::new("sh")
+                .arg("-c")
+                .arg(&command)
+                .env(EVENT_ENV_VAR, payload)
+                .status()
+        })
+        .await;
+
+        match result {
+            Ok(Ok(status)) if status.success() => {
+                info!(
+                    "[{}] Ran command for {} event.",
+                    hook.name,
+                    event.action.as_str()
+                );
+            }
+            Ok(Ok(status)) => warn!("[{}] Command exited with {status}.", hook.name),
+            Ok(Err(e)) => warn!("[{}] Command failed to run: {e}", hook.name),
+            Err(e) => warn!("[{}] Command panicked: {e}", hook.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(action: EnrollmentAction) -> EnrollmentEvent {
+        EnrollmentEvent {
+            term: "FA24".to_string(),
+            action,
+            section_id: "123456".to_string(),
+            subj_course_id: Some("CSE 100".to_string()),
+            section_code: Some("A01".to_string()),
+            action_id: Some(1),
+            timestamp: "2024-09-01T00:00:00+00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_hook_filters() {
+        let mut hook = ConfigHook {
+            name: "discord".to_string(),
+            webhook_url: Some("https://example.com".to_string()),
+            channels: vec![],
+            command: None,
+            actions: vec![],
+            terms: vec![],
+            courses: vec![],
+        };
+        assert!(hook_matches(&hook, &event(EnrollmentAction::Drop)));
+
+        hook.actions = vec!["ADD".to_string()];
+        hook.courses = vec!["cse 100".to_string()];
+        assert!(hook_matches(&hook, &event(EnrollmentAction::Add)));
+        assert!(!hook_matches(&hook, &event(EnrollmentAction::Drop)));
+
+        hook.terms = vec!["WI25".to_string()];
+        assert!(!hook_matches(&hook, &event(EnrollmentAction::Add)));
+    }
+}
@@ -0,0 +1,110 @@
+//! Shared handling of pages fetched from upstream systems (e.g., DARS) before they're parsed.
+//!
+//! Upstream pages are outside of our control, so every page is checked against a size limit
+//! and decoded leniently before parsing. Script and style content is stripped from pages
+//! that are parsed in full, since the parser never looks at it and it can make up most of
+//! a page.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+use thiserror::Error;
+
+/// The largest page, in bytes, that will be parsed.
+pub const MAX_PAGE_BYTES: usize = 8 * 1024 * 1024;
+
+static SCRIPT_STYLE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<script\b.*?</script\s*>|<style\b.*?</style\s*>").unwrap());
+
+/// The reason a page was rejected.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum IngestError {
+    #[error("Page is {size} bytes, which is over the {limit}-byte limit")]
+    TooLarge { size: usize, limit: usize },
+}
+
+/// Checks that a page isn't too large to be parsed.
+///
+/// # Parameters
+/// - `size`: The size of the page, in bytes.
+///
+/// # Returns
+/// An error if the page is too large.
+pub fn check_size(size: usize) -> Result<(), IngestError> {
+    if size > MAX_PAGE_BYTES {
+        return Err(IngestError::TooLarge {
+            size,
+            limit: MAX_PAGE_BYTES,
+        });
+    }
+
+    Ok(())
+}
+
+/// Decodes a page. Pages with a byte order mark are decoded as UTF-8 or UTF-16 accordingly,
+/// other pages are decoded as UTF-8 if valid and as Latin-1 otherwise. `NUL` characters are
+/// removed.
+///
+/// # Parameters
+/// - `bytes`: The raw page.
+///
+/// # Returns
+/// The decoded page, or an error if the page is too large.
+pub fn decode(bytes: &[u8]) -> Result<String, IngestError> {
+    check_size(bytes.len())?;
+
+    let text = if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        String::from_utf8_lossy(rest).into_owned()
+    } else if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+        decode_utf16(rest, u16::from_le_bytes)
+    } else if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+        decode_utf16(rest, u16::from_be_bytes)
+    } else {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_owned(),
+            Err(_) => bytes.iter().map(|&b| b as char).collect(),
+        }
+    };
+
+    Ok(text.replace('\0', ""))
+}
+
+/// Decodes UTF-16 text, replacing invalid code units.
+fn decode_utf16(bytes: &[u8], to_unit: fn([u8; 2]) -> u16) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| to_unit([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Prepares a page to be parsed in full by removing its `<script>` and `<style>` elements.
+///
+/// # Parameters
+/// - `html`: The page.
+///
+/// # Returns
+/// The sanitized page, or an error if the page is too large.
+pub fn sanitize_html(html: &str) -> Result<String, IngestError> {
+    check_size(html.len())?;
+    Ok(SCRIPT_STYLE_REGEX.replace_all(html, "").into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_and_decode() {
+        let html = "<p>a</p><SCRIPT type=\"x\">if (a < b) {}</script ><style>p {}</style><p>b</p>";
+        assert_eq!(Ok("<p>a</p><p>b</p>".to_string()), sanitize_html(html));
+
+        assert_eq!(Ok("é".to_string()), decode(b"\xEF\xBB\xBF\xC3\xA9"));
+        assert_eq!(Ok("é".to_string()), decode(b"\xE9"));
+        assert_eq!(Ok("hi".to_string()), decode(b"\xFF\xFEh\0i\0"));
+
+        let huge = vec![b'a'; MAX_PAGE_BYTES + 1];
+        assert!(matches!(decode(&huge), Err(IngestError::TooLarge { .. })));
+    }
+}
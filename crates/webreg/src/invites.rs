@@ -0,0 +1,72 @@
+//! Invite codes, which let people register for an API key on their own on deployments
+//! shared by several users, rather than having whoever runs the scraper make one for each
+//! of them.
+//!
+//! Only a hash of each code is stored, so a code can't be recovered from the database; it's
+//! shown once, when it's minted.
+
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use crate::degree_audit::cache::hex;
+
+/// The characters that codes are made of. Characters that are easily confused with each
+/// other (e.g., `0` and `O`) are left out, since codes are often typed in by hand.
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+/// The number of groups in a code, and the number of characters in each.
+const CODE_GROUPS: usize = 3;
+const CODE_GROUP_LEN: usize = 4;
+
+/// Generates a new invite code, like `7KQM-X2TR-HC9A`.
+pub fn generate_invite_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..CODE_GROUPS)
+        .map(|_| {
+            (0..CODE_GROUP_LEN)
+                .map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char)
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Hashes an invite code for storage. Codes are compared without regard to case, dashes, or
+/// surrounding whitespace, so that they're forgiving to type.
+///
+/// # Parameters
+/// - `code`: The invite code.
+///
+/// # Returns
+/// The hash, in hex.
+pub fn hash_invite_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    hex::encode(&Sha256::digest(normalized.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invite_codes() {
+        let code = generate_invite_code();
+        assert_eq!(CODE_GROUPS * (CODE_GROUP_LEN + 1) - 1, code.len());
+        assert!(code
+            .split('-')
+            .all(|g| g.bytes().all(|b| CODE_ALPHABET.contains(&b))));
+
+        assert_eq!(
+            hash_invite_code("7KQM-X2TR-HC9A"),
+            hash_invite_code(" 7kqmx2trhc9a ")
+        );
+        assert_ne!(
+            hash_invite_code("7KQM-X2TR-HC9A"),
+            hash_invite_code("7KQM-X2TR-HC9B")
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! Daily and monthly request quotas per API key.
+//!
+//! Unlike the rate limits (see [`crate::rate_limit`]), which smooth out bursts, quotas cap
+//! the total number of requests that an integration may make over a day or a month. Every
+//! request made with an API key is counted per day, and the counts are kept in SQLite so
+//! that operators can see which integrations drive load, and so that restarting doesn't
+//! reset anyone's quota.
+
+use std::sync::Mutex;
+
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveTime};
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use tracing::warn;
+
+/// How long, in days, daily counts are kept for.
+const USAGE_RETENTION_DAYS: i64 = 90;
+
+/// The period that a quota applies to.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaPeriod {
+    Daily,
+    Monthly,
+}
+
+/// Why a request was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    pub period: QuotaPeriod,
+    pub used: u32,
+    pub limit: u32,
+    /// How long until the quota resets.
+    pub resets_in: Duration,
+}
+
+/// The number of requests that a key made on a single day.
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyUsage {
+    /// The day, in `YYYY-MM-DD` format.
+    pub day: String,
+    pub count: u32,
+}
+
+/// A key's usage of its quotas.
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyUsage {
+    pub key: String,
+    pub today: u32,
+    pub this_month: u32,
+    pub daily_limit: Option<u32>,
+    pub monthly_limit: Option<u32>,
+    /// The key's daily counts, newest first.
+    pub days: Vec<DailyUsage>,
+}
+
+/// Counts the requests that each API key makes, and enforces its quotas.
+pub struct KeyQuota {
+    db: Mutex<Connection>,
+    daily_limit: Option<u32>,
+    monthly_limit: Option<u32>,
+}
+
+impl KeyQuota {
+    /// Opens (or creates) the usage database at the given path.
+    ///
+    /// Falls back to an in-memory database if the file can't be opened.
+    ///
+    /// # Parameters
+    /// - `path`: The path to the database.
+    /// - `daily_limit`: The number of requests each key may make per day, if limited.
+    /// - `monthly_limit`: The number of requests each key may make per month, if limited.
+    pub fn open(path: &str, daily_limit: Option<u32>, monthly_limit: Option<u32>) -> Self {
+        let conn = Connection::open(path).unwrap_or_else(|e| {
+            warn!(
+                "Failed to open key usage database {}: {}. Using memory.",
+                path, e
+            );
+            Connection::open_in_memory().expect("Failed to open in-memory database")
+        });
+
+        Self::from_connection(conn, daily_limit, monthly_limit)
+    }
+
+    /// Creates a quota tracker that isn't persisted.
+    #[cfg(test)]
+    pub fn in_memory(daily_limit: Option<u32>, monthly_limit: Option<u32>) -> Self {
+        let conn = Connection::open_in_memory().expect("Failed to open in-memory database");
+        Self::from_connection(conn, daily_limit, monthly_limit)
+    }
+
+    fn from_connection(
+        conn: Connection,
+        daily_limit: Option<u32>,
+        monthly_limit: Option<u32>,
+    ) -> Self {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS key_usage (
+                key_id TEXT NOT NULL,
+                day DATE NOT NULL,
+                count INTEGER NOT NULL,
+                PRIMARY KEY (key_id, day)
+            )",
+        )
+        .expect("Failed to initialize key usage schema");
+
+        let cutoff = Local::now().date_naive() - Duration::days(USAGE_RETENTION_DAYS);
+        if let Err(e) = conn.execute("DELETE FROM key_usage WHERE day < ?", [format_day(cutoff)]) {
+            warn!("Failed to prune old key usage counts: {}", e);
+        }
+
+        Self {
+            db: Mutex::new(conn),
+            daily_limit,
+            monthly_limit,
+        }
+    }
+
+    /// Records a request made with the given key.
+    ///
+    /// # Parameters
+    /// - `key`: The key's ID (its prefix).
+    ///
+    /// # Returns
+    /// Nothing if the request may go ahead, or which quota the key has used up. Nothing is
+    /// recorded in the latter case.
+    pub fn try_consume(&self, key: &str) -> Result<(), QuotaExceeded> {
+        self.try_consume_on(key, Local::now().date_naive())
+    }
+
+    fn try_consume_on(&self, key: &str, day: NaiveDate) -> Result<(), QuotaExceeded> {
+        let db = self.db.lock().unwrap();
+        let (today, this_month) = counts(&db, key, day);
+
+        if let Some(limit) = self.daily_limit.filter(|&l| today >= l) {
+            return Err(QuotaExceeded {
+                period: QuotaPeriod::Daily,
+                used: today,
+                limit,
+                resets_in: until(day + Duration::days(1)),
+            });
+        }
+
+        if let Some(limit) = self.monthly_limit.filter(|&l| this_month >= l) {
+            return Err(QuotaExceeded {
+                period: QuotaPeriod::Monthly,
+                used: this_month,
+                limit,
+                resets_in: until(first_of_next_month(day)),
+            });
+        }
+
+        if let Err(e) = db.execute(
+            "INSERT INTO key_usage (key_id, day, count) VALUES (?1, ?2, 1)
+             ON CONFLICT(key_id, day) DO UPDATE SET count = count + 1",
+            (key, format_day(day)),
+        ) {
+            warn!("Failed to record request for key {}: {}", key, e);
+        }
+
+        Ok(())
+    }
+
+    /// Gets a key's usage of its quotas.
+    ///
+    /// # Parameters
+    /// - `key`: The key's ID (its prefix).
+    ///
+    /// # Returns
+    /// The usage, or nothing if the key hasn't made any requests that are still on record.
+    pub fn usage(&self, key: &str) -> Option<KeyUsage> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db
+            .prepare("SELECT day, count FROM key_usage WHERE key_id = ? ORDER BY day DESC")
+            .ok()?;
+        let days: Vec<DailyUsage> = stmt
+            .query_map([key], |row| {
+                Ok(DailyUsage {
+                    day: row.get(0)?,
+                    count: row.get(1)?,
+                })
+            })
+            .map(|rows| rows.filter_map(Result::ok).collect())
+            .unwrap_or_default();
+
+        if days.is_empty() {
+            return None;
+        }
+
+        let (today, this_month) = counts(&db, key, Local::now().date_naive());
+        Some(KeyUsage {
+            key: key.to_owned(),
+            today,
+            this_month,
+            daily_limit: self.daily_limit,
+            monthly_limit: self.monthly_limit,
+            days,
+        })
+    }
+}
+
+/// Gets the number of requests that a key made on the given day, and in that day's month.
+fn counts(db: &Connection, key: &str, day: NaiveDate) -> (u32, u32) {
+    let today: u32 = db
+        .query_row(
+            "SELECT count FROM key_usage WHERE key_id = ?1 AND day = ?2",
+            (key, format_day(day)),
+            |row| row.get(0),
+        )
+        .optional()
+        .ok()
+        .flatten()
+        .unwrap_or(0);
+    let this_month: u32 = db
+        .query_row(
+            "SELECT COALESCE(SUM(count), 0) FROM key_usage
+             WHERE key_id = ?1 AND day >= ?2 AND day < ?3",
+            (
+                key,
+                format_day(day.with_day(1).unwrap()),
+                format_day(first_of_next_month(day)),
+            ),
+            |row| row.get(0),
+        )
+        .unwrap_or(0);
+
+    (today, this_month)
+}
+
+/// The first day of the month after the given day's.
+fn first_of_next_month(day: NaiveDate) -> NaiveDate {
+    let (year, month) = match day.month() {
+        12 => (day.year() + 1, 1),
+        m => (day.year(), m + 1),
+    };
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap()
+}
+
+/// How long until the start of the given day, in local time.
+fn until(day: NaiveDate) -> Duration {
+    let now = Local::now().naive_local();
+    (day.and_time(NaiveTime::MIN) - now).max(Duration::zero())
+}
+
+/// Formats a day in `YYYY-MM-DD` format.
+fn format_day(day: NaiveDate) -> String {
+    day.format("%Y-%m-%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_and_monthly_quotas() {
+        let quota = KeyQuota::in_memory(Some(2), Some(3));
+        let jan_30 = NaiveDate::from_ymd_opt(2026, 1, 30).unwrap();
+        let jan_31 = NaiveDate::from_ymd_opt(2026, 1, 31).unwrap();
+        let feb_1 = NaiveDate::from_ymd_opt(2026, 2, 1).unwrap();
+
+        assert!(quota.try_consume_on("app", jan_30).is_ok());
+        assert!(quota.try_consume_on("app", jan_30).is_ok());
+        let err = quota.try_consume_on("app", jan_30).unwrap_err();
+        assert_eq!(
+            (QuotaPeriod::Daily, 2, 2),
+            (err.period, err.used, err.limit)
+        );
+
+        // Other keys have their own quotas
+        assert!(quota.try_consume_on("other", jan_30).is_ok());
+
+        assert!(quota.try_consume_on("app", jan_31).is_ok());
+        let err = quota.try_consume_on("app", jan_31).unwrap_err();
+        assert_eq!(
+            (QuotaPeriod::Monthly, 3, 3),
+            (err.period, err.used, err.limit)
+        );
+
+        assert!(quota.try_consume_on("app", feb_1).is_ok());
+
+        let usage = quota.usage("app").unwrap();
+        let days: Vec<_> = usage
+            .days
+            .iter()
+            .map(|d| (d.day.as_str(), d.count))
+            .collect();
+        assert_eq!(
+            vec![("2026-02-01", 1), ("2026-01-31", 1), ("2026-01-30", 2)],
+            days
+        );
+        assert!(quota.usage("unknown").is_none());
+    }
+}
@@ -0,0 +1,210 @@
+//! Leader election for deployments where several instances share the schedule database.
+//!
+//! Every instance serves the API, but only the one holding the leader lease (a row in the
+//! schedule database) runs the background work: the tracker, enrollment jobs, audit archive
+//! cleanup, and replication. That way, WebReg isn't scraped twice and students aren't
+//! enrolled twice. The leader renews its lease three times per lease duration; if it stops
+//! renewing (e.g., it crashed), another instance takes the lease over once it expires.
+//! Instances' clocks should agree to well within the lease duration.
+//!
+//! Unless `leaderElection` is set, every instance is its own leader.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::Rng;
+use serde::Serialize;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::db::ScheduleDbManager;
+use crate::replication::when_primary;
+use crate::types::WrapperState;
+
+/// How long the leader lease lasts without being renewed, by default.
+pub const DEFAULT_LEASE_DURATION: Duration = Duration::from_secs(30);
+/// The name of the lease in the database.
+const LEADER_LEASE: &str = "leader";
+
+/// Whether this instance is the leader, as seen by the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaderStatus {
+    pub enabled: bool,
+    pub instance_id: String,
+    pub is_leader: bool,
+    /// The instance holding the lease, if anyone holds it.
+    pub holder: Option<String>,
+    /// When the lease expires, in milliseconds since the epoch.
+    pub expires_at: Option<i64>,
+}
+
+/// Decides whether this instance runs the background work.
+pub struct LeaderElection {
+    /// The ID that this instance holds the lease under.
+    instance_id: String,
+    /// How long the lease lasts, or nothing if leader election is off.
+    lease_duration: Option<Duration>,
+    /// Whether this instance holds the lease.
+    leader: watch::Sender<bool>,
+}
+
+impl LeaderElection {
+    /// Creates the leader election state.
+    ///
+    /// # Parameters
+    /// - `enabled`: Whether leader election is on. If not, this instance is always the
+    ///   leader.
+    /// - `instance_id`: The ID to hold the lease under. If not given, a random one is used.
+    /// - `lease_duration`: How long the lease lasts without being renewed.
+    pub fn new(enabled: bool, instance_id: Option<String>, lease_duration: Duration) -> Self {
+        Self {
+            instance_id: instance_id.unwrap_or_else(|| {
+                format!(
+                    "{}-{:08x}",
+                    std::process::id(),
+                    rand::thread_rng().gen::<u32>()
+                )
+            }),
+            lease_duration: enabled.then_some(lease_duration.max(Duration::from_secs(1))),
+            leader: watch::Sender::new(!enabled),
+        }
+    }
+
+    /// Whether this instance holds the lease.
+    pub fn is_leader(&self) -> bool {
+        *self.leader.borrow()
+    }
+
+    /// Waits until this instance holds the lease.
+    pub async fn wait_until_leader(&self) {
+        let mut leader = self.leader.subscribe();
+        // The sender lives as long as `self`, so this can't fail
+        let _ = leader.wait_for(|leader| *leader).await;
+    }
+
+    /// Gives up the lease, so that another instance can take over right away. This is done
+    /// when the server shuts down.
+    ///
+    /// # Parameters
+    /// - `db`: The database that the lease is stored in.
+    pub fn step_down(&self, db: &ScheduleDbManager) {
+        if self.lease_duration.is_none() || !self.leader.send_replace(false) {
+            return;
+        }
+
+        match db.release_lease(LEADER_LEASE, &self.instance_id) {
+            Ok(()) => info!("Gave up the leader lease."),
+            Err(e) => warn!("Failed to give up the leader lease: {e}"),
+        }
+    }
+
+    /// Gets whether this instance is the leader, and who holds the lease.
+    ///
+    /// # Parameters
+    /// - `db`: The database that the lease is stored in.
+    pub fn status(&self, db: &ScheduleDbManager) -> LeaderStatus {
+        let lease = db.get_lease(LEADER_LEASE).ok().flatten();
+        LeaderStatus {
+            enabled: self.lease_duration.is_some(),
+            instance_id: self.instance_id.clone(),
+            is_leader: self.is_leader(),
+            holder: lease.as_ref().map(|(holder, _)| holder.clone()),
+            expires_at: lease.map(|(_, expires_at)| expires_at),
+        }
+    }
+}
+
+/// Runs a background worker once this instance is the primary (see
+/// [`crate::replication`]) and holds the leader lease. Workers that run in rounds should
+/// also skip rounds while this instance isn't the leader, since it can lose the lease.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `worker`: The worker to run.
+pub async fn when_leader<F, Fut>(state: Arc<WrapperState>, worker: F)
+where
+    F: FnOnce(Arc<WrapperState>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    when_primary(state, |state| async move {
+        state.leader.wait_until_leader().await;
+        worker(state).await;
+    })
+    .await;
+}
+
+/// Takes and renews the leader lease, if leader election is on.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+pub async fn run_leader_election(state: Arc<WrapperState>) {
+    let election = &state.leader;
+    let Some(lease_duration) = election.lease_duration else {
+        return;
+    };
+
+    info!(
+        "Competing for the leader lease as {}.",
+        election.instance_id
+    );
+    let mut interval = tokio::time::interval(lease_duration / 3);
+    loop {
+        interval.tick().await;
+        if state.should_stop() {
+            break;
+        }
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let held = state
+            .schedule_db
+            .try_acquire_lease(
+                LEADER_LEASE,
+                &election.instance_id,
+                now,
+                lease_duration.as_millis() as i64,
+            )
+            // If the lease couldn't be renewed, another instance may take it over
+            .inspect_err(|e| warn!("Failed to renew the leader lease: {e}"))
+            .unwrap_or(false);
+
+        match (election.leader.send_replace(held), held) {
+            (false, true) => info!("This instance is now the leader."),
+            (true, false) => warn!("This instance lost the leader lease."),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lease_takeover() {
+        let db = ScheduleDbManager::new(":memory:");
+        assert!(db.try_acquire_lease(LEADER_LEASE, "a", 0, 1_000).unwrap());
+        assert!(!db.try_acquire_lease(LEADER_LEASE, "b", 500, 1_000).unwrap());
+
+        // The holder can renew the lease, which pushes back when it can be taken over
+        assert!(db.try_acquire_lease(LEADER_LEASE, "a", 900, 1_000).unwrap());
+        assert!(!db
+            .try_acquire_lease(LEADER_LEASE, "b", 1_500, 1_000)
+            .unwrap());
+        assert!(db
+            .try_acquire_lease(LEADER_LEASE, "b", 1_900, 1_000)
+            .unwrap());
+        assert_eq!(
+            Some(("b".to_owned(), 2_900)),
+            db.get_lease(LEADER_LEASE).unwrap()
+        );
+
+        // Only the holder can give the lease up
+        db.release_lease(LEADER_LEASE, "a").unwrap();
+        assert!(db.get_lease(LEADER_LEASE).unwrap().is_some());
+        db.release_lease(LEADER_LEASE, "b").unwrap();
+        assert!(db
+            .try_acquire_lease(LEADER_LEASE, "a", 2_000, 1_000)
+            .unwrap());
+    }
+}
@@ -0,0 +1,57 @@
+//! The scraper and API server. The `webreg` binary runs them; the library exists so that
+//! benchmarks (see `benches/`) can exercise the same code.
+
+pub mod alternatives;
+#[cfg(feature = "auth")]
+pub mod api_keys;
+pub mod attribution;
+pub mod audit_trail;
+pub mod changes;
+pub mod co_enrollment;
+pub mod cookie_freshness;
+pub mod cookie_health;
+pub mod course_plans;
+pub mod cross_listings;
+pub mod db;
+pub mod degree_audit;
+pub mod drift;
+pub mod enroll_jobs;
+pub mod error;
+pub mod fill_rate;
+pub mod grades;
+pub mod hooks;
+pub mod ingest;
+#[cfg(feature = "auth")]
+pub mod invites;
+pub mod key_quota;
+pub mod leader;
+pub mod load_shed;
+pub mod notify;
+pub mod offerings;
+pub mod pass_times;
+pub mod payload_store;
+pub mod plan_sync;
+pub mod rate_limit;
+pub mod receipts;
+#[cfg(feature = "auth")]
+pub mod redaction;
+pub mod replication;
+pub mod request_log;
+pub mod restrictions;
+pub mod retry;
+pub mod schedule;
+pub mod schedule_mirror;
+pub mod scheduler;
+pub mod scraper;
+pub mod search;
+pub mod semantic;
+pub mod server;
+pub mod sessions;
+pub mod sharing;
+pub mod standalone;
+pub mod status_history;
+pub mod sync;
+pub mod types;
+#[cfg(feature = "auth")]
+pub mod vault;
+pub mod webweg_types;
@@ -0,0 +1,211 @@
+//! Load shedding, so that enrollment stays responsive when the server is overloaded.
+//!
+//! Every request to the API is given a priority from its route. When the number of requests
+//! being served, or the number of tasks waiting for the tokio runtime, passes its threshold,
+//! low-priority requests (exports and analytics, which can be retried later) are turned
+//! away with a `503 Service Unavailable`. At twice the threshold, ordinary requests are
+//! turned away too. Enrollment changes and seat checks are never turned away.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use serde::Serialize;
+use tokio::runtime::Handle;
+
+/// The default number of requests that may be served at once before requests are shed.
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 256;
+/// The default number of tasks that may be waiting for the tokio runtime before requests
+/// are shed.
+pub const DEFAULT_MAX_QUEUE_DEPTH: usize = 1024;
+
+/// Routes that are never shed: enrollment changes and seat checks.
+const CRITICAL_ROUTES: &[&str] = &[
+    "/health",
+    "/live/:term/add_section",
+    "/live/:term/validate_add_section",
+    "/live/:term/drop_section",
+    "/live/:term/swap_sections",
+    "/live/:term/bulk_add",
+    "/live/:term/course_info",
+    "/live/:term/schedule",
+];
+
+/// Routes that are shed first: exports and analytics.
+const LOW_PRIORITY_ROUTES: &[&str] = &[
+    "/live/:term/schedule_data",
+    "/live/:term/schedule_data/export",
+    "/live/:term/heatmap",
+    "/live/:term/analytics/fill_rate",
+    "/schedule_ical",
+    "/users/:id/export",
+    "/timing/:term",
+    "/login_stat/:stat",
+    "/degree_audit/report.pdf",
+    "/degree_audit/export.xlsx",
+    "/degree_audit/graduation_plan",
+    "/degree_audit/cache_stats",
+];
+
+/// How important it is that a request is served when the server is overloaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Critical,
+    Normal,
+    Low,
+}
+
+impl Priority {
+    /// Gets the priority of a route.
+    ///
+    /// # Parameters
+    /// - `path`: The route's path, as registered with the router (e.g.,
+    ///   `/live/:term/add_section`).
+    pub fn of_route(path: &str) -> Self {
+        if CRITICAL_ROUTES.contains(&path) {
+            Priority::Critical
+        } else if LOW_PRIORITY_ROUTES.contains(&path) {
+            Priority::Low
+        } else {
+            Priority::Normal
+        }
+    }
+}
+
+/// A snapshot of the server's load, and of how many requests were shed.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadStats {
+    pub in_flight: usize,
+    pub max_in_flight: usize,
+    pub queue_depth: usize,
+    pub max_queue_depth: usize,
+    /// The number of ordinary requests turned away since the server started.
+    pub shed_normal: u64,
+    /// The number of low-priority requests turned away since the server started.
+    pub shed_low: u64,
+}
+
+/// Counts the requests being served, and decides which requests to turn away.
+pub struct LoadShedder {
+    max_in_flight: usize,
+    max_queue_depth: usize,
+    in_flight: AtomicUsize,
+    shed_normal: AtomicU64,
+    shed_low: AtomicU64,
+}
+
+/// A request being served. The request stops counting as in flight once this is dropped.
+pub struct InFlight<'a>(&'a LoadShedder);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl LoadShedder {
+    /// Creates a new load shedder.
+    ///
+    /// # Parameters
+    /// - `max_in_flight`: The number of requests that may be served at once.
+    /// - `max_queue_depth`: The number of tasks that may be waiting for the runtime.
+    pub fn new(max_in_flight: usize, max_queue_depth: usize) -> Self {
+        Self {
+            max_in_flight: max_in_flight.max(1),
+            max_queue_depth: max_queue_depth.max(1),
+            in_flight: AtomicUsize::new(0),
+            shed_normal: AtomicU64::new(0),
+            shed_low: AtomicU64::new(0),
+        }
+    }
+
+    /// Admits a request, unless the server is too loaded to serve a request of its priority.
+    ///
+    /// # Parameters
+    /// - `priority`: The request's priority.
+    ///
+    /// # Returns
+    /// The request's place among the requests being served, or nothing if it should be
+    /// turned away. In the latter case, it's counted as shed.
+    pub fn admit(&self, priority: Priority) -> Option<InFlight<'_>> {
+        self.admit_at(priority, queue_depth())
+    }
+
+    fn admit_at(&self, priority: Priority, queue_depth: usize) -> Option<InFlight<'_>> {
+        // How far the server is past its thresholds: 1 at a threshold, 2 at twice it
+        let load = (self.in_flight.load(Ordering::Relaxed) as f64 / self.max_in_flight as f64)
+            .max(queue_depth as f64 / self.max_queue_depth as f64);
+
+        let shed = match priority {
+            Priority::Critical => None,
+            Priority::Normal => Some(&self.shed_normal).filter(|_| load >= 2.0),
+            Priority::Low => Some(&self.shed_low).filter(|_| load >= 1.0),
+        };
+        if let Some(count) = shed {
+            count.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        Some(InFlight(self))
+    }
+
+    /// Gets the server's current load, and how many requests were shed.
+    pub fn stats(&self) -> LoadStats {
+        LoadStats {
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            max_in_flight: self.max_in_flight,
+            queue_depth: queue_depth(),
+            max_queue_depth: self.max_queue_depth,
+            shed_normal: self.shed_normal.load(Ordering::Relaxed),
+            shed_low: self.shed_low.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The number of tasks waiting in the runtime's global queue.
+fn queue_depth() -> usize {
+    Handle::try_current().map_or(0, |h| h.metrics().global_queue_depth())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shedding_by_priority() {
+        assert_eq!(
+            Priority::Critical,
+            Priority::of_route("/live/:term/add_section")
+        );
+        assert_eq!(
+            Priority::Low,
+            Priority::of_route("/live/:term/schedule_data/export")
+        );
+        assert_eq!(Priority::Normal, Priority::of_route("/terms"));
+
+        let shedder = LoadShedder::new(2, 10);
+        let first = shedder.admit_at(Priority::Low, 0).unwrap();
+        let _second = shedder.admit_at(Priority::Normal, 0).unwrap();
+
+        // At the threshold, only low-priority requests are shed
+        assert!(shedder.admit_at(Priority::Low, 0).is_none());
+        let mut admitted = vec![shedder.admit_at(Priority::Normal, 0).unwrap()];
+        admitted.push(shedder.admit_at(Priority::Normal, 0).unwrap());
+
+        // At twice the threshold, so are ordinary requests, but never critical ones
+        assert!(shedder.admit_at(Priority::Normal, 0).is_none());
+        admitted.push(shedder.admit_at(Priority::Critical, 0).unwrap());
+
+        // A long queue counts as load too
+        drop(admitted);
+        drop(first);
+        assert!(shedder.admit_at(Priority::Low, 10).is_none());
+        assert!(shedder.admit_at(Priority::Low, 0).is_some());
+
+        let stats = shedder.stats();
+        assert_eq!(
+            (1, 1, 2),
+            (stats.in_flight, stats.shed_normal, stats.shed_low)
+        );
+    }
+}
@@ -1,6 +1,3 @@
-use crate::scraper::tracker::run_tracker;
-use crate::server::create_router;
-use crate::types::{ConfigScraper, WrapperState};
 use std::fs;
 use std::net::SocketAddr;
 use std::path::Path;
@@ -9,57 +6,79 @@ use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::log::{error, info, warn};
-
-mod db;
-mod degree_audit;
-mod scraper;
-mod server;
-mod types;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use webreg::enroll_jobs::run_watch_alerts;
+use webreg::grades::run_grade_sync;
+use webreg::hooks::run_hooks;
+use webreg::leader::{run_leader_election, when_leader};
+use webreg::replication::{run_replication, when_primary};
+use webreg::request_log::{RequestLogLayer, REQUEST_LOG};
+use webreg::scheduler::run_scheduler;
+use webreg::scraper::catalog::run_catalog_scrape;
+use webreg::scraper::tracker::run_tracker;
+use webreg::semantic::run_semantic_index;
+use webreg::server::{create_admin_router, create_router};
+use webreg::types::{ConfigScraper, WrapperState};
+use webreg::{cookie_health, standalone};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[tokio::main]
 async fn main() -> ExitCode {
-    tracing_subscriber::fmt::init();
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer())
+        .with(RequestLogLayer::new(&REQUEST_LOG))
+        .with(LevelFilter::INFO)
+        .init();
     info!("Started webreg_scraper, version {VERSION}");
-    // First, get the configuration file.
-    let config_path = match std::env::args().skip(1).last() {
-        Some(s) => s,
-        None => {
-            error!("Provide a path to the configuration JSON file as an argument.");
-            return ExitCode::FAILURE;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // First, get the configuration file. Running standalone, it's generated in the data
+    // directory if it isn't there yet
+    let config_info = if args.iter().any(|arg| arg == "--standalone") {
+        let data_dir = args
+            .iter()
+            .position(|arg| arg == "--data-dir")
+            .and_then(|i| args.get(i + 1).cloned())
+            .or_else(|| std::env::var(standalone::DATA_DIR_ENV).ok())
+            .unwrap_or_else(|| standalone::DEFAULT_DATA_DIR.to_owned());
+        match standalone::load_config(Path::new(&data_dir)) {
+            Ok(config) => config,
+            Err(err) => {
+                error!("Couldn't set up the data directory {data_dir}: {err}");
+                return ExitCode::FAILURE;
+            }
         }
-    };
-
-    let config_path = Path::new(config_path.as_str());
-    if !config_path.exists() {
-        error!("Invalid path. Please provide the path to a configuration file.");
-        return ExitCode::FAILURE;
-    }
-
-    let config_info = match serde_json::from_str::<ConfigScraper>(
-        fs::read_to_string(config_path)
-            .expect("Unable to read file.")
-            .as_str(),
-    ) {
-        Ok(config) => config,
-        Err(err) => {
-            error!("Bad config file. Please fix it and then try again.\n{err}");
-            return ExitCode::FAILURE;
+    } else {
+        match read_config(args.last()) {
+            Some(config) => config,
+            None => return ExitCode::FAILURE,
         }
     };
 
     let is_verbose = config_info.verbose;
+    REQUEST_LOG.set_capacity(config_info.request_log_capacity.unwrap_or(0));
     info!("Loaded configuration file: {}", config_info.config_name);
 
-    // Run the tracker for each term
+    // Run the tracker for each term. A standby holds off on anything that talks to WebReg
+    // or fires hooks until it's promoted, and only the leader runs the background work
     let state = Arc::new(WrapperState::new(config_info));
-    tokio::spawn({
-        let cloned_state = state.clone();
-        async move {
-            run_tracker(cloned_state, is_verbose).await;
-        }
-    });
+    if state.cookie_server.is_none() {
+        warn!("There's no cookie server, so degree audits won't be served.");
+    }
+    tokio::spawn(run_leader_election(state.clone()));
+    tokio::spawn(when_leader(state.clone(), move |s| {
+        run_tracker(s, is_verbose)
+    }));
+    tokio::spawn(when_primary(state.clone(), run_hooks));
+    tokio::spawn(when_leader(state.clone(), run_watch_alerts));
+    tokio::spawn(cookie_health::run_cookie_server_health_check(state.clone()));
+    tokio::spawn(when_leader(state.clone(), run_scheduler));
+    tokio::spawn(when_leader(state.clone(), run_replication));
+    tokio::spawn(when_leader(state.clone(), run_grade_sync));
+    tokio::spawn(when_leader(state.clone(), run_catalog_scrape));
+    tokio::spawn(run_semantic_index(state.clone()));
 
     let addr = SocketAddr::from_str(
         format!(
@@ -76,14 +95,119 @@ async fn main() -> ExitCode {
         state.api_base_endpoint.port
     );
 
+    // The admin API gets its own listener if it has an address, so that it's never exposed
+    // on the public listener by accident. Otherwise, it's only served alongside the rest of
+    // the API if it's protected by a token. It's never served without a token unless that's
+    // explicitly allowed.
+    let mut router = create_router(state.clone());
+    let mut admin_server = None;
+    match (&state.admin_endpoint, &state.admin_token) {
+        (Some(_), None) if !state.allow_unauthenticated_admin => {
+            error!(
+                "adminEndpoint is set without an adminToken. Set adminToken, or set \
+                allowUnauthenticatedAdmin if the admin API can't be reached by anyone else."
+            );
+            return ExitCode::FAILURE;
+        }
+        (Some(admin_endpoint), _) => {
+            let admin_addr = SocketAddr::from_str(
+                format!("{}:{}", admin_endpoint.address, admin_endpoint.port).as_str(),
+            );
+
+            if state.admin_token.is_none() {
+                warn!(
+                    "The admin API is served without a token, as allowUnauthenticatedAdmin is set."
+                );
+            }
+
+            info!(
+                "Admin server started on address {}:{}",
+                admin_endpoint.address, admin_endpoint.port
+            );
+
+            let admin_listener = tokio::net::TcpListener::bind(&admin_addr.unwrap())
+                .await
+                .unwrap();
+            let admin_router = create_admin_router(state.clone());
+            admin_server = Some(tokio::spawn(async move {
+                axum::serve(
+                    admin_listener,
+                    admin_router.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .with_graceful_shutdown(admin_shutdown_signal())
+                .await
+                .unwrap();
+            }));
+        }
+        (None, Some(_)) => {
+            info!("Serving the admin API alongside the rest of the API.");
+            router = router.merge(create_admin_router(state.clone()));
+        }
+        (None, None) => {
+            warn!("The admin API is disabled; set adminEndpoint or adminToken to enable it.");
+        }
+    }
+
     let listener = tokio::net::TcpListener::bind(&addr.unwrap()).await.unwrap();
-    axum::serve(listener, create_router(state.clone()).into_make_service())
-        .with_graceful_shutdown(shutdown_signal(state))
-        .await
-        .unwrap();
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal(state))
+    .await
+    .unwrap();
+
+    // Let the admin server finish the requests that it's serving, too
+    if let Some(admin_server) = admin_server {
+        let _ = admin_server.await;
+    }
+
     ExitCode::SUCCESS
 }
 
+/// Reads the configuration file given as an argument.
+///
+/// # Parameters
+/// - `config_path`: The path to the configuration file, if one was given.
+///
+/// # Returns
+/// The configuration, or nothing if it couldn't be read (which is logged).
+fn read_config(config_path: Option<&String>) -> Option<ConfigScraper> {
+    let Some(config_path) = config_path else {
+        error!(
+            "Provide a path to the configuration JSON file as an argument, or use --standalone."
+        );
+        return None;
+    };
+
+    let config_path = Path::new(config_path.as_str());
+    if !config_path.exists() {
+        error!("Invalid path. Please provide the path to a configuration file.");
+        return None;
+    }
+
+    match serde_json::from_str::<ConfigScraper>(
+        fs::read_to_string(config_path)
+            .expect("Unable to read file.")
+            .as_str(),
+    ) {
+        Ok(config) => Some(config),
+        Err(err) => {
+            error!("Bad config file. Please fix it and then try again.\n{err}");
+            None
+        }
+    }
+}
+
+/// Handles shutting down the admin server. The main server's shutdown handler stops the
+/// scraper; this only stops accepting requests to the admin API.
+async fn admin_shutdown_signal() {
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Expected shutdown signal handler.");
+    info!("Stopping the admin server.");
+}
+
 /// Handles shutting down the server.
 ///
 /// # Parameters
@@ -97,6 +221,7 @@ async fn shutdown_signal(state: Arc<WrapperState>) {
     // Intercept ctrl_c event
     warn!("Invoked ctrl+c event, stopping the scraper and server.");
     state.set_stop_flag(true);
+    state.leader.step_down(&state.schedule_db);
     while state.is_running() {
         tokio::time::sleep(Duration::from_secs(1)).await;
     }
@@ -0,0 +1,50 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::notify::{check_status, truncate, Notification, Notifier, NotifyError};
+
+/// The longest title that a Discord embed can have.
+const MAX_EMBED_TITLE: usize = 256;
+/// The longest description that a Discord embed can have.
+const MAX_EMBED_DESCRIPTION: usize = 4096;
+
+/// Posts each notification as an embed through a Discord channel's webhook.
+pub struct DiscordNotifier {
+    client: reqwest::Client,
+    webhook_url: String,
+}
+
+impl DiscordNotifier {
+    /// Creates a notifier that posts through the given webhook.
+    pub fn new(client: reqwest::Client, webhook_url: &str) -> Self {
+        Self {
+            client,
+            webhook_url: webhook_url.to_owned(),
+        }
+    }
+}
+
+/// Formats a notification as the body of a Discord webhook request.
+pub(super) fn discord_body(notification: &Notification) -> Value {
+    json!({
+        "embeds": [{
+            "title": truncate(&notification.title, MAX_EMBED_TITLE),
+            "description": truncate(&notification.message, MAX_EMBED_DESCRIPTION),
+        }],
+        // Nothing in a notification should be able to ping anyone
+        "allowed_mentions": { "parse": [] },
+    })
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let resp = self
+            .client
+            .post(&self.webhook_url)
+            .json(&discord_body(notification))
+            .send()
+            .await?;
+        check_status(resp)
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::notify::{Notification, Notifier, NotifyError};
+use crate::types::{ConfigSmtp, SmtpTls};
+
+/// How long to wait to connect to, or hear back from, the SMTP server.
+const SMTP_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Emails each notification through an SMTP server.
+///
+/// The connection is secured with STARTTLS or implicit TLS, as configured, and the server's
+/// credentials are only ever sent over TLS. A server can be spoken to in plain SMTP, but only
+/// if it's a relay on this machine (e.g., a local Postfix instance that handles delivery).
+pub struct EmailNotifier {
+    smtp: ConfigSmtp,
+    to: Vec<String>,
+}
+
+impl EmailNotifier {
+    /// Creates a notifier that emails the given addresses.
+    pub fn new(smtp: ConfigSmtp, to: Vec<String>) -> Self {
+        Self { smtp, to }
+    }
+}
+
+/// Whether some text is a plain email address that can safely be put in a message's
+/// headers and an SMTP command.
+pub(super) fn is_address(address: &str) -> bool {
+    let Some((local, domain)) = address.split_once('@') else {
+        return false;
+    };
+
+    !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && address
+            .chars()
+            .all(|c| c.is_ascii_graphic() && !matches!(c, '<' | '>' | ',' | ';' | '"'))
+        && address.matches('@').count() == 1
+}
+
+/// Builds the message for a notification. Headers with anything other than ASCII in them
+/// (e.g., a course title in the subject) are encoded as RFC 2047 encoded words.
+pub(super) fn build_message(
+    from: &str,
+    to: &[String],
+    notification: &Notification,
+) -> Result<Message, NotifyError> {
+    let address = |a: &str| {
+        a.parse::<Mailbox>()
+            .map_err(|e| NotifyError::Smtp(format!("invalid address {a}: {e}")))
+    };
+
+    let mut builder = Message::builder()
+        .from(address(from)?)
+        .subject(notification.title.replace(['\r', '\n'], " "))
+        .header(ContentType::TEXT_PLAIN);
+    for to in to {
+        builder = builder.to(address(to)?);
+    }
+
+    let body = notification
+        .message
+        .lines()
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    builder
+        .body(body)
+        .map_err(|e| NotifyError::Smtp(e.to_string()))
+}
+
+/// Checks that a server can be spoken to in plain SMTP, which is only allowed for a relay on
+/// this machine.
+async fn check_local_relay(server: &str) -> Result<(), NotifyError> {
+    let mut addrs = tokio::net::lookup_host(server).await?.peekable();
+    if addrs.peek().is_none() || !addrs.all(|addr| addr.ip().is_loopback()) {
+        return Err(NotifyError::Smtp(format!(
+            "{server} isn't on this machine, so it needs TLS"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Connects to the configured server.
+async fn transport(smtp: &ConfigSmtp) -> Result<AsyncSmtpTransport<Tokio1Executor>, NotifyError> {
+    let (host, port) = smtp
+        .server
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?)))
+        .ok_or_else(|| NotifyError::Smtp(format!("{} isn't a host and port", smtp.server)))?;
+
+    let builder = match smtp.tls {
+        SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+        SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+        SmtpTls::None => {
+            check_local_relay(&smtp.server).await?;
+            if smtp.username.is_some() {
+                return Err(NotifyError::Smtp(
+                    "credentials are only sent over TLS".into(),
+                ));
+            }
+            Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                host,
+            ))
+        }
+    }
+    .map_err(|e| NotifyError::Smtp(e.to_string()))?
+    .port(port)
+    .timeout(Some(SMTP_TIMEOUT));
+
+    Ok(match (&smtp.username, &smtp.password) {
+        (Some(username), Some(password)) => builder
+            .credentials(Credentials::new(username.clone(), password.clone()))
+            .build(),
+        _ => builder.build(),
+    })
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let to: Vec<String> = self.to.iter().filter(|a| is_address(a)).cloned().collect();
+        if to.is_empty() {
+            return Err(NotifyError::Smtp("no valid recipients".into()));
+        }
+
+        let message = build_message(&self.smtp.from, &to, notification)?;
+        transport(&self.smtp)
+            .await?
+            .send(message)
+            .await
+            .map_err(|e| NotifyError::Smtp(e.to_string()))?;
+        Ok(())
+    }
+}
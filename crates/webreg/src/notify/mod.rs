@@ -0,0 +1,538 @@
+//! Delivering notifications (e.g., that a watch enrolled the student, or that a pass is
+//! about to open) to wherever the student or operator wants them.
+//!
+//! Each place that a notification can go is a [`NotifyChannel`], which is configured per
+//! alert: per hook (see [`crate::hooks`]), for pass time reminders (see
+//! [`crate::pass_times`]), and per watch (see [`crate::enroll_jobs`]). A channel is turned
+//! into a [`Notifier`] when a notification is sent, which formats the notification for its
+//! service: a plain webhook gets the event as JSON, Discord and Slack get a message through
+//! their incoming webhooks, email is sent through the configured SMTP server, and ntfy gets
+//! a push notification on a topic.
+
+mod discord;
+mod email;
+mod ntfy;
+mod slack;
+mod webhook;
+
+use async_trait::async_trait;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use tracing::warn;
+use url::{Host, Url};
+
+pub use discord::DiscordNotifier;
+pub use email::EmailNotifier;
+pub use ntfy::NtfyNotifier;
+pub use slack::SlackNotifier;
+pub use webhook::WebhookNotifier;
+
+use crate::types::{ConfigNotifications, WrapperState};
+
+/// The most channels that a single watch can notify.
+pub const MAX_WATCH_CHANNELS: usize = 5;
+/// The most addresses that a single email channel can send to.
+const MAX_EMAIL_RECIPIENTS: usize = 5;
+/// The hosts that Discord's webhooks are served from.
+const DISCORD_HOSTS: &[&str] = &["discord.com", "discordapp.com"];
+/// The host that Slack's incoming webhooks are served from.
+const SLACK_HOST: &str = "hooks.slack.com";
+/// The endings of hostnames that are only used on private networks.
+const INTERNAL_HOST_SUFFIXES: &[&str] =
+    &[".localhost", ".local", ".internal", ".lan", ".home.arpa"];
+
+/// Why a notification couldn't be sent.
+#[derive(Debug, Error)]
+pub enum NotifyError {
+    #[error("{0} notifications aren't configured")]
+    NotConfigured(&'static str),
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("the service responded with {0}")]
+    Status(reqwest::StatusCode),
+    #[error("SMTP error: {0}")]
+    Smtp(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Somewhere that a notification can be sent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum NotifyChannel {
+    /// Posts the notification's event to a URL, as JSON.
+    Webhook { url: String },
+    /// Posts a message through a Discord channel's webhook.
+    Discord { webhook_url: String },
+    /// Posts a message through a Slack app's incoming webhook.
+    Slack { webhook_url: String },
+    /// Emails the notification through the configured SMTP relay.
+    Email { to: Vec<String> },
+    /// Publishes the notification to an ntfy topic, on the configured server unless one
+    /// is given.
+    Ntfy {
+        topic: String,
+        #[serde(default)]
+        server: Option<String>,
+    },
+}
+
+impl NotifyChannel {
+    /// The kind of the channel, for logging.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NotifyChannel::Webhook { .. } => "webhook",
+            NotifyChannel::Discord { .. } => "discord",
+            NotifyChannel::Slack { .. } => "slack",
+            NotifyChannel::Email { .. } => "email",
+            NotifyChannel::Ntfy { .. } => "ntfy",
+        }
+    }
+
+    /// Checks a channel that a student gave (e.g., for a watch), which, unlike the channels
+    /// in the configuration file, can't be trusted to point somewhere sensible. URLs must
+    /// use HTTPS; Discord and Slack webhooks must be on those services' own hosts, and
+    /// other URLs mustn't point into the server's own network.
+    ///
+    /// # Returns
+    /// What's wrong with the channel, if anything.
+    pub fn check(&self) -> Option<String> {
+        let public = |url: &str| https_url(url).is_some_and(|u| is_public_host(&u));
+        let on_host = |url: &str, hosts: &[&str]| {
+            https_url(url).is_some_and(|u| u.host_str().is_some_and(|h| hosts.contains(&h)))
+        };
+
+        match self {
+            NotifyChannel::Webhook { url } => {
+                (!public(url)).then(|| "must have a public HTTPS URL".to_owned())
+            }
+            NotifyChannel::Discord { webhook_url } => (!on_host(webhook_url, DISCORD_HOSTS))
+                .then(|| "must have an HTTPS URL on discord.com".to_owned()),
+            NotifyChannel::Slack { webhook_url } => (!on_host(webhook_url, &[SLACK_HOST]))
+                .then(|| format!("must have an HTTPS URL on {SLACK_HOST}")),
+            NotifyChannel::Email { to } => {
+                if to.is_empty() || to.len() > MAX_EMAIL_RECIPIENTS {
+                    Some(format!(
+                        "must have between 1 and {MAX_EMAIL_RECIPIENTS} addresses"
+                    ))
+                } else if !to.iter().all(|address| email::is_address(address)) {
+                    Some("must only have email addresses".to_owned())
+                } else {
+                    None
+                }
+            }
+            NotifyChannel::Ntfy { topic, server } => {
+                if !ntfy::is_topic(topic) {
+                    Some("must have a topic of up to 64 letters, digits, - or _".to_owned())
+                } else if server.as_deref().is_some_and(|s| !public(s)) {
+                    Some("must have a public HTTPS server".to_owned())
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Parses a URL, if it uses HTTPS and has a host.
+fn https_url(url: &str) -> Option<Url> {
+    Url::parse(url)
+        .ok()
+        .filter(|u| u.scheme() == "https" && u.host().is_some())
+}
+
+/// Whether a URL's host is on the public internet, rather than the server's own network
+/// (e.g., `localhost`, `10.0.0.1`, or `metadata.google.internal`), where a student's
+/// webhook could reach services that were never meant to be reachable from outside.
+fn is_public_host(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Ipv4(ip)) => is_public_ipv4(ip),
+        Some(Host::Ipv6(ip)) => is_public_ipv6(ip),
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            // Names without a dot (e.g., `localhost`, or `redis`) are resolved on the local
+            // network, if at all
+            domain.contains('.')
+                && !INTERNAL_HOST_SUFFIXES
+                    .iter()
+                    .any(|suffix| domain.ends_with(suffix))
+        }
+        None => false,
+    }
+}
+
+/// Whether an address is on the public internet.
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => is_public_ipv6(ip),
+    }
+}
+
+/// Whether an IPv4 address is on the public internet.
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || first == 0
+        // Shared address space, used by carrier-grade NAT (100.64.0.0/10)
+        || (first == 100 && (64..128).contains(&second)))
+}
+
+/// Whether an IPv6 address is on the public internet.
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    if let Some(ip) = ip.to_ipv4_mapped() {
+        return is_public_ipv4(ip);
+    }
+
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local (fc00::/7) and link-local (fe80::/10) addresses
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80)
+}
+
+/// Something to tell a student or operator about.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    /// A one-line summary (e.g., `Enrolled in CSE 100`).
+    pub title: String,
+    /// The details, in plain text.
+    pub message: String,
+    /// The event as JSON, which is what plain webhooks are sent.
+    pub payload: Value,
+}
+
+/// Something that can deliver notifications.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Sends a notification.
+    async fn send(&self, notification: &Notification) -> Result<(), NotifyError>;
+}
+
+/// Resolves hostnames for the student notification client (see [`student_client`]),
+/// leaving out any addresses that aren't on the public internet. A hostname that passed
+/// [`NotifyChannel::check`] can still resolve into the server's own network (e.g.,
+/// `127.0.0.1.nip.io`, or a name that's rebound after it was checked), so what it resolves
+/// to is checked every time a connection is made.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<_> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(
+                    format!("{} doesn't resolve to a public address", name.as_str()).into(),
+                );
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Creates the HTTP client that notifications from the configuration file (e.g., hooks)
+/// are sent with. It doesn't follow redirects.
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Failed to create the notification client")
+}
+
+/// Creates the HTTP client that notifications are sent to students' own channels with. It
+/// doesn't follow redirects, since a URL that passed [`NotifyChannel::check`] could
+/// otherwise redirect somewhere that wouldn't have, and it only connects to hostnames'
+/// public addresses. (Hosts that are IP addresses were already checked, and can't change.)
+pub fn student_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+        .expect("Failed to create the student notification client")
+}
+
+/// Creates the notifier for a channel.
+///
+/// # Parameters
+/// - `channel`: The channel.
+/// - `client`: The HTTP client to send notifications with.
+/// - `config`: The notification settings (e.g., the SMTP relay).
+///
+/// # Returns
+/// The notifier, or an error if the channel needs settings that aren't configured.
+pub fn notifier(
+    channel: &NotifyChannel,
+    client: &reqwest::Client,
+    config: &ConfigNotifications,
+) -> Result<Box<dyn Notifier>, NotifyError> {
+    Ok(match channel {
+        NotifyChannel::Webhook { url } => Box::new(WebhookNotifier::new(client.clone(), url)),
+        NotifyChannel::Discord { webhook_url } => {
+            Box::new(DiscordNotifier::new(client.clone(), webhook_url))
+        }
+        NotifyChannel::Slack { webhook_url } => {
+            Box::new(SlackNotifier::new(client.clone(), webhook_url))
+        }
+        NotifyChannel::Email { to } => {
+            let smtp = config
+                .smtp
+                .as_ref()
+                .ok_or(NotifyError::NotConfigured("email"))?;
+            Box::new(EmailNotifier::new(smtp.clone(), to.clone()))
+        }
+        NotifyChannel::Ntfy { topic, server } => {
+            let server = server
+                .as_deref()
+                .or(config.ntfy_server.as_deref())
+                .unwrap_or(ntfy::DEFAULT_NTFY_SERVER);
+            Box::new(NtfyNotifier::new(client.clone(), server, topic))
+        }
+    })
+}
+
+/// Sends a notification to each of the given channels. A channel that the notification
+/// can't be sent to is logged, and doesn't stop the others.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `client`: The HTTP client to send notifications with, which is
+///   [`WrapperState::student_notify_client`] for channels that a student gave.
+/// - `channels`: The channels.
+/// - `notification`: The notification.
+///
+/// # Returns
+/// The number of channels that the notification was sent to.
+pub async fn notify_all(
+    state: &WrapperState,
+    client: &reqwest::Client,
+    channels: &[NotifyChannel],
+    notification: &Notification,
+) -> usize {
+    let mut sent = 0;
+    for channel in channels {
+        let result = match notifier(channel, client, &state.notifications) {
+            Ok(notifier) => notifier.send(notification).await,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => sent += 1,
+            Err(e) => warn!(
+                "Failed to send '{}' to a {} channel: {e}",
+                notification.title,
+                channel.kind()
+            ),
+        }
+    }
+    sent
+}
+
+/// Checks the status of a service's response to a notification.
+fn check_status(resp: reqwest::Response) -> Result<(), NotifyError> {
+    if resp.status().is_success() {
+        Ok(())
+    } else {
+        Err(NotifyError::Status(resp.status()))
+    }
+}
+
+/// Shortens text to at most the given number of characters, ending it with an ellipsis if
+/// it was cut, for services that limit how long a message can be.
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_owned();
+    }
+
+    let mut cut: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ConfigSmtp, SmtpTls};
+
+    #[test]
+    fn test_channel_config() {
+        let channels: Vec<NotifyChannel> = serde_json::from_str(
+            r#"[
+                {"kind": "discord", "webhookUrl": "https://discord.com/api/webhooks/1/a"},
+                {"kind": "ntfy", "topic": "cse100-seats"},
+                {"kind": "email", "to": ["student@ucsd.edu"]}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            NotifyChannel::Ntfy {
+                topic: "cse100-seats".to_owned(),
+                server: None,
+            },
+            channels[1]
+        );
+        assert!(channels.iter().all(|c| c.check().is_none()));
+
+        let insecure = NotifyChannel::Webhook {
+            url: "http://example.com/hook".to_owned(),
+        };
+        assert!(insecure.check().is_some());
+        for url in [
+            "https://10.0.0.1/hook",
+            "https://127.1/hook",
+            "https://169.254.169.254/latest/meta-data",
+            "https://[::1]/hook",
+            "https://[::ffff:192.168.0.1]/hook",
+            "https://[fd00::1]/hook",
+            "https://localhost/hook",
+            "https://redis/hook",
+            "https://metadata.google.internal/hook",
+        ] {
+            let internal = NotifyChannel::Webhook {
+                url: url.to_owned(),
+            };
+            assert!(internal.check().is_some(), "{url}");
+        }
+        let public = NotifyChannel::Webhook {
+            url: "https://hooks.example.com/notify".to_owned(),
+        };
+        assert!(public.check().is_none());
+
+        let fake_discord = NotifyChannel::Discord {
+            webhook_url: "https://discord.com.example.com/api/webhooks/1/a".to_owned(),
+        };
+        assert!(fake_discord.check().is_some());
+        let slack = NotifyChannel::Slack {
+            webhook_url: "https://hooks.slack.com/services/T0/B0/x".to_owned(),
+        };
+        assert!(slack.check().is_none());
+        let fake_slack = NotifyChannel::Slack {
+            webhook_url: "https://10.0.0.1/services/T0/B0/x".to_owned(),
+        };
+        assert!(fake_slack.check().is_some());
+        let internal_ntfy = NotifyChannel::Ntfy {
+            topic: "cse100-seats".to_owned(),
+            server: Some("https://192.168.1.10".to_owned()),
+        };
+        assert!(internal_ntfy.check().is_some());
+        let bad_topic = NotifyChannel::Ntfy {
+            topic: "../admin".to_owned(),
+            server: None,
+        };
+        assert!(bad_topic.check().is_some());
+        let bad_address = NotifyChannel::Email {
+            to: vec!["student@ucsd.edu\r\nBcc: someone@example.com".to_owned()],
+        };
+        assert!(bad_address.check().is_some());
+
+        assert_eq!("abc", truncate("abc", 3));
+        assert_eq!("ab…", truncate("abcd", 3));
+    }
+
+    #[test]
+    fn test_formatting() {
+        let notification = Notification {
+            title: "Enrolled in CSE 100\r\nBcc: someone@example.com".to_owned(),
+            message: "Section 123456.\n.\nDone <@everyone>".to_owned(),
+            payload: Value::Null,
+        };
+
+        let to = ["student@ucsd.edu".to_owned()];
+        let message = email::build_message("webreg@example.com", &to, &notification).unwrap();
+        let message = String::from_utf8(message.formatted()).unwrap();
+        assert!(message.contains("Subject: Enrolled in CSE 100  Bcc: someone@example.com\r\n"));
+        assert!(!message.contains("\r\nBcc:"));
+        assert!(message.ends_with("Section 123456.\r\n.\r\nDone <@everyone>"));
+
+        // A subject that isn't ASCII is encoded
+        let accented = Notification {
+            title: "Inscrit à CSE 100".to_owned(),
+            ..notification.clone()
+        };
+        let message = email::build_message("webreg@example.com", &to, &accented).unwrap();
+        let message = String::from_utf8(message.formatted()).unwrap();
+        assert!(message.contains("Subject: Inscrit =?utf-8?b?w6A=?= CSE 100\r\n"));
+
+        let slack = slack::slack_body(&notification);
+        assert!(slack["text"]
+            .as_str()
+            .unwrap()
+            .ends_with("Done &lt;@everyone&gt;"));
+        let discord = discord::discord_body(&notification);
+        assert_eq!(
+            notification.message,
+            discord["embeds"][0]["description"].as_str().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plain_smtp_is_local_only() {
+        let notification = Notification {
+            title: "Enrolled".to_owned(),
+            message: "Section 123456.".to_owned(),
+            payload: Value::Null,
+        };
+        // A relay elsewhere, and credentials without TLS, are refused before connecting
+        for (server, username, error) in [
+            ("203.0.113.1:25", None, "needs TLS"),
+            ("127.0.0.1:25", Some("webreg"), "only sent over TLS"),
+        ] {
+            let smtp = ConfigSmtp {
+                server: server.to_owned(),
+                from: "webreg@example.com".to_owned(),
+                tls: SmtpTls::None,
+                username: username.map(str::to_owned),
+                password: username.map(str::to_owned),
+            };
+            let err = EmailNotifier::new(smtp, vec!["student@ucsd.edu".to_owned()])
+                .send(&notification)
+                .await
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains(error), "{err}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_private_resolution_is_refused() {
+        let resolved = PublicResolver.resolve("localhost".parse().unwrap()).await;
+        assert!(resolved.is_err());
+
+        // The name would pass a check of the URL alone, but the connection is never made
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let res = student_client()
+            .get(format!("http://localhost:{port}/"))
+            .send()
+            .await;
+        assert!(res.is_err());
+        // The trusted client still reaches it
+        let accept = tokio::spawn(async move { listener.accept().await.is_ok() });
+        let _ = client()
+            .get(format!("http://localhost:{port}/"))
+            .timeout(std::time::Duration::from_millis(200))
+            .send()
+            .await;
+        assert!(accept.await.unwrap());
+    }
+}
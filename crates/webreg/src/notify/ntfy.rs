@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+use reqwest::header::HeaderValue;
+
+use crate::notify::{check_status, Notification, Notifier, NotifyError};
+
+/// The ntfy server that notifications are published to if no other is configured.
+pub const DEFAULT_NTFY_SERVER: &str = "https://ntfy.sh";
+/// The longest topic name that ntfy allows.
+const MAX_TOPIC_LEN: usize = 64;
+
+/// Publishes each notification to an ntfy topic.
+pub struct NtfyNotifier {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl NtfyNotifier {
+    /// Creates a notifier that publishes to a topic on the given server.
+    pub fn new(client: reqwest::Client, server: &str, topic: &str) -> Self {
+        Self {
+            client,
+            url: format!("{}/{topic}", server.trim_end_matches('/')),
+        }
+    }
+}
+
+/// Whether a topic name is one that ntfy accepts.
+pub(super) fn is_topic(topic: &str) -> bool {
+    !topic.is_empty()
+        && topic.len() <= MAX_TOPIC_LEN
+        && topic
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[async_trait]
+impl Notifier for NtfyNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let req = self.client.post(&self.url);
+        // Titles that can't be sent as a header (e.g., with non-ASCII characters) are
+        // put at the start of the message instead
+        let req = match HeaderValue::from_str(&notification.title) {
+            Ok(title) if notification.title.is_ascii() => req
+                .header("Title", title)
+                .body(notification.message.clone()),
+            _ => req.body(format!("{}\n{}", notification.title, notification.message)),
+        };
+
+        check_status(req.send().await?)
+    }
+}
@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::notify::{check_status, Notification, Notifier, NotifyError};
+
+/// Posts each notification as a message through a Slack app's incoming webhook.
+pub struct SlackNotifier {
+    client: reqwest::Client,
+    webhook_url: String,
+}
+
+impl SlackNotifier {
+    /// Creates a notifier that posts through the given webhook.
+    pub fn new(client: reqwest::Client, webhook_url: &str) -> Self {
+        Self {
+            client,
+            webhook_url: webhook_url.to_owned(),
+        }
+    }
+}
+
+/// Escapes the characters that Slack treats as markup.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Formats a notification as the body of a Slack webhook request.
+pub(super) fn slack_body(notification: &Notification) -> Value {
+    json!({
+        "text": format!(
+            "*{}*\n{}",
+            escape(&notification.title),
+            escape(&notification.message)
+        ),
+    })
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let resp = self
+            .client
+            .post(&self.webhook_url)
+            .json(&slack_body(notification))
+            .send()
+            .await?;
+        check_status(resp)
+    }
+}
@@ -0,0 +1,32 @@
+use async_trait::async_trait;
+
+use crate::notify::{check_status, Notification, Notifier, NotifyError};
+
+/// Posts each notification's event, as JSON, to a URL.
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    /// Creates a notifier that posts to the given URL.
+    pub fn new(client: reqwest::Client, url: &str) -> Self {
+        Self {
+            client,
+            url: url.to_owned(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let resp = self
+            .client
+            .post(&self.url)
+            .json(&notification.payload)
+            .send()
+            .await?;
+        check_status(resp)
+    }
+}
@@ -0,0 +1,190 @@
+//! How often courses are offered, judging by the terms they've been scraped in.
+//!
+//! Each time a term's schedule data is scraped, the courses offered in it are recorded,
+//! with their section and seat counts, in a table that outlives the term's own data. From
+//! that, a course's offering pattern can be worked out: a course that has appeared in the
+//! scraped fall quarters, but in none of the scraped winter or spring ones, is only offered
+//! in the fall. The graduation planner (see [`crate::degree_audit::planner`]) places courses
+//! in the quarters that they're offered in, and warns about the ones that are only offered
+//! once a year, since putting them off delays them by a year.
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Serialize;
+
+use crate::db::CourseOffering;
+
+/// The regular quarters' seasons, in the order of the academic year.
+pub const REGULAR_SEASONS: [&str; 3] = ["FA", "WI", "SP"];
+
+/// The season of a term (e.g., `FA` for `FA24`). All summer sessions are grouped together
+/// as `SU`.
+pub fn season(term: &str) -> &str {
+    match term.get(..2).unwrap_or("") {
+        "S1" | "S2" | "S3" | "SU" => "SU",
+        s => s,
+    }
+}
+
+/// The name of a season (e.g., `fall` for `FA`).
+pub fn season_name(season: &str) -> &'static str {
+    match season {
+        "FA" => "fall",
+        "WI" => "winter",
+        "SP" => "spring",
+        "SU" => "summer",
+        _ => "other",
+    }
+}
+
+/// A key that sorts term codes chronologically (e.g., `WI25` after `FA24`).
+pub fn term_sort_key(term: &str) -> (u32, u8) {
+    let year = term.get(2..4).and_then(|y| y.parse().ok()).unwrap_or(0);
+    let season = match term.get(..2) {
+        Some("WI") => 0,
+        Some("SP") => 1,
+        Some("S1" | "S2" | "S3" | "SU") => 2,
+        _ => 3,
+    };
+    (year, season)
+}
+
+/// Finds the one regular season that a course is offered in, if it's only offered in one.
+///
+/// # Parameters
+/// - `offered`: The terms that the course was offered in.
+/// - `scraped`: Every term that's been scraped. A course is only judged to be offered in
+///   one season if another regular season has been scraped and it wasn't offered then.
+///
+/// # Returns
+/// The season (e.g., `FA`), or nothing if the course is offered in more than one regular
+/// season, in none, or if no other regular season has been scraped.
+pub fn only_season<'a, 'b>(
+    offered: impl IntoIterator<Item = &'a str>,
+    scraped: impl IntoIterator<Item = &'b str>,
+) -> Option<&'static str> {
+    let offered: HashSet<_> = offered.into_iter().map(season).collect();
+    let mut regular = REGULAR_SEASONS.iter().filter(|s| offered.contains(**s));
+    let only = *regular.next()?;
+    if regular.next().is_some() {
+        return None;
+    }
+
+    scraped
+        .into_iter()
+        .map(season)
+        .any(|s| s != only && REGULAR_SEASONS.contains(&s))
+        .then_some(only)
+}
+
+/// How many terms of a season have been scraped, and how many of them offered a course.
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+pub struct SeasonFrequency {
+    pub offered: usize,
+    pub scraped: usize,
+}
+
+/// A course's offering history.
+#[derive(Debug, Clone, Serialize)]
+pub struct OfferingHistory {
+    pub course: String,
+    /// The terms that the course was offered in, oldest first.
+    pub terms: Vec<CourseOffering>,
+    /// How often the course was offered in each season, keyed by the season's name.
+    pub seasons: BTreeMap<&'static str, SeasonFrequency>,
+    /// The name of the one regular season that the course is offered in, if it's only
+    /// offered in one (see [`only_season`]).
+    pub only_offered_in: Option<&'static str>,
+}
+
+impl OfferingHistory {
+    /// Builds a course's offering history.
+    ///
+    /// # Parameters
+    /// - `course`: The course's code.
+    /// - `terms`: The terms that the course was offered in.
+    /// - `scraped`: Every term that's been scraped.
+    pub fn new(course: String, mut terms: Vec<CourseOffering>, scraped: &HashSet<String>) -> Self {
+        terms.sort_by_key(|t| term_sort_key(&t.term));
+
+        let mut seasons: BTreeMap<&'static str, SeasonFrequency> = BTreeMap::new();
+        for term in scraped {
+            seasons
+                .entry(season_name(season(term)))
+                .or_default()
+                .scraped += 1;
+        }
+        for term in &terms {
+            seasons
+                .entry(season_name(season(&term.term)))
+                .or_default()
+                .offered += 1;
+        }
+
+        let only_offered_in = only_season(
+            terms.iter().map(|t| t.term.as_str()),
+            scraped.iter().map(String::as_str),
+        )
+        .map(season_name);
+
+        Self {
+            course,
+            terms,
+            seasons,
+            only_offered_in,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offering(term: &str) -> CourseOffering {
+        CourseOffering {
+            term: term.to_owned(),
+            section_count: 2,
+            total_seats: 100,
+            enrolled_ct: 90,
+        }
+    }
+
+    #[test]
+    fn test_offering_history() {
+        assert!(term_sort_key("FA24") < term_sort_key("WI25"));
+        assert!(term_sort_key("S124") < term_sort_key("FA24"));
+
+        let scraped: HashSet<String> = ["FA23", "WI24", "SP24", "S124", "FA24"]
+            .map(String::from)
+            .into();
+        let history = OfferingHistory::new(
+            "CSE 110".to_owned(),
+            vec![offering("FA24"), offering("S124"), offering("FA23")],
+            &scraped,
+        );
+        assert_eq!(
+            vec!["FA23", "S124", "FA24"],
+            history
+                .terms
+                .iter()
+                .map(|t| t.term.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some("fall"), history.only_offered_in);
+        assert_eq!(
+            SeasonFrequency {
+                offered: 2,
+                scraped: 2
+            },
+            history.seasons["fall"]
+        );
+        assert_eq!(0, history.seasons["winter"].offered);
+
+        // Nothing is known about the other seasons yet
+        assert_eq!(None, only_season(["FA24"], ["FA23", "FA24", "S124"]));
+        assert_eq!(
+            None,
+            only_season(["FA24", "SP25"], ["FA24", "WI25", "SP25"])
+        );
+    }
+}
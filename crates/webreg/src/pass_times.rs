@@ -0,0 +1,279 @@
+//! Students' enrollment appointments (passes), and reminders before they open.
+//!
+//! Each term, a student gets a first pass, when they can enroll in a limited number of
+//! units, and a second pass, when they can enroll in the rest. The times are fetched from
+//! WebReg with the student's cookies (see `/live/:term/sync_my_passtimes`) and stored with
+//! the prefix of their API key. If reminders are configured, the scheduler sends one to each
+//! configured webhook and notification channel the given number of hours before each pass
+//! opens.
+
+use std::time::Duration;
+
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use reqwest::header::COOKIE;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::info;
+
+use crate::db::{PassTime, PASS_FIRST, PASS_SECOND};
+use crate::error::WebregError;
+use crate::notify::{notify_all, Notification, NotifyChannel};
+use crate::types::WrapperState;
+
+/// Where WebReg serves a student's enrollment appointments for a term.
+const PASS_TIME_URL: &str = "https://act.ucsd.edu/webreg2/svc/wradapter/secure/get-enroll-appt";
+/// How long before a pass opens to remind the student, if not configured.
+const DEFAULT_REMINDER_HOURS: u64 = 24;
+/// How often passes are checked for reminders that are due, if not configured.
+pub const DEFAULT_PASS_TIME_REMINDER_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// The format that pass times are stored in.
+const PASS_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// A reminder that one of a student's passes is about to open.
+#[derive(Debug, Clone, Serialize)]
+pub struct PassTimeReminder {
+    /// The student, by the prefix of their API key.
+    pub key_prefix: String,
+    pub term: String,
+    /// `first` or `second`.
+    pub pass: String,
+    /// When the pass opens, in local time.
+    pub starts_at: String,
+    /// How many hours before the pass opens that the reminder is for.
+    pub hours_before: u64,
+}
+
+/// Parses the enrollment appointments that WebReg returns for a term. WebReg lists them in
+/// the order of the passes, each with the date that it opens on in `START_DATE`
+/// (`YYYY-MM-DD`) and the time in `START_TIME` (`HHMM` or `HH:MM`, 24-hour). Passes that
+/// haven't been scheduled yet have no date, and are left out.
+///
+/// # Parameters
+/// - `term`: The term that the appointments are for.
+/// - `body`: The response's body.
+///
+/// # Returns
+/// The passes, or `None` if the response isn't a list of appointments.
+pub fn parse_pass_times(term: &str, body: &str) -> Option<Vec<PassTime>> {
+    let Value::Array(appointments) = serde_json::from_str(body).ok()? else {
+        return None;
+    };
+
+    let passes = appointments
+        .iter()
+        .zip([PASS_FIRST, PASS_SECOND])
+        .filter_map(|(appointment, pass)| {
+            let field = |name| appointment.get(name)?.as_str().map(str::trim);
+            let date = NaiveDate::parse_from_str(field("START_DATE")?, "%Y-%m-%d").ok()?;
+            let time = field("START_TIME")?.replace(':', "");
+            let time = NaiveTime::parse_from_str(&time, "%H%M").ok()?;
+            Some(PassTime {
+                key_prefix: String::new(),
+                term: term.to_owned(),
+                pass: pass.to_owned(),
+                starts_at: date.and_time(time).format(PASS_TIME_FORMAT).to_string(),
+                reminded_hours: None,
+                synced_at: None,
+            })
+        })
+        .collect();
+    Some(passes)
+}
+
+/// Finds the reminder that's due for a pass, if any. Only the latest reminder that's due is
+/// sent, so reminders that were missed (e.g., while the API was down, or because the pass was
+/// fetched late) are skipped rather than sent all at once.
+///
+/// # Parameters
+/// - `starts_at`: When the pass opens.
+/// - `now`: The current time.
+/// - `hours_before`: How many hours before a pass opens to send reminders at.
+/// - `reminded_hours`: The fewest hours before the pass that a reminder has been sent at,
+///   if any.
+///
+/// # Returns
+/// How many hours before the pass that the due reminder is for.
+pub fn due_reminder(
+    starts_at: NaiveDateTime,
+    now: NaiveDateTime,
+    hours_before: &[u64],
+    reminded_hours: Option<u64>,
+) -> Option<u64> {
+    if now >= starts_at {
+        return None;
+    }
+
+    hours_before
+        .iter()
+        .copied()
+        .filter(|&hours| reminded_hours.is_none_or(|reminded| hours < reminded))
+        .filter(|&hours| now >= starts_at - TimeDelta::hours(hours as i64))
+        .min()
+}
+
+/// Fetches a student's passes for a term from WebReg.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `term`: The term.
+/// - `cookies`: The student's WebReg cookies.
+///
+/// # Returns
+/// The passes, in order.
+pub async fn fetch_pass_times(
+    state: &WrapperState,
+    term: &str,
+    cookies: &str,
+) -> Result<Vec<PassTime>, WebregError> {
+    let body = state
+        .client
+        .get(PASS_TIME_URL)
+        .query(&[("termcode", term)])
+        .header(COOKIE, cookies)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())?
+        .text()
+        .await?;
+
+    parse_pass_times(term, &body).ok_or_else(|| WebregError::Upstream {
+        message: "WebReg didn't return enrollment appointments.".into(),
+        context: Some(body.chars().take(200).collect()),
+        code: None,
+    })
+}
+
+/// Sends the reminders that are due for every student's upcoming passes. This does nothing
+/// if no reminders are configured.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+///
+/// # Returns
+/// The number of reminders sent.
+pub async fn send_pass_time_reminders(state: &WrapperState) -> rusqlite::Result<usize> {
+    let Some(config) = &state.pass_time_reminders else {
+        return Ok(0);
+    };
+    let hours_before = if config.hours_before.is_empty() {
+        &[DEFAULT_REMINDER_HOURS][..]
+    } else {
+        &config.hours_before[..]
+    };
+
+    let channels: Vec<NotifyChannel> = config
+        .webhook_urls
+        .iter()
+        .map(|url| NotifyChannel::Webhook { url: url.clone() })
+        .chain(config.channels.iter().cloned())
+        .collect();
+
+    let now = Local::now().naive_local();
+    let passes = state
+        .schedule_db
+        .get_upcoming_pass_times(&now.format(PASS_TIME_FORMAT).to_string())?;
+
+    let mut sent = 0;
+    for pass in passes {
+        let Ok(starts_at) = NaiveDateTime::parse_from_str(&pass.starts_at, PASS_TIME_FORMAT) else {
+            continue;
+        };
+        let Some(hours) = due_reminder(starts_at, now, hours_before, pass.reminded_hours) else {
+            continue;
+        };
+
+        let reminder = PassTimeReminder {
+            key_prefix: pass.key_prefix.clone(),
+            term: pass.term.clone(),
+            pass: pass.pass.clone(),
+            starts_at: pass.starts_at.clone(),
+            hours_before: hours,
+        };
+        let notification = Notification {
+            title: format!("Your {} pass opens in {hours} hour(s)", pass.pass),
+            message: format!(
+                "Your {} pass for {} opens at {}.",
+                pass.pass, pass.term, pass.starts_at
+            ),
+            payload: serde_json::to_value(&reminder).unwrap(),
+        };
+        notify_all(state, &state.notify_client, &channels, &notification).await;
+
+        // Recorded even if a channel failed, so that it isn't sent again every round
+        state.schedule_db.set_pass_time_reminded(
+            &pass.key_prefix,
+            &pass.term,
+            &pass.pass,
+            hours,
+        )?;
+        info!(
+            "[{}] Sent a reminder {hours} hour(s) before a {} pass.",
+            pass.term, pass.pass
+        );
+        sent += 1;
+    }
+
+    Ok(sent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, PASS_TIME_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn test_parse_pass_times() {
+        let body = r#"[
+            {"START_DATE": "2024-05-20", "START_TIME": "0830"},
+            {"START_DATE": "2024-05-28", "START_TIME": "14:00"}
+        ]"#;
+        let passes = parse_pass_times("FA24", body).unwrap();
+        let summary: Vec<_> = passes
+            .iter()
+            .map(|p| (p.pass.as_str(), p.starts_at.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                (PASS_FIRST, "2024-05-20 08:30:00"),
+                (PASS_SECOND, "2024-05-28 14:00:00"),
+            ],
+            summary
+        );
+
+        // The second pass hasn't been scheduled yet
+        let body = r#"[{"START_DATE": "2024-05-20", "START_TIME": "0830"}, {"START_DATE": ""}]"#;
+        assert_eq!(1, parse_pass_times("FA24", body).unwrap().len());
+        assert!(parse_pass_times("FA24", "<html></html>").is_none());
+    }
+
+    #[test]
+    fn test_due_reminder() {
+        let starts_at = time("2024-05-20 08:30:00");
+        let hours = [24, 1];
+
+        assert_eq!(
+            None,
+            due_reminder(starts_at, time("2024-05-19 08:00:00"), &hours, None)
+        );
+        assert_eq!(
+            Some(24),
+            due_reminder(starts_at, time("2024-05-19 09:00:00"), &hours, None)
+        );
+        assert_eq!(
+            None,
+            due_reminder(starts_at, time("2024-05-19 09:00:00"), &hours, Some(24))
+        );
+        // A missed reminder is skipped in favor of the latest one
+        assert_eq!(
+            Some(1),
+            due_reminder(starts_at, time("2024-05-20 08:00:00"), &hours, None)
+        );
+        assert_eq!(
+            None,
+            due_reminder(starts_at, time("2024-05-20 08:30:00"), &hours, None)
+        );
+    }
+}
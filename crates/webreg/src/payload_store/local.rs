@@ -0,0 +1,135 @@
+//! A payload store backed by a local directory.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+
+use super::{check_key, PayloadStore, PayloadStoreError, StoredPayload};
+
+/// A payload store that keeps each payload as a file under a directory, at its key's path.
+pub struct LocalPayloadStore {
+    root: PathBuf,
+}
+
+impl LocalPayloadStore {
+    /// Creates a store under a directory, which is created when the first payload is stored.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Gets the path of a payload's file.
+    fn path(&self, key: &str) -> Result<PathBuf, PayloadStoreError> {
+        check_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+/// Runs file system operations off of the async runtime's threads.
+async fn blocking<T, F>(f: F) -> Result<T, PayloadStoreError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, PayloadStoreError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| PayloadStoreError::Io(std::io::Error::other(e)))?
+}
+
+/// Adds the files under a directory, whose keys start with a prefix, to a list.
+fn walk(
+    root: &Path,
+    dir: &Path,
+    prefix: &str,
+    payloads: &mut Vec<StoredPayload>,
+) -> std::io::Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            walk(root, &path, prefix, payloads)?;
+            continue;
+        }
+
+        // Partially written files aren't payloads yet
+        if path.extension().is_some_and(|e| e == "tmp") {
+            continue;
+        }
+
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let key = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if key.starts_with(prefix) {
+            payloads.push(StoredPayload {
+                key,
+                size: metadata.len(),
+                modified: DateTime::<Utc>::from(metadata.modified()?),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+impl PayloadStore for LocalPayloadStore {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), PayloadStoreError> {
+        let path = self.path(key)?;
+        blocking(move || {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            // Written to a temporary file first, so that a payload is never half-written
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, data)?;
+            fs::rename(&tmp, &path)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, PayloadStoreError> {
+        let path = self.path(key)?;
+        blocking(move || match fs::read(path) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        })
+        .await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), PayloadStoreError> {
+        let path = self.path(key)?;
+        blocking(move || match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        })
+        .await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredPayload>, PayloadStoreError> {
+        let (root, prefix) = (self.root.clone(), prefix.to_owned());
+        blocking(move || {
+            let mut payloads = vec![];
+            walk(&root, &root, &prefix, &mut payloads)?;
+            payloads.sort_by(|a, b| a.key.cmp(&b.key));
+            Ok(payloads)
+        })
+        .await
+    }
+}
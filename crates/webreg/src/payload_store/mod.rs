@@ -0,0 +1,285 @@
+//! Archival storage for the raw payloads that upstream systems return, kept outside of the
+//! SQLite databases.
+//!
+//! When a parser misreads something, the payload that it was given is the first thing
+//! needed to debug it, but keeping every WebReg page and DARS audit in the schedule
+//! database would make it grow without bound. With `payloadStore` set in the configuration
+//! file, the raw WebReg JSON of each course that a scrape stores, and the raw HTML of each
+//! archived degree audit (see [`crate::degree_audit::archive`]), is written to a local
+//! directory or an S3-compatible bucket instead. Payloads are keyed so that they can be
+//! found from what refers to them: a scrape's under `webreg/<term>/job-<id>/`, and an
+//! audit snapshot's by its content hash under `dars/`. Each kind of payload is kept for its
+//! own retention period, after which it's removed by the `cache_cleanup` job.
+
+mod local;
+mod s3;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+use tracing::warn;
+
+pub use local::LocalPayloadStore;
+pub use s3::S3PayloadStore;
+
+use crate::types::{ConfigPayloadStore, PayloadBackend};
+
+/// The default number of days that WebReg payloads are kept for.
+pub const DEFAULT_WEBREG_RETENTION_DAYS: u64 = 14;
+/// The default number of days that DARS payloads are kept for.
+pub const DEFAULT_DARS_RETENTION_DAYS: u64 = 90;
+
+/// Why a payload couldn't be stored, read, or removed.
+#[derive(Debug, Error)]
+pub enum PayloadStoreError {
+    #[error("invalid payload key `{0}`")]
+    InvalidKey(String),
+    #[error("invalid payload store configuration: {0}")]
+    Config(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("request to the object store failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("the object store responded with {status}: {body}")]
+    Status {
+        status: reqwest::StatusCode,
+        body: String,
+    },
+}
+
+/// A payload in the store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredPayload {
+    pub key: String,
+    /// The payload's size, in bytes.
+    pub size: u64,
+    /// When the payload was stored.
+    pub modified: DateTime<Utc>,
+}
+
+/// Somewhere that payloads can be kept, keyed by `/`-separated paths.
+#[async_trait]
+pub trait PayloadStore: Send + Sync {
+    /// Stores a payload, replacing the one with the same key, if any.
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), PayloadStoreError>;
+
+    /// Gets a payload, or nothing if there's no payload with the key.
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, PayloadStoreError>;
+
+    /// Removes a payload. Removing a payload that doesn't exist isn't an error.
+    async fn delete(&self, key: &str) -> Result<(), PayloadStoreError>;
+
+    /// Lists the payloads whose keys start with a prefix.
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredPayload>, PayloadStoreError>;
+}
+
+/// A kind of payload, which decides where it's kept and for how long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PayloadKind {
+    /// WebReg's JSON responses.
+    WebReg,
+    /// DARS's audit pages.
+    Dars,
+}
+
+impl PayloadKind {
+    /// Every kind of payload.
+    pub const ALL: [PayloadKind; 2] = [PayloadKind::WebReg, PayloadKind::Dars];
+
+    /// The kind's name, which its keys start with, and which its retention period is
+    /// configured under.
+    pub fn name(self) -> &'static str {
+        match self {
+            PayloadKind::WebReg => "webreg",
+            PayloadKind::Dars => "dars",
+        }
+    }
+
+    /// The number of days that the kind is kept for, if not configured.
+    fn default_retention_days(self) -> u64 {
+        match self {
+            PayloadKind::WebReg => DEFAULT_WEBREG_RETENTION_DAYS,
+            PayloadKind::Dars => DEFAULT_DARS_RETENTION_DAYS,
+        }
+    }
+}
+
+/// Gets the key of a course's WebReg payload from a scrape.
+///
+/// # Parameters
+/// - `term`: The term that was scraped.
+/// - `job_id`: The scrape's ID, or nothing for the tracker's initial scrape.
+/// - `subj_code`: The course's subject (e.g., `CSE`).
+/// - `course_code`: The course's number (e.g., `110`).
+pub fn webreg_payload_key(
+    term: &str,
+    job_id: Option<i64>,
+    subj_code: &str,
+    course_code: &str,
+) -> String {
+    let run = job_id.map_or_else(|| "initial".to_owned(), |id| format!("job-{id}"));
+    format!(
+        "{}/{term}/{run}/{}_{}.json",
+        PayloadKind::WebReg.name(),
+        subj_code.trim(),
+        course_code.trim()
+    )
+}
+
+/// Gets the key of a DARS audit page, by the hash of its content.
+pub fn dars_payload_key(content_hash: &str) -> String {
+    format!("{}/{content_hash}.html", PayloadKind::Dars.name())
+}
+
+/// Checks that a key is a relative, `/`-separated path without empty, `.`, or `..`
+/// segments, so that it can't escape a local store's directory.
+pub fn check_key(key: &str) -> Result<(), PayloadStoreError> {
+    let valid = !key.is_empty()
+        && key
+            .split('/')
+            .all(|s| !s.is_empty() && s != "." && s != ".." && !s.contains('\\'));
+    if valid {
+        Ok(())
+    } else {
+        Err(PayloadStoreError::InvalidKey(key.to_owned()))
+    }
+}
+
+/// The configured payload store, along with how long each kind of payload is kept for.
+pub struct PayloadArchive {
+    store: Arc<dyn PayloadStore>,
+    retention: HashMap<PayloadKind, Duration>,
+}
+
+impl PayloadArchive {
+    /// Sets the payload store up from the configuration file.
+    ///
+    /// # Parameters
+    /// - `config`: The `payloadStore` entry of the configuration file.
+    /// - `data_dir`: The data directory, which a local store's relative path is under.
+    /// - `client`: The HTTP client to make requests to an S3-compatible store with.
+    ///
+    /// # Returns
+    /// The archive, or why the configuration isn't valid.
+    pub fn from_config(
+        config: &ConfigPayloadStore,
+        data_dir: Option<&str>,
+        client: reqwest::Client,
+    ) -> Result<Self, PayloadStoreError> {
+        let store: Arc<dyn PayloadStore> = match config.backend {
+            PayloadBackend::Local => {
+                let path = config.path.as_deref().unwrap_or("payloads");
+                Arc::new(LocalPayloadStore::new(crate::types::data_path(
+                    data_dir, path,
+                )))
+            }
+            PayloadBackend::S3 => Arc::new(S3PayloadStore::from_config(config, client)?),
+        };
+
+        let retention = PayloadKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let days = config
+                    .retention_days
+                    .get(kind.name())
+                    .copied()
+                    .unwrap_or_else(|| kind.default_retention_days());
+                (kind, Duration::from_secs(days * 24 * 60 * 60))
+            })
+            .collect();
+
+        Ok(Self { store, retention })
+    }
+
+    /// Gets the underlying store.
+    pub fn store(&self) -> &Arc<dyn PayloadStore> {
+        &self.store
+    }
+
+    /// Stores a payload in the background. Failures are logged rather than returned, since
+    /// whatever the payload was parsed into is still usable.
+    pub fn archive(&self, key: String, data: Vec<u8>) {
+        let store = self.store.clone();
+        tokio::spawn(async move {
+            if let Err(e) = store.put(&key, data).await {
+                warn!("Failed to archive payload {}: {}", key, e);
+            }
+        });
+    }
+
+    /// Removes the payloads of each kind that are older than the kind's retention period.
+    ///
+    /// # Returns
+    /// The number of payloads removed.
+    pub async fn collect_garbage(&self) -> Result<usize, PayloadStoreError> {
+        self.collect_garbage_at(Utc::now()).await
+    }
+
+    async fn collect_garbage_at(&self, now: DateTime<Utc>) -> Result<usize, PayloadStoreError> {
+        let mut removed = 0;
+        for (kind, retention) in &self.retention {
+            let cutoff = now - chrono::Duration::from_std(*retention).unwrap_or_default();
+            for payload in self.store.list(&format!("{}/", kind.name())).await? {
+                if payload.modified < cutoff {
+                    self.store.delete(&payload.key).await?;
+                    removed += 1;
+                }
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ConfigPayloadStore;
+
+    #[tokio::test]
+    async fn test_local_payload_archive() {
+        let dir = std::env::temp_dir().join(format!("webreg-payloads-{}", std::process::id()));
+        let config = ConfigPayloadStore {
+            backend: PayloadBackend::Local,
+            path: Some(dir.to_string_lossy().into_owned()),
+            endpoint: None,
+            bucket: None,
+            region: None,
+            access_key_id: None,
+            secret_access_key: None,
+            retention_days: HashMap::from([("webreg".to_owned(), 1)]),
+        };
+        let archive = PayloadArchive::from_config(&config, None, reqwest::Client::new()).unwrap();
+        let store = archive.store();
+
+        let key = webreg_payload_key("FA24", Some(3), " CSE ", "110 ");
+        assert_eq!("webreg/FA24/job-3/CSE_110.json", key);
+        store.put(&key, b"[]".to_vec()).await.unwrap();
+        store
+            .put(&dars_payload_key("abc"), b"<html>".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(Some(b"[]".to_vec()), store.get(&key).await.unwrap());
+        assert_eq!(None, store.get("webreg/FA24/none.json").await.unwrap());
+        assert!(store.put("../escape", vec![]).await.is_err());
+
+        let listed = store.list("webreg/FA24/").await.unwrap();
+        assert_eq!(
+            vec![key.clone()],
+            listed.iter().map(|p| &p.key).cloned().collect::<Vec<_>>()
+        );
+        assert_eq!(2, listed[0].size);
+
+        // Two days later, only the WebReg payload is past its retention period
+        let later = Utc::now() + chrono::Duration::days(2);
+        assert_eq!(1, archive.collect_garbage_at(later).await.unwrap());
+        assert!(store.list("webreg/").await.unwrap().is_empty());
+        assert_eq!(1, store.list("dars/").await.unwrap().len());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
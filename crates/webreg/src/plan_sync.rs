@@ -0,0 +1,135 @@
+//! Reconciling a saved plan with the WebReg schedule of the same name.
+//!
+//! A plan lists the sections that a student wants in one of their WebReg schedules. Syncing
+//! it adds the plan's sections that the schedule doesn't have and removes the planned
+//! sections that the plan no longer lists, leaving everything else alone. Sections that the
+//! student is enrolled or waitlisted in are never removed, since they aren't plans.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+use webweg::types::{EnrollmentStatus, ScheduledSection};
+
+use crate::db::PlanEntry;
+
+/// The most schedules that WebReg lets a student have in a term. Syncing a plan into a
+/// schedule that doesn't exist yet creates it, so this is checked before anything changes.
+pub const MAX_WEBREG_SCHEDULES: usize = 15;
+
+/// A section that's in one of the student's WebReg schedules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurrentSection {
+    pub section_id: String,
+    /// Whether the section is only planned, rather than enrolled or waitlisted.
+    pub planned: bool,
+}
+
+impl From<&ScheduledSection> for CurrentSection {
+    fn from(section: &ScheduledSection) -> Self {
+        Self {
+            section_id: section.section_id.clone(),
+            planned: matches!(section.enrolled_status, EnrollmentStatus::Planned),
+        }
+    }
+}
+
+/// What syncing a plan would change.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlanDiff {
+    /// The plan's sections that aren't in the schedule yet.
+    pub to_add: Vec<PlanEntry>,
+    /// The IDs of the schedule's planned sections that the plan doesn't list.
+    pub to_remove: Vec<String>,
+    /// The IDs of the plan's sections that are already planned in the schedule.
+    pub unchanged: Vec<String>,
+    /// The IDs of the plan's sections that the student is already enrolled or waitlisted
+    /// in, which are left as they are.
+    pub enrolled: Vec<String>,
+}
+
+impl PlanDiff {
+    /// Whether the schedule already matches the plan.
+    pub fn is_empty(&self) -> bool {
+        self.to_add.is_empty() && self.to_remove.is_empty()
+    }
+}
+
+/// Works out what syncing a plan into a schedule would change.
+///
+/// # Parameters
+/// - `entries`: The plan's sections.
+/// - `current`: The sections in the WebReg schedule that the plan is synced into.
+///
+/// # Returns
+/// The changes.
+pub fn diff_plan(entries: &[PlanEntry], current: &[CurrentSection]) -> PlanDiff {
+    let mut diff = PlanDiff::default();
+    let mut seen = HashSet::new();
+    for entry in entries {
+        if !seen.insert(entry.section_id.as_str()) {
+            continue;
+        }
+
+        match current.iter().find(|s| s.section_id == entry.section_id) {
+            Some(section) if section.planned => diff.unchanged.push(entry.section_id.clone()),
+            Some(_) => diff.enrolled.push(entry.section_id.clone()),
+            None => diff.to_add.push(entry.clone()),
+        }
+    }
+
+    let mut removed = HashSet::new();
+    for section in current {
+        if section.planned
+            && !seen.contains(section.section_id.as_str())
+            && removed.insert(section.section_id.as_str())
+        {
+            diff.to_remove.push(section.section_id.clone());
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(section_id: &str) -> PlanEntry {
+        PlanEntry {
+            subject_code: "CSE".to_owned(),
+            course_code: "100".to_owned(),
+            section_id: section_id.to_owned(),
+            section_code: "A01".to_owned(),
+            grading_option: None,
+            unit_count: None,
+        }
+    }
+
+    fn current(section_id: &str, planned: bool) -> CurrentSection {
+        CurrentSection {
+            section_id: section_id.to_owned(),
+            planned,
+        }
+    }
+
+    #[test]
+    fn test_diff_plan() {
+        let entries = [entry("100"), entry("200"), entry("300"), entry("100")];
+        let schedule = [
+            current("200", true),
+            current("300", false),
+            current("400", true),
+            current("500", false),
+        ];
+
+        let diff = diff_plan(&entries, &schedule);
+        assert_eq!(vec![entry("100")], diff.to_add);
+        assert_eq!(vec!["400".to_owned()], diff.to_remove);
+        assert_eq!(vec!["200".to_owned()], diff.unchanged);
+        assert_eq!(vec!["300".to_owned()], diff.enrolled);
+        assert!(!diff.is_empty());
+
+        let synced = diff_plan(&entries[1..3], &schedule[..2]);
+        assert!(synced.is_empty());
+    }
+}
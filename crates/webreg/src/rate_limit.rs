@@ -0,0 +1,280 @@
+//! Token bucket rate limiting for the API.
+//!
+//! Each client, identified by its API key if it has one and by its IP address otherwise,
+//! gets a bucket of tokens that refills at a steady rate. Every request takes one token, and
+//! requests made while the bucket is empty are rejected until a token becomes available.
+//! Cheap requests and requests that change a student's enrollment are limited separately,
+//! since the latter are much more expensive for WebReg. Every IP address also gets a bucket
+//! that's checked before the request is authenticated, so that requests with bad keys are
+//! limited too.
+//!
+//! Requests that come through a trusted reverse proxy are attributed to the address that the
+//! proxy forwarded them for, rather than to the proxy itself.
+//!
+//! A bucket that has refilled completely is the same as no bucket at all, so full buckets
+//! are removed every so often to keep clients that stopped making requests from piling up.
+//! This happens as requests come in, rather than in a scheduled job, since every instance
+//! limits its own requests but only the leader runs jobs.
+
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+
+/// The default number of requests that a client may make per minute.
+pub const DEFAULT_GENERAL_RATE_LIMIT: u32 = 300;
+/// The default number of enrollment changes (e.g., adding or dropping a section) that a
+/// client may make per minute.
+pub const DEFAULT_MUTATION_RATE_LIMIT: u32 = 20;
+/// The default number of requests that a single IP address may make per minute.
+pub const DEFAULT_IP_RATE_LIMIT: u32 = 600;
+/// How often full buckets are removed.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A single client's bucket.
+struct Bucket {
+    /// The number of tokens left.
+    tokens: f64,
+    /// When the bucket was last refilled.
+    last_refill: Instant,
+}
+
+/// Limits how often each client may make requests.
+pub struct TokenBucketLimiter {
+    buckets: DashMap<String, Bucket>,
+    /// The most tokens a bucket can hold.
+    capacity: f64,
+    /// The number of tokens added to each bucket per second.
+    refill_per_sec: f64,
+    /// When full buckets were last removed.
+    last_prune: Mutex<Instant>,
+}
+
+impl TokenBucketLimiter {
+    /// Creates a new limiter. Clients may make up to `per_minute` requests at once, after
+    /// which they may make one request every `60 / per_minute` seconds.
+    ///
+    /// # Parameters
+    /// - `per_minute`: The number of requests each client may make per minute.
+    pub fn new(per_minute: u32) -> Self {
+        let per_minute = per_minute.max(1) as f64;
+        Self {
+            buckets: DashMap::new(),
+            capacity: per_minute,
+            refill_per_sec: per_minute / 60.0,
+            last_prune: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Takes a token from the client's bucket.
+    ///
+    /// # Parameters
+    /// - `client`: The client.
+    ///
+    /// # Returns
+    /// Nothing if the request may go ahead, or how long the client should wait before
+    /// trying again.
+    pub fn acquire(&self, client: &str) -> Result<(), Duration> {
+        self.acquire_at(client, Instant::now())
+    }
+
+    fn acquire_at(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        self.prune_at(now);
+        let mut bucket = self.buckets.entry(client.to_owned()).or_insert(Bucket {
+            tokens: self.capacity,
+            last_refill: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * self.refill_per_sec).min(self.capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.refill_per_sec,
+            ))
+        }
+    }
+
+    /// Removes the buckets that have refilled completely, if they weren't checked within
+    /// the last [`PRUNE_INTERVAL`].
+    ///
+    /// # Parameters
+    /// - `now`: The current time.
+    fn prune_at(&self, now: Instant) {
+        // Another request is already pruning, if the lock is taken
+        let Ok(mut last_prune) = self.last_prune.try_lock() else {
+            return;
+        };
+        if now.saturating_duration_since(*last_prune) < PRUNE_INTERVAL {
+            return;
+        }
+
+        *last_prune = now;
+        drop(last_prune);
+        self.buckets.retain(|_, bucket| {
+            let elapsed = now.saturating_duration_since(bucket.last_refill);
+            bucket.tokens + elapsed.as_secs_f64() * self.refill_per_sec < self.capacity
+        });
+    }
+}
+
+/// The rate limits applied to the API.
+pub struct RateLimits {
+    /// Applies to every request.
+    pub general: TokenBucketLimiter,
+    /// Applies to requests that change a student's enrollment or plans through WebReg.
+    pub mutations: TokenBucketLimiter,
+    /// Applies to every request from an IP address, before it's authenticated.
+    pub per_ip: TokenBucketLimiter,
+    /// The addresses of the reverse proxies whose forwarding headers are believed.
+    trusted_proxies: Vec<IpAddr>,
+}
+
+impl RateLimits {
+    /// Creates the rate limits.
+    ///
+    /// # Parameters
+    /// - `general`: The number of requests each client may make per minute.
+    /// - `mutations`: The number of enrollment changes each client may make per minute.
+    /// - `per_ip`: The number of requests each IP address may make per minute.
+    /// - `trusted_proxies`: The addresses of the reverse proxies in front of the API.
+    pub fn new(general: u32, mutations: u32, per_ip: u32, trusted_proxies: Vec<IpAddr>) -> Self {
+        Self {
+            general: TokenBucketLimiter::new(general),
+            mutations: TokenBucketLimiter::new(mutations),
+            per_ip: TokenBucketLimiter::new(per_ip),
+            trusted_proxies,
+        }
+    }
+
+    /// Works out the address of the client that made a request. If the request came from a
+    /// trusted proxy, this is the last address in `X-Forwarded-For` that isn't a trusted
+    /// proxy (since the ones before it could have been made up by the client), or else the
+    /// address in `X-Real-IP`.
+    ///
+    /// # Parameters
+    /// - `peer`: The address that the request was received from.
+    /// - `forwarded_for`: The request's `X-Forwarded-For` header, if any.
+    /// - `real_ip`: The request's `X-Real-IP` header, if any.
+    ///
+    /// # Returns
+    /// The client's address.
+    pub fn client_ip(
+        &self,
+        peer: IpAddr,
+        forwarded_for: Option<&str>,
+        real_ip: Option<&str>,
+    ) -> IpAddr {
+        if !self.trusted_proxies.contains(&peer) {
+            return peer;
+        }
+
+        if let Some(forwarded_for) = forwarded_for {
+            let mut hops = forwarded_for
+                .rsplit(',')
+                .map(|hop| hop.trim().parse::<IpAddr>());
+            let client =
+                hops.find(|hop| !matches!(hop, Ok(ip) if self.trusted_proxies.contains(ip)));
+            return match client {
+                Some(Ok(ip)) => ip,
+                // Every hop was a trusted proxy, so the first one is the client
+                None => forwarded_for
+                    .split(',')
+                    .next()
+                    .and_then(|hop| hop.trim().parse().ok())
+                    .unwrap_or(peer),
+                Some(Err(_)) => peer,
+            };
+        }
+
+        real_ip
+            .and_then(|ip| ip.trim().parse().ok())
+            .unwrap_or(peer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills() {
+        let limiter = TokenBucketLimiter::new(2);
+        let start = Instant::now();
+        assert!(limiter.acquire_at("a", start).is_ok());
+        assert!(limiter.acquire_at("a", start).is_ok());
+
+        // One token comes back every 30 seconds.
+        let retry_after = limiter.acquire_at("a", start).unwrap_err();
+        assert_eq!(30, retry_after.as_secs());
+        assert!(limiter.acquire_at("b", start).is_ok());
+        assert!(limiter
+            .acquire_at("a", start + Duration::from_secs(30))
+            .is_ok());
+        assert!(limiter
+            .acquire_at("a", start + Duration::from_secs(31))
+            .is_err());
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let proxy: IpAddr = "127.0.0.1".parse().unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let limits = RateLimits::new(1, 1, 1, vec![proxy]);
+
+        // Forwarding headers are only believed from a trusted proxy
+        assert_eq!(
+            client,
+            limits.client_ip(client, Some("198.51.100.1"), Some("198.51.100.2"))
+        );
+        assert_eq!(proxy, limits.client_ip(proxy, None, None));
+        assert_eq!(client, limits.client_ip(proxy, None, Some("203.0.113.7")));
+
+        // A client can put whatever it wants at the front of X-Forwarded-For, so the proxy's
+        // own entry at the end is the one used
+        assert_eq!(
+            client,
+            limits.client_ip(proxy, Some("198.51.100.1, 203.0.113.7"), None)
+        );
+        assert_eq!(
+            client,
+            limits.client_ip(proxy, Some("203.0.113.7, 127.0.0.1"), None)
+        );
+        assert_eq!(proxy, limits.client_ip(proxy, Some("garbage"), None));
+    }
+
+    #[test]
+    fn test_full_buckets_pruned() {
+        let limiter = TokenBucketLimiter::new(60);
+        let start = *limiter.last_prune.lock().unwrap();
+        assert!(limiter.acquire_at("a", start).is_ok());
+        let halfway = start + Duration::from_secs(30);
+        for _ in 0..60 {
+            assert!(limiter.acquire_at("b", halfway).is_ok());
+        }
+        assert_eq!(2, limiter.buckets.len());
+
+        // After a minute, the first client's bucket is full again, but the second one has
+        // only had half a minute to refill
+        let later = start + PRUNE_INTERVAL;
+        assert!(limiter.acquire_at("c", later).is_ok());
+        assert!(!limiter.buckets.contains_key("a"));
+        assert!(limiter.buckets.contains_key("b"));
+        assert!(limiter.buckets.contains_key("c"));
+
+        // Nothing is removed again until another interval has passed
+        assert!(limiter
+            .acquire_at("a", later + Duration::from_secs(1))
+            .is_ok());
+        assert!(limiter
+            .acquire_at("d", later + Duration::from_secs(59))
+            .is_ok());
+        assert_eq!(4, limiter.buckets.len());
+    }
+}
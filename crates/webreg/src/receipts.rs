@@ -0,0 +1,274 @@
+//! Receipts for enrollment changes.
+//!
+//! WebReg sometimes reports that an add or drop succeeded when the student's schedule
+//! didn't actually change. Rather than trusting that response, the student's schedule is
+//! fetched again after the change, and the receipt says whether the change can be seen.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use webweg::types::{EnrollmentStatus, ScheduledSection};
+
+use crate::db::EnrollmentActionEntry;
+use crate::hooks::EnrollmentEvent;
+use crate::types::WrapperState;
+use crate::webweg_types;
+
+pub use webreg_types::receipts::{EnrollmentAction, EnrollmentReceipt};
+
+/// Finds a section that the student is enrolled in or waitlisted for.
+///
+/// # Parameters
+/// - `schedule`: The student's schedule.
+/// - `section_id`: The section ID.
+///
+/// # Returns
+/// The section, if the student is enrolled in or waitlisted for it.
+pub fn find_enrolled<'a>(
+    schedule: &'a [ScheduledSection],
+    section_id: &str,
+) -> Option<&'a ScheduledSection> {
+    schedule.iter().find(|s| {
+        s.section_id == section_id
+            && matches!(
+                s.enrolled_status,
+                EnrollmentStatus::Enrolled | EnrollmentStatus::Waitlist { .. }
+            )
+    })
+}
+
+/// Checks whether an enrollment change took effect.
+///
+/// # Parameters
+/// - `action`: The change.
+/// - `section_id`: The section that was added or dropped.
+/// - `after`: The student's schedule after the change, if it could be fetched.
+///
+/// # Returns
+/// `true` if the student is now enrolled in (or waitlisted for) an added section, or is no
+/// longer in a dropped section. If the schedule couldn't be fetched, the change can't be
+/// verified.
+pub fn verify_change(
+    action: EnrollmentAction,
+    section_id: &str,
+    after: Option<&[ScheduledSection]>,
+) -> bool {
+    let Some(after) = after else {
+        return false;
+    };
+
+    let enrolled = find_enrolled(after, section_id).is_some();
+    match action {
+        EnrollmentAction::Add => enrolled,
+        EnrollmentAction::Drop => !enrolled,
+    }
+}
+
+/// How a swap (dropping one section and adding another in its place) turned out.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapState {
+    /// The new section was added.
+    Swapped,
+    /// The new section couldn't be added, so the dropped section was added back.
+    Restored,
+    /// The new section couldn't be added, and neither could the dropped section.
+    NotRestored,
+    /// The student's schedule couldn't be fetched after the new section was added, so it
+    /// isn't known whether it was. The dropped section isn't added back, since the student
+    /// could end up in both sections.
+    Unknown,
+}
+
+impl SwapState {
+    /// Works out how a swap turned out once the new section was added.
+    ///
+    /// # Parameters
+    /// - `section_id`: The new section.
+    /// - `after_add`: The student's schedule after the add, if it could be fetched.
+    ///
+    /// # Returns
+    /// How the swap turned out, or `None` if the new section is known not to have been
+    /// added, in which case the dropped section should be added back.
+    pub fn after_add(section_id: &str, after_add: Option<&[ScheduledSection]>) -> Option<Self> {
+        match after_add {
+            None => Some(SwapState::Unknown),
+            Some(after) if find_enrolled(after, section_id).is_some() => Some(SwapState::Swapped),
+            Some(_) => None,
+        }
+    }
+
+    /// Works out how a swap turned out once the dropped section was added back (or not).
+    ///
+    /// # Parameters
+    /// - `restore`: The receipt for the last attempt at adding the dropped section back.
+    pub fn after_restore(restore: Option<&EnrollmentReceipt>) -> Self {
+        if restore.is_some_and(|r| r.verified) {
+            SwapState::Restored
+        } else {
+            SwapState::NotRestored
+        }
+    }
+}
+
+/// Checks whether an add or drop took effect, records it in the action log, and announces
+/// it to enrollment hooks if it did.
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `term`: The term.
+/// - `action`: The change that was made.
+/// - `section_id`: The section that was added or dropped.
+/// - `webreg_success`: Whether WebReg said that the change succeeded.
+/// - `before`: The student's schedule before the change.
+/// - `after`: The student's schedule after the change, if it could be fetched.
+///
+/// # Returns
+/// The receipt for the change.
+pub fn record_change(
+    s: &WrapperState,
+    term: &str,
+    action: EnrollmentAction,
+    section_id: &str,
+    webreg_success: bool,
+    before: &[ScheduledSection],
+    after: Option<&[ScheduledSection]>,
+) -> EnrollmentReceipt {
+    let find = |schedule: &[ScheduledSection]| {
+        schedule
+            .iter()
+            .find(|sec| sec.section_id == section_id)
+            .cloned()
+            .map(webweg_types::ScheduledSection::from)
+    };
+
+    let before_section = find(before);
+    let after_section = after.and_then(find);
+    let verified = verify_change(action, section_id, after);
+    if !verified {
+        warn!(
+            "[{term}] {} of section {section_id} could not be verified (WebReg said {webreg_success}).",
+            action.as_str()
+        );
+    }
+
+    let timestamp = Utc::now().to_rfc3339();
+    let action_id = s
+        .schedule_db
+        .insert_enrollment_action(&EnrollmentActionEntry {
+            term,
+            section_id,
+            action: action.as_str(),
+            webreg_success,
+            verified,
+            before_snapshot: before_section
+                .as_ref()
+                .map(|sec| serde_json::to_string(sec).unwrap()),
+            after_snapshot: after_section
+                .as_ref()
+                .map(|sec| serde_json::to_string(sec).unwrap()),
+            created_at: &timestamp,
+        })
+        .inspect_err(|e| warn!("[{term}] Failed to record enrollment action: {e}"))
+        .ok();
+
+    let receipt = EnrollmentReceipt {
+        action_id,
+        action,
+        section_id: section_id.to_owned(),
+        webreg_success,
+        verified,
+        before: before_section,
+        after: after_section,
+        timestamp,
+    };
+
+    if verified {
+        s.enrollment_bus
+            .publish(EnrollmentEvent::from_receipt(term, &receipt));
+    }
+
+    receipt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(section_id: &str, enrolled_status: EnrollmentStatus) -> ScheduledSection {
+        ScheduledSection {
+            section_id: section_id.to_string(),
+            all_instructors: vec![],
+            subject_code: "CSE".to_string(),
+            course_code: "100".to_string(),
+            course_title: "Advanced Data Structures".to_string(),
+            section_code: "A01".to_string(),
+            section_capacity: 100,
+            enrolled_count: 50,
+            available_seats: 50,
+            grade_option: "L".to_string(),
+            units: 4,
+            enrolled_status,
+            waitlist_ct: 0,
+            meetings: vec![],
+        }
+    }
+
+    #[test]
+    fn test_verify_change() {
+        let after = vec![
+            section("1", EnrollmentStatus::Enrolled),
+            section("2", EnrollmentStatus::Planned),
+        ];
+
+        assert!(verify_change(EnrollmentAction::Add, "1", Some(&after)));
+        // A planned section isn't an enrollment.
+        assert!(!verify_change(EnrollmentAction::Add, "2", Some(&after)));
+        assert!(verify_change(EnrollmentAction::Drop, "2", Some(&after)));
+        assert!(!verify_change(EnrollmentAction::Drop, "1", Some(&after)));
+        assert!(!verify_change(EnrollmentAction::Drop, "1", None));
+    }
+
+    fn receipt(section_id: &str, after: &[ScheduledSection]) -> EnrollmentReceipt {
+        EnrollmentReceipt {
+            action_id: None,
+            action: EnrollmentAction::Add,
+            section_id: section_id.to_string(),
+            webreg_success: true,
+            verified: verify_change(EnrollmentAction::Add, section_id, Some(after)),
+            before: None,
+            after: None,
+            timestamp: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_swap_state() {
+        // Section 1 was dropped for section 2
+        let added = vec![section("2", EnrollmentStatus::Enrolled)];
+        assert_eq!(
+            Some(SwapState::Swapped),
+            SwapState::after_add("2", Some(&added))
+        );
+        assert_eq!(Some(SwapState::Unknown), SwapState::after_add("2", None));
+
+        // The add failed, so section 1 is added back
+        let failed = vec![section("3", EnrollmentStatus::Enrolled)];
+        assert_eq!(None, SwapState::after_add("2", Some(&failed)));
+        let restored = vec![
+            section("1", EnrollmentStatus::Enrolled),
+            section("3", EnrollmentStatus::Enrolled),
+        ];
+        assert_eq!(
+            SwapState::Restored,
+            SwapState::after_restore(Some(&receipt("1", &restored)))
+        );
+
+        // WebReg said that section 1 was added back, but it isn't in the schedule
+        assert_eq!(
+            SwapState::NotRestored,
+            SwapState::after_restore(Some(&receipt("1", &failed)))
+        );
+        assert_eq!(SwapState::NotRestored, SwapState::after_restore(None));
+    }
+}
@@ -0,0 +1,215 @@
+//! Response filters, which keep fields out of every response that an API key gets.
+//!
+//! Some integrations (e.g., a public Discord bot that shows degree progress) must never see
+//! fields like grades or names, even though their scopes let them use the endpoints that
+//! return those fields. A key's filter has a deny list, whose fields are removed wherever
+//! they appear, and an allow list, which (if not empty) is the only fields that are kept.
+//! Objects and arrays of objects are kept even if they aren't allowed, so that the allowed
+//! fields inside them can be reached, but anything else under them is removed.
+//!
+//! Fields are named as they appear in responses, matched without regard to case, `_`, or
+//! `-` (so `first_name` also matches `firstName`), and may use `*` as a wildcard (e.g.,
+//! `*name`). Filters are stored with the key's prefix in the schedule database, and applied
+//! to each response's JSON after it's serialized (see
+//! `server::middleware::redaction`), so that no endpoint can leak a field by forgetting to
+//! filter it.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// The most patterns that an allow or deny list can have.
+pub const MAX_FILTER_PATTERNS: usize = 100;
+
+/// The fields that an API key may and may not see in responses. Unknown fields are refused,
+/// so that a misspelled list (e.g., `denny`) can't leave the key seeing everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResponseFilter {
+    /// If not empty, the only fields that are kept.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// The fields that are removed, even if they're allowed.
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl ResponseFilter {
+    /// Whether the filter lets everything through.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Filters a response's body.
+    ///
+    /// # Parameters
+    /// - `body`: The body, as JSON.
+    ///
+    /// # Returns
+    /// The filtered body.
+    pub fn apply(&self, body: Value) -> Value {
+        if self.allow.is_empty() {
+            self.remove_denied(body)
+        } else {
+            self.keep_allowed(body).unwrap_or(Value::Null)
+        }
+    }
+
+    fn denies(&self, field: &str) -> bool {
+        self.deny
+            .iter()
+            .any(|pattern| field_matches(pattern, field))
+    }
+
+    fn allows(&self, field: &str) -> bool {
+        self.allow.is_empty()
+            || self
+                .allow
+                .iter()
+                .any(|pattern| field_matches(pattern, field))
+    }
+
+    /// Removes the denied fields from a value, at any depth.
+    fn remove_denied(&self, value: Value) -> Value {
+        match value {
+            Value::Object(fields) => Value::Object(
+                fields
+                    .into_iter()
+                    .filter(|(field, _)| !self.denies(field))
+                    .map(|(field, value)| (field, self.remove_denied(value)))
+                    .collect(),
+            ),
+            Value::Array(items) => {
+                Value::Array(items.into_iter().map(|v| self.remove_denied(v)).collect())
+            }
+            value => value,
+        }
+    }
+
+    /// Keeps the allowed fields of a value that isn't itself under an allowed field.
+    ///
+    /// # Returns
+    /// The value, or `None` if nothing in it is allowed (e.g., it's a number).
+    fn keep_allowed(&self, value: Value) -> Option<Value> {
+        match value {
+            Value::Object(fields) => {
+                let mut kept = Map::new();
+                for (field, value) in fields {
+                    if self.denies(&field) {
+                        continue;
+                    }
+
+                    let value = if self.allows(&field) {
+                        Some(self.remove_denied(value))
+                    } else {
+                        self.keep_allowed(value)
+                    };
+                    if let Some(value) = value {
+                        kept.insert(field, value);
+                    }
+                }
+                Some(Value::Object(kept))
+            }
+            Value::Array(items) => Some(Value::Array(
+                items
+                    .into_iter()
+                    .filter_map(|v| self.keep_allowed(v))
+                    .collect(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Normalizes a field's name, or a pattern, for matching.
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '_' | '-'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Whether a field's name matches a pattern, which may use `*` to match any characters.
+pub fn field_matches(pattern: &str, field: &str) -> bool {
+    let pattern = normalize(pattern);
+    let field = normalize(field);
+    let mut parts = pattern.split('*');
+    // There's always at least one part, even for an empty pattern
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = field.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<_> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcards, so the whole field has to match
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_response_filter() {
+        assert!(field_matches("first_name", "firstName"));
+        assert!(field_matches("*name", "student_name"));
+        assert!(field_matches("*grade*", "gradeOption"));
+        assert!(!field_matches("name", "names"));
+        assert!(!field_matches("a*b*b", "ab"));
+
+        let body = json!({
+            "name": "Jane Doe",
+            "requirements": [
+                { "title": "Lower Division", "status": "complete", "courses": [
+                    { "course": "CSE 12", "grade": "A" },
+                ] },
+            ],
+            "gpa": 3.9,
+        });
+
+        let deny = ResponseFilter {
+            allow: vec![],
+            deny: vec!["*name".to_owned(), "grade".to_owned(), "GPA".to_owned()],
+        };
+        assert_eq!(
+            json!({
+                "requirements": [
+                    { "title": "Lower Division", "status": "complete", "courses": [
+                        { "course": "CSE 12" },
+                    ] },
+                ],
+            }),
+            deny.apply(body.clone())
+        );
+
+        let allow = ResponseFilter {
+            allow: vec![
+                "title".to_owned(),
+                "status".to_owned(),
+                "courses".to_owned(),
+            ],
+            deny: vec!["grade".to_owned()],
+        };
+        assert_eq!(
+            json!({
+                "requirements": [
+                    { "title": "Lower Division", "status": "complete", "courses": [
+                        { "course": "CSE 12" },
+                    ] },
+                ],
+            }),
+            allow.apply(body)
+        );
+        assert_eq!(Value::Null, allow.apply(json!(42)));
+    }
+}
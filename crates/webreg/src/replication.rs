@@ -0,0 +1,315 @@
+//! Warm standby replication of the schedule database, which holds enrollment jobs and the
+//! seat counts that the tracker records for each section.
+//!
+//! A primary with a `standbyUrl` pushes a snapshot of the database to the standby's admin
+//! API every so often. A standby (`standby: true`) restores each snapshot that it's sent,
+//! and holds off on running its background workers (the tracker, enrollment jobs, and
+//! hooks) so that students aren't enrolled twice. If the primary fails, the standby is
+//! promoted through `POST /admin/replication/promote`: it then starts its workers and
+//! refuses any further snapshots, so that a primary that comes back can't overwrite it.
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::header::AUTHORIZATION;
+use reqwest::StatusCode;
+use serde::Serialize;
+use thiserror::Error;
+use tokio::sync::{watch, Mutex};
+use tracing::{info, warn};
+
+use crate::types::WrapperState;
+
+/// How often the primary pushes a snapshot to the standby, by default.
+pub const DEFAULT_REPLICATION_INTERVAL: Duration = Duration::from_secs(60);
+/// The largest snapshot that a standby accepts, in bytes.
+pub const MAX_SNAPSHOT_SIZE: usize = 1 << 30;
+/// The header that carries when a snapshot was taken, in milliseconds since the epoch.
+pub const SNAPSHOT_TAKEN_AT_HEADER: &str = "x-snapshot-taken-at";
+
+/// Where the primary pushes its snapshots to.
+pub struct StandbyTarget {
+    /// The base URL of the standby's admin API (e.g., `http://10.0.0.2:3001`).
+    pub url: String,
+    /// The standby's admin token, if it has one.
+    pub token: Option<String>,
+}
+
+/// A snapshot that was pushed to, or restored from, the primary.
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotInfo {
+    /// When the snapshot was taken on the primary, in milliseconds since the epoch.
+    pub taken_at: i64,
+    /// The size of the snapshot, in bytes.
+    pub size: usize,
+    /// When the snapshot was pushed or restored, in RFC 3339 format.
+    pub replicated_at: String,
+}
+
+/// Why a snapshot couldn't be pushed or restored.
+#[derive(Debug, Error)]
+pub enum ReplicationError {
+    #[error("this instance isn't a standby")]
+    NotStandby,
+    #[error("the snapshot (taken at {taken_at}) is older than the last one (taken at {last})")]
+    Outdated { taken_at: i64, last: i64 },
+    #[error("the standby rejected the snapshot with status {0}")]
+    Rejected(StatusCode),
+    #[error("failed to copy the database: {0}")]
+    Db(#[from] rusqlite::Error),
+    #[error("failed to access the snapshot: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to push the snapshot: {0}")]
+    Http(#[from] reqwest::Error),
+}
+
+/// Whether this instance is the primary or a standby, and how replication between them is
+/// going.
+pub struct ReplicationState {
+    /// Whether this instance is a standby. Only ever goes from `true` to `false`.
+    standby: watch::Sender<bool>,
+    /// Where snapshots are pushed to, if this instance has a standby.
+    target: Option<StandbyTarget>,
+    /// How often snapshots are pushed.
+    interval: Duration,
+    /// The most recent snapshot that was pushed (on the primary) or restored (on a
+    /// standby). Restores hold this lock so that they're applied in order.
+    last_snapshot: Mutex<Option<SnapshotInfo>>,
+}
+
+impl ReplicationState {
+    /// Creates the replication state.
+    ///
+    /// # Parameters
+    /// - `standby`: Whether this instance starts out as a standby.
+    /// - `target`: Where to push snapshots to, if anywhere.
+    /// - `interval`: How often to push snapshots.
+    pub fn new(standby: bool, target: Option<StandbyTarget>, interval: Duration) -> Self {
+        Self {
+            standby: watch::Sender::new(standby),
+            target,
+            interval,
+            last_snapshot: Mutex::new(None),
+        }
+    }
+
+    /// Whether this instance is a standby.
+    pub fn is_standby(&self) -> bool {
+        *self.standby.borrow()
+    }
+
+    /// Waits until this instance is the primary, which is immediately if it isn't a
+    /// standby.
+    pub async fn wait_until_primary(&self) {
+        let mut role = self.standby.subscribe();
+        // The sender lives as long as `self`, so this can't fail
+        let _ = role.wait_for(|standby| !standby).await;
+    }
+
+    /// Promotes this instance to the primary.
+    ///
+    /// # Returns
+    /// `true` if this instance was a standby.
+    pub fn promote(&self) -> bool {
+        self.standby.send_replace(false)
+    }
+
+    /// Gets the most recent snapshot that was pushed or restored.
+    pub async fn last_snapshot(&self) -> Option<SnapshotInfo> {
+        self.last_snapshot.lock().await.clone()
+    }
+}
+
+/// Checks that a snapshot can be restored.
+///
+/// # Parameters
+/// - `standby`: Whether this instance is a standby.
+/// - `last`: The most recent snapshot that was restored.
+/// - `taken_at`: When the snapshot was taken.
+fn check_snapshot(
+    standby: bool,
+    last: Option<&SnapshotInfo>,
+    taken_at: i64,
+) -> Result<(), ReplicationError> {
+    if !standby {
+        return Err(ReplicationError::NotStandby);
+    }
+
+    match last {
+        Some(last) if last.taken_at >= taken_at => Err(ReplicationError::Outdated {
+            taken_at,
+            last: last.taken_at,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// A path to stage a snapshot at, unique to this process and the snapshot.
+fn snapshot_path(label: &str, taken_at: i64) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "webreg-snapshot-{}-{label}-{taken_at}.db",
+        std::process::id()
+    ))
+}
+
+/// Runs a background worker once this instance is the primary.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `worker`: The worker to run.
+pub async fn when_primary<F, Fut>(state: Arc<WrapperState>, worker: F)
+where
+    F: FnOnce(Arc<WrapperState>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    if state.replication.is_standby() {
+        info!("Waiting for this standby to be promoted before starting a background worker.");
+    }
+
+    state.replication.wait_until_primary().await;
+    worker(state).await;
+}
+
+/// Pushes a snapshot of the schedule database to the standby every so often, if one is
+/// configured. A standby only starts pushing once it's promoted, and only the leader
+/// pushes (see [`crate::leader`]).
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+pub async fn run_replication(state: Arc<WrapperState>) {
+    let Some(target) = &state.replication.target else {
+        return;
+    };
+
+    state.replication.wait_until_primary().await;
+    info!("Replicating the schedule database to {}.", target.url);
+    let mut interval = tokio::time::interval(state.replication.interval);
+    loop {
+        interval.tick().await;
+        if !state.leader.is_leader() {
+            continue;
+        }
+
+        match push_snapshot(&state, target).await {
+            Ok(info) => info!("Pushed a {} byte snapshot to the standby.", info.size),
+            Err(ReplicationError::Rejected(StatusCode::CONFLICT)) => {
+                warn!("The standby refused a snapshot; it may have been promoted.")
+            }
+            Err(e) => warn!("Failed to replicate to the standby: {e}"),
+        }
+    }
+}
+
+/// Takes a snapshot of the schedule database and pushes it to the standby.
+async fn push_snapshot(
+    state: &Arc<WrapperState>,
+    target: &StandbyTarget,
+) -> Result<SnapshotInfo, ReplicationError> {
+    let taken_at = chrono::Utc::now().timestamp_millis();
+    let snapshot = tokio::task::spawn_blocking({
+        let state = state.clone();
+        move || -> Result<Vec<u8>, ReplicationError> {
+            let path = snapshot_path("push", taken_at);
+            state.schedule_db.snapshot_to(&path)?;
+            let bytes = std::fs::read(&path);
+            let _ = std::fs::remove_file(&path);
+            Ok(bytes?)
+        }
+    })
+    .await
+    .map_err(std::io::Error::other)??;
+
+    let size = snapshot.len();
+    let mut request = state
+        .client
+        .post(format!(
+            "{}/admin/replication/snapshot",
+            target.url.trim_end_matches('/')
+        ))
+        .header(SNAPSHOT_TAKEN_AT_HEADER, taken_at)
+        .body(snapshot);
+    if let Some(token) = &target.token {
+        request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+    }
+
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(ReplicationError::Rejected(response.status()));
+    }
+
+    let info = SnapshotInfo {
+        taken_at,
+        size,
+        replicated_at: chrono::Local::now().to_rfc3339(),
+    };
+    *state.replication.last_snapshot.lock().await = Some(info.clone());
+    Ok(info)
+}
+
+/// Replaces the schedule database with a snapshot pushed by the primary.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `taken_at`: When the snapshot was taken, in milliseconds since the epoch.
+/// - `snapshot`: The snapshot.
+///
+/// # Returns
+/// The restored snapshot, or why it couldn't be restored. Snapshots are refused once this
+/// instance has been promoted, and if they're older than the last one restored.
+pub async fn restore_snapshot(
+    state: &Arc<WrapperState>,
+    taken_at: i64,
+    snapshot: Vec<u8>,
+) -> Result<SnapshotInfo, ReplicationError> {
+    let mut last = state.replication.last_snapshot.lock().await;
+    check_snapshot(state.replication.is_standby(), last.as_ref(), taken_at)?;
+
+    let size = snapshot.len();
+    tokio::task::spawn_blocking({
+        let state = state.clone();
+        move || -> Result<(), ReplicationError> {
+            let path = snapshot_path("restore", taken_at);
+            std::fs::write(&path, snapshot)?;
+            let restored = state.schedule_db.restore_from(&path);
+            let _ = std::fs::remove_file(&path);
+            Ok(restored?)
+        }
+    })
+    .await
+    .map_err(std::io::Error::other)??;
+
+    let info = SnapshotInfo {
+        taken_at,
+        size,
+        replicated_at: chrono::Local::now().to_rfc3339(),
+    };
+    *last = Some(info.clone());
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_snapshot() {
+        let last = SnapshotInfo {
+            taken_at: 1_000,
+            size: 4096,
+            replicated_at: String::new(),
+        };
+
+        assert!(check_snapshot(true, None, 500).is_ok());
+        assert!(check_snapshot(true, Some(&last), 2_000).is_ok());
+        assert!(matches!(
+            check_snapshot(true, Some(&last), 1_000),
+            Err(ReplicationError::Outdated { last: 1_000, .. })
+        ));
+        assert!(matches!(
+            check_snapshot(false, None, 2_000),
+            Err(ReplicationError::NotStandby)
+        ));
+    }
+}
@@ -0,0 +1,242 @@
+//! Per-request log lines, kept in memory for debugging.
+//!
+//! Every request to the API runs in a `request` span that carries its request ID (see
+//! [`crate::server`]'s request ID middleware). When the ring buffer is enabled, the
+//! [`RequestLogLayer`] copies every event logged under such a span into [`REQUEST_LOG`], so
+//! that the lines logged while serving a request can be looked up by its ID.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::Utc;
+use rand::Rng;
+use serde::Serialize;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// The name of the span field that holds the request ID.
+pub const REQUEST_ID_FIELD: &str = "request_id";
+
+/// The log lines of recent requests. This is disabled until a capacity is set.
+pub static REQUEST_LOG: RequestLog = RequestLog::new();
+
+/// A line that was logged while serving a request.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    #[serde(skip)]
+    pub request_id: String,
+    /// When the line was logged, in RFC 3339 format.
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    /// The message, followed by any other fields as `key=value`.
+    pub message: String,
+}
+
+/// A ring buffer of the lines logged while serving requests. Once full, the oldest lines
+/// are dropped first.
+pub struct RequestLog {
+    capacity: AtomicUsize,
+    lines: Mutex<VecDeque<LogLine>>,
+}
+
+impl Default for RequestLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestLog {
+    /// Creates a new, disabled buffer.
+    pub const fn new() -> Self {
+        Self {
+            capacity: AtomicUsize::new(0),
+            lines: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Sets the number of lines to keep. A capacity of 0 disables the buffer.
+    ///
+    /// # Parameters
+    /// - `capacity`: The number of lines.
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut lines = self.lines.lock().unwrap();
+        while lines.len() > capacity {
+            lines.pop_front();
+        }
+    }
+
+    /// Whether lines are being kept.
+    pub fn is_enabled(&self) -> bool {
+        self.capacity.load(Ordering::Relaxed) > 0
+    }
+
+    /// Adds a line, dropping the oldest line if the buffer is full. Nothing happens if the
+    /// buffer is disabled.
+    ///
+    /// # Parameters
+    /// - `line`: The line.
+    pub fn push(&self, line: LogLine) {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        if capacity == 0 {
+            return;
+        }
+
+        let mut lines = self.lines.lock().unwrap();
+        while lines.len() >= capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// Gets the lines that are still kept for a request, oldest first.
+    ///
+    /// # Parameters
+    /// - `request_id`: The request ID.
+    ///
+    /// # Returns
+    /// The lines.
+    pub fn get(&self, request_id: &str) -> Vec<LogLine> {
+        self.lines
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|l| l.request_id == request_id)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Generates an ID for a request.
+pub fn generate_request_id() -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros();
+    let random: u32 = rand::thread_rng().gen();
+    format!("{:x}-{:08x}", timestamp & 0xFFFFFFFF, random)
+}
+
+/// The request ID of a span, stored in the span's extensions.
+struct SpanRequestId(String);
+
+/// Collects the fields of a span or an event.
+#[derive(Default)]
+struct FieldVisitor {
+    request_id: Option<String>,
+    message: String,
+    fields: String,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            REQUEST_ID_FIELD => self.request_id = Some(value.to_owned()),
+            "message" => self.message = value.to_owned(),
+            name => {
+                let _ = write!(self.fields, " {name}={value}");
+            }
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            REQUEST_ID_FIELD => self.request_id = Some(format!("{value:?}")),
+            "message" => self.message = format!("{value:?}"),
+            name => {
+                let _ = write!(self.fields, " {name}={value:?}");
+            }
+        }
+    }
+}
+
+/// A `tracing` layer that copies events logged under a request's span into a
+/// [`RequestLog`].
+pub struct RequestLogLayer {
+    log: &'static RequestLog,
+}
+
+impl RequestLogLayer {
+    /// Creates a layer that writes to the given buffer.
+    pub fn new(log: &'static RequestLog) -> Self {
+        Self { log }
+    }
+}
+
+impl<S> Layer<S> for RequestLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        if let (Some(request_id), Some(span)) = (visitor.request_id, ctx.span(id)) {
+            span.extensions_mut().insert(SpanRequestId(request_id));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if !self.log.is_enabled() {
+            return;
+        }
+
+        let Some(request_id) = ctx.event_scope(event).and_then(|scope| {
+            scope.into_iter().find_map(|span| {
+                span.extensions()
+                    .get::<SpanRequestId>()
+                    .map(|r| r.0.clone())
+            })
+        }) else {
+            return;
+        };
+
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        self.log.push(LogLine {
+            request_id,
+            timestamp: Utc::now().to_rfc3339(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_owned(),
+            message: visitor.message + &visitor.fields,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::info_span;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_request_log() {
+        let log: &'static RequestLog = Box::leak(Box::new(RequestLog::new()));
+        let subscriber = tracing_subscriber::registry().with(RequestLogLayer::new(log));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("Before the buffer is enabled");
+            log.set_capacity(2);
+
+            let span = info_span!("request", request_id = "abc");
+            let _guard = span.enter();
+            tracing::info!(section_id = "123", "Adding section");
+            tracing::warn!("Not verified");
+            tracing::info!("Done");
+        });
+
+        let lines = log.get("abc");
+        assert_eq!(2, lines.len());
+        assert_eq!("WARN", lines[0].level);
+        assert_eq!("Not verified", lines[0].message);
+        assert!(log.get("xyz").is_empty());
+    }
+}
@@ -0,0 +1,551 @@
+//! Section notes, and the enrollment restrictions that they describe.
+//!
+//! WebReg attaches free-text notes to some sections (e.g., "Department approval required"
+//! or "Restricted to CS25 majors"). The notes are fetched with each course when a term is
+//! scraped, and the restrictions found in them are stored alongside, so that they can be
+//! returned with the term's schedule data and so that the endpoints that validate adds can
+//! warn about sections that the student may not be able to enroll in. WebReg itself still
+//! decides whether an add goes through.
+//!
+//! Some departments only open their sections to students outside the major after a certain
+//! date. That date is taken from the notes (e.g., "Open to non-majors on 5/24"), or from
+//! `restrictionWindows` in the configuration file for departments that don't say so in
+//! their notes, and is stored as the section's enrollment window. The live feed mentions it
+//! when seats open in a section that students outside the major can't take yet.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+static HTML_TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+static WHITESPACE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s+").unwrap());
+static MAJOR_CODE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b[A-Z]{2}\d{2}[A-Z]?\b").unwrap());
+static CLASS_LEVEL_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(freshm[ae]n|sophomores?|juniors?|seniors?)\b(?:\s+(?:standing|only))?")
+        .unwrap()
+});
+static NON_MAJOR_DATE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    let date = r"(\d{1,2}/\d{1,2}(?:/\d{2,4})?|(?:jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)[a-z]*\.?\s+\d{1,2}(?:,?\s+\d{4})?)";
+    Regex::new(&format!(
+        r"(?i)(?:(?:non-?majors?|all students|open to all)[^.;]*?\b(?:on|after|beginning|starting|as of)|restricted to [^.;]*?\buntil)\s+{date}"
+    ))
+    .unwrap()
+});
+static SECTION_PREFIX_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:Section\s+)?([A-Z]\d{2})\s*[:\-]\s*").unwrap());
+
+/// A kind of enrollment restriction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestrictionKind {
+    /// The department has to approve the enrollment.
+    DepartmentApproval,
+    /// The instructor has to approve the enrollment.
+    InstructorApproval,
+    /// Only students in certain majors can enroll.
+    MajorRestricted,
+    /// Only students in certain colleges can enroll.
+    CollegeRestricted,
+    /// Only students at certain class levels (e.g., seniors) can enroll.
+    ClassLevel,
+    /// Only graduate students can enroll.
+    GraduateOnly,
+}
+
+/// An enrollment restriction found in a section's notes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Restriction {
+    pub kind: RestrictionKind,
+    /// What the restriction is limited to, if the note says (e.g., the major codes for
+    /// [`RestrictionKind::MajorRestricted`], or the class level for
+    /// [`RestrictionKind::ClassLevel`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl Restriction {
+    /// Describes the restriction, for warning a student who's about to add the section.
+    pub fn warning(&self) -> String {
+        let detail = self.detail.as_deref();
+        match (self.kind, detail) {
+            (RestrictionKind::DepartmentApproval, _) => {
+                "The department has to approve enrollment in this section.".to_owned()
+            }
+            (RestrictionKind::InstructorApproval, _) => {
+                "The instructor has to approve enrollment in this section.".to_owned()
+            }
+            (RestrictionKind::MajorRestricted, Some(majors)) => {
+                format!("This section is restricted to {majors} majors.")
+            }
+            (RestrictionKind::MajorRestricted, None) => {
+                "This section is restricted to certain majors.".to_owned()
+            }
+            (RestrictionKind::CollegeRestricted, _) => {
+                "This section is restricted to students in certain colleges.".to_owned()
+            }
+            (RestrictionKind::ClassLevel, Some(level)) => {
+                format!("This section is restricted to {level}.")
+            }
+            (RestrictionKind::ClassLevel, None) => {
+                "This section is restricted to certain class levels.".to_owned()
+            }
+            (RestrictionKind::GraduateOnly, _) => {
+                "This section is restricted to graduate students.".to_owned()
+            }
+        }
+    }
+}
+
+/// Where a section's enrollment window came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowSource {
+    /// The section's notes.
+    Notes,
+    /// `restrictionWindows` in the configuration file.
+    Config,
+}
+
+/// When a section that's restricted to majors opens to everyone else.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnrollmentWindow {
+    /// The date, in `YYYY-MM-DD` format.
+    pub opens_to_non_majors: String,
+    pub source: WindowSource,
+}
+
+impl EnrollmentWindow {
+    /// Whether the section hasn't opened to non-majors yet.
+    ///
+    /// # Parameters
+    /// - `today`: The current date.
+    pub fn is_pending(&self, today: NaiveDate) -> bool {
+        NaiveDate::parse_from_str(&self.opens_to_non_majors, "%Y-%m-%d")
+            .is_ok_and(|opens| opens > today)
+    }
+}
+
+/// A section's notes, the restrictions found in them, and when the section opens to
+/// non-majors, if it's known.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionNotes {
+    pub notes: Vec<String>,
+    pub restrictions: Vec<Restriction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<EnrollmentWindow>,
+}
+
+impl SectionNotes {
+    /// Collects a section's notes and parses the restrictions, and the date that the
+    /// section opens to non-majors, out of them.
+    ///
+    /// # Parameters
+    /// - `notes`: The notes.
+    /// - `term`: The section's term, which dates without a year are assumed to be for.
+    pub fn new(notes: Vec<String>, term: &str) -> Self {
+        let mut restrictions = vec![];
+        for restriction in notes.iter().flat_map(|note| parse_restrictions(note)) {
+            if !restrictions.contains(&restriction) {
+                restrictions.push(restriction);
+            }
+        }
+
+        let window = notes
+            .iter()
+            .find_map(|note| parse_non_major_date(note, term))
+            .map(|date| EnrollmentWindow {
+                opens_to_non_majors: date.format("%Y-%m-%d").to_string(),
+                source: WindowSource::Notes,
+            });
+
+        Self {
+            notes,
+            restrictions,
+            window,
+        }
+    }
+}
+
+/// A department's window from `restrictionWindows` in the configuration file.
+#[derive(Debug, Clone)]
+pub struct RestrictionWindowRule {
+    pub term: String,
+    /// The department's subject code (e.g., `CSE`).
+    pub department: String,
+    /// The courses that the window applies to, as normalized codes. If empty, it applies to
+    /// every course of the department.
+    pub courses: Vec<String>,
+    pub opens_to_non_majors: NaiveDate,
+}
+
+impl RestrictionWindowRule {
+    /// Whether the window applies to a course.
+    ///
+    /// # Parameters
+    /// - `term`: The course's term.
+    /// - `subj_code`: The course's subject code.
+    /// - `subj_course_id`: The course's normalized code (e.g., `CSE 100`).
+    pub fn applies_to(&self, term: &str, subj_code: &str, subj_course_id: &str) -> bool {
+        self.term.eq_ignore_ascii_case(term)
+            && self.department.eq_ignore_ascii_case(subj_code.trim())
+            && (self.courses.is_empty() || self.courses.iter().any(|c| c == subj_course_id))
+    }
+}
+
+/// Gives a course's sections the enrollment window that's configured for its department,
+/// if there is one. A configured window takes precedence over one from the notes, and
+/// sections without notes get an entry of their own.
+///
+/// # Parameters
+/// - `notes`: The notes of the course's sections, keyed by section ID.
+/// - `section_ids`: The IDs of the course's sections.
+/// - `rules`: The configured windows.
+/// - `term`: The course's term.
+/// - `subj_code`: The course's subject code.
+/// - `subj_course_id`: The course's normalized code.
+pub fn apply_window_rules(
+    notes: &mut HashMap<String, SectionNotes>,
+    section_ids: &[String],
+    rules: &[RestrictionWindowRule],
+    term: &str,
+    subj_code: &str,
+    subj_course_id: &str,
+) {
+    let Some(rule) = rules
+        .iter()
+        .find(|r| r.applies_to(term, subj_code, subj_course_id))
+    else {
+        return;
+    };
+
+    for section_id in section_ids {
+        notes.entry(section_id.clone()).or_default().window = Some(EnrollmentWindow {
+            opens_to_non_majors: rule.opens_to_non_majors.format("%Y-%m-%d").to_string(),
+            source: WindowSource::Config,
+        });
+    }
+}
+
+/// Finds the date that a note says the section opens to non-majors on.
+///
+/// # Parameters
+/// - `note`: The note.
+/// - `term`: The section's term. A date without a year is assumed to fall in the year that
+///   the term's enrollment happens in, which for a winter quarter's fall dates is the year
+///   before the term's.
+///
+/// # Returns
+/// The date, or nothing if the note doesn't give one.
+pub fn parse_non_major_date(note: &str, term: &str) -> Option<NaiveDate> {
+    let date = NON_MAJOR_DATE_REGEX.captures(note)?.get(1)?.as_str();
+    let term_year = 2000 + term.get(2..4)?.parse::<i32>().ok()?;
+
+    let (month, day, year) = match date.split_once('/') {
+        Some((month, rest)) => {
+            let (day, year) = match rest.split_once('/') {
+                Some((day, year)) => (day, Some(year.parse::<i32>().ok()?)),
+                None => (rest, None),
+            };
+            (
+                month.parse::<u32>().ok()?,
+                day.parse::<u32>().ok()?,
+                year.map(|y| if y < 100 { 2000 + y } else { y }),
+            )
+        }
+        None => {
+            let cleaned = date.replace([',', '.'], " ");
+            let mut parts = cleaned.split_whitespace();
+            let month = parts.next()?.get(..3)?.to_lowercase();
+            let month = [
+                "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+            ]
+            .iter()
+            .position(|m| *m == month)? as u32
+                + 1;
+            let day = parts.next()?.parse().ok()?;
+            (month, day, parts.next().and_then(|y| y.parse().ok()))
+        }
+    };
+
+    let year = year.unwrap_or(if term.starts_with("WI") && month >= 9 {
+        term_year - 1
+    } else {
+        term_year
+    });
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// Finds the enrollment restrictions that a note describes.
+///
+/// # Parameters
+/// - `note`: The note.
+///
+/// # Returns
+/// The restrictions, which are empty if the note doesn't describe any.
+pub fn parse_restrictions(note: &str) -> Vec<Restriction> {
+    let lower = note.to_lowercase();
+    let restricted = lower.contains("restricted to") || lower.contains(" only");
+    let mut restrictions = vec![];
+    let mut push = |kind, detail: Option<String>| restrictions.push(Restriction { kind, detail });
+
+    if [
+        "department approval",
+        "dept approval",
+        "department stamp",
+        "department consent",
+    ]
+    .iter()
+    .any(|p| lower.contains(p))
+    {
+        push(RestrictionKind::DepartmentApproval, None);
+    }
+
+    if [
+        "instructor approval",
+        "consent of instructor",
+        "instructor consent",
+    ]
+    .iter()
+    .any(|p| lower.contains(p))
+    {
+        push(RestrictionKind::InstructorApproval, None);
+    }
+
+    if restricted && lower.contains("major") {
+        let majors: Vec<_> = MAJOR_CODE_REGEX
+            .find_iter(note)
+            .map(|m| m.as_str())
+            .collect();
+        push(
+            RestrictionKind::MajorRestricted,
+            (!majors.is_empty()).then(|| majors.join(", ")),
+        );
+    }
+
+    if restricted && lower.contains("college") {
+        push(RestrictionKind::CollegeRestricted, None);
+    }
+
+    if lower.contains("graduate students only") || lower.contains("restricted to graduate") {
+        push(RestrictionKind::GraduateOnly, None);
+    } else if restricted {
+        let levels: Vec<_> = CLASS_LEVEL_REGEX
+            .captures_iter(note)
+            .map(|c| c[1].to_lowercase())
+            .collect();
+        if !levels.is_empty() {
+            push(RestrictionKind::ClassLevel, Some(levels.join(" and ")));
+        }
+    }
+
+    restrictions
+}
+
+/// Strips the HTML out of a note and collapses its whitespace.
+fn clean_note(note: &str) -> String {
+    let text = HTML_TAG_REGEX.replace_all(note, " ");
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .replace("&#39;", "'");
+    WHITESPACE_REGEX.replace_all(text.trim(), " ").into_owned()
+}
+
+/// Collects the notes that are strings in a JSON value.
+fn note_strings(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(note) => vec![clean_note(note)],
+        Value::Array(notes) => notes.iter().flat_map(note_strings).collect(),
+        _ => vec![],
+    }
+    .into_iter()
+    .filter(|note| !note.is_empty())
+    .collect()
+}
+
+/// Sorts a course's section notes, as WebReg returned them, by section. Notes are either
+/// keyed by the section's ID or code, or listed together, in which case a note that starts
+/// with a section's code (e.g., `A01: ...`) belongs to that section and any other note
+/// belongs to every section of the course.
+///
+/// # Parameters
+/// - `notes`: The notes, as JSON.
+/// - `sections`: The course's sections, as `(section ID, section code)` pairs.
+/// - `term`: The course's term.
+///
+/// # Returns
+/// Each section's notes, keyed by its section ID. Sections without notes are left out.
+pub fn notes_by_section(
+    notes: &Value,
+    sections: &[(String, String)],
+    term: &str,
+) -> HashMap<String, SectionNotes> {
+    let mut by_section: HashMap<&str, Vec<String>> = HashMap::new();
+    match notes {
+        Value::Object(keyed) => {
+            for (key, value) in keyed {
+                let key = key.trim();
+                if let Some((section_id, _)) = sections
+                    .iter()
+                    .find(|(id, code)| id == key || code.eq_ignore_ascii_case(key))
+                {
+                    by_section
+                        .entry(section_id)
+                        .or_default()
+                        .extend(note_strings(value));
+                }
+            }
+        }
+        _ => {
+            for note in note_strings(notes) {
+                let prefix = SECTION_PREFIX_REGEX
+                    .captures(&note)
+                    .map(|c| (c[1].to_owned(), c[0].len()));
+                match prefix {
+                    Some((code, len)) => {
+                        if let Some((section_id, _)) =
+                            sections.iter().find(|(_, c)| c.eq_ignore_ascii_case(&code))
+                        {
+                            by_section
+                                .entry(section_id)
+                                .or_default()
+                                .push(note[len..].to_owned());
+                        }
+                    }
+                    None => {
+                        for (section_id, _) in sections {
+                            by_section.entry(section_id).or_default().push(note.clone());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    by_section
+        .into_iter()
+        .filter(|(_, notes)| !notes.is_empty())
+        .map(|(section_id, notes)| (section_id.to_owned(), SectionNotes::new(notes, term)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_section_notes() {
+        assert_eq!(
+            vec![
+                Restriction {
+                    kind: RestrictionKind::DepartmentApproval,
+                    detail: None
+                },
+                Restriction {
+                    kind: RestrictionKind::MajorRestricted,
+                    detail: Some("CS25, CS26".to_owned())
+                },
+            ],
+            parse_restrictions("Department approval required. Restricted to CS25, CS26 majors.")
+        );
+        assert_eq!(
+            Some("seniors".to_owned()),
+            parse_restrictions("Restricted to seniors only")[0].detail
+        );
+        assert!(parse_restrictions("Meets in the lab on the first week.").is_empty());
+
+        let sections = [
+            ("111111".to_owned(), "A01".to_owned()),
+            ("222222".to_owned(), "A02".to_owned()),
+        ];
+        let notes = json!([
+            "<b>A02:</b> Graduate students only.",
+            "Bring a laptop&nbsp;to lecture.",
+        ]);
+        let by_section = notes_by_section(&notes, &sections, "FA24");
+        assert_eq!(
+            vec!["Bring a laptop to lecture."],
+            by_section["111111"].notes
+        );
+        assert!(by_section["111111"].restrictions.is_empty());
+        assert_eq!(
+            RestrictionKind::GraduateOnly,
+            by_section["222222"].restrictions[0].kind
+        );
+        assert_eq!(2, by_section["222222"].notes.len());
+
+        let keyed = json!({ "A01": ["Instructor approval required."] });
+        let by_section = notes_by_section(&keyed, &sections, "FA24");
+        assert_eq!(
+            RestrictionKind::InstructorApproval,
+            by_section["111111"].restrictions[0].kind
+        );
+        assert!(!by_section.contains_key("222222"));
+    }
+
+    #[test]
+    fn test_enrollment_windows() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d);
+        assert_eq!(
+            date(2024, 5, 24),
+            parse_non_major_date("Open to non-majors on 5/24.", "FA24")
+        );
+        assert_eq!(
+            date(2024, 11, 15),
+            parse_non_major_date("Restricted to CS25 majors until Nov. 15", "WI25")
+        );
+        assert_eq!(
+            date(2025, 2, 3),
+            parse_non_major_date("Non-majors may enroll beginning February 3, 2025.", "SP25")
+        );
+        assert_eq!(
+            None,
+            parse_non_major_date("Restricted to CS25 majors.", "FA24")
+        );
+
+        let notes = SectionNotes::new(
+            vec!["Open to all students after 5/20/24".to_owned()],
+            "FA24",
+        );
+        let window = notes.window.unwrap();
+        assert_eq!("2024-05-20", window.opens_to_non_majors);
+        assert!(window.is_pending(date(2024, 5, 19).unwrap()));
+        assert!(!window.is_pending(date(2024, 5, 20).unwrap()));
+
+        let rules = [RestrictionWindowRule {
+            term: "FA24".to_owned(),
+            department: "CSE".to_owned(),
+            courses: vec![],
+            opens_to_non_majors: date(2024, 6, 1).unwrap(),
+        }];
+        let mut by_section = HashMap::from([(
+            "111111".to_owned(),
+            SectionNotes::new(vec!["Open to non-majors on 5/24.".to_owned()], "FA24"),
+        )]);
+        let ids = ["111111".to_owned(), "222222".to_owned()];
+        apply_window_rules(&mut by_section, &ids, &rules, "FA24", "MATH", "MATH 20C");
+        assert_eq!(
+            WindowSource::Notes,
+            by_section["111111"].window.as_ref().unwrap().source
+        );
+        apply_window_rules(&mut by_section, &ids, &rules, "FA24", "CSE ", "CSE 100");
+        assert_eq!(
+            "2024-06-01",
+            by_section["222222"]
+                .window
+                .as_ref()
+                .unwrap()
+                .opens_to_non_majors
+        );
+        assert_eq!(
+            WindowSource::Config,
+            by_section["111111"].window.as_ref().unwrap().source
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Retrying WebReg requests that fail for transient reasons.
+//!
+//! WebReg often fails with a 5xx status or times out under load, and usually succeeds if the
+//! request is made again shortly after. Requests that only read from WebReg are retried on
+//! any such failure, with exponential backoff and jitter between attempts. Requests that
+//! change a student's enrollment or plans aren't idempotent (e.g., a timed-out add may have
+//! gone through), so they're only retried when WebReg says that it turned the request away
+//! without handling it (`429 Too Many Requests` or `503 Service Unavailable`).
+//!
+//! The number of retries made while serving an API request is counted and returned in the
+//! [`RETRY_COUNT_HEADER`] header, for debugging.
+
+use std::cell::Cell;
+use std::future::Future;
+use std::time::Duration;
+
+use tracing::warn;
+use webreg_types::retry::backoff;
+use webweg::types::WrapperError;
+
+use crate::error::WebregError;
+
+/// The number of times that a request is made, including the first, if not configured.
+pub const DEFAULT_RETRY_ATTEMPTS: u32 = 3;
+/// The delay before the first retry, if not configured. It doubles with each retry.
+pub const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_millis(200);
+/// The longest delay between retries, if not configured.
+pub const DEFAULT_RETRY_MAX_DELAY: Duration = Duration::from_secs(2);
+/// The header that the number of retries made while serving a request is returned in.
+pub const RETRY_COUNT_HEADER: &str = "X-WebReg-Retries";
+
+tokio::task_local! {
+    /// The number of retries made while serving the current API request.
+    static RETRIES: Cell<u32>;
+}
+
+/// Whether a request can safely be made more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Idempotency {
+    /// The request only reads from WebReg.
+    Idempotent,
+    /// The request changes something on WebReg, like the student's enrollment.
+    NonIdempotent,
+}
+
+/// How failed WebReg requests are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The number of times that a request is made, including the first.
+    pub max_attempts: u32,
+    /// The delay before the first retry.
+    pub base_delay: Duration,
+    /// The longest delay between retries.
+    pub max_delay: Duration,
+    /// Whether each delay is randomized, so that requests that failed together aren't all
+    /// retried together.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_RETRY_ATTEMPTS,
+            base_delay: DEFAULT_RETRY_BASE_DELAY,
+            max_delay: DEFAULT_RETRY_MAX_DELAY,
+            jitter: true,
+        }
+    }
+}
+
+/// Whether a failed request might succeed if it's made again.
+pub fn is_transient(err: &WrapperError) -> bool {
+    WebregError::from(err).is_retryable()
+}
+
+/// Whether WebReg turned a request away without handling it, so that it's safe to make
+/// again even if it isn't idempotent.
+fn was_turned_away(err: &WrapperError) -> bool {
+    matches!(
+        WebregError::from(err),
+        WebregError::RateLimited { .. } | WebregError::Unavailable { .. }
+    )
+}
+
+impl RetryPolicy {
+    /// Gets how long to wait before a retry.
+    ///
+    /// # Parameters
+    /// - `retry`: The retry, starting at 1.
+    ///
+    /// # Returns
+    /// The delay. With jitter, it's somewhere between half of the full delay and the full
+    /// delay.
+    pub fn delay(&self, retry: u32) -> Duration {
+        backoff(
+            self.base_delay,
+            self.max_delay,
+            retry.saturating_sub(1),
+            self.jitter,
+        )
+    }
+
+    /// Whether a failed request should be made again.
+    ///
+    /// # Parameters
+    /// - `err`: Why the request failed.
+    /// - `idempotency`: Whether the request can safely be made more than once.
+    /// - `attempt`: The number of times that the request has been made.
+    pub fn should_retry(&self, err: &WrapperError, idempotency: Idempotency, attempt: u32) -> bool {
+        attempt < self.max_attempts
+            && match idempotency {
+                Idempotency::Idempotent => is_transient(err),
+                Idempotency::NonIdempotent => was_turned_away(err),
+            }
+    }
+
+    /// Makes a WebReg request, retrying it according to this policy.
+    ///
+    /// # Parameters
+    /// - `idempotency`: Whether the request can safely be made more than once.
+    /// - `request`: Makes the request.
+    ///
+    /// # Returns
+    /// The result of the last attempt.
+    pub async fn run<T, F, Fut>(
+        &self,
+        idempotency: Idempotency,
+        mut request: F,
+    ) -> Result<T, WrapperError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, WrapperError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match request().await {
+                Err(e) if self.should_retry(&e, idempotency, attempt) => {
+                    let delay = self.delay(attempt);
+                    warn!("WebReg request failed (attempt {attempt}), retrying in {delay:?}: {e}");
+                    let _ = RETRIES.try_with(|r| r.set(r.get() + 1));
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Runs a future, counting the WebReg retries made while it runs.
+///
+/// # Returns
+/// The future's output, and the number of retries.
+pub async fn count_retries<F: Future>(future: F) -> (F::Output, u32) {
+    RETRIES
+        .scope(Cell::new(0), async {
+            let output = future.await;
+            (output, RETRIES.with(Cell::get))
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_retry_policy() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(3),
+            jitter: false,
+        };
+        assert_eq!(Duration::from_millis(2), policy.delay(2));
+        assert_eq!(Duration::from_millis(3), policy.delay(5));
+
+        // Reads are retried until they succeed, up to the limit
+        let mut calls = 0;
+        let (result, retries) = count_retries(policy.run(Idempotency::Idempotent, || {
+            calls += 1;
+            let fail = calls < 3;
+            async move {
+                if fail {
+                    Err(WrapperError::BadStatusCode(502, None))
+                } else {
+                    Ok(calls)
+                }
+            }
+        }))
+        .await;
+        assert_eq!(3, result.unwrap());
+        assert_eq!(2, retries);
+
+        // Changes aren't retried if they might have gone through, or if they were rejected
+        for (status, expected) in [(502, 1), (400, 1), (503, 3)] {
+            let mut calls = 0;
+            let result: Result<(), _> = policy
+                .run(Idempotency::NonIdempotent, || {
+                    calls += 1;
+                    async move { Err(WrapperError::BadStatusCode(status, None)) }
+                })
+                .await;
+            assert!(result.is_err());
+            assert_eq!(expected, calls);
+        }
+    }
+}
@@ -0,0 +1,391 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::Serialize;
+use webweg::types::{Meeting, MeetingDay};
+
+use crate::db::{CustomEvent, DbMeeting};
+use crate::schedule::{is_final, DateRange, SubSession};
+
+/// The days on which a meeting takes place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlotDays {
+    /// The meeting repeats every week on these days.
+    Weekly(Vec<Weekday>),
+    /// The meeting happens once, on this date (e.g., a final exam).
+    Once(NaiveDate),
+}
+
+/// A single meeting, reduced to what's needed to check for conflicts.
+#[derive(Debug, Clone)]
+pub struct MeetingSlot {
+    /// The meeting type (e.g., `LE`, `DI`, `FI`).
+    pub meeting_type: Option<String>,
+    /// The days on which the meeting happens.
+    pub days: SlotDays,
+    /// The start time, in minutes since midnight.
+    pub start: u32,
+    /// The end time, in minutes since midnight.
+    pub end: u32,
+    /// The dates during which the meeting happens, if known.
+    pub dates: Option<DateRange>,
+    /// Where the meeting happens (e.g., `CENTR 105`), if known.
+    pub location: Option<String>,
+}
+
+impl MeetingSlot {
+    /// Converts a meeting from the database into a slot.
+    ///
+    /// # Parameters
+    /// - `meeting`: The meeting.
+    ///
+    /// # Returns
+    /// The slot, or `None` if the meeting has no days or times (e.g., asynchronous
+    /// online meetings), since those can't conflict with anything.
+    pub fn from_db(meeting: &DbMeeting) -> Option<Self> {
+        let days = match meeting.meeting_days_type.as_str() {
+            "repeated" => {
+                let raw: Vec<String> =
+                    serde_json::from_str(meeting.meeting_days.as_deref()?).ok()?;
+                let days: Vec<Weekday> = raw.iter().filter_map(|d| parse_weekday(d)).collect();
+                if days.is_empty() {
+                    return None;
+                }
+
+                SlotDays::Weekly(days)
+            }
+            "onetime" => SlotDays::Once(
+                NaiveDate::parse_from_str(meeting.meeting_days.as_deref()?.trim(), "%Y-%m-%d")
+                    .ok()?,
+            ),
+            _ => return None,
+        };
+
+        let start = meeting.start_hr? as u32 * 60 + meeting.start_min? as u32;
+        let end = meeting.end_hr? as u32 * 60 + meeting.end_min? as u32;
+        if start == 0 && end == 0 {
+            return None;
+        }
+
+        let dates = match (&meeting.start_date, &meeting.end_date) {
+            (Some(start), Some(end)) => DateRange::parse(start, end),
+            _ => None,
+        };
+
+        let location = match (&meeting.building, &meeting.room) {
+            (Some(b), Some(r)) if !b.trim().is_empty() => {
+                Some(format!("{} {}", b.trim(), r.trim()).trim().to_owned())
+            }
+            _ => None,
+        };
+
+        Some(Self {
+            meeting_type: meeting.meeting_type.clone(),
+            days,
+            start,
+            end,
+            dates,
+            location,
+        })
+    }
+
+    /// Converts a meeting from WebReg (e.g., from a student's schedule) into a slot.
+    ///
+    /// # Parameters
+    /// - `meeting`: The meeting.
+    ///
+    /// # Returns
+    /// The slot, or `None` if the meeting has no days or times.
+    pub fn from_webreg(meeting: &Meeting) -> Option<Self> {
+        let days = match &meeting.meeting_days {
+            MeetingDay::Repeated(raw) => {
+                let days: Vec<Weekday> = raw.iter().filter_map(|d| parse_weekday(d)).collect();
+                if days.is_empty() {
+                    return None;
+                }
+
+                SlotDays::Weekly(days)
+            }
+            MeetingDay::OneTime(date) => {
+                SlotDays::Once(NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()?)
+            }
+            MeetingDay::None => return None,
+        };
+
+        let start = meeting.start_hr * 60 + meeting.start_min;
+        let end = meeting.end_hr * 60 + meeting.end_min;
+        if start == 0 && end == 0 {
+            return None;
+        }
+
+        let location = format!("{} {}", meeting.building.trim(), meeting.room.trim());
+        Some(Self {
+            meeting_type: Some(meeting.meeting_type.trim().to_owned()),
+            days,
+            start,
+            end,
+            dates: None,
+            location: Some(location.trim().to_owned()).filter(|l| !l.is_empty()),
+        })
+    }
+
+    /// Converts one of the student's custom events into a slot.
+    ///
+    /// # Parameters
+    /// - `event`: The event.
+    ///
+    /// # Returns
+    /// The slot, or `None` if the event doesn't happen on any valid day.
+    pub fn from_event(event: &CustomEvent) -> Option<Self> {
+        let days: Vec<Weekday> = event.days.iter().filter_map(|d| parse_weekday(d)).collect();
+        if days.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            meeting_type: None,
+            days: SlotDays::Weekly(days),
+            start: (event.start_hr * 60 + event.start_min) as u32,
+            end: (event.end_hr * 60 + event.end_min) as u32,
+            dates: None,
+            location: event.location.clone().filter(|l| !l.trim().is_empty()),
+        })
+    }
+
+    /// Whether the two meetings take place at the same time on at least one day.
+    ///
+    /// # Parameters
+    /// - `other`: The other meeting.
+    /// - `sessions`: The sub-sessions of this meeting's and the other meeting's terms,
+    ///   used when a meeting's dates aren't known.
+    ///
+    /// # Returns
+    /// `true` if the meetings overlap.
+    pub fn conflicts_with(&self, other: &MeetingSlot, sessions: (SubSession, SubSession)) -> bool {
+        if !(self.start < other.end && other.start < self.end) {
+            return false;
+        }
+
+        match (&self.days, &other.days) {
+            (SlotDays::Once(a), SlotDays::Once(b)) => a == b,
+            (SlotDays::Once(date), SlotDays::Weekly(days))
+            | (SlotDays::Weekly(days), SlotDays::Once(date)) => {
+                let weekly_dates = if matches!(self.days, SlotDays::Weekly(_)) {
+                    self.dates
+                } else {
+                    other.dates
+                };
+
+                let in_range = match weekly_dates {
+                    Some(r) => r.contains(*date),
+                    None => true,
+                };
+
+                days.contains(&date.weekday()) && in_range
+            }
+            (SlotDays::Weekly(a), SlotDays::Weekly(b)) => {
+                if !a.iter().any(|d| b.contains(d)) {
+                    return false;
+                }
+
+                match (self.dates, other.dates) {
+                    (Some(x), Some(y)) => x.overlaps(&y),
+                    _ => sessions.0.may_overlap(&sessions.1),
+                }
+            }
+        }
+    }
+}
+
+/// A section in a student's schedule.
+#[derive(Debug, Clone)]
+pub struct ScheduledSection {
+    /// The term that the section is offered in.
+    pub term: String,
+    /// The section ID.
+    pub section_id: String,
+    /// A human-readable name for the section (e.g., `CSE 100 (A00)`).
+    pub title: String,
+    /// The sub-session of the section's term.
+    pub sub_session: SubSession,
+    /// The section's meetings.
+    pub slots: Vec<MeetingSlot>,
+}
+
+/// A conflict between two sections.
+#[derive(Debug, Clone, Serialize)]
+pub struct Conflict {
+    pub first_term: String,
+    pub first_section_id: String,
+    pub first_meeting_type: Option<String>,
+    pub second_term: String,
+    pub second_section_id: String,
+    pub second_meeting_type: Option<String>,
+    /// Whether either meeting is a final exam
+    pub final_exam: bool,
+}
+
+/// Finds every pair of meetings, across different sections, that conflict with each other.
+///
+/// # Parameters
+/// - `sections`: The sections to check.
+///
+/// # Returns
+/// The conflicts.
+pub fn find_conflicts(sections: &[ScheduledSection]) -> Vec<Conflict> {
+    let mut conflicts = vec![];
+    for (i, a) in sections.iter().enumerate() {
+        for b in &sections[i + 1..] {
+            for slot_a in &a.slots {
+                for slot_b in &b.slots {
+                    if slot_a.conflicts_with(slot_b, (a.sub_session, b.sub_session)) {
+                        conflicts.push(Conflict {
+                            first_term: a.term.clone(),
+                            first_section_id: a.section_id.clone(),
+                            first_meeting_type: slot_a.meeting_type.clone(),
+                            second_term: b.term.clone(),
+                            second_section_id: b.section_id.clone(),
+                            second_meeting_type: slot_b.meeting_type.clone(),
+                            final_exam: is_final(slot_a) || is_final(slot_b),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    conflicts
+}
+
+/// Parses a WebReg day abbreviation (e.g., `Tu`) or a full day name (e.g., `Tuesday`).
+pub fn parse_weekday(day: &str) -> Option<Weekday> {
+    match day.trim().to_lowercase().as_str() {
+        "m" | "monday" => Some(Weekday::Mon),
+        "tu" | "tuesday" => Some(Weekday::Tue),
+        "w" | "wednesday" => Some(Weekday::Wed),
+        "th" | "thursday" => Some(Weekday::Thu),
+        "f" | "friday" => Some(Weekday::Fri),
+        "sa" | "saturday" => Some(Weekday::Sat),
+        "su" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// The WebReg abbreviation for a day of the week (e.g., `Tu`).
+pub fn day_abbreviation(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "M",
+        Weekday::Tue => "Tu",
+        Weekday::Wed => "W",
+        Weekday::Thu => "Th",
+        Weekday::Fri => "F",
+        Weekday::Sat => "Sa",
+        Weekday::Sun => "Su",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weekly(days: &[Weekday], start: u32, end: u32) -> MeetingSlot {
+        MeetingSlot {
+            meeting_type: Some("LE".to_string()),
+            days: SlotDays::Weekly(days.to_vec()),
+            start,
+            end,
+            dates: None,
+            location: None,
+        }
+    }
+
+    fn section(term: &str, id: &str, slots: Vec<MeetingSlot>) -> ScheduledSection {
+        ScheduledSection {
+            term: term.to_string(),
+            section_id: id.to_string(),
+            title: id.to_string(),
+            sub_session: SubSession::from_term(term),
+            slots,
+        }
+    }
+
+    #[test]
+    fn test_same_time_conflicts() {
+        let conflicts = find_conflicts(&[
+            section("FA24", "1", vec![weekly(&[Weekday::Mon], 600, 650)]),
+            section("FA24", "2", vec![weekly(&[Weekday::Mon], 630, 700)]),
+        ]);
+        assert_eq!(1, conflicts.len());
+        assert!(!conflicts[0].final_exam);
+    }
+
+    #[test]
+    fn test_summer_sessions_do_not_conflict() {
+        let conflicts = find_conflicts(&[
+            section("S124", "1", vec![weekly(&[Weekday::Mon], 600, 650)]),
+            section("S224", "2", vec![weekly(&[Weekday::Mon], 600, 650)]),
+            section("S324", "3", vec![weekly(&[Weekday::Mon], 600, 650)]),
+        ]);
+
+        // S3 overlaps with both S1 and S2, but S1 and S2 don't overlap with each other.
+        assert_eq!(2, conflicts.len());
+        assert!(conflicts
+            .iter()
+            .all(|c| c.first_term == "S324" || c.second_term == "S324"));
+    }
+
+    #[test]
+    fn test_known_dates_take_precedence() {
+        let mut a = weekly(&[Weekday::Tue], 600, 650);
+        let mut b = weekly(&[Weekday::Tue], 600, 650);
+        a.dates = DateRange::parse("2024-09-26", "2024-10-31");
+        b.dates = DateRange::parse("2024-11-01", "2024-12-06");
+        assert!(!a.conflicts_with(&b, (SubSession::Regular, SubSession::Regular)));
+
+        let exam = MeetingSlot {
+            meeting_type: Some("FI".to_string()),
+            days: SlotDays::Once(NaiveDate::from_ymd_opt(2024, 10, 1).unwrap()),
+            start: 600,
+            end: 780,
+            dates: None,
+            location: None,
+        };
+        assert!(exam.conflicts_with(&a, (SubSession::Regular, SubSession::Regular)));
+        assert!(!exam.conflicts_with(&b, (SubSession::Regular, SubSession::Regular)));
+
+        let conflicts = find_conflicts(&[
+            section("FA24", "1", vec![a]),
+            section("FA24", "2", vec![exam]),
+        ]);
+        assert!(conflicts[0].final_exam);
+    }
+
+    #[test]
+    fn test_event_conflicts_with_section() {
+        let event = CustomEvent {
+            event_id: 1,
+            term: "FA24".to_string(),
+            name: "Work".to_string(),
+            location: Some(" ".to_string()),
+            days: vec!["Monday".to_string(), "W".to_string(), "Xy".to_string()],
+            start_hr: 10,
+            start_min: 15,
+            end_hr: 12,
+            end_min: 0,
+            webreg_timestamp: None,
+            sync_state: "pending_push".to_string(),
+            updated_at: "2024-09-01 00:00:00".to_string(),
+        };
+
+        let slot = MeetingSlot::from_event(&event).unwrap();
+        assert_eq!(
+            SlotDays::Weekly(vec![Weekday::Mon, Weekday::Wed]),
+            slot.days
+        );
+        assert_eq!(None, slot.location);
+
+        let conflicts = find_conflicts(&[
+            section("FA24", "event-1", vec![slot]),
+            section("FA24", "2", vec![weekly(&[Weekday::Wed], 600, 650)]),
+        ]);
+        assert_eq!(1, conflicts.len());
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::schedule::DateRange;
+
+/// The dates of instruction for each meeting of a course, as reported by WebReg. Most
+/// meetings run for the whole term, but some courses only meet for part of it.
+#[derive(Debug, Default)]
+pub struct MeetingDates {
+    /// Date ranges keyed by section ID and meeting type.
+    dates: HashMap<(String, String), DateRange>,
+}
+
+impl MeetingDates {
+    /// Extracts the meeting dates from a raw course info response.
+    ///
+    /// # Parameters
+    /// - `body`: The raw response body.
+    ///
+    /// # Returns
+    /// The meeting dates. Meetings without dates are left out.
+    pub fn from_raw_course_info(body: &str) -> Self {
+        let Ok(Value::Array(rows)) = serde_json::from_str::<Value>(body) else {
+            return Self::default();
+        };
+
+        let dates = rows
+            .iter()
+            .filter_map(|row| {
+                let section_id = json_to_string(&row["SECTION_NUMBER"])?;
+                let meeting_type = json_to_string(&row["FK_CDI_INSTR_TYPE"])?;
+                let start = json_to_string(&row["SECTION_START_DATE"])?;
+                let end = json_to_string(&row["SECTION_END_DATE"])?;
+
+                // Dates sometimes come with a time attached, which we don't need.
+                let range = DateRange::parse(start.get(..10)?, end.get(..10)?)?;
+                Some(((section_id, meeting_type), range))
+            })
+            .collect();
+
+        Self { dates }
+    }
+
+    /// Gets the dates for a meeting.
+    ///
+    /// # Parameters
+    /// - `section_id`: The section ID.
+    /// - `meeting_type`: The meeting type (e.g., `LE`).
+    ///
+    /// # Returns
+    /// The dates, if known.
+    pub fn get(&self, section_id: &str, meeting_type: &str) -> Option<DateRange> {
+        self.dates
+            .get(&(section_id.trim().to_owned(), meeting_type.trim().to_owned()))
+            .copied()
+    }
+}
+
+/// Converts a JSON string or number into a trimmed string.
+fn json_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_owned()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_term_dates() {
+        let body = r#"[
+            {"SECTION_NUMBER": "123456", "FK_CDI_INSTR_TYPE": "LE",
+             "SECTION_START_DATE": "2024-09-26", "SECTION_END_DATE": "2024-10-25"},
+            {"SECTION_NUMBER": 123457, "FK_CDI_INSTR_TYPE": "DI ",
+             "SECTION_START_DATE": "2024-10-28 00:00:00", "SECTION_END_DATE": "2024-12-06"},
+            {"SECTION_NUMBER": "123458", "FK_CDI_INSTR_TYPE": "LE"}
+        ]"#;
+
+        let dates = MeetingDates::from_raw_course_info(body);
+        assert_eq!(
+            DateRange::parse("2024-09-26", "2024-10-25"),
+            dates.get("123456", "LE")
+        );
+        assert_eq!(
+            DateRange::parse("2024-10-28", "2024-12-06"),
+            dates.get("123457", "DI")
+        );
+        assert_eq!(None, dates.get("123458", "LE"));
+    }
+}
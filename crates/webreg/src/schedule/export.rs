@@ -0,0 +1,416 @@
+//! Formats a term's schedule data for clients: as JSON, one object per section, and as
+//! CSV or Parquet, with one row per meeting, for use in spreadsheets and data-analysis tools.
+
+use std::fmt::Write;
+use std::sync::Arc;
+
+use parquet::basic::Compression;
+use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+use parquet::errors::Result as ParquetResult;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::db::{DbMeeting, DbSection, ScheduleExportRow};
+use crate::restrictions::SectionNotes;
+
+/// The first line of a CSV export.
+pub const CSV_HEADER: &str = "subj_code,course_code,section_id,section_code,\
+                              section_start_date,section_end_date,total_seats,enrolled_ct,\
+                              meeting_type,meeting_days_type,meeting_days,start_time,end_time,\
+                              building,room,instructors,meeting_start_date,meeting_end_date\n";
+
+/// Formats a section and its meetings as JSON, as returned by `/schedule_data`.
+///
+/// # Parameters
+/// - `section`: The section.
+/// - `meetings`: The section's meetings.
+/// - `notes`: The section's notes, the enrollment restrictions found in them, and when the
+///   section opens to non-majors, if it has any.
+///
+/// # Returns
+/// The section's JSON object.
+pub fn section_json(
+    section: DbSection,
+    meetings: Vec<DbMeeting>,
+    notes: Option<SectionNotes>,
+) -> Value {
+    let notes = notes.unwrap_or_default();
+    json!({
+        "section_id": section.section_id,
+        "section_code": section.section_code,
+        "start_date": section.start_date,
+        "end_date": section.end_date,
+        "cancelled_at": section.cancelled_at,
+        "notes": notes.notes,
+        "restrictions": notes.restrictions,
+        "enrollment_window": notes.window,
+        "meetings": meetings.into_iter().map(|m| {
+            json!({
+                "type": m.meeting_type,
+                "days_type": m.meeting_days_type,
+                "days": m.meeting_days,
+                "start_hr": m.start_hr,
+                "start_min": m.start_min,
+                "end_hr": m.end_hr,
+                "end_min": m.end_min,
+                "building": m.building,
+                "room": m.room,
+                "instructors": m.instructors,
+                "start_date": m.start_date,
+                "end_date": m.end_date,
+            })
+        }).collect::<Vec<_>>()
+    })
+}
+
+/// Formats a row as a line of CSV. Lists (the meeting's days and instructors) are joined
+/// with `;`.
+///
+/// # Parameters
+/// - `row`: The row.
+///
+/// # Returns
+/// The line, ending in a newline.
+pub fn csv_row(row: &ScheduleExportRow) -> String {
+    let time = |hr, min| clock_time(hr, min).unwrap_or_default();
+    let fields = [
+        row.subj_code.clone(),
+        row.course_code.clone(),
+        row.section_id.clone(),
+        row.section_code.clone(),
+        row.section_start_date.clone().unwrap_or_default(),
+        row.section_end_date.clone().unwrap_or_default(),
+        row.total_seats.map(|n| n.to_string()).unwrap_or_default(),
+        row.enrolled_ct.map(|n| n.to_string()).unwrap_or_default(),
+        row.meeting_type.clone().unwrap_or_default(),
+        row.meeting_days_type.clone().unwrap_or_default(),
+        json_list(row.meeting_days.as_deref()),
+        time(row.start_hr, row.start_min),
+        time(row.end_hr, row.end_min),
+        row.building.clone().unwrap_or_default(),
+        row.room.clone().unwrap_or_default(),
+        json_list(row.instructors.as_deref()),
+        row.meeting_start_date.clone().unwrap_or_default(),
+        row.meeting_end_date.clone().unwrap_or_default(),
+    ];
+
+    let mut line = String::new();
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            line.push(',');
+        }
+
+        if field.contains([',', '"', '\n', '\r']) {
+            let _ = write!(line, "\"{}\"", field.replace('"', "\"\""));
+        } else {
+            line.push_str(field);
+        }
+    }
+
+    line.push('\n');
+    line
+}
+
+/// Formats a term's rows as canonical CSV, along with a hash that identifies the data.
+///
+/// The lines are sorted, so the output only depends on what was scraped, not on the order
+/// it was scraped or stored in. Two exports of the same data are byte-for-byte identical,
+/// and a diff between two exports only shows what changed.
+///
+/// # Parameters
+/// - `rows`: The rows, in any order.
+///
+/// # Returns
+/// The CSV (with its header) and its SHA-256 hash, as hex.
+pub fn canonical_csv(rows: &[ScheduleExportRow]) -> (String, String) {
+    let mut lines: Vec<String> = rows.iter().map(csv_row).collect();
+    lines.sort_unstable();
+
+    let csv = std::iter::once(CSV_HEADER.to_owned())
+        .chain(lines)
+        .collect::<String>();
+    let hash = format!("{:x}", Sha256::digest(csv.as_bytes()));
+    (csv, hash)
+}
+
+/// A column of a Parquet export, and how its value is read from a row.
+enum ParquetColumn {
+    Text(fn(&ScheduleExportRow) -> Option<String>),
+    Int(fn(&ScheduleExportRow) -> Option<i64>),
+}
+
+/// The columns of a Parquet export, which are the same as a CSV export's. Lists are joined
+/// with `;`, as in CSV, but missing values are null rather than empty, and seat counts are
+/// integers.
+fn parquet_columns() -> [(&'static str, ParquetColumn); 18] {
+    use ParquetColumn::{Int, Text};
+
+    [
+        ("subj_code", Text(|r| Some(r.subj_code.clone()))),
+        ("course_code", Text(|r| Some(r.course_code.clone()))),
+        ("section_id", Text(|r| Some(r.section_id.clone()))),
+        ("section_code", Text(|r| Some(r.section_code.clone()))),
+        ("section_start_date", Text(|r| r.section_start_date.clone())),
+        ("section_end_date", Text(|r| r.section_end_date.clone())),
+        ("total_seats", Int(|r| r.total_seats)),
+        ("enrolled_ct", Int(|r| r.enrolled_ct)),
+        ("meeting_type", Text(|r| r.meeting_type.clone())),
+        ("meeting_days_type", Text(|r| r.meeting_days_type.clone())),
+        (
+            "meeting_days",
+            Text(|r| r.meeting_days.as_deref().map(|d| json_list(Some(d)))),
+        ),
+        ("start_time", Text(|r| clock_time(r.start_hr, r.start_min))),
+        ("end_time", Text(|r| clock_time(r.end_hr, r.end_min))),
+        ("building", Text(|r| r.building.clone())),
+        ("room", Text(|r| r.room.clone())),
+        (
+            "instructors",
+            Text(|r| r.instructors.as_deref().map(|i| json_list(Some(i)))),
+        ),
+        ("meeting_start_date", Text(|r| r.meeting_start_date.clone())),
+        ("meeting_end_date", Text(|r| r.meeting_end_date.clone())),
+    ]
+}
+
+/// Writes a Parquet export a row group at a time, so that each group can be sent as soon as
+/// it's written rather than holding the whole file in memory.
+pub struct ParquetExport {
+    writer: SerializedFileWriter<Vec<u8>>,
+}
+
+impl ParquetExport {
+    /// Starts a new export.
+    pub fn new() -> ParquetResult<Self> {
+        let fields: String = parquet_columns()
+            .iter()
+            .map(|(name, column)| match column {
+                ParquetColumn::Text(_) => format!("OPTIONAL BYTE_ARRAY {name} (UTF8); "),
+                ParquetColumn::Int(_) => format!("OPTIONAL INT64 {name}; "),
+            })
+            .collect();
+        let schema = parse_message_type(&format!("message schedule_data {{ {fields}}}"))?;
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+
+        Ok(Self {
+            writer: SerializedFileWriter::new(vec![], Arc::new(schema), Arc::new(props))?,
+        })
+    }
+
+    /// Writes rows as a row group.
+    ///
+    /// # Parameters
+    /// - `rows`: The rows.
+    ///
+    /// # Returns
+    /// The bytes of the file that are ready to be sent. Some of what was written may only
+    /// be returned by a later call, or by [`ParquetExport::finish`].
+    pub fn write_rows(&mut self, rows: &[ScheduleExportRow]) -> ParquetResult<Vec<u8>> {
+        let mut group = self.writer.next_row_group()?;
+        for (_, column) in parquet_columns() {
+            let Some(mut writer) = group.next_column()? else {
+                break;
+            };
+
+            match column {
+                ParquetColumn::Text(value) => {
+                    let values: Vec<_> = rows.iter().map(value).collect();
+                    let levels: Vec<i16> = values.iter().map(|v| v.is_some() as i16).collect();
+                    let values: Vec<ByteArray> = values
+                        .iter()
+                        .flatten()
+                        .map(|v| ByteArray::from(v.as_str()))
+                        .collect();
+                    writer
+                        .typed::<ByteArrayType>()
+                        .write_batch(&values, Some(&levels), None)?;
+                }
+                ParquetColumn::Int(value) => {
+                    let values: Vec<_> = rows.iter().map(value).collect();
+                    let levels: Vec<i16> = values.iter().map(|v| v.is_some() as i16).collect();
+                    let values: Vec<i64> = values.into_iter().flatten().collect();
+                    writer
+                        .typed::<Int64Type>()
+                        .write_batch(&values, Some(&levels), None)?;
+                }
+            }
+            writer.close()?;
+        }
+        group.close()?;
+
+        Ok(std::mem::take(self.writer.inner_mut()))
+    }
+
+    /// Finishes the export.
+    ///
+    /// # Returns
+    /// The rest of the file's bytes, including its footer.
+    pub fn finish(self) -> ParquetResult<Vec<u8>> {
+        self.writer.into_inner()
+    }
+}
+
+/// Formats a term's rows as canonical Parquet, along with a hash that identifies the data.
+/// The rows are sorted as in [`canonical_csv`], and written as a single row group.
+///
+/// # Parameters
+/// - `rows`: The rows, in any order.
+///
+/// # Returns
+/// The file and its SHA-256 hash, as hex.
+pub fn canonical_parquet(rows: &[ScheduleExportRow]) -> ParquetResult<(Vec<u8>, String)> {
+    let mut rows = rows.to_vec();
+    rows.sort_by_cached_key(csv_row);
+
+    let mut export = ParquetExport::new()?;
+    let mut file = export.write_rows(&rows)?;
+    file.extend(export.finish()?);
+    let hash = format!("{:x}", Sha256::digest(&file));
+    Ok((file, hash))
+}
+
+/// Formats a time of day as `HH:MM`, if both parts are known.
+fn clock_time(hr: Option<i32>, min: Option<i32>) -> Option<String> {
+    match (hr, min) {
+        (Some(hr), Some(min)) => Some(format!("{hr:02}:{min:02}")),
+        _ => None,
+    }
+}
+
+/// Joins a list stored as a JSON array with `;`. Values that aren't JSON arrays (e.g., the
+/// date of a one-time meeting) are kept as they are.
+fn json_list(value: Option<&str>) -> String {
+    let Some(value) = value else {
+        return String::new();
+    };
+
+    match serde_json::from_str::<Vec<String>>(value) {
+        Ok(items) => items.join(";"),
+        Err(_) => value.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_row() {
+        let row = ScheduleExportRow {
+            subj_code: "CSE".to_owned(),
+            course_code: "100".to_owned(),
+            section_id: "123456".to_owned(),
+            section_code: "A01".to_owned(),
+            total_seats: Some(50),
+            meeting_type: Some("LE".to_owned()),
+            meeting_days_type: Some("repeated".to_owned()),
+            meeting_days: Some(r#"["M","W"]"#.to_owned()),
+            start_hr: Some(9),
+            start_min: Some(0),
+            end_hr: Some(9),
+            end_min: Some(50),
+            instructors: Some(r#"["Doe, Jane","Smith, \"Sam\""]"#.to_owned()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            "CSE,100,123456,A01,,,50,,LE,repeated,M;W,09:00,09:50,,,\
+             \"Doe, Jane;Smith, \"\"Sam\"\"\",,\n",
+            csv_row(&row)
+        );
+        assert_eq!(
+            CSV_HEADER.matches(',').count(),
+            csv_row(&ScheduleExportRow::default()).matches(',').count()
+        );
+    }
+
+    #[test]
+    fn test_canonical_csv() {
+        let row = |section_id_pk: i64, course_code: &str, section_code: &str| ScheduleExportRow {
+            section_id_pk,
+            subj_code: "CSE".to_owned(),
+            course_code: course_code.to_owned(),
+            section_code: section_code.to_owned(),
+            ..Default::default()
+        };
+
+        // The same data, stored in a different order
+        let first = [
+            row(1, "100", "A01"),
+            row(2, "11", "A00"),
+            row(3, "100", "A00"),
+        ];
+        let second = [
+            row(7, "100", "A00"),
+            row(8, "100", "A01"),
+            row(9, "11", "A00"),
+        ];
+
+        let (csv, hash) = canonical_csv(&first);
+        assert_eq!((csv.clone(), hash.clone()), canonical_csv(&second));
+        assert_eq!(64, hash.len());
+        assert_eq!(
+            vec!["CSE,100,,A00", "CSE,100,,A01", "CSE,11,,A00"],
+            csv.lines()
+                .skip(1)
+                .map(|l| l.trim_end_matches(','))
+                .collect::<Vec<_>>()
+        );
+
+        let (_, changed) = canonical_csv(&first[..2]);
+        assert_ne!(hash, changed);
+    }
+
+    #[test]
+    fn test_parquet_export() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::{Field, RowAccessor};
+
+        let row = |section_id_pk: i64, section_code: &str| ScheduleExportRow {
+            section_id_pk,
+            subj_code: "CSE".to_owned(),
+            course_code: "100".to_owned(),
+            section_code: section_code.to_owned(),
+            total_seats: Some(50),
+            meeting_days: Some(r#"["M","W"]"#.to_owned()),
+            start_hr: Some(9),
+            start_min: Some(0),
+            ..Default::default()
+        };
+
+        // Each page is its own row group, sent as soon as it's written
+        let mut export = ParquetExport::new().unwrap();
+        let mut file = export.write_rows(&[row(1, "A00"), row(1, "A01")]).unwrap();
+        file.extend(export.write_rows(&[row(2, "B00")]).unwrap());
+        file.extend(export.finish().unwrap());
+
+        let reader = SerializedFileReader::new(axum::body::Bytes::from(file)).unwrap();
+        assert_eq!(2, reader.metadata().num_row_groups());
+        let rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(3, rows.len());
+        assert_eq!("B00", rows[2].get_string(3).unwrap());
+        assert_eq!(50, rows[0].get_long(6).unwrap());
+        assert_eq!("M;W", rows[0].get_string(10).unwrap());
+        assert_eq!("09:00", rows[0].get_string(11).unwrap());
+        // Missing values are null, rather than empty
+        let (name, enrolled_ct) = rows[0].get_column_iter().nth(7).unwrap();
+        assert_eq!(("enrolled_ct", &Field::Null), (name.as_str(), enrolled_ct));
+        assert_eq!(&Field::Null, rows[0].get_column_iter().nth(12).unwrap().1);
+
+        // As with CSV, a canonical export doesn't depend on the order of the rows
+        let (first, hash) = canonical_parquet(&[row(1, "A01"), row(2, "A00")]).unwrap();
+        let (second, same) = canonical_parquet(&[row(7, "A00"), row(8, "A01")]).unwrap();
+        assert_eq!((first, hash.clone()), (second, same));
+        let (_, changed) = canonical_parquet(&[row(1, "A01")]).unwrap();
+        assert_ne!(hash, changed);
+    }
+}
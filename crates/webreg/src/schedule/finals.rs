@@ -0,0 +1,116 @@
+//! Final and midterm exams, which WebReg lists as one-time meetings of a section (`FI` and
+//! `MI`) alongside its weekly meetings.
+
+use serde::Serialize;
+
+use super::conflict::{MeetingSlot, SlotDays};
+
+/// The kind of exam that a meeting is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExamKind {
+    Final,
+    Midterm,
+}
+
+impl ExamKind {
+    /// Classifies a meeting by its type.
+    ///
+    /// # Parameters
+    /// - `meeting_type`: The meeting type (e.g., `FI`).
+    ///
+    /// # Returns
+    /// The kind of exam, or `None` if the meeting isn't an exam.
+    pub fn from_meeting_type(meeting_type: &str) -> Option<Self> {
+        match meeting_type.trim() {
+            "FI" => Some(ExamKind::Final),
+            "MI" => Some(ExamKind::Midterm),
+            _ => None,
+        }
+    }
+
+    /// The name that the kind is stored under in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExamKind::Final => "final",
+            ExamKind::Midterm => "midterm",
+        }
+    }
+}
+
+/// An exam of a section.
+#[derive(Debug, Clone, Serialize)]
+pub struct Exam {
+    pub kind: ExamKind,
+    /// The day of the exam (`YYYY-MM-DD`), if WebReg gives one.
+    pub date: Option<String>,
+    /// The start time, in `HH:MM` format.
+    pub start: String,
+    /// The end time, in `HH:MM` format.
+    pub end: String,
+    /// Where the exam happens (e.g., `CENTR 105`), if known.
+    pub location: Option<String>,
+}
+
+impl Exam {
+    /// Converts a meeting into an exam, if it's one.
+    ///
+    /// # Parameters
+    /// - `slot`: The meeting.
+    ///
+    /// # Returns
+    /// The exam, or `None` if the meeting isn't an exam.
+    pub fn from_slot(slot: &MeetingSlot) -> Option<Self> {
+        let kind = ExamKind::from_meeting_type(slot.meeting_type.as_deref()?)?;
+        let time = |minutes: u32| format!("{:02}:{:02}", minutes / 60, minutes % 60);
+        Some(Self {
+            kind,
+            date: match &slot.days {
+                SlotDays::Once(date) => Some(date.format("%Y-%m-%d").to_string()),
+                SlotDays::Weekly(_) => None,
+            },
+            start: time(slot.start),
+            end: time(slot.end),
+            location: slot.location.clone(),
+        })
+    }
+}
+
+/// Whether a meeting is a final exam.
+pub fn is_final(slot: &MeetingSlot) -> bool {
+    slot.meeting_type
+        .as_deref()
+        .and_then(ExamKind::from_meeting_type)
+        == Some(ExamKind::Final)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_exam_from_slot() {
+        let mut slot = MeetingSlot {
+            meeting_type: Some("FI".to_string()),
+            days: SlotDays::Once(NaiveDate::from_ymd_opt(2024, 12, 10).unwrap()),
+            start: 8 * 60,
+            end: 11 * 60,
+            dates: None,
+            location: Some("CENTR 105".to_string()),
+        };
+
+        let exam = Exam::from_slot(&slot).unwrap();
+        assert_eq!(ExamKind::Final, exam.kind);
+        assert_eq!(Some("2024-12-10"), exam.date.as_deref());
+        assert_eq!(("08:00", "11:00"), (exam.start.as_str(), exam.end.as_str()));
+        assert!(is_final(&slot));
+
+        slot.meeting_type = Some("MI".to_string());
+        assert_eq!(ExamKind::Midterm, Exam::from_slot(&slot).unwrap().kind);
+        assert!(!is_final(&slot));
+
+        slot.meeting_type = Some("LE".to_string());
+        assert!(Exam::from_slot(&slot).is_none());
+    }
+}
@@ -0,0 +1,113 @@
+use serde::Serialize;
+
+use crate::db::normalize_course_code;
+use crate::schedule::ScheduledSection;
+
+/// Why adding a section to a student's schedule was refused.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum AddConflict {
+    /// The student is already enrolled in (or waitlisted for) a section of the same course.
+    SameCourse { section_id: String, title: String },
+    /// One of the new section's meetings overlaps a meeting of a section that the student is
+    /// already enrolled in (or waitlisted for).
+    TimeConflict {
+        section_id: String,
+        title: String,
+        meeting_type: Option<String>,
+    },
+}
+
+/// Checks whether a section can be added to a student's schedule without duplicating a
+/// course or overlapping a section that they're already in.
+///
+/// # Parameters
+/// - `course`: The course that the new section belongs to (e.g., `CSE 100`).
+/// - `section`: The new section.
+/// - `schedule`: The sections that the student is enrolled in or waitlisted for, each along
+///   with its course.
+///
+/// # Returns
+/// The first problem found, if any.
+pub fn check_add(
+    course: &str,
+    section: &ScheduledSection,
+    schedule: &[(String, ScheduledSection)],
+) -> Option<AddConflict> {
+    let course = normalize_course_code(course);
+    if let Some((_, existing)) = schedule
+        .iter()
+        .find(|(c, _)| normalize_course_code(c) == course)
+    {
+        return Some(AddConflict::SameCourse {
+            section_id: existing.section_id.clone(),
+            title: existing.title.clone(),
+        });
+    }
+
+    schedule.iter().find_map(|(_, existing)| {
+        section.slots.iter().find_map(|slot| {
+            existing
+                .slots
+                .iter()
+                .find(|other| {
+                    slot.conflicts_with(other, (section.sub_session, existing.sub_session))
+                })
+                .map(|other| AddConflict::TimeConflict {
+                    section_id: existing.section_id.clone(),
+                    title: existing.title.clone(),
+                    meeting_type: other.meeting_type.clone(),
+                })
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::{MeetingSlot, SlotDays, SubSession};
+    use chrono::Weekday;
+
+    fn section(id: &str, title: &str, day: Weekday, start: u32, end: u32) -> ScheduledSection {
+        ScheduledSection {
+            term: "FA24".to_string(),
+            section_id: id.to_string(),
+            title: title.to_string(),
+            sub_session: SubSession::Regular,
+            slots: vec![MeetingSlot {
+                meeting_type: Some("LE".to_string()),
+                days: SlotDays::Weekly(vec![day]),
+                start,
+                end,
+                dates: None,
+                location: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_check_add() {
+        let schedule = vec![(
+            "CSE 100".to_string(),
+            section("1", "CSE 100 (A00)", Weekday::Mon, 600, 650),
+        )];
+
+        let other_section = section("2", "CSE 100 (B00)", Weekday::Fri, 600, 650);
+        assert_eq!(
+            Some(AddConflict::SameCourse {
+                section_id: "1".to_string(),
+                title: "CSE 100 (A00)".to_string(),
+            }),
+            check_add("cse  100", &other_section, &schedule)
+        );
+
+        let overlapping = section("3", "MATH 20C (A00)", Weekday::Mon, 630, 680);
+        assert!(matches!(
+            check_add("MATH 20C", &overlapping, &schedule),
+            Some(AddConflict::TimeConflict { section_id, .. }) if section_id == "1"
+        ));
+
+        let fine = section("4", "MATH 20C (B00)", Weekday::Mon, 650, 700);
+        assert_eq!(None, check_add("MATH 20C", &fine, &schedule));
+    }
+}
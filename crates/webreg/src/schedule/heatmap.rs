@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::schedule::{day_abbreviation, MeetingSlot, SlotDays};
+
+/// A meeting, along with where it happens and how many seats its section has.
+#[derive(Debug, Clone)]
+pub struct MeetingLoad {
+    /// The building (e.g., `CENTR`).
+    pub building: String,
+    /// When the meeting happens.
+    pub slot: MeetingSlot,
+    /// The section's seat capacity.
+    pub seats: i64,
+    /// The number of students enrolled in the section.
+    pub enrolled: i64,
+}
+
+/// How busy a building is during one hour of the week.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct HeatmapCell {
+    /// The day (e.g., `Tu`).
+    pub day: &'static str,
+    /// The hour, from 0 to 23. A meeting counts toward every hour it overlaps.
+    pub hour: u32,
+    /// The total seat capacity of the meetings happening during this hour.
+    pub seats: i64,
+    /// The total enrollment of the meetings happening during this hour.
+    pub enrolled: i64,
+    /// The number of meetings happening during this hour.
+    pub meetings: usize,
+}
+
+/// How busy a building is over the week.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildingHeatmap {
+    pub building: String,
+    /// The most seats in use during any single hour.
+    pub peak_seats: i64,
+    /// Every hour with at least one meeting, in order.
+    pub cells: Vec<HeatmapCell>,
+}
+
+/// Class density for a term, by building and hour of the week.
+#[derive(Debug, Clone, Serialize)]
+pub struct Heatmap {
+    pub term: String,
+    /// When the heatmap was computed, in RFC 3339 format.
+    pub generated_at: String,
+    pub buildings: Vec<BuildingHeatmap>,
+}
+
+/// Computes the number of concurrent class seats in each building for every hour of the
+/// week. Only weekly meetings are counted, so one-time meetings such as final exams are
+/// left out, as are meetings without a known building.
+///
+/// # Parameters
+/// - `term`: The term.
+/// - `loads`: Every meeting in the term.
+///
+/// # Returns
+/// The heatmap.
+pub fn build_heatmap(term: &str, loads: &[MeetingLoad]) -> Heatmap {
+    // Keyed by building, then by day (counting from Monday) and hour.
+    let mut totals: BTreeMap<&str, BTreeMap<(u32, u32), HeatmapCell>> = BTreeMap::new();
+    for load in loads {
+        let building = load.building.trim();
+        if building.is_empty() || building == "TBA" {
+            continue;
+        }
+
+        let SlotDays::Weekly(days) = &load.slot.days else {
+            continue;
+        };
+
+        let (start, end) = (load.slot.start, load.slot.end);
+        if end <= start {
+            continue;
+        }
+
+        let cells = totals.entry(building).or_default();
+        for day in days {
+            for hour in start / 60..end.div_ceil(60).min(24) {
+                let cell = cells
+                    .entry((day.num_days_from_monday(), hour))
+                    .or_insert(HeatmapCell {
+                        day: day_abbreviation(*day),
+                        hour,
+                        seats: 0,
+                        enrolled: 0,
+                        meetings: 0,
+                    });
+                cell.seats += load.seats;
+                cell.enrolled += load.enrolled;
+                cell.meetings += 1;
+            }
+        }
+    }
+
+    let buildings = totals
+        .into_iter()
+        .map(|(building, cells)| {
+            let cells: Vec<HeatmapCell> = cells.into_values().collect();
+            BuildingHeatmap {
+                building: building.to_owned(),
+                peak_seats: cells.iter().map(|c| c.seats).max().unwrap_or(0),
+                cells,
+            }
+        })
+        .collect();
+
+    Heatmap {
+        term: term.to_owned(),
+        generated_at: Utc::now().to_rfc3339(),
+        buildings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Weekday;
+
+    fn load(building: &str, days: &[Weekday], start: u32, end: u32, seats: i64) -> MeetingLoad {
+        MeetingLoad {
+            building: building.to_string(),
+            slot: MeetingSlot {
+                meeting_type: Some("LE".to_string()),
+                days: SlotDays::Weekly(days.to_vec()),
+                start,
+                end,
+                dates: None,
+                location: None,
+            },
+            seats,
+            enrolled: seats / 2,
+        }
+    }
+
+    #[test]
+    fn test_overlapping_hours() {
+        let heatmap = build_heatmap(
+            "FA24",
+            &[
+                // 9:30 to 10:50 counts toward both the 9 and 10 o'clock hours.
+                load("CENTR", &[Weekday::Tue, Weekday::Thu], 570, 650, 100),
+                load("CENTR", &[Weekday::Tue], 600, 650, 50),
+                load("TBA", &[Weekday::Mon], 600, 650, 30),
+                load(" ", &[Weekday::Mon], 600, 650, 30),
+            ],
+        );
+
+        assert_eq!(1, heatmap.buildings.len());
+        let centr = &heatmap.buildings[0];
+        assert_eq!(150, centr.peak_seats);
+        assert_eq!(4, centr.cells.len());
+        assert_eq!(
+            HeatmapCell {
+                day: "Tu",
+                hour: 10,
+                seats: 150,
+                enrolled: 75,
+                meetings: 2,
+            },
+            centr.cells[1]
+        );
+        assert_eq!(("Th", 9), (centr.cells[2].day, centr.cells[2].hour));
+    }
+}
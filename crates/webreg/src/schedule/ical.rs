@@ -0,0 +1,201 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+
+use crate::schedule::{MeetingSlot, ScheduledSection, SlotDays};
+
+/// The time zone that all WebReg meeting times are in.
+const TIME_ZONE: &str = "America/Los_Angeles";
+
+/// Builds an iCalendar file containing every meeting of the given sections.
+///
+/// Weekly meetings are only included if their dates are known, since otherwise there's
+/// no way to tell which weeks they happen in.
+///
+/// # Parameters
+/// - `sections`: The sections.
+///
+/// # Returns
+/// The contents of the `.ics` file.
+pub fn build_ical(sections: &[ScheduledSection]) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//webreg_scraper//schedule//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    for section in sections {
+        for (idx, slot) in section.slots.iter().enumerate() {
+            let Some((first_day, rrule)) = first_occurrence(slot) else {
+                continue;
+            };
+
+            lines.push("BEGIN:VEVENT".to_string());
+            lines.push(format!(
+                "UID:{}-{}-{idx}@webreg_scraper",
+                section.term, section.section_id
+            ));
+            lines.push(format!("DTSTAMP:{stamp}"));
+            lines.push(format!(
+                "DTSTART;TZID={TIME_ZONE}:{}",
+                format_local(first_day, slot.start)
+            ));
+            lines.push(format!(
+                "DTEND;TZID={TIME_ZONE}:{}",
+                format_local(first_day, slot.end)
+            ));
+            if let Some(rrule) = rrule {
+                lines.push(rrule);
+            }
+
+            let summary = match &slot.meeting_type {
+                Some(t) => format!("{} {}", section.title, t.trim()),
+                None => section.title.clone(),
+            };
+            lines.push(format!("SUMMARY:{}", escape_text(&summary)));
+            if let Some(location) = &slot.location {
+                lines.push(format!("LOCATION:{}", escape_text(location)));
+            }
+
+            lines.push("END:VEVENT".to_string());
+        }
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    let mut ical = lines.join("\r\n");
+    ical.push_str("\r\n");
+    ical
+}
+
+/// Works out the first day that a meeting happens on and, for weekly meetings, the rule
+/// describing when it repeats.
+fn first_occurrence(slot: &MeetingSlot) -> Option<(NaiveDate, Option<String>)> {
+    match &slot.days {
+        SlotDays::Once(date) => Some((*date, None)),
+        SlotDays::Weekly(days) => {
+            let range = slot.dates?;
+            let first = (0..7)
+                .map(|offset| range.start + Duration::days(offset))
+                .find(|d| days.contains(&d.weekday()))
+                .filter(|d| *d <= range.end)?;
+
+            // Since DTSTART has a time zone, UNTIL has to be in UTC. The last meeting is on
+            // the end date, so the rule runs until the end of that day in local time
+            let by_day = days.iter().map(ical_day).collect::<Vec<_>>().join(",");
+            let until = pacific_to_utc(range.end, NaiveTime::from_hms_opt(23, 59, 59)?);
+            let rrule = format!(
+                "RRULE:FREQ=WEEKLY;BYDAY={by_day};UNTIL={}",
+                until.format("%Y%m%dT%H%M%SZ")
+            );
+
+            Some((first, Some(rrule)))
+        }
+    }
+}
+
+/// Converts a time in Pacific time, which all meetings are in, to UTC. Daylight saving time
+/// runs from 2 AM on the second Sunday in March to 2 AM on the first Sunday in November.
+fn pacific_to_utc(date: NaiveDate, time: NaiveTime) -> NaiveDateTime {
+    let two_am = NaiveTime::from_hms_opt(2, 0, 0).unwrap();
+    let dst_start = NaiveDate::from_weekday_of_month_opt(date.year(), 3, Weekday::Sun, 2);
+    let dst_end = NaiveDate::from_weekday_of_month_opt(date.year(), 11, Weekday::Sun, 1);
+    let local = date.and_time(time);
+    let is_dst = match (dst_start, dst_end) {
+        (Some(start), Some(end)) => local >= start.and_time(two_am) && local < end.and_time(two_am),
+        _ => false,
+    };
+
+    local + Duration::hours(if is_dst { 7 } else { 8 })
+}
+
+/// Formats a date and a time (in minutes since midnight) as a local iCalendar date-time.
+fn format_local(date: NaiveDate, minutes: u32) -> String {
+    format!(
+        "{}T{:02}{:02}00",
+        date.format("%Y%m%d"),
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+/// The two-letter iCalendar code for a day of the week.
+fn ical_day(day: &Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+/// Escapes text for use in an iCalendar property value.
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::{DateRange, SubSession};
+
+    #[test]
+    fn test_partial_term_meeting() {
+        let section = ScheduledSection {
+            term: "FA24".to_string(),
+            section_id: "123456".to_string(),
+            title: "CSE 100 (A00)".to_string(),
+            sub_session: SubSession::Regular,
+            slots: vec![
+                MeetingSlot {
+                    meeting_type: Some("LE".to_string()),
+                    days: SlotDays::Weekly(vec![Weekday::Tue, Weekday::Thu]),
+                    start: 9 * 60 + 30,
+                    end: 10 * 60 + 50,
+                    dates: DateRange::parse("2024-10-28", "2024-12-06"),
+                    location: Some("CENTR 105".to_string()),
+                },
+                MeetingSlot {
+                    meeting_type: Some("DI".to_string()),
+                    days: SlotDays::Weekly(vec![Weekday::Fri]),
+                    start: 600,
+                    end: 650,
+                    dates: None,
+                    location: None,
+                },
+            ],
+        };
+
+        let ical = build_ical(&[section]);
+        assert!(ical.contains("DTSTART;TZID=America/Los_Angeles:20241029T093000"));
+        // The end of December 6th in Pacific time, in UTC
+        assert!(ical.contains("RRULE:FREQ=WEEKLY;BYDAY=TU,TH;UNTIL=20241207T075959Z"));
+        assert!(ical.contains("SUMMARY:CSE 100 (A00) LE"));
+        // The discussion's dates aren't known, so it's left out.
+        assert_eq!(1, ical.matches("BEGIN:VEVENT").count());
+    }
+
+    #[test]
+    fn test_pacific_to_utc() {
+        let end_of_day = NaiveTime::from_hms_opt(23, 59, 59).unwrap();
+        let utc = |date: &str, time| {
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+            pacific_to_utc(date, time)
+                .format("%Y%m%dT%H%M%S")
+                .to_string()
+        };
+
+        assert_eq!("20240608T065959", utc("2024-06-07", end_of_day));
+        assert_eq!("20240315T065959", utc("2024-03-14", end_of_day));
+        // Daylight saving time starts on March 10th, and ends on November 3rd
+        assert_eq!("20240310T075959", utc("2024-03-09", end_of_day));
+        assert_eq!("20240311T065959", utc("2024-03-10", end_of_day));
+        assert_eq!("20241103T065959", utc("2024-11-02", end_of_day));
+        assert_eq!("20241104T075959", utc("2024-11-03", end_of_day));
+    }
+}
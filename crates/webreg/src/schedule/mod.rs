@@ -0,0 +1,29 @@
+//! Schedule logic that doesn't depend on WebReg itself, such as working out which part
+//! of the year a term covers, whether two sections' meetings conflict, and exporting
+//! meetings to a calendar, an image, or a spreadsheet.
+
+mod conflict;
+mod dates;
+mod export;
+mod finals;
+mod guard;
+mod heatmap;
+mod ical;
+mod png;
+mod rooms;
+mod session;
+
+pub use conflict::{
+    day_abbreviation, find_conflicts, parse_weekday, MeetingSlot, ScheduledSection, SlotDays,
+};
+pub use dates::MeetingDates;
+pub use export::{
+    canonical_csv, canonical_parquet, csv_row, section_json, ParquetExport, CSV_HEADER,
+};
+pub use finals::{is_final, Exam, ExamKind};
+pub use guard::{check_add, AddConflict};
+pub use heatmap::{build_heatmap, Heatmap, MeetingLoad};
+pub use ical::build_ical;
+pub use png::render_week_png;
+pub use rooms::{is_free, parse_clock_time};
+pub use session::{DateRange, SubSession};
@@ -0,0 +1,257 @@
+use chrono::Weekday;
+
+use crate::schedule::{ScheduledSection, SlotDays};
+
+/// The width of each day's column, in pixels.
+const COLUMN_WIDTH: usize = 120;
+/// The height of one hour, in pixels.
+const HOUR_HEIGHT: usize = 48;
+/// The space around the grid, in pixels.
+const MARGIN: usize = 8;
+
+const BACKGROUND: [u8; 3] = [255, 255, 255];
+const HOUR_LINE: [u8; 3] = [230, 230, 230];
+const DAY_LINE: [u8; 3] = [190, 190, 190];
+
+/// Colors given to sections, in order.
+const PALETTE: [[u8; 3]; 8] = [
+    [66, 133, 244],
+    [219, 68, 55],
+    [244, 180, 0],
+    [15, 157, 88],
+    [171, 71, 188],
+    [0, 172, 193],
+    [255, 112, 67],
+    [124, 179, 66],
+];
+
+/// An RGB image being drawn.
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![BACKGROUND; width * height],
+        }
+    }
+
+    /// Fills the rectangle from `(x0, y0)` up to, but not including, `(x1, y1)`.
+    fn fill(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, color: [u8; 3]) {
+        for y in y0..y1.min(self.height) {
+            for x in x0..x1.min(self.width) {
+                self.pixels[y * self.width + x] = color;
+            }
+        }
+    }
+
+    #[cfg(test)]
+    fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        self.pixels[y * self.width + x]
+    }
+}
+
+/// Renders the weekly meetings of the given sections as a PNG image of a week grid, with
+/// one column per day and one colored block per meeting. Meetings that only happen once
+/// (e.g., final exams) aren't shown.
+///
+/// # Parameters
+/// - `sections`: The sections.
+///
+/// # Returns
+/// The contents of the `.png` file.
+pub fn render_week_png(sections: &[ScheduledSection]) -> Vec<u8> {
+    encode_png(&draw_week(sections))
+}
+
+/// Draws the week grid.
+fn draw_week(sections: &[ScheduledSection]) -> Canvas {
+    let weekly: Vec<(usize, &Vec<Weekday>, u32, u32)> = sections
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, section)| {
+            section
+                .slots
+                .iter()
+                .filter_map(move |slot| match &slot.days {
+                    SlotDays::Weekly(days) => Some((idx, days, slot.start, slot.end)),
+                    SlotDays::Once(_) => None,
+                })
+        })
+        .collect();
+
+    let weekend = weekly.iter().any(|(_, days, _, _)| {
+        days.iter()
+            .any(|d| matches!(d, Weekday::Sat | Weekday::Sun))
+    });
+    let num_days = if weekend { 7 } else { 5 };
+
+    // Show 8 AM to 8 PM at minimum, growing to fit every meeting.
+    let first_hour = weekly
+        .iter()
+        .map(|(_, _, start, _)| (start / 60) as usize)
+        .min()
+        .unwrap_or(8)
+        .min(8);
+    let last_hour = weekly
+        .iter()
+        .map(|(_, _, _, end)| end.div_ceil(60) as usize)
+        .max()
+        .unwrap_or(20)
+        .clamp(20, 24);
+
+    let grid_height = (last_hour - first_hour) * HOUR_HEIGHT;
+    let mut canvas = Canvas::new(
+        num_days * COLUMN_WIDTH + 2 * MARGIN,
+        grid_height + 2 * MARGIN,
+    );
+
+    for hour in 0..=(last_hour - first_hour) {
+        let y = MARGIN + hour * HOUR_HEIGHT;
+        canvas.fill(MARGIN, y, canvas.width - MARGIN, y + 1, HOUR_LINE);
+    }
+
+    for day in 0..=num_days {
+        let x = MARGIN + day * COLUMN_WIDTH;
+        canvas.fill(x, MARGIN, x + 1, MARGIN + grid_height + 1, DAY_LINE);
+    }
+
+    let to_y = |minutes: u32| {
+        let offset = (minutes as usize).saturating_sub(first_hour * 60);
+        MARGIN + offset * HOUR_HEIGHT / 60
+    };
+
+    for (idx, days, start, end) in weekly {
+        let color = PALETTE[idx % PALETTE.len()];
+        for day in days {
+            let column = day.num_days_from_monday() as usize;
+            if column >= num_days {
+                continue;
+            }
+
+            let x = MARGIN + column * COLUMN_WIDTH;
+            canvas.fill(
+                x + 3,
+                to_y(start) + 1,
+                x + COLUMN_WIDTH - 2,
+                to_y(end),
+                color,
+            );
+        }
+    }
+
+    canvas
+}
+
+/// Encodes the canvas as an uncompressed PNG.
+fn encode_png(canvas: &Canvas) -> Vec<u8> {
+    // Each row starts with its filter type, which is always "none".
+    let mut raw = Vec::with_capacity(canvas.height * (canvas.width * 3 + 1));
+    for row in canvas.pixels.chunks(canvas.width) {
+        raw.push(0);
+        raw.extend(row.iter().flatten());
+    }
+
+    // A zlib stream made of stored (uncompressed) deflate blocks.
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = raw.chunks(u16::MAX as usize).collect();
+    for (i, block) in blocks.iter().enumerate() {
+        let len = block.len() as u16;
+        zlib.push(u8::from(i == blocks.len() - 1));
+        zlib.extend(len.to_le_bytes());
+        zlib.extend((!len).to_le_bytes());
+        zlib.extend(*block);
+    }
+    zlib.extend(adler32(&raw).to_be_bytes());
+
+    let mut header = vec![];
+    header.extend((canvas.width as u32).to_be_bytes());
+    header.extend((canvas.height as u32).to_be_bytes());
+    // 8-bit RGB, default compression and filtering, no interlacing.
+    header.extend([8, 2, 0, 0, 0]);
+
+    let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib);
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Appends a PNG chunk, along with its length and checksum.
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    png.extend(kind);
+    png.extend(data);
+
+    let mut crc = !0u32;
+    for byte in kind.iter().chain(data) {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    png.extend((!crc).to_be_bytes());
+}
+
+/// Computes the Adler-32 checksum used by zlib.
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::{MeetingSlot, SubSession};
+
+    #[test]
+    fn test_week_grid() {
+        let section = ScheduledSection {
+            term: "FA24".to_string(),
+            section_id: "123456".to_string(),
+            title: "CSE 100 (A00)".to_string(),
+            sub_session: SubSession::Regular,
+            slots: vec![MeetingSlot {
+                meeting_type: Some("LE".to_string()),
+                days: SlotDays::Weekly(vec![Weekday::Tue]),
+                start: 9 * 60,
+                end: 10 * 60,
+                dates: None,
+                location: None,
+            }],
+        };
+
+        let canvas = draw_week(std::slice::from_ref(&section));
+        assert_eq!(5 * COLUMN_WIDTH + 2 * MARGIN, canvas.width);
+        assert_eq!(12 * HOUR_HEIGHT + 2 * MARGIN, canvas.height);
+
+        // Tuesday, 9:30 AM.
+        let x = MARGIN + COLUMN_WIDTH + COLUMN_WIDTH / 2;
+        let y = MARGIN + HOUR_HEIGHT + HOUR_HEIGHT / 2;
+        assert_eq!(PALETTE[0], canvas.pixel(x, y));
+        // Monday, 9:30 AM.
+        assert_eq!(BACKGROUND, canvas.pixel(x - COLUMN_WIDTH, y));
+
+        let png = render_week_png(&[section]);
+        assert_eq!(b"\x89PNG\r\n\x1a\n", &png[..8]);
+        assert_eq!(b"IEND", &png[png.len() - 8..png.len() - 4]);
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(0x11e6_0398, adler32(b"Wikipedia"));
+    }
+}
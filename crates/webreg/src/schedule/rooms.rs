@@ -0,0 +1,58 @@
+use chrono::Weekday;
+
+use crate::schedule::{MeetingSlot, SlotDays};
+
+/// Parses a time of day given as `HH:MM` in 24-hour time (e.g., `13:30`).
+///
+/// # Parameters
+/// - `time`: The time.
+///
+/// # Returns
+/// The number of minutes since midnight, or `None` if the time is invalid.
+pub fn parse_clock_time(time: &str) -> Option<u32> {
+    let (hr, min) = time.trim().split_once(':')?;
+    let (hr, min): (u32, u32) = (hr.parse().ok()?, min.parse().ok()?);
+    (hr < 24 && min < 60).then_some(hr * 60 + min)
+}
+
+/// Whether none of the given weekly meetings take place on `day` between `start` and `end`.
+/// Meetings that only happen once (e.g., final exams) are ignored.
+///
+/// # Parameters
+/// - `slots`: The meetings held in a room.
+/// - `day`: The day of the week.
+/// - `start`: The start of the window, in minutes since midnight.
+/// - `end`: The end of the window, in minutes since midnight.
+///
+/// # Returns
+/// `true` if the room is free for the whole window.
+pub fn is_free(slots: &[MeetingSlot], day: Weekday, start: u32, end: u32) -> bool {
+    !slots.iter().any(|slot| match &slot.days {
+        SlotDays::Weekly(days) => days.contains(&day) && slot.start < end && start < slot.end,
+        SlotDays::Once(_) => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_free_window() {
+        let slots = [MeetingSlot {
+            meeting_type: Some("LE".to_string()),
+            days: SlotDays::Weekly(vec![Weekday::Mon, Weekday::Wed]),
+            start: 600,
+            end: 650,
+            dates: None,
+            location: None,
+        }];
+
+        assert_eq!(Some(600), parse_clock_time("10:00"));
+        assert_eq!(None, parse_clock_time("24:00"));
+        assert!(!is_free(&slots, Weekday::Mon, 540, 610));
+        // Back-to-back is fine.
+        assert!(is_free(&slots, Weekday::Mon, 650, 720));
+        assert!(is_free(&slots, Weekday::Tue, 540, 720));
+    }
+}
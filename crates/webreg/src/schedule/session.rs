@@ -0,0 +1,98 @@
+use chrono::NaiveDate;
+
+/// The part of the academic year that a term belongs to. Summer is split into several
+/// sub-sessions that run over different weeks, so two summer sections that meet at the
+/// same time don't necessarily conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SubSession {
+    /// A regular quarter (fall, winter, or spring).
+    Regular,
+    /// Summer Session I.
+    Summer1,
+    /// Summer Session II.
+    Summer2,
+    /// Summer Session III, which spans most of both Summer Session I and II.
+    Summer3,
+    /// A special summer session, whose dates vary by course.
+    SpecialSummer,
+}
+
+impl SubSession {
+    /// Determines the sub-session from a term code (e.g., `S124`).
+    ///
+    /// # Parameters
+    /// - `term`: The term code.
+    ///
+    /// # Returns
+    /// The sub-session.
+    pub fn from_term(term: &str) -> Self {
+        match term.get(..2).map(|p| p.to_uppercase()).as_deref() {
+            Some("S1") => SubSession::Summer1,
+            Some("S2") => SubSession::Summer2,
+            Some("S3") => SubSession::Summer3,
+            Some("SU") => SubSession::SpecialSummer,
+            _ => SubSession::Regular,
+        }
+    }
+
+    /// A short, stable name for this sub-session.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubSession::Regular => "regular",
+            SubSession::Summer1 => "summer_1",
+            SubSession::Summer2 => "summer_2",
+            SubSession::Summer3 => "summer_3",
+            SubSession::SpecialSummer => "special_summer",
+        }
+    }
+
+    /// Whether this sub-session and `other` could run during the same weeks. This is only
+    /// used when the actual dates aren't known, so it errs on the side of overlapping.
+    ///
+    /// # Parameters
+    /// - `other`: The other sub-session.
+    ///
+    /// # Returns
+    /// `false` only if the two sub-sessions are known to never overlap.
+    pub fn may_overlap(&self, other: &SubSession) -> bool {
+        !matches!(
+            (self, other),
+            (SubSession::Summer1, SubSession::Summer2) | (SubSession::Summer2, SubSession::Summer1)
+        )
+    }
+}
+
+/// An inclusive range of dates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    /// The first day of the range.
+    pub start: NaiveDate,
+    /// The last day of the range.
+    pub end: NaiveDate,
+}
+
+impl DateRange {
+    /// Parses a date range from two `YYYY-MM-DD` strings.
+    ///
+    /// # Parameters
+    /// - `start`: The first day.
+    /// - `end`: The last day.
+    ///
+    /// # Returns
+    /// The date range, or `None` if either date is invalid or `end` is before `start`.
+    pub fn parse(start: &str, end: &str) -> Option<Self> {
+        let start = NaiveDate::parse_from_str(start.trim(), "%Y-%m-%d").ok()?;
+        let end = NaiveDate::parse_from_str(end.trim(), "%Y-%m-%d").ok()?;
+        (start <= end).then_some(Self { start, end })
+    }
+
+    /// Whether this range shares at least one day with `other`.
+    pub fn overlaps(&self, other: &DateRange) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    /// Whether the given date falls within this range.
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date <= self.end
+    }
+}
@@ -0,0 +1,182 @@
+//! Mirroring a student's WebReg schedule into the database.
+//!
+//! Syncing pulls the sections that the student is enrolled or waitlisted in from WebReg and
+//! stores them (see `/live/:term/sync_my_schedule`), so that their schedule can be checked
+//! for conflicts or exported without asking WebReg again. Each sync is compared against the
+//! last one, so that adds, drops, and waitlist moves made outside of the API are noticed.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use webweg::types::{EnrollmentStatus, ScheduledSection};
+
+use crate::db::{UserEnrollment, USER_ENROLLMENT_ENROLLED, USER_ENROLLMENT_WAITLISTED};
+
+/// Converts a section of the student's WebReg schedule into its mirror.
+///
+/// # Parameters
+/// - `term`: The term that the schedule is for.
+/// - `section`: The section.
+///
+/// # Returns
+/// The mirrored section, or `None` if the section is only planned.
+pub fn mirror_section(term: &str, section: &ScheduledSection) -> Option<UserEnrollment> {
+    let (status, waitlist_pos) = match section.enrolled_status {
+        EnrollmentStatus::Enrolled => (USER_ENROLLMENT_ENROLLED, None),
+        EnrollmentStatus::Waitlist { waitlist_pos } => {
+            (USER_ENROLLMENT_WAITLISTED, Some(waitlist_pos))
+        }
+        _ => return None,
+    };
+
+    Some(UserEnrollment {
+        term: term.to_owned(),
+        section_id: section.section_id.trim().to_owned(),
+        subject_code: section.subject_code.trim().to_owned(),
+        course_code: section.course_code.trim().to_owned(),
+        section_code: section.section_code.trim().to_owned(),
+        status: status.to_owned(),
+        waitlist_pos,
+        units: section.units as f32,
+        grade_option: section.grade_option.trim().to_owned(),
+        first_seen_at: None,
+        synced_at: None,
+    })
+}
+
+/// A section whose status, or place on the waitlist, changed between syncs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EnrollmentChange {
+    pub section_id: String,
+    /// The course (e.g., `CSE 100`).
+    pub course: String,
+    pub section_code: String,
+    pub old_status: String,
+    pub new_status: String,
+    pub old_waitlist_pos: Option<i64>,
+    pub new_waitlist_pos: Option<i64>,
+}
+
+/// How a student's schedule changed between two syncs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EnrollmentChanges {
+    /// Sections that the student is now enrolled or waitlisted in.
+    pub added: Vec<UserEnrollment>,
+    /// Sections that the student is no longer enrolled or waitlisted in.
+    pub dropped: Vec<UserEnrollment>,
+    /// Sections that the student moved between enrolled and waitlisted in, or moved on the
+    /// waitlist of.
+    pub changed: Vec<EnrollmentChange>,
+}
+
+/// Compares two mirrors of a student's schedule.
+///
+/// # Parameters
+/// - `previous`: The sections from the last sync.
+/// - `current`: The sections from this sync.
+///
+/// # Returns
+/// The changes, in the order of the sections in `current` (then `previous`, for drops).
+pub fn diff_enrollments(
+    previous: &[UserEnrollment],
+    current: &[UserEnrollment],
+) -> EnrollmentChanges {
+    let before: HashMap<&str, &UserEnrollment> = previous
+        .iter()
+        .map(|s| (s.section_id.as_str(), s))
+        .collect();
+    let after: HashMap<&str, &UserEnrollment> =
+        current.iter().map(|s| (s.section_id.as_str(), s)).collect();
+
+    let mut changes = EnrollmentChanges::default();
+    for section in current {
+        match before.get(section.section_id.as_str()) {
+            None => changes.added.push(section.clone()),
+            Some(old)
+                if old.status != section.status || old.waitlist_pos != section.waitlist_pos =>
+            {
+                changes.changed.push(EnrollmentChange {
+                    section_id: section.section_id.clone(),
+                    course: format!("{} {}", section.subject_code, section.course_code),
+                    section_code: section.section_code.clone(),
+                    old_status: old.status.clone(),
+                    new_status: section.status.clone(),
+                    old_waitlist_pos: old.waitlist_pos,
+                    new_waitlist_pos: section.waitlist_pos,
+                });
+            }
+            Some(_) => {}
+        }
+    }
+
+    changes.dropped = previous
+        .iter()
+        .filter(|s| !after.contains_key(s.section_id.as_str()))
+        .cloned()
+        .collect();
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enrollment(section_id: &str, status: &str, waitlist_pos: Option<i64>) -> UserEnrollment {
+        UserEnrollment {
+            term: "FA24".to_owned(),
+            section_id: section_id.to_owned(),
+            subject_code: "CSE".to_owned(),
+            course_code: "100".to_owned(),
+            section_code: "A01".to_owned(),
+            status: status.to_owned(),
+            waitlist_pos,
+            units: 4.0,
+            grade_option: "L".to_owned(),
+            first_seen_at: None,
+            synced_at: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_enrollments() {
+        let previous = [
+            enrollment("1", USER_ENROLLMENT_ENROLLED, None),
+            enrollment("2", USER_ENROLLMENT_WAITLISTED, Some(5)),
+            enrollment("3", USER_ENROLLMENT_WAITLISTED, Some(2)),
+            enrollment("4", USER_ENROLLMENT_ENROLLED, None),
+        ];
+        let current = [
+            enrollment("1", USER_ENROLLMENT_ENROLLED, None),
+            enrollment("2", USER_ENROLLMENT_WAITLISTED, Some(3)),
+            enrollment("3", USER_ENROLLMENT_ENROLLED, None),
+            enrollment("5", USER_ENROLLMENT_ENROLLED, None),
+        ];
+
+        let changes = diff_enrollments(&previous, &current);
+        assert_eq!(vec![current[3].clone()], changes.added);
+        assert_eq!(vec![previous[3].clone()], changes.dropped);
+        let changed: Vec<_> = changes
+            .changed
+            .iter()
+            .map(|c| {
+                (
+                    c.section_id.as_str(),
+                    c.new_status.as_str(),
+                    c.new_waitlist_pos,
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("2", USER_ENROLLMENT_WAITLISTED, Some(3)),
+                ("3", USER_ENROLLMENT_ENROLLED, None),
+            ],
+            changed
+        );
+
+        assert_eq!(
+            EnrollmentChanges::default(),
+            diff_enrollments(&current, &current)
+        );
+    }
+}
@@ -0,0 +1,756 @@
+//! A scheduler for the recurring background jobs.
+//!
+//! Each job is registered with a schedule, which is either a cron expression (five fields:
+//! minute, hour, day of the month, month, and day of the week, in the server's local time)
+//! or a fixed interval (e.g., `@every 30s`). The built-in jobs have default schedules that
+//! can be changed, or turned `off`, with `jobSchedules` in the configuration file. Only the
+//! leader runs jobs, and a job that's still running when it's next due skips that run.
+//!
+//! `GET /admin/jobs` reports each job's schedule, when it'll next run, and how its last
+//! run went.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+use serde::Serialize;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::cookie_freshness::check_cookie_freshness;
+use crate::enroll_jobs::{
+    expire_watches, run_due_enroll_jobs, DEFAULT_ENROLL_JOB_INTERVAL, DEFAULT_WATCH_EXPIRY_INTERVAL,
+};
+use crate::pass_times::{send_pass_time_reminders, DEFAULT_PASS_TIME_REMINDER_INTERVAL};
+use crate::scraper::term_scrape::start_term_scrape;
+use crate::status_history::{record_dependency_statuses, DEFAULT_STATUS_CHECK_INTERVAL};
+use crate::types::WrapperState;
+
+/// The job that starts a full scrape of each tracked term. It isn't scheduled by default.
+pub const TERM_SCRAPE_JOB: &str = "term_scrape";
+/// The job that runs the enrollment jobs that are due (see [`crate::enroll_jobs`]).
+pub const ENROLLMENT_POLLING_JOB: &str = "enrollment_polling";
+/// The job that removes expired degree audits from the cache and the archive.
+pub const CACHE_CLEANUP_JOB: &str = "cache_cleanup";
+/// The job that validates the tracker's session cookies (see [`crate::cookie_freshness`]).
+pub const COOKIE_VALIDATION_JOB: &str = "cookie_validation";
+/// The job that records whether the services that the API depends on are up (see
+/// [`crate::status_history`]).
+pub const DEPENDENCY_STATUS_JOB: &str = "dependency_status";
+/// The job that writes the schedule database's write-ahead log back to the database (see
+/// [`crate::db::ScheduleDbManager::checkpoint`]).
+pub const DB_CHECKPOINT_JOB: &str = "db_checkpoint";
+/// The job that expires watches past their term's add deadline, or whose student enrolled
+/// in the watched course (see [`crate::enroll_jobs::expire_watches`]).
+pub const WATCH_EXPIRY_JOB: &str = "watch_expiry";
+/// The job that reminds students before their passes open, if reminders are configured (see
+/// [`crate::pass_times`]).
+pub const PASS_TIME_REMINDER_JOB: &str = "pass_time_reminders";
+
+/// How often the cache is cleaned up, if not configured.
+const DEFAULT_CACHE_CLEANUP_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+/// How often the write-ahead log is checkpointed, if not configured.
+const DEFAULT_DB_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// How often the scheduler checks for jobs that are due.
+const SCHEDULER_TICK: Duration = Duration::from_secs(1);
+/// The most steps taken to find a cron expression's next match before giving up on it
+/// (e.g., for `0 0 30 2 *`, which never matches).
+const MAX_CRON_STEPS: usize = 20_000;
+
+/// Why a schedule couldn't be parsed.
+#[derive(Debug, Error)]
+pub enum ScheduleError {
+    #[error("expected 5 fields, found {0}")]
+    FieldCount(usize),
+    #[error("invalid field `{0}`")]
+    Field(String),
+    #[error("invalid interval `{0}`")]
+    Interval(String),
+}
+
+/// A parsed cron expression. Each field is a bit set of the values that it matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day of the month is `*`. If neither day field is, a day matches if
+    /// either field does, as with cron.
+    any_day: bool,
+    /// Whether the day of the week is `*`.
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// Parses a cron expression.
+    ///
+    /// # Parameters
+    /// - `expression`: The expression, with five fields.
+    ///
+    /// # Returns
+    /// The schedule.
+    pub fn parse(expression: &str) -> Result<Self, ScheduleError> {
+        let fields: Vec<_> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(ScheduleError::FieldCount(fields.len()));
+        };
+
+        // Sunday is both 0 and 7
+        let weekdays = parse_field(weekday, 0, 7)?;
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays: (weekdays | weekdays >> 7) & 0x7f,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    /// Whether the schedule matches a day.
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        if self.any_day || self.any_weekday {
+            day && weekday
+        } else {
+            day || weekday
+        }
+    }
+
+    /// Finds the first minute after a time that the schedule matches.
+    ///
+    /// # Parameters
+    /// - `after`: The time.
+    ///
+    /// # Returns
+    /// The matching minute, or `None` if the schedule doesn't match any time in the next
+    /// few years.
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+        for _ in 0..MAX_CRON_STEPS {
+            if self.months & (1 << t.month()) == 0 {
+                let (year, month) = match t.month() {
+                    12 => (t.year() + 1, 1),
+                    month => (t.year(), month + 1),
+                };
+                t = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_day(t.date()) {
+                t = t.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & (1 << t.hour()) == 0 {
+                t = t.date().and_hms_opt(t.hour(), 0, 0)? + chrono::Duration::hours(1);
+            } else if self.minutes & (1 << t.minute()) == 0 {
+                t += chrono::Duration::minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+
+        None
+    }
+}
+
+/// Parses one field of a cron expression: `*`, a value, a range (`a-b`), or a list of
+/// them, each optionally with a step (`*/15`, `0-30/10`, or `5/20`).
+///
+/// # Parameters
+/// - `field`: The field.
+/// - `min`: The smallest value that the field can have.
+/// - `max`: The largest value that the field can have.
+///
+/// # Returns
+/// The values that the field matches, as a bit set.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, ScheduleError> {
+    let invalid = || ScheduleError::Field(field.to_owned());
+    let mut values = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|&step| step > 0)
+                    .ok_or_else(invalid)?,
+            ),
+            None => (part, 1),
+        };
+
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (
+                start.parse().map_err(|_| invalid())?,
+                end.parse().map_err(|_| invalid())?,
+            ),
+            None => {
+                let start = range.parse().map_err(|_| invalid())?;
+                (start, if step > 1 { max } else { start })
+            }
+        };
+
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            values |= 1 << value;
+        }
+    }
+
+    Ok(values)
+}
+
+/// When a job runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// Whenever a cron expression matches.
+    Cron(CronSchedule),
+    /// A fixed time after the job was last due.
+    Every(Duration),
+}
+
+impl Schedule {
+    /// Parses a schedule, which is a cron expression, `@every` followed by an interval
+    /// (e.g., `@every 90s`, `@every 15m`, or `@every 6h`), or one of `@hourly`, `@daily`,
+    /// `@weekly`, and `@monthly`.
+    ///
+    /// # Parameters
+    /// - `expression`: The schedule.
+    ///
+    /// # Returns
+    /// The parsed schedule.
+    pub fn parse(expression: &str) -> Result<Self, ScheduleError> {
+        let expression = expression.trim();
+        let cron = match expression {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            _ => match expression.strip_prefix("@every") {
+                Some(interval) => return parse_interval(interval.trim()).map(Schedule::Every),
+                None => expression,
+            },
+        };
+
+        CronSchedule::parse(cron).map(Schedule::Cron)
+    }
+
+    /// Finds when a job with this schedule is next due.
+    ///
+    /// # Parameters
+    /// - `after`: When the job was last due, or when it was registered.
+    ///
+    /// # Returns
+    /// When the job is next due, or `None` if it never is.
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        match self {
+            Schedule::Every(interval) => Some(after + chrono::Duration::from_std(*interval).ok()?),
+            Schedule::Cron(cron) => {
+                // A time that's skipped when the clocks go forward is skipped here too
+                let mut next = after.naive_local();
+                loop {
+                    next = cron.next_after(next)?;
+                    if let Some(time) = Local.from_local_datetime(&next).earliest() {
+                        return Some(time);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Parses an interval like `30s`, `15m`, `6h`, or `1d`.
+fn parse_interval(interval: &str) -> Result<Duration, ScheduleError> {
+    let invalid = || ScheduleError::Interval(interval.to_owned());
+    let split = interval
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let count: u64 = interval[..split].parse().map_err(|_| invalid())?;
+    let unit = match &interval[split..] {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+
+    match count * unit {
+        0 => Err(invalid()),
+        secs => Ok(Duration::from_secs(secs)),
+    }
+}
+
+/// The future of a job's run, which resolves to a summary of what was done, or to what went
+/// wrong.
+pub type JobFuture = Pin<Box<dyn Future<Output = Result<String, String>> + Send>>;
+/// What a job does each time that it runs.
+pub type JobTask = fn(Arc<WrapperState>) -> JobFuture;
+
+/// How a job's run went.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRun {
+    /// When the run started, in RFC 3339 format.
+    pub started_at: String,
+    /// When the run finished, in RFC 3339 format.
+    pub finished_at: String,
+    pub duration_ms: u64,
+    pub succeeded: bool,
+    /// A summary of what was done, or what went wrong.
+    pub message: String,
+}
+
+/// A job's schedule and status, as reported by `/admin/jobs`.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobReport {
+    pub name: String,
+    /// The job's schedule, as configured.
+    pub schedule: String,
+    /// When the job is next due, in RFC 3339 format.
+    pub next_run: Option<String>,
+    pub running: bool,
+    /// The number of runs that have finished since the server started.
+    pub runs: u64,
+    /// The number of those runs that failed.
+    pub failures: u64,
+    /// The number of runs that were skipped because the previous one was still running.
+    pub skipped: u64,
+    pub last_run: Option<JobRun>,
+}
+
+#[derive(Debug, Default)]
+struct JobStatus {
+    next_run: Option<DateTime<Local>>,
+    running: bool,
+    runs: u64,
+    failures: u64,
+    skipped: u64,
+    last_run: Option<JobRun>,
+}
+
+/// A registered job.
+struct Job {
+    name: String,
+    expression: String,
+    schedule: Schedule,
+    task: JobTask,
+    status: Mutex<JobStatus>,
+}
+
+/// The registered jobs.
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: Vec<Arc<Job>>,
+}
+
+impl Scheduler {
+    /// Creates a scheduler without any jobs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a scheduler with the built-in jobs.
+    ///
+    /// # Parameters
+    /// - `schedules`: The configured schedules, keyed by job name. A job whose schedule is
+    ///   `off` isn't registered.
+    /// - `cookie_validation_interval`: How often the cookies are validated, if the
+    ///   [`COOKIE_VALIDATION_JOB`] isn't configured.
+    ///
+    /// # Returns
+    /// The scheduler, or the name of the job whose schedule couldn't be parsed and why.
+    pub fn with_builtin_jobs(
+        schedules: &HashMap<String, String>,
+        cookie_validation_interval: Duration,
+    ) -> Result<Self, (String, ScheduleError)> {
+        let builtin: [(&str, Option<String>, JobTask); 8] = [
+            (TERM_SCRAPE_JOB, None, term_scrape_job),
+            (
+                ENROLLMENT_POLLING_JOB,
+                Some(every(DEFAULT_ENROLL_JOB_INTERVAL)),
+                enrollment_polling_job,
+            ),
+            (
+                CACHE_CLEANUP_JOB,
+                Some(every(DEFAULT_CACHE_CLEANUP_INTERVAL)),
+                cache_cleanup_job,
+            ),
+            (
+                COOKIE_VALIDATION_JOB,
+                Some(every(cookie_validation_interval)),
+                cookie_validation_job,
+            ),
+            (
+                DEPENDENCY_STATUS_JOB,
+                Some(every(DEFAULT_STATUS_CHECK_INTERVAL)),
+                dependency_status_job,
+            ),
+            (
+                DB_CHECKPOINT_JOB,
+                Some(every(DEFAULT_DB_CHECKPOINT_INTERVAL)),
+                db_checkpoint_job,
+            ),
+            (
+                WATCH_EXPIRY_JOB,
+                Some(every(DEFAULT_WATCH_EXPIRY_INTERVAL)),
+                watch_expiry_job,
+            ),
+            (
+                PASS_TIME_REMINDER_JOB,
+                Some(every(DEFAULT_PASS_TIME_REMINDER_INTERVAL)),
+                pass_time_reminder_job,
+            ),
+        ];
+
+        for name in schedules.keys() {
+            if !builtin.iter().any(|(job, _, _)| job == name) {
+                warn!("Ignoring the schedule of unknown job {name}.");
+            }
+        }
+
+        let mut scheduler = Self::new();
+        for (name, default, task) in builtin {
+            let expression = match schedules.get(name) {
+                Some(expression) if expression.trim() == "off" => continue,
+                Some(expression) => expression.clone(),
+                None => match default {
+                    Some(expression) => expression,
+                    None => continue,
+                },
+            };
+
+            scheduler
+                .register(name, &expression, task)
+                .map_err(|e| (name.to_owned(), e))?;
+        }
+
+        Ok(scheduler)
+    }
+
+    /// Registers a job. It's first due at the first time that its schedule matches.
+    ///
+    /// # Parameters
+    /// - `name`: The job's name.
+    /// - `expression`: The job's schedule (see [`Schedule::parse`]).
+    /// - `task`: What the job does.
+    pub fn register(
+        &mut self,
+        name: &str,
+        expression: &str,
+        task: JobTask,
+    ) -> Result<(), ScheduleError> {
+        let schedule = Schedule::parse(expression)?;
+        let next_run = schedule.next_after(Local::now());
+        if next_run.is_none() {
+            warn!("The schedule of job {name} ({expression}) never matches.");
+        }
+
+        self.jobs.push(Arc::new(Job {
+            name: name.to_owned(),
+            expression: expression.trim().to_owned(),
+            schedule,
+            task,
+            status: Mutex::new(JobStatus {
+                next_run,
+                ..Default::default()
+            }),
+        }));
+        Ok(())
+    }
+
+    /// Reports each job's schedule and status.
+    pub fn report(&self) -> Vec<JobReport> {
+        self.jobs
+            .iter()
+            .map(|job| {
+                let status = job.status.lock().unwrap();
+                JobReport {
+                    name: job.name.clone(),
+                    schedule: job.expression.clone(),
+                    next_run: status.next_run.map(|t| t.to_rfc3339()),
+                    running: status.running,
+                    runs: status.runs,
+                    failures: status.failures,
+                    skipped: status.skipped,
+                    last_run: status.last_run.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Finds the jobs that are due, marks them as running, and works out when they're next
+    /// due. A job that's still running is skipped.
+    fn take_due(&self, now: DateTime<Local>) -> Vec<Arc<Job>> {
+        let mut due = vec![];
+        for job in &self.jobs {
+            let mut status = job.status.lock().unwrap();
+            let Some(next_run) = status.next_run.filter(|&t| t <= now) else {
+                continue;
+            };
+
+            // Intervals are kept in step with when the job was due, unless the scheduler
+            // fell behind (e.g., while this instance wasn't the leader)
+            let after = match job.schedule {
+                Schedule::Every(interval)
+                    if chrono::Duration::from_std(interval)
+                        .is_ok_and(|interval| now - next_run < interval) =>
+                {
+                    next_run
+                }
+                _ => now,
+            };
+            status.next_run = job.schedule.next_after(after);
+
+            if status.running {
+                status.skipped += 1;
+                warn!("Skipping job {}, since it's still running.", job.name);
+                continue;
+            }
+
+            status.running = true;
+            due.push(job.clone());
+        }
+
+        due
+    }
+}
+
+/// Runs the jobs as they come due, until the scraper stops.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+pub async fn run_scheduler(state: Arc<WrapperState>) {
+    let mut interval = tokio::time::interval(SCHEDULER_TICK);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        if state.should_stop() {
+            break;
+        }
+
+        if !state.leader.is_leader() {
+            continue;
+        }
+
+        for job in state.scheduler.take_due(Local::now()) {
+            tokio::spawn(run_job(state.clone(), job));
+        }
+    }
+}
+
+/// Runs a job once, and records how it went.
+async fn run_job(state: Arc<WrapperState>, job: Arc<Job>) {
+    let started_at = Local::now();
+    let start = Instant::now();
+    let result = (job.task)(state).await;
+    let duration = start.elapsed();
+
+    let mut status = job.status.lock().unwrap();
+    status.running = false;
+    status.runs += 1;
+    let (succeeded, message) = match result {
+        Ok(message) => (true, message),
+        Err(message) => {
+            status.failures += 1;
+            warn!("Job {} failed: {message}", job.name);
+            (false, message)
+        }
+    };
+
+    status.last_run = Some(JobRun {
+        started_at: started_at.to_rfc3339(),
+        finished_at: Local::now().to_rfc3339(),
+        duration_ms: duration.as_millis() as u64,
+        succeeded,
+        message,
+    });
+}
+
+/// Formats an interval as an `@every` schedule.
+fn every(interval: Duration) -> String {
+    format!("@every {}s", interval.as_secs().max(1))
+}
+
+/// Starts a full scrape of each tracked term (see [`crate::scraper::term_scrape`]).
+fn term_scrape_job(state: Arc<WrapperState>) -> JobFuture {
+    Box::pin(async move {
+        let terms: Vec<_> = state.all_terms.iter().map(|t| t.key().clone()).collect();
+        let mut started = vec![];
+        let mut failed = vec![];
+        for term in terms {
+            match start_term_scrape(&state, &term) {
+                Ok((job_id, _)) => {
+                    info!("[{term}] Started scrape {job_id} on schedule.");
+                    started.push(format!("{term} ({job_id})"));
+                }
+                Err(e) => failed.push(format!("{term}: {e}")),
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(format!("Started scrapes of {}.", started.join(", ")))
+        } else {
+            Err(format!("Failed to start scrapes of {}.", failed.join("; ")))
+        }
+    })
+}
+
+/// Runs the enrollment jobs that are due.
+fn enrollment_polling_job(state: Arc<WrapperState>) -> JobFuture {
+    Box::pin(async move {
+        run_due_enroll_jobs(&state)
+            .await
+            .map(|attempted| format!("Attempted {attempted} enrollment job(s)."))
+            .map_err(|e| format!("Failed to load enrollment jobs: {e}"))
+    })
+}
+
+/// Expires the watches that are past their term's add deadline, or whose student enrolled
+/// in the watched course.
+fn watch_expiry_job(state: Arc<WrapperState>) -> JobFuture {
+    Box::pin(async move {
+        expire_watches(&state)
+            .await
+            .map(|expired| format!("Expired {expired} watch(es)."))
+            .map_err(|e| format!("Failed to load watches: {e}"))
+    })
+}
+
+/// Sends the reminders that are due for students' upcoming passes.
+fn pass_time_reminder_job(state: Arc<WrapperState>) -> JobFuture {
+    Box::pin(async move {
+        send_pass_time_reminders(&state)
+            .await
+            .map(|sent| format!("Sent {sent} pass time reminder(s)."))
+            .map_err(|e| format!("Failed to load pass times: {e}"))
+    })
+}
+
+/// Removes degree audits that are too old to be served from the cache, expired snapshots
+/// and unreferenced pages from the archive, if archiving is enabled, and payloads past their
+/// retention period from the payload store, if there is one.
+fn cache_cleanup_job(state: Arc<WrapperState>) -> JobFuture {
+    Box::pin(async move {
+        let cache_state = &state.degree_audit_cache_state;
+        let before = cache_state.cache.len();
+        cache_state.cache.cleanup_expired();
+        let removed = before.saturating_sub(cache_state.cache.len());
+        let mut summary = format!("Removed {removed} cached audit(s)");
+
+        if let Some(archive) = &cache_state.archive {
+            let stats = archive
+                .collect_garbage()
+                .map_err(|e| format!("Failed to clean up the audit archive: {e}"))?;
+            if stats.snapshots_removed > 0 || stats.blobs_removed > 0 {
+                info!(
+                    "Removed {} audit snapshot(s) and {} page(s) ({} bytes) from the archive.",
+                    stats.snapshots_removed, stats.blobs_removed, stats.bytes_freed
+                );
+            }
+
+            summary += &format!(
+                ", {} snapshot(s) and {} page(s) from the archive",
+                stats.snapshots_removed, stats.blobs_removed
+            );
+        }
+
+        if let Some(payloads) = &state.payload_archive {
+            let removed = payloads
+                .collect_garbage()
+                .await
+                .map_err(|e| format!("Failed to clean up the payload store: {e}"))?;
+            summary += &format!(", {removed} payload(s) from the payload store");
+        }
+
+        Ok(format!("{summary}."))
+    })
+}
+
+/// Validates the tracker's session cookies, and requests new ones if needed.
+fn cookie_validation_job(state: Arc<WrapperState>) -> JobFuture {
+    Box::pin(async move { check_cookie_freshness(&state).await })
+}
+
+/// Records whether WebReg, DARS, and the cookie server are up.
+fn dependency_status_job(state: Arc<WrapperState>) -> JobFuture {
+    Box::pin(async move { record_dependency_statuses(&state) })
+}
+
+/// Checkpoints the schedule database's write-ahead log.
+fn db_checkpoint_job(state: Arc<WrapperState>) -> JobFuture {
+    Box::pin(async move {
+        let stats = state
+            .schedule_db
+            .checkpoint()
+            .map_err(|e| format!("Failed to checkpoint the database: {e}"))?;
+        if stats.busy {
+            return Err(format!(
+                "Checkpointed {} of {} frame(s), but a reader kept the log from being truncated.",
+                stats.checkpointed_frames, stats.log_frames
+            ));
+        }
+
+        Ok(format!(
+            "Checkpointed {} frame(s).",
+            stats.checkpointed_frames
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_cron_schedule() {
+        let nightly = CronSchedule::parse("30 3 * * *").unwrap();
+        assert_eq!(
+            Some(at("2024-05-01 03:30")),
+            nightly.next_after(at("2024-04-30 03:30"))
+        );
+
+        let quarter_hours = CronSchedule::parse("*/15 8-17 * * 1-5").unwrap();
+        // Friday evening rolls over to Monday morning
+        assert_eq!(
+            Some(at("2024-05-06 08:00")),
+            quarter_hours.next_after(at("2024-05-03 17:45"))
+        );
+        assert_eq!(
+            Some(at("2024-05-06 08:15")),
+            quarter_hours.next_after(at("2024-05-06 08:01"))
+        );
+
+        // Either day field matches if both are restricted, and 7 is Sunday
+        let either = CronSchedule::parse("0 0 1 * 7").unwrap();
+        assert_eq!(
+            Some(at("2024-05-05 00:00")),
+            either.next_after(at("2024-05-01 12:00"))
+        );
+        assert_eq!(
+            None,
+            CronSchedule::parse("0 0 30 2 *")
+                .unwrap()
+                .next_after(at("2024-01-01 00:00"))
+        );
+
+        assert!(matches!(
+            Schedule::parse("@every 90s"),
+            Ok(Schedule::Every(d)) if d == Duration::from_secs(90)
+        ));
+        assert_eq!(
+            Schedule::parse("0 0 * * *").unwrap(),
+            Schedule::parse("@daily").unwrap()
+        );
+        assert!(Schedule::parse("@every 0m").is_err());
+        assert!(Schedule::parse("60 * * * *").is_err());
+        assert!(Schedule::parse("* * * *").is_err());
+    }
+}
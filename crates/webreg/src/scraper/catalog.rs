@@ -0,0 +1,262 @@
+//! Scrapes the UCSD course catalog, which has each course's full description, unit range,
+//! prerequisites, and cross-listings. Unlike WebReg, the catalog doesn't change from term to
+//! term and doesn't need a login, so it's scraped rarely, one subject page at a time.
+//!
+//! Each subject page lists its courses as a `course-name` paragraph (e.g., `CSE 100/MATH
+//! 176. Advanced Data Structures (4)`) followed by a `course-descriptions` paragraph, whose
+//! prerequisites come after `Prerequisites:`.
+
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use tracing::{info, warn};
+
+use crate::cross_listings::{catalog_cross_listings, CrossListingSource};
+use crate::db::CatalogCourse;
+use crate::types::WrapperState;
+
+/// The base URL of the catalog's subject pages.
+const CATALOG_BASE_URL: &str = "https://catalog.ucsd.edu/courses";
+/// How often the catalog is scraped.
+const CATALOG_SCRAPE_INTERVAL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// The delay between requests for subject pages.
+const SUBJECT_DELAY: Duration = Duration::from_secs(1);
+
+static COURSE_PARAGRAPH_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("p.course-name, p.course-descriptions").unwrap());
+static COURSE_NAME_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?P<codes>[A-Z]{2,5}\s*\d+[A-Z]*(?:\s*/\s*[A-Z]{2,5}\s*\d+[A-Z]*)*)\.\s*(?P<title>.*?)\s*(?:\((?P<units>[^)]*)\))?$").unwrap()
+});
+static COURSE_CODE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b([A-Z]{2,5})\s*(\d+[A-Z]*)\b").unwrap());
+static CROSS_LISTED_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)cross-listed with ([^.)]*)").unwrap());
+static UNITS_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+(?:\.\d+)?").unwrap());
+
+/// Collapses an element's text into a single line.
+fn element_text(element: ElementRef) -> String {
+    element
+        .text()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses the course codes in some text (e.g., `CSE 100/MATH 176`).
+fn course_codes(text: &str) -> Vec<(String, String)> {
+    COURSE_CODE_REGEX
+        .captures_iter(text)
+        .map(|c| (c[1].to_owned(), c[2].to_owned()))
+        .collect()
+}
+
+/// Parses the courses on a subject page of the catalog.
+///
+/// # Parameters
+/// - `html`: The page.
+/// - `subject`: The page's subject code. A course listed under several codes is stored
+///   under this subject's code, if it's one of them, and cross-listed with the others.
+///
+/// # Returns
+/// The courses on the page.
+pub fn parse_catalog_page(html: &str, subject: &str) -> Vec<CatalogCourse> {
+    let document = Html::parse_document(html);
+    let mut courses = vec![];
+    let mut paragraphs = document.select(&COURSE_PARAGRAPH_SELECTOR).peekable();
+    while let Some(name) = paragraphs.next() {
+        if !name.value().classes().any(|c| c == "course-name") {
+            continue;
+        }
+
+        let name = element_text(name);
+        let Some(caps) = COURSE_NAME_REGEX.captures(&name) else {
+            continue;
+        };
+        let description = paragraphs
+            .next_if(|p| p.value().classes().any(|c| c == "course-descriptions"))
+            .map(element_text)
+            .unwrap_or_default();
+
+        let mut codes = course_codes(&caps["codes"]);
+        let Some(idx) = codes
+            .iter()
+            .position(|(s, _)| s.eq_ignore_ascii_case(subject))
+            .or((!codes.is_empty()).then_some(0))
+        else {
+            continue;
+        };
+        let (subj, number) = codes.remove(idx);
+
+        let (description, prerequisites) = match description.split_once("Prerequisites:") {
+            Some((desc, prereqs)) => (desc.trim().to_owned(), Some(prereqs.trim().to_owned())),
+            None => (description, None),
+        };
+
+        let mut cross_listings: Vec<String> =
+            codes.into_iter().map(|(s, n)| format!("{s} {n}")).collect();
+        if let Some(listed) = CROSS_LISTED_REGEX.captures(&description) {
+            for (s, n) in course_codes(&listed[1]) {
+                let code = format!("{s} {n}");
+                if !cross_listings.contains(&code) && (s != subj || n != number) {
+                    cross_listings.push(code);
+                }
+            }
+        }
+
+        let units: Vec<f32> = caps
+            .name("units")
+            .map(|u| {
+                UNITS_REGEX
+                    .find_iter(u.as_str())
+                    .filter_map(|n| n.as_str().parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        courses.push(CatalogCourse {
+            subject: subj,
+            number,
+            title: caps["title"].to_owned(),
+            description,
+            min_units: units.iter().copied().reduce(f32::min),
+            max_units: units.iter().copied().reduce(f32::max),
+            prerequisites,
+            cross_listings,
+        });
+    }
+
+    courses
+}
+
+/// Scrapes a subject's page of the catalog and stores its courses.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `subject`: The subject code.
+///
+/// # Returns
+/// The number of courses stored.
+async fn scrape_subject(state: &WrapperState, subject: &str) -> Result<usize, String> {
+    let html = state
+        .client
+        .get(format!("{CATALOG_BASE_URL}/{subject}.html"))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .text()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let courses = parse_catalog_page(&html, subject);
+    state
+        .schedule_db
+        .upsert_catalog_courses(&courses)
+        .map_err(|e| e.to_string())?;
+    Ok(courses.len())
+}
+
+/// Scrapes the catalog once a week, if `scrapeCatalog` is set. Every subject that WebReg has
+/// been scraped for is looked up.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+pub async fn run_catalog_scrape(state: Arc<WrapperState>) {
+    if !state.scrape_catalog {
+        return;
+    }
+
+    let mut interval = tokio::time::interval(CATALOG_SCRAPE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if !state.leader.is_leader() {
+            continue;
+        }
+
+        let subjects = match state.schedule_db.get_scraped_subjects() {
+            Ok(subjects) => subjects,
+            Err(e) => {
+                warn!("Failed to get the subjects to scrape the catalog for: {e}");
+                continue;
+            }
+        };
+
+        let mut scraped = 0;
+        for subject in &subjects {
+            if state.should_stop() {
+                return;
+            }
+
+            match scrape_subject(&state, subject).await {
+                Ok(count) => scraped += count,
+                // Not every subject has its own page
+                Err(e) => warn!("Failed to scrape the catalog for {subject}: {e}"),
+            }
+            tokio::time::sleep(SUBJECT_DELAY).await;
+        }
+
+        info!(
+            "Scraped {scraped} course(s) from the catalog across {} subject(s).",
+            subjects.len()
+        );
+
+        let stored = state.schedule_db.get_catalog_courses().and_then(|courses| {
+            state.schedule_db.replace_cross_listings(
+                "",
+                CrossListingSource::Catalog,
+                &catalog_cross_listings(&courses),
+            )
+        });
+        if let Err(e) = stored {
+            warn!("Failed to store the catalog's cross-listed courses: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_catalog_page() {
+        let html = r#"<html><body>
+            <h2>Courses</h2>
+            <p class="course-name">CSE 100/MATH 176. Advanced Data
+                Structures (4)</p>
+            <p class="course-descriptions">High-performance data structures. Cross-listed with
+                MATH 176. <strong class="italic">Prerequisites:</strong> CSE 21 and CSE 30.</p>
+            <p class="course-name">CSE 198. Directed Group Study (2&ndash;4)</p>
+            <p class="course-descriptions">Computer science topics in a small group.</p>
+            <p class="course-name">Not a course</p>
+            <p class="course-name">CSE 87. Freshman Seminar (1)</p>
+        </body></html>"#;
+
+        let courses = parse_catalog_page(html, "CSE");
+        assert_eq!(3, courses.len());
+
+        let cse100 = &courses[0];
+        assert_eq!("CSE 100", cse100.code());
+        assert_eq!("Advanced Data Structures", cse100.title);
+        assert_eq!(
+            "High-performance data structures. Cross-listed with MATH 176.",
+            cse100.description
+        );
+        assert_eq!(Some("CSE 21 and CSE 30."), cse100.prerequisites.as_deref());
+        assert_eq!(vec!["MATH 176".to_owned()], cse100.cross_listings);
+        assert_eq!((Some(4.0), Some(4.0)), (cse100.min_units, cse100.max_units));
+
+        assert_eq!(
+            (Some(2.0), Some(4.0)),
+            (courses[1].min_units, courses[1].max_units)
+        );
+        assert_eq!("", courses[2].description);
+
+        // On the other subject's page, the course is stored under that subject
+        let courses = parse_catalog_page(html, "MATH");
+        assert_eq!("MATH 176", courses[0].code());
+        assert_eq!(vec!["CSE 100".to_owned()], courses[0].cross_listings);
+    }
+}
@@ -0,0 +1,349 @@
+//! Detection of changes to tracked sections, so that clients can be notified as soon as
+//! the tracker sees them instead of having to poll the API.
+
+use std::collections::HashMap;
+
+use dashmap::DashMap;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use webweg::types::CourseSection;
+
+/// The number of events that can be buffered for slow subscribers before they start
+/// missing events.
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// An event describing a change to a tracked section.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SectionEvent {
+    /// A section has more available seats than it did (e.g., it had none, and now has some).
+    SeatsOpened {
+        term: String,
+        subj_course_id: String,
+        section_code: String,
+        section_id: String,
+        /// The number of available seats that the section had before.
+        previous_seats: i64,
+        available_seats: i64,
+        total_seats: i64,
+        /// When the section opens to non-majors, if it's restricted to majors until then
+        /// (see [`crate::restrictions`]).
+        opens_to_non_majors: Option<String>,
+        /// A summary of the event, for showing to students.
+        message: String,
+    },
+    /// The number of students on a section's waitlist changed.
+    WaitlistChanged {
+        term: String,
+        subj_course_id: String,
+        section_code: String,
+        section_id: String,
+        old_waitlist_ct: i64,
+        new_waitlist_ct: i64,
+    },
+    /// A section that was previously offered no longer appears for its course.
+    SectionCancelled {
+        term: String,
+        subj_course_id: String,
+        section_code: String,
+        section_id: String,
+    },
+}
+
+impl SectionEvent {
+    /// The term that this event is for.
+    pub fn term(&self) -> &str {
+        match self {
+            SectionEvent::SeatsOpened { term, .. }
+            | SectionEvent::WaitlistChanged { term, .. }
+            | SectionEvent::SectionCancelled { term, .. } => term,
+        }
+    }
+
+    /// The ID of the section that this event is for.
+    pub fn section_id(&self) -> &str {
+        match self {
+            SectionEvent::SeatsOpened { section_id, .. }
+            | SectionEvent::WaitlistChanged { section_id, .. }
+            | SectionEvent::SectionCancelled { section_id, .. } => section_id,
+        }
+    }
+}
+
+/// The values of a section that we compare between polls.
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionSnapshot {
+    pub section_id: String,
+    pub available_seats: i64,
+    pub total_seats: i64,
+    pub waitlist_ct: i64,
+}
+
+/// Keeps the last seen state of every tracked section and broadcasts any changes.
+pub struct LiveFeed {
+    /// The last seen sections, keyed by term and course, then by section code.
+    snapshots: DashMap<(String, String), HashMap<String, SectionSnapshot>>,
+    /// The sender used to publish events to all subscribers.
+    sender: broadcast::Sender<SectionEvent>,
+}
+
+impl Default for LiveFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LiveFeed {
+    /// Creates a new live feed with no subscribers.
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self {
+            snapshots: DashMap::new(),
+            sender,
+        }
+    }
+
+    /// Subscribes to all future events.
+    ///
+    /// # Returns
+    /// The receiver for the events.
+    pub fn subscribe(&self) -> broadcast::Receiver<SectionEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Gets the last seen state of a section.
+    ///
+    /// # Parameters
+    /// - `term`: The term that the section is in.
+    /// - `section_id`: The section's ID.
+    ///
+    /// # Returns
+    /// The section's seats, or `None` if the tracker hasn't seen it.
+    pub fn last_seen(&self, term: &str, section_id: &str) -> Option<SectionSnapshot> {
+        self.snapshots
+            .iter()
+            .filter(|entry| entry.key().0 == term)
+            .find_map(|entry| {
+                entry
+                    .value()
+                    .values()
+                    .find(|s| s.section_id == section_id)
+                    .cloned()
+            })
+    }
+
+    /// Gets the sections of a course whose seats differ from the last time the course was
+    /// seen, including sections that weren't seen before. Nothing is recorded; see
+    /// [`LiveFeed::observe`].
+    ///
+    /// # Parameters
+    /// - `term`: The term that the sections are for.
+    /// - `sections`: All sections of one course, as returned by WebReg.
+    ///
+    /// # Returns
+    /// The IDs of the sections that changed.
+    pub fn changed_sections(&self, term: &str, sections: &[CourseSection]) -> Vec<String> {
+        let Some(first) = sections.first() else {
+            return vec![];
+        };
+
+        let key = (term.to_owned(), first.subj_course_id.trim().to_owned());
+        let previous = self.snapshots.get(&key);
+        sections
+            .iter()
+            .filter(|s| {
+                let before = previous.as_ref().and_then(|p| p.get(&s.section_code));
+                before.is_none_or(|before| {
+                    before.section_id != s.section_id
+                        || before.available_seats != s.available_seats
+                        || before.total_seats != s.total_seats
+                        || before.waitlist_ct != s.waitlist_ct
+                })
+            })
+            .map(|s| s.section_id.clone())
+            .collect()
+    }
+
+    /// Compares the sections for a single course against the last time it was seen and
+    /// publishes any changes. The first observation of a course only records its state.
+    ///
+    /// # Parameters
+    /// - `term`: The term that the sections are for.
+    /// - `sections`: All sections of one course, as returned by WebReg.
+    /// - `opens_to_non_majors`: Gets the date that a section opens to non-majors on, given
+    ///   its ID, if it hasn't yet. This is only called for sections whose seats opened.
+    ///
+    /// # Returns
+    /// The events that were detected.
+    pub fn observe(
+        &self,
+        term: &str,
+        sections: &[CourseSection],
+        opens_to_non_majors: impl Fn(&str) -> Option<String>,
+    ) -> Vec<SectionEvent> {
+        let Some(first) = sections.first() else {
+            return vec![];
+        };
+
+        let subj_course_id = first.subj_course_id.trim().to_owned();
+        let current: HashMap<String, SectionSnapshot> = sections
+            .iter()
+            .map(|s| {
+                (
+                    s.section_code.clone(),
+                    SectionSnapshot {
+                        section_id: s.section_id.clone(),
+                        available_seats: s.available_seats,
+                        total_seats: s.total_seats,
+                        waitlist_ct: s.waitlist_ct,
+                    },
+                )
+            })
+            .collect();
+
+        let key = (term.to_owned(), subj_course_id.clone());
+        let Some(previous) = self.snapshots.insert(key, current.clone()) else {
+            return vec![];
+        };
+
+        let mut events = vec![];
+        for (section_code, now) in &current {
+            let Some(before) = previous.get(section_code) else {
+                continue;
+            };
+
+            if now.available_seats > 0 && now.available_seats > before.available_seats {
+                let opens = opens_to_non_majors(&now.section_id);
+                let mut message = format!(
+                    "{} of {} seats are open in {subj_course_id} {section_code}",
+                    now.available_seats, now.total_seats
+                );
+                match &opens {
+                    Some(date) => message.push_str(&format!(
+                        ", but only to majors; it opens to non-majors on {date}."
+                    )),
+                    None => message.push('.'),
+                }
+
+                events.push(SectionEvent::SeatsOpened {
+                    term: term.to_owned(),
+                    subj_course_id: subj_course_id.clone(),
+                    section_code: section_code.clone(),
+                    section_id: now.section_id.clone(),
+                    previous_seats: before.available_seats.max(0),
+                    available_seats: now.available_seats,
+                    total_seats: now.total_seats,
+                    opens_to_non_majors: opens,
+                    message,
+                });
+            }
+
+            if before.waitlist_ct != now.waitlist_ct {
+                events.push(SectionEvent::WaitlistChanged {
+                    term: term.to_owned(),
+                    subj_course_id: subj_course_id.clone(),
+                    section_code: section_code.clone(),
+                    section_id: now.section_id.clone(),
+                    old_waitlist_ct: before.waitlist_ct,
+                    new_waitlist_ct: now.waitlist_ct,
+                });
+            }
+        }
+
+        for (section_code, before) in &previous {
+            if !current.contains_key(section_code) {
+                events.push(SectionEvent::SectionCancelled {
+                    term: term.to_owned(),
+                    subj_course_id: subj_course_id.clone(),
+                    section_code: section_code.clone(),
+                    section_id: before.section_id.clone(),
+                });
+            }
+        }
+
+        for event in &events {
+            // An error here just means nobody is listening right now.
+            let _ = self.sender.send(event.clone());
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(code: &str, available: i64, waitlist: i64) -> CourseSection {
+        CourseSection {
+            subj_course_id: "CSE 100".to_string(),
+            section_id: format!("1{code}"),
+            section_code: code.to_string(),
+            available_seats: available,
+            total_seats: 100,
+            enrolled_ct: 100 - available,
+            waitlist_ct: waitlist,
+            all_instructors: vec![],
+            meetings: vec![],
+            is_visible: true,
+        }
+    }
+
+    #[test]
+    fn test_first_observation_is_silent() {
+        let feed = LiveFeed::new();
+        assert!(feed
+            .observe("FA24", &[section("A01", 0, 5)], |_| None)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_changes_are_detected() {
+        let feed = LiveFeed::new();
+        let mut rx = feed.subscribe();
+        feed.observe(
+            "FA24",
+            &[section("A01", 0, 5), section("A02", 0, 0)],
+            |_| None,
+        );
+        let events = feed.observe("FA24", &[section("A01", 3, 2)], |id| {
+            (id == "1A01").then(|| "2024-05-24".to_string())
+        });
+
+        assert_eq!(3, events.len());
+        assert!(events.iter().any(|e| matches!(
+            e,
+            SectionEvent::SeatsOpened { section_code, available_seats: 3, .. } if section_code == "A01"
+        )));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            SectionEvent::SeatsOpened { message, .. }
+                if message == "3 of 100 seats are open in CSE 100 A01, but only to majors; it \
+                               opens to non-majors on 2024-05-24."
+        )));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            SectionEvent::WaitlistChanged {
+                old_waitlist_ct: 5,
+                new_waitlist_ct: 2,
+                ..
+            }
+        )));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            SectionEvent::SectionCancelled { section_code, .. } if section_code == "A02"
+        )));
+        assert_eq!("FA24", rx.try_recv().unwrap().term());
+
+        // More seats opening in a section that already had some is reported too
+        let events = feed.observe("FA24", &[section("A01", 5, 2)], |_| None);
+        assert!(matches!(
+            events.as_slice(),
+            [SectionEvent::SeatsOpened {
+                previous_seats: 3,
+                available_seats: 5,
+                ..
+            }]
+        ));
+    }
+}
@@ -1,2 +1,6 @@
+pub mod catalog;
+pub mod live;
+pub mod term_scrape;
+pub mod throttle;
 pub mod tracker;
 mod util;
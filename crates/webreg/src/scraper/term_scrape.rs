@@ -0,0 +1,555 @@
+//! Full-term scrapes, which store every section of every course in a term.
+//!
+//! A scrape walks the term's departments one at a time, and the departments are recorded in
+//! the database as its checkpoints (see the `scrape_jobs` table). If the scraper crashes or
+//! is stopped partway through, the scrape is resumed from the first department that wasn't
+//! finished once the scraper is logged in again, rather than starting over. Each course's
+//! sections replace whatever was stored for it before, so scraping a department twice
+//! doesn't duplicate anything, and the differences are recorded for the change feed (see
+//! [`crate::changes`]).
+//!
+//! Sections that a scrape no longer finds are kept, but marked as cancelled, so that their
+//! history isn't lost; they're left out of the API's responses unless asked for. A
+//! section missing from its course is marked when the course is scraped, and the sections
+//! of courses that disappeared altogether are marked once the whole term has been scraped.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use dashmap::mapref::entry::Entry;
+use thiserror::Error;
+use tracing::{info, warn};
+use webweg::types::{CourseSection, WrapperError};
+use webweg::wrapper::input_types::{SearchRequestBuilder, SearchType};
+use webweg::ww_parser::parse_enrollment_count;
+
+use crate::changes::{diff_sections, SectionSnapshot};
+use crate::cross_listings::refresh_term_cross_listings;
+use crate::db::{
+    normalize_course_code, SCRAPE_JOB_COMPLETED, SCRAPE_JOB_FAILED, SCRAPE_JOB_RUNNING,
+};
+use crate::drift::ResponseKind;
+use crate::payload_store::webreg_payload_key;
+use crate::restrictions::{apply_window_rules, notes_by_section};
+use crate::schedule::{canonical_csv, MeetingDates};
+use crate::types::{TermInfo, WrapperState};
+
+/// The number of courses in a row that can fail before the scrape stops. This usually
+/// means that the scraper was logged out.
+const MAX_CONSECUTIVE_FAILURES: usize = 12;
+/// The delay between requests for courses.
+const COURSE_DELAY: Duration = Duration::from_secs(2);
+
+/// Why a course couldn't be scraped.
+#[derive(Debug, Error)]
+pub enum ScrapeCourseError {
+    #[error("failed to fetch sections: {0}")]
+    Fetch(#[from] WrapperError),
+    #[error("failed to store sections: {0}")]
+    Store(#[from] rusqlite::Error),
+}
+
+/// Parses a course's sections out of WebReg's raw response for the course, the same way
+/// that the parsed wrapper does.
+///
+/// # Parameters
+/// - `body`: The raw response.
+/// - `subj_code`: The course's subject code.
+/// - `course_code`: The course's code.
+///
+/// # Returns
+/// The course's sections, or an error if the response couldn't be parsed.
+pub fn parse_course_info(
+    body: &str,
+    subj_code: &str,
+    course_code: &str,
+) -> webweg::types::Result<Vec<CourseSection>> {
+    let meetings = serde_json::from_str(body)?;
+    let subj_course_id = format!("{} {}", subj_code.trim(), course_code.trim()).to_uppercase();
+    parse_enrollment_count(meetings, subj_course_id)
+}
+
+/// Fetches a course's sections, along with the dates of meetings that only run for part of
+/// the term, and stores them in place of whatever was stored for the course before.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `info`: The term information.
+/// - `subj_code`: The course's subject code.
+/// - `course_code`: The course's code.
+/// - `job_id`: The scrape that the course is part of, or nothing for the tracker's initial
+///   scrape. WebReg's response is archived under it, if payloads are archived.
+///
+/// # Returns
+/// The number of sections that were stored.
+pub async fn store_course(
+    state: &WrapperState,
+    info: &TermInfo,
+    subj_code: &str,
+    course_code: &str,
+    job_id: Option<i64>,
+) -> Result<usize, ScrapeCourseError> {
+    // The parsed sections don't include meeting dates, which matter for courses that
+    // only run for part of the term, so both are taken from the same raw response
+    let raw = info
+        .wrapper
+        .req(info.term.as_str())
+        .raw()
+        .get_course_info(subj_code, course_code)
+        .await;
+    state
+        .drift_tracker
+        .observe_result(ResponseKind::CourseInfo, &raw);
+    let body = raw?;
+    if let Some(archive) = &state.payload_archive {
+        archive.archive(
+            webreg_payload_key(&info.term, job_id, subj_code, course_code),
+            body.clone().into_bytes(),
+        );
+    }
+    let meeting_dates = MeetingDates::from_raw_course_info(&body);
+    let sections = parse_course_info(&body, subj_code, course_code)?;
+
+    let count = sections.len();
+    let Some(subj_course_id) = sections.first().map(|s| s.subj_course_id.clone()) else {
+        return Ok(0);
+    };
+
+    // Compare the sections to the stored ones for the change feed. A course's first scrape
+    // isn't compared, since every section would show up as added
+    let term = info.term.as_str();
+    let before = state
+        .schedule_db
+        .get_course_snapshots(term, &subj_course_id)?;
+    let after: Vec<_> = sections.iter().map(SectionSnapshot::from_section).collect();
+    let section_codes: Vec<_> = sections
+        .iter()
+        .map(|s| (s.section_id.clone(), s.section_code.clone()))
+        .collect();
+    let section_ids: Vec<_> = section_codes.iter().map(|(id, _)| id.clone()).collect();
+
+    // Sections that were stored before but are missing now are kept as cancelled
+    state
+        .schedule_db
+        .clear_course_sections(term, &subj_course_id, &section_ids)?;
+    state.schedule_db.insert_course_with_sections(
+        term,
+        sections,
+        info.date_range,
+        &meeting_dates,
+    )?;
+
+    // Notes are only informational, so the course's sections are kept even if its notes
+    // can't be fetched. They're sorted by section from their JSON form, and the department's
+    // configured enrollment window, if any, is added to them
+    match info
+        .wrapper
+        .req(term)
+        .parsed()
+        .get_section_notes_by_course(subj_code, course_code)
+        .await
+    {
+        Ok(notes) => {
+            let mut notes = notes_by_section(
+                &serde_json::to_value(&notes).unwrap_or_default(),
+                &section_codes,
+                term,
+            );
+            apply_window_rules(
+                &mut notes,
+                &section_ids,
+                &state.restriction_windows,
+                term,
+                subj_code,
+                &normalize_course_code(&subj_course_id),
+            );
+            state
+                .schedule_db
+                .replace_section_notes(term, &section_ids, &notes)?;
+        }
+        Err(e) => warn!("[{term}] Failed to fetch section notes for {subj_course_id}: {e}"),
+    }
+
+    if !before.is_empty() {
+        let changes = diff_sections(&before, &after);
+        if !changes.is_empty() {
+            info!(
+                "[{term}] {} section(s) of {subj_course_id} changed since the last scrape.",
+                changes.len()
+            );
+        }
+        state.schedule_db.record_section_changes(
+            term,
+            &subj_course_id,
+            &changes,
+            chrono::Utc::now().timestamp_millis(),
+        )?;
+    }
+
+    Ok(count)
+}
+
+/// Why a scrape couldn't be started.
+#[derive(Debug, Error)]
+pub enum StartScrapeError {
+    #[error("scrape {0} of this term is already running")]
+    AlreadyRunning(i64),
+    #[error("failed to access scrapes: {0}")]
+    Db(#[from] rusqlite::Error),
+}
+
+/// Starts a scrape of a term in the background. If the term's most recent scrape didn't
+/// complete, it's resumed from its last checkpoint; otherwise, a new scrape is started.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `term`: The term to scrape.
+///
+/// # Returns
+/// The scrape's ID, and whether an earlier scrape was resumed.
+pub fn start_term_scrape(
+    state: &Arc<WrapperState>,
+    term: &str,
+) -> Result<(i64, bool), StartScrapeError> {
+    // Only one scrape of a term may run at a time, and the term stays reserved while the
+    // scrape is picked, so that two requests can't both start one
+    let slot = match state.active_term_scrapes.entry(term.to_owned()) {
+        Entry::Occupied(e) => return Err(StartScrapeError::AlreadyRunning(*e.get())),
+        Entry::Vacant(slot) => slot,
+    };
+
+    let (job_id, resumed) = match state.schedule_db.get_latest_scrape_job(term)? {
+        Some(job) if job.status != SCRAPE_JOB_COMPLETED => {
+            state
+                .schedule_db
+                .set_scrape_job_status(job.job_id, SCRAPE_JOB_RUNNING, None)?;
+            (job.job_id, true)
+        }
+        _ => (state.schedule_db.insert_scrape_job(term)?, false),
+    };
+
+    slot.insert(job_id);
+    tokio::spawn(run_term_scrape(state.clone(), job_id, term.to_owned()));
+    Ok((job_id, resumed))
+}
+
+/// Resumes every scrape that was still running when the scraper last stopped.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+pub async fn resume_term_scrapes(state: Arc<WrapperState>) {
+    let jobs = match state.schedule_db.get_running_scrape_jobs() {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            warn!("Failed to look up interrupted scrapes: {e}");
+            return;
+        }
+    };
+
+    for job in jobs {
+        match start_term_scrape(&state, &job.term) {
+            Ok((job_id, _)) => info!(
+                "[{}] Resuming scrape {} ({}/{} departments done).",
+                job.term, job_id, job.departments_done, job.departments_total
+            ),
+            Err(StartScrapeError::AlreadyRunning(_)) => {}
+            Err(e) => warn!("[{}] Failed to resume scrape {}: {e}", job.term, job.job_id),
+        }
+    }
+}
+
+/// Runs a full-term scrape until every department is done, the scrape fails, or the
+/// scraper is stopped. In the last case, the scrape is left running so that it's resumed
+/// on the next start. The term must already be reserved for the scrape.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `job_id`: The scrape's ID.
+/// - `term`: The term to scrape.
+async fn run_term_scrape(state: Arc<WrapperState>, job_id: i64, term: String) {
+    let result = walk_departments(&state, job_id, &term).await;
+    let (status, error) = match result {
+        Ok(true) => {
+            info!("[{term}] Scrape {job_id} is complete.");
+            reconcile_term(&state, job_id, &term);
+            if let Err(e) = state.refresh_heatmap(&term) {
+                warn!("[{term}] Failed to compute class density heatmap: {e}");
+            }
+            if let Err(e) = refresh_term_cross_listings(&state.schedule_db, &term) {
+                warn!("[{term}] Failed to detect cross-listed courses: {e}");
+            }
+            if let Err(e) = state.schedule_db.record_course_offerings(&term) {
+                warn!("[{term}] Failed to record course offerings: {e}");
+            }
+
+            // Record what the scrape produced, so that exports can be checked against it
+            let recorded = state
+                .schedule_db
+                .get_schedule_export(&term)
+                .map(|rows| canonical_csv(&rows).1)
+                .and_then(|hash| {
+                    info!("[{term}] Scrape {job_id} produced dataset {hash}.");
+                    state.schedule_db.set_scrape_job_dataset_hash(job_id, &hash)
+                });
+            if let Err(e) = recorded {
+                warn!("[{term}] Failed to record the dataset hash of scrape {job_id}: {e}");
+            }
+
+            (SCRAPE_JOB_COMPLETED, None)
+        }
+        Ok(false) => {
+            info!("[{term}] Scrape {job_id} was stopped; it'll be resumed on the next start.");
+            state.active_term_scrapes.remove(&term);
+            return;
+        }
+        Err(e) => {
+            warn!("[{term}] Scrape {job_id} failed: {e}");
+            (SCRAPE_JOB_FAILED, Some(e))
+        }
+    };
+
+    if let Err(e) = state
+        .schedule_db
+        .set_scrape_job_status(job_id, status, error.as_deref())
+    {
+        warn!("[{term}] Failed to record the status of scrape {job_id}: {e}");
+    }
+
+    // Only release the term once the status is recorded, so that starting another scrape
+    // doesn't pick this one up again
+    state.active_term_scrapes.remove(&term);
+}
+
+/// Marks the sections of a term that a completed scrape didn't find as cancelled. If any
+/// course failed to be scraped, nothing is marked, since its sections weren't seen either.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `job_id`: The scrape's ID.
+/// - `term`: The term that was scraped.
+fn reconcile_term(state: &WrapperState, job_id: i64, term: &str) {
+    let job = match state.schedule_db.get_scrape_job(job_id) {
+        Ok(Some(job)) => job,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("[{term}] Failed to look up scrape {job_id} to reconcile sections: {e}");
+            return;
+        }
+    };
+
+    if job.courses_failed > 0 {
+        info!(
+            "[{term}] Not looking for cancelled sections, since {} course(s) failed to be scraped.",
+            job.courses_failed
+        );
+        return;
+    }
+
+    match state
+        .schedule_db
+        .cancel_unseen_sections(term, &job.started_at)
+    {
+        Ok(0) => {}
+        Ok(cancelled) => info!("[{term}] Marked {cancelled} section(s) as cancelled."),
+        Err(e) => warn!("[{term}] Failed to mark cancelled sections: {e}"),
+    }
+}
+
+/// Scrapes every department of a term that the scrape hasn't finished yet.
+///
+/// # Returns
+/// Whether every department is done, or why the scrape had to stop.
+async fn walk_departments(state: &WrapperState, job_id: i64, term: &str) -> Result<bool, String> {
+    let info = state
+        .term(term)
+        .ok_or_else(|| format!("the term {term} isn't tracked"))?;
+
+    // The departments are only listed once per scrape, so that resuming doesn't depend on
+    // WebReg listing the same departments again
+    let mut pending = state
+        .schedule_db
+        .get_pending_scrape_departments(job_id)
+        .map_err(|e| e.to_string())?;
+    let is_new = state
+        .schedule_db
+        .get_scrape_job(job_id)
+        .map_err(|e| e.to_string())?
+        .is_some_and(|job| job.departments_total == 0);
+    if is_new {
+        let depts: Vec<String> = info
+            .wrapper
+            .req(term)
+            .parsed()
+            .get_department_codes()
+            .await
+            .map_err(|e| format!("failed to list departments: {e}"))?;
+        state
+            .schedule_db
+            .add_scrape_departments(job_id, &depts)
+            .map_err(|e| e.to_string())?;
+        pending = state
+            .schedule_db
+            .get_pending_scrape_departments(job_id)
+            .map_err(|e| e.to_string())?;
+    }
+
+    info!(
+        "[{term}] Scrape {job_id} has {} departments left.",
+        pending.len()
+    );
+    let mut consecutive_failures = 0;
+    for dept in pending {
+        let mut courses = info
+            .wrapper
+            .req(term)
+            .parsed()
+            .search_courses(SearchType::Advanced(
+                SearchRequestBuilder::new().add_department(&dept),
+            ))
+            .await
+            .map_err(|e| format!("failed to search department {dept}: {e}"))?;
+        // Scrape in a fixed order, whatever order WebReg lists the courses in
+        courses.sort_by(|a, b| {
+            (a.subj_code.trim(), a.course_code.trim())
+                .cmp(&(b.subj_code.trim(), b.course_code.trim()))
+        });
+
+        let (mut scraped, mut failed) = (0, 0);
+        for course in &courses {
+            if state.should_stop() {
+                return Ok(false);
+            }
+
+            let (subj_code, course_code) = (course.subj_code.trim(), course.course_code.trim());
+            match store_course(state, &info, subj_code, course_code, Some(job_id)).await {
+                Ok(_) => {
+                    scraped += 1;
+                    consecutive_failures = 0;
+                }
+                Err(e) => {
+                    warn!("[{term}] Failed to scrape {subj_code} {course_code}: {e}");
+                    failed += 1;
+                    consecutive_failures += 1;
+                    if consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
+                        return Err(format!(
+                            "{consecutive_failures} courses in a row failed; last error: {e}"
+                        ));
+                    }
+                }
+            }
+
+            tokio::time::sleep(COURSE_DELAY).await;
+        }
+
+        state
+            .schedule_db
+            .complete_scrape_department(job_id, &dept, scraped, failed)
+            .map_err(|e| e.to_string())?;
+        info!("[{term}] Scraped department {dept} ({scraped} courses, {failed} failed).");
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::types::tests::state;
+
+    fn depts(codes: &[&str]) -> Vec<String> {
+        codes.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn test_department_checkpoints() {
+        let s = state("scrape-checkpoints", &["FA24"], json!({}));
+        let db = &s.schedule_db;
+        let job_id = db.insert_scrape_job("FA24").unwrap();
+        db.add_scrape_departments(job_id, &depts(&["MATH", "BILD", "CSE"]))
+            .unwrap();
+        db.complete_scrape_department(job_id, "BILD", 10, 1)
+            .unwrap();
+
+        assert_eq!(
+            depts(&["CSE", "MATH"]),
+            db.get_pending_scrape_departments(job_id).unwrap()
+        );
+        let job = db.get_scrape_job(job_id).unwrap().unwrap();
+        assert_eq!((3, 1), (job.departments_total, job.departments_done));
+        assert_eq!((10, 1), (job.courses_scraped, job.courses_failed));
+
+        // Listing the departments again keeps what was already done
+        db.add_scrape_departments(job_id, &depts(&["BILD", "CSE", "MATH"]))
+            .unwrap();
+        assert_eq!(
+            depts(&["CSE", "MATH"]),
+            db.get_pending_scrape_departments(job_id).unwrap()
+        );
+        assert_eq!(
+            1,
+            db.get_scrape_job(job_id).unwrap().unwrap().departments_done
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_resumes_unfinished_scrape() {
+        // Nothing is awaited here, so the scrapes that are started never reach WebReg
+        let s = Arc::new(state("scrape-start", &["FA24", "WI25"], json!({})));
+        let db = &s.schedule_db;
+        let failed = db.insert_scrape_job("FA24").unwrap();
+        db.add_scrape_departments(failed, &depts(&["BILD", "CSE"]))
+            .unwrap();
+        db.complete_scrape_department(failed, "BILD", 5, 0).unwrap();
+        db.set_scrape_job_status(failed, SCRAPE_JOB_FAILED, Some("logged out"))
+            .unwrap();
+
+        assert_eq!((failed, true), start_term_scrape(&s, "FA24").unwrap());
+        let job = db.get_scrape_job(failed).unwrap().unwrap();
+        assert_eq!(SCRAPE_JOB_RUNNING, job.status);
+        assert_eq!(
+            depts(&["CSE"]),
+            db.get_pending_scrape_departments(failed).unwrap()
+        );
+        assert!(matches!(
+            start_term_scrape(&s, "FA24"),
+            Err(StartScrapeError::AlreadyRunning(id)) if id == failed
+        ));
+
+        // A term whose last scrape completed gets a new one
+        let completed = db.insert_scrape_job("WI25").unwrap();
+        db.set_scrape_job_status(completed, SCRAPE_JOB_COMPLETED, None)
+            .unwrap();
+        let (job_id, resumed) = start_term_scrape(&s, "WI25").unwrap();
+        assert!(job_id != completed && !resumed);
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_scrape_resumed_on_start() {
+        let s = Arc::new(state("scrape-resume", &["FA24"], json!({})));
+        let job_id = s.schedule_db.insert_scrape_job("FA24").unwrap();
+        s.schedule_db
+            .add_scrape_departments(job_id, &depts(&["BILD", "CSE"]))
+            .unwrap();
+        for dept in ["BILD", "CSE"] {
+            s.schedule_db
+                .complete_scrape_department(job_id, dept, 3, 0)
+                .unwrap();
+        }
+
+        // The scraper stopped after the last checkpoint, but before the scrape was marked as
+        // complete, so resuming it has nothing left to scrape
+        resume_term_scrapes(s.clone()).await;
+        assert_eq!(Some(job_id), s.active_term_scrapes.get("FA24").map(|e| *e));
+        while s.active_term_scrapes.contains_key("FA24") {
+            tokio::task::yield_now().await;
+        }
+
+        let job = s.schedule_db.get_scrape_job(job_id).unwrap().unwrap();
+        assert_eq!(SCRAPE_JOB_COMPLETED, job.status);
+        assert_eq!(6, job.courses_scraped);
+        assert!(job.completed_at.is_some() && job.dataset_hash.is_some());
+        assert!(s.schedule_db.get_running_scrape_jobs().unwrap().is_empty());
+    }
+}
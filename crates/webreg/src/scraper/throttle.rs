@@ -0,0 +1,202 @@
+//! Keeping a flapping section from flooding a watcher with alerts.
+//!
+//! When one seat in a section is repeatedly taken and released, the live feed reports the
+//! seat opening every time. Each watch (a client of `/live/:term/ws`, or a saved watch with
+//! notification channels; see [`crate::enroll_jobs`]) filters the feed through its own
+//! [`AlertThrottle`], which can:
+//! - wait out a cooldown after alerting about a section before alerting about it again,
+//! - drop an alert if the same state (e.g., one open seat) was alerted about recently, and
+//! - only alert once enough seats are open (e.g., at least two).
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::live::SectionEvent;
+
+/// How a watch throttles its alerts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchSettings {
+    /// How long to wait after alerting about a section before alerting about it again.
+    pub cooldown: Duration,
+    /// How long an alert about the same state of a section is dropped for.
+    pub dedup_window: Duration,
+    /// The number of seats that need to be open before seat openings are alerted about.
+    pub min_open_seats: i64,
+}
+
+impl Default for WatchSettings {
+    fn default() -> Self {
+        Self {
+            cooldown: Duration::ZERO,
+            dedup_window: Duration::ZERO,
+            min_open_seats: 1,
+        }
+    }
+}
+
+/// The kind of an event, so that, e.g., a waitlist change doesn't start the cooldown for
+/// seat openings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum EventKind {
+    SeatsOpened,
+    WaitlistChanged,
+    SectionCancelled,
+}
+
+/// Decides which of the live feed's events a single watch is alerted about.
+pub struct AlertThrottle {
+    settings: WatchSettings,
+    /// When each section was last alerted about, by kind of event.
+    last_alert: HashMap<(String, EventKind), Instant>,
+    /// When each state of a section was last alerted about.
+    last_state: HashMap<(String, EventKind, i64), Instant>,
+}
+
+impl AlertThrottle {
+    /// Creates a throttle that hasn't alerted about anything yet.
+    ///
+    /// # Parameters
+    /// - `settings`: How to throttle the alerts.
+    pub fn new(settings: WatchSettings) -> Self {
+        Self {
+            settings,
+            last_alert: HashMap::new(),
+            last_state: HashMap::new(),
+        }
+    }
+
+    /// Decides whether to alert about an event, and records the alert if so.
+    ///
+    /// # Parameters
+    /// - `event`: The event.
+    /// - `now`: The current time.
+    ///
+    /// # Returns
+    /// `true` if the watch should be alerted.
+    pub fn admit(&mut self, event: &SectionEvent, now: Instant) -> bool {
+        let (kind, state) = match event {
+            SectionEvent::SeatsOpened {
+                previous_seats,
+                available_seats,
+                ..
+            } => {
+                // Only alert when the section reaches the minimum, not every time more seats
+                // open after that
+                let min = self.settings.min_open_seats;
+                if *available_seats < min || *previous_seats >= min {
+                    return false;
+                }
+                (EventKind::SeatsOpened, *available_seats)
+            }
+            SectionEvent::WaitlistChanged {
+                new_waitlist_ct, ..
+            } => (EventKind::WaitlistChanged, *new_waitlist_ct),
+            SectionEvent::SectionCancelled { .. } => (EventKind::SectionCancelled, 0),
+        };
+
+        let section = event.section_id().to_owned();
+        let within = |at: Option<&Instant>, window: Duration| {
+            at.is_some_and(|at| now.saturating_duration_since(*at) < window)
+        };
+        if within(
+            self.last_alert.get(&(section.clone(), kind)),
+            self.settings.cooldown,
+        ) || within(
+            self.last_state.get(&(section.clone(), kind, state)),
+            self.settings.dedup_window,
+        ) {
+            return false;
+        }
+
+        self.prune(now);
+        self.last_alert.insert((section.clone(), kind), now);
+        self.last_state.insert((section, kind, state), now);
+        true
+    }
+
+    /// Forgets the alerts that can no longer hold any event back.
+    fn prune(&mut self, now: Instant) {
+        let WatchSettings {
+            cooldown,
+            dedup_window,
+            ..
+        } = self.settings;
+        self.last_alert
+            .retain(|_, at| now.saturating_duration_since(*at) < cooldown);
+        self.last_state
+            .retain(|_, at| now.saturating_duration_since(*at) < dedup_window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opened(previous_seats: i64, available_seats: i64) -> SectionEvent {
+        SectionEvent::SeatsOpened {
+            term: "FA24".to_owned(),
+            subj_course_id: "CSE 100".to_owned(),
+            section_code: "A01".to_owned(),
+            section_id: "123456".to_owned(),
+            previous_seats,
+            available_seats,
+            total_seats: 100,
+            opens_to_non_majors: None,
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_flapping_section_is_throttled() {
+        let start = Instant::now();
+        let mins = |m: u64| start + Duration::from_secs(m * 60);
+
+        // By default, every opening is alerted about, but not more seats opening after
+        let mut any = AlertThrottle::new(WatchSettings::default());
+        assert!(any.admit(&opened(0, 1), start));
+        assert!(any.admit(&opened(0, 1), start));
+        assert!(!any.admit(&opened(1, 2), start));
+
+        // The same seat being released again is dropped within the dedup window, but a
+        // different number of seats isn't
+        let mut dedup = AlertThrottle::new(WatchSettings {
+            dedup_window: Duration::from_secs(10 * 60),
+            ..WatchSettings::default()
+        });
+        assert!(dedup.admit(&opened(0, 1), mins(0)));
+        assert!(!dedup.admit(&opened(0, 1), mins(5)));
+        assert!(dedup.admit(&opened(0, 2), mins(6)));
+        assert!(dedup.admit(&opened(0, 1), mins(11)));
+
+        // Nothing about the section is alerted during the cooldown, but other kinds of
+        // events still are
+        let mut cooldown = AlertThrottle::new(WatchSettings {
+            cooldown: Duration::from_secs(30 * 60),
+            ..WatchSettings::default()
+        });
+        assert!(cooldown.admit(&opened(0, 1), mins(0)));
+        assert!(!cooldown.admit(&opened(0, 3), mins(29)));
+        assert!(cooldown.admit(
+            &SectionEvent::WaitlistChanged {
+                term: "FA24".to_owned(),
+                subj_course_id: "CSE 100".to_owned(),
+                section_code: "A01".to_owned(),
+                section_id: "123456".to_owned(),
+                old_waitlist_ct: 3,
+                new_waitlist_ct: 2,
+            },
+            mins(29)
+        ));
+        assert!(cooldown.admit(&opened(0, 3), mins(30)));
+
+        // Escalation: a single seat isn't worth an alert, but reaching two seats is
+        let mut escalate = AlertThrottle::new(WatchSettings {
+            min_open_seats: 2,
+            ..WatchSettings::default()
+        });
+        assert!(!escalate.admit(&opened(0, 1), start));
+        assert!(escalate.admit(&opened(1, 2), start));
+        assert!(!escalate.admit(&opened(2, 4), start));
+        assert!(escalate.admit(&opened(0, 3), start));
+    }
+}
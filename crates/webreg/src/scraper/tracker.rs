@@ -11,7 +11,12 @@ use tracing::log::error;
 use tracing::{info, warn};
 use webweg::wrapper::input_types::{SearchRequestBuilder, SearchType};
 
+use crate::cross_listings::refresh_term_cross_listings;
+use crate::db::SeatSample;
+use crate::drift::ResponseKind;
+use crate::scraper::term_scrape::{parse_course_info, resume_term_scrapes, store_course};
 use crate::scraper::util::get_epoch_time;
+use crate::sync::{record_changes, seats_key, ChangeKind};
 use crate::types::{TermInfo, WrapperState};
 use {
     std::fs::OpenOptions,
@@ -45,9 +50,86 @@ pub async fn run_tracker(state: Arc<WrapperState>, verbose: bool) {
         return;
     }
 
-    // Scrape degree audit data FIRST (quick, runs before long schedule scrape)
+    // Full-term scrapes that were interrupted by the last shutdown can pick up again now
+    // that we're logged in
+    tokio::spawn(resume_term_scrapes(state.clone()));
+
+    // Scrape degree audit data FIRST (quick, runs before long schedule scrape). Audits come
+    // from the cookie server, so there's nothing to scrape without one
+    if state.cookie_server.is_some() {
+        scrape_degree_audit(&state).await;
+    } else {
+        info!("Skipping the degree audit scrape, since there's no cookie server.");
+    }
+
+    // Scrape schedule data for all terms ONCE at startup (slow, ~1 hour for 1904 courses)
+    for term_data in state.terms().into_iter().filter(|t| t.is_logged_in()) {
+        if let Err(e) = scrape_initial_schedule_data(&state, &term_data).await {
+            warn!("[{}] Failed to scrape initial schedule data: {}", term_data.term, e);
+        }
+    }
+
+    loop {
+        // Terms that couldn't be logged in aren't tracked, so that they don't stop the others
+        let all_terms: Vec<_> = state
+            .terms()
+            .into_iter()
+            .filter(|t| t.is_logged_in())
+            .collect();
+        let current_loop_stop_flag = Arc::new(AtomicBool::new(false));
+        let mut futures = FuturesUnordered::new();
+        for term_data in &all_terms {
+            futures.push(track_webreg_enrollment(
+                &state,
+                term_data,
+                verbose,
+                current_loop_stop_flag.clone(),
+            ));
+        }
+
+        // Wait until ONE of the futures completed, indicating that ONE of the
+        // runners is now done.
+        futures.next().await;
+        info!("A tracker is currently done. Attempting to stop other trackers.");
+        current_loop_stop_flag.store(true, Ordering::SeqCst);
+        while let Some(()) = futures.next().await {
+            // Do nothing.
+        }
+
+        info!("All trackers have been stopped.");
+        if state.should_stop() {
+            break;
+        }
+
+        // Another instance took over as the leader, so wait for the lease to come back
+        if !state.leader.is_leader() {
+            warn!("This instance isn't the leader anymore; pausing the tracker.");
+            state.leader.wait_until_leader().await;
+        }
+
+        // Attempt to login again.
+        if try_login(&state, false).await {
+            continue;
+        }
+
+        // Otherwise, gracefully quit.
+        break;
+    }
+
+    // This should only run if we're 100% done with this
+    // wrapper. For example, either the wrapper could not
+    // log back in or we forced it to stop.
+    info!("Quitting the tracker.");
+}
+
+/// Fetches and parses the cookie server's degree audit, saving both for manual inspection.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+async fn scrape_degree_audit(state: &Arc<WrapperState>) {
     info!("Starting degree audit scrape");
-    match crate::degree_audit::fetch_degree_audit(&state).await {
+    let timeout = state.degree_audit_client.poll_timeout(None);
+    match crate::degree_audit::fetch_degree_audit(state, false, timeout).await {
         Ok(raw_audit) => {
             info!("Successfully fetched degree audit data (ID: {})", raw_audit.audit_id);
 
@@ -101,56 +183,6 @@ pub async fn run_tracker(state: Arc<WrapperState>, verbose: bool) {
             warn!("Failed to fetch degree audit data: {}", e);
         }
     }
-
-    // Scrape schedule data for all terms ONCE at startup (slow, ~1 hour for 1904 courses)
-    for term_data in state.all_terms.values() {
-        if let Err(e) = scrape_initial_schedule_data(&state, term_data).await {
-            warn!("[{}] Failed to scrape initial schedule data: {}", term_data.term, e);
-        }
-    }
-
-    loop {
-        state.is_running.store(true, Ordering::SeqCst);
-
-        let current_loop_stop_flag = Arc::new(AtomicBool::new(false));
-        let mut futures = FuturesUnordered::new();
-        for term_data in state.all_terms.values() {
-            futures.push(track_webreg_enrollment(
-                &state,
-                term_data,
-                verbose,
-                current_loop_stop_flag.clone(),
-            ));
-        }
-
-        // Wait until ONE of the futures completed, indicating that ONE of the
-        // runners is now done.
-        futures.next().await;
-        info!("A tracker is currently done. Attempting to stop other trackers.");
-        current_loop_stop_flag.store(true, Ordering::SeqCst);
-        while let Some(()) = futures.next().await {
-            // Do nothing.
-        }
-        state.is_running.store(false, Ordering::SeqCst);
-
-        info!("All trackers have been stopped.");
-        if state.should_stop() {
-            break;
-        }
-
-        // Attempt to login again.
-        if try_login(&state, false).await {
-            continue;
-        }
-
-        // Otherwise, gracefully quit.
-        break;
-    }
-
-    // This should only run if we're 100% done with this
-    // wrapper. For example, either the wrapper could not
-    // log back in or we forced it to stop.
-    info!("Quitting the tracker.");
 }
 
 /// Scrapes initial schedule data (meeting times, days, locations) for a term.
@@ -174,7 +206,7 @@ async fn scrape_initial_schedule_data(
     // Search for all courses (same logic as enrollment tracking)
     let mut results = vec![];
     for search_query in &info.search_query {
-        let mut temp = state
+        let mut temp = info
             .wrapper
             .req(info.term.as_str())
             .parsed()
@@ -192,33 +224,37 @@ async fn scrape_initial_schedule_data(
             info!("[{}] Progress: {}/{} courses processed", info.term, idx, results.len());
         }
 
-        match state
-            .wrapper
-            .req(info.term.as_str())
-            .parsed()
-            .get_enrollment_count(course.subj_code.trim(), course.course_code.trim())
-            .await
-        {
-            Ok(sections) => {
-                // Insert into database
-                if let Err(e) = state.schedule_db.insert_course_with_sections(
-                    info.term.as_str(),
-                    sections,
-                ) {
-                    warn!("[{}] Failed to insert course {} {}: {}",
-                        info.term, course.subj_code, course.course_code, e);
-                }
-            }
-            Err(e) => {
-                warn!("[{}] Failed to fetch sections for {} {}: {}",
-                    info.term, course.subj_code, course.course_code, e);
-            }
+        let (subj_code, course_code) = (course.subj_code.trim(), course.course_code.trim());
+        if let Err(e) = store_course(state, info, subj_code, course_code, None).await {
+            warn!(
+                "[{}] Failed to scrape course {} {}: {}",
+                info.term, subj_code, course_code, e
+            );
         }
 
         tokio::time::sleep(Duration::from_secs(2)).await;
     }
 
     info!("[{}] Initial schedule data scrape complete", info.term);
+    if let Err(e) = state.refresh_heatmap(info.term.as_str()) {
+        warn!(
+            "[{}] Failed to compute class density heatmap: {}",
+            info.term, e
+        );
+    }
+    if let Err(e) = refresh_term_cross_listings(&state.schedule_db, info.term.as_str()) {
+        warn!(
+            "[{}] Failed to detect cross-listed courses: {}",
+            info.term, e
+        );
+    }
+    if let Err(e) = state
+        .schedule_db
+        .record_course_offerings(info.term.as_str())
+    {
+        warn!("[{}] Failed to record course offerings: {}", info.term, e);
+    }
+
     Ok(())
 }
 
@@ -331,13 +367,14 @@ async fn track_webreg_enrollment(
         w
     };
 
+    info.is_running.store(true, Ordering::SeqCst);
     let mut fail_count = 0;
     'main: loop {
         writer.flush().unwrap();
         let results = {
             let mut r = vec![];
             for search_query in &info.search_query {
-                let mut temp = state
+                let mut temp = info
                     .wrapper
                     .req(info.term.as_str())
                     .parsed()
@@ -366,8 +403,12 @@ async fn track_webreg_enrollment(
 
         for r in results {
             // If the stop flag is set so that the scraper itself should STOP, or we just need
-            // to stop for this iteration, then break out
-            if state.should_stop() || current_loop_stop_flag.load(Ordering::SeqCst) {
+            // to stop for this iteration (including because another instance took over as the
+            // leader), then break out
+            if state.should_stop()
+                || current_loop_stop_flag.load(Ordering::SeqCst)
+                || !state.leader.is_leader()
+            {
                 break 'main;
             }
 
@@ -382,16 +423,25 @@ async fn track_webreg_enrollment(
             // Start timing.
             let start_time = Instant::now();
 
-            let res = state
+            // Every raw response is checked against the schema we expect, so changes to
+            // WebReg's API show up in the drift report before they break parsing
+            let raw = info
                 .wrapper
                 .req(info.term.as_str())
-                .parsed()
-                .get_enrollment_count(r.subj_code.trim(), r.course_code.trim())
+                .raw()
+                .get_course_info(r.subj_code.trim(), r.course_code.trim())
                 .await;
+            state
+                .drift_tracker
+                .observe_result(ResponseKind::CourseInfo, &raw);
+            let res = raw.and_then(|body| {
+                parse_course_info(&body, r.subj_code.trim(), r.course_code.trim())
+            });
 
             match res {
                 Err(e) => {
                     fail_count += 1;
+                    info.tracker.record_failure();
                     warn!(
                         "[{}] An error occurred ({}). Skipping. (FAIL_COUNT: {})",
                         info.term, e, fail_count
@@ -399,6 +449,7 @@ async fn track_webreg_enrollment(
                 }
                 Ok(r) if !r.is_empty() => {
                     fail_count = 0;
+                    info.tracker.record_success();
                     if verbose {
                         info!(
                             "[{}] Processing {} section(s) for {}",
@@ -413,9 +464,49 @@ async fn track_webreg_enrollment(
                     for section in &r {
                         write_section_with_meetings(&mut writer, time, section).unwrap();
                     }
+
+                    let changed = state.live_feed.changed_sections(info.term.as_str(), &r);
+                    let history: Vec<_> = r
+                        .iter()
+                        .filter(|s| changed.contains(&s.section_id))
+                        .map(|s| SeatSample {
+                            subj_course_id: s.subj_course_id.trim().to_owned(),
+                            section_id: s.section_id.clone(),
+                            available_seats: s.available_seats,
+                            waitlist_ct: s.waitlist_ct,
+                            total_seats: s.total_seats,
+                            recorded_at: time,
+                        })
+                        .collect();
+                    if let Err(e) = state
+                        .schedule_db
+                        .record_seat_history(info.term.as_str(), &history)
+                    {
+                        warn!("[{}] Failed to record seat history: {}", info.term, e);
+                    }
+
+                    let changed: Vec<_> = changed
+                        .iter()
+                        .map(|id| seats_key(info.term.as_str(), id))
+                        .collect();
+                    record_changes(&state.schedule_db, ChangeKind::Seats, &changed);
+                    let today = chrono::Local::now().date_naive();
+                    state
+                        .live_feed
+                        .observe(info.term.as_str(), &r, |section_id| {
+                            let notes = state
+                                .schedule_db
+                                .get_section_notes(info.term.as_str(), section_id)
+                                .ok()??;
+                            notes
+                                .window
+                                .filter(|w| w.is_pending(today))
+                                .map(|w| w.opens_to_non_majors)
+                        });
                 }
                 _ => {
                     fail_count += 1;
+                    info.tracker.record_failure();
                     warn!(
                         "[{}] Course {} {} not found. Were you logged out? (FAIL_COUNT: {}).",
                         info.term,
@@ -436,6 +527,7 @@ async fn track_webreg_enrollment(
     }
 
     // Out of loop, this should run only if we need to exit the scraper (e.g., need to log back in)
+    info.is_running.store(false, Ordering::SeqCst);
     if !writer.buffer().is_empty() {
         info!(
             "[{}] Buffer not empty! Buffer has length {}.",
@@ -465,11 +557,6 @@ async fn track_webreg_enrollment(
 /// make requests again. `false` otherwise.
 async fn try_login(state: &Arc<WrapperState>, is_init: bool) -> bool {
     info!("Attempting to get new WebReg session cookies.");
-    let address = format!(
-        "{}:{}",
-        state.cookie_server.address, state.cookie_server.port
-    );
-
     let mut num_failures = 0;
     while num_failures <= MAX_NUM_LOGIN_FAILURES {
         if is_init {
@@ -496,131 +583,181 @@ async fn try_login(state: &Arc<WrapperState>, is_init: bool) -> bool {
             break;
         }
 
-        info!("Making a request to the cookie server (http://{address}/cookie) to get session cookies.");
-        let data = match state
-            .client
-            .get(format!("http://{address}/cookie"))
-            .send()
-            .await
-        {
-            Ok(o) => o,
-            Err(e) => {
-                warn!("Failed to connect to the cookie server; reason: '{e}'");
-                num_failures += 1;
-                continue;
-            }
-        };
+        let _guard = state.cookie_freshness.login_lock().lock().await;
+        if relogin(state).await {
+            return true;
+        }
 
-        let Ok(text) = data.text().await else {
-            warn!("An unknown error occurred when making a request to the cookie server.");
-            num_failures += 1;
-            continue;
+        num_failures += 1;
+    }
+
+    false
+}
+
+/// Requests new session cookies from the cookie server once, and then ensures that they're
+/// valid. Without a cookie server, the configured session cookies are used instead. The
+/// caller should hold the cookie login lock (see [`CookieFreshness`]).
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+///
+/// # Returns
+/// `true` if the new cookies are in use, and `false` otherwise.
+///
+/// [`CookieFreshness`]: crate::cookie_freshness::CookieFreshness
+pub async fn relogin(state: &Arc<WrapperState>) -> bool {
+    let Some(address) = state.cookie_server_address() else {
+        let Some(cookies) = &state.session_cookies else {
+            warn!("There's no cookie server or session cookies to log in with.");
+            return false;
         };
 
-        let json: Value = serde_json::from_str(text.as_str()).unwrap_or_default();
-        info!("Received response from cookie server: '{json}'");
-        if !json["cookie"].is_string() {
-            warn!("The 'cookie' key from the response is not valid.");
-            continue;
+        if login_with_cookies(state, cookies).await {
+            info!("The configured session cookies were authenticated.");
+            state.cookie_freshness.record_refresh();
+            return true;
         }
 
-        let cookies = json["cookie"].as_str().unwrap().to_string();
+        warn!("The configured session cookies couldn't be authenticated; they may have expired.");
+        return false;
+    };
 
-        // Update the cookies for the general wrapper, but also authenticate the cookies.
-        // Remember, we're sharing the same cookies.
-        if login_with_cookies(state, cookies.as_str()).await {
-            info!("Cookies were successfully fetched and authenticated for all terms specified.");
-            return true;
+    info!(
+        "Making a request to the cookie server (http://{address}/cookie) to get session cookies."
+    );
+    let data = match state
+        .client
+        .get(format!("http://{address}/cookie"))
+        .send()
+        .await
+    {
+        Ok(o) => o,
+        Err(e) => {
+            warn!("Failed to connect to the cookie server; reason: '{e}'");
+            return false;
         }
+    };
 
-        warn!("An unknown error occurred when trying to authenticate the cookies.");
-        num_failures += 1;
+    let Ok(text) = data.text().await else {
+        warn!("An unknown error occurred when making a request to the cookie server.");
+        return false;
+    };
+
+    let json: Value = serde_json::from_str(text.as_str()).unwrap_or_default();
+    info!("Received response from cookie server: '{json}'");
+    let Some(cookies) = json["cookie"].as_str() else {
+        warn!("The 'cookie' key from the response is not valid.");
+        return false;
+    };
+
+    // Update the cookies for the general wrapper, but also authenticate the cookies.
+    // Remember, we're sharing the same cookies.
+    if login_with_cookies(state, cookies).await {
+        info!("Cookies were successfully fetched and authenticated.");
+        state.cookie_freshness.record_refresh();
+        return true;
     }
 
+    warn!("An unknown error occurred when trying to authenticate the cookies.");
     false
 }
 
-/// Sets the cookies to the specified wrapper and then attempts to validate that the
-/// cookies are valid. This will attempt to make several requests until either one
-/// request is successful or all requests fail.
+/// Sets the cookies to every wrapper and then attempts to validate that the cookies can be
+/// used for each term. Each term is logged in on its own, so a term that can't be used
+/// doesn't keep the others from being tracked (see [`TermInfo::is_logged_in`]).
 ///
 /// # Parameters
 /// - `state`: The wrapper state.
 /// - `cookies`: The session cookies to use.
 ///
 /// # Returns
-/// `true` if the login process is successful, indicating that the wrapper is ready to
+/// `true` if at least one term was logged in, indicating that the wrapper is ready to
 /// make requests again. `false` otherwise.
 #[inline]
 async fn login_with_cookies(state: &Arc<WrapperState>, cookies: &str) -> bool {
     state.wrapper.set_cookies(cookies);
+    let all_terms = state.terms();
+    for term_data in &all_terms {
+        term_data.wrapper.set_cookies(cookies);
+    }
+
+    let mut any_logged_in = false;
+    for term_data in &all_terms {
+        let logged_in = login_term(term_data).await;
+        if !logged_in {
+            warn!(
+                "[{}] The term couldn't be logged in, so it won't be tracked until the next login.",
+                term_data.term
+            );
+        }
+
+        term_data.is_logged_in.store(logged_in, Ordering::SeqCst);
+        any_logged_in |= logged_in;
+    }
+
+    any_logged_in
+}
 
+/// Attempts to register the session cookies for a term with the term's own wrapper, and
+/// ensures that courses can be found for the term. This will attempt to make several
+/// requests until either one attempt is successful or all attempts fail.
+///
+/// # Parameters
+/// - `info`: The term information.
+///
+/// # Returns
+/// `true` if the term can be used with the session cookies, and `false` otherwise.
+async fn login_term(info: &TermInfo) -> bool {
+    let term = info.term.as_str();
     let mut num_tries = 0;
-    while num_tries <= MAX_NUM_REGISTER {
+    while num_tries < MAX_NUM_REGISTER {
         tokio::time::sleep(Duration::from_secs(GENERAL_DELAY)).await;
 
-        info!("Attempting to register all terms for the given session cookies.");
-        if let Err(e) = state.wrapper.register_all_terms().await {
+        info!("[{term}] Attempting to register all terms for the given session cookies.");
+        if let Err(e) = info.wrapper.register_all_terms().await {
             num_tries += 1;
             warn!(
-                "An error occurred when trying to register all terms ({num_tries}/{MAX_NUM_REGISTER}): '{e}'"
+                "[{term}] An error occurred when trying to register all terms ({num_tries}/{MAX_NUM_REGISTER}): '{e}'"
             );
             continue;
         };
 
-        info!(
-            "All terms for the cookies were registered. Now, checking that requests can be made."
-        );
-        // To ensure that login was successful, try to get all courses and ensure those courses
-        // are not empty for all terms.
-        let mut is_successful = true;
-        for term in state.all_terms.keys() {
-            // Wait a few seconds before looping.
-            tokio::time::sleep(Duration::from_secs(GENERAL_DELAY)).await;
-            // Try to associate this term in particular, it's possible that this term might not
-            // be on the list of all terms because it is hidden.
-            if let Err(e) = state.wrapper.associate_term(term).await {
+        // Wait a few seconds before making another request.
+        tokio::time::sleep(Duration::from_secs(GENERAL_DELAY)).await;
+        // Try to associate this term in particular, it's possible that this term might not
+        // be on the list of all terms because it is hidden.
+        if let Err(e) = info.wrapper.associate_term(term).await {
+            num_tries += 1;
+            warn!(
+                "An error occurred when trying to register term '{term}' ({num_tries}/{MAX_NUM_REGISTER}): '{e}'"
+            );
+            continue;
+        }
+
+        // To ensure that login was successful, try to get all courses and ensure those
+        // courses are not empty.
+        tokio::time::sleep(Duration::from_secs(GENERAL_DELAY)).await;
+        match info
+            .wrapper
+            .req(term)
+            .parsed()
+            .search_courses(SearchType::Advanced(SearchRequestBuilder::new()))
+            .await
+        {
+            Ok(o) if !o.is_empty() => {
+                info!("Found {} courses for the term '{term}'.", o.len());
+                return true;
+            }
+            Ok(_) => {
                 num_tries += 1;
-                warn!(
-                    "An error occurred when trying to register term '{term}' ({num_tries}/{MAX_NUM_REGISTER}): '{e}'"
-                );
-                is_successful = false;
-                break;
+                warn!("No courses were found for term '{term}' ({num_tries}/{MAX_NUM_REGISTER}).");
             }
-
-            // Wait a few seconds before making another request.
-            tokio::time::sleep(Duration::from_secs(GENERAL_DELAY)).await;
-            let all_courses = match state
-                .wrapper
-                .req(term)
-                .parsed()
-                .search_courses(SearchType::Advanced(SearchRequestBuilder::new()))
-                .await
-            {
-                Ok(o) => {
-                    info!("Found {} courses for the term '{term}'.", o.len());
-                    o
-                }
-                Err(e) => {
-                    num_tries += 1;
-                    warn!("Failed to fetch courses for term '{term}' ({num_tries}/{MAX_NUM_REGISTER}); error received: '{e}'");
-                    is_successful = false;
-                    break;
-                }
-            };
-
-            if all_courses.is_empty() {
-                is_successful = false;
-                break;
+            Err(e) => {
+                num_tries += 1;
+                warn!("Failed to fetch courses for term '{term}' ({num_tries}/{MAX_NUM_REGISTER}); error received: '{e}'");
             }
         }
-
-        if !is_successful {
-            continue;
-        }
-
-        break;
     }
 
-    num_tries < MAX_NUM_REGISTER
+    false
 }
@@ -0,0 +1,426 @@
+//! Filtering, sorting, and pagination for course search.
+//!
+//! WebReg's own search only narrows down which courses match. The filters here are checked
+//! against the sections and meetings stored for the term, so that they can be combined
+//! freely and so that each result only lists the sections that actually match.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::Weekday;
+use serde::Deserialize;
+
+use crate::cross_listings::CrossListings;
+use crate::db::{normalize_course_code, TermSection};
+use crate::degree_audit::config::RequirementsConfig;
+use crate::schedule::{MeetingSlot, SlotDays};
+
+/// The number of results returned per page if the caller doesn't say.
+pub const DEFAULT_PAGE_SIZE: usize = 25;
+
+/// The largest number of results that can be returned per page.
+pub const MAX_PAGE_SIZE: usize = 100;
+
+/// A course as returned by WebReg's search. webweg's parsed search results leave out the
+/// units, so the raw response is read instead.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawSearchResult {
+    #[serde(rename = "SUBJ_CODE")]
+    pub subj_code: String,
+    #[serde(rename = "CRSE_CODE")]
+    pub course_code: String,
+    #[serde(rename = "CRSE_TITLE")]
+    pub course_title: String,
+    #[serde(rename = "UNIT_FROM")]
+    pub min_units: f32,
+    #[serde(rename = "UNIT_TO")]
+    pub max_units: f32,
+}
+
+/// A course returned by WebReg's search, along with its stored sections.
+#[derive(Debug, Clone)]
+pub struct CourseCandidate {
+    /// The normalized subject/course ID (e.g., `CSE 100`).
+    pub subj_course_id: String,
+    pub title: String,
+    pub min_units: f32,
+    pub max_units: f32,
+    /// The sections of the course that match the filters, including those of the course's
+    /// other listings if it's cross-listed.
+    pub sections: Vec<TermSection>,
+    /// The other codes that the course is offered as, if it's cross-listed.
+    pub also_offered_as: Vec<String>,
+}
+
+impl CourseCandidate {
+    /// The number of open seats across the course's sections.
+    pub fn available_seats(&self) -> i64 {
+        self.sections
+            .iter()
+            .map(|s| (s.total_seats - s.enrolled_ct).max(0))
+            .sum()
+    }
+}
+
+/// The courses that count towards a general education category.
+#[derive(Debug, Clone, Default)]
+pub struct GeScope {
+    /// Courses that are listed by name (e.g., `HUM 1`).
+    courses: HashSet<String>,
+    /// Subjects whose courses all count (e.g., `CHEM`).
+    departments: HashSet<String>,
+}
+
+impl GeScope {
+    /// Collects the courses that count towards every college requirement category whose
+    /// name contains `category` (case-insensitive).
+    ///
+    /// # Parameters
+    /// - `config`: The requirements configuration.
+    /// - `college`: Only look at this college's requirements (e.g., `RE`).
+    /// - `category`: The category to look for (e.g., `Natural Sciences`).
+    ///
+    /// # Returns
+    /// The courses, or `None` if no category matches.
+    pub fn from_config(
+        config: &RequirementsConfig,
+        college: Option<&str>,
+        category: &str,
+    ) -> Option<Self> {
+        let category = category.trim().to_lowercase();
+        let mut scope = Self::default();
+        let mut found = false;
+        for requirements in config.colleges.values() {
+            if college.is_some_and(|c| !c.eq_ignore_ascii_case(&requirements.college_code)) {
+                continue;
+            }
+
+            for req in &requirements.requirements {
+                if !req.category.to_lowercase().contains(&category) {
+                    continue;
+                }
+
+                found = true;
+                for sub in &req.subrequirements {
+                    scope.courses.extend(
+                        sub.eligible_courses
+                            .iter()
+                            .map(|c| normalize_course_code(c)),
+                    );
+                    scope
+                        .departments
+                        .extend(sub.departments.iter().map(|d| d.trim().to_uppercase()));
+                }
+            }
+        }
+
+        found.then_some(scope)
+    }
+
+    /// Whether the course counts towards the category.
+    fn contains(&self, subj_course_id: &str) -> bool {
+        let subject = subj_course_id.split(' ').next().unwrap_or_default();
+        self.courses.contains(subj_course_id) || self.departments.contains(subject)
+    }
+}
+
+/// The filters that a search can be narrowed down with.
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+    /// Only include sections that had open seats when they were scraped.
+    pub only_open: bool,
+    /// Only include sections taught by someone whose name contains this (lowercase).
+    pub instructor: Option<String>,
+    /// Only include sections whose weekly meetings all fall on these days.
+    pub days: Vec<Weekday>,
+    /// Only include sections whose weekly meetings all fall within this window, in minutes
+    /// since midnight.
+    pub window: Option<(u32, u32)>,
+    pub min_units: Option<f32>,
+    pub max_units: Option<f32>,
+    /// Only include courses that count towards this general education category.
+    pub ge: Option<GeScope>,
+}
+
+impl SearchFilters {
+    /// Whether any of the filters look at a course's sections, in which case courses
+    /// without any stored sections can't match.
+    fn filters_sections(&self) -> bool {
+        self.only_open
+            || self.instructor.is_some()
+            || !self.days.is_empty()
+            || self.window.is_some()
+    }
+
+    /// Whether the course itself (rather than its sections) matches the filters.
+    fn matches_course(&self, course: &CourseCandidate) -> bool {
+        self.min_units.is_none_or(|min| course.max_units >= min)
+            && self.max_units.is_none_or(|max| course.min_units <= max)
+            && self
+                .ge
+                .as_ref()
+                .is_none_or(|ge| ge.contains(&course.subj_course_id))
+    }
+
+    /// Whether the section matches the filters. Meetings that only happen once (e.g., final
+    /// exams) aren't checked against the day and time filters.
+    fn matches_section(&self, section: &TermSection) -> bool {
+        if self.only_open && section.enrolled_ct >= section.total_seats {
+            return false;
+        }
+
+        if let Some(instructor) = &self.instructor {
+            let teaches = section.meetings.iter().any(|m| {
+                let names: Vec<String> = m
+                    .instructors
+                    .as_deref()
+                    .and_then(|i| serde_json::from_str(i).ok())
+                    .unwrap_or_default();
+                names.iter().any(|n| n.to_lowercase().contains(instructor))
+            });
+
+            if !teaches {
+                return false;
+            }
+        }
+
+        section
+            .meetings
+            .iter()
+            .filter_map(MeetingSlot::from_db)
+            .all(|slot| {
+                let SlotDays::Weekly(days) = &slot.days else {
+                    return true;
+                };
+
+                (self.days.is_empty() || days.iter().all(|d| self.days.contains(d)))
+                    && self
+                        .window
+                        .is_none_or(|(start, end)| start <= slot.start && slot.end <= end)
+            })
+    }
+
+    /// Narrows down the courses to those that match, dropping each course's sections that
+    /// don't match.
+    ///
+    /// # Parameters
+    /// - `courses`: The courses returned by WebReg's search.
+    ///
+    /// # Returns
+    /// The matching courses.
+    pub fn apply(&self, courses: Vec<CourseCandidate>) -> Vec<CourseCandidate> {
+        courses
+            .into_iter()
+            .filter(|course| self.matches_course(course))
+            .filter_map(|mut course| {
+                course.sections.retain(|s| self.matches_section(s));
+                (!self.filters_sections() || !course.sections.is_empty()).then_some(course)
+            })
+            .collect()
+    }
+}
+
+/// How search results are ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchSort {
+    /// By subject and course number (e.g., `CSE 8A` before `CSE 100`).
+    #[default]
+    Course,
+    /// By fewest units first.
+    Units,
+    /// By most open seats first.
+    Seats,
+}
+
+impl SearchSort {
+    /// Parses a sort option (`course`, `units`, or `seats`).
+    pub fn parse(sort: &str) -> Option<Self> {
+        match sort.trim().to_lowercase().as_str() {
+            "course" => Some(Self::Course),
+            "units" => Some(Self::Units),
+            "seats" => Some(Self::Seats),
+            _ => None,
+        }
+    }
+
+    /// Sorts the courses. Ties are broken by course so that pages are stable.
+    pub fn sort(self, courses: &mut [CourseCandidate]) {
+        courses.sort_by(|a, b| {
+            let by_course =
+                || course_sort_key(&a.subj_course_id).cmp(&course_sort_key(&b.subj_course_id));
+            match self {
+                Self::Course => by_course(),
+                Self::Units => a.min_units.total_cmp(&b.min_units).then_with(by_course),
+                Self::Seats => b
+                    .available_seats()
+                    .cmp(&a.available_seats())
+                    .then_with(by_course),
+            }
+        });
+    }
+}
+
+/// The key that courses are sorted by, so that course numbers compare numerically (e.g.,
+/// `CSE 8A` is `("CSE", 8, "A")`).
+fn course_sort_key(subj_course_id: &str) -> (&str, u32, &str) {
+    let (subject, number) = subj_course_id
+        .split_once(' ')
+        .unwrap_or((subj_course_id, ""));
+    let split = number
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(number.len());
+    (
+        subject,
+        number[..split].parse().unwrap_or(0),
+        &number[split..],
+    )
+}
+
+/// Merges the listings of cross-listed courses into one result, so that a class doesn't
+/// show up once per code. The result is named after whichever of its listings that WebReg
+/// returned comes first in course order, and has the sections of all of them.
+///
+/// # Parameters
+/// - `courses`: The courses returned by WebReg's search.
+/// - `cross_listings`: The term's cross-listed courses.
+///
+/// # Returns
+/// The courses, with each one's other codes filled in.
+pub fn merge_cross_listed(
+    courses: Vec<CourseCandidate>,
+    cross_listings: &CrossListings,
+) -> Vec<CourseCandidate> {
+    let mut merged: Vec<CourseCandidate> = vec![];
+    let mut index = HashMap::new();
+    for course in courses {
+        let canonical = cross_listings.canonical(&course.subj_course_id);
+        let Some(&i) = index.get(&canonical) else {
+            index.insert(canonical, merged.len());
+            merged.push(course);
+            continue;
+        };
+
+        let existing = &mut merged[i];
+        if course_sort_key(&course.subj_course_id) < course_sort_key(&existing.subj_course_id) {
+            existing.subj_course_id = course.subj_course_id;
+            existing.title = course.title;
+        }
+        existing.min_units = existing.min_units.min(course.min_units);
+        existing.max_units = existing.max_units.max(course.max_units);
+        existing.sections.extend(course.sections);
+    }
+
+    for course in &mut merged {
+        course.also_offered_as = cross_listings.also_offered_as(&course.subj_course_id);
+    }
+
+    merged
+}
+
+/// Gets a page of results. Cursors are opaque to callers; each one is the position of the
+/// first result on the page.
+///
+/// # Parameters
+/// - `courses`: The sorted results.
+/// - `cursor`: The cursor returned with the previous page, if any.
+/// - `limit`: The number of results per page.
+///
+/// # Returns
+/// The page and the cursor for the next page (if there is one), or `None` if the cursor
+/// is invalid.
+pub fn paginate(
+    courses: Vec<CourseCandidate>,
+    cursor: Option<&str>,
+    limit: usize,
+) -> Option<(Vec<CourseCandidate>, Option<String>)> {
+    let start = match cursor {
+        Some(cursor) => cursor
+            .parse::<usize>()
+            .ok()
+            .filter(|&c| c <= courses.len())?,
+        None => 0,
+    };
+
+    let end = courses.len().min(start + limit);
+    let next = (end < courses.len()).then(|| end.to_string());
+    Some((courses.into_iter().skip(start).take(limit).collect(), next))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::term_section;
+
+    fn section(section_code: &str, enrolled_ct: i64, days: &str, start_hr: i32) -> TermSection {
+        term_section("", section_code, days, start_hr, "Doe, Jane", enrolled_ct)
+    }
+
+    fn course(subj_course_id: &str, units: f32, sections: Vec<TermSection>) -> CourseCandidate {
+        CourseCandidate {
+            subj_course_id: subj_course_id.to_string(),
+            title: String::new(),
+            min_units: units,
+            max_units: units,
+            sections,
+            also_offered_as: vec![],
+        }
+    }
+
+    #[test]
+    fn test_filter_sort_paginate() {
+        let courses = vec![
+            course("CSE 100", 4.0, vec![section("A00", 30, r#"["M","W"]"#, 10)]),
+            course("CSE 8A", 4.0, vec![section("A00", 10, r#"["Tu","Th"]"#, 9)]),
+            course(
+                "CSE 12",
+                4.0,
+                vec![
+                    section("A00", 0, r#"["M","W","F"]"#, 8),
+                    section("B00", 29, r#"["Tu","Th"]"#, 14),
+                ],
+            ),
+            course("CSE 199", 2.0, vec![]),
+        ];
+
+        let filters = SearchFilters {
+            only_open: true,
+            instructor: Some("doe".to_string()),
+            days: vec![Weekday::Tue, Weekday::Thu],
+            window: Some((8 * 60, 12 * 60)),
+            ..Default::default()
+        };
+        let matched = filters.apply(courses.clone());
+        assert_eq!(1, matched.len());
+        assert_eq!("CSE 8A", matched[0].subj_course_id);
+
+        let mut all = SearchFilters::default().apply(courses);
+        assert_eq!(4, all.len());
+
+        SearchSort::Course.sort(&mut all);
+        let order: Vec<_> = all.iter().map(|c| c.subj_course_id.as_str()).collect();
+        assert_eq!(vec!["CSE 8A", "CSE 12", "CSE 100", "CSE 199"], order);
+
+        SearchSort::Seats.sort(&mut all);
+        assert_eq!("CSE 12", all[0].subj_course_id);
+
+        let (page, next) = paginate(all.clone(), None, 3).unwrap();
+        assert_eq!((3, Some("3".to_string())), (page.len(), next.clone()));
+        let (page, next) = paginate(all.clone(), next.as_deref(), 3).unwrap();
+        assert_eq!((1, None), (page.len(), next));
+        assert!(paginate(all, Some("bogus"), 3).is_none());
+
+        let cross_listings =
+            CrossListings::from_pairs([("CSE 100", "MATH 176"), ("CSE 100", "ECE 100")]);
+        let merged = merge_cross_listed(
+            vec![
+                course("MATH 176", 4.0, vec![section("A00", 0, r#"["M"]"#, 9)]),
+                course("CSE 8A", 4.0, vec![]),
+                course("CSE 100", 4.0, vec![section("A00", 0, r#"["M"]"#, 9)]),
+            ],
+            &cross_listings,
+        );
+        assert_eq!(2, merged.len());
+        assert_eq!("CSE 100", merged[0].subj_course_id);
+        assert_eq!(2, merged[0].sections.len());
+        assert_eq!(vec!["ECE 100", "MATH 176"], merged[0].also_offered_as);
+    }
+}
@@ -0,0 +1,387 @@
+//! Semantic search over the course catalog's descriptions (see [`crate::scraper::catalog`]),
+//! so that students can find courses by what they're about rather than by their titles
+//! (e.g., `machine learning with lots of math`).
+//!
+//! Each course's title and description are turned into an embedding, either by a local
+//! model or by an embedding API (anything that accepts OpenAI-style `{"model", "input"}`
+//! requests). The local model hashes words and pairs of words into a fixed number of
+//! dimensions, so it needs no downloads but only matches on shared vocabulary; an API model
+//! also matches on meaning. Embeddings are stored in the schedule database alongside the
+//! hash of the text they were made from, so courses are only embedded again when their
+//! description changes. Searches compare the query's embedding against every course's by
+//! cosine similarity.
+
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use reqwest::header::AUTHORIZATION;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::db::CatalogCourse;
+use crate::types::WrapperState;
+
+/// The number of dimensions of the local model's embeddings.
+pub const LOCAL_DIMENSIONS: usize = 512;
+/// The number of results returned by a search if the caller doesn't say.
+pub const DEFAULT_SEMANTIC_LIMIT: usize = 20;
+/// The most results returned by a search.
+pub const MAX_SEMANTIC_LIMIT: usize = 100;
+/// The number of courses embedded per request to an embedding API.
+const EMBED_BATCH_SIZE: usize = 64;
+/// How often new and changed courses are embedded, and the index is reloaded.
+const INDEX_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Words too common to say anything about what a course is about.
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "course", "for", "from", "in", "into", "is",
+    "it", "its", "of", "on", "or", "students", "that", "the", "this", "to", "with",
+];
+
+/// Why an embedding couldn't be made or a search couldn't be run.
+#[derive(Debug, Error)]
+pub enum SemanticError {
+    #[error("failed to reach the embedding API: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("the embedding API returned {got} embedding(s) for {expected} text(s)")]
+    Mismatch { expected: usize, got: usize },
+    #[error("failed to access the stored embeddings: {0}")]
+    Db(#[from] rusqlite::Error),
+}
+
+/// Makes embeddings of text.
+pub enum Embedder {
+    /// Hashes words and pairs of words into [`LOCAL_DIMENSIONS`] dimensions.
+    Local,
+    /// Asks an OpenAI-style embedding API.
+    Api {
+        url: String,
+        model: String,
+        token: Option<String>,
+    },
+}
+
+#[derive(Deserialize)]
+struct ApiEmbeddingResponse {
+    data: Vec<ApiEmbedding>,
+}
+
+#[derive(Deserialize)]
+struct ApiEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl Embedder {
+    /// The name of the model, which embeddings are stored under.
+    pub fn model(&self) -> String {
+        match self {
+            Embedder::Local => format!("local-hash-{LOCAL_DIMENSIONS}"),
+            Embedder::Api { model, .. } => model.clone(),
+        }
+    }
+
+    /// Makes embeddings of some text, each with unit length.
+    ///
+    /// # Parameters
+    /// - `client`: The client to reach the embedding API with.
+    /// - `texts`: The text to embed.
+    ///
+    /// # Returns
+    /// An embedding of each text, in order.
+    pub async fn embed(
+        &self,
+        client: &reqwest::Client,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>, SemanticError> {
+        let (url, model, token) = match self {
+            Embedder::Local => return Ok(texts.iter().map(|t| local_embedding(t)).collect()),
+            Embedder::Api { url, model, token } => (url, model, token),
+        };
+
+        let mut request = client
+            .post(url)
+            .json(&json!({ "model": model, "input": texts }));
+        if let Some(token) = token {
+            request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+
+        let mut response: ApiEmbeddingResponse =
+            request.send().await?.error_for_status()?.json().await?;
+        if response.data.len() != texts.len() {
+            return Err(SemanticError::Mismatch {
+                expected: texts.len(),
+                got: response.data.len(),
+            });
+        }
+
+        response.data.sort_by_key(|e| e.index);
+        Ok(response
+            .data
+            .into_iter()
+            .map(|e| normalized(e.embedding))
+            .collect())
+    }
+}
+
+/// Splits text into lowercase words, leaving out stop words and plural endings.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|w| !w.is_empty() && !STOP_WORDS.contains(&w.as_str()))
+        .map(|w| match w.strip_suffix('s') {
+            Some(stem) if stem.len() > 3 && !stem.ends_with('s') => stem.to_owned(),
+            _ => w,
+        })
+        .collect()
+}
+
+/// Hashes a feature with FNV-1a, which (unlike the standard library's hasher) is stable
+/// across releases, so stored embeddings stay comparable.
+fn fnv1a(feature: &str) -> u64 {
+    feature.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Makes an embedding of text with the local model. Each word and pair of adjacent words is
+/// hashed to a dimension and a sign; pairs count for half as much as words.
+pub fn local_embedding(text: &str) -> Vec<f32> {
+    let words = words(text);
+    let pairs = words
+        .windows(2)
+        .map(|w| (format!("{} {}", w[0], w[1]), 0.5));
+    let mut embedding = vec![0.0; LOCAL_DIMENSIONS];
+    for (feature, weight) in words.iter().map(|w| (w.clone(), 1.0)).chain(pairs) {
+        let hash = fnv1a(&feature);
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        embedding[(hash % LOCAL_DIMENSIONS as u64) as usize] += sign * weight;
+    }
+
+    normalized(embedding)
+}
+
+/// Scales a vector to unit length, unless it's all zeros.
+fn normalized(mut v: Vec<f32>) -> Vec<f32> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+    v
+}
+
+/// The cosine similarity of two unit-length embeddings.
+pub fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// The text of a course that's embedded.
+fn course_text(course: &CatalogCourse) -> String {
+    format!("{}. {}", course.title, course.description)
+}
+
+/// A course that matched a search.
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticMatch {
+    /// The course's code (e.g., `CSE 151A`).
+    pub code: String,
+    pub title: String,
+    /// How similar the course is to the query, from -1 to 1.
+    pub score: f32,
+}
+
+/// A course's embedding, kept in memory for searches.
+struct IndexedCourse {
+    code: String,
+    title: String,
+    embedding: Vec<f32>,
+}
+
+/// The embeddings of every course in the catalog.
+pub struct SemanticIndex {
+    embedder: Embedder,
+    courses: RwLock<Vec<IndexedCourse>>,
+}
+
+impl SemanticIndex {
+    /// Creates an empty index. It's filled in by [`run_semantic_index`].
+    ///
+    /// # Parameters
+    /// - `embedder`: What to make embeddings with.
+    pub fn new(embedder: Embedder) -> Self {
+        Self {
+            embedder,
+            courses: RwLock::new(vec![]),
+        }
+    }
+
+    /// The name of the model that the index's embeddings were made with.
+    pub fn model(&self) -> String {
+        self.embedder.model()
+    }
+
+    /// The number of courses in the index.
+    pub fn len(&self) -> usize {
+        self.courses.read().unwrap().len()
+    }
+
+    /// Whether the index has no courses in it.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Finds the courses most similar to a query.
+    ///
+    /// # Parameters
+    /// - `client`: The client to reach the embedding API with.
+    /// - `query`: What to search for.
+    /// - `limit`: The most courses to return.
+    /// - `include`: Whether a course (by its normalized code) may be returned.
+    ///
+    /// # Returns
+    /// The matching courses, most similar first.
+    pub async fn search(
+        &self,
+        client: &reqwest::Client,
+        query: &str,
+        limit: usize,
+        include: impl Fn(&str) -> bool,
+    ) -> Result<Vec<SemanticMatch>, SemanticError> {
+        let query = self
+            .embedder
+            .embed(client, &[query.to_owned()])
+            .await?
+            .pop()
+            .unwrap_or_default();
+
+        let courses = self.courses.read().unwrap();
+        let mut matches: Vec<SemanticMatch> = courses
+            .iter()
+            .filter(|c| include(&c.code))
+            .map(|c| SemanticMatch {
+                code: c.code.clone(),
+                title: c.title.clone(),
+                score: similarity(&query, &c.embedding),
+            })
+            .collect();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.code.cmp(&b.code)));
+        matches.truncate(limit);
+        Ok(matches)
+    }
+}
+
+/// Embeds courses whose text has changed since they were last embedded (only on the
+/// leader), and then reloads the index from the stored embeddings.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `index`: The index to refresh.
+///
+/// # Returns
+/// The number of courses that were embedded.
+async fn refresh_index(
+    state: &WrapperState,
+    index: &SemanticIndex,
+) -> Result<usize, SemanticError> {
+    let model = index.model();
+    let catalog = state.schedule_db.get_catalog_courses()?;
+    let stored = state.schedule_db.get_catalog_embeddings(&model)?;
+
+    let mut embedded = 0;
+    if state.leader.is_leader() && !state.replication.is_standby() {
+        let stale: Vec<(String, String, String)> = catalog
+            .iter()
+            .map(|c| {
+                let text = course_text(c);
+                let hash = format!("{:x}", Sha256::digest(text.as_bytes()));
+                (c.code(), hash, text)
+            })
+            .filter(|(code, hash, _)| !stored.iter().any(|(c, h, _)| c == code && h == hash))
+            .collect();
+
+        for batch in stale.chunks(EMBED_BATCH_SIZE) {
+            let texts: Vec<String> = batch.iter().map(|(_, _, text)| text.clone()).collect();
+            let embeddings = index.embedder.embed(&state.client, &texts).await?;
+            let rows: Vec<(String, String, Vec<f32>)> = batch
+                .iter()
+                .zip(embeddings)
+                .map(|((code, hash, _), embedding)| (code.clone(), hash.clone(), embedding))
+                .collect();
+            state.schedule_db.upsert_catalog_embeddings(&model, &rows)?;
+            embedded += rows.len();
+        }
+    }
+
+    let stored = if embedded > 0 {
+        state.schedule_db.get_catalog_embeddings(&model)?
+    } else {
+        stored
+    };
+    let courses = stored
+        .into_iter()
+        .filter_map(|(code, _, embedding)| {
+            let course = catalog.iter().find(|c| c.code() == code)?;
+            Some(IndexedCourse {
+                code,
+                title: course.title.clone(),
+                embedding,
+            })
+        })
+        .collect();
+    *index.courses.write().unwrap() = courses;
+
+    Ok(embedded)
+}
+
+/// Keeps the semantic search index up to date with the catalog, if semantic search is on.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+pub async fn run_semantic_index(state: Arc<WrapperState>) {
+    let Some(index) = &state.semantic_index else {
+        return;
+    };
+
+    info!("Semantic search is using the {} model.", index.model());
+    let mut interval = tokio::time::interval(INDEX_REFRESH_INTERVAL);
+    loop {
+        interval.tick().await;
+        if state.should_stop() {
+            break;
+        }
+
+        match refresh_index(&state, index).await {
+            Ok(0) => {}
+            Ok(embedded) => info!("Embedded {embedded} catalog course(s) for semantic search."),
+            Err(e) => warn!("Failed to refresh the semantic search index: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_embeddings() {
+        let ml = local_embedding(
+            "Introduction to Machine Learning. Linear algebra, probability, and \
+             optimization for learning from data.",
+        );
+        let art = local_embedding("Survey of European Art. Painting from the Renaissance.");
+        let query = local_embedding("machine learning with lots of math and linear algebra");
+
+        assert!((similarity(&ml, &ml) - 1.0).abs() < 1e-5);
+        assert!(similarity(&query, &ml) > similarity(&query, &art));
+
+        // Plurals and stop words don't matter
+        assert_eq!(
+            local_embedding("the data structures"),
+            local_embedding("data structure")
+        );
+        assert!(local_embedding("of the").iter().all(|x| *x == 0.0));
+    }
+}
@@ -0,0 +1,75 @@
+//! What the API's data is, where it comes from, and the terms that it's served under.
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::Utc;
+use serde_json::json;
+use tracing::info;
+
+use crate::attribution::DEFAULT_DATA_SOURCE;
+use crate::error::WebregError;
+use crate::types::WrapperState;
+
+/// GET /about
+///
+/// Returns where the data comes from, the license and terms of use that it's served under
+/// (from `dataLicense` in the configuration file), and, for each term, whether its seats
+/// are being tracked live, when it was last fully scraped, and the version of its schedule
+/// data.
+#[tracing::instrument(skip(s))]
+pub async fn get_about(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("Called `about` endpoint.");
+    let mut terms = vec![];
+    for term in s.terms() {
+        let scrape = match s.schedule_db.get_latest_scrape_job(&term.term) {
+            Ok(scrape) => scrape,
+            Err(e) => {
+                return WebregError::from(e)
+                    .with_message("Failed to load the term's latest scrape.")
+                    .into_response();
+            }
+        };
+        let data_version = match s.schedule_db.get_data_version(&term.term) {
+            Ok(version) => version,
+            Err(e) => {
+                return WebregError::from(e)
+                    .with_message("Failed to load the term's data version.")
+                    .into_response();
+            }
+        };
+
+        terms.push(json!({
+            "term": term.term,
+            "live_tracking": term.is_running(),
+            "data_version": data_version,
+            "last_scrape": scrape.map(|job| json!({
+                "status": job.status,
+                "started_at": job.started_at,
+                "completed_at": job.completed_at,
+                "dataset_hash": job.dataset_hash,
+            })),
+        }));
+    }
+    terms.sort_by(|a, b| a["term"].as_str().cmp(&b["term"].as_str()));
+
+    let license = s.data_license.as_ref();
+    (
+        StatusCode::OK,
+        Json(json!({
+            "source": license.map_or(DEFAULT_DATA_SOURCE, |l| l.source.as_str()),
+            "license": license.and_then(|l| l.license.as_deref()),
+            "license_url": license.and_then(|l| l.license_url.as_deref()),
+            "attribution": license.and_then(|l| l.attribution.as_deref()),
+            "terms_of_use": license.and_then(|l| l.terms_of_use.as_deref()),
+            "terms_url": license.and_then(|l| l.terms_url.as_deref()),
+            "contact": license.and_then(|l| l.contact.as_deref()),
+            "terms": terms,
+            "generated_at": Utc::now().to_rfc3339(),
+        })),
+    )
+        .into_response()
+}
@@ -0,0 +1,545 @@
+//! Operational endpoints intended for whoever runs the server, rather than students. These
+//! are served by the admin router, never alongside the student-facing endpoints unless an
+//! admin token is set.
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{info, warn};
+
+use crate::degree_audit::build_gap_report;
+use crate::degree_audit::cache::SessionKey;
+use crate::error::WebregError;
+use crate::grades::import_grade_csv;
+use crate::payload_store::PayloadStoreError;
+use crate::replication::{restore_snapshot, ReplicationError, SNAPSHOT_TAKEN_AT_HEADER};
+use crate::request_log::REQUEST_LOG;
+use crate::scraper::term_scrape::{start_term_scrape, StartScrapeError};
+use crate::server::endpoints::db_error;
+use crate::server::middleware::deprecation::DEPRECATED_ROUTES;
+use crate::server::types::{ApiErrorType, GapReportQueryStr, PayloadQueryStr};
+use crate::types::WrapperState;
+
+/// The number of subrequirements and courses in a gap report if the caller doesn't say.
+const DEFAULT_GAP_REPORT_LIMIT: usize = 20;
+/// The message for a database error.
+const DB_ERROR: &str = "Failed to read the database.";
+
+/// GET /admin/upstream_drift
+///
+/// Returns, for each kind of WebReg response, how many responses were checked and
+/// which fields were unknown or missing compared to the schema webweg expects.
+pub async fn get_upstream_drift(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("GET /admin/upstream_drift");
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "drift_detected": s.drift_tracker.has_drift(),
+            "responses": s.drift_tracker.report(),
+        })),
+    )
+        .into_response()
+}
+
+/// GET /admin/status
+///
+/// Returns a consolidated view of the server's operational state: how each term's tracker
+/// is doing, when session cookies were last refreshed, the cookie server's health, the
+/// degree audit circuit breaker, the number of rows in each table, and how much
+/// background work is waiting.
+pub async fn get_status(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("GET /admin/status");
+
+    let row_counts = match s.schedule_db.get_table_row_counts() {
+        Ok(counts) => counts,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+    let queues = match s.schedule_db.get_queue_depths() {
+        Ok(queues) => queues,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    let mut terms = s.terms();
+    terms.sort_by(|a, b| a.term.cmp(&b.term));
+    let terms: Vec<_> = terms
+        .iter()
+        .map(|t| {
+            let requests = t.tracker.num_requests.load(Ordering::SeqCst);
+            let time_spent = t.tracker.total_time_spent.load(Ordering::SeqCst);
+            json!({
+                "term": t.term,
+                "running": t.is_running(),
+                "requests": requests,
+                "avg_request_ms": (requests > 0).then(|| time_spent / requests),
+                "last_success": *t.tracker.last_success.lock().unwrap(),
+                "consecutive_failures": t.tracker.consecutive_failures.load(Ordering::SeqCst),
+                "active_scrape_job": s.active_term_scrapes.get(&t.term).map(|j| *j),
+            })
+        })
+        .collect();
+
+    let audit_state = &s.degree_audit_cache_state;
+    let cache_stats = audit_state.cache.stats();
+    (
+        StatusCode::OK,
+        Json(json!({
+            "running": s.is_running(),
+            "terms": terms,
+            "last_cookie_refresh": s.cookie_freshness.report().refreshed_at,
+            "cookie_server": s.cookie_server_health.report(),
+            "degree_audit": {
+                "circuit_breaker": audit_state.circuit_breaker.status(),
+                "cache": {
+                    "total_entries": cache_stats.total_entries,
+                    "active_entries": cache_stats.active_entries,
+                },
+                "dropped_blocks": audit_state.dropped_blocks.load(Ordering::Relaxed),
+            },
+            "leader": s.leader.status(&s.schedule_db),
+            "load": s.load_shedder.stats(),
+            "queues": {
+                "enroll_jobs": queues.pending_enroll_jobs,
+                "scrape_jobs": queues.running_scrape_jobs,
+                "scrape_departments": queues.pending_scrape_departments,
+                "custom_event_sync": queues.unsynced_custom_events,
+                "enrollment_events": s.enrollment_bus.queued(),
+            },
+            "db_row_counts": row_counts,
+        })),
+    )
+        .into_response()
+}
+
+/// GET /admin/load
+///
+/// Returns the server's current load, along with how many requests were shed because the
+/// server was overloaded.
+pub async fn get_load(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("GET /admin/load");
+    (StatusCode::OK, Json(s.load_shedder.stats())).into_response()
+}
+
+/// GET /admin/leader
+///
+/// Returns whether this instance holds the leader lease, and which instance does.
+pub async fn get_leader(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("GET /admin/leader");
+    (StatusCode::OK, Json(s.leader.status(&s.schedule_db))).into_response()
+}
+
+/// GET /admin/jobs
+///
+/// Returns each recurring background job's schedule, when it's next due, and how its last
+/// run went.
+pub async fn get_jobs(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("GET /admin/jobs");
+    (StatusCode::OK, Json(s.scheduler.report())).into_response()
+}
+
+/// POST /admin/grade_distributions
+///
+/// Imports grade distributions and course evaluations from a CSV file (see
+/// [`crate::grades`] for its columns), replacing any existing records for the same course,
+/// term, and instructor.
+pub async fn post_grade_distributions(State(s): State<Arc<WrapperState>>, body: Bytes) -> Response {
+    info!("POST /admin/grade_distributions ({} bytes)", body.len());
+
+    let text = String::from_utf8_lossy(&body);
+    match import_grade_csv(&s, &text) {
+        Ok(imported) => (StatusCode::OK, Json(json!({ "imported": imported }))).into_response(),
+        Err(e) => WebregError::from(e).into_response(),
+    }
+}
+
+/// GET /admin/replication
+///
+/// Returns whether this instance is the primary or a standby, along with the most recent
+/// snapshot of the schedule database that it pushed or restored.
+pub async fn get_replication(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("GET /admin/replication");
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "role": if s.replication.is_standby() { "standby" } else { "primary" },
+            "last_snapshot": s.replication.last_snapshot().await,
+        })),
+    )
+        .into_response()
+}
+
+/// POST /admin/replication/snapshot
+///
+/// Replaces the schedule database with a snapshot pushed by the primary. The time the
+/// snapshot was taken must be given in the `X-Snapshot-Taken-At` header.
+pub async fn post_replication_snapshot(
+    State(s): State<Arc<WrapperState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    info!("POST /admin/replication/snapshot ({} bytes)", body.len());
+
+    let Some(taken_at) = headers
+        .get(SNAPSHOT_TAKEN_AT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<i64>().ok())
+    else {
+        return ApiErrorType::from((
+            StatusCode::BAD_REQUEST,
+            "The X-Snapshot-Taken-At header is required.",
+            None,
+        ))
+        .into_response();
+    };
+
+    match restore_snapshot(&s, taken_at, body.to_vec()).await {
+        Ok(snapshot) => (StatusCode::OK, Json(snapshot)).into_response(),
+        Err(e @ (ReplicationError::NotStandby | ReplicationError::Outdated { .. })) => {
+            WebregError::from(e).into_response()
+        }
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to restore the snapshot.")
+            .into_response(),
+    }
+}
+
+/// POST /admin/replication/promote
+///
+/// Promotes this standby to the primary, so that it starts its background workers and stops
+/// accepting snapshots.
+pub async fn post_replication_promote(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("POST /admin/replication/promote");
+
+    let promoted = s.replication.promote();
+    if promoted {
+        warn!("This standby was promoted to the primary.");
+    }
+
+    (StatusCode::OK, Json(json!({ "promoted": promoted }))).into_response()
+}
+
+/// POST /admin/db/vacuum
+///
+/// Rebuilds the schedule database without its free pages, and returns its size before and
+/// after. Other requests that use the database wait until it's done.
+pub async fn post_db_vacuum(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("POST /admin/db/vacuum");
+
+    let start = Instant::now();
+    let result = tokio::task::spawn_blocking(move || s.schedule_db.vacuum()).await;
+    match result {
+        Ok(Ok(size)) => (
+            StatusCode::OK,
+            Json(json!({
+                "bytes_before": size.bytes_before,
+                "bytes_after": size.bytes_after,
+                "elapsed_ms": start.elapsed().as_millis() as u64,
+            })),
+        )
+            .into_response(),
+        Ok(Err(e)) => WebregError::from(e)
+            .with_message("Failed to vacuum the database.")
+            .into_response(),
+        Err(e) => WebregError::internal("Failed to vacuum the database.", e).into_response(),
+    }
+}
+
+/// POST /admin/db/analyze
+///
+/// Updates the statistics that the schedule database's query planner picks indexes with.
+pub async fn post_db_analyze(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("POST /admin/db/analyze");
+
+    let start = Instant::now();
+    let result = tokio::task::spawn_blocking(move || s.schedule_db.analyze()).await;
+    match result {
+        Ok(Ok(())) => (
+            StatusCode::OK,
+            Json(json!({ "elapsed_ms": start.elapsed().as_millis() as u64 })),
+        )
+            .into_response(),
+        Ok(Err(e)) => WebregError::from(e)
+            .with_message("Failed to analyze the database.")
+            .into_response(),
+        Err(e) => WebregError::internal("Failed to analyze the database.", e).into_response(),
+    }
+}
+
+/// GET /admin/deprecations
+///
+/// Returns every deprecated route along with how often it's still being used.
+pub async fn get_deprecations(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("GET /admin/deprecations");
+
+    let routes: Vec<_> = DEPRECATED_ROUTES
+        .iter()
+        .map(|r| {
+            let usage = s.deprecation_tracker.usage(r.path);
+            json!({
+                "path": r.path,
+                "since": r.since,
+                "sunset": r.sunset,
+                "replacement": r.replacement,
+                "count": usage.count,
+                "last_used": usage.last_used,
+            })
+        })
+        .collect();
+
+    (StatusCode::OK, Json(routes)).into_response()
+}
+
+/// GET /admin/advising/gap_report?major=MA30
+///
+/// Returns which subrequirements are most commonly incomplete among registered students in
+/// the given major, and which courses would count towards one for the most students. Only
+/// audits that are already cached are used (including expired ones), so building the report
+/// never runs new audits against students' quotas.
+pub async fn get_gap_report(
+    Query(query): Query<GapReportQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /admin/advising/gap_report");
+
+    if query.major.trim().is_empty() {
+        return ApiErrorType::from((StatusCode::BAD_REQUEST, "A major must be given.", None))
+            .into_response();
+    }
+
+    let sessions = s.sessions.all_cookies();
+    let cache = &s.degree_audit_cache_state.cache;
+    let audits: Vec<_> = sessions
+        .iter()
+        .filter_map(|cookies| cache.get_allowing_stale(&SessionKey::from_cookie(cookies)))
+        .map(|(audit, _)| audit)
+        .collect();
+
+    let report = build_gap_report(
+        &query.major,
+        &audits,
+        query.limit.unwrap_or(DEFAULT_GAP_REPORT_LIMIT),
+    );
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "registered_students": sessions.len(),
+            "students_with_audit": audits.len(),
+            "report": report,
+        })),
+    )
+        .into_response()
+}
+
+/// POST /admin/scrape_term/:term
+///
+/// Starts scraping every section of every course in the term into the schedule database,
+/// one department at a time. If the term's last scrape didn't complete (e.g., the scraper
+/// crashed partway through), it's resumed from the first department it didn't finish.
+/// Returns the scrape's ID; its progress can be followed with `GET
+/// /admin/scrape_term/:term`.
+pub async fn post_scrape_term(
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("POST /admin/scrape_term/{}", term);
+
+    if s.term(&term).is_none() {
+        return ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "This term isn't tracked.",
+            Some(term),
+        ))
+        .into_response();
+    }
+
+    match start_term_scrape(&s, &term) {
+        Ok((job_id, resumed)) => (
+            StatusCode::ACCEPTED,
+            Json(json!({
+                "job_id": job_id,
+                "resumed": resumed,
+            })),
+        )
+            .into_response(),
+        Err(e @ StartScrapeError::AlreadyRunning(_)) => WebregError::from(e).into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to start the scrape.")
+            .into_response(),
+    }
+}
+
+/// GET /admin/scrape_term/:term
+///
+/// Returns the progress of the term's most recent scrape.
+pub async fn get_scrape_term(
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /admin/scrape_term/{}", term);
+
+    match s.schedule_db.get_latest_scrape_job(&term) {
+        Ok(Some(job)) => (StatusCode::OK, Json(job)).into_response(),
+        Ok(None) => ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "This term hasn't been scraped.",
+            Some(term),
+        ))
+        .into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to look up the scrape.")
+            .into_response(),
+    }
+}
+
+/// GET /admin/payloads?prefix=webreg/FA24/
+///
+/// Lists the archived payloads whose keys start with the prefix (or every payload, if
+/// there's no prefix), with their sizes and when they were stored. This is only available
+/// if `payloadStore` is set.
+pub async fn get_payloads(
+    Query(query): Query<PayloadQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /admin/payloads");
+
+    let Some(archive) = &s.payload_archive else {
+        return payload_store_disabled();
+    };
+
+    match archive
+        .store()
+        .list(query.prefix.as_deref().unwrap_or_default())
+        .await
+    {
+        Ok(payloads) => (
+            StatusCode::OK,
+            Json(
+                payloads
+                    .into_iter()
+                    .map(|p| {
+                        json!({
+                            "key": p.key,
+                            "size": p.size,
+                            "modified": p.modified.to_rfc3339(),
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+        )
+            .into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to list the payloads.")
+            .into_response(),
+    }
+}
+
+/// GET /admin/payloads/*key
+///
+/// Returns an archived payload as it was received. This is only available if
+/// `payloadStore` is set.
+pub async fn get_payload(Path(key): Path<String>, State(s): State<Arc<WrapperState>>) -> Response {
+    info!("GET /admin/payloads/{}", key);
+
+    let Some(archive) = &s.payload_archive else {
+        return payload_store_disabled();
+    };
+
+    let content_type = match key.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("json") => "application/json",
+        Some("html") => "text/html; charset=utf-8",
+        _ => "application/octet-stream",
+    };
+
+    match archive.store().get(&key).await {
+        Ok(Some(data)) => {
+            (StatusCode::OK, [(header::CONTENT_TYPE, content_type)], data).into_response()
+        }
+        Ok(None) => ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "No payload was found with this key.",
+            Some(key),
+        ))
+        .into_response(),
+        Err(e @ PayloadStoreError::InvalidKey(_)) => WebregError::from(e).into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to get the payload.")
+            .into_response(),
+    }
+}
+
+/// The response to requests for payloads when there's no payload store.
+fn payload_store_disabled() -> Response {
+    ApiErrorType::from((
+        StatusCode::NOT_FOUND,
+        "The payload store is not enabled.",
+        None,
+    ))
+    .into_response()
+}
+
+/// GET /admin/keys/:key_id/usage
+///
+/// Returns how many requests an API key (identified by its prefix) made on each day that's
+/// still on record, along with its usage of its daily and monthly quotas.
+pub async fn get_key_usage(
+    Path(key_id): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /admin/keys/{}/usage", key_id);
+
+    match s.key_quota.usage(&key_id) {
+        Some(usage) => (StatusCode::OK, Json(usage)).into_response(),
+        None => ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "No requests were found for this key.",
+            Some(key_id),
+        ))
+        .into_response(),
+    }
+}
+
+/// GET /admin/logs/:request_id
+///
+/// Returns the lines that were logged while serving a request, as long as they're still
+/// in the request log. This is only available if `requestLogCapacity` is set.
+pub async fn get_request_logs(Path(request_id): Path<String>) -> Response {
+    info!("GET /admin/logs/{}", request_id);
+
+    if !REQUEST_LOG.is_enabled() {
+        return ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "The request log is not enabled.",
+            None,
+        ))
+        .into_response();
+    }
+
+    let lines = REQUEST_LOG.get(&request_id);
+    if lines.is_empty() {
+        return ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "No lines were found for this request.",
+            Some(request_id),
+        ))
+        .into_response();
+    }
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "request_id": request_id,
+            "lines": lines,
+        })),
+    )
+        .into_response()
+}
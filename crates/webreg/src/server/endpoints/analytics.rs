@@ -0,0 +1,106 @@
+//! Endpoints for analytics computed from the seat history that the tracker records, and
+//! from students' mirrored schedules.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use tracing::info;
+
+use crate::co_enrollment::{CoEnrollmentGraph, MIN_SCHEDULES};
+use crate::db::normalize_course_code;
+use crate::error::WebregError;
+use crate::fill_rate::{compute_fill_rates, FILLS_QUICKLY_HOURS};
+use crate::server::types::{ApiErrorType, CoEnrollmentQueryStr};
+use crate::types::WrapperState;
+
+/// GET /live/:term/analytics/fill_rate
+/// Returns how quickly each tracked course filled, its waitlist resolution rate, and
+/// whether it fills within a day, quickest to fill first
+pub async fn get_fill_rate(
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/analytics/fill_rate", term);
+
+    let history = match s.schedule_db.get_seat_history(&term) {
+        Ok(history) => history,
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to fetch seat history")
+                .into_response()
+        }
+    };
+
+    let first_pass = s.term(&term).and_then(|t| t.first_pass);
+    let courses = compute_fill_rates(&history, first_pass);
+    (
+        StatusCode::OK,
+        Json(json!({
+            "term": term,
+            "measured_from_first_pass": first_pass.is_some(),
+            "fills_quickly_hours": FILLS_QUICKLY_HOURS,
+            "courses": courses,
+        })),
+    )
+        .into_response()
+}
+
+/// The number of co-enrolled courses returned if no limit is given.
+const DEFAULT_CO_ENROLLMENT_LIMIT: usize = 10;
+/// The most co-enrolled courses that can be returned.
+const MAX_CO_ENROLLMENT_LIMIT: usize = 50;
+
+/// GET /analytics/co_enrollment/:course?term=FA24&limit=10
+/// Returns the courses that students enrolled in the course most often take in the same
+/// term, from the schedules that students have mirrored. Courses and pairs of courses seen
+/// in too few schedules aren't reported
+pub async fn get_co_enrollment(
+    Path(course): Path<String>,
+    Query(query): Query<CoEnrollmentQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /analytics/co_enrollment/{}", course);
+
+    let course = normalize_course_code(&course);
+    let term = query.term.map(|t| t.to_uppercase());
+    let schedules = match s.schedule_db.get_enrolled_schedules(term.as_deref()) {
+        Ok(schedules) => schedules,
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to fetch enrolled schedules")
+                .into_response()
+        }
+    };
+
+    let graph = CoEnrollmentGraph::build(&schedules);
+    let Some(course_schedules) = graph.course_schedules(&course) else {
+        return ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            format!(
+                "Not enough schedules include this course (at least {MIN_SCHEDULES} are needed)"
+            ),
+            Some(course),
+        ))
+        .into_response();
+    };
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_CO_ENROLLMENT_LIMIT)
+        .min(MAX_CO_ENROLLMENT_LIMIT);
+    (
+        StatusCode::OK,
+        Json(json!({
+            "course": course,
+            "term": term,
+            "schedules": course_schedules,
+            "total_schedules": graph.schedules,
+            "co_enrolled": graph.neighbors(&course, limit),
+        })),
+    )
+        .into_response()
+}
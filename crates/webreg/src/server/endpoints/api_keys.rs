@@ -0,0 +1,293 @@
+//! Admin endpoints for minting, listing, and revoking API keys along with their scopes
+//! (see [`crate::api_keys`]) and response filters (see [`crate::redaction`]).
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::api_keys::{format_scopes, parse_scopes, ApiScope};
+use crate::error::WebregError;
+use crate::redaction::{ResponseFilter, MAX_FILTER_PATTERNS};
+use crate::server::endpoints::db_error;
+use crate::server::types::{ApiErrorType, BodyMintKey, FieldError};
+use crate::server::validation::{ValidJson, Validate};
+use crate::types::WrapperState;
+
+/// The message for a database error.
+const DB_ERROR: &str = "Failed to access key scopes or filters";
+
+/// Checks the patterns of a response filter.
+///
+/// # Parameters
+/// - `errors`: The errors to add to.
+/// - `field`: The filter's path in the body (e.g., `filter`), or empty if it's the body.
+/// - `filter`: The filter.
+fn check_filter(errors: &mut Vec<FieldError>, field: &str, filter: &ResponseFilter) {
+    for (list, patterns) in [("allow", &filter.allow), ("deny", &filter.deny)] {
+        let path = if field.is_empty() {
+            list.to_owned()
+        } else {
+            format!("{field}.{list}")
+        };
+
+        if patterns.len() > MAX_FILTER_PATTERNS {
+            errors.push(FieldError::new(
+                &path,
+                format!("must have at most {MAX_FILTER_PATTERNS} fields"),
+            ));
+        }
+        for (i, pattern) in patterns.iter().enumerate() {
+            if pattern.trim_matches(['*', '_', '-']).is_empty() && pattern != "*" {
+                errors.push(FieldError::new(
+                    format!("{path}[{i}]"),
+                    "must name a field, or be *",
+                ));
+            }
+        }
+    }
+}
+
+impl Validate for BodyMintKey {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        if self.scopes.is_empty() {
+            errors.push(FieldError::new("scopes", "must have at least one scope"));
+        }
+        if let Some(filter) = &self.filter {
+            check_filter(&mut errors, "filter", filter);
+        }
+        errors
+    }
+}
+
+impl Validate for ResponseFilter {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        check_filter(&mut errors, "", self);
+        errors
+    }
+}
+
+/// Gets the response filter of an API key, if it has one. Filters are checked before
+/// they're saved, so one that can't be parsed was changed outside of the API, and is
+/// reported as an error rather than shown as no filter.
+fn key_filter(s: &WrapperState, prefix: &str) -> Result<Option<ResponseFilter>, WebregError> {
+    let filter = s
+        .schedule_db
+        .get_key_filter(prefix)
+        .map_err(|e| WebregError::from(e).with_message("Failed to access key scopes or filters"))?;
+    let Some(filter) = filter else {
+        return Ok(None);
+    };
+
+    serde_json::from_str(&filter).map(Some).map_err(|e| {
+        warn!("The response filter of '{prefix}' is invalid: {e}");
+        WebregError::Internal {
+            message: "The key's response filter is invalid".into(),
+            context: Some(prefix.to_owned()),
+            code: None,
+        }
+    })
+}
+
+/// POST /admin/keys
+///
+/// Mints an API key with the given scopes and, optionally, a response filter. The key is
+/// only shown in this response.
+pub async fn post_key(
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyMintKey>,
+) -> Response {
+    info!("POST /admin/keys");
+
+    let api_key = s.auth_manager.generate_api_key(body.description.as_deref());
+    let prefix = api_key.split_once('#').map_or(api_key.as_str(), |(p, _)| p);
+    let scopes = format_scopes(&body.scopes);
+    if let Err(e) = s.schedule_db.set_key_scopes(prefix, &scopes) {
+        // A key that was minted without its scopes would have every scope
+        s.auth_manager.delete_by_prefix(prefix);
+        return db_error(e, DB_ERROR);
+    }
+
+    let filter = body.filter.filter(|f| !f.is_empty());
+    if let Some(filter) = &filter {
+        if let Err(e) = s
+            .schedule_db
+            .set_key_filter(prefix, &serde_json::to_string(filter).unwrap())
+        {
+            // Nor should one without its filter see every field
+            s.auth_manager.delete_by_prefix(prefix);
+            let _ = s.schedule_db.delete_key_scopes(prefix);
+            return db_error(e, DB_ERROR);
+        }
+    }
+
+    (
+        StatusCode::CREATED,
+        Json(json!({
+            "api_key": api_key,
+            "prefix": prefix,
+            "scopes": parse_scopes(&scopes).0,
+            "filter": filter,
+        })),
+    )
+        .into_response()
+}
+
+/// GET /admin/keys
+///
+/// Returns every API key's prefix, description, expiration, scopes, and response filter. The
+/// keys themselves aren't shown.
+pub async fn get_keys(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("GET /admin/keys");
+
+    let mut keys = vec![];
+    for entry in s.auth_manager.get_all_entries() {
+        let scopes = match s.schedule_db.get_key_scopes(&entry.prefix) {
+            Ok(Some(scopes)) => parse_scopes(&scopes).0,
+            Ok(None) => ApiScope::ALL.to_vec(),
+            Err(e) => return db_error(e, DB_ERROR),
+        };
+        let filter = match key_filter(&s, &entry.prefix) {
+            Ok(filter) => filter,
+            Err(e) => return e.into_response(),
+        };
+
+        keys.push(json!({
+            "prefix": entry.prefix,
+            "description": entry.description,
+            "created_at": entry.created_at.to_rfc3339(),
+            "expires_at": entry.expires_at.to_rfc3339(),
+            "scopes": scopes,
+            "filter": filter,
+        }));
+    }
+
+    (StatusCode::OK, Json(keys)).into_response()
+}
+
+/// DELETE /admin/keys/:key_id
+///
+/// Revokes an API key, identified by its prefix, so that it can't be used anymore.
+pub async fn delete_key(
+    Path(key_id): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("DELETE /admin/keys/{}", key_id);
+
+    if !s.auth_manager.delete_by_prefix(&key_id) {
+        return ApiErrorType::from((StatusCode::NOT_FOUND, "No such key was found.", None))
+            .into_response();
+    }
+
+    // A registered key's cookie server can be given to whoever registers next
+    s.user_cookie_servers.write().unwrap().remove(&key_id);
+    let deleted = s
+        .schedule_db
+        .delete_key_scopes(&key_id)
+        .and_then(|_| s.schedule_db.delete_key_filter(&key_id))
+        .and_then(|_| s.schedule_db.release_cookie_server(&key_id));
+    match deleted {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// PUT /admin/keys/:key_id/filter
+///
+/// Sets the response filter of an API key, identified by its prefix, which decides the
+/// fields that it may and may not see in responses. Takes effect on the key's next request.
+pub async fn put_key_filter(
+    Path(key_id): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<ResponseFilter>,
+) -> Response {
+    info!("PUT /admin/keys/{}/filter", key_id);
+
+    if !s.auth_manager.get_all_prefixes().contains(&key_id) {
+        return ApiErrorType::from((StatusCode::NOT_FOUND, "No such key was found.", None))
+            .into_response();
+    }
+
+    let result = if body.is_empty() {
+        s.schedule_db.delete_key_filter(&key_id).map(|_| ())
+    } else {
+        s.schedule_db
+            .set_key_filter(&key_id, &serde_json::to_string(&body).unwrap())
+    };
+
+    match result {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(json!({ "prefix": key_id, "filter": body })),
+        )
+            .into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// DELETE /admin/keys/:key_id/filter
+///
+/// Removes the response filter of an API key, so that it sees every field again.
+pub async fn delete_key_filter(
+    Path(key_id): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("DELETE /admin/keys/{}/filter", key_id);
+
+    match s.schedule_db.delete_key_filter(&key_id) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "The key doesn't have a response filter.",
+            None,
+        ))
+        .into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::extract::{FromRequest, Request};
+
+    async fn read<T: serde::de::DeserializeOwned + Validate>(
+        body: serde_json::Value,
+    ) -> StatusCode {
+        let req = Request::builder()
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        match ValidJson::<T>::from_request(req, &()).await {
+            Ok(_) => StatusCode::OK,
+            Err(response) => response.status(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_filter_rejected() {
+        // A misspelled list would otherwise be read as an empty filter
+        let filter = json!({ "denny": ["grade"] });
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            read::<ResponseFilter>(filter.clone()).await
+        );
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            read::<BodyMintKey>(json!({ "scopes": ["schedule"], "filter": filter })).await
+        );
+
+        assert_eq!(
+            StatusCode::OK,
+            read::<ResponseFilter>(json!({ "deny": ["grade"] })).await
+        );
+    }
+}
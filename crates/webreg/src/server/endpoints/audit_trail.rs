@@ -0,0 +1,304 @@
+//! Admin endpoints for editing requirement configs and overrides, and for viewing and
+//! rolling back their history (see [`crate::audit_trail`]).
+
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::audit_trail::{
+    apply_config_version, override_key, OverrideKind, RequirementOverride, TrailKind, FILE_AUTHOR,
+    MAX_TRAIL_ENTRIES,
+};
+use crate::db::TrailEntry;
+use crate::error::WebregError;
+use crate::server::endpoints::db_error;
+use crate::server::types::{
+    ApiErrorType, AuditTrailQueryStr, BodyRequirementConfig, BodyRequirementOverride, BodyRollback,
+    FieldError,
+};
+use crate::server::validation::{require, ValidJson, Validate};
+use crate::types::WrapperState;
+
+/// The number of versions returned if the caller doesn't say.
+const DEFAULT_TRAIL_LIMIT: usize = 100;
+
+/// The message for a database error.
+const DB_ERROR: &str = "Failed to access the audit trail.";
+
+/// Checks that an edit says who made it and why.
+fn check_attribution(errors: &mut Vec<FieldError>, author: &str, reason: &str) {
+    require(errors, "author", author);
+    require(errors, "reason", reason);
+}
+
+impl Validate for BodyRequirementConfig {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        check_attribution(&mut errors, &self.author, &self.reason);
+        errors
+    }
+}
+
+impl Validate for BodyRequirementOverride {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        require(&mut errors, "requirement", &self.requirement);
+        check_attribution(&mut errors, &self.author, &self.reason);
+        errors
+    }
+}
+
+impl Validate for BodyRollback {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        check_attribution(&mut errors, &self.author, &self.reason);
+        errors
+    }
+}
+
+/// Converts a version to JSON, with its content as JSON rather than a string.
+fn entry_json(entry: &TrailEntry) -> Value {
+    let mut value = json!(entry);
+    if let Some(content) = entry.content.as_deref() {
+        value["content"] = serde_json::from_str(content).unwrap_or(Value::Null);
+    }
+
+    value
+}
+
+/// Sets a college's or major's config in the live requirements config.
+fn apply_live(s: &WrapperState, kind: TrailKind, code: &str, content: &str) -> Result<(), String> {
+    apply_config_version(
+        &mut s.requirements_config.write().unwrap(),
+        kind,
+        code,
+        content,
+    )
+}
+
+/// PUT /admin/requirements/:kind/:code
+///
+/// Replaces a college's (`kind` is `college`) or major's (`kind` is `major`) requirement
+/// config, recording it as a new version along with who made the change and why. The
+/// first time a config from the `requirements_config` files is edited, the file's version
+/// is recorded first, so that the edit can be rolled back.
+pub async fn put_requirement_config(
+    Path((kind, code)): Path<(String, String)>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyRequirementConfig>,
+) -> Response {
+    info!("PUT /admin/requirements/{}/{}", kind, code);
+
+    let kind = match TrailKind::parse(&kind) {
+        Some(kind @ (TrailKind::College | TrailKind::Major)) => kind,
+        _ => {
+            return ApiErrorType::from((
+                StatusCode::NOT_FOUND,
+                "Only college and major configs can be edited.",
+                Some(kind),
+            ))
+            .into_response()
+        }
+    };
+
+    let content = body.config.to_string();
+    if let Err(e) = apply_config_version(&mut s.requirements(), kind, &code, &content) {
+        return ApiErrorType::from((StatusCode::BAD_REQUEST, "The config isn't valid.", Some(e)))
+            .into_response();
+    }
+
+    match s.schedule_db.has_trail_versions(kind.as_str(), &code) {
+        Ok(true) => {}
+        Ok(false) => {
+            let baseline = {
+                let config = s.requirements_config.read().unwrap();
+                match kind {
+                    TrailKind::College => config.colleges.get(&code).map(|c| json!(c)),
+                    _ => config.majors.get(&code).map(|m| json!(m)),
+                }
+            };
+            if let Some(baseline) = baseline {
+                if let Err(e) = s.schedule_db.record_trail_version(
+                    kind.as_str(),
+                    &code,
+                    Some(&baseline.to_string()),
+                    FILE_AUTHOR,
+                    "The version from the requirements_config files",
+                ) {
+                    return db_error(e, DB_ERROR);
+                }
+            }
+        }
+        Err(e) => return db_error(e, DB_ERROR),
+    }
+
+    let entry = match s.schedule_db.record_trail_version(
+        kind.as_str(),
+        &code,
+        Some(&content),
+        body.author.trim(),
+        body.reason.trim(),
+    ) {
+        Ok(entry) => entry,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    // Already validated above, so this can't fail
+    let _ = apply_live(&s, kind, &code, &content);
+    (StatusCode::OK, Json(entry_json(&entry))).into_response()
+}
+
+/// PUT /admin/overrides
+///
+/// Overrides the status of a requirement or subrequirement on a student's audit (e.g.,
+/// when a petition is approved), or removes the override if no status is given. In
+/// multi-user mode, the override is only for the audit of the `user` given; otherwise, it's
+/// for the shared audit. The change is recorded as a new version of the requirement's
+/// override for that user, along with who made it and why.
+pub async fn put_requirement_override(
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyRequirementOverride>,
+) -> Response {
+    info!("PUT /admin/overrides ({})", body.requirement);
+
+    let requirement = body.requirement.trim();
+    let user = body
+        .user
+        .as_deref()
+        .map(str::trim)
+        .filter(|u| !u.is_empty());
+
+    let content = body.status.map(|status| {
+        json!(RequirementOverride {
+            requirement: requirement.to_owned(),
+            status,
+            kind: body.kind.unwrap_or(OverrideKind::Manual),
+            user: user.map(str::to_owned),
+        })
+        .to_string()
+    });
+    match s.schedule_db.record_trail_version(
+        TrailKind::Override.as_str(),
+        &override_key(user, requirement),
+        content.as_deref(),
+        body.author.trim(),
+        body.reason.trim(),
+    ) {
+        Ok(entry) => (StatusCode::OK, Json(entry_json(&entry))).into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// GET /admin/audit_trail
+///
+/// Returns the versions of requirement configs and overrides, newest first, each with who
+/// made the change, when, and why. Can be filtered by `kind` (`college`, `major`, or
+/// `override`) and `key` (the college or major code, or the requirement's name in lower
+/// case, after the user's key prefix and a `/` for a user's override).
+pub async fn get_audit_trail(
+    Query(query): Query<AuditTrailQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /admin/audit_trail");
+
+    if let Some(ref kind) = query.kind {
+        if TrailKind::parse(kind).is_none() {
+            return ApiErrorType::from((
+                StatusCode::BAD_REQUEST,
+                "The kind must be college, major, or override.",
+                Some(kind.to_owned()),
+            ))
+            .into_response();
+        }
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_TRAIL_LIMIT)
+        .clamp(1, MAX_TRAIL_ENTRIES);
+    match s
+        .schedule_db
+        .get_audit_trail(query.kind.as_deref(), query.key.as_deref(), limit)
+    {
+        Ok(entries) => (
+            StatusCode::OK,
+            Json(json!({
+                "entries": entries.iter().map(entry_json).collect::<Vec<_>>(),
+            })),
+        )
+            .into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// POST /admin/audit_trail/:entry_id/rollback
+///
+/// Rolls a requirement config or override back to an earlier version, by recording that
+/// version's content as a new version. The rollback itself is recorded with who made it
+/// and why, so it can be rolled back too.
+pub async fn post_rollback(
+    Path(entry_id): Path<i64>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyRollback>,
+) -> Response {
+    info!("POST /admin/audit_trail/{}/rollback", entry_id);
+
+    let target = match s.schedule_db.get_trail_entry(entry_id) {
+        Ok(Some(target)) => target,
+        Ok(None) => {
+            return ApiErrorType::from((
+                StatusCode::NOT_FOUND,
+                "No version has that ID.",
+                Some(entry_id.to_string()),
+            ))
+            .into_response()
+        }
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+    let Some(kind) = TrailKind::parse(&target.kind) else {
+        return WebregError::internal("The version is of an unknown kind.", target.kind)
+            .into_response();
+    };
+
+    // A stored config may no longer parse (e.g., if the config format changed since)
+    let config = target
+        .content
+        .as_deref()
+        .filter(|_| kind != TrailKind::Override);
+    if let Some(content) = config {
+        let check = apply_config_version(&mut s.requirements(), kind, &target.entity_key, content);
+        if let Err(e) = check {
+            return ApiErrorType::from((
+                StatusCode::CONFLICT,
+                "The version's config is no longer valid.",
+                Some(e),
+            ))
+            .into_response();
+        }
+    }
+
+    let entry = match s.schedule_db.record_trail_version(
+        &target.kind,
+        &target.entity_key,
+        target.content.as_deref(),
+        body.author.trim(),
+        &format!(
+            "Rolled back to version {}: {}",
+            target.version,
+            body.reason.trim()
+        ),
+    ) {
+        Ok(entry) => entry,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    if let Some(content) = config {
+        let _ = apply_live(&s, kind, &target.entity_key, content);
+    }
+    (StatusCode::OK, Json(entry_json(&entry))).into_response()
+}
@@ -0,0 +1,594 @@
+//! Endpoints for the course cart, which holds the courses that a student is considering
+//! (with the sections they'd take, notes, and a priority for each) on this server, since
+//! WebReg's plans only hold a few schedules.
+//!
+//! Like enrollment jobs, carts are kept between requests, so they can only be used with a
+//! session token (see [`SESSION_TOKEN_HEADER`]). Items are promoted into WebReg through the
+//! `add_plan` and `add_section` endpoints, so they get the same checks and receipts.
+//!
+//! [`SESSION_TOKEN_HEADER`]: crate::sessions::SESSION_TOKEN_HEADER
+
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::db::{CartItem, CartSection, CatalogCourse, NewCartItem};
+use crate::server::endpoints::db_error;
+use crate::server::endpoints::enroll_jobs::{no_session, session_token};
+use crate::server::endpoints::ww_cookies;
+use crate::server::types::{
+    ApiErrorType, BodyAddInfo, BodyCartItem, BodyCartPromote, BodyPlanAdd, CartTarget,
+    DryRunQueryStr, FieldError, ForceQueryStr,
+};
+use crate::server::validation::{check_range, check_section_id, require, ValidJson, Validate};
+use crate::types::WrapperState;
+
+/// The largest number of courses that a session's cart can hold in a term.
+pub(super) const MAX_CART_ITEMS: usize = 50;
+/// The largest number of sections that a cart item can list.
+const MAX_CART_SECTIONS: usize = 10;
+
+/// Why the endpoints need a session token.
+const NO_SESSION: &str = "The cart needs a session token, since it's kept between requests.";
+/// The message for a database error.
+const DB_ERROR: &str = "Failed to access the cart";
+
+/// Creates the response for an item that isn't in the session's cart.
+fn not_found() -> Response {
+    ApiErrorType::from((StatusCode::NOT_FOUND, "Cart item not found", None)).into_response()
+}
+
+/// Gets the number of units that a course is always taken for, according to the catalog.
+///
+/// # Returns
+/// The number of units, or `None` if the catalog gives a range of units (e.g., for an
+/// independent study) or doesn't say.
+fn fixed_units(course: &CatalogCourse) -> Option<i64> {
+    match (course.min_units, course.max_units) {
+        (Some(min), Some(max)) if min == max && min >= 1.0 && min.fract() == 0.0 => {
+            Some(min as i64)
+        }
+        _ => None,
+    }
+}
+
+/// Finds the number of units to plan a cart item's section for: the number given when
+/// promoting it, or else the item's, or else the number that the course is always taken
+/// for, if its catalog entry has been scraped.
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `item`: The cart item.
+/// - `requested`: The number of units given when promoting the item, if any.
+///
+/// # Returns
+/// The number of units, or `None` if it isn't known.
+fn plan_unit_count(
+    s: &WrapperState,
+    item: &CartItem,
+    requested: Option<i64>,
+) -> rusqlite::Result<Option<i64>> {
+    if let Some(units) = requested.or(item.unit_count) {
+        return Ok(Some(units));
+    }
+
+    Ok(s.schedule_db
+        .get_catalog_course(&item.subject_code, &item.course_code)?
+        .as_ref()
+        .and_then(fixed_units))
+}
+
+/// Normalizes the sections of a cart item's body, keeping them in the order they were given.
+pub(super) fn sections_from_body(body: &BodyCartItem) -> Vec<CartSection> {
+    body.sections
+        .iter()
+        .map(|section| CartSection {
+            section_id: section.section_id.trim().to_owned(),
+            section_code: section.section_code.trim().to_uppercase(),
+        })
+        .collect()
+}
+
+/// GET /live/:term/cart
+/// Returns the session's cart for the term, most important first
+pub async fn get_cart(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/cart", term);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    match s.schedule_db.get_cart(&term, token) {
+        Ok(items) => (StatusCode::OK, Json(items)).into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+impl Validate for BodyCartItem {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        require(&mut errors, "subjectCode", &self.subject_code);
+        require(&mut errors, "courseCode", &self.course_code);
+        if self.sections.len() > MAX_CART_SECTIONS {
+            errors.push(FieldError::new(
+                "sections",
+                format!("can list at most {MAX_CART_SECTIONS} sections"),
+            ));
+        }
+        for (i, section) in self.sections.iter().enumerate() {
+            check_section_id(
+                &mut errors,
+                &format!("sections[{i}].sectionId"),
+                &section.section_id,
+            );
+        }
+        errors
+    }
+}
+
+impl Validate for BodyCartPromote {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        if let Some(section_id) = &self.section_id {
+            check_section_id(&mut errors, "sectionId", section_id);
+        }
+        check_range(
+            &mut errors,
+            "unitCount",
+            self.unit_count,
+            1..=u8::MAX as i64,
+        );
+        errors
+    }
+}
+
+/// POST /live/:term/cart
+/// Adds a course to the session's cart, or replaces its item if it's already in the cart.
+/// Without a `priority`, a new course goes after every other course, and a course that's
+/// already in the cart keeps its priority. Returns the item's ID
+pub async fn post_cart_item(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyCartItem>,
+) -> Response {
+    info!("POST /live/{}/cart", term);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    let (subject_code, course_code) = (body.subject_code.trim(), body.course_code.trim());
+    let sections = sections_from_body(&body);
+
+    let cart = match s.schedule_db.get_cart(&term, token) {
+        Ok(cart) => cart,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    let existing = cart.iter().find(|item| {
+        item.subject_code.eq_ignore_ascii_case(subject_code)
+            && item.course_code.eq_ignore_ascii_case(course_code)
+    });
+    if existing.is_none() && cart.len() >= MAX_CART_ITEMS {
+        return ApiErrorType::from((
+            StatusCode::TOO_MANY_REQUESTS,
+            "This session's cart is full for this term.",
+            None,
+        ))
+        .into_response();
+    }
+
+    let priority = body.priority.unwrap_or_else(|| match existing {
+        Some(item) => item.priority,
+        None => cart.iter().map(|i| i.priority + 1).max().unwrap_or(0),
+    });
+
+    let (subject_code, course_code) = (subject_code.to_uppercase(), course_code.to_uppercase());
+    let item = NewCartItem {
+        term: &term,
+        session_token: token,
+        subject_code: &subject_code,
+        course_code: &course_code,
+        sections: &sections,
+        notes: body
+            .notes
+            .as_deref()
+            .map(str::trim)
+            .filter(|n| !n.is_empty()),
+        priority,
+        grading_option: body.grading_option.as_deref(),
+        unit_count: body.unit_count,
+    };
+
+    match s.schedule_db.upsert_cart_item(&item) {
+        Ok(item_id) => (StatusCode::OK, Json(json!({ "item_id": item_id }))).into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// DELETE /live/:term/cart/:item_id
+/// Removes a course from the session's cart
+pub async fn delete_cart_item(
+    headers: HeaderMap,
+    Path((term, item_id)): Path<(String, i64)>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("DELETE /live/{}/cart/{}", term, item_id);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    match s.schedule_db.delete_cart_item(&term, token, item_id) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => not_found(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// POST /live/:term/cart/:item_id/promote
+/// Promotes one of a cart item's sections (by default, its most preferred one) into WebReg,
+/// either by adding it to a plan (`"target": "plan"`) or by enrolling in it
+/// (`"target": "enroll"`). The response is that of `add_plan` or `add_section`, which this
+/// calls; `?force=true` is passed on to `add_section`, and `?dry_run=true` to both. Once
+/// the student is enrolled, the item is removed from the cart. A section is only added to a
+/// plan if its units are known (see `unitCount`); otherwise, this is a `400 Bad Request`
+pub async fn post_promote_cart_item(
+    headers: HeaderMap,
+    Path((term, item_id)): Path<(String, i64)>,
+    Query(query): Query<ForceQueryStr>,
+    Query(dry_run): Query<DryRunQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyCartPromote>,
+) -> Response {
+    info!("POST /live/{}/cart/{}/promote", term, item_id);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    let item = match s.schedule_db.get_cart_item(&term, token, item_id) {
+        Ok(Some(item)) => item,
+        Ok(None) => return not_found(),
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    let section = match body.section_id.as_deref().map(str::trim) {
+        Some(section_id) => item.sections.iter().find(|s| s.section_id == section_id),
+        None => item.sections.first(),
+    };
+    let Some(section) = section.cloned() else {
+        return ApiErrorType::from((
+            StatusCode::BAD_REQUEST,
+            "The cart item doesn't have that section.",
+            body.section_id,
+        ))
+        .into_response();
+    };
+
+    match body.target {
+        CartTarget::Plan => {
+            let unit_count = match plan_unit_count(&s, &item, body.unit_count) {
+                Ok(Some(unit_count)) => unit_count,
+                Ok(None) => {
+                    return ApiErrorType::invalid_fields(vec![FieldError::new(
+                        "unitCount",
+                        "is required, since the course's units aren't known",
+                    )])
+                    .into_response();
+                }
+                Err(e) => return db_error(e, DB_ERROR),
+            };
+            let plan_add = BodyPlanAdd {
+                subject_code: item.subject_code,
+                course_code: item.course_code,
+                section_id: section.section_id,
+                section_code: section.section_code,
+                grading_option: item.grading_option,
+                schedule_name: body.schedule_name,
+                unit_count,
+                validate: body.validate,
+            };
+            ww_cookies::post_add_plan(
+                headers,
+                Path(term),
+                Query(dry_run),
+                State(s),
+                ValidJson(plan_add),
+            )
+            .await
+        }
+        CartTarget::Enroll => {
+            let add = BodyAddInfo {
+                section_id: section.section_id,
+                grading_option: item.grading_option,
+                unit_count: item.unit_count,
+                validate: body.validate,
+            };
+            let dry_run_only = dry_run.dry_run.unwrap_or(false);
+            let response = ww_cookies::post_add_section(
+                headers.clone(),
+                Path(term.clone()),
+                Query(query),
+                Query(dry_run),
+                State(s.clone()),
+                ValidJson(add),
+            )
+            .await;
+
+            if response.status() == StatusCode::OK && !dry_run_only {
+                if let Err(e) = s.schedule_db.delete_cart_item(&term, token, item_id) {
+                    warn!("Failed to remove promoted cart item {}: {}", item_id, e);
+                }
+            }
+
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+    use axum::http::header::COOKIE;
+    use axum::http::HeaderValue;
+    use serde_json::Value;
+    use std::time::Duration;
+    use webweg::wrapper::WebRegWrapper;
+
+    use crate::server::types::BodyCartSection;
+    use crate::sessions::SESSION_TOKEN_HEADER;
+    use crate::types::tests::state;
+
+    /// Creates a state whose requests to WebReg time out right away, as if it were down.
+    fn cart_state(name: &str) -> Arc<WrapperState> {
+        let mut s = state(name, &["FA24"], json!({ "webregRetry": { "attempts": 1 } }));
+        s.c_wrapper = WebRegWrapper::builder()
+            .with_cookies("")
+            .with_default_timeout(Duration::from_millis(1))
+            .should_close_after_request(true)
+            .try_build_wrapper()
+            .unwrap();
+        Arc::new(s)
+    }
+
+    fn session(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(SESSION_TOKEN_HEADER, HeaderValue::from_str(token).unwrap());
+        headers.insert(COOKIE, HeaderValue::from_static("jlinksessionidx=abc"));
+        headers
+    }
+
+    fn item(course_code: &str, section_ids: &[&str], priority: Option<i64>) -> BodyCartItem {
+        BodyCartItem {
+            subject_code: " cse ".to_owned(),
+            course_code: course_code.to_owned(),
+            sections: section_ids
+                .iter()
+                .map(|id| BodyCartSection {
+                    section_id: format!(" {id} "),
+                    section_code: "a01".to_owned(),
+                })
+                .collect(),
+            notes: Some("  ".to_owned()),
+            priority,
+            grading_option: None,
+            unit_count: Some(4),
+        }
+    }
+
+    async fn json_body(res: Response) -> Value {
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    async fn add(s: &Arc<WrapperState>, token: &str, body: BodyCartItem) -> i64 {
+        let res = post_cart_item(
+            session(token),
+            Path("FA24".to_owned()),
+            State(s.clone()),
+            ValidJson(body),
+        )
+        .await;
+        assert_eq!(StatusCode::OK, res.status());
+        json_body(res).await["item_id"].as_i64().unwrap()
+    }
+
+    async fn cart(s: &Arc<WrapperState>, token: &str) -> Value {
+        let res = get_cart(session(token), Path("FA24".to_owned()), State(s.clone())).await;
+        assert_eq!(StatusCode::OK, res.status());
+        json_body(res).await
+    }
+
+    async fn promote_to(
+        s: &Arc<WrapperState>,
+        item_id: i64,
+        target: CartTarget,
+        section_id: Option<&str>,
+    ) -> Response {
+        let body = BodyCartPromote {
+            target,
+            section_id: section_id.map(str::to_owned),
+            schedule_name: None,
+            unit_count: None,
+            validate: None,
+        };
+        post_promote_cart_item(
+            session("a"),
+            Path(("FA24".to_owned(), item_id)),
+            Query(ForceQueryStr { force: None }),
+            Query(DryRunQueryStr { dry_run: None }),
+            State(s.clone()),
+            ValidJson(body),
+        )
+        .await
+    }
+
+    async fn promote(s: &Arc<WrapperState>, item_id: i64, section_id: Option<&str>) -> StatusCode {
+        promote_to(s, item_id, CartTarget::Enroll, section_id)
+            .await
+            .status()
+    }
+
+    fn catalog_course(number: &str, min_units: f32, max_units: f32) -> CatalogCourse {
+        CatalogCourse {
+            subject: "CSE".to_owned(),
+            number: number.to_owned(),
+            title: String::new(),
+            description: String::new(),
+            min_units: Some(min_units),
+            max_units: Some(max_units),
+            prerequisites: None,
+            cross_listings: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cart_add_and_remove() {
+        let s = cart_state("cart-add-remove");
+        let first = add(&s, "a", item("100", &["111111"], None)).await;
+        let second = add(&s, "a", item("101", &["222222", "333333"], None)).await;
+
+        let items = cart(&s, "a").await;
+        assert_eq!(2, items.as_array().unwrap().len());
+        assert_eq!(first, items[0]["item_id"]);
+        assert_eq!("CSE", items[0]["subject_code"]);
+        assert_eq!(0, items[0]["priority"]);
+        assert!(items[0]["notes"].is_null());
+        assert!(items[0].get("session_token").is_none());
+        assert_eq!(second, items[1]["item_id"]);
+        assert_eq!(1, items[1]["priority"]);
+        assert_eq!(
+            json!([
+                { "section_id": "222222", "section_code": "A01" },
+                { "section_id": "333333", "section_code": "A01" },
+            ]),
+            items[1]["sections"]
+        );
+
+        // Adding a course that's already in the cart replaces its item, keeping its priority
+        assert_eq!(first, add(&s, "a", item("100", &["444444"], None)).await);
+        let items = cart(&s, "a").await;
+        assert_eq!(2, items.as_array().unwrap().len());
+        assert_eq!(0, items[0]["priority"]);
+        assert_eq!("444444", items[0]["sections"][0]["section_id"]);
+
+        // Another session has its own cart, and can't remove this one's items
+        assert!(cart(&s, "b").await.as_array().unwrap().is_empty());
+        let res = delete_cart_item(
+            session("b"),
+            Path(("FA24".to_owned(), first)),
+            State(s.clone()),
+        )
+        .await;
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+
+        let res = delete_cart_item(
+            session("a"),
+            Path(("FA24".to_owned(), first)),
+            State(s.clone()),
+        )
+        .await;
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+        let items = cart(&s, "a").await;
+        assert_eq!(1, items.as_array().unwrap().len());
+        assert_eq!(second, items[0]["item_id"]);
+
+        let res = get_cart(HeaderMap::new(), Path("FA24".to_owned()), State(s.clone())).await;
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+    }
+
+    #[tokio::test]
+    async fn test_cart_is_limited() {
+        let s = cart_state("cart-limit");
+        for i in 0..MAX_CART_ITEMS {
+            add(&s, "a", item(&i.to_string(), &[], None)).await;
+        }
+
+        let res = post_cart_item(
+            session("a"),
+            Path("FA24".to_owned()),
+            State(s.clone()),
+            ValidJson(item("999", &[], None)),
+        )
+        .await;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, res.status());
+        // A course that's already in the cart can still be changed
+        add(&s, "a", item("0", &["111111"], Some(3))).await;
+    }
+
+    #[tokio::test]
+    async fn test_failed_promotion_keeps_item() {
+        let s = cart_state("cart-promote");
+        let item_id = add(&s, "a", item("100", &["111111", "222222"], None)).await;
+
+        assert_eq!(StatusCode::NOT_FOUND, promote(&s, item_id + 1, None).await);
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            promote(&s, item_id, Some("999999")).await
+        );
+
+        // WebReg can't be reached, so the student isn't enrolled and the item stays
+        let status = promote(&s, item_id, Some("222222")).await;
+        assert_ne!(StatusCode::OK, status);
+        let items = cart(&s, "a").await;
+        assert_eq!(item_id, items[0]["item_id"]);
+        assert_eq!(2, items[0]["sections"].as_array().unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn test_plan_promotion_needs_units() {
+        let s = cart_state("cart-promote-units");
+        let mut fixed = item("100", &["111111"], None);
+        fixed.unit_count = None;
+        let fixed = add(&s, "a", fixed).await;
+        let mut variable = item("199", &["222222"], None);
+        variable.unit_count = None;
+        let variable = add(&s, "a", variable).await;
+        let cart_item = |item_id| {
+            s.schedule_db
+                .get_cart_item("FA24", "a", item_id)
+                .unwrap()
+                .unwrap()
+        };
+
+        // Nothing says how many units the course is for, so it isn't planned for any
+        let res = promote_to(&s, fixed, CartTarget::Plan, None).await;
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+        assert_eq!("unitCount", json_body(res).await["details"][0]["field"]);
+        assert_eq!(None, plan_unit_count(&s, &cart_item(fixed), None).unwrap());
+
+        s.schedule_db
+            .upsert_catalog_courses(&[
+                catalog_course("100", 4.0, 4.0),
+                catalog_course("199", 1.0, 4.0),
+            ])
+            .unwrap();
+        assert_eq!(
+            Some(4),
+            plan_unit_count(&s, &cart_item(fixed), None).unwrap()
+        );
+        // A course with a range of units needs to be told which
+        assert_eq!(
+            None,
+            plan_unit_count(&s, &cart_item(variable), None).unwrap()
+        );
+        let res = promote_to(&s, variable, CartTarget::Plan, None).await;
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+        assert_eq!(
+            Some(2),
+            plan_unit_count(&s, &cart_item(variable), Some(2)).unwrap()
+        );
+    }
+}
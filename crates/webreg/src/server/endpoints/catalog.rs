@@ -0,0 +1,36 @@
+//! Endpoints for the course catalog (see [`crate::scraper::catalog`]).
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use tracing::info;
+
+use crate::error::WebregError;
+use crate::server::types::ApiErrorType;
+use crate::types::WrapperState;
+
+/// GET /catalog/:subject/:number
+/// Returns the course's description, unit range, prerequisites, and cross-listings from the
+/// course catalog
+pub async fn get_catalog_course(
+    Path((subject, number)): Path<(String, String)>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /catalog/{}/{}", subject, number);
+
+    match s.schedule_db.get_catalog_course(&subject, &number) {
+        Ok(Some(course)) => (StatusCode::OK, Json(course)).into_response(),
+        Ok(None) => ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "Course not found in the catalog",
+            Some(format!("{subject} {number}")),
+        ))
+        .into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to fetch the course from the catalog")
+            .into_response(),
+    }
+}
@@ -0,0 +1,85 @@
+//! The feed of changes to a term's sections between scrapes (see [`crate::changes`]).
+
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::DateTime;
+use serde_json::json;
+use tracing::info;
+
+use crate::changes::MAX_CHANGES;
+use crate::error::WebregError;
+use crate::server::types::{ApiErrorType, ChangesQueryStr};
+use crate::types::WrapperState;
+
+/// Parses the start of the feed, either as milliseconds since the epoch or in RFC 3339
+/// format.
+fn parse_since(since: &str) -> Option<i64> {
+    since.trim().parse::<i64>().ok().or_else(|| {
+        DateTime::parse_from_rfc3339(since.trim())
+            .ok()
+            .map(|t| t.timestamp_millis())
+    })
+}
+
+/// GET /live/:term/changes
+/// Returns the sections that were added, cancelled, or modified (a meeting's time, room,
+/// or instructors changed) since `since`, oldest first, found by comparing each course's
+/// scrapes. Can be narrowed down to one `subject` (e.g., `CSE`). Passing back `until` as
+/// the next request's `since` returns only what changed after this request.
+pub async fn get_section_changes(
+    Path(term): Path<String>,
+    Query(query): Query<ChangesQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/changes (since={})", term, query.since);
+
+    let Some(since) = parse_since(&query.since) else {
+        return ApiErrorType::from((
+            StatusCode::BAD_REQUEST,
+            "The since parameter must be milliseconds since the epoch or an RFC 3339 time.",
+            Some(query.since),
+        ))
+        .into_response();
+    };
+
+    let limit = query.limit.unwrap_or(MAX_CHANGES).clamp(1, MAX_CHANGES);
+    let subject = query.subject.as_deref().map(str::trim);
+    match s
+        .schedule_db
+        .get_section_changes(&term, since, subject, limit)
+    {
+        Ok(mut changes) => {
+            // Changes found by the same scrape share a time, so a page can't end partway
+            // through them without the rest being skipped by the next page
+            let has_more = changes.len() == limit;
+            if has_more {
+                let last = changes.last().map(|c| c.changed_at);
+                let whole = changes
+                    .iter()
+                    .rposition(|c| Some(c.changed_at) != last)
+                    .map_or(changes.len(), |i| i + 1);
+                changes.truncate(whole);
+            }
+
+            let until = changes.last().map_or(since, |c| c.changed_at);
+            (
+                StatusCode::OK,
+                Json(json!({
+                    "term": term,
+                    "since": since,
+                    "until": until,
+                    "has_more": has_more,
+                    "changes": changes,
+                })),
+            )
+                .into_response()
+        }
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to fetch section changes")
+            .into_response(),
+    }
+}
@@ -0,0 +1,444 @@
+//! Endpoints for course plans, which lay out the courses a student means to take over the
+//! coming quarters (see [`crate::course_plans`]).
+//!
+//! Like saved plans, course plans are kept between requests, so they can only be used with
+//! a session token (see [`SESSION_TOKEN_HEADER`]).
+//!
+//! [`SESSION_TOKEN_HEADER`]: crate::sessions::SESSION_TOKEN_HEADER
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::{Extensions, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+#[cfg(feature = "auth")]
+use crate::api_keys::{ApiScope, KeyScopes};
+use crate::course_plans::check_course_plan;
+use crate::db::{normalize_course_code, CoursePlan, PlannedQuarter};
+use crate::server::endpoints::db_error;
+use crate::server::endpoints::degree_audit::{completed_courses, fetch_prerequisites};
+use crate::server::endpoints::enroll_jobs::no_session;
+use crate::server::endpoints::sessions::owned_session_token;
+use crate::server::types::{ApiErrorType, BodyCoursePlan, FieldError};
+use crate::server::validation::{require, ValidJson, Validate};
+use crate::types::WrapperState;
+
+/// The largest number of course plans that a session can save.
+pub(super) const MAX_COURSE_PLANS: usize = 20;
+/// The largest number of quarters that a course plan can span.
+const MAX_PLAN_QUARTERS: usize = 24;
+/// The largest number of courses that can be planned for one quarter.
+const MAX_QUARTER_COURSES: usize = 10;
+
+/// Why the endpoints need a session token, which is only accepted with the API key that
+/// registered its session.
+const NO_SESSION: &str =
+    "Course plans need a valid session token, since they're kept between requests.";
+/// The message for a database error.
+const DB_ERROR: &str = "Failed to access course plans";
+
+/// Creates the response for a database error, which is a conflict if the session already
+/// has a course plan with the name that was given.
+fn course_plan_error(e: rusqlite::Error) -> Response {
+    if e.sqlite_error_code() == Some(rusqlite::ErrorCode::ConstraintViolation) {
+        return ApiErrorType::from((
+            StatusCode::CONFLICT,
+            "This session already has a course plan with that name.",
+            None,
+        ))
+        .into_response();
+    }
+
+    db_error(e, DB_ERROR)
+}
+
+/// Creates the response for a plan that the session hasn't saved.
+fn not_found() -> Response {
+    ApiErrorType::from((StatusCode::NOT_FOUND, "Course plan not found", None)).into_response()
+}
+
+/// Whether a term code names a quarter (e.g., `FA24` or `S124`).
+fn is_term_code(term: &str) -> bool {
+    matches!(
+        term.get(..2),
+        Some("FA" | "WI" | "SP" | "S1" | "S2" | "S3" | "SU")
+    ) && term.len() == 4
+        && term[2..].chars().all(|c| c.is_ascii_digit())
+}
+
+impl Validate for BodyCoursePlan {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        require(&mut errors, "name", &self.name);
+        if self.quarters.len() > MAX_PLAN_QUARTERS {
+            errors.push(FieldError::new(
+                "quarters",
+                format!("can list at most {MAX_PLAN_QUARTERS} quarters"),
+            ));
+        }
+
+        let mut terms = HashSet::new();
+        for (i, quarter) in self.quarters.iter().enumerate() {
+            let term = quarter.term.trim().to_uppercase();
+            if !is_term_code(&term) {
+                errors.push(FieldError::new(
+                    format!("quarters[{i}].term"),
+                    "must be a term code (e.g., FA24)",
+                ));
+            } else if !terms.insert(term) {
+                errors.push(FieldError::new(
+                    format!("quarters[{i}].term"),
+                    "is listed more than once",
+                ));
+            }
+
+            if quarter.courses.len() > MAX_QUARTER_COURSES {
+                errors.push(FieldError::new(
+                    format!("quarters[{i}].courses"),
+                    format!("can list at most {MAX_QUARTER_COURSES} courses"),
+                ));
+            }
+            for (j, course) in quarter.courses.iter().enumerate() {
+                if course.split_whitespace().count() != 2 {
+                    errors.push(FieldError::new(
+                        format!("quarters[{i}].courses[{j}]"),
+                        "must be a course code (e.g., CSE 100)",
+                    ));
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// Normalizes the quarters of a plan's body, keeping them in the order they were given.
+pub(super) fn quarters_from_body(body: &BodyCoursePlan) -> Vec<PlannedQuarter> {
+    body.quarters
+        .iter()
+        .map(|q| PlannedQuarter {
+            term: q.term.trim().to_uppercase(),
+            courses: q.courses.iter().map(|c| normalize_course_code(c)).collect(),
+        })
+        .collect()
+}
+
+/// Checks a plan against its courses' prerequisites and, if the caller may see it, their
+/// degree audit. Either check is skipped if what it needs can't be fetched.
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `plan`: The plan.
+/// - `extensions`: The request's extensions, which the auth middleware attaches the
+///   caller's key prefix and scopes to.
+///
+/// # Returns
+/// The plan, along with what was checked and the problems found.
+async fn checked_plan(s: &Arc<WrapperState>, plan: CoursePlan, extensions: &Extensions) -> Value {
+    let key_prefix = extensions.get::<String>().map(String::as_str);
+    // The audit is only used if the caller could have fetched it themselves
+    #[cfg(feature = "auth")]
+    let audit_allowed = extensions
+        .get::<KeyScopes>()
+        .is_some_and(|scopes| scopes.allows(ApiScope::DegreeAudit));
+    #[cfg(not(feature = "auth"))]
+    let audit_allowed = true;
+
+    let courses: HashSet<String> = plan
+        .quarters
+        .iter()
+        .flat_map(|q| q.courses.iter().cloned())
+        .collect();
+    let (prerequisites, prerequisites_checked) = if courses.is_empty() {
+        (HashMap::new(), true)
+    } else {
+        fetch_prerequisites(s, courses).await
+    };
+
+    let completed = if audit_allowed {
+        completed_courses(s, key_prefix)
+            .await
+            .inspect_err(|e| {
+                warn!(
+                    "Course plan {} wasn't checked against the degree audit: {e}",
+                    plan.plan_id
+                )
+            })
+            .ok()
+    } else {
+        None
+    };
+
+    let issues = check_course_plan(
+        &plan.quarters,
+        completed.as_ref().unwrap_or(&HashSet::new()),
+        &prerequisites,
+    );
+    json!({
+        "plan": plan,
+        "prerequisites_checked": prerequisites_checked,
+        "audit_checked": completed.is_some(),
+        "issues": issues,
+    })
+}
+
+/// GET /plans/:plan_id
+/// Returns one of the session's course plans, checked against its courses' prerequisites
+/// and the student's degree audit
+pub async fn get_course_plan(
+    headers: HeaderMap,
+    Path(plan_id): Path<i64>,
+    State(s): State<Arc<WrapperState>>,
+    extensions: Extensions,
+) -> Response {
+    info!("GET /plans/{}", plan_id);
+
+    let Some(token) = owned_session_token(&headers, &s, &extensions) else {
+        return no_session(NO_SESSION);
+    };
+
+    match s.schedule_db.get_course_plan(token, plan_id) {
+        Ok(Some(plan)) => {
+            let body = checked_plan(&s, plan, &extensions).await;
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Ok(None) => not_found(),
+        Err(e) => course_plan_error(e),
+    }
+}
+
+/// POST /plans
+/// Saves a new course plan for the session, and returns it checked against its courses'
+/// prerequisites and the student's degree audit. A plan with problems is still saved
+pub async fn post_course_plan(
+    headers: HeaderMap,
+    State(s): State<Arc<WrapperState>>,
+    extensions: Extensions,
+    ValidJson(body): ValidJson<BodyCoursePlan>,
+) -> Response {
+    info!("POST /plans");
+
+    let Some(token) = owned_session_token(&headers, &s, &extensions) else {
+        return no_session(NO_SESSION);
+    };
+
+    match s.schedule_db.count_course_plans(token) {
+        Ok(count) if count >= MAX_COURSE_PLANS => {
+            return ApiErrorType::from((
+                StatusCode::TOO_MANY_REQUESTS,
+                "This session has saved too many course plans.",
+                None,
+            ))
+            .into_response();
+        }
+        Ok(_) => {}
+        Err(e) => return course_plan_error(e),
+    }
+
+    let quarters = quarters_from_body(&body);
+    let plan = match s
+        .schedule_db
+        .insert_course_plan(token, body.name.trim(), &quarters)
+        .and_then(|plan_id| s.schedule_db.get_course_plan(token, plan_id))
+    {
+        Ok(Some(plan)) => plan,
+        Ok(None) => return not_found(),
+        Err(e) => return course_plan_error(e),
+    };
+
+    let body = checked_plan(&s, plan, &extensions).await;
+    (StatusCode::CREATED, Json(body)).into_response()
+}
+
+/// PUT /plans/:plan_id
+/// Replaces the name and quarters of one of the session's course plans, and returns it
+/// checked against its courses' prerequisites and the student's degree audit
+pub async fn put_course_plan(
+    headers: HeaderMap,
+    Path(plan_id): Path<i64>,
+    State(s): State<Arc<WrapperState>>,
+    extensions: Extensions,
+    ValidJson(body): ValidJson<BodyCoursePlan>,
+) -> Response {
+    info!("PUT /plans/{}", plan_id);
+
+    let Some(token) = owned_session_token(&headers, &s, &extensions) else {
+        return no_session(NO_SESSION);
+    };
+
+    let quarters = quarters_from_body(&body);
+    let plan = match s
+        .schedule_db
+        .update_course_plan(token, plan_id, body.name.trim(), &quarters)
+    {
+        Ok(false) => return not_found(),
+        Ok(true) => match s.schedule_db.get_course_plan(token, plan_id) {
+            Ok(Some(plan)) => plan,
+            Ok(None) => return not_found(),
+            Err(e) => return course_plan_error(e),
+        },
+        Err(e) => return course_plan_error(e),
+    };
+
+    let body = checked_plan(&s, plan, &extensions).await;
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+    use axum::http::HeaderValue;
+    use std::time::Duration;
+    use webweg::wrapper::WebRegWrapper;
+
+    use crate::server::types::BodyPlannedQuarter;
+    use crate::sessions::SESSION_TOKEN_HEADER;
+    use crate::types::tests::state;
+
+    /// Creates a state whose requests to WebReg time out right away, so that a plan is
+    /// never checked against anything fetched.
+    fn plan_state(name: &str) -> Arc<WrapperState> {
+        let mut s = state(name, &["FA24"], json!({ "webregRetry": { "attempts": 1 } }));
+        s.c_wrapper = WebRegWrapper::builder()
+            .with_cookies("")
+            .with_default_timeout(Duration::from_millis(1))
+            .should_close_after_request(true)
+            .try_build_wrapper()
+            .unwrap();
+        Arc::new(s)
+    }
+
+    fn session(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(SESSION_TOKEN_HEADER, HeaderValue::from_str(token).unwrap());
+        headers
+    }
+
+    fn plan(name: &str, quarters: &[(&str, &[&str])]) -> BodyCoursePlan {
+        BodyCoursePlan {
+            name: name.to_owned(),
+            quarters: quarters
+                .iter()
+                .map(|(term, courses)| BodyPlannedQuarter {
+                    term: term.to_string(),
+                    courses: courses.iter().map(|c| c.to_string()).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    fn fields(body: &BodyCoursePlan) -> Vec<String> {
+        body.validate().into_iter().map(|e| e.field).collect()
+    }
+
+    async fn post(s: &Arc<WrapperState>, token: &str, body: BodyCoursePlan) -> Response {
+        post_course_plan(
+            session(token),
+            State(s.clone()),
+            Extensions::new(),
+            ValidJson(body),
+        )
+        .await
+    }
+
+    #[test]
+    fn test_validate_course_plan() {
+        assert!(fields(&plan(
+            "Four years",
+            &[(" fa24 ", &["cse 100", "MATH  20C"]), ("S125", &[])]
+        ))
+        .is_empty());
+
+        assert_eq!(vec!["name"], fields(&plan("  ", &[])));
+        assert_eq!(
+            vec!["quarters[0].term", "quarters[1].term"],
+            fields(&plan("Plan", &[("FALL24", &[]), ("FA2", &[])]))
+        );
+        // Terms are compared after they're normalized
+        assert_eq!(
+            vec!["quarters[1].term"],
+            fields(&plan("Plan", &[("FA24", &[]), ("fa24", &[])]))
+        );
+        assert_eq!(
+            vec!["quarters[0].courses[0]", "quarters[0].courses[2]"],
+            fields(&plan(
+                "Plan",
+                &[("FA24", &["CSE100", "CSE 101", "CSE 1 2"])]
+            ))
+        );
+
+        let courses = vec!["CSE 100"; MAX_QUARTER_COURSES + 1];
+        assert_eq!(
+            vec!["quarters[0].courses"],
+            fields(&plan("Plan", &[("FA24", &courses)]))
+        );
+        let quarters = vec![("FA24", &[][..]); MAX_PLAN_QUARTERS + 1];
+        assert!(fields(&plan("Plan", &quarters)).contains(&"quarters".to_owned()));
+    }
+
+    #[test]
+    fn test_quarters_from_body() {
+        assert_eq!(
+            vec![
+                PlannedQuarter {
+                    term: "WI25".to_owned(),
+                    courses: vec!["CSE 100".to_owned(), "MATH 20C".to_owned()],
+                },
+                PlannedQuarter {
+                    term: "FA24".to_owned(),
+                    courses: vec![],
+                },
+            ],
+            quarters_from_body(&plan(
+                "Plan",
+                &[(" wi25 ", &[" cse 100", "math  20c"]), ("FA24", &[])]
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_course_plan() {
+        let s = plan_state("course-plan-create");
+        let token = s.sessions.register("jlinksessionidx=abc", None);
+
+        let res = post(
+            &s,
+            &token,
+            plan(" Four years ", &[("fa24", &[]), ("WI25", &[])]),
+        )
+        .await;
+        assert_eq!(StatusCode::CREATED, res.status());
+        let body: Value =
+            serde_json::from_slice(&to_bytes(res.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!("Four years", body["plan"]["name"]);
+        assert_eq!(
+            json!([
+                { "term": "FA24", "courses": [] },
+                { "term": "WI25", "courses": [] },
+            ]),
+            body["plan"]["quarters"]
+        );
+        // There's nothing to look up prerequisites for, and no audit to check against
+        assert_eq!(true, body["prerequisites_checked"]);
+        assert_eq!(false, body["audit_checked"]);
+        assert_eq!(json!([]), body["issues"]);
+
+        let plans = s.schedule_db.get_course_plans(&token).unwrap();
+        assert_eq!(1, plans.len());
+        assert_eq!(body["plan"]["plan_id"], plans[0].plan_id);
+
+        // A session can't have two plans with the same name
+        let res = post(&s, &token, plan("Four years", &[])).await;
+        assert_eq!(StatusCode::CONFLICT, res.status());
+        assert_eq!(1, s.schedule_db.get_course_plans(&token).unwrap().len());
+
+        // A session has to have been registered for a plan to be saved for it
+        let res = post(&s, "unknown", plan("Four years", &[])).await;
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+    }
+}
@@ -0,0 +1,177 @@
+//! Endpoints for the requirement sets that users define for themselves (e.g., a pre-med
+//! checklist or a certificate), in the same shape as the subrequirements of the official
+//! requirements (see [`crate::degree_audit::config`]). The student's progress towards each
+//! set is included in `/degree_audit/progress`.
+//!
+//! Sets are kept per API key. The user in the path is `me` or the prefix of the caller's own
+//! API key; no one can see or change another user's sets.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use serde_json::json;
+use tracing::info;
+
+use crate::server::endpoints::db_error;
+use crate::server::endpoints::user_bundles::check_user;
+use crate::server::types::{ApiErrorType, BodyCustomRequirements, FieldError};
+use crate::server::validation::{require, ValidJson, Validate};
+use crate::types::WrapperState;
+
+/// The largest number of requirement sets that a user can define.
+pub(super) const MAX_CUSTOM_REQUIREMENT_SETS: usize = 20;
+/// The largest number of subrequirements that a requirement set can have.
+const MAX_CUSTOM_SUBREQUIREMENTS: usize = 30;
+
+impl Validate for BodyCustomRequirements {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        require(&mut errors, "name", &self.name);
+        if self.subrequirements.is_empty()
+            || self.subrequirements.len() > MAX_CUSTOM_SUBREQUIREMENTS
+        {
+            errors.push(FieldError::new(
+                "subrequirements",
+                format!("must have between 1 and {MAX_CUSTOM_SUBREQUIREMENTS} subrequirements"),
+            ));
+        }
+
+        for (i, subreq) in self.subrequirements.iter().enumerate() {
+            let field = |name: &str| format!("subrequirements[{i}].{name}");
+            require(&mut errors, &field("title"), &subreq.title);
+            if !subreq.required_units.is_finite() || subreq.required_units <= 0.0 {
+                errors.push(FieldError::new(
+                    field("required_units"),
+                    "must be more than 0",
+                ));
+            }
+            if subreq.eligible_courses.is_empty() && subreq.departments.is_empty() {
+                errors.push(FieldError::new(
+                    field("eligible_courses"),
+                    "must be given if no departments are",
+                ));
+            }
+            for (j, level) in subreq.level_filters.iter().enumerate() {
+                if !matches!(level.to_ascii_lowercase().as_str(), "l" | "u" | "g") {
+                    errors.push(FieldError::new(
+                        field(&format!("level_filters[{j}]")),
+                        "must be l (lower-division), u (upper-division), or g (graduate)",
+                    ));
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// Why the caller can't use another user's requirement sets.
+const OTHER_USER: &str = "You can only manage your own custom requirements.";
+
+/// The message for a database error.
+const DB_ERROR: &str = "Failed to access custom requirements";
+
+/// Creates the response for a requirement set that the user hasn't defined.
+fn not_found() -> Response {
+    ApiErrorType::from((
+        StatusCode::NOT_FOUND,
+        "Custom requirement set not found",
+        None,
+    ))
+    .into_response()
+}
+
+/// GET /users/:id/custom_requirements
+/// Returns the user's requirement sets, in the order they were defined
+pub async fn get_custom_requirements(
+    Extension(prefix): Extension<String>,
+    Path(user): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /users/{}/custom_requirements", user);
+
+    if let Some(response) = check_user(&user, &prefix, OTHER_USER) {
+        return response;
+    }
+
+    match s.schedule_db.get_custom_requirement_sets(&prefix) {
+        Ok(sets) => (StatusCode::OK, Json(json!({ "requirements": sets }))).into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// POST /users/:id/custom_requirements
+/// Defines a requirement set, replacing the user's set with the same name if there is one,
+/// and returns it
+pub async fn post_custom_requirements(
+    Extension(prefix): Extension<String>,
+    Path(user): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyCustomRequirements>,
+) -> Response {
+    info!("POST /users/{}/custom_requirements", user);
+
+    if let Some(response) = check_user(&user, &prefix, OTHER_USER) {
+        return response;
+    }
+
+    let name = body.name.trim();
+    match s.schedule_db.get_custom_requirement_sets(&prefix) {
+        Ok(sets)
+            if sets.len() >= MAX_CUSTOM_REQUIREMENT_SETS
+                && !sets.iter().any(|r| r.name == name) =>
+        {
+            return ApiErrorType::from((
+                StatusCode::TOO_MANY_REQUESTS,
+                "You have defined too many custom requirement sets.",
+                None,
+            ))
+            .into_response();
+        }
+        Ok(_) => {}
+        Err(e) => return db_error(e, DB_ERROR),
+    }
+
+    let saved = s
+        .schedule_db
+        .upsert_custom_requirement_set(&prefix, name, &body.subrequirements)
+        .and_then(|requirement_id| {
+            let sets = s.schedule_db.get_custom_requirement_sets(&prefix)?;
+            Ok(sets
+                .into_iter()
+                .find(|r| r.requirement_id == requirement_id))
+        });
+    match saved {
+        Ok(Some(set)) => (StatusCode::CREATED, Json(set)).into_response(),
+        Ok(None) => not_found(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// DELETE /users/:id/custom_requirements/:requirement_id
+/// Removes one of the user's requirement sets
+pub async fn delete_custom_requirements(
+    Extension(prefix): Extension<String>,
+    Path((user, requirement_id)): Path<(String, i64)>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!(
+        "DELETE /users/{}/custom_requirements/{}",
+        user, requirement_id
+    );
+
+    if let Some(response) = check_user(&user, &prefix, OTHER_USER) {
+        return response;
+    }
+
+    match s
+        .schedule_db
+        .delete_custom_requirement_set(&prefix, requirement_id)
+    {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => not_found(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
@@ -4,87 +4,393 @@
 //! progress tracking, and course recommendations.
 
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    async_trait,
+    extract::{FromRequestParts, Path, Query, State},
+    http::{header, request::Parts, StatusCode},
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
-use serde::Deserialize;
-use serde_json::json;
+use chrono::{Local, Timelike};
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{error, info, warn};
 
+use crate::audit_trail::{apply_overrides, current_overrides};
+use crate::cross_listings::CrossListings;
+use crate::db::normalize_course_code;
+use crate::degree_audit::anonymize::anonymize_audit;
+use crate::degree_audit::cache::SessionKey;
 use crate::degree_audit::{
-    self, DegreeAudit, DegreeAuditError, DegreeProgressProcessor,
+    self, build_graduation_plan, suggest_electives, AuditFetch, AuditOwner, CachePolicy,
+    CourseStatus, CustomRequirementProgress, DegreeAudit, DegreeAuditError,
+    DegreeProgressProcessor, EligibleCourse, NextCourseRecommendation, PlanInputs,
+    RequirementStatus,
 };
-use crate::server::types::ApiErrorType;
+use crate::error::WebregError;
+use crate::grades::summarize;
+use crate::offerings::term_sort_key;
+use crate::retry::Idempotency;
+use crate::server::types::{
+    ApiErrorType, AuditQueryParams, ElectiveQueryParams, GraduationPlanQueryParams,
+};
+use crate::sync::{record_changes, ChangeKind, AUDIT_SUMMARY_KEY};
 use crate::types::WrapperState;
 
-/// Query parameters for degree audit endpoints.
-#[derive(Debug, Deserialize)]
-pub struct AuditQueryParams {
-    /// If true, bypass cache and fetch fresh data
-    #[serde(default)]
-    pub refresh: bool,
+/// The number of units planned per quarter if none is given.
+const DEFAULT_UNITS_PER_TERM: f32 = 16.0;
+
+/// The most quarters that a graduation plan will cover.
+const MAX_PLAN_TERMS: usize = 12;
+
+/// The number of electives suggested if none is given.
+const DEFAULT_ELECTIVE_LIMIT: usize = 10;
+
+/// The most electives that will be suggested.
+const MAX_ELECTIVE_LIMIT: usize = 50;
+
+/// The number of prerequisite lookups that can be made to WebReg at once.
+const PREREQUISITE_CONCURRENCY: usize = 4;
+
+/// Every degree audit endpoint takes the audit's owner, which is worked out from the
+/// caller's API key (see [`crate::degree_audit::owner`]). In multi-user mode, callers
+/// without a cookie server of their own are turned away with a `403 Forbidden` before the
+/// handler runs.
+#[async_trait]
+impl FromRequestParts<Arc<WrapperState>> for AuditOwner {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<WrapperState>,
+    ) -> Result<Self, Self::Rejection> {
+        // The auth middleware attaches the prefix of every key that it lets through
+        let key_prefix = parts.extensions.get::<String>().map(String::as_str);
+        state.audit_owner(key_prefix).map_err(|e| {
+            warn!("Denied access to degree audits: {}", e);
+            audit_error_to_response(e)
+        })
+    }
+}
+
+/// Internal helper to get a degree audit, waiting for a fresh one if the cached audit has
+/// expired. The audit is anonymized if `anonymize` is set (see [`anonymize_audit`]).
+async fn get_audit_internal(
+    state: &Arc<WrapperState>,
+    owner: &AuditOwner,
+    force_refresh: bool,
+    anonymize: bool,
+) -> Result<DegreeAudit, DegreeAuditError> {
+    let mut audit =
+        get_audit_with_policy(state, owner, force_refresh, CachePolicy::FreshOnly, None)
+            .await?
+            .audit;
+    if anonymize {
+        anonymize_audit(&mut audit);
+    }
+    Ok(audit)
+}
+
+/// Gets the courses on an audit that were passed or transferred in, by normalized code.
+fn passed_courses(state: &WrapperState, audit: &DegreeAudit) -> HashSet<String> {
+    let scale = state.requirements().grade_scale;
+    audit
+        .requirements
+        .iter()
+        .flat_map(|r| &r.courses)
+        .filter(|c| match c.grade {
+            _ if matches!(c.status, CourseStatus::Transfer) => true,
+            Some(ref grade) => scale.is_passing_grade(grade),
+            None => false,
+        })
+        .map(|c| normalize_course_code(&c.course_code))
+        .collect()
+}
+
+/// Gets the courses that the student has completed, for checking course plans. An expired
+/// audit is used if that's all that's cached, while a fresh one is fetched in the
+/// background.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `key_prefix`: The prefix of the caller's API key, which decides whose audit is used.
+pub(super) async fn completed_courses(
+    state: &Arc<WrapperState>,
+    key_prefix: Option<&str>,
+) -> Result<HashSet<String>, String> {
+    let owner = state.audit_owner(key_prefix).map_err(|e| e.to_string())?;
+    let fetched = get_audit_with_policy(
+        state,
+        &owner,
+        false,
+        CachePolicy::StaleWhileRevalidate,
+        None,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(passed_courses(state, &fetched.audit))
 }
 
-/// Internal helper to get a degree audit.
+/// Computes the headline numbers of the student's degree progress, for the term overview.
+/// An expired audit is used if that's all that's cached, while a fresh one is fetched in the
+/// background.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `key_prefix`: The prefix of the caller's API key, which decides whose audit is used.
+pub(super) async fn progress_headline(
+    state: &Arc<WrapperState>,
+    key_prefix: Option<&str>,
+) -> Result<Value, WebregError> {
+    let owner = state.audit_owner(key_prefix)?;
+    let fetched = get_audit_with_policy(
+        state,
+        &owner,
+        false,
+        CachePolicy::StaleWhileRevalidate,
+        None,
+    )
+    .await?;
+    let progress = progress_processor(state)
+        .compute_degree_progress(&fetched.audit)
+        .map_err(|e| WebregError::internal("Failed to compute degree progress", e))?;
+
+    let percent_complete = if progress.total_units_required > 0.0 {
+        (progress.total_units_completed / progress.total_units_required * 100.0).min(100.0)
+    } else {
+        100.0
+    };
+    Ok(json!({
+        "audit_id": progress.audit_id,
+        "stale": fetched.stale,
+        "total_units_required": progress.total_units_required,
+        "total_units_completed": progress.total_units_completed,
+        "total_units_remaining": progress.total_units_remaining,
+        "percent_complete": percent_complete,
+        "upper_division": progress.upper_division,
+        "residency": progress.residency,
+        "requirements_remaining": progress
+            .requirements_summary
+            .iter()
+            .filter(|r| !matches!(r.status, RequirementStatus::Complete))
+            .count(),
+    }))
+}
+
+/// Internal helper to get a degree audit, using the cache according to `policy`, with the
+/// owner's requirement overrides applied to it. Audits are cached as DARS returned them,
+/// so that changing an override takes effect immediately. A new audit is waited for for up
+/// to `timeout`, clamped to the configured maximum.
+async fn get_audit_with_policy(
+    state: &Arc<WrapperState>,
+    owner: &AuditOwner,
+    force_refresh: bool,
+    policy: CachePolicy,
+    timeout: Option<Duration>,
+) -> Result<AuditFetch, DegreeAuditError> {
+    let timeout = state.degree_audit_client.poll_timeout(timeout);
+    let mut fetched = fetch_audit_with_policy(state, owner, force_refresh, policy, timeout).await?;
+    let overrides = current_overrides(&state.schedule_db, owner.user());
+    apply_overrides(&mut fetched.audit, &overrides);
+    Ok(fetched)
+}
+
+/// Gets the owner's degree audit as DARS returned it, using the cache according to `policy`.
 ///
 /// Uses the Puppeteer-based `/degree_audit` endpoint on webregautoin server,
 /// which handles all browser navigation, authentication, and HTML scraping.
 /// This is more reliable than extracting cookies and making HTTP requests.
-async fn get_audit_internal(
+async fn fetch_audit_with_policy(
+    state: &Arc<WrapperState>,
+    owner: &AuditOwner,
+    force_refresh: bool,
+    policy: CachePolicy,
+    timeout: Duration,
+) -> Result<AuditFetch, DegreeAuditError> {
+    let key = owner.session_key();
+    let cache = &state.degree_audit_cache_state.cache;
+    if !force_refresh {
+        match cache.get_allowing_stale(&key) {
+            Some((audit, false)) => {
+                return Ok(AuditFetch {
+                    audit,
+                    stale: false,
+                })
+            }
+            Some((audit, true)) if policy == CachePolicy::StaleWhileRevalidate => {
+                info!("Returning stale degree audit and refreshing in the background");
+                tokio::spawn(refresh_audit(state.clone(), owner.clone()));
+                return Ok(AuditFetch { audit, stale: true });
+            }
+            _ => {}
+        }
+    }
+
+    let lock = state.degree_audit_cache_state.get_session_lock(&key);
+    let _guard = lock.lock().await;
+
+    // Another request may have fetched the audit while we were waiting
+    if !force_refresh {
+        if let Some(audit) = cache.get(&key) {
+            return Ok(AuditFetch {
+                audit,
+                stale: false,
+            });
+        }
+    } else {
+        // The cached audit is out of date as far as the user is concerned, so it shouldn't
+        // be served to anyone else either, even if the refresh fails
+        cache.invalidate(&key);
+    }
+
+    fetch_autoin_audit(state, owner, force_refresh, timeout)
+        .await
+        .map(|audit| AuditFetch {
+            audit,
+            stale: false,
+        })
+}
+
+/// Refreshes the cached audit in the background, unless it's already being fetched.
+async fn refresh_audit(state: Arc<WrapperState>, owner: AuditOwner) {
+    let lock = state
+        .degree_audit_cache_state
+        .get_session_lock(&owner.session_key());
+    let Ok(_guard) = lock.try_lock() else {
+        return;
+    };
+
+    let timeout = state.degree_audit_client.poll_timeout(None);
+    if let Err(e) = fetch_autoin_audit(&state, &owner, false, timeout).await {
+        warn!("Failed to refresh stale degree audit: {}", e);
+    }
+}
+
+/// Fetches the owner's degree audit through their webregautoin server and caches it. If
+/// `fresh` is set, DARS runs a new audit rather than returning its most recent one. The
+/// caller must hold the session's lock.
+async fn fetch_autoin_audit(
     state: &Arc<WrapperState>,
-    _force_refresh: bool, // Note: refresh not yet implemented for Puppeteer path
+    owner: &AuditOwner,
+    fresh: bool,
+    timeout: Duration,
 ) -> Result<DegreeAudit, DegreeAuditError> {
+    let key = owner.session_key();
+
+    // Every audit run counts against the daily quota
+    state.degree_audit_cache_state.quota.try_consume(&key)?;
+
     // Use the Puppeteer-based approach which handles authentication internally
-    degree_audit::get_degree_audit(state)
-        .await
-        .map_err(|e| DegreeAuditError::Network {
+    let start = Instant::now();
+    let start_hour = Local::now().hour();
+    let result =
+        degree_audit::fetch_degree_audit_from(state, owner.cookie_server(), fresh, timeout).await;
+    let raw_audit = result.map_err(|e| match e.downcast_ref::<reqwest::Error>() {
+        Some(e) if e.is_timeout() => DegreeAuditError::PollTimeout {
+            attempts: 1,
+            elapsed_secs: start.elapsed().as_secs_f64(),
+        },
+        _ => DegreeAuditError::Network {
             message: e.to_string(),
+        },
+    })?;
+
+    let cache_state = &state.degree_audit_cache_state;
+    cache_state
+        .wait_estimator
+        .record(start_hour, start.elapsed());
+    cache_state.archive_raw(&key, &raw_audit);
+    let audit = degree_audit::parse_degree_audit_html(&raw_audit).map_err(|e| {
+        DegreeAuditError::ParseError {
+            message: e.to_string(),
+        }
+    })?;
+
+    cache_state.record_parse_warnings(&audit);
+    cache_state.cache.insert(key, audit.clone());
+    record_changes(
+        &state.schedule_db,
+        ChangeKind::Audit,
+        &[AUDIT_SUMMARY_KEY.to_owned()],
+    );
+    Ok(audit)
+}
+
+/// Creates the processor that computes degree progress, which treats the listings of
+/// cross-listed courses as the same course.
+fn progress_processor(state: &WrapperState) -> DegreeProgressProcessor {
+    let cross_listings = state
+        .schedule_db
+        .get_cross_listings(None)
+        .unwrap_or_else(|e| {
+            warn!("Failed to fetch cross-listed courses: {}", e);
+            CrossListings::default()
+        });
+    DegreeProgressProcessor::new(state.requirements()).with_cross_listings(cross_listings)
+}
+
+/// Computes the progress towards each requirement set that a user defined. The sets are
+/// left out if they can't be fetched.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+/// - `processor`: The processor that computes progress.
+/// - `audit`: The student's degree audit.
+/// - `key_prefix`: The prefix of the user's API key.
+///
+/// # Returns
+/// The progress towards each set, in the order they were defined.
+fn custom_progress(
+    state: &WrapperState,
+    processor: &DegreeProgressProcessor,
+    audit: &DegreeAudit,
+    key_prefix: &str,
+) -> Vec<CustomRequirementProgress> {
+    let sets = state
+        .schedule_db
+        .get_custom_requirement_sets(key_prefix)
+        .unwrap_or_else(|e| {
+            warn!("Failed to fetch custom requirements: {}", e);
+            vec![]
+        });
+    sets.iter()
+        .map(|set| {
+            processor.compute_custom_progress(
+                audit,
+                set.requirement_id,
+                &set.name,
+                &set.subrequirements,
+            )
         })
+        .collect()
 }
 
 /// Converts DegreeAuditError to API response.
 fn audit_error_to_response(error: DegreeAuditError) -> Response {
-    let (status, message) = match &error {
-        DegreeAuditError::SessionExpired { .. } => (
-            StatusCode::UNAUTHORIZED,
-            "Session expired - please re-authenticate",
-        ),
-        DegreeAuditError::NoSession { .. } => (
-            StatusCode::UNAUTHORIZED,
-            "No active session",
-        ),
-        DegreeAuditError::CircuitBreakerOpen => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            "Service temporarily unavailable due to repeated failures",
-        ),
-        DegreeAuditError::PollTimeout { .. } => (
-            StatusCode::GATEWAY_TIMEOUT,
-            "Audit generation timed out",
-        ),
-        DegreeAuditError::CookieFetchError { .. } => (
-            StatusCode::BAD_GATEWAY,
-            "Failed to fetch authentication cookies",
-        ),
-        _ => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to fetch degree audit",
-        ),
-    };
-
-    ApiErrorType::from((status, message, Some(error.to_string()))).into_response()
+    WebregError::from(error).into_response()
 }
 
 /// GET /degree_audit
 ///
-/// Fetches and returns the full parsed degree audit.
+/// Fetches and returns the full parsed degree audit. If the cached audit has expired, it's
+/// returned right away with `stale: true` while a fresh one is fetched in the background.
 ///
 /// Query parameters:
 /// - `refresh` (optional): Set to `true` to bypass cache
+/// - `timeout` (optional): The most seconds to wait for a new audit to be generated. It's
+///   clamped to the configured `auditMaxPollSeconds`
+/// - `anonymize` (optional): Set to `true` to leave out the student's name and PID, and
+///   hash the audit's ID, so that the audit can be shared
+/// - `async` (optional): Set to `true` to have a new audit generated in the background
+///   rather than waiting for it. If there's no fresh audit to return (or `refresh` is set),
+///   a `202 Accepted` is returned with a handle for `/degree_audit/jobs/:handle` and how
+///   long the audit is expected to take, in `estimated_wait_seconds`
 pub async fn get_audit(
     State(s): State<Arc<WrapperState>>,
+    owner: AuditOwner,
     Query(params): Query<AuditQueryParams>,
 ) -> Response {
     info!(
@@ -92,8 +398,53 @@ pub async fn get_audit(
         params.refresh
     );
 
-    match get_audit_internal(&s, params.refresh).await {
-        Ok(audit) => (StatusCode::OK, Json(audit)).into_response(),
+    let cache_state = &s.degree_audit_cache_state;
+    let key = owner.session_key();
+    if params.run_async && (params.refresh || cache_state.cache.get(&key).is_none()) {
+        let estimate = cache_state.wait_estimator.estimate(Local::now().hour());
+        let handle = cache_state.pending.start(key, estimate);
+        let state = s.clone();
+        let task_handle = handle.clone();
+        tokio::spawn(async move {
+            let result =
+                get_audit_with_policy(&state, &owner, params.refresh, CachePolicy::FreshOnly, None)
+                    .await;
+            if let Err(e) = &result {
+                warn!("Failed to generate degree audit in the background: {}", e);
+            }
+            state
+                .degree_audit_cache_state
+                .pending
+                .finish(&task_handle, result.map(|_| ()).map_err(|e| e.to_string()));
+        });
+
+        return (
+            StatusCode::ACCEPTED,
+            Json(json!({
+                "handle": handle,
+                "status": "pending",
+                "estimated_wait_seconds": estimate.as_secs().max(1),
+            })),
+        )
+            .into_response();
+    }
+
+    let timeout = params.timeout.map(Duration::from_secs);
+    match get_audit_with_policy(
+        &s,
+        &owner,
+        params.refresh,
+        CachePolicy::StaleWhileRevalidate,
+        timeout,
+    )
+    .await
+    {
+        Ok(mut fetched) => {
+            if params.anonymize {
+                anonymize_audit(&mut fetched.audit);
+            }
+            (StatusCode::OK, Json(fetched)).into_response()
+        }
         Err(e) => {
             error!("Failed to fetch degree audit: {}", e);
             audit_error_to_response(e)
@@ -101,11 +452,51 @@ pub async fn get_audit(
     }
 }
 
+/// GET /degree_audit/jobs/:handle
+///
+/// Returns how an audit that's being generated in the background (see `/degree_audit`'s
+/// `async`) is going: `pending` along with `estimated_wait_seconds`, `completed` once it can
+/// be fetched with `/degree_audit`, or `failed` along with why. Only the session that asked
+/// for the audit can see it
+pub async fn get_audit_job(
+    State(s): State<Arc<WrapperState>>,
+    owner: AuditOwner,
+    Path(handle): Path<String>,
+) -> Response {
+    info!("GET /degree_audit/jobs/{}", handle);
+
+    match s
+        .degree_audit_cache_state
+        .pending
+        .get(&handle, &owner.session_key())
+    {
+        Some(job) => {
+            let mut body = json!(job);
+            body["handle"] = json!(handle);
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        None => ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "No degree audit is being generated with that handle",
+            None,
+        ))
+        .into_response(),
+    }
+}
+
 /// GET /degree_audit/progress
 ///
-/// Returns computed degree progress with recommendations.
+/// Returns computed degree progress with recommendations, along with the progress towards
+/// each requirement set that the caller defined (see `/users/:id/custom_requirements`).
+///
+/// Query parameters:
+/// - `refresh` (optional): Set to `true` to bypass cache
+/// - `anonymize` (optional): Set to `true` to leave out the student's name and PID, and
+///   hash the audit's ID, so that the progress can be shared
 pub async fn get_degree_progress(
     State(s): State<Arc<WrapperState>>,
+    owner: AuditOwner,
+    key_prefix: Option<Extension<String>>,
     Query(params): Query<AuditQueryParams>,
 ) -> Response {
     info!(
@@ -113,20 +504,21 @@ pub async fn get_degree_progress(
         params.refresh
     );
 
-    match get_audit_internal(&s, params.refresh).await {
+    match get_audit_internal(&s, &owner, params.refresh, params.anonymize).await {
         Ok(audit) => {
-            let processor = DegreeProgressProcessor::new(s.requirements_config.clone());
+            let processor = progress_processor(&s);
 
             match processor.compute_degree_progress(&audit) {
-                Ok(progress) => (StatusCode::OK, Json(progress)).into_response(),
+                Ok(mut progress) => {
+                    if let Some(Extension(prefix)) = key_prefix {
+                        progress.custom_requirements =
+                            custom_progress(&s, &processor, &audit, &prefix);
+                    }
+                    (StatusCode::OK, Json(progress)).into_response()
+                }
                 Err(e) => {
                     error!("Failed to compute degree progress: {}", e);
-                    ApiErrorType::from((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "Failed to compute degree progress",
-                        Some(e.to_string()),
-                    ))
-                    .into_response()
+                    WebregError::internal("Failed to compute degree progress", e).into_response()
                 }
             }
         }
@@ -137,11 +529,124 @@ pub async fn get_degree_progress(
     }
 }
 
+/// GET /degree_audit/report.pdf
+///
+/// Renders the degree audit, along with the progress computed from it, as a PDF that
+/// students can bring to advising appointments.
+///
+/// Query parameters:
+/// - `refresh` (optional): Set to `true` to bypass cache
+/// - `anonymize` (optional): Set to `true` to leave out the student's name and PID
+pub async fn get_audit_report(
+    State(s): State<Arc<WrapperState>>,
+    owner: AuditOwner,
+    Query(params): Query<AuditQueryParams>,
+) -> Response {
+    info!("GET /degree_audit/report.pdf (refresh={})", params.refresh);
+
+    let audit = match get_audit_internal(&s, &owner, params.refresh, params.anonymize).await {
+        Ok(audit) => audit,
+        Err(e) => {
+            error!("Failed to fetch degree audit for report: {}", e);
+            return audit_error_to_response(e);
+        }
+    };
+
+    let processor = progress_processor(&s);
+    let pdf = processor
+        .compute_degree_progress(&audit)
+        .map_err(|e| ("Failed to compute degree progress", e.to_string()))
+        .and_then(|progress| {
+            degree_audit::render_report(&audit, &progress)
+                .map_err(|e| ("Failed to render degree audit report", e.to_string()))
+        });
+
+    match pdf {
+        Ok(pdf) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "application/pdf".to_owned()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("inline; filename=\"degree-audit-{}.pdf\"", audit.audit_id),
+                ),
+            ],
+            pdf,
+        )
+            .into_response(),
+        Err((msg, e)) => {
+            error!("{}: {}", msg, e);
+            WebregError::internal(msg, e).into_response()
+        }
+    }
+}
+
+/// GET /degree_audit/export.xlsx
+///
+/// Exports the degree audit, along with the progress computed from it, as a spreadsheet:
+/// a summary sheet with the computed totals, a sheet of every course taken with its grade
+/// and term, and one sheet per requirement category.
+///
+/// Query parameters:
+/// - `refresh` (optional): Set to `true` to bypass cache
+/// - `anonymize` (optional): Set to `true` to leave out the student's name and PID
+pub async fn get_audit_export(
+    State(s): State<Arc<WrapperState>>,
+    owner: AuditOwner,
+    Query(params): Query<AuditQueryParams>,
+) -> Response {
+    info!("GET /degree_audit/export.xlsx (refresh={})", params.refresh);
+
+    let audit = match get_audit_internal(&s, &owner, params.refresh, params.anonymize).await {
+        Ok(audit) => audit,
+        Err(e) => {
+            error!("Failed to fetch degree audit for export: {}", e);
+            return audit_error_to_response(e);
+        }
+    };
+
+    let processor = progress_processor(&s);
+    let xlsx = processor
+        .compute_degree_progress(&audit)
+        .map_err(|e| ("Failed to compute degree progress", e.to_string()))
+        .and_then(|progress| {
+            degree_audit::render_workbook(&audit, &progress)
+                .map_err(|e| ("Failed to export degree audit", e.to_string()))
+        });
+
+    match xlsx {
+        Ok(xlsx) => (
+            StatusCode::OK,
+            [
+                (
+                    header::CONTENT_TYPE,
+                    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet".to_owned(),
+                ),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!(
+                        "attachment; filename=\"degree-audit-{}.xlsx\"",
+                        audit.audit_id
+                    ),
+                ),
+            ],
+            xlsx,
+        )
+            .into_response(),
+        Err((msg, e)) => {
+            error!("{}: {}", msg, e);
+            WebregError::internal(msg, e).into_response()
+        }
+    }
+}
+
 /// GET /degree_audit/completed_courses
 ///
-/// Returns all completed courses with passing grades (C- or higher).
+/// Returns all completed courses with passing grades (C- or higher), along with any
+/// transfer or AP/IB exam credit.
 pub async fn get_completed_courses(
     State(s): State<Arc<WrapperState>>,
+    owner: AuditOwner,
     Query(params): Query<AuditQueryParams>,
 ) -> Response {
     info!(
@@ -149,15 +654,18 @@ pub async fn get_completed_courses(
         params.refresh
     );
 
-    match get_audit_internal(&s, params.refresh).await {
+    match get_audit_internal(&s, &owner, params.refresh, false).await {
         Ok(audit) => {
+            let scale = s.requirements().grade_scale;
             let completed: Vec<_> = audit
                 .requirements
                 .iter()
                 .flat_map(|r| &r.courses)
                 .filter(|c| {
-                    if let Some(ref grade) = c.grade {
-                        crate::degree_audit::GradeValidator::is_passing_grade(grade)
+                    if matches!(c.status, CourseStatus::Transfer) {
+                        true
+                    } else if let Some(ref grade) = c.grade {
+                        scale.is_passing_grade(grade)
                     } else {
                         false
                     }
@@ -179,6 +687,7 @@ pub async fn get_completed_courses(
 pub async fn get_eligible_courses_for_subreq(
     Path(subreq_id): Path<String>,
     State(s): State<Arc<WrapperState>>,
+    owner: AuditOwner,
     Query(params): Query<AuditQueryParams>,
 ) -> Response {
     info!(
@@ -186,7 +695,7 @@ pub async fn get_eligible_courses_for_subreq(
         subreq_id, params.refresh
     );
 
-    match get_audit_internal(&s, params.refresh).await {
+    match get_audit_internal(&s, &owner, params.refresh, false).await {
         Ok(audit) => {
             // Find the subrequirement
             let subreq = audit
@@ -201,10 +710,13 @@ pub async fn get_eligible_courses_for_subreq(
                         "subrequirement_id": sr.id,
                         "title": sr.title,
                         "required_units": sr.required_units,
+                        "required_courses": sr.required_courses,
+                        "needs": sr.needs,
+                        "courses_needed": sr.courses_needed,
                         "units_completed": sr.units_completed,
                         "units_remaining": sr.units_remaining,
                         "status": sr.status,
-                        "eligible_courses": sr.eligible_courses,
+                        "eligible_courses": with_course_details(&s, &sr.eligible_courses),
                         "category_groups": sr.category_groups,
                     });
 
@@ -233,6 +745,7 @@ pub async fn get_eligible_courses_for_subreq(
 /// Returns summary of all requirements.
 pub async fn get_requirements_summary(
     State(s): State<Arc<WrapperState>>,
+    owner: AuditOwner,
     Query(params): Query<AuditQueryParams>,
 ) -> Response {
     info!(
@@ -240,7 +753,7 @@ pub async fn get_requirements_summary(
         params.refresh
     );
 
-    match get_audit_internal(&s, params.refresh).await {
+    match get_audit_internal(&s, &owner, params.refresh, false).await {
         Ok(audit) => {
             let summary: Vec<_> = audit
                 .requirements
@@ -271,6 +784,7 @@ pub async fn get_requirements_summary(
 /// Returns recommended next courses to take.
 pub async fn get_next_courses(
     State(s): State<Arc<WrapperState>>,
+    owner: AuditOwner,
     Query(params): Query<AuditQueryParams>,
 ) -> Response {
     info!(
@@ -278,22 +792,22 @@ pub async fn get_next_courses(
         params.refresh
     );
 
-    match get_audit_internal(&s, params.refresh).await {
+    match get_audit_internal(&s, &owner, params.refresh, false).await {
         Ok(audit) => {
-            let processor = DegreeProgressProcessor::new(s.requirements_config.clone());
+            let processor = progress_processor(&s);
 
             match processor.compute_degree_progress(&audit) {
                 Ok(progress) => {
-                    (StatusCode::OK, Json(progress.next_courses_to_take)).into_response()
+                    let recommendations: Vec<Value> = progress
+                        .next_courses_to_take
+                        .iter()
+                        .map(|rec| recommendation_with_details(&s, rec))
+                        .collect();
+                    (StatusCode::OK, Json(recommendations)).into_response()
                 }
                 Err(e) => {
                     error!("Failed to compute next courses: {}", e);
-                    ApiErrorType::from((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "Failed to compute next courses",
-                        Some(e.to_string()),
-                    ))
-                    .into_response()
+                    WebregError::internal("Failed to compute next courses", e).into_response()
                 }
             }
         }
@@ -306,15 +820,55 @@ pub async fn get_next_courses(
 
 /// GET /degree_audit/cache_stats
 ///
-/// Returns cache statistics for monitoring.
-pub async fn get_cache_stats(State(s): State<Arc<WrapperState>>) -> Response {
+/// Returns cache statistics, the number of audit blocks dropped by the parser, the size of
+/// the audit archive (if enabled), and today's audit quota usage for monitoring. In
+/// multi-user mode, only the caller's own session is listed in the quota usage.
+pub async fn get_cache_stats(State(s): State<Arc<WrapperState>>, owner: AuditOwner) -> Response {
     let stats = s.degree_audit_client.cache_stats();
+    let quota = &s.degree_audit_cache_state.quota;
+    let own_session = owner.session_key().to_string();
+    let mut usage = quota.usage_today();
+    if owner.user().is_some() {
+        usage.retain(|u| u.session == own_session);
+    }
+    let used = usage
+        .iter()
+        .find(|u| u.session == own_session)
+        .map_or(0, |u| u.used);
+    let archive = s
+        .degree_audit_cache_state
+        .archive
+        .as_ref()
+        .and_then(|a| a.stats().ok());
     (
         StatusCode::OK,
         Json(json!({
             "total_entries": stats.total_entries,
             "active_entries": stats.active_entries,
             "expired_entries": stats.expired_entries,
+            "dropped_blocks": s.degree_audit_cache_state.dropped_blocks.load(Ordering::Relaxed),
+            "archive": archive.map(|a| {
+                json!({
+                    "snapshots": a.snapshots,
+                    "blobs": a.blobs,
+                    "bytes": a.bytes,
+                })
+            }),
+            "quota": {
+                "daily_limit": quota.daily_limit(),
+                "used_today": used,
+                "remaining": quota.daily_limit().saturating_sub(used),
+                "sessions": usage
+                    .iter()
+                    .map(|u| {
+                        json!({
+                            "session": u.session,
+                            "used": u.used,
+                            "remaining": u.remaining,
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+            },
         })),
     )
         .into_response()
@@ -322,12 +876,362 @@ pub async fn get_cache_stats(State(s): State<Arc<WrapperState>>) -> Response {
 
 /// POST /degree_audit/invalidate_cache
 ///
-/// Invalidates the degree audit cache.
-pub async fn invalidate_cache(State(s): State<Arc<WrapperState>>) -> Response {
+/// Invalidates the degree audit cache. In multi-user mode, only the caller's own audit is
+/// invalidated.
+pub async fn invalidate_cache(State(s): State<Arc<WrapperState>>, owner: AuditOwner) -> Response {
     info!("POST /degree_audit/invalidate_cache");
 
-    // Clear all cache entries
-    s.degree_audit_cache_state.cache.clear();
+    let cache = &s.degree_audit_cache_state.cache;
+    match owner.user() {
+        Some(_) => cache.invalidate(&owner.session_key()),
+        None => cache.clear(),
+    }
 
     (StatusCode::OK, Json(json!({ "message": "Cache invalidated" }))).into_response()
 }
+
+/// GET /degree_audit/graduation_plan
+///
+/// Returns a quarter-by-quarter plan for the courses still needed to graduate. The plan
+/// respects prerequisites (looked up from WebReg), the per-quarter unit cap, and the
+/// quarters that each course has historically been offered in.
+///
+/// Query parameters:
+/// - `units_per_term` (optional): Maximum units per quarter, defaulting to 16
+/// - `start_term` (optional): The first quarter to plan (e.g., `FA24`)
+/// - `refresh` (optional): Set to `true` to bypass cache
+/// - `anonymize` (optional): Set to `true` to hash the audit's ID
+pub async fn get_graduation_plan(
+    State(s): State<Arc<WrapperState>>,
+    owner: AuditOwner,
+    Query(params): Query<GraduationPlanQueryParams>,
+) -> Response {
+    info!(
+        "GET /degree_audit/graduation_plan (units_per_term={:?}, refresh={})",
+        params.units_per_term, params.refresh
+    );
+
+    let units_per_term = params.units_per_term.unwrap_or(DEFAULT_UNITS_PER_TERM);
+    if !(units_per_term > 0.0 && units_per_term <= 30.0) {
+        return ApiErrorType::from((
+            StatusCode::BAD_REQUEST,
+            "units_per_term must be between 0 and 30",
+            None,
+        ))
+        .into_response();
+    }
+
+    let start_term = match params.start_term.map(|t| t.to_uppercase()).or_else(|| {
+        s.terms()
+            .iter()
+            .map(|t| t.term.clone())
+            .filter(|t| matches!(t.get(..2), Some("FA" | "WI" | "SP")))
+            .max_by_key(|t| term_sort_key(t))
+    }) {
+        Some(t) => t,
+        None => {
+            return ApiErrorType::from((
+                StatusCode::BAD_REQUEST,
+                "No start_term given, and no regular quarter is configured",
+                None,
+            ))
+            .into_response();
+        }
+    };
+
+    let audit = match get_audit_internal(&s, &owner, params.refresh, params.anonymize).await {
+        Ok(audit) => audit,
+        Err(e) => {
+            error!("Failed to fetch degree audit for graduation plan: {}", e);
+            return audit_error_to_response(e);
+        }
+    };
+
+    let processor = progress_processor(&s);
+    let progress = match processor.compute_degree_progress(&audit) {
+        Ok(progress) => progress,
+        Err(e) => {
+            error!("Failed to compute degree progress: {}", e);
+            return WebregError::internal("Failed to compute degree progress", e).into_response();
+        }
+    };
+
+    let completed = passed_courses(&s, &audit);
+
+    let candidates: HashSet<String> = progress
+        .next_courses_to_take
+        .iter()
+        .flat_map(|r| &r.eligible_courses)
+        .map(|c| c.full_code.clone())
+        .filter(|c| !completed.contains(c))
+        .collect();
+
+    let (prerequisites, prerequisites_checked) = fetch_prerequisites(&s, candidates).await;
+    let offerings = match s.schedule_db.get_offering_terms() {
+        Ok(offerings) => offerings,
+        Err(e) => {
+            warn!("Failed to load course offering history: {}", e);
+            HashMap::new()
+        }
+    };
+
+    let plan = build_graduation_plan(
+        &progress.next_courses_to_take,
+        &PlanInputs {
+            completed,
+            prerequisites,
+            offerings,
+            units_per_term,
+            start_term,
+            max_terms: MAX_PLAN_TERMS,
+        },
+    );
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "audit_id": audit.audit_id,
+            "units_per_term": units_per_term,
+            "prerequisites_checked": prerequisites_checked,
+            "plan": plan,
+        })),
+    )
+        .into_response()
+}
+
+/// Adds each course's title, description, and units from the catalog (see
+/// [`crate::scraper::catalog`]), along with its average GPA and instructor rating (see
+/// [`crate::grades`]), so that students can compare the courses they could take. Details
+/// that aren't known are `null`.
+fn with_course_details(s: &WrapperState, courses: &[EligibleCourse]) -> Vec<Value> {
+    let codes: Vec<String> = courses.iter().map(|c| c.full_code.clone()).collect();
+    let catalog = s
+        .schedule_db
+        .get_catalog_courses_for(&codes)
+        .unwrap_or_else(|e| {
+            warn!("Failed to load catalog courses: {}", e);
+            HashMap::new()
+        });
+    let records = s
+        .schedule_db
+        .get_grade_records_for(&codes)
+        .unwrap_or_else(|e| {
+            warn!("Failed to load grade distributions: {}", e);
+            HashMap::new()
+        });
+
+    courses
+        .iter()
+        .map(|c| {
+            let code = normalize_course_code(&c.full_code);
+            let summary = records.get(&code).and_then(|r| summarize(r));
+            let catalog = catalog.get(&code);
+            json!({
+                "department": c.department,
+                "course_number": c.course_number,
+                "full_code": c.full_code,
+                "title": catalog.map(|c| &c.title),
+                "description": catalog.map(|c| &c.description),
+                "min_units": catalog.and_then(|c| c.min_units),
+                "max_units": catalog.and_then(|c| c.max_units),
+                "avg_gpa": summary.as_ref().and_then(|s| s.avg_gpa),
+                "instructor_rating": summary.as_ref().and_then(|s| s.instructor_rating),
+            })
+        })
+        .collect()
+}
+
+/// A recommendation, with its eligible courses' details added (see
+/// [`with_course_details`]).
+fn recommendation_with_details(s: &WrapperState, rec: &NextCourseRecommendation) -> Value {
+    json!({
+        "subrequirement_title": rec.subrequirement_title,
+        "priority": rec.priority,
+        "eligible_courses": with_course_details(s, &rec.eligible_courses),
+        "units_needed": rec.units_needed,
+    })
+}
+
+/// GET /degree_audit/elective_suggestions
+///
+/// Suggests electives from the courses that registered students with similar completed
+/// courses have taken, or, if there aren't enough of them, from the departments of the
+/// student's completed upper-division courses. Other students' audits are only used if
+/// they're already cached, and only courses taken by several of them are suggested.
+pub async fn get_elective_suggestions(
+    State(s): State<Arc<WrapperState>>,
+    owner: AuditOwner,
+    Query(params): Query<ElectiveQueryParams>,
+) -> Response {
+    info!(
+        "GET /degree_audit/elective_suggestions (refresh={})",
+        params.refresh
+    );
+
+    let audit = match get_audit_internal(&s, &owner, params.refresh, false).await {
+        Ok(audit) => audit,
+        Err(e) => {
+            error!(
+                "Failed to fetch degree audit for elective suggestions: {}",
+                e
+            );
+            return audit_error_to_response(e);
+        }
+    };
+
+    let cache = &s.degree_audit_cache_state.cache;
+    let others: Vec<DegreeAudit> = s
+        .sessions
+        .all_cookies()
+        .iter()
+        .filter_map(|cookies| cache.get_allowing_stale(&SessionKey::from_cookie(cookies)))
+        .map(|(audit, _)| audit)
+        .collect();
+    let catalog = s.schedule_db.get_catalog_courses().unwrap_or_else(|e| {
+        warn!("Failed to load catalog courses: {}", e);
+        vec![]
+    });
+
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_ELECTIVE_LIMIT)
+        .clamp(1, MAX_ELECTIVE_LIMIT);
+    let suggestions = suggest_electives(
+        &audit,
+        &others,
+        &catalog,
+        limit,
+        &s.requirements().grade_scale,
+    );
+    (StatusCode::OK, Json(suggestions)).into_response()
+}
+
+/// Looks up the prerequisites of each course from WebReg, using whichever term's scraper is
+/// currently running.
+///
+/// # Arguments
+/// * `state` - The wrapper state
+/// * `courses` - The courses to look up (e.g., `CSE 100`)
+///
+/// # Returns
+/// * The prerequisite groups of each course that could be looked up, and whether WebReg
+///   could be reached at all
+pub(super) async fn fetch_prerequisites(
+    state: &Arc<WrapperState>,
+    courses: HashSet<String>,
+) -> (HashMap<String, Vec<Vec<String>>>, bool) {
+    let Some(term_info) = state
+        .terms()
+        .into_iter()
+        .filter(|t| t.is_running())
+        .max_by_key(|t| term_sort_key(&t.term))
+    else {
+        warn!("No term is running, so prerequisites can't be checked.");
+        return (HashMap::new(), false);
+    };
+
+    let retry = state.webreg_retry;
+    let prerequisites = futures::stream::iter(courses)
+        .map(|course| {
+            let term_info = term_info.clone();
+            async move {
+                let (subject, number) = course.split_once(' ')?;
+                let requester = term_info.wrapper.req(term_info.term.as_str()).parsed();
+                let prereqs = retry
+                    .run(Idempotency::Idempotent, || {
+                        requester.get_prerequisites(subject, number)
+                    })
+                    .await
+                    .ok()?;
+
+                let groups = prereqs
+                    .course_prerequisites
+                    .iter()
+                    .map(|group| {
+                        group
+                            .iter()
+                            .map(|p| normalize_course_code(&p.subj_course_id))
+                            .collect()
+                    })
+                    .collect();
+                Some((course, groups))
+            }
+        })
+        .buffer_unordered(PREREQUISITE_CONCURRENCY)
+        .filter_map(|r| async move { r })
+        .collect()
+        .await;
+
+    (prerequisites, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::Router;
+    use std::sync::Mutex;
+
+    use crate::types::tests::state;
+
+    /// Starts a cookie server that serves a new audit each time one is asked for, up to
+    /// `audits` of them, and records whether each request asked for a fresh audit.
+    async fn cookie_server(audits: usize) -> (u16, Arc<Mutex<Vec<bool>>>) {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let recorded = requests.clone();
+        let app = Router::new().route(
+            "/degree_audit",
+            get(move |Query(query): Query<HashMap<String, String>>| async move {
+                let mut requests = recorded.lock().unwrap();
+                requests.push(query.get("fresh").is_some_and(|f| f == "true"));
+                if requests.len() > audits {
+                    return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                }
+
+                Json(json!({
+                    "auditId": format!("a{}", requests.len()),
+                    "scrapedAt": "2024-01-01T00:00:00Z",
+                    "url": "https://example.com/read.html",
+                    "html": r#"<div class="requirement Status_OK"><span class="reqTitle">GE</span></div>"#,
+                }))
+                .into_response()
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (port, requests)
+    }
+
+    #[tokio::test]
+    async fn test_force_refresh() {
+        let (port, requests) = cookie_server(2).await;
+        let s = Arc::new(state(
+            "force-refresh",
+            &["FA24"],
+            json!({ "cookieServer": { "address": "127.0.0.1", "port": port } }),
+        ));
+        let owner = s.audit_owner(None).unwrap();
+        let audit_id = |force_refresh| {
+            let (s, owner) = (s.clone(), owner.clone());
+            async move {
+                get_audit_internal(&s, &owner, force_refresh, false)
+                    .await
+                    .map(|audit| audit.audit_id)
+            }
+        };
+
+        assert_eq!("a1", audit_id(false).await.unwrap());
+        assert_eq!("a1", audit_id(false).await.unwrap());
+        // Refreshing asks DARS for a new audit, rather than the most recent one
+        assert_eq!("a2", audit_id(true).await.unwrap());
+        assert_eq!(vec![false, true], *requests.lock().unwrap());
+
+        // A refresh that fails doesn't leave the old audit to be served
+        assert!(audit_id(true).await.is_err());
+        let cache = &s.degree_audit_cache_state.cache;
+        assert!(cache.get_allowing_stale(&owner.session_key()).is_none());
+        assert_eq!(vec![false, true, true], *requests.lock().unwrap());
+    }
+}
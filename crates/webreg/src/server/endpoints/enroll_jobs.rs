@@ -0,0 +1,316 @@
+//! Endpoints for scheduling enrollment jobs, which are run in the background by
+//! [`crate::enroll_jobs`].
+//!
+//! Jobs need to keep working after the request that created them ends, so they can only be
+//! made with a session token (see [`SESSION_TOKEN_HEADER`]), not with raw cookies. Each
+//! session only sees and manages its own jobs.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde_json::json;
+use tracing::info;
+
+use crate::db::{NewEnrollJob, WatchAlerts, ENROLL_JOB_EXPIRED, ENROLL_JOB_PENDING};
+use crate::enroll_jobs::job_expiry;
+use crate::notify::MAX_WATCH_CHANNELS;
+use crate::server::endpoints::db_error;
+use crate::server::types::{ApiErrorType, BodyEnrollJob, FieldError, WatchesQueryStr};
+use crate::server::validation::{check_section_id, require, ValidJson, Validate};
+use crate::sessions::SESSION_TOKEN_HEADER;
+use crate::types::WrapperState;
+
+/// The largest number of pending jobs that a session can have in a term.
+pub(super) const MAX_PENDING_JOBS: usize = 20;
+
+/// Gets the session token that the request was made with.
+pub(super) fn session_token(headers: &HeaderMap) -> Option<&str> {
+    headers.get(SESSION_TOKEN_HEADER)?.to_str().ok()
+}
+
+/// Why the endpoints need a session token.
+const NO_SESSION: &str =
+    "Enrollment jobs need a session token, since they run after this request ends.";
+/// The message for a database error.
+const DB_ERROR: &str = "Failed to access enrollment jobs";
+
+/// Creates the response for a request that wasn't made with a session token.
+///
+/// # Parameters
+/// - `reason`: Why the endpoint needs a session token.
+pub(super) fn no_session(reason: &'static str) -> Response {
+    ApiErrorType::from((
+        StatusCode::BAD_REQUEST,
+        reason,
+        Some(SESSION_TOKEN_HEADER.to_owned()),
+    ))
+    .into_response()
+}
+
+/// Parses an RFC 3339 time into the format that the database stores times in.
+pub(super) fn parse_time(time: &str) -> Option<String> {
+    DateTime::parse_from_rfc3339(time.trim()).ok().map(|t| {
+        t.with_timezone(&Utc)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    })
+}
+
+/// Formats a time that the database stored as an RFC 3339 time.
+pub(super) fn format_time(time: &str) -> Option<String> {
+    NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|t| t.and_utc().to_rfc3339())
+}
+
+/// Reads how a job's seat alerts are throttled from its body, defaulting to alerting about
+/// every opening.
+pub(super) fn alerts_from_body(body: &BodyEnrollJob) -> WatchAlerts {
+    let default = WatchAlerts::default();
+    WatchAlerts {
+        cooldown_mins: body.cooldown_mins.unwrap_or(default.cooldown_mins),
+        dedup_mins: body.dedup_mins.unwrap_or(default.dedup_mins),
+        min_seats: body.min_seats.unwrap_or(default.min_seats),
+    }
+}
+
+/// GET /live/:term/enroll_jobs
+/// Returns the session's enrollment jobs for the term, newest first, along with every
+/// attempt that each job made
+pub async fn get_enroll_jobs(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/enroll_jobs", term);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    let jobs = match s.schedule_db.get_enroll_jobs(&term, token) {
+        Ok(jobs) => jobs,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    let mut results = Vec::with_capacity(jobs.len());
+    for job in jobs {
+        let attempts = match s.schedule_db.get_enroll_job_attempts(job.job_id) {
+            Ok(attempts) => attempts,
+            Err(e) => return db_error(e, DB_ERROR),
+        };
+
+        let mut result = json!(job);
+        result["attempt_log"] = json!(attempts);
+        results.push(result);
+    }
+
+    (StatusCode::OK, Json(results)).into_response()
+}
+
+/// GET /live/:term/watches
+/// Returns the sections that the session's pending enrollment jobs are watching, with the
+/// seats last seen by the tracker, how their seat alerts are throttled, and when each watch
+/// expires. With
+/// `?include_expired=true`, expired watches are included too, with why they expired (e.g.,
+/// `add_deadline`, or `enrolled_in_course` if the student enrolled in another section)
+pub async fn get_watches(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    Query(query): Query<WatchesQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!(
+        "GET /live/{}/watches (include_expired={:?})",
+        term, query.include_expired
+    );
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    let jobs = match s.schedule_db.get_enroll_jobs(&term, token) {
+        Ok(jobs) => jobs,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    let include_expired = query.include_expired.unwrap_or(false);
+    let watches: Vec<_> = jobs
+        .iter()
+        .filter(|j| {
+            j.status == ENROLL_JOB_PENDING || (include_expired && j.status == ENROLL_JOB_EXPIRED)
+        })
+        .map(|job| {
+            let expired = job.status == ENROLL_JOB_EXPIRED;
+            json!({
+                "job_id": job.job_id,
+                "section_id": job.section_id,
+                "course": format!("{} {}", job.subject_code.trim(), job.course_code.trim()),
+                "seats": s.live_feed.last_seen(&term, &job.section_id),
+                "expired": expired,
+                "expires_at": job.expires_at.as_deref().and_then(format_time),
+                "expired_at": expired.then(|| format_time(&job.updated_at)).flatten(),
+                "expiry_reason": job.expiry_reason,
+                "alerts": job.alerts,
+            })
+        })
+        .collect();
+
+    let add_deadline = s
+        .term(&term)
+        .and_then(|t| t.add_deadline)
+        .and_then(DateTime::<Utc>::from_timestamp_millis)
+        .map(|t| t.to_rfc3339());
+    (
+        StatusCode::OK,
+        Json(json!({
+            "term": term,
+            "add_deadline": add_deadline,
+            "watches": watches,
+        })),
+    )
+        .into_response()
+}
+
+impl Validate for BodyEnrollJob {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        check_section_id(&mut errors, "sectionId", &self.section_id);
+        require(&mut errors, "subjectCode", &self.subject_code);
+        require(&mut errors, "courseCode", &self.course_code);
+
+        let mut times = [None, None];
+        for ((parsed, time), field) in times
+            .iter_mut()
+            .zip([&self.run_at, &self.expires_at])
+            .zip(["runAt", "expiresAt"])
+        {
+            let Some(time) = time else {
+                continue;
+            };
+
+            *parsed = parse_time(time);
+            if parsed.is_none() {
+                errors.push(FieldError::new(field, "must be in RFC 3339 format"));
+            }
+        }
+
+        if let [Some(run_at), Some(expires_at)] = &times {
+            if expires_at <= run_at {
+                errors.push(FieldError::new("expiresAt", "must be after runAt"));
+            }
+        }
+
+        if self.notify.len() > MAX_WATCH_CHANNELS {
+            errors.push(FieldError::new(
+                "notify",
+                format!("must have at most {MAX_WATCH_CHANNELS} channels"),
+            ));
+        }
+        for (i, channel) in self.notify.iter().enumerate() {
+            if let Some(problem) = channel.check() {
+                errors.push(FieldError::new(format!("notify[{i}]"), problem));
+            }
+        }
+        if self.min_seats.is_some_and(|min| min < 1) {
+            errors.push(FieldError::new("minSeats", "must be at least 1"));
+        }
+        errors
+    }
+}
+
+/// POST /live/:term/enroll_jobs
+/// Schedules a job that enrolls the student in a section as soon as it has an open seat.
+/// If `runAt` is given, nothing is attempted before then (e.g., before the student's first
+/// pass); if `expiresAt` is given, the job gives up after then. Either way, it gives up at
+/// the term's add deadline, if one is configured. Times are in RFC 3339 format. The
+/// channels in `notify` are told when the job enrolls the student, fails, or expires, and
+/// are alerted when the section's seats open. Those alerts can be throttled for a flapping
+/// section: `cooldownMins` waits that long after an alert before alerting again,
+/// `dedupMins` drops alerts about a number of open seats that was alerted about that
+/// recently, and `minSeats` only alerts once that many seats are open.
+/// Returns the new job's ID
+pub async fn post_enroll_job(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyEnrollJob>,
+) -> Response {
+    info!("POST /live/{}/enroll_jobs", term);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    // The times were checked when the body was read
+    let run_at = body.run_at.as_deref().and_then(parse_time);
+    let expires_at = job_expiry(
+        body.expires_at.as_deref().and_then(parse_time),
+        s.term(&term).and_then(|t| t.add_deadline),
+    );
+
+    let pending = match s.schedule_db.get_enroll_jobs(&term, token) {
+        Ok(jobs) => jobs
+            .iter()
+            .filter(|j| j.status == ENROLL_JOB_PENDING)
+            .count(),
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    if pending >= MAX_PENDING_JOBS {
+        return ApiErrorType::from((
+            StatusCode::TOO_MANY_REQUESTS,
+            "This session has too many pending enrollment jobs in this term.",
+            None,
+        ))
+        .into_response();
+    }
+
+    let job = NewEnrollJob {
+        term: &term,
+        session_token: token,
+        section_id: body.section_id.trim(),
+        subject_code: body.subject_code.trim(),
+        course_code: body.course_code.trim(),
+        grading_option: body.grading_option.as_deref(),
+        unit_count: body.unit_count,
+        run_at,
+        expires_at,
+        notify_channels: &body.notify,
+        alerts: alerts_from_body(&body),
+    };
+
+    match s.schedule_db.insert_enroll_job(&job) {
+        Ok(job_id) => (StatusCode::CREATED, Json(json!({ "job_id": job_id }))).into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// DELETE /live/:term/enroll_jobs/:job_id
+/// Cancels one of the session's pending enrollment jobs
+pub async fn delete_enroll_job(
+    headers: HeaderMap,
+    Path((term, job_id)): Path<(String, i64)>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("DELETE /live/{}/enroll_jobs/{}", term, job_id);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    match s.schedule_db.cancel_enroll_job(&term, token, job_id) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "No pending enrollment job was found.",
+            None,
+        ))
+        .into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
@@ -0,0 +1,662 @@
+//! Endpoints for the student's custom events (e.g., work shifts or club meetings).
+//!
+//! Events are stored locally so that the conflict checker can account for them, and can
+//! be synced both ways with WebReg's own custom event feature. Each session only sees and
+//! syncs its own events, so they can only be used with a session token (see
+//! [`SESSION_TOKEN_HEADER`]).
+//!
+//! [`SESSION_TOKEN_HEADER`]: crate::sessions::SESSION_TOKEN_HEADER
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::header::COOKIE;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use serde_json::json;
+use tracing::{info, warn};
+use webweg::types::{Event, WrapperError};
+use webweg::wrapper::input_types::{DayOfWeek, EventAdd};
+
+use crate::db::{
+    CustomEvent, CustomEventFields, SYNC_STATE_PENDING_DELETE, SYNC_STATE_PENDING_PUSH,
+    SYNC_STATE_SYNCED,
+};
+use crate::retry::Idempotency;
+use crate::schedule::{day_abbreviation, parse_weekday};
+use crate::server::endpoints::db_error;
+use crate::server::endpoints::enroll_jobs::{no_session, session_token};
+use crate::server::types::{ApiErrorType, BodyCustomEvent};
+use crate::server::validation::ValidJson;
+use crate::types::WrapperState;
+
+/// Why the endpoints need a session token.
+const NO_SESSION: &str = "Custom events need a session token, since they're kept per session.";
+/// The message for a database error.
+const DB_ERROR: &str = "Failed to access custom events";
+
+/// Validates and normalizes a custom event from a request body.
+///
+/// # Parameters
+/// - `body`: The request body.
+///
+/// # Returns
+/// The event's fields, or a message describing what's wrong with them.
+fn fields_from_body(body: BodyCustomEvent) -> Result<CustomEventFields, &'static str> {
+    if body.name.trim().is_empty() {
+        return Err("The event must have a name.");
+    }
+
+    let mut days = vec![];
+    for day in &body.days {
+        let Some(d) = parse_weekday(day) else {
+            return Err("Days must be one of M, Tu, W, Th, F, Sa, Su.");
+        };
+
+        let abbr = day_abbreviation(d).to_owned();
+        if !days.contains(&abbr) {
+            days.push(abbr);
+        }
+    }
+
+    if days.is_empty() {
+        return Err("The event must happen on at least one day.");
+    }
+
+    let valid_time = |hr: i32, min: i32| (0..24).contains(&hr) && (0..60).contains(&min);
+    if !valid_time(body.start_hr, body.start_min) || !valid_time(body.end_hr, body.end_min) {
+        return Err("Times must be given as a valid hour (0-23) and minute (0-59).");
+    }
+
+    if body.start_hr * 60 + body.start_min >= body.end_hr * 60 + body.end_min {
+        return Err("The event must end after it starts.");
+    }
+
+    Ok(CustomEventFields {
+        name: body.name.trim().to_owned(),
+        location: body
+            .location
+            .map(|l| l.trim().to_owned())
+            .filter(|l| !l.is_empty()),
+        days,
+        start_hr: body.start_hr,
+        start_min: body.start_min,
+        end_hr: body.end_hr,
+        end_min: body.end_min,
+    })
+}
+
+/// Creates the response for an event that doesn't exist.
+fn not_found() -> Response {
+    ApiErrorType::from((StatusCode::NOT_FOUND, "Custom event not found", None)).into_response()
+}
+
+/// GET /live/:term/local_events
+/// Returns the session's custom events for the term, along with whether each one has been
+/// synced with WebReg
+pub async fn get_local_events(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/local_events", term);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    match s.schedule_db.get_custom_events(&term, token) {
+        Ok(events) => {
+            let events: Vec<_> = events
+                .into_iter()
+                .filter(|e| e.sync_state != SYNC_STATE_PENDING_DELETE)
+                .collect();
+            (StatusCode::OK, Json(events)).into_response()
+        }
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// POST /live/:term/local_events
+/// Creates a custom event for the session. It's sent to WebReg on the next sync
+pub async fn post_local_event(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyCustomEvent>,
+) -> Response {
+    info!("POST /live/{}/local_events", term);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    let fields = match fields_from_body(body) {
+        Ok(f) => f,
+        Err(msg) => {
+            return ApiErrorType::from((StatusCode::BAD_REQUEST, msg, None)).into_response();
+        }
+    };
+
+    match s
+        .schedule_db
+        .insert_custom_event(&term, token, &fields, None)
+    {
+        Ok(id) => (StatusCode::CREATED, Json(json!({ "event_id": id }))).into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// PUT /live/:term/local_events/:event_id
+/// Replaces one of the session's custom events. The change is sent to WebReg on the next
+/// sync
+pub async fn put_local_event(
+    headers: HeaderMap,
+    Path((term, event_id)): Path<(String, i64)>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyCustomEvent>,
+) -> Response {
+    info!("PUT /live/{}/local_events/{}", term, event_id);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    let fields = match fields_from_body(body) {
+        Ok(f) => f,
+        Err(msg) => {
+            return ApiErrorType::from((StatusCode::BAD_REQUEST, msg, None)).into_response();
+        }
+    };
+
+    match s.schedule_db.get_custom_event(&term, token, event_id) {
+        Ok(Some(e)) if e.sync_state != SYNC_STATE_PENDING_DELETE => {}
+        Ok(_) => return not_found(),
+        Err(e) => return db_error(e, DB_ERROR),
+    }
+
+    match s
+        .schedule_db
+        .update_custom_event(token, event_id, &fields, None)
+    {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => not_found(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// DELETE /live/:term/local_events/:event_id
+/// Deletes one of the session's custom events. If it was synced, it's removed from WebReg on
+/// the next sync
+pub async fn delete_local_event(
+    headers: HeaderMap,
+    Path((term, event_id)): Path<(String, i64)>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("DELETE /live/{}/local_events/{}", term, event_id);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    match s.schedule_db.delete_custom_event(&term, token, event_id) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => not_found(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// What happened during a sync.
+#[derive(Debug, Default, Serialize)]
+struct SyncSummary {
+    /// Local events that were created or updated on WebReg.
+    pushed: usize,
+    /// Events that were created or updated locally from WebReg.
+    pulled: usize,
+    /// Events that were deleted on WebReg because they were deleted locally.
+    deleted_remote: usize,
+    /// Events that were deleted locally because they were deleted on WebReg.
+    deleted_local: usize,
+    /// Events that couldn't be synced, and why.
+    errors: Vec<String>,
+}
+
+/// POST /live/:term/local_events/sync
+/// Syncs the session's custom events with WebReg in both directions. Local changes are sent
+/// to WebReg, and events created, changed, or deleted on WebReg are applied locally. If an
+/// event was changed in both places, the local change wins
+pub async fn post_sync_local_events(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("POST /live/{}/local_events/sync", term);
+
+    // The cookies are the session's, so its events are the ones that belong in this account
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+    let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
+    let remote = match fetch_remote_events(&s, &term, cookies).await {
+        Ok(r) => r,
+        Err(e) => return e.into_response(),
+    };
+
+    let local = match s.schedule_db.get_custom_events(&term, token) {
+        Ok(l) => l,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    let mut summary = SyncSummary::default();
+    let remote_by_ts: HashMap<&str, &Event> =
+        remote.iter().map(|e| (e.timestamp.as_str(), e)).collect();
+    let known_ts: HashSet<&str> = local
+        .iter()
+        .filter_map(|e| e.webreg_timestamp.as_deref())
+        .collect();
+
+    let requester = s
+        .c_wrapper
+        .req(term.as_str())
+        .override_cookies(cookies)
+        .parsed();
+    let mut new_events = vec![];
+    for event in &local {
+        let ts = event.webreg_timestamp.as_deref();
+        let remote_event = ts.and_then(|t| remote_by_ts.get(t));
+        match (event.sync_state.as_str(), ts) {
+            (SYNC_STATE_PENDING_DELETE, Some(ts)) => {
+                if remote_event.is_some() {
+                    let removed = s
+                        .webreg_retry
+                        .run(Idempotency::NonIdempotent, || requester.remove_event(ts))
+                        .await;
+                    if let Err(e) = removed {
+                        summary.errors.push(format!("{}: {e}", event.name));
+                        continue;
+                    }
+
+                    summary.deleted_remote += 1;
+                }
+
+                if let Err(e) = s.schedule_db.purge_custom_event(token, event.event_id) {
+                    summary.errors.push(format!("{}: {e}", event.name));
+                }
+            }
+            (SYNC_STATE_PENDING_PUSH, _) => {
+                if build_event_add(event).is_none() {
+                    summary
+                        .errors
+                        .push(format!("{}: could not be converted", event.name));
+                    continue;
+                }
+
+                // If the event was deleted on WebReg in the meantime, recreate it. An
+                // `EventAdd` can't be cloned, so each attempt builds its own.
+                let edit_ts = ts.filter(|_| remote_event.is_some());
+                let requester = &requester;
+                let pushed = s
+                    .webreg_retry
+                    .run(Idempotency::NonIdempotent, || async move {
+                        let event_add = build_event_add(event)
+                            .ok_or(WrapperError::InputError("event", "could not be converted"))?;
+                        requester.add_or_edit_event(event_add, edit_ts).await
+                    })
+                    .await;
+                match pushed {
+                    Ok(_) => match edit_ts {
+                        Some(ts) => {
+                            summary.pushed += 1;
+                            if let Err(e) =
+                                s.schedule_db
+                                    .mark_custom_event_synced(token, event.event_id, ts)
+                            {
+                                summary.errors.push(format!("{}: {e}", event.name));
+                            }
+                        }
+                        None => new_events.push(event),
+                    },
+                    Err(e) => summary.errors.push(format!("{}: {e}", event.name)),
+                }
+            }
+            (_, Some(_)) => match remote_event {
+                Some(r) => {
+                    let fields = fields_from_remote(r);
+                    if !same_fields(event, &fields) {
+                        match s.schedule_db.update_custom_event(
+                            token,
+                            event.event_id,
+                            &fields,
+                            Some(SYNC_STATE_SYNCED),
+                        ) {
+                            Ok(_) => summary.pulled += 1,
+                            Err(e) => summary.errors.push(format!("{}: {e}", event.name)),
+                        }
+                    }
+                }
+                None => match s.schedule_db.purge_custom_event(token, event.event_id) {
+                    Ok(_) => summary.deleted_local += 1,
+                    Err(e) => summary.errors.push(format!("{}: {e}", event.name)),
+                },
+            },
+            _ => {}
+        }
+    }
+
+    // Events that only exist on WebReg.
+    for r in remote
+        .iter()
+        .filter(|r| !known_ts.contains(r.timestamp.as_str()))
+    {
+        match s.schedule_db.insert_custom_event(
+            &term,
+            token,
+            &fields_from_remote(r),
+            Some(&r.timestamp),
+        ) {
+            Ok(_) => summary.pulled += 1,
+            Err(e) => summary.errors.push(format!("{}: {e}", r.name)),
+        }
+    }
+
+    // WebReg doesn't tell us the identifier of newly created events, so find them by
+    // looking for events we haven't seen before that match.
+    if !new_events.is_empty() {
+        let refreshed = match fetch_remote_events(&s, &term, cookies).await {
+            Ok(r) => r,
+            Err(e) => return e.into_response(),
+        };
+
+        let mut claimed: HashSet<&str> = remote.iter().map(|r| r.timestamp.as_str()).collect();
+        for event in new_events {
+            let matched = refreshed.iter().find(|r| {
+                !claimed.contains(r.timestamp.as_str())
+                    && same_fields(event, &fields_from_remote(r))
+            });
+
+            match matched {
+                Some(r) => {
+                    claimed.insert(r.timestamp.as_str());
+                    summary.pushed += 1;
+                    if let Err(e) =
+                        s.schedule_db
+                            .mark_custom_event_synced(token, event.event_id, &r.timestamp)
+                    {
+                        summary.errors.push(format!("{}: {e}", event.name));
+                    }
+                }
+                None => {
+                    warn!(
+                        "[{term}] Created event '{}' could not be found on WebReg.",
+                        event.name
+                    );
+                    summary.errors.push(format!(
+                        "{}: not found on WebReg after creation",
+                        event.name
+                    ));
+                }
+            }
+        }
+    }
+
+    (StatusCode::OK, Json(summary)).into_response()
+}
+
+/// Gets the student's custom events from WebReg.
+async fn fetch_remote_events(
+    s: &WrapperState,
+    term: &str,
+    cookies: &str,
+) -> Result<Vec<Event>, ApiErrorType<'static>> {
+    let requester = s.c_wrapper.req(term).override_cookies(cookies).parsed();
+    s.webreg_retry
+        .run(Idempotency::Idempotent, || requester.get_events())
+        .await
+        .map_err(ApiErrorType::from)
+}
+
+/// Converts an event from WebReg into local fields.
+fn fields_from_remote(event: &Event) -> CustomEventFields {
+    CustomEventFields {
+        name: event.name.trim().to_owned(),
+        location: Some(event.location.trim().to_owned()).filter(|l| !l.is_empty()),
+        days: event
+            .days
+            .iter()
+            .filter_map(|d| parse_weekday(d))
+            .map(|d| day_abbreviation(d).to_owned())
+            .collect(),
+        start_hr: event.start_hr as i32,
+        start_min: event.start_min as i32,
+        end_hr: event.end_hr as i32,
+        end_min: event.end_min as i32,
+    }
+}
+
+/// Whether a local event has the same fields as the given ones.
+fn same_fields(event: &CustomEvent, fields: &CustomEventFields) -> bool {
+    event.name == fields.name
+        && event.location == fields.location
+        && event.days == fields.days
+        && (event.start_hr, event.start_min, event.end_hr, event.end_min)
+            == (
+                fields.start_hr,
+                fields.start_min,
+                fields.end_hr,
+                fields.end_min,
+            )
+}
+
+/// Builds the request needed to create or edit the event on WebReg.
+fn build_event_add(event: &CustomEvent) -> Option<EventAdd<'_>> {
+    let mut builder = EventAdd::builder()
+        .with_name(event.name.as_str())
+        .with_start_time(event.start_hr as u32, event.start_min as u32)
+        .with_end_time(event.end_hr as u32, event.end_min as u32);
+
+    if let Some(location) = &event.location {
+        builder = builder.with_location(location.as_str());
+    }
+
+    for day in event.days.iter().filter_map(|d| parse_weekday(d)) {
+        builder = builder.with_day(match day {
+            chrono::Weekday::Mon => DayOfWeek::Monday,
+            chrono::Weekday::Tue => DayOfWeek::Tuesday,
+            chrono::Weekday::Wed => DayOfWeek::Wednesday,
+            chrono::Weekday::Thu => DayOfWeek::Thursday,
+            chrono::Weekday::Fri => DayOfWeek::Friday,
+            chrono::Weekday::Sat => DayOfWeek::Saturday,
+            chrono::Weekday::Sun => DayOfWeek::Sunday,
+        });
+    }
+
+    builder.try_build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+    use axum::http::HeaderValue;
+    use serde_json::Value;
+
+    use crate::sessions::SESSION_TOKEN_HEADER;
+    use crate::types::tests::state;
+
+    fn event_body(name: &str, days: &[&str], start_hr: i32, end_hr: i32) -> BodyCustomEvent {
+        BodyCustomEvent {
+            name: name.to_owned(),
+            location: Some("  ".to_owned()),
+            days: days.iter().map(|d| d.to_string()).collect(),
+            start_hr,
+            start_min: 0,
+            end_hr,
+            end_min: 30,
+        }
+    }
+
+    fn session(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(SESSION_TOKEN_HEADER, HeaderValue::from_str(token).unwrap());
+        headers
+    }
+
+    async fn get(s: &Arc<WrapperState>, token: &str, term: &str) -> Value {
+        let res = get_local_events(session(token), Path(term.to_owned()), State(s.clone())).await;
+        assert_eq!(StatusCode::OK, res.status());
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn test_fields_from_body() {
+        let fields = fields_from_body(event_body(" Work ", &["tu", "Th", "Tu"], 9, 11)).unwrap();
+        assert_eq!("Work", fields.name);
+        assert_eq!(None, fields.location);
+        assert_eq!(vec!["Tu", "Th"], fields.days);
+
+        for (body, error) in [
+            (event_body(" ", &["M"], 9, 11), "name"),
+            (event_body("Work", &[], 9, 11), "at least one day"),
+            (event_body("Work", &["Monday?"], 9, 11), "Days must be"),
+            (event_body("Work", &["M"], 9, 24), "valid hour"),
+            (event_body("Work", &["M"], 11, 9), "end after it starts"),
+        ] {
+            assert!(
+                fields_from_body(body).unwrap_err().contains(error),
+                "{error}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_local_events_per_session() {
+        let s = Arc::new(state("events-per-session", &["FA24"], json!({})));
+        let fields = fields_from_body(event_body("Work", &["M", "W"], 9, 11)).unwrap();
+        let mine = s
+            .schedule_db
+            .insert_custom_event("FA24", "a", &fields, None)
+            .unwrap();
+        let synced = s
+            .schedule_db
+            .insert_custom_event("FA24", "a", &fields, Some("2024-01-01 00:00:00"))
+            .unwrap();
+        s.schedule_db
+            .insert_custom_event("FA24", "b", &fields, None)
+            .unwrap();
+        s.schedule_db
+            .insert_custom_event("WI25", "a", &fields, None)
+            .unwrap();
+
+        let events = get(&s, "a", "FA24").await;
+        let ids: Vec<_> = events
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["event_id"].as_i64().unwrap())
+            .collect();
+        assert_eq!(2, ids.len());
+        assert!(ids.contains(&mine) && ids.contains(&synced));
+
+        // An event that's waiting to be deleted on WebReg isn't returned
+        let res = delete_local_event(
+            session("a"),
+            Path(("FA24".to_owned(), synced)),
+            State(s.clone()),
+        )
+        .await;
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+        let events = get(&s, "a", "FA24").await;
+        assert_eq!(1, events.as_array().unwrap().len());
+
+        // Another session can't change or delete the event
+        let res = put_local_event(
+            session("b"),
+            Path(("FA24".to_owned(), mine)),
+            State(s.clone()),
+            ValidJson(event_body("Gym", &["F"], 7, 8)),
+        )
+        .await;
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+        let res = delete_local_event(
+            session("b"),
+            Path(("FA24".to_owned(), mine)),
+            State(s.clone()),
+        )
+        .await;
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+
+        let res =
+            get_local_events(HeaderMap::new(), Path("FA24".to_owned()), State(s.clone())).await;
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+    }
+
+    #[tokio::test]
+    async fn test_local_event_serialization() {
+        let s = Arc::new(state("events-serialization", &["FA24"], json!({})));
+        let mut body = event_body("Club", &["F", "M"], 18, 19);
+        body.location = Some(" PC East ".to_owned());
+        let res = post_local_event(
+            session("a"),
+            Path("FA24".to_owned()),
+            State(s.clone()),
+            ValidJson(body),
+        )
+        .await;
+        assert_eq!(StatusCode::CREATED, res.status());
+        let created: Value =
+            serde_json::from_slice(&to_bytes(res.into_body(), usize::MAX).await.unwrap()).unwrap();
+        let event_id = created["event_id"].as_i64().unwrap();
+
+        let event = &get(&s, "a", "FA24").await[0];
+        assert_eq!(event_id, event["event_id"]);
+        assert_eq!("FA24", event["term"]);
+        assert_eq!("Club", event["name"]);
+        assert_eq!("PC East", event["location"]);
+        assert_eq!(json!(["F", "M"]), event["days"]);
+        assert_eq!(
+            json!([18, 0, 19, 30]),
+            json!([
+                event["start_hr"],
+                event["start_min"],
+                event["end_hr"],
+                event["end_min"]
+            ])
+        );
+        assert_eq!(SYNC_STATE_PENDING_PUSH, event["sync_state"]);
+        assert!(event["webreg_timestamp"].is_null());
+        assert!(event.get("session_token").is_none());
+    }
+
+    #[test]
+    fn test_fields_from_remote() {
+        let remote = Event {
+            location: " ".to_owned(),
+            start_hr: 9,
+            start_min: 0,
+            end_hr: 10,
+            end_min: 15,
+            name: " Work ".to_owned(),
+            days: vec!["M".to_owned(), "w".to_owned(), "?".to_owned()],
+            timestamp: "2024-01-01 00:00:00".to_owned(),
+        };
+
+        let fields = fields_from_remote(&remote);
+        assert_eq!("Work", fields.name);
+        assert_eq!(None, fields.location);
+        // Days that can't be read are left out
+        assert_eq!(vec!["M", "W"], fields.days);
+        assert_eq!(
+            (9, 0, 10, 15),
+            (
+                fields.start_hr,
+                fields.start_min,
+                fields.end_hr,
+                fields.end_min
+            )
+        );
+    }
+}
@@ -0,0 +1,49 @@
+//! Endpoints for historical grade distributions and course evaluations.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use tracing::info;
+
+use crate::db::normalize_course_code;
+use crate::error::WebregError;
+use crate::grades::{summarize, summarize_by_instructor};
+use crate::server::types::ApiErrorType;
+use crate::types::WrapperState;
+
+/// GET /courses/:course/grade_distribution
+/// Returns every grade distribution and evaluation on record for the course (e.g., `CSE 100`),
+/// newest term first, along with a summary overall and for each instructor
+pub async fn get_grade_distribution(
+    Path(course): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /courses/{}/grade_distribution", course);
+
+    let course = normalize_course_code(&course);
+    match s.schedule_db.get_grade_records(&course) {
+        Ok(records) if records.is_empty() => ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "No grade data found for course",
+            Some(course),
+        ))
+        .into_response(),
+        Ok(records) => (
+            StatusCode::OK,
+            Json(json!({
+                "course": course,
+                "summary": summarize(&records),
+                "instructors": summarize_by_instructor(&records),
+                "offerings": records,
+            })),
+        )
+            .into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to fetch grade data")
+            .into_response(),
+    }
+}
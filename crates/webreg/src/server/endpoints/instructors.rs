@@ -0,0 +1,92 @@
+//! Endpoints for looking up sections by instructor.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use tracing::info;
+
+use crate::error::WebregError;
+use crate::server::types::{ApiErrorType, InstructorQueryStr};
+use crate::types::WrapperState;
+
+/// GET /live/:term/instructors?search=smith
+/// Returns every instructor teaching in the term, along with the number of sections and the
+/// courses they teach. If `search` is given, only instructors whose name contains it are
+/// returned
+pub async fn get_instructors(
+    Path(term): Path<String>,
+    Query(query): Query<InstructorQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/instructors", term);
+
+    let search = query
+        .search
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty());
+
+    match s.schedule_db.get_instructors(&term, search) {
+        Ok(instructors) => (StatusCode::OK, Json(instructors)).into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to fetch instructors")
+            .into_response(),
+    }
+}
+
+/// GET /live/:term/instructors/:name/sections
+/// Returns every section that the instructor teaches in the term, along with its meetings
+pub async fn get_instructor_sections(
+    Path((term, name)): Path<(String, String)>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/instructors/{}/sections", term, name);
+
+    match s.schedule_db.get_instructor_sections(&term, &name) {
+        Ok(sections) if sections.is_empty() => ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "No sections found for instructor",
+            Some(name),
+        ))
+        .into_response(),
+        Ok(sections) => {
+            let response: Vec<_> = sections
+                .into_iter()
+                .map(|(subj_course_id, section, meetings)| {
+                    json!({
+                        "subj_course_id": subj_course_id.trim(),
+                        "section_id": section.section_id,
+                        "section_code": section.section_code,
+                        "start_date": section.start_date,
+                        "end_date": section.end_date,
+                        "meetings": meetings.into_iter().map(|m| {
+                            json!({
+                                "type": m.meeting_type,
+                                "days_type": m.meeting_days_type,
+                                "days": m.meeting_days,
+                                "start_hr": m.start_hr,
+                                "start_min": m.start_min,
+                                "end_hr": m.end_hr,
+                                "end_min": m.end_min,
+                                "building": m.building,
+                                "room": m.room,
+                                "instructors": m.instructors,
+                                "start_date": m.start_date,
+                                "end_date": m.end_date,
+                            })
+                        }).collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to fetch instructor sections")
+            .into_response(),
+    }
+}
@@ -0,0 +1,107 @@
+//! Streaming endpoints that push changes to tracked sections to clients.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use futures::{SinkExt, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+use crate::scraper::throttle::{AlertThrottle, WatchSettings};
+use crate::server::types::{ApiErrorType, LiveWatchQueryStr};
+use crate::types::WrapperState;
+
+/// GET /live/:term/ws
+///
+/// Upgrades the connection to a WebSocket, over which a JSON message is sent every time
+/// the tracker notices that a section for this term opened up, had its waitlist change,
+/// or was cancelled.
+///
+/// Each connection is a watch of its own, and can throttle its alerts: `cooldown_mins`
+/// waits that long after an alert about a section before alerting about it again,
+/// `dedup_mins` drops alerts about a state of a section (e.g., one open seat) that was
+/// alerted about that recently, and `min_seats` only alerts once that many seats are open.
+pub async fn get_live_events(
+    Path(term): Path<String>,
+    Query(query): Query<LiveWatchQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    info!("GET /live/{term}/ws");
+    let min_open_seats = query.min_seats.unwrap_or(1);
+    if min_open_seats < 1 {
+        return ApiErrorType::from((
+            StatusCode::BAD_REQUEST,
+            "The min_seats parameter must be at least 1.",
+            Some(min_open_seats.to_string()),
+        ))
+        .into_response();
+    }
+
+    let settings = WatchSettings {
+        cooldown: Duration::from_secs(query.cooldown_mins.unwrap_or(0).saturating_mul(60)),
+        dedup_window: Duration::from_secs(query.dedup_mins.unwrap_or(0).saturating_mul(60)),
+        min_open_seats,
+    };
+    let term = term.to_uppercase();
+    ws.on_upgrade(move |socket| stream_events(socket, s, term, settings))
+}
+
+/// Forwards events for the given term to the socket until either side closes.
+///
+/// # Parameters
+/// - `socket`: The client's socket.
+/// - `s`: The wrapper state.
+/// - `term`: The term to send events for.
+/// - `settings`: How to throttle the events.
+async fn stream_events(
+    socket: WebSocket,
+    s: Arc<WrapperState>,
+    term: String,
+    settings: WatchSettings,
+) {
+    let mut events = s.live_feed.subscribe();
+    let mut throttle = AlertThrottle::new(settings);
+    let (mut sender, mut receiver) = socket.split();
+
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let event = match event {
+                    Ok(e) => e,
+                    Err(RecvError::Lagged(n)) => {
+                        warn!("[{term}] WebSocket client fell behind; skipped {n} event(s).");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                if event.term() != term || !throttle.admit(&event, Instant::now()) {
+                    continue;
+                }
+
+                let Ok(json) = serde_json::to_string(&event) else {
+                    continue;
+                };
+
+                if sender.send(Message::Text(json)).await.is_err() {
+                    break;
+                }
+            }
+            msg = receiver.next() => {
+                // We don't expect anything from the client, but we need to notice when
+                // it goes away.
+                match msg {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+
+    info!("[{term}] WebSocket client disconnected.");
+}
@@ -1,5 +1,51 @@
+use axum::response::{IntoResponse, Response};
+
+use crate::error::WebregError;
+
+pub mod about;
+pub mod admin;
+pub mod analytics;
+#[cfg(feature = "auth")]
+pub mod api_keys;
+pub mod audit_trail;
+pub mod cart;
+pub mod catalog;
+pub mod changes;
+pub mod course_plans;
+#[cfg(feature = "auth")]
+pub mod custom_requirements;
 pub mod degree_audit;
+pub mod enroll_jobs;
+pub mod events;
+pub mod grades;
+pub mod instructors;
+pub mod live;
+#[cfg(feature = "auth")]
+pub mod my_schedule;
+pub mod offerings;
+pub mod overview;
+pub mod plans;
+#[cfg(feature = "auth")]
+pub mod registration;
+pub mod rooms;
 pub mod schedule;
+pub mod search;
+pub mod sessions;
+pub mod sharing;
 pub mod status;
+pub mod sync;
+#[cfg(feature = "auth")]
+pub mod user_bundles;
+#[cfg(feature = "auth")]
+pub mod vault;
 pub mod ww_cookies;
 pub mod ww_general;
+
+/// Creates the response for a database error.
+///
+/// # Parameters
+/// - `e`: The error.
+/// - `message`: What couldn't be done (e.g., `Failed to access the cart`).
+fn db_error(e: rusqlite::Error, message: &'static str) -> Response {
+    WebregError::from(e).with_message(message).into_response()
+}
@@ -0,0 +1,225 @@
+//! Endpoints for the mirror of the student's WebReg schedule (see
+//! [`crate::schedule_mirror`]) and for their enrollment appointments (see
+//! [`crate::pass_times`]). Both are kept per API key.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::header::{self, COOKIE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use serde_json::json;
+use tracing::info;
+
+use crate::error::WebregError;
+use crate::pass_times::fetch_pass_times;
+use crate::retry::Idempotency;
+use crate::schedule::{build_ical, find_conflicts, ScheduledSection};
+use crate::schedule_mirror::{diff_enrollments, mirror_section};
+use crate::server::endpoints::schedule::load_scheduled_sections;
+use crate::server::types::{ApiErrorType, MyScheduleQueryStr};
+use crate::types::WrapperState;
+
+/// POST /live/:term/sync_my_schedule
+/// Pulls the sections that the student is enrolled or waitlisted in from WebReg and stores
+/// them, replacing the last sync. Returns the stored sections along with what changed since
+/// the last sync
+pub async fn post_sync_my_schedule(
+    headers: HeaderMap,
+    Extension(prefix): Extension<String>,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("POST /live/{}/sync_my_schedule", term);
+
+    let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
+    let requester = s
+        .c_wrapper
+        .req(term.as_str())
+        .override_cookies(cookies)
+        .parsed();
+    let schedule = match s
+        .webreg_retry
+        .run(Idempotency::Idempotent, || requester.get_schedule(None))
+        .await
+    {
+        Ok(schedule) => schedule,
+        Err(e) => return ApiErrorType::from(e).into_response(),
+    };
+
+    let current: Vec<_> = schedule
+        .iter()
+        .filter_map(|section| mirror_section(&term, section))
+        .collect();
+    let previous = match s.schedule_db.get_user_enrollments(&prefix, &term) {
+        Ok(previous) => previous,
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to fetch the mirrored schedule")
+                .into_response()
+        }
+    };
+
+    let changes = diff_enrollments(&previous, &current);
+    match s
+        .schedule_db
+        .replace_user_enrollments(&prefix, &term, &current)
+        .and_then(|_| s.schedule_db.get_user_enrollments(&prefix, &term))
+    {
+        Ok(sections) => (
+            StatusCode::OK,
+            Json(json!({
+                "term": term,
+                "sections": sections,
+                "changes": changes,
+            })),
+        )
+            .into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to store the mirrored schedule")
+            .into_response(),
+    }
+}
+
+/// GET /live/:term/my_schedule?format=json
+/// Returns the student's schedule as of the last sync, along with any conflicts between its
+/// sections, without calling WebReg. With `format=ics`, the schedule is returned as an
+/// iCalendar file instead. Sections without schedule data are left out of both
+pub async fn get_my_schedule(
+    Extension(prefix): Extension<String>,
+    Path(term): Path<String>,
+    Query(query): Query<MyScheduleQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/my_schedule", term);
+
+    let ical = match query.format.as_deref().unwrap_or("json") {
+        "json" => false,
+        "ics" => true,
+        other => {
+            return ApiErrorType::from((
+                StatusCode::BAD_REQUEST,
+                "Unsupported format; use json or ics",
+                Some(other.to_owned()),
+            ))
+            .into_response();
+        }
+    };
+
+    let enrollments = match s.schedule_db.get_user_enrollments(&prefix, &term) {
+        Ok(enrollments) => enrollments,
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to fetch the mirrored schedule")
+                .into_response()
+        }
+    };
+
+    let mut sections: Vec<ScheduledSection> = vec![];
+    let mut missing = vec![];
+    for enrollment in &enrollments {
+        let entry = format!("{}:{}", enrollment.term, enrollment.section_id);
+        match load_scheduled_sections(&s, &entry, None) {
+            Ok((loaded, _)) => sections.extend(loaded),
+            Err(ApiErrorType::General {
+                status: StatusCode::NOT_FOUND,
+                ..
+            }) => missing.push(enrollment.section_id.clone()),
+            Err(e) => return e.into_response(),
+        }
+    }
+
+    if ical {
+        return (
+            StatusCode::OK,
+            [
+                (
+                    header::CONTENT_TYPE,
+                    "text/calendar; charset=utf-8".to_owned(),
+                ),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"my_schedule_{term}.ics\""),
+                ),
+            ],
+            build_ical(&sections),
+        )
+            .into_response();
+    }
+
+    let conflicts = find_conflicts(&sections);
+    (
+        StatusCode::OK,
+        Json(json!({
+            "term": term,
+            "sections": enrollments,
+            "missing_schedule_data": missing,
+            "has_conflicts": !conflicts.is_empty(),
+            "conflicts": conflicts,
+        })),
+    )
+        .into_response()
+}
+
+/// POST /live/:term/sync_my_passtimes
+/// Fetches the student's enrollment appointments (first and second pass) from WebReg and
+/// stores them, replacing the last fetch. Reminders are sent before each pass opens if they're
+/// configured
+pub async fn post_sync_my_passtimes(
+    headers: HeaderMap,
+    Extension(prefix): Extension<String>,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("POST /live/{}/sync_my_passtimes", term);
+
+    let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
+    let passes = match fetch_pass_times(&s, &term, cookies).await {
+        Ok(passes) => passes,
+        Err(e) => return e.into_response(),
+    };
+
+    match s
+        .schedule_db
+        .replace_pass_times(&prefix, &term, &passes)
+        .and_then(|_| s.schedule_db.get_pass_times(&prefix, &term))
+    {
+        Ok(passes) => (
+            StatusCode::OK,
+            Json(json!({
+                "term": term,
+                "passes": passes,
+            })),
+        )
+            .into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to store enrollment appointments")
+            .into_response(),
+    }
+}
+
+/// GET /live/:term/my_passtimes
+/// Returns the student's enrollment appointments as of the last fetch, without calling
+/// WebReg
+pub async fn get_my_passtimes(
+    Extension(prefix): Extension<String>,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/my_passtimes", term);
+
+    match s.schedule_db.get_pass_times(&prefix, &term) {
+        Ok(passes) => (
+            StatusCode::OK,
+            Json(json!({
+                "term": term,
+                "passes": passes,
+            })),
+        )
+            .into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to fetch enrollment appointments")
+            .into_response(),
+    }
+}
@@ -0,0 +1,48 @@
+//! Endpoints for how often courses are offered.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use tracing::info;
+
+use crate::db::normalize_course_code;
+use crate::error::WebregError;
+use crate::offerings::OfferingHistory;
+use crate::server::types::ApiErrorType;
+use crate::types::WrapperState;
+
+/// GET /courses/:course/offering_history
+/// Returns every scraped term that the course (e.g., `CSE 100`) was offered in, oldest
+/// first, with its section and seat counts, how often it was offered in each season, and
+/// the one quarter of the year that it's offered in, if it's only offered in one
+pub async fn get_offering_history(
+    Path(course): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /courses/{}/offering_history", course);
+
+    let course = normalize_course_code(&course);
+    let offerings = s
+        .schedule_db
+        .get_course_offerings(&course)
+        .and_then(|offerings| Ok((offerings, s.schedule_db.get_offered_terms()?)));
+    match offerings {
+        Ok((offerings, _)) if offerings.is_empty() => ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "No offerings found for course",
+            Some(course),
+        ))
+        .into_response(),
+        Ok((offerings, scraped)) => (
+            StatusCode::OK,
+            Json(OfferingHistory::new(course, offerings, &scraped)),
+        )
+            .into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to fetch offering history")
+            .into_response(),
+    }
+}
@@ -0,0 +1,410 @@
+//! The term overview, which gathers what a student's home screen shows into one response.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::header::COOKIE;
+use axum::http::{Extensions, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+use webweg::types::{EnrollmentStatus, ScheduledSection};
+
+#[cfg(feature = "auth")]
+use crate::api_keys::{ApiScope, KeyScopes};
+use crate::db::{EnrollJob, ENROLL_JOB_PENDING};
+use crate::error::WebregError;
+use crate::retry::Idempotency;
+use crate::schedule::{Exam, ExamKind, MeetingSlot};
+use crate::server::endpoints::degree_audit::progress_headline;
+use crate::server::endpoints::enroll_jobs::session_token;
+use crate::server::types::ApiErrorType;
+use crate::types::WrapperState;
+
+/// Converts a time from the database (`YYYY-MM-DD HH:MM:SS`, in UTC) to the local date and
+/// time (`YYYY-MM-DD` and `HH:MM`).
+fn local_date_time(utc: &str) -> Option<(String, String)> {
+    let time = NaiveDateTime::parse_from_str(utc, "%Y-%m-%d %H:%M:%S").ok()?;
+    let local = Utc.from_utc_datetime(&time).with_timezone(&Local);
+    Some((
+        local.format("%Y-%m-%d").to_string(),
+        local.format("%H:%M").to_string(),
+    ))
+}
+
+/// GET /live/:term/overview
+/// Returns the student's enrolled schedule, their waitlist positions, the sections that
+/// their enrollment jobs are watching (with the seats last seen by the tracker), upcoming
+/// deadlines (finals, enrollment job start and expiry times, and the end of the term), and
+/// the headline numbers of their degree progress (for keys with the `degree_audit` scope).
+/// The schedule and degree audit are fetched concurrently; if the degree audit can't be
+/// fetched, the rest is still returned
+pub async fn get_overview(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+    extensions: Extensions,
+) -> Response {
+    info!("GET /live/{}/overview", term);
+
+    let key_prefix = extensions.get::<String>().map(String::as_str);
+    // The audit is only used if the caller could have fetched it themselves
+    #[cfg(feature = "auth")]
+    let audit_allowed = extensions
+        .get::<KeyScopes>()
+        .is_some_and(|scopes| scopes.allows(ApiScope::DegreeAudit));
+    #[cfg(not(feature = "auth"))]
+    let audit_allowed = true;
+
+    let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
+    let requester = s
+        .c_wrapper
+        .req(term.as_str())
+        .override_cookies(cookies)
+        .parsed();
+    let schedule = s
+        .webreg_retry
+        .run(Idempotency::Idempotent, || requester.get_schedule(None));
+    let progress = async {
+        if audit_allowed {
+            Some(progress_headline(&s, key_prefix).await)
+        } else {
+            None
+        }
+    };
+    let (schedule, progress) = tokio::join!(schedule, progress);
+
+    let schedule = match schedule {
+        Ok(schedule) => schedule,
+        Err(e) => return ApiErrorType::from(e).into_response(),
+    };
+
+    let jobs = match session_token(&headers) {
+        Some(token) => match s.schedule_db.get_enroll_jobs(&term, token) {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                return WebregError::from(e)
+                    .with_message("Failed to fetch enrollment jobs")
+                    .into_response()
+            }
+        },
+        None => vec![],
+    };
+
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let mut overview = build_overview(&s, &term, &schedule, &jobs, &today);
+
+    let (progress, progress_error) = match progress {
+        Some(Ok(progress)) => (Some(progress), None),
+        Some(Err(e)) => {
+            warn!("Failed to fetch degree progress for the overview: {}", e);
+            e.log_context();
+            (None, Some(e.message().to_owned()))
+        }
+        None => (None, None),
+    };
+    overview["degree_progress"] = json!(progress);
+    overview["degree_progress_error"] = json!(progress_error);
+
+    (StatusCode::OK, Json(overview)).into_response()
+}
+
+/// Builds the parts of the overview that come from the student's schedule and enrollment
+/// jobs.
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `term`: The term.
+/// - `schedule`: The student's schedule.
+/// - `jobs`: The student's enrollment jobs in the term.
+/// - `today`: Today's local date (`YYYY-MM-DD`). Deadlines before it are left out.
+///
+/// # Returns
+/// The overview, without the student's degree progress.
+fn build_overview(
+    s: &WrapperState,
+    term: &str,
+    schedule: &[ScheduledSection],
+    jobs: &[EnrollJob],
+    today: &str,
+) -> Value {
+    // Each deadline is keyed by its local date and time, so that they can be sorted
+    let mut deadlines: Vec<(String, String, Value)> = vec![];
+    let mut enrolled = vec![];
+    let mut waitlists = vec![];
+    for section in schedule {
+        let course = format!(
+            "{} {}",
+            section.subject_code.trim(),
+            section.course_code.trim()
+        );
+        match section.enrolled_status {
+            EnrollmentStatus::Enrolled => enrolled.push(section),
+            EnrollmentStatus::Waitlist { waitlist_pos } => {
+                waitlists.push(json!({
+                    "section_id": section.section_id,
+                    "course": course,
+                    "section_code": section.section_code,
+                    "position": waitlist_pos,
+                    "waitlist_ct": section.waitlist_ct,
+                }));
+                enrolled.push(section);
+            }
+            _ => continue,
+        }
+
+        let finals = section
+            .meetings
+            .iter()
+            .filter_map(MeetingSlot::from_webreg)
+            .filter_map(|slot| Exam::from_slot(&slot))
+            .filter(|exam| exam.kind == ExamKind::Final);
+        for exam in finals {
+            let Some(date) = exam.date.clone() else {
+                continue;
+            };
+            deadlines.push((
+                date,
+                exam.start.clone(),
+                json!({
+                    "kind": "final_exam",
+                    "course": course,
+                    "section_id": section.section_id,
+                    "exam": exam,
+                }),
+            ));
+        }
+    }
+
+    let mut watched = vec![];
+    for job in jobs.iter().filter(|j| j.status == ENROLL_JOB_PENDING) {
+        let course = format!("{} {}", job.subject_code.trim(), job.course_code.trim());
+        watched.push(json!({
+            "job_id": job.job_id,
+            "section_id": job.section_id,
+            "course": course,
+            "seats": s.live_feed.last_seen(term, &job.section_id),
+        }));
+
+        for (kind, at) in [
+            ("enroll_job_starts", &job.run_at),
+            ("enroll_job_expires", &job.expires_at),
+        ] {
+            if let Some((date, time)) = at.as_deref().and_then(local_date_time) {
+                deadlines.push((
+                    date,
+                    time,
+                    json!({
+                        "kind": kind,
+                        "course": course,
+                        "job_id": job.job_id,
+                    }),
+                ));
+            }
+        }
+    }
+
+    if let Some(dates) = s.term(term).and_then(|t| t.date_range) {
+        deadlines.push((
+            dates.end.format("%Y-%m-%d").to_string(),
+            String::new(),
+            json!({ "kind": "term_ends" }),
+        ));
+    }
+
+    deadlines.retain(|(date, _, _)| date.as_str() >= today);
+    deadlines.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+    let deadlines: Vec<Value> = deadlines
+        .into_iter()
+        .map(|(date, time, mut deadline)| {
+            deadline["date"] = json!(date);
+            deadline["time"] = json!(Some(time).filter(|t| !t.is_empty()));
+            deadline
+        })
+        .collect();
+
+    json!({
+        "term": term,
+        "schedule": enrolled,
+        "enrolled_units": enrolled
+            .iter()
+            .filter(|s| matches!(s.enrolled_status, EnrollmentStatus::Enrolled))
+            .map(|s| s.units)
+            .sum::<i64>(),
+        "waitlists": waitlists,
+        "watched_sections": watched,
+        "deadlines": deadlines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use webweg::types::{Meeting, MeetingDay};
+
+    use super::*;
+    use crate::db::{WatchAlerts, ENROLL_JOB_ENROLLED};
+    use crate::types::tests::state;
+
+    fn section(
+        section_id: &str,
+        enrolled_status: EnrollmentStatus,
+        finals: &[&str],
+    ) -> ScheduledSection {
+        ScheduledSection {
+            section_id: section_id.to_owned(),
+            subject_code: "CSE".to_owned(),
+            course_code: section_id.to_owned(),
+            course_title: "Course".to_owned(),
+            section_code: "A01".to_owned(),
+            section_capacity: 100,
+            enrolled_count: 100,
+            available_seats: 0,
+            grade_option: "L".to_owned(),
+            all_instructors: vec![],
+            units: 4,
+            enrolled_status,
+            waitlist_ct: 10,
+            meetings: finals
+                .iter()
+                .map(|date| Meeting {
+                    meeting_type: "FI".to_owned(),
+                    meeting_days: MeetingDay::OneTime(date.to_string()),
+                    start_hr: 8,
+                    start_min: 0,
+                    end_hr: 10,
+                    end_min: 59,
+                    building: "CENTR".to_owned(),
+                    room: "101".to_owned(),
+                    instructors: vec![],
+                })
+                .collect(),
+        }
+    }
+
+    fn job(job_id: i64, status: &str, run_at: Option<&str>) -> EnrollJob {
+        EnrollJob {
+            job_id,
+            term: "FA24".to_owned(),
+            session_token: String::new(),
+            section_id: format!("{job_id}"),
+            subject_code: "MATH".to_owned(),
+            course_code: "20C".to_owned(),
+            grading_option: None,
+            unit_count: None,
+            run_at: run_at.map(str::to_owned),
+            expires_at: None,
+            status: status.to_owned(),
+            attempts: 0,
+            created_at: String::new(),
+            updated_at: String::new(),
+            expiry_reason: None,
+            notify_channels: vec![],
+            alerts: WatchAlerts::default(),
+        }
+    }
+
+    #[test]
+    fn test_overview_schedule() {
+        let s = state("overview-schedule", &["FA24"], json!({}));
+        let schedule = vec![
+            section("100", EnrollmentStatus::Enrolled, &[]),
+            section("101", EnrollmentStatus::Waitlist { waitlist_pos: 2 }, &[]),
+            section("102", EnrollmentStatus::Planned, &[]),
+        ];
+        let jobs = vec![
+            job(1, ENROLL_JOB_PENDING, None),
+            job(2, ENROLL_JOB_ENROLLED, None),
+        ];
+
+        let overview = build_overview(&s, "FA24", &schedule, &jobs, "2024-09-01");
+        // Planned sections aren't part of the schedule
+        let ids: Vec<_> = overview["schedule"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["section_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(vec!["100", "101"], ids);
+        // Waitlisted sections don't count towards the enrolled units
+        assert_eq!(4, overview["enrolled_units"]);
+        assert_eq!(
+            json!([{
+                "section_id": "101",
+                "course": "CSE 101",
+                "section_code": "A01",
+                "position": 2,
+                "waitlist_ct": 10,
+            }]),
+            overview["waitlists"]
+        );
+        // Only pending jobs are watching a section
+        assert_eq!(
+            json!([{
+                "job_id": 1,
+                "section_id": "1",
+                "course": "MATH 20C",
+                "seats": null,
+            }]),
+            overview["watched_sections"]
+        );
+    }
+
+    #[test]
+    fn test_overview_deadlines() {
+        let s = state(
+            "overview-deadlines",
+            &["FA24"],
+            json!({
+                "wrapperData": [{
+                    "term": "FA24",
+                    "cooldown": 0.0,
+                    "searchQuery": [],
+                    "saveDataToFile": false,
+                    "startDate": "2024-09-26",
+                    "endDate": "2024-12-06",
+                }],
+            }),
+        );
+        let schedule = vec![
+            section("100", EnrollmentStatus::Enrolled, &["2024-12-10"]),
+            section(
+                "101",
+                EnrollmentStatus::Waitlist { waitlist_pos: 1 },
+                &["2024-12-09", "2024-10-01"],
+            ),
+            section("102", EnrollmentStatus::Planned, &["2024-12-08"]),
+        ];
+        let jobs = vec![job(1, ENROLL_JOB_PENDING, Some("2024-11-20 18:00:00"))];
+
+        let overview = build_overview(&s, "FA24", &schedule, &jobs, "2024-11-01");
+        let deadlines: Vec<_> = overview["deadlines"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| {
+                (
+                    d["kind"].as_str().unwrap(),
+                    d["date"].as_str().unwrap().to_owned(),
+                )
+            })
+            .collect();
+        // The job's start is given in local time. The final that has passed, and the
+        // planned section's final, are left out, and the rest are in order
+        let (job_date, job_time) = local_date_time("2024-11-20 18:00:00").unwrap();
+        assert_eq!(
+            vec![
+                ("enroll_job_starts", job_date),
+                ("term_ends", "2024-12-06".to_owned()),
+                ("final_exam", "2024-12-09".to_owned()),
+                ("final_exam", "2024-12-10".to_owned()),
+            ],
+            deadlines
+        );
+        assert_eq!(json!(job_time), overview["deadlines"][0]["time"]);
+        assert_eq!(json!(null), overview["deadlines"][1]["time"]);
+        assert_eq!("08:00", overview["deadlines"][2]["time"]);
+        assert_eq!("101", overview["deadlines"][2]["section_id"]);
+    }
+}
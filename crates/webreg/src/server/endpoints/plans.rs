@@ -0,0 +1,342 @@
+//! Endpoints for saved plans, which hold the sections that a student wants in one of their
+//! WebReg schedules, and for syncing them into WebReg (see [`crate::plan_sync`]).
+//!
+//! Like the cart, saved plans are kept between requests, so they can only be used with a
+//! session token (see [`SESSION_TOKEN_HEADER`]).
+//!
+//! [`SESSION_TOKEN_HEADER`]: crate::sessions::SESSION_TOKEN_HEADER
+
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::header::COOKIE;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use tracing::info;
+
+use crate::db::PlanEntry;
+use crate::error::WebregError;
+use crate::plan_sync::{diff_plan, CurrentSection, MAX_WEBREG_SCHEDULES};
+use crate::retry::Idempotency;
+use crate::server::endpoints::db_error;
+use crate::server::endpoints::enroll_jobs::{no_session, session_token};
+use crate::server::types::{ApiErrorType, BodyPlanAdd, BodySavedPlan, DryRunQueryStr, FieldError};
+use crate::server::util::build_add_plan_object;
+use crate::server::validation::{check_section_id, require, ValidJson, Validate};
+use crate::types::WrapperState;
+
+/// The largest number of plans that a session can save in a term.
+pub(super) const MAX_SAVED_PLANS: usize = 20;
+/// The largest number of sections that a saved plan can list.
+const MAX_PLAN_ENTRIES: usize = 20;
+
+/// Why the endpoints need a session token.
+const NO_SESSION: &str = "Saved plans need a session token, since they're kept between requests.";
+/// The message for a database error.
+const DB_ERROR: &str = "Failed to access saved plans";
+
+/// Creates the response for a plan that the session hasn't saved.
+fn not_found() -> Response {
+    ApiErrorType::from((StatusCode::NOT_FOUND, "Saved plan not found", None)).into_response()
+}
+
+/// Normalizes the sections of a plan's body, keeping them in the order they were given.
+pub(super) fn entries_from_body(body: &BodySavedPlan) -> Vec<PlanEntry> {
+    body.entries
+        .iter()
+        .map(|entry| PlanEntry {
+            subject_code: entry.subject_code.trim().to_uppercase(),
+            course_code: entry.course_code.trim().to_uppercase(),
+            section_id: entry.section_id.trim().to_owned(),
+            section_code: entry.section_code.trim().to_uppercase(),
+            grading_option: entry.grading_option.clone(),
+            unit_count: entry.unit_count,
+        })
+        .collect()
+}
+
+/// GET /live/:term/plans
+/// Returns the session's saved plans for the term
+pub async fn get_saved_plans(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/plans", term);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    match s.schedule_db.get_saved_plans(&term, token) {
+        Ok(plans) => (StatusCode::OK, Json(plans)).into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+impl Validate for BodySavedPlan {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        require(&mut errors, "scheduleName", &self.schedule_name);
+        if self.entries.len() > MAX_PLAN_ENTRIES {
+            errors.push(FieldError::new(
+                "entries",
+                format!("can list at most {MAX_PLAN_ENTRIES} sections"),
+            ));
+        }
+        for (i, entry) in self.entries.iter().enumerate() {
+            require(
+                &mut errors,
+                &format!("entries[{i}].subjectCode"),
+                &entry.subject_code,
+            );
+            require(
+                &mut errors,
+                &format!("entries[{i}].courseCode"),
+                &entry.course_code,
+            );
+            check_section_id(
+                &mut errors,
+                &format!("entries[{i}].sectionId"),
+                &entry.section_id,
+            );
+        }
+        errors
+    }
+}
+
+/// POST /live/:term/plans
+/// Saves a plan, replacing the session's plan with the same schedule name if there is one.
+/// Returns the plan's ID
+pub async fn post_saved_plan(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodySavedPlan>,
+) -> Response {
+    info!("POST /live/{}/plans", term);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    let schedule_name = body.schedule_name.trim();
+    let entries = entries_from_body(&body);
+
+    let plans = match s.schedule_db.get_saved_plans(&term, token) {
+        Ok(plans) => plans,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+    if plans.len() >= MAX_SAVED_PLANS && !plans.iter().any(|p| p.schedule_name == schedule_name) {
+        return ApiErrorType::from((
+            StatusCode::TOO_MANY_REQUESTS,
+            "This session has saved too many plans for this term.",
+            None,
+        ))
+        .into_response();
+    }
+
+    match s
+        .schedule_db
+        .upsert_saved_plan(&term, token, schedule_name, &entries)
+    {
+        Ok(plan_id) => (StatusCode::OK, Json(json!({ "plan_id": plan_id }))).into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// DELETE /live/:term/plans/:plan_id
+/// Removes one of the session's saved plans. The WebReg schedule it was synced into is left
+/// as it is
+pub async fn delete_saved_plan(
+    headers: HeaderMap,
+    Path((term, plan_id)): Path<(String, i64)>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("DELETE /live/{}/plans/{}", term, plan_id);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    match s.schedule_db.delete_saved_plan(&term, token, plan_id) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => not_found(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// POST /live/:term/plans/:plan_id/sync_to_webreg
+/// Makes the WebReg schedule named by the plan match it: the plan's sections that the
+/// schedule doesn't have are added, and the schedule's planned sections that the plan
+/// doesn't list are removed. Sections that the student is enrolled or waitlisted in are left
+/// alone. If the schedule doesn't exist yet, it's created, unless the student already has as
+/// many schedules as WebReg allows, in which case nothing is changed. With `?dry_run=true`,
+/// the changes (including whether the schedule would be created) are only reported. A
+/// change that WebReg rejects doesn't stop the others; the body lists each one that failed
+pub async fn post_sync_plan(
+    headers: HeaderMap,
+    Path((term, plan_id)): Path<(String, i64)>,
+    Query(query): Query<DryRunQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("POST /live/{}/plans/{}/sync_to_webreg", term, plan_id);
+
+    let Some(token) = session_token(&headers) else {
+        return no_session(NO_SESSION);
+    };
+
+    let plan = match s.schedule_db.get_saved_plan(&term, token, plan_id) {
+        Ok(Some(plan)) => plan,
+        Ok(None) => return not_found(),
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
+    let requester = s
+        .c_wrapper
+        .req(term.as_str())
+        .override_cookies(cookies)
+        .parsed();
+
+    let schedule_names = match s
+        .webreg_retry
+        .run(Idempotency::Idempotent, || requester.get_schedule_list())
+        .await
+    {
+        Ok(names) => names,
+        Err(err) => return ApiErrorType::from(err).into_response(),
+    };
+
+    let schedule_name = plan.schedule_name.as_str();
+    let creates_schedule = !schedule_names.iter().any(|name| name == schedule_name);
+    let current = if creates_schedule {
+        vec![]
+    } else {
+        match s
+            .webreg_retry
+            .run(Idempotency::Idempotent, || {
+                requester.get_schedule(Some(schedule_name))
+            })
+            .await
+        {
+            Ok(schedule) => schedule.iter().map(CurrentSection::from).collect(),
+            Err(err) => return ApiErrorType::from(err).into_response(),
+        }
+    };
+
+    let diff = diff_plan(&plan.entries, &current);
+    if creates_schedule && !diff.to_add.is_empty() && schedule_names.len() >= MAX_WEBREG_SCHEDULES {
+        return ApiErrorType::from((
+            StatusCode::CONFLICT,
+            "WebReg doesn't allow any more schedules this term. Remove one, or sync the plan into an existing schedule.",
+            Some(schedule_names.join(", ")),
+        ))
+        .into_response();
+    }
+
+    if query.dry_run.unwrap_or(false) || diff.is_empty() {
+        return (
+            StatusCode::OK,
+            Json(json!({
+                "plan_id": plan.plan_id,
+                "schedule_name": schedule_name,
+                "created_schedule": creates_schedule && !diff.to_add.is_empty(),
+                "dry_run": query.dry_run.unwrap_or(false),
+                "added": diff.to_add.iter().map(|e| &e.section_id).collect::<Vec<_>>(),
+                "removed": diff.to_remove,
+                "unchanged": diff.unchanged,
+                "enrolled": diff.enrolled,
+                "failed": [],
+            })),
+        )
+            .into_response();
+    }
+
+    let mut failed = vec![];
+
+    // Removing first makes room for sections that take the place of removed ones
+    let mut removed = vec![];
+    for section_id in &diff.to_remove {
+        match s
+            .webreg_retry
+            .run(Idempotency::NonIdempotent, || {
+                requester.remove_from_plan(section_id.as_str(), Some(schedule_name))
+            })
+            .await
+        {
+            Ok(true) => removed.push(section_id.clone()),
+            Ok(false) => failed.push(json!({
+                "section_id": section_id,
+                "action": "remove",
+                "error": "WebReg didn't remove the section.",
+            })),
+            Err(e) => {
+                let error = WebregError::from(e);
+                error.log_context();
+                failed.push(json!({
+                    "section_id": section_id,
+                    "action": "remove",
+                    "code": error.code(),
+                    "error": error.message(),
+                }))
+            }
+        }
+    }
+
+    let mut added = vec![];
+    for entry in &diff.to_add {
+        let plan_add = BodyPlanAdd {
+            subject_code: entry.subject_code.clone(),
+            course_code: entry.course_code.clone(),
+            section_id: entry.section_id.clone(),
+            section_code: entry.section_code.clone(),
+            grading_option: entry.grading_option.clone(),
+            schedule_name: Some(plan.schedule_name.clone()),
+            unit_count: entry.unit_count.unwrap_or(4),
+            validate: None,
+        };
+        match s
+            .webreg_retry
+            .run(Idempotency::NonIdempotent, || {
+                requester.add_to_plan(build_add_plan_object(&plan_add), true)
+            })
+            .await
+        {
+            Ok(true) => added.push(entry.section_id.clone()),
+            Ok(false) => failed.push(json!({
+                "section_id": entry.section_id,
+                "action": "add",
+                "error": "WebReg didn't add the section.",
+            })),
+            Err(e) => {
+                let error = WebregError::from(e);
+                error.log_context();
+                failed.push(json!({
+                    "section_id": entry.section_id,
+                    "action": "add",
+                    "code": error.code(),
+                    "error": error.message(),
+                }))
+            }
+        }
+    }
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "plan_id": plan.plan_id,
+            "schedule_name": schedule_name,
+            "created_schedule": creates_schedule && !added.is_empty(),
+            "dry_run": false,
+            "added": added,
+            "removed": removed,
+            "unchanged": diff.unchanged,
+            "enrolled": diff.enrolled,
+            "failed": failed,
+        })),
+    )
+        .into_response()
+}
@@ -0,0 +1,418 @@
+//! Self-service registration with invite codes (see [`crate::invites`]), along with the
+//! admin endpoints for minting and revoking the codes. Registering gives the user everything
+//! that would otherwise be added to the configuration file for them: an API key, a cookie
+//! server of their own (in multi-user mode), and the scopes and response filter set under
+//! `registration`.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{Duration, Utc};
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::api_keys::{format_scopes, parse_scopes, ApiScope};
+use crate::invites::{generate_invite_code, hash_invite_code};
+use crate::server::endpoints::db_error;
+use crate::server::types::{ApiErrorType, BodyMintInvite, BodyRegister, FieldError};
+use crate::server::validation::{check_range, require, ValidJson, Validate};
+use crate::types::{AddressPortInfo, WrapperState};
+
+/// The largest number of times that a single invite code can be used.
+const MAX_INVITE_USES: i64 = 1000;
+
+/// The message for a database error.
+const DB_ERROR: &str = "Failed to access invites";
+
+/// Creates the response for when there's no cookie server left to give a new user.
+fn no_cookie_server() -> Response {
+    ApiErrorType::from((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Every cookie server for new users has been given out, so no one can register until \
+         another is added to `registration.cookieServers`.",
+        None,
+    ))
+    .into_response()
+}
+
+/// Finds a cookie server in `registration.cookieServers` that no key has been given yet.
+fn free_cookie_server(s: &WrapperState) -> Option<AddressPortInfo> {
+    let taken = s.user_cookie_servers.read().unwrap();
+    s.registration
+        .cookie_servers
+        .iter()
+        .find(|server| {
+            !taken
+                .values()
+                .any(|t| t.address == server.address && t.port == server.port)
+        })
+        .cloned()
+}
+
+/// Gives a newly registered key its cookie server, if it needs one, and the scopes and
+/// response filter that registered keys start with.
+///
+/// # Returns
+/// The response to send instead, if the key couldn't be given everything.
+fn provision_key(
+    s: &WrapperState,
+    prefix: &str,
+    cookie_server: Option<AddressPortInfo>,
+) -> Option<Response> {
+    if let Some(server) = cookie_server {
+        match s
+            .schedule_db
+            .assign_cookie_server(prefix, &server.address, server.port)
+        {
+            Ok(true) => {
+                s.user_cookie_servers
+                    .write()
+                    .unwrap()
+                    .insert(prefix.to_owned(), server);
+            }
+            // Someone who registered at the same time was given it first
+            Ok(false) => return Some(no_cookie_server()),
+            Err(e) => return Some(db_error(e, DB_ERROR)),
+        }
+    }
+
+    if let Some(scopes) = &s.registration.scopes {
+        if let Err(e) = s.schedule_db.set_key_scopes(prefix, &format_scopes(scopes)) {
+            return Some(db_error(e, DB_ERROR));
+        }
+    }
+
+    let filter = s.registration.filter.as_ref().filter(|f| !f.is_empty())?;
+    s.schedule_db
+        .set_key_filter(prefix, &serde_json::to_string(filter).unwrap())
+        .err()
+        .map(|e| db_error(e, DB_ERROR))
+}
+
+/// Removes a key that couldn't be given everything it should have, along with what it was
+/// given, so that a key is never left half set up (e.g., with every scope). The use of the
+/// invite that it was registered with is given back.
+fn discard_key(s: &WrapperState, invite_id: i64, prefix: &str) {
+    s.auth_manager.delete_by_prefix(prefix);
+    s.user_cookie_servers.write().unwrap().remove(prefix);
+    let _ = s.schedule_db.release_cookie_server(prefix);
+    let _ = s.schedule_db.delete_key_scopes(prefix);
+    let _ = s.schedule_db.delete_key_filter(prefix);
+    if let Err(e) = s.schedule_db.unredeem_invite(invite_id, prefix) {
+        warn!("Couldn't give back a use of invite {invite_id}: {e}");
+    }
+}
+
+impl Validate for BodyRegister {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        require(&mut errors, "inviteCode", &self.invite_code);
+        errors
+    }
+}
+
+impl Validate for BodyMintInvite {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        check_range(&mut errors, "maxUses", self.max_uses, 1..=MAX_INVITE_USES);
+        if self.expires_in_days.is_some_and(|days| days <= 0) {
+            errors.push(FieldError::new("expiresInDays", "must be positive"));
+        }
+        errors
+    }
+}
+
+/// POST /register
+///
+/// Registers for an API key with an invite code. The key is only shown in this response.
+/// This doesn't need an API key, since it's how clients get one.
+///
+/// In multi-user mode, the key is also given one of `registration.cookieServers`; if none
+/// is left, registering fails without using up the invite, since a key without a cookie
+/// server couldn't fetch degree audits or use the credential vault. The same goes for any
+/// other failure after the invite is redeemed.
+pub async fn post_register(
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyRegister>,
+) -> Response {
+    info!("POST /register");
+
+    let cookie_server = if s.is_multi_user() {
+        match free_cookie_server(&s) {
+            Some(server) => Some(server),
+            None => return no_cookie_server(),
+        }
+    } else {
+        None
+    };
+
+    let (invite_id, invite_description) = match s
+        .schedule_db
+        .redeem_invite(&hash_invite_code(&body.invite_code))
+    {
+        Ok(Some(invite)) => invite,
+        Ok(None) => {
+            return ApiErrorType::from((
+                StatusCode::FORBIDDEN,
+                "This invite code is invalid, revoked, expired, or used up.",
+                None,
+            ))
+            .into_response();
+        }
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    let name = body
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .or(invite_description.as_deref())
+        .unwrap_or("Registered user");
+    let api_key = s
+        .auth_manager
+        .generate_api_key(Some(format!("{name} (invite {invite_id})")));
+
+    // The key is `prefix#key`, and only the prefix identifies it from here on
+    let prefix = api_key.split_once('#').map_or(api_key.as_str(), |(p, _)| p);
+    if let Err(e) = s.schedule_db.record_invite_redemption(invite_id, prefix) {
+        discard_key(&s, invite_id, prefix);
+        return db_error(e, DB_ERROR);
+    }
+
+    if let Some(resp) = provision_key(&s, prefix, cookie_server) {
+        discard_key(&s, invite_id, prefix);
+        return resp;
+    }
+
+    let scopes = match &s.registration.scopes {
+        Some(scopes) => parse_scopes(&format_scopes(scopes)).0,
+        None => ApiScope::ALL.to_vec(),
+    };
+
+    (
+        StatusCode::CREATED,
+        Json(json!({
+            "api_key": api_key,
+            "prefix": prefix,
+            "scopes": scopes,
+        })),
+    )
+        .into_response()
+}
+
+/// POST /admin/invites
+///
+/// Mints an invite code that can be used `maxUses` times (once, by default), optionally
+/// expiring after `expiresInDays` days. The code is only shown in this response.
+pub async fn post_invite(
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyMintInvite>,
+) -> Response {
+    info!("POST /admin/invites");
+
+    let max_uses = body.max_uses.unwrap_or(1);
+    let expires_at = body.expires_in_days.map(|days| {
+        (Utc::now() + Duration::days(days))
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    });
+
+    let code = generate_invite_code();
+    match s.schedule_db.insert_invite(
+        &hash_invite_code(&code),
+        body.description.as_deref(),
+        max_uses,
+        expires_at.as_deref(),
+    ) {
+        Ok(invite_id) => (
+            StatusCode::CREATED,
+            Json(json!({
+                "invite_id": invite_id,
+                "code": code,
+                "max_uses": max_uses,
+                "expires_at": expires_at,
+            })),
+        )
+            .into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// GET /admin/invites
+///
+/// Returns every invite, newest first. The codes themselves aren't stored, so they can't be
+/// shown.
+pub async fn get_invites(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("GET /admin/invites");
+
+    match s.schedule_db.get_invites() {
+        Ok(invites) => (StatusCode::OK, Json(invites)).into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+/// DELETE /admin/invites/:invite_id
+///
+/// Revokes an invite, so that it can't be used to register anymore. Keys that were already
+/// registered with it keep working.
+pub async fn delete_invite(
+    Path(invite_id): Path<i64>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("DELETE /admin/invites/{}", invite_id);
+
+    match s.schedule_db.revoke_invite(invite_id) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "No invite that hasn't been revoked was found.",
+            None,
+        ))
+        .into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+    use serde_json::Value;
+
+    use crate::types::tests::state;
+
+    const CODE: &str = "7KQM-X2TR-HC9A";
+
+    async fn register(s: &Arc<WrapperState>) -> (StatusCode, Value) {
+        let body = BodyRegister {
+            invite_code: CODE.to_owned(),
+            name: Some("Alice".to_owned()),
+        };
+        let res = post_register(State(s.clone()), ValidJson(body)).await;
+        let status = res.status();
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_register_provisions_key() {
+        let s = Arc::new(state(
+            "register-provision",
+            &["FA24"],
+            json!({
+                "registration": {
+                    "cookieServers": [{ "address": "127.0.0.1", "port": 3001 }],
+                    "scopes": ["schedule", "degree_audit"],
+                    "filter": { "deny": ["instructors"] },
+                },
+            }),
+        ));
+        s.schedule_db
+            .insert_invite(&hash_invite_code(CODE), None, 1, None)
+            .unwrap();
+
+        let (status, body) = register(&s).await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!(json!(["schedule", "degree_audit"]), body["scopes"]);
+        let prefix = body["prefix"].as_str().unwrap();
+
+        // The key can fetch its own degree audit through the cookie server it was given
+        let owner = s.audit_owner(Some(prefix)).unwrap();
+        assert_eq!("127.0.0.1:3001", owner.cookie_server());
+        assert_eq!(
+            vec![(prefix.to_owned(), "127.0.0.1".to_owned(), 3001)],
+            s.schedule_db.get_cookie_server_slots().unwrap()
+        );
+        assert_eq!(
+            Some("degree_audit,schedule".to_owned()),
+            s.schedule_db.get_key_scopes(prefix).unwrap()
+        );
+        assert!(s.schedule_db.get_key_filter(prefix).unwrap().is_some());
+
+        assert_eq!(1, s.schedule_db.get_invites().unwrap()[0].uses);
+    }
+
+    #[tokio::test]
+    async fn test_register_without_cookie_server() {
+        let s = Arc::new(state(
+            "register-no-server",
+            &["FA24"],
+            json!({
+                "registration": {
+                    "cookieServers": [{ "address": "127.0.0.1", "port": 3001 }],
+                },
+            }),
+        ));
+        s.schedule_db
+            .insert_invite(&hash_invite_code(CODE), None, 2, None)
+            .unwrap();
+
+        let (status, _) = register(&s).await;
+        assert_eq!(StatusCode::CREATED, status);
+
+        // The only cookie server is taken, so the second registration fails, without using
+        // the invite or leaving a key behind
+        let (status, _) = register(&s).await;
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, status);
+        assert_eq!(1, s.schedule_db.get_invites().unwrap()[0].uses);
+        assert_eq!(1, s.auth_manager.get_all_prefixes().len());
+    }
+
+    #[tokio::test]
+    async fn test_register_gives_invite_back_on_failure() {
+        let s = Arc::new(state(
+            "register-race",
+            &["FA24"],
+            json!({
+                "registration": {
+                    "cookieServers": [{ "address": "127.0.0.1", "port": 3001 }],
+                },
+            }),
+        ));
+        s.schedule_db
+            .insert_invite(&hash_invite_code(CODE), None, 1, None)
+            .unwrap();
+
+        // Someone registering at the same time takes the cookie server after it was found
+        // to be free, but before this registration could be given it
+        assert!(s
+            .schedule_db
+            .assign_cookie_server("other", "127.0.0.1", 3001)
+            .unwrap());
+
+        let (status, _) = register(&s).await;
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, status);
+        assert_eq!(0, s.schedule_db.get_invites().unwrap()[0].uses);
+        assert!(s.auth_manager.get_all_prefixes().is_empty());
+
+        // Once the server is free again, the invite can still be used
+        s.schedule_db.release_cookie_server("other").unwrap();
+        let (status, _) = register(&s).await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!(1, s.schedule_db.get_invites().unwrap()[0].uses);
+    }
+
+    #[tokio::test]
+    async fn test_register_single_user() {
+        let s = Arc::new(state("register-single-user", &["FA24"], json!({})));
+        s.schedule_db
+            .insert_invite(&hash_invite_code(CODE), None, 1, None)
+            .unwrap();
+
+        // Every key shares the tracker's cookie server, so none is given out
+        let (status, body) = register(&s).await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!(json!(ApiScope::ALL), body["scopes"]);
+        assert!(s.schedule_db.get_cookie_server_slots().unwrap().is_empty());
+        let prefix = body["prefix"].as_str().unwrap();
+        assert!(s.schedule_db.get_key_scopes(prefix).unwrap().is_none());
+
+        // The invite could only be used once
+        let (status, _) = register(&s).await;
+        assert_eq!(StatusCode::FORBIDDEN, status);
+    }
+}
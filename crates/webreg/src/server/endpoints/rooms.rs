@@ -0,0 +1,134 @@
+//! Endpoints for looking up how rooms are used.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use tracing::info;
+
+use crate::error::WebregError;
+use crate::schedule::{is_free, parse_clock_time, parse_weekday, MeetingSlot};
+use crate::server::types::{ApiErrorType, FreeRoomQueryStr};
+use crate::types::WrapperState;
+
+/// GET /live/:term/rooms/:building
+/// Returns every room in the building that's used in the term, along with the meetings held
+/// in each room
+pub async fn get_building_rooms(
+    Path((term, building)): Path<(String, String)>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/rooms/{}", term, building);
+
+    let meetings = match s
+        .schedule_db
+        .get_room_meetings(&term, Some(building.trim()))
+    {
+        Ok(meetings) => meetings,
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to fetch rooms")
+                .into_response();
+        }
+    };
+
+    if meetings.is_empty() {
+        return ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "No rooms found for building",
+            Some(building),
+        ))
+        .into_response();
+    }
+
+    let mut rooms: BTreeMap<String, Vec<_>> = BTreeMap::new();
+    for m in meetings {
+        rooms.entry(m.room).or_default().push(json!({
+            "subj_course_id": m.subj_course_id.trim(),
+            "section_code": m.section_code,
+            "type": m.meeting.meeting_type,
+            "days_type": m.meeting.meeting_days_type,
+            "days": m.meeting.meeting_days,
+            "start_hr": m.meeting.start_hr,
+            "start_min": m.meeting.start_min,
+            "end_hr": m.meeting.end_hr,
+            "end_min": m.meeting.end_min,
+            "instructors": m.meeting.instructors,
+        }));
+    }
+
+    let response: Vec<_> = rooms
+        .into_iter()
+        .map(|(room, meetings)| json!({ "room": room, "meetings": meetings }))
+        .collect();
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "building": building.trim().to_uppercase(),
+            "rooms": response,
+        })),
+    )
+        .into_response()
+}
+
+/// GET /live/:term/rooms/free?day=M&start=10:00&end=12:00
+/// Returns every room used in the term that has no weekly meetings during the given window.
+/// If `building` is given, only rooms in that building are considered
+pub async fn get_free_rooms(
+    Path(term): Path<String>,
+    Query(query): Query<FreeRoomQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/rooms/free", term);
+
+    let Some(day) = parse_weekday(&query.day) else {
+        return ApiErrorType::from((StatusCode::BAD_REQUEST, "Invalid day", Some(query.day)))
+            .into_response();
+    };
+
+    let (start, end) = match (parse_clock_time(&query.start), parse_clock_time(&query.end)) {
+        (Some(start), Some(end)) if start < end => (start, end),
+        _ => {
+            return ApiErrorType::from((
+                StatusCode::BAD_REQUEST,
+                "start and end must be given as HH:MM, with start before end",
+                None,
+            ))
+            .into_response();
+        }
+    };
+
+    let building = query
+        .building
+        .as_deref()
+        .map(str::trim)
+        .filter(|b| !b.is_empty());
+
+    let meetings = match s.schedule_db.get_room_meetings(&term, building) {
+        Ok(meetings) => meetings,
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to fetch rooms")
+                .into_response();
+        }
+    };
+
+    let mut rooms: BTreeMap<(String, String), Vec<MeetingSlot>> = BTreeMap::new();
+    for m in meetings {
+        let slots = rooms.entry((m.building, m.room)).or_default();
+        slots.extend(MeetingSlot::from_db(&m.meeting));
+    }
+
+    let free: Vec<_> = rooms
+        .into_iter()
+        .filter(|(_, slots)| is_free(slots, day, start, end))
+        .map(|((building, room), _)| json!({ "building": building, "room": room }))
+        .collect();
+
+    (StatusCode::OK, Json(free)).into_response()
+}
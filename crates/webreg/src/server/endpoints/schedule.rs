@@ -1,61 +1,272 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
     response::{IntoResponse, Response},
-    Json,
+    BoxError, Json,
 };
-use serde_json::json;
+use futures::stream::{self, StreamExt};
+use serde_json::{json, Value};
 use std::sync::Arc;
 use tracing::info;
 
-use crate::server::types::ApiErrorType;
+use crate::db::SYNC_STATE_PENDING_DELETE;
+use crate::error::WebregError;
+use crate::schedule::{
+    build_ical, canonical_csv, canonical_parquet, csv_row, find_conflicts, is_final, section_json,
+    DateRange, Exam, ExamKind, MeetingSlot, ParquetExport, ScheduledSection, SubSession,
+    CSV_HEADER,
+};
+use crate::server::endpoints::enroll_jobs::session_token;
+use crate::server::types::{
+    ApiErrorType, FinalsQueryStr, ScheduleDataQueryStr, ScheduleExportQueryStr, SectionListQueryStr,
+};
+use crate::server::util::{cache_headers, data_etag, etag_matches, not_modified};
+use crate::sessions::SESSION_TOKEN_HEADER;
 use crate::types::WrapperState;
 
-/// GET /live/:term/schedule_data
-/// Returns all schedule data (courses, sections, meetings) for a term
+/// Gets the ETag of a response built from a term's schedule data. The version is read
+/// before the data, so that a write in between leaves the response with an older tag
+/// rather than a newer one.
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `term`: The term.
+/// - `variant`: What sets the response apart from others built from the same data.
+///
+/// # Returns
+/// The ETag, or the error from reading the data version.
+fn schedule_data_etag(
+    s: &WrapperState,
+    term: &str,
+    variant: &str,
+) -> Result<String, rusqlite::Error> {
+    s.schedule_db
+        .get_data_version(term)
+        .map(|version| data_etag(term, version, variant))
+}
+
+/// GET /live/:term/schedule_data?include_cancelled=true
+/// Returns all schedule data (courses, sections, meetings) for a term. Sections that were
+/// cancelled after being scraped are left out unless `include_cancelled` is set, in which
+/// case they have a `cancelled_at` time. The response is tagged with the version of the
+/// term's data, so a client that sends the tag back in `If-None-Match` gets a
+/// `304 Not Modified` until the data changes
 pub async fn get_schedule_data(
+    headers: HeaderMap,
     Path(term): Path<String>,
+    Query(query): Query<ScheduleDataQueryStr>,
     State(s): State<Arc<WrapperState>>,
 ) -> Response {
     info!("GET /live/{}/schedule_data", term);
 
-    match s.schedule_db.get_all_sections_for_term(&term) {
-        Ok(data) => {
+    let variant = if query.include_cancelled {
+        "cancelled"
+    } else {
+        ""
+    };
+    let etag = match schedule_data_etag(&s, &term, variant) {
+        Ok(etag) => etag,
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to fetch schedule data")
+                .into_response()
+        }
+    };
+    if etag_matches(&headers, &etag) {
+        return not_modified(etag);
+    }
+
+    let data = s
+        .schedule_db
+        .get_all_sections_for_term(&term, query.include_cancelled)
+        .and_then(|data| Ok((data, s.schedule_db.get_term_section_notes(&term)?)));
+    match data {
+        Ok((data, mut notes)) => {
             let response: Vec<_> = data
                 .into_iter()
                 .map(|(section, meetings)| {
-                    json!({
-                        "section_id": section.section_id,
-                        "section_code": section.section_code,
-                        "meetings": meetings.into_iter().map(|m| {
-                            json!({
-                                "type": m.meeting_type,
-                                "days_type": m.meeting_days_type,
-                                "days": m.meeting_days,
-                                "start_hr": m.start_hr,
-                                "start_min": m.start_min,
-                                "end_hr": m.end_hr,
-                                "end_min": m.end_min,
-                                "building": m.building,
-                                "room": m.room,
-                                "instructors": m.instructors,
-                            })
-                        }).collect::<Vec<_>>()
-                    })
+                    let section_notes = notes.remove(&section.section_id);
+                    section_json(section, meetings, section_notes)
                 })
                 .collect();
 
-            (StatusCode::OK, Json(response)).into_response()
+            (StatusCode::OK, cache_headers(etag), Json(response)).into_response()
         }
-        Err(e) => ApiErrorType::from((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to fetch schedule data",
-            Some(e.to_string()),
-        ))
-        .into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to fetch schedule data")
+            .into_response(),
     }
 }
 
+/// The number of sections read from the database at a time when exporting schedule data.
+const EXPORT_PAGE_SECTIONS: usize = 500;
+/// The header that a canonical export's hash is returned in.
+const DATASET_HASH_HEADER: &str = "x-dataset-hash";
+
+/// GET /live/:term/schedule_data/export?format=csv&canonical=true
+/// Returns every meeting in the term as a download, one row per meeting (sections without
+/// meetings get a single row), as CSV (the default) or, with `format=parquet`, as a Parquet
+/// file with the same columns. The rows are read from the database a page at a time while
+/// the response is being sent, so large terms are never held in memory; each page of a
+/// Parquet file is its own row group
+///
+/// With `canonical`, the rows are sorted so that the same data always gives the same
+/// bytes, and the SHA-256 hash of the export is returned in the `X-Dataset-Hash` header.
+/// This is built in memory, since the hash has to be known before the response is sent
+///
+/// As with `schedule_data`, the response is tagged with the version of the term's data and
+/// `If-None-Match` is honored
+pub async fn get_schedule_data_export(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    Query(query): Query<ScheduleExportQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/schedule_data/export", term);
+
+    let format = query.format.as_deref().unwrap_or("csv");
+    let content_type = match format {
+        "csv" => "text/csv; charset=utf-8",
+        "parquet" => "application/vnd.apache.parquet",
+        other => {
+            return ApiErrorType::from((
+                StatusCode::BAD_REQUEST,
+                "Unsupported format; use csv or parquet",
+                Some(other.to_owned()),
+            ))
+            .into_response();
+        }
+    };
+
+    let variant = if query.canonical {
+        format!("{format}-canonical")
+    } else {
+        format.to_owned()
+    };
+    let etag = match schedule_data_etag(&s, &term, &variant) {
+        Ok(etag) => etag,
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to fetch schedule data")
+                .into_response()
+        }
+    };
+    if etag_matches(&headers, &etag) {
+        return not_modified(etag);
+    }
+
+    let [etag_header, cache_control] = cache_headers(etag);
+    let disposition = format!("attachment; filename=\"schedule_data_{term}.{format}\"");
+    if query.canonical {
+        let export = s
+            .schedule_db
+            .get_schedule_export(&term)
+            .map_err(WebregError::from)
+            .and_then(|rows| match format {
+                "parquet" => canonical_parquet(&rows).map_err(|e| WebregError::Internal {
+                    message: "Failed to write the Parquet file".into(),
+                    context: Some(e.to_string()),
+                    code: None,
+                }),
+                _ => {
+                    let (csv, hash) = canonical_csv(&rows);
+                    Ok((csv.into_bytes(), hash))
+                }
+            });
+        return match export {
+            Ok((file, hash)) => (
+                StatusCode::OK,
+                [
+                    (header::CONTENT_TYPE, content_type.to_owned()),
+                    (header::CONTENT_DISPOSITION, disposition),
+                    (HeaderName::from_static(DATASET_HASH_HEADER), hash),
+                    etag_header,
+                    cache_control,
+                ],
+                file,
+            )
+                .into_response(),
+            Err(e) => e
+                .with_message("Failed to export schedule data")
+                .into_response(),
+        };
+    }
+
+    let body = if format == "parquet" {
+        parquet_export_body(s, term)
+    } else {
+        csv_export_body(s, term)
+    };
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type.to_owned()),
+            (header::CONTENT_DISPOSITION, disposition),
+            etag_header,
+            cache_control,
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Streams a term's rows as CSV, a page at a time.
+fn csv_export_body(s: Arc<WrapperState>, term: String) -> Body {
+    let header = stream::once(async { Ok(CSV_HEADER.to_owned()) });
+    let rows = stream::try_unfold(0, move |after_section_pk| {
+        let s = s.clone();
+        let term = term.clone();
+        async move {
+            let page = s.schedule_db.get_schedule_export_page(
+                &term,
+                after_section_pk,
+                EXPORT_PAGE_SECTIONS,
+            )?;
+            let Some(last) = page.last() else {
+                return Ok::<_, rusqlite::Error>(None);
+            };
+
+            let next = last.section_id_pk;
+            Ok(Some((page.iter().map(csv_row).collect::<String>(), next)))
+        }
+    });
+
+    Body::from_stream(header.chain(rows))
+}
+
+/// Streams a term's rows as Parquet, writing each page as a row group. The file's footer is
+/// sent after the last page.
+fn parquet_export_body(s: Arc<WrapperState>, term: String) -> Body {
+    let export = ParquetExport::new().map_err(BoxError::from);
+    let rows = stream::try_unfold((Some(export), 0), move |(export, after_section_pk)| {
+        let s = s.clone();
+        let term = term.clone();
+        async move {
+            let Some(export) = export else {
+                return Ok(None);
+            };
+
+            let mut export = export?;
+            let page = s.schedule_db.get_schedule_export_page(
+                &term,
+                after_section_pk,
+                EXPORT_PAGE_SECTIONS,
+            )?;
+            match page.last() {
+                Some(last) => {
+                    let next = last.section_id_pk;
+                    let bytes = export.write_rows(&page)?;
+                    Ok::<_, BoxError>(Some((bytes, (Some(Ok(export)), next))))
+                }
+                None => Ok(Some((export.finish()?, (None, after_section_pk)))),
+            }
+        }
+    });
+
+    Body::from_stream(rows)
+}
+
 /// GET /live/:term/schedule_data/:section_id
 /// Returns meetings for a specific section
 pub async fn get_section_meetings(
@@ -64,7 +275,13 @@ pub async fn get_section_meetings(
 ) -> Response {
     info!("GET /live/{}/schedule_data/{}", term, section_id);
 
-    match s.schedule_db.get_meetings_for_section(&section_id) {
+    // The same section ID can be used in other terms, and a cancelled section has no meetings
+    let meetings = s
+        .schedule_db
+        .get_section(&term, &section_id)
+        .map(|section| section.map(|(_, _, meetings)| meetings).unwrap_or_default());
+
+    match meetings {
         Ok(meetings) => {
             let response: Vec<_> = meetings
                 .into_iter()
@@ -80,17 +297,443 @@ pub async fn get_section_meetings(
                         "building": m.building,
                         "room": m.room,
                         "instructors": m.instructors,
+                        "start_date": m.start_date,
+                        "end_date": m.end_date,
                     })
                 })
                 .collect();
 
             (StatusCode::OK, Json(response)).into_response()
         }
-        Err(e) => ApiErrorType::from((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to fetch meetings",
-            Some(e.to_string()),
-        ))
-        .into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to fetch meetings")
+            .into_response(),
+    }
+}
+
+/// GET /live/:term/heatmap
+/// Returns the number of concurrent class seats in each building for every hour of the
+/// week, based on the term's weekly meetings
+pub async fn get_heatmap(Path(term): Path<String>, State(s): State<Arc<WrapperState>>) -> Response {
+    info!("GET /live/{}/heatmap", term);
+
+    match s.heatmap(&term) {
+        Ok(heatmap) => (StatusCode::OK, Json(heatmap.as_ref())).into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to compute heatmap")
+            .into_response(),
+    }
+}
+
+/// GET /live/:term/finals?sections=123456,234567
+/// Returns the final exam (and any midterms) of each of the given sections, along with
+/// every pair of sections whose finals conflict
+pub async fn get_finals(
+    Path(term): Path<String>,
+    Query(query): Query<FinalsQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/finals", term);
+
+    let section_ids: Vec<String> = query
+        .sections
+        .split(',')
+        .map(|id| id.trim().to_owned())
+        .filter(|id| !id.is_empty())
+        .collect();
+    let exams = match s.schedule_db.get_section_exams(&term, &section_ids) {
+        Ok(exams) => exams,
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to fetch exams")
+                .into_response()
+        }
+    };
+
+    let sub_session = s
+        .term(&term)
+        .map(|t| t.sub_session)
+        .unwrap_or_else(|| SubSession::from_term(&term));
+    let mut response = vec![];
+    let mut finals = vec![];
+    for section_id in &section_ids {
+        let slots: Vec<_> = exams
+            .iter()
+            .filter(|(id, _, _)| id == section_id)
+            .filter_map(|(_, _, meeting)| MeetingSlot::from_db(meeting))
+            .collect();
+        let course = exams
+            .iter()
+            .find(|(id, _, _)| id == section_id)
+            .map(|(_, course, _)| course.trim());
+        let section_exams: Vec<_> = slots.iter().filter_map(Exam::from_slot).collect();
+
+        response.push(json!({
+            "section_id": section_id,
+            "course": course,
+            "final": section_exams.iter().find(|e| e.kind == ExamKind::Final),
+            "midterms": section_exams
+                .iter()
+                .filter(|e| e.kind == ExamKind::Midterm)
+                .collect::<Vec<_>>(),
+        }));
+
+        finals.push(ScheduledSection {
+            sub_session,
+            term: term.clone(),
+            title: course.unwrap_or(section_id).to_owned(),
+            section_id: section_id.clone(),
+            slots: slots.into_iter().filter(is_final).collect(),
+        });
+    }
+
+    let conflicts = find_conflicts(&finals);
+    (
+        StatusCode::OK,
+        Json(json!({
+            "sections": response,
+            "has_conflicts": !conflicts.is_empty(),
+            "conflicts": conflicts,
+        })),
+    )
+        .into_response()
+}
+
+/// Looks up the given sections' meetings so they can be checked or exported.
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `sections_param`: The sections, each given as `TERM:SECTION_ID` and separated by commas.
+/// - `events_session`: The session whose custom events in the sections' terms should be
+///   added as well, if any.
+///
+/// # Returns
+/// The sections along with a summary of each, or an error if any section couldn't be
+/// found.
+pub(super) fn load_scheduled_sections(
+    s: &WrapperState,
+    sections_param: &str,
+    events_session: Option<&str>,
+) -> Result<(Vec<ScheduledSection>, Vec<Value>), ApiErrorType<'static>> {
+    let mut sections = vec![];
+    let mut section_info = vec![];
+    for entry in sections_param.split(',').filter(|e| !e.trim().is_empty()) {
+        let Some((term, section_id)) = entry.trim().split_once(':') else {
+            return Err(ApiErrorType::from((
+                StatusCode::BAD_REQUEST,
+                "Each section must be given as TERM:SECTION_ID",
+                Some(entry.to_owned()),
+            )));
+        };
+
+        let term = term.to_uppercase();
+        let (subj_course_id, db_section, meetings) =
+            match s.schedule_db.get_section(&term, section_id) {
+                Ok(Some(data)) => data,
+                Ok(None) => {
+                    return Err(ApiErrorType::from((
+                        StatusCode::NOT_FOUND,
+                        "No schedule data found for section",
+                        Some(entry.to_owned()),
+                    )));
+                }
+                Err(e) => {
+                    return Err(WebregError::from(e)
+                        .with_message("Failed to fetch schedule data")
+                        .into());
+                }
+            };
+
+        // Prefer the dates stored with the section, falling back to the term's dates.
+        // Individual meetings may still have their own, narrower dates.
+        let dates = match (&db_section.start_date, &db_section.end_date) {
+            (Some(start), Some(end)) => DateRange::parse(start, end),
+            _ => None,
+        }
+        .or_else(|| s.term(&term).and_then(|t| t.date_range));
+
+        let sub_session = s
+            .term(&term)
+            .map(|t| t.sub_session)
+            .unwrap_or_else(|| SubSession::from_term(&term));
+
+        section_info.push(json!({
+            "term": term,
+            "section_id": db_section.section_id,
+            "sub_session": sub_session.as_str(),
+            "start_date": dates.map(|d| d.start.format("%Y-%m-%d").to_string()),
+            "end_date": dates.map(|d| d.end.format("%Y-%m-%d").to_string()),
+        }));
+
+        sections.push(ScheduledSection {
+            sub_session,
+            term,
+            title: format!("{} ({})", subj_course_id.trim(), db_section.section_code),
+            section_id: db_section.section_id,
+            slots: meetings
+                .iter()
+                .filter_map(MeetingSlot::from_db)
+                .map(|mut slot| {
+                    slot.dates = slot.dates.or(dates);
+                    slot
+                })
+                .collect(),
+        });
+    }
+
+    if let Some(token) = events_session {
+        let mut terms: Vec<String> = sections.iter().map(|s| s.term.clone()).collect();
+        terms.sort();
+        terms.dedup();
+        for term in terms {
+            let events = s
+                .schedule_db
+                .get_custom_events(&term, token)
+                .map_err(|e| WebregError::from(e).with_message("Failed to fetch custom events"))?;
+
+            let dates = s.term(&term).and_then(|t| t.date_range);
+            let sub_session = s
+                .term(&term)
+                .map(|t| t.sub_session)
+                .unwrap_or_else(|| SubSession::from_term(&term));
+            for event in events
+                .iter()
+                .filter(|e| e.sync_state != SYNC_STATE_PENDING_DELETE)
+            {
+                sections.push(ScheduledSection {
+                    sub_session,
+                    term: term.clone(),
+                    title: event.name.clone(),
+                    section_id: format!("event-{}", event.event_id),
+                    slots: MeetingSlot::from_event(event)
+                        .map(|mut slot| {
+                            slot.dates = dates;
+                            slot
+                        })
+                        .into_iter()
+                        .collect(),
+                });
+            }
+        }
+    }
+
+    Ok((sections, section_info))
+}
+
+/// Gets the session whose custom events should be added to the sections, if the request
+/// asked for them. Custom events are kept per session, so asking for them needs a session
+/// token.
+fn events_session<'a>(
+    headers: &'a HeaderMap,
+    query: &SectionListQueryStr,
+) -> Result<Option<&'a str>, ApiErrorType<'static>> {
+    if !query.include_events.unwrap_or(false) {
+        return Ok(None);
+    }
+
+    match session_token(headers) {
+        Some(token) => Ok(Some(token)),
+        None => Err(ApiErrorType::from((
+            StatusCode::BAD_REQUEST,
+            "Including custom events needs a session token.",
+            Some(SESSION_TOKEN_HEADER.to_owned()),
+        ))),
+    }
+}
+
+/// GET /schedule_conflicts?sections=S124:123456,S224:234567
+/// Returns every pair of meetings among the given sections that conflict. Sections may come
+/// from different terms; meetings that run over different weeks (e.g., in different summer
+/// sub-sessions, or in different halves of a quarter) never conflict
+pub async fn get_schedule_conflicts(
+    headers: HeaderMap,
+    Query(query): Query<SectionListQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /schedule_conflicts");
+
+    let events_session = match events_session(&headers, &query) {
+        Ok(token) => token,
+        Err(e) => return e.into_response(),
+    };
+    let (sections, section_info) =
+        match load_scheduled_sections(&s, &query.sections, events_session) {
+            Ok(data) => data,
+            Err(e) => return e.into_response(),
+        };
+
+    let conflicts = find_conflicts(&sections);
+    (
+        StatusCode::OK,
+        Json(json!({
+            "sections": section_info,
+            "has_conflicts": !conflicts.is_empty(),
+            "has_final_conflicts": conflicts.iter().any(|c| c.final_exam),
+            "conflicts": conflicts,
+        })),
+    )
+        .into_response()
+}
+
+/// GET /schedule_ical?sections=FA24:123456,FA24:234567
+/// Returns the meetings of the given sections as an iCalendar (`.ics`) file. Meetings that
+/// only run for part of the term only appear during those weeks
+pub async fn get_schedule_ical(
+    headers: HeaderMap,
+    Query(query): Query<SectionListQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /schedule_ical");
+
+    let events_session = match events_session(&headers, &query) {
+        Ok(token) => token,
+        Err(e) => return e.into_response(),
+    };
+    let (sections, _) = match load_scheduled_sections(&s, &query.sections, events_session) {
+        Ok(data) => data,
+        Err(e) => return e.into_response(),
+    };
+
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"schedule.ics\"",
+            ),
+        ],
+        build_ical(&sections),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+    use webweg::types::{CourseSection, Meeting, MeetingDay};
+
+    use crate::schedule::MeetingDates;
+
+    fn section(section_id: &str, section_code: &str) -> CourseSection {
+        CourseSection {
+            subj_course_id: "CSE 100".to_owned(),
+            section_id: section_id.to_owned(),
+            section_code: section_code.to_owned(),
+            all_instructors: vec!["Doe, Jane".to_owned()],
+            available_seats: 10,
+            enrolled_ct: 90,
+            total_seats: 100,
+            waitlist_ct: 0,
+            meetings: vec![Meeting {
+                meeting_type: "LE".to_owned(),
+                meeting_days: MeetingDay::Repeated(vec!["M".to_owned(), "W".to_owned()]),
+                start_min: 0,
+                start_hr: 9,
+                end_min: 50,
+                end_hr: 9,
+                building: "CENTR".to_owned(),
+                room: "101".to_owned(),
+                instructors: vec!["Doe, Jane".to_owned()],
+            }],
+            is_visible: true,
+        }
+    }
+
+    async fn export(s: &Arc<WrapperState>, format: &str, canonical: bool) -> Response {
+        let query = ScheduleExportQueryStr {
+            format: Some(format.to_owned()),
+            canonical,
+        };
+        get_schedule_data_export(
+            HeaderMap::new(),
+            Path("FA24".to_owned()),
+            Query(query),
+            State(s.clone()),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_parquet_export() {
+        let s = Arc::new(crate::types::tests::state(
+            "parquet-export",
+            &["FA24"],
+            json!({}),
+        ));
+        s.schedule_db
+            .insert_course_with_sections(
+                "FA24",
+                vec![section("123456", "A01"), section("123457", "A02")],
+                None,
+                &MeetingDates::default(),
+            )
+            .unwrap();
+
+        for canonical in [false, true] {
+            let res = export(&s, "parquet", canonical).await;
+            assert_eq!(StatusCode::OK, res.status());
+            assert_eq!(
+                "application/vnd.apache.parquet",
+                res.headers()[header::CONTENT_TYPE]
+            );
+            assert_eq!(canonical, res.headers().contains_key(DATASET_HASH_HEADER));
+
+            let file = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+            let reader = SerializedFileReader::new(file).unwrap();
+            let rows: Vec<_> = reader
+                .get_row_iter(None)
+                .unwrap()
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(2, rows.len());
+            assert_eq!("A01", rows[0].get_string(3).unwrap());
+            assert_eq!("CENTR", rows[1].get_string(13).unwrap());
+        }
+
+        let res = export(&s, "xlsx", false).await;
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+    }
+
+    async fn meetings(s: &Arc<WrapperState>, term: &str) -> Value {
+        let res = get_section_meetings(
+            Path((term.to_owned(), "123456".to_owned())),
+            State(s.clone()),
+        )
+        .await;
+        assert_eq!(StatusCode::OK, res.status());
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_section_meetings_by_term() {
+        let s = Arc::new(crate::types::tests::state(
+            "section-meetings",
+            &["FA24", "WI25"],
+            json!({}),
+        ));
+        let mut other = section("123456", "A01");
+        other.meetings[0].building = "PCYNH".to_owned();
+        for (term, section) in [("FA24", section("123456", "A01")), ("WI25", other)] {
+            s.schedule_db
+                .insert_course_with_sections(term, vec![section], None, &MeetingDates::default())
+                .unwrap();
+        }
+
+        let fa24 = meetings(&s, "FA24").await;
+        assert_eq!(1, fa24.as_array().unwrap().len());
+        assert_eq!("CENTR", fa24[0]["building"]);
+        assert_eq!("PCYNH", meetings(&s, "WI25").await[0]["building"]);
+
+        // Cancelled sections don't have meetings
+        s.schedule_db
+            .cancel_unseen_sections("WI25", "9999-12-31")
+            .unwrap();
+        assert_eq!(json!([]), meetings(&s, "WI25").await);
+        assert_eq!(1, meetings(&s, "FA24").await.as_array().unwrap().len());
     }
 }
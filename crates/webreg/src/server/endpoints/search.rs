@@ -0,0 +1,447 @@
+//! Endpoints for searching courses with filters that WebReg doesn't support on its own.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use tracing::info;
+use webweg::types::WrapperError;
+
+use crate::alternatives::rank_alternatives;
+use crate::db::normalize_course_code;
+use crate::error::WebregError;
+use crate::retry::Idempotency;
+use crate::schedule::{parse_clock_time, parse_weekday, section_json};
+use crate::search::{
+    merge_cross_listed, paginate, CourseCandidate, GeScope, RawSearchResult, SearchFilters,
+    SearchSort, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE,
+};
+use crate::semantic::{DEFAULT_SEMANTIC_LIMIT, MAX_SEMANTIC_LIMIT};
+use crate::server::types::{
+    AlternativesQueryStr, ApiErrorType, BodySearchType, SearchV2QueryStr, SemanticSearchQueryStr,
+};
+use crate::server::util::term_info;
+use crate::types::WrapperState;
+
+/// The number of alternatives to a section returned if the caller doesn't say.
+const DEFAULT_ALTERNATIVES_LIMIT: usize = 10;
+/// The largest number of alternatives to a section that can be returned.
+const MAX_ALTERNATIVES_LIMIT: usize = 50;
+
+/// Splits a comma-separated list, dropping empty entries.
+fn split_list(list: &Option<String>) -> Option<Vec<String>> {
+    list.as_deref().map(|l| {
+        l.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_owned)
+            .collect()
+    })
+}
+
+/// Builds the filters for a search from its query string.
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `query`: The query string.
+///
+/// # Returns
+/// The filters, or an error message and its context if the query string is invalid.
+fn build_filters(
+    s: &WrapperState,
+    query: &SearchV2QueryStr,
+) -> Result<SearchFilters, (&'static str, Option<String>)> {
+    let mut days = vec![];
+    for day in split_list(&query.days).unwrap_or_default() {
+        match parse_weekday(&day) {
+            Some(d) => days.push(d),
+            None => return Err(("Invalid day", Some(day))),
+        }
+    }
+
+    let window = match (&query.start_time, &query.end_time) {
+        (None, None) => None,
+        (start, end) => {
+            let start = start.as_deref().map_or(Some(0), parse_clock_time);
+            let end = end.as_deref().map_or(Some(24 * 60), parse_clock_time);
+            match (start, end) {
+                (Some(start), Some(end)) if start < end => Some((start, end)),
+                _ => return Err(("startTime and endTime must be HH:MM, in order", None)),
+            }
+        }
+    };
+
+    let ge = match query.ge_category.as_deref().map(str::trim) {
+        Some(category) if !category.is_empty() => {
+            let college = query.college.as_deref().map(str::trim);
+            match GeScope::from_config(&s.requirements(), college, category) {
+                Some(scope) => Some(scope),
+                None => {
+                    return Err((
+                        "Unknown general education category",
+                        Some(category.to_owned()),
+                    ))
+                }
+            }
+        }
+        _ => None,
+    };
+
+    Ok(SearchFilters {
+        only_open: query.only_open.unwrap_or(false),
+        instructor: query
+            .instructor
+            .as_deref()
+            .map(|i| i.trim().to_lowercase())
+            .filter(|i| !i.is_empty()),
+        days,
+        window,
+        min_units: query.min_units,
+        max_units: query.max_units,
+        ge,
+    })
+}
+
+/// GET /live/:term/search/v2?subjects=CSE&onlyOpen=true&days=Tu,Th&startTime=09:00
+/// Searches for courses using WebReg's search, then narrows down the results using the
+/// sections stored for the term. Supports filtering by open seats, instructor, meeting days,
+/// time of day, units, and general education category, sorting by `course`, `units`, or
+/// `seats`, and cursor-based pagination. Cross-listed courses are merged into one result,
+/// which lists the other codes that it's offered as
+pub async fn get_search_v2(
+    Path(term): Path<String>,
+    Query(query): Query<SearchV2QueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/search/v2", term);
+
+    let filters = match build_filters(&s, &query) {
+        Ok(filters) => filters,
+        Err((msg, context)) => {
+            return ApiErrorType::from((StatusCode::BAD_REQUEST, msg, context)).into_response();
+        }
+    };
+
+    let Some(sort) = query
+        .sort
+        .as_deref()
+        .map_or(Some(SearchSort::default()), SearchSort::parse)
+    else {
+        return ApiErrorType::from((
+            StatusCode::BAD_REQUEST,
+            "sort must be one of course, units, or seats",
+            query.sort,
+        ))
+        .into_response();
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return ApiErrorType::from((
+            StatusCode::BAD_REQUEST,
+            "limit must be between 1 and 100.",
+            None,
+        ))
+        .into_response();
+    }
+
+    let search = BodySearchType::SearchAdvanced {
+        subjects: split_list(&query.subjects),
+        courses: None,
+        departments: split_list(&query.departments),
+        instructor: None,
+        title: query.title.clone(),
+        only_open: query.only_open,
+        start_hour: None,
+        start_min: None,
+        end_hour: None,
+        end_min: None,
+        days: None,
+        level_filter: None,
+    };
+
+    let info = match term_info(&s, &term) {
+        Ok(info) => info,
+        Err(e) => return e.into_response(),
+    };
+    let requester = info.wrapper.req(term.as_str()).raw();
+    let found = match s
+        .webreg_retry
+        .run(Idempotency::Idempotent, || {
+            requester.search_courses(search.clone().into())
+        })
+        .await
+        .and_then(|body| {
+            serde_json::from_str::<Vec<RawSearchResult>>(&body).map_err(WrapperError::SerdeError)
+        }) {
+        Ok(found) => found,
+        Err(e) => return ApiErrorType::from(e).into_response(),
+    };
+
+    let cross_listings = match s.schedule_db.get_cross_listings(Some(&term)) {
+        Ok(cross_listings) => cross_listings,
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to fetch cross-listed courses")
+                .into_response();
+        }
+    };
+
+    let mut sections = match s.schedule_db.get_term_sections(&term) {
+        Ok(sections) => sections,
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to fetch sections")
+                .into_response();
+        }
+    };
+
+    let courses = found
+        .into_iter()
+        .map(|item| {
+            let subj_course_id =
+                normalize_course_code(&format!("{} {}", item.subj_code, item.course_code));
+            CourseCandidate {
+                sections: sections.remove(&subj_course_id).unwrap_or_default(),
+                subj_course_id,
+                title: item.course_title.trim().to_owned(),
+                min_units: item.min_units,
+                max_units: item.max_units,
+                also_offered_as: vec![],
+            }
+        })
+        .collect();
+
+    let mut courses = filters.apply(merge_cross_listed(courses, &cross_listings));
+    sort.sort(&mut courses);
+    let total = courses.len();
+
+    let Some((page, next_cursor)) = paginate(courses, query.cursor.as_deref(), limit) else {
+        return ApiErrorType::from((StatusCode::BAD_REQUEST, "Invalid cursor", query.cursor))
+            .into_response();
+    };
+
+    let results: Vec<_> = page
+        .iter()
+        .map(|course| {
+            let sections: Vec<_> = course
+                .sections
+                .iter()
+                .map(|section| {
+                    let meetings: Vec<_> = section
+                        .meetings
+                        .iter()
+                        .map(|m| {
+                            json!({
+                                "type": m.meeting_type,
+                                "days_type": m.meeting_days_type,
+                                "days": m.meeting_days,
+                                "start_hr": m.start_hr,
+                                "start_min": m.start_min,
+                                "end_hr": m.end_hr,
+                                "end_min": m.end_min,
+                                "building": m.building,
+                                "room": m.room,
+                                "instructors": m.instructors,
+                            })
+                        })
+                        .collect();
+
+                    json!({
+                        "listed_as": normalize_course_code(&section.subj_course_id),
+                        "section_id": section.section.section_id,
+                        "section_code": section.section.section_code,
+                        "total_seats": section.total_seats,
+                        "available_seats": (section.total_seats - section.enrolled_ct).max(0),
+                        "meetings": meetings,
+                    })
+                })
+                .collect();
+
+            json!({
+                "subj_course_id": course.subj_course_id,
+                "title": course.title,
+                "min_units": course.min_units,
+                "max_units": course.max_units,
+                "also_offered_as": course.also_offered_as,
+                "available_seats": course.available_seats(),
+                "sections": sections,
+            })
+        })
+        .collect();
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "total": total,
+            "next_cursor": next_cursor,
+            "results": results,
+        })),
+    )
+        .into_response()
+}
+
+/// GET /live/:term/search_semantic?q=machine learning with lots of math&offeredOnly=true
+/// Returns the catalog courses whose descriptions are most similar to the query, most similar
+/// first. By default, only courses offered in the term are included. Needs `semanticSearch`
+/// to be set
+pub async fn get_search_semantic(
+    Path(term): Path<String>,
+    Query(query): Query<SemanticSearchQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/search_semantic", term);
+
+    let Some(index) = &s.semantic_index else {
+        return ApiErrorType::from((
+            StatusCode::NOT_IMPLEMENTED,
+            "Semantic search isn't enabled",
+            None,
+        ))
+        .into_response();
+    };
+
+    let q = query.q.trim();
+    if q.is_empty() {
+        return ApiErrorType::from((StatusCode::BAD_REQUEST, "The query is empty", None))
+            .into_response();
+    }
+
+    let offered = match s.schedule_db.get_term_course_codes(&term) {
+        Ok(offered) => offered,
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to fetch the term's courses")
+                .into_response()
+        }
+    };
+
+    let offered_only = query.offered_only.unwrap_or(true);
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SEMANTIC_LIMIT)
+        .clamp(1, MAX_SEMANTIC_LIMIT);
+    match index
+        .search(&s.client, q, limit, |code| {
+            !offered_only || offered.contains(code)
+        })
+        .await
+    {
+        Ok(matches) => {
+            let results: Vec<_> = matches
+                .into_iter()
+                .map(|m| {
+                    json!({
+                        "offered": offered.contains(&m.code),
+                        "code": m.code,
+                        "title": m.title,
+                        "score": m.score,
+                    })
+                })
+                .collect();
+
+            (
+                StatusCode::OK,
+                Json(json!({
+                    "model": index.model(),
+                    "indexed": index.len(),
+                    "results": results,
+                })),
+            )
+                .into_response()
+        }
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to run the semantic search")
+            .into_response(),
+    }
+}
+
+/// GET /live/:term/alternatives/:section_id?limit=10
+/// Returns the other sections of a section's course, and of the courses that it's
+/// cross-listed with, that had open seats when the term was last scraped. They're ranked by
+/// how similar their meeting times and instructors are to the section's (see
+/// [`crate::alternatives`])
+pub async fn get_section_alternatives(
+    Path((term, section_id)): Path<(String, String)>,
+    Query(query): Query<AlternativesQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /live/{}/alternatives/{}", term, section_id);
+
+    let data = s
+        .schedule_db
+        .get_term_sections(&term)
+        .and_then(|sections| Ok((sections, s.schedule_db.get_cross_listings(Some(&term))?)));
+    let (sections, listings) = match data {
+        Ok(data) => data,
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to fetch the term's sections")
+                .into_response()
+        }
+    };
+
+    let Some((course, target)) = sections.iter().find_map(|(course, sections)| {
+        sections
+            .iter()
+            .find(|t| t.section.section_id == section_id)
+            .map(|target| (course, target))
+    }) else {
+        return ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "This section isn't in the term's schedule data.",
+            Some(section_id),
+        ))
+        .into_response();
+    };
+
+    let also_offered_as = listings.also_offered_as(course);
+    let candidates = sections
+        .get(course)
+        .into_iter()
+        .flatten()
+        .map(|section| (section, false))
+        .chain(
+            also_offered_as
+                .iter()
+                .filter_map(|code| sections.get(code))
+                .flatten()
+                .map(|section| (section, true)),
+        );
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_ALTERNATIVES_LIMIT)
+        .clamp(1, MAX_ALTERNATIVES_LIMIT);
+    let alternatives: Vec<_> = rank_alternatives(target, candidates)
+        .into_iter()
+        .take(limit)
+        .map(|alternative| {
+            let section = alternative.section;
+            json!({
+                "course": normalize_course_code(&section.subj_course_id),
+                "cross_listed": alternative.cross_listed,
+                "available_seats": alternative.available_seats,
+                "total_seats": section.total_seats,
+                "similarity": alternative.similarity,
+                "same_instructor": alternative.same_instructor,
+                "section": section_json(section.section.clone(), section.meetings.clone(), None),
+            })
+        })
+        .collect();
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "section_id": target.section.section_id,
+            "section_code": target.section.section_code,
+            "course": course,
+            "also_offered_as": also_offered_as,
+            "alternatives": alternatives,
+        })),
+    )
+        .into_response()
+}
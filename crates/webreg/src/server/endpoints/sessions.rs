@@ -0,0 +1,315 @@
+//! Endpoints for registering and managing students' WebReg sessions.
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::{Extensions, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use tracing::info;
+
+use crate::db::{ENROLL_JOB_NEEDS_SESSION, ENROLL_JOB_PENDING};
+use crate::server::endpoints::db_error;
+use crate::server::endpoints::enroll_jobs::MAX_PENDING_JOBS;
+use crate::server::types::{ApiErrorType, BodySessionCookies, BodySessionReattach, FieldError};
+use crate::server::validation::{require, ValidJson, Validate};
+use crate::sessions::SESSION_TOKEN_HEADER;
+use crate::types::WrapperState;
+
+/// The message for a database error.
+const DB_ERROR: &str = "Failed to access the session's data";
+
+/// Gets the session token from the request headers.
+pub(super) fn session_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(SESSION_TOKEN_HEADER)
+        .and_then(|t| t.to_str().ok())
+}
+
+/// Gets the session token from the request headers, if it's for a session that belongs to
+/// the caller's API key.
+///
+/// # Parameters
+/// - `headers`: The request's headers.
+/// - `s`: The wrapper state.
+/// - `extensions`: The request's extensions, which the auth middleware attaches the
+///   caller's key prefix to.
+///
+/// # Returns
+/// The session token, if there is one and it's the caller's.
+pub(super) fn owned_session_token<'a>(
+    headers: &'a HeaderMap,
+    s: &WrapperState,
+    extensions: &Extensions,
+) -> Option<&'a str> {
+    let key_prefix = extensions.get::<String>().map(String::as_str);
+    session_token(headers).filter(|t| s.sessions.info(t, key_prefix).is_some())
+}
+
+/// Creates the response for an import or a reattach that would give the user more of
+/// something than they're allowed.
+pub(super) fn too_many(message: &'static str, term: Option<&str>) -> Response {
+    ApiErrorType::from((
+        StatusCode::TOO_MANY_REQUESTS,
+        message,
+        term.map(str::to_owned),
+    ))
+    .into_response()
+}
+
+/// Creates the response for a request without a valid session token.
+pub(super) fn invalid_token() -> Response {
+    ApiErrorType::from((
+        StatusCode::UNAUTHORIZED,
+        "The given session token is not valid.",
+        None,
+    ))
+    .into_response()
+}
+
+impl Validate for BodySessionCookies {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        require(&mut errors, "cookies", &self.cookies);
+        if !self.cookies.is_ascii() {
+            errors.push(FieldError::new(
+                "cookies",
+                "must only contain ASCII characters",
+            ));
+        }
+
+        errors
+    }
+}
+
+impl Validate for BodySessionReattach {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        require(&mut errors, "previousToken", &self.previous_token);
+        errors
+    }
+}
+
+/// POST /sessions
+///
+/// Registers the given WebReg cookies and returns a session token that can be passed in
+/// the `X-Session-Token` header in place of the cookies. The session can only be used with
+/// the caller's API key.
+pub async fn post_session(
+    State(s): State<Arc<WrapperState>>,
+    extensions: Extensions,
+    ValidJson(body): ValidJson<BodySessionCookies>,
+) -> Response {
+    info!("POST /sessions");
+    let key_prefix = extensions.get::<String>().map(String::as_str);
+    let token = s.sessions.register(&body.cookies, key_prefix);
+
+    // The session's data is exported by the key that registered it (see `user_bundles`)
+    if let Some(prefix) = key_prefix {
+        if let Err(e) = s.schedule_db.record_session_owner(&token, prefix) {
+            s.sessions.remove(&token, key_prefix);
+            return db_error(e, DB_ERROR);
+        }
+    }
+
+    (StatusCode::CREATED, Json(json!({ "token": token }))).into_response()
+}
+
+/// GET /sessions
+///
+/// Returns information about the caller's session.
+pub async fn get_session(
+    headers: HeaderMap,
+    State(s): State<Arc<WrapperState>>,
+    extensions: Extensions,
+) -> Response {
+    info!("GET /sessions");
+
+    let key_prefix = extensions.get::<String>().map(String::as_str);
+    match session_token(&headers).and_then(|t| s.sessions.info(t, key_prefix)) {
+        Some(info) => (StatusCode::OK, Json(info)).into_response(),
+        None => invalid_token(),
+    }
+}
+
+/// PUT /sessions
+///
+/// Replaces the cookies associated with the caller's session.
+pub async fn put_session(
+    headers: HeaderMap,
+    State(s): State<Arc<WrapperState>>,
+    extensions: Extensions,
+    ValidJson(body): ValidJson<BodySessionCookies>,
+) -> Response {
+    info!("PUT /sessions");
+
+    let key_prefix = extensions.get::<String>().map(String::as_str);
+    match session_token(&headers) {
+        Some(t) if s.sessions.update_cookies(t, key_prefix, &body.cookies) => {
+            StatusCode::NO_CONTENT.into_response()
+        }
+        _ => invalid_token(),
+    }
+}
+
+/// DELETE /sessions
+///
+/// Removes the caller's session, forgetting its cookies.
+pub async fn delete_session(
+    headers: HeaderMap,
+    State(s): State<Arc<WrapperState>>,
+    extensions: Extensions,
+) -> Response {
+    info!("DELETE /sessions");
+
+    let key_prefix = extensions.get::<String>().map(String::as_str);
+    match session_token(&headers) {
+        Some(t) if s.sessions.remove(t, key_prefix) => StatusCode::NO_CONTENT.into_response(),
+        _ => invalid_token(),
+    }
+}
+
+/// POST /sessions/reattach
+///
+/// Moves the pending and paused enrollment jobs of a session that no longer exists (e.g.,
+/// because the server restarted) onto the caller's session, resuming the paused ones.
+/// Returns the IDs of the jobs that were moved.
+pub async fn post_session_reattach(
+    headers: HeaderMap,
+    State(s): State<Arc<WrapperState>>,
+    extensions: Extensions,
+    ValidJson(body): ValidJson<BodySessionReattach>,
+) -> Response {
+    info!("POST /sessions/reattach");
+
+    let Some(token) = owned_session_token(&headers, &s, &extensions) else {
+        return invalid_token();
+    };
+
+    // A session that still exists keeps its jobs, so they can't be taken from its owner
+    let previous = body.previous_token.trim();
+    if s.sessions.contains(previous) {
+        return ApiErrorType::from((
+            StatusCode::CONFLICT,
+            "The previous session still exists, so its jobs can't be moved.",
+            None,
+        ))
+        .into_response();
+    }
+
+    for entry in s.all_terms.iter() {
+        let term = entry.key().as_str();
+        let count = |token: &str, statuses: &[&str]| {
+            s.schedule_db.get_enroll_jobs(term, token).map(|jobs| {
+                jobs.iter()
+                    .filter(|j| statuses.contains(&j.status.as_str()))
+                    .count()
+            })
+        };
+        let moved = count(previous, &[ENROLL_JOB_PENDING, ENROLL_JOB_NEEDS_SESSION]);
+        let pending = count(token, &[ENROLL_JOB_PENDING]);
+        match (moved, pending) {
+            (Ok(moved), Ok(pending)) if moved > 0 && moved + pending > MAX_PENDING_JOBS => {
+                return too_many(
+                    "Moving these jobs would give this session too many pending enrollment \
+                     jobs in a term.",
+                    Some(term),
+                );
+            }
+            (Ok(_), Ok(_)) => {}
+            (Err(e), _) | (_, Err(e)) => return db_error(e, DB_ERROR),
+        }
+    }
+
+    match s.schedule_db.move_enroll_jobs(previous, token) {
+        Ok(job_ids) => (StatusCode::OK, Json(json!({ "enrollJobIds": job_ids }))).into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{NewEnrollJob, WatchAlerts};
+
+    fn headers(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(SESSION_TOKEN_HEADER, token.parse().unwrap());
+        headers
+    }
+
+    async fn body_json(res: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_session_cookies() {
+        let body = |cookies: &str| BodySessionCookies {
+            cookies: cookies.to_owned(),
+        };
+
+        assert!(body("jlinksessionidx=abc").validate().is_empty());
+        // Both POST and PUT validate the cookies, so neither can set them to nothing
+        for cookies in ["", "  \t"] {
+            let errors = body(cookies).validate();
+            assert_eq!(1, errors.len());
+            assert_eq!("cookies", errors[0].field);
+        }
+        assert_eq!(1, body("café").validate().len());
+    }
+
+    #[tokio::test]
+    async fn test_reattach_moves_jobs() {
+        let s = Arc::new(crate::types::tests::state("reattach", &["FA24"], json!({})));
+        let reattach = |token: String, previous: &str| {
+            let body = BodySessionReattach {
+                previous_token: previous.to_owned(),
+            };
+            post_session_reattach(
+                headers(&token),
+                State(s.clone()),
+                Extensions::new(),
+                ValidJson(body),
+            )
+        };
+
+        // The job's session was lost (e.g., the server restarted), so the job was paused
+        let job_id = s
+            .schedule_db
+            .insert_enroll_job(&NewEnrollJob {
+                term: "FA24",
+                session_token: "gone",
+                section_id: "123456",
+                subject_code: "CSE",
+                course_code: "100",
+                grading_option: None,
+                unit_count: Some(4),
+                run_at: None,
+                expires_at: None,
+                notify_channels: &[],
+                alerts: WatchAlerts::default(),
+            })
+            .unwrap();
+        assert!(s.schedule_db.pause_enroll_job(job_id).unwrap());
+
+        // A session that still exists keeps its jobs
+        let token = s.sessions.register("a=1", None);
+        let other = s.sessions.register("b=2", None);
+        let res = reattach(token.clone(), &other).await;
+        assert_eq!(StatusCode::CONFLICT, res.status());
+
+        let res = reattach(token.clone(), "gone").await;
+        assert_eq!(StatusCode::OK, res.status());
+        assert_eq!(json!({ "enrollJobIds": [job_id] }), body_json(res).await);
+        let jobs = s.schedule_db.get_enroll_jobs("FA24", &token).unwrap();
+        assert_eq!(ENROLL_JOB_PENDING, jobs[0].status);
+
+        // Nothing is left to move
+        let res = reattach(token.clone(), "gone").await;
+        assert_eq!(json!({ "enrollJobIds": [] }), body_json(res).await);
+    }
+}
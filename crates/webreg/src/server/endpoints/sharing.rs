@@ -0,0 +1,192 @@
+//! Endpoints for sharing read-only snapshots of a student's schedule by link.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::header::{self, COOKIE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{Duration, Utc};
+use serde_json::json;
+use tracing::info;
+use webweg::types::ScheduledSection as WebRegSection;
+
+use crate::error::WebregError;
+use crate::retry::Idempotency;
+use crate::schedule::{build_ical, render_week_png};
+use crate::server::types::{ApiErrorType, BodyShareSchedule, FieldError};
+use crate::server::util::to_scheduled_sections;
+use crate::server::validation::{check_range, require, ValidJson, Validate};
+use crate::sharing::ShareTokenError;
+use crate::types::WrapperState;
+use crate::webweg_types;
+
+/// How long a sharing link works for if the student doesn't say.
+const DEFAULT_TTL_HOURS: u32 = 72;
+/// The longest that a sharing link can work for.
+const MAX_TTL_HOURS: u32 = 24 * 30;
+
+impl Validate for BodyShareSchedule {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        require(&mut errors, "scheduleName", &self.schedule_name);
+        check_range(&mut errors, "ttlHours", self.ttl_hours, 1..=MAX_TTL_HOURS);
+        errors
+    }
+}
+
+/// POST /live/:term/share_schedule
+/// Takes a snapshot of one of the student's schedules and returns a signed link to it,
+/// which anyone can use to view the snapshot until the link expires
+pub async fn post_share_schedule(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyShareSchedule>,
+) -> Response {
+    info!("POST /live/{}/share_schedule", term);
+
+    let schedule_name = body.schedule_name.trim();
+    let ttl_hours = body.ttl_hours.unwrap_or(DEFAULT_TTL_HOURS);
+
+    let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
+    let requester = s
+        .c_wrapper
+        .req(term.as_str())
+        .override_cookies(cookies)
+        .parsed();
+    let sections = match s
+        .webreg_retry
+        .run(Idempotency::Idempotent, || {
+            requester.get_schedule(Some(schedule_name))
+        })
+        .await
+    {
+        Ok(sections) => sections,
+        Err(e) => return ApiErrorType::from(e).into_response(),
+    };
+
+    let expires_at = Utc::now() + Duration::hours(ttl_hours as i64);
+    let share_id = match s.schedule_db.insert_shared_schedule(
+        &term,
+        schedule_name,
+        &serde_json::to_string(&sections).unwrap(),
+        &expires_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+    ) {
+        Ok(id) => id,
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to save schedule snapshot")
+                .into_response();
+        }
+    };
+
+    let token = s.share_signer.sign(share_id, expires_at);
+    (
+        StatusCode::CREATED,
+        Json(json!({
+            "path": format!("/shared/{token}"),
+            "token": token,
+            "expires_at": expires_at.to_rfc3339(),
+        })),
+    )
+        .into_response()
+}
+
+/// GET /shared/:token
+/// Returns a shared schedule snapshot. Adding `.ics` or `.png` to the token returns the
+/// snapshot as an iCalendar file or as an image of the week, respectively
+pub async fn get_shared_schedule(
+    Path(token): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /shared/:token");
+
+    let (token, format) = match token.rsplit_once('.') {
+        Some((token, format)) => (token, Some(format)),
+        None => (token.as_str(), None),
+    };
+
+    let share_id = match s.share_signer.verify(token, Utc::now()) {
+        Ok(id) => id,
+        Err(ShareTokenError::Expired) => {
+            return ApiErrorType::from((StatusCode::GONE, "This link has expired.", None))
+                .into_response();
+        }
+        Err(ShareTokenError::Invalid) => {
+            return ApiErrorType::from((StatusCode::NOT_FOUND, "Shared schedule not found", None))
+                .into_response();
+        }
+    };
+
+    let shared = match s.schedule_db.get_shared_schedule(share_id) {
+        Ok(Some(shared)) => shared,
+        Ok(None) => {
+            return ApiErrorType::from((StatusCode::NOT_FOUND, "Shared schedule not found", None))
+                .into_response();
+        }
+        Err(e) => {
+            return WebregError::from(e)
+                .with_message("Failed to fetch shared schedule")
+                .into_response();
+        }
+    };
+
+    // A snapshot that can't be read is our fault, and shouldn't be shared as an empty
+    // schedule
+    let snapshot =
+        match serde_json::from_str::<Vec<webweg_types::ScheduledSection>>(&shared.snapshot) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                return WebregError::Internal {
+                    message: "Failed to read shared schedule".into(),
+                    context: Some(e.to_string()),
+                    code: None,
+                }
+                .into_response();
+            }
+        };
+    let sections = || {
+        let snapshot: Vec<WebRegSection> = snapshot.iter().cloned().map(Into::into).collect();
+        to_scheduled_sections(&s, &shared.term, &snapshot)
+    };
+
+    match format {
+        None => (
+            StatusCode::OK,
+            Json(json!({
+                "term": shared.term,
+                "schedule_name": shared.schedule_name,
+                "created_at": shared.created_at,
+                "expires_at": shared.expires_at,
+                "sections": snapshot,
+            })),
+        )
+            .into_response(),
+        Some("ics") => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"schedule.ics\"",
+                ),
+            ],
+            build_ical(&sections()),
+        )
+            .into_response(),
+        Some("png") => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "image/png")],
+            render_week_png(&sections()),
+        )
+            .into_response(),
+        Some(_) => ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "Unsupported format; use .ics or .png",
+            None,
+        ))
+        .into_response(),
+    }
+}
@@ -1,26 +1,100 @@
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
-use axum::response::{IntoResponse, Response};
+use axum::response::{Html, IntoResponse, Response};
 use axum::Json;
+use chrono::Utc;
 use serde_json::{json, Value};
 use tracing::log::info;
 
+use crate::error::WebregError;
+use crate::server::types::{ApiErrorType, StatusHistoryQueryStr};
+use crate::status_history::{
+    render_status_page, summarize, Dependency, DependencyHistory, MAX_HISTORY_DAYS,
+};
 use crate::types::WrapperState;
 
+/// How many days of the status history are returned, if not requested.
+const DEFAULT_HISTORY_DAYS: u32 = 30;
+
 /// A function to be executed when the `health` endpoint is called.
 #[tracing::instrument(skip(s))]
 pub async fn get_health(State(s): State<Arc<WrapperState>>) -> Response {
     info!("Called `health` endpoint.");
     let status = s.is_running();
-    let response = json!({ "api": status });
+    let terms: serde_json::Map<String, Value> = s
+        .terms()
+        .into_iter()
+        .map(|t| (t.term.clone(), Value::Bool(t.is_running())))
+        .collect();
+    let response = json!({
+        "api": status,
+        "terms": terms,
+        "cookie_server": s.cookie_server_health.report(),
+    });
 
     info!("Returned status: {status}");
     (StatusCode::OK, Json(response)).into_response()
 }
 
+/// GET /status/history?days=30
+///
+/// Returns whether WebReg, DARS, and the cookie server are up, their uptime on each of the
+/// last `days` days (at most 90), and their outages over those days.
+#[tracing::instrument(skip(s))]
+pub async fn get_status_history(
+    Query(q): Query<StatusHistoryQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("Called `status/history` endpoint.");
+    let days = q
+        .days
+        .unwrap_or(DEFAULT_HISTORY_DAYS)
+        .clamp(1, MAX_HISTORY_DAYS);
+    match status_histories(&s, days) {
+        Ok(histories) => (
+            StatusCode::OK,
+            Json(json!({
+                "days": days,
+                "generated_at": Utc::now().to_rfc3339(),
+                "dependencies": histories,
+            })),
+        )
+            .into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to load the status history.")
+            .into_response(),
+    }
+}
+
+/// GET /status
+///
+/// Returns a page showing whether WebReg, DARS, and the cookie server are up, and their
+/// uptime on each of the last 30 days.
+#[tracing::instrument(skip(s))]
+pub async fn get_status_page(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("Called `status` endpoint.");
+    match status_histories(&s, DEFAULT_HISTORY_DAYS) {
+        Ok(histories) => Html(render_status_page(&histories, DEFAULT_HISTORY_DAYS)).into_response(),
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to load the status history.")
+            .into_response(),
+    }
+}
+
+/// Summarizes each dependency's history over the last few days.
+fn status_histories(s: &WrapperState, days: u32) -> rusqlite::Result<Vec<DependencyHistory>> {
+    let now = Utc::now();
+    let since = (now - chrono::Duration::days(i64::from(days))).timestamp_millis();
+    let intervals = s.schedule_db.get_dependency_status_intervals(since)?;
+    Ok(Dependency::ALL
+        .into_iter()
+        .map(|dependency| summarize(dependency, &intervals, days, now))
+        .collect())
+}
+
 /// An endpoint for checking the time stats for a specific term's scrapers.
 #[tracing::instrument(skip(s))]
 pub async fn get_timing_stats(
@@ -48,6 +122,16 @@ pub async fn get_timing_stats(
     }
 }
 
+/// GET /login_stat/cookie_health
+///
+/// Returns how old the tracker's WebReg session cookies are, when they were last validated
+/// and whether they worked, and when new ones will be requested ahead of time.
+#[tracing::instrument(skip(s))]
+pub async fn get_cookie_health(State(s): State<Arc<WrapperState>>) -> Response {
+    info!("Called `cookie_health` endpoint.");
+    (StatusCode::OK, Json(s.cookie_freshness.report())).into_response()
+}
+
 /// An endpoint for checking the status of a specific term's scrapers.
 #[tracing::instrument(skip(s))]
 pub async fn get_login_script_stats(
@@ -57,19 +141,24 @@ pub async fn get_login_script_stats(
     info!("Called with path '{stat_type}'.");
 
     if stat_type != "start" && stat_type != "history" {
-        return (
+        return ApiErrorType::from((
             StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "Use either 'start' or 'history' as the endpoint."
-            })),
-        )
-            .into_response();
+            "Use either 'start' or 'history' as the endpoint.",
+            Some(stat_type),
+        ))
+        .into_response();
     }
 
-    let cookie_url = format!(
-        "http://{}:{}/{}",
-        s.cookie_server.address, s.cookie_server.port, stat_type
-    );
+    let Some(address) = s.cookie_server_address() else {
+        return ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "There's no cookie server to get login statistics from.",
+            None,
+        ))
+        .into_response();
+    };
+
+    let cookie_url = format!("http://{address}/{stat_type}");
 
     match s.client.get(cookie_url).send().await {
         Ok(r) => {
@@ -88,20 +177,16 @@ pub async fn get_login_script_stats(
             // return that as JSON.
             match serde_json::from_str::<Value>(resp.as_str()) {
                 Ok(o) => (StatusCode::OK, Json(o)).into_response(),
-                Err(e) => {
-                    let err = json!({
-                        "error": e.to_string()
-                    });
-                    (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
+                Err(e) => WebregError::Upstream {
+                    message: "The cookie server's response wasn't JSON.".into(),
+                    context: Some(e.to_string()),
+                    code: None,
                 }
+                .into_response(),
             }
         }
-        Err(e) => {
-            let json = json!({
-                "error": e.to_string()
-            });
-
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(json)).into_response()
-        }
+        Err(e) => WebregError::from(e)
+            .with_message("Failed to reach the cookie server.")
+            .into_response(),
     }
 }
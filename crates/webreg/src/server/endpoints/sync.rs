@@ -0,0 +1,164 @@
+//! Delta sync (see [`crate::sync`]), so that a client can refresh everything it shows in
+//! one small request.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::http::header::COOKIE;
+use axum::http::{Extensions, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::{json, Value};
+use tracing::info;
+
+#[cfg(feature = "auth")]
+use crate::api_keys::{ApiScope, KeyScopes};
+use crate::error::WebregError;
+use crate::retry::Idempotency;
+use crate::server::endpoints::db_error;
+use crate::server::endpoints::degree_audit::progress_headline;
+use crate::server::endpoints::sessions::owned_session_token;
+use crate::server::types::{ApiErrorType, SyncQueryStr};
+use crate::sync::{seats_key, ChangeKind, MAX_SYNC_CHANGES};
+use crate::types::WrapperState;
+
+/// The message for a database error.
+const DB_ERROR: &str = "Failed to read changes";
+
+/// GET /sync?cursor=...
+/// Returns the entities that changed since the client's cursor, along with the cursor to
+/// send next time. Without a cursor, every entity is returned. Enrollment jobs and the
+/// seats of the sections they watch are only returned with the session token that made
+/// the jobs, used with the API key that registered the session; schedules are only fetched from WebReg if the request has the student's
+/// cookies, and are otherwise returned without their sections. Degree progress is only
+/// returned to keys with the `degree_audit` scope. If `reset` is set, the server's change
+/// sequence started over (e.g., a standby took over), so the client should throw away what
+/// it has. If `has_more` is set, the client should sync again right away
+pub async fn get_sync(
+    headers: HeaderMap,
+    Query(query): Query<SyncQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+    extensions: Extensions,
+) -> Response {
+    info!("GET /sync (cursor={:?})", query.cursor);
+
+    let key_prefix = extensions.get::<String>().map(String::as_str);
+    // The audit is only returned if the caller could have fetched it themselves
+    #[cfg(feature = "auth")]
+    let audit_allowed = extensions
+        .get::<KeyScopes>()
+        .is_some_and(|scopes| scopes.allows(ApiScope::DegreeAudit));
+    #[cfg(not(feature = "auth"))]
+    let audit_allowed = true;
+
+    let cursor = match query.cursor.as_deref().map(str::parse::<i64>) {
+        None => 0,
+        Some(Ok(cursor)) if cursor >= 0 => cursor,
+        Some(_) => {
+            return ApiErrorType::from((
+                StatusCode::BAD_REQUEST,
+                "The cursor isn't valid.",
+                query.cursor,
+            ))
+            .into_response()
+        }
+    };
+
+    let latest = match s.schedule_db.get_latest_change_seq() {
+        Ok(latest) => latest,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+    let reset = cursor > latest;
+    let since = if reset { 0 } else { cursor };
+    let changes = match s.schedule_db.get_changes_since(since, MAX_SYNC_CHANGES) {
+        Ok(changes) => changes,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    let token = owned_session_token(&headers, &s, &extensions);
+    let watched: HashSet<String> = match token {
+        Some(token) => match s.schedule_db.get_pending_enroll_jobs_for_session(token) {
+            Ok(jobs) => jobs
+                .iter()
+                .map(|j| seats_key(&j.term, &j.section_id))
+                .collect(),
+            Err(e) => return db_error(e, DB_ERROR),
+        },
+        None => HashSet::new(),
+    };
+    let cookies = headers.get(COOKIE).and_then(|c| c.to_str().ok());
+
+    let mut entities = vec![];
+    for change in &changes {
+        let data = match ChangeKind::parse(&change.kind) {
+            Some(ChangeKind::Watch) => {
+                let job = change
+                    .entity_key
+                    .parse()
+                    .ok()
+                    .and_then(|id| s.schedule_db.get_enroll_job(id).ok().flatten());
+                match job {
+                    Some(job) if Some(job.session_token.as_str()) == token => json!(job),
+                    _ => continue,
+                }
+            }
+            Some(ChangeKind::Seats) if watched.contains(&change.entity_key) => {
+                let Some((term, section_id)) = change.entity_key.split_once(':') else {
+                    continue;
+                };
+                json!(s.live_feed.last_seen(term, section_id))
+            }
+            Some(ChangeKind::Schedule) => match cookies {
+                Some(cookies) => {
+                    let requester = s
+                        .c_wrapper
+                        .req(change.entity_key.as_str())
+                        .override_cookies(cookies)
+                        .parsed();
+                    let schedule = s
+                        .webreg_retry
+                        .run(Idempotency::Idempotent, || requester.get_schedule(None))
+                        .await;
+                    match schedule {
+                        Ok(schedule) => json!({ "sections": schedule }),
+                        Err(e) => {
+                            let error = WebregError::from(e);
+                            error.log_context();
+                            json!({ "code": error.code(), "error": error.message() })
+                        }
+                    }
+                }
+                None => Value::Null,
+            },
+            Some(ChangeKind::Audit) if audit_allowed => {
+                match progress_headline(&s, key_prefix).await {
+                    Ok(progress) => progress,
+                    Err(e) => {
+                        e.log_context();
+                        json!({ "code": e.code(), "error": e.message() })
+                    }
+                }
+            }
+            _ => continue,
+        };
+
+        entities.push(json!({
+            "seq": change.seq,
+            "kind": change.kind,
+            "key": change.entity_key,
+            "data": data,
+        }));
+    }
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "cursor": changes.last().map_or(since, |c| c.seq).to_string(),
+            "reset": reset,
+            "has_more": changes.len() == MAX_SYNC_CHANGES,
+            "changes": entities,
+        })),
+    )
+        .into_response()
+}
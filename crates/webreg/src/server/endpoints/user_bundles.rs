@@ -0,0 +1,925 @@
+//! Endpoints for exporting everything that a user has saved (their watches, with where each
+//! one sends its notifications, saved plans, carts, course plans, custom requirement sets,
+//! and the requirement overrides on their audit) as a portable bundle, and importing such a
+//! bundle, so that users can move between deployments or back up their data.
+//!
+//! Bundles are made per API key. Everything but the requirement sets and overrides is kept
+//! per session, so
+//! the export has what's saved in every session that the user has registered, including the
+//! ones that no longer exist (e.g., because the server restarted). The user in the path is
+//! `me` or the prefix of the caller's own API key; no one can export or import another
+//! user's data.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use chrono::Utc;
+use serde_json::json;
+use tracing::info;
+
+use crate::audit_trail::{override_key, RequirementOverride, TrailKind};
+use crate::db::{
+    BundledCoursePlan, BundledOverride, BundledRequirementSet, BundledSavedPlan, CartItem,
+    CoursePlan, NewCartItem, NewEnrollJob, SavedPlan, UserBundle, ENROLL_JOB_PENDING,
+};
+use crate::enroll_jobs::job_expiry;
+use crate::server::endpoints::cart::{sections_from_body, MAX_CART_ITEMS};
+use crate::server::endpoints::course_plans::{quarters_from_body, MAX_COURSE_PLANS};
+use crate::server::endpoints::custom_requirements::MAX_CUSTOM_REQUIREMENT_SETS;
+use crate::server::endpoints::db_error;
+use crate::server::endpoints::enroll_jobs::{
+    alerts_from_body, format_time, parse_time, MAX_PENDING_JOBS,
+};
+use crate::server::endpoints::plans::{entries_from_body, MAX_SAVED_PLANS};
+use crate::server::endpoints::sessions::{invalid_token, session_token, too_many};
+use crate::server::types::{
+    ApiErrorType, BodyCartItem, BodyCartSection, BodyCoursePlan, BodyCustomRequirements,
+    BodyPlanEntry, BodyPlannedQuarter, BodySavedPlan, BodyUserBundle, BundleCartItem,
+    BundleRequirementOverride, BundleSavedPlan, FieldError,
+};
+use crate::server::validation::{extend_nested, require, ValidJson, Validate};
+use crate::types::WrapperState;
+
+/// The message for a database error.
+const DB_ERROR: &str = "Failed to access the user's data";
+/// Why the caller can't export or import another user's data.
+const OTHER_USER: &str = "You can only export or import your own data.";
+/// The version of the bundles made by `GET /users/:id/export`. Bundles of version 1 only
+/// have enrollment jobs, bundles of version 2 don't have custom requirement sets, and
+/// bundles of version 3 don't have requirement overrides; all of them can still be
+/// imported.
+const BUNDLE_VERSION: u32 = 4;
+
+/// Checks that the user in the path is the caller.
+///
+/// # Parameters
+/// - `user`: The user in the path.
+/// - `prefix`: The prefix of the caller's API key.
+/// - `message`: Why the caller can't act as someone else (e.g., `You can only export or
+///   import your own data.`).
+///
+/// # Returns
+/// The response to return instead if the user is someone else.
+pub(super) fn check_user(user: &str, prefix: &str, message: &'static str) -> Option<Response> {
+    if user == "me" || user == prefix {
+        return None;
+    }
+
+    Some(ApiErrorType::from((StatusCode::FORBIDDEN, message, None)).into_response())
+}
+
+/// Creates the response for a bundle with something in a term that isn't tracked.
+fn untracked_term(term: &str) -> Response {
+    ApiErrorType::from((
+        StatusCode::BAD_REQUEST,
+        "The bundle has something in a term that isn't tracked here.",
+        Some(term.to_owned()),
+    ))
+    .into_response()
+}
+
+impl Validate for BodyUserBundle {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        if !(1..=BUNDLE_VERSION).contains(&self.version) {
+            errors.push(FieldError::new(
+                "version",
+                format!("must be between 1 and {BUNDLE_VERSION}; other versions aren't supported"),
+            ));
+        }
+        for (i, bundled) in self.enroll_jobs.iter().enumerate() {
+            let field = format!("enrollJobs[{i}]");
+            require(&mut errors, &format!("{field}.term"), &bundled.term);
+            extend_nested(&mut errors, &field, bundled.job.validate());
+        }
+        for (i, bundled) in self.saved_plans.iter().enumerate() {
+            let field = format!("savedPlans[{i}]");
+            require(&mut errors, &format!("{field}.term"), &bundled.term);
+            extend_nested(&mut errors, &field, bundled.plan.validate());
+        }
+        for (i, bundled) in self.cart.iter().enumerate() {
+            let field = format!("cart[{i}]");
+            require(&mut errors, &format!("{field}.term"), &bundled.term);
+            extend_nested(&mut errors, &field, bundled.item.validate());
+        }
+        for (i, plan) in self.course_plans.iter().enumerate() {
+            extend_nested(&mut errors, &format!("coursePlans[{i}]"), plan.validate());
+        }
+        for (i, set) in self.custom_requirements.iter().enumerate() {
+            let field = format!("customRequirements[{i}]");
+            extend_nested(&mut errors, &field, set.validate());
+        }
+        for (i, o) in self.requirement_overrides.iter().enumerate() {
+            let field = format!("requirementOverrides[{i}].requirement");
+            require(&mut errors, &field, &o.requirement);
+        }
+        errors
+    }
+}
+
+/// GET /users/:id/export
+///
+/// Returns a bundle of the user's pending and paused enrollment jobs, saved plans, and cart
+/// in every term, along with their course plans, custom requirement sets, and the
+/// requirement overrides on their audit, which can be
+/// imported on another deployment with `POST /users/:id/import`. Where a later session has
+/// a saved plan, cart item, or course plan with the same schedule name, course, or name as
+/// an earlier one, only the later one is exported. Sessions' cookies aren't part of the
+/// bundle.
+pub async fn get_user_export(
+    Extension(prefix): Extension<String>,
+    Path(user): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /users/{}/export", user);
+
+    if let Some(response) = check_user(&user, &prefix, OTHER_USER) {
+        return response;
+    }
+
+    let tokens = match s.schedule_db.get_owned_session_tokens(&prefix) {
+        Ok(tokens) => tokens,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    let mut jobs = vec![];
+    let mut saved_plans: Vec<SavedPlan> = vec![];
+    let mut cart: Vec<CartItem> = vec![];
+    let mut course_plans: Vec<CoursePlan> = vec![];
+    for token in &tokens {
+        match s.schedule_db.get_pending_enroll_jobs_for_session(token) {
+            Ok(session_jobs) => jobs.extend(session_jobs),
+            Err(e) => return db_error(e, DB_ERROR),
+        }
+
+        match s.schedule_db.get_session_saved_plans(token) {
+            Ok(plans) => {
+                for plan in plans {
+                    saved_plans
+                        .retain(|p| p.term != plan.term || p.schedule_name != plan.schedule_name);
+                    saved_plans.push(plan);
+                }
+            }
+            Err(e) => return db_error(e, DB_ERROR),
+        }
+
+        match s.schedule_db.get_session_cart(token) {
+            Ok(items) => {
+                for item in items {
+                    cart.retain(|i| {
+                        i.term != item.term
+                            || i.subject_code != item.subject_code
+                            || i.course_code != item.course_code
+                    });
+                    cart.push(item);
+                }
+            }
+            Err(e) => return db_error(e, DB_ERROR),
+        }
+
+        match s.schedule_db.get_course_plans(token) {
+            Ok(plans) => {
+                for plan in plans {
+                    course_plans.retain(|p| p.name != plan.name);
+                    course_plans.push(plan);
+                }
+            }
+            Err(e) => return db_error(e, DB_ERROR),
+        }
+    }
+
+    let custom_requirements = match s.schedule_db.get_custom_requirement_sets(&prefix) {
+        Ok(sets) => sets,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+    let overrides = match s
+        .schedule_db
+        .get_current_trail_versions(TrailKind::Override.as_str())
+    {
+        Ok(entries) => entries,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    let enroll_jobs: Vec<_> = jobs
+        .iter()
+        .map(|job| {
+            json!({
+                "term": job.term,
+                "sectionId": job.section_id,
+                "subjectCode": job.subject_code,
+                "courseCode": job.course_code,
+                "gradingOption": job.grading_option,
+                "unitCount": job.unit_count,
+                "runAt": job.run_at.as_deref().and_then(format_time),
+                "expiresAt": job.expires_at.as_deref().and_then(format_time),
+                "notify": job.notify_channels,
+                "cooldownMins": job.alerts.cooldown_mins,
+                "dedupMins": job.alerts.dedup_mins,
+                "minSeats": job.alerts.min_seats,
+            })
+        })
+        .collect();
+
+    // Everything else is exported in the same form that it's saved in
+    let saved_plans: Vec<_> = saved_plans
+        .into_iter()
+        .map(|plan| BundleSavedPlan {
+            term: plan.term,
+            plan: BodySavedPlan {
+                schedule_name: plan.schedule_name,
+                entries: plan
+                    .entries
+                    .into_iter()
+                    .map(|entry| BodyPlanEntry {
+                        subject_code: entry.subject_code,
+                        course_code: entry.course_code,
+                        section_id: entry.section_id,
+                        section_code: entry.section_code,
+                        grading_option: entry.grading_option,
+                        unit_count: entry.unit_count,
+                    })
+                    .collect(),
+            },
+        })
+        .collect();
+    let cart: Vec<_> = cart
+        .into_iter()
+        .map(|item| BundleCartItem {
+            term: item.term,
+            item: BodyCartItem {
+                subject_code: item.subject_code,
+                course_code: item.course_code,
+                sections: item
+                    .sections
+                    .into_iter()
+                    .map(|section| BodyCartSection {
+                        section_id: section.section_id,
+                        section_code: section.section_code,
+                    })
+                    .collect(),
+                notes: item.notes,
+                priority: Some(item.priority),
+                grading_option: item.grading_option,
+                unit_count: item.unit_count,
+            },
+        })
+        .collect();
+    let course_plans: Vec<_> = course_plans
+        .into_iter()
+        .map(|plan| BodyCoursePlan {
+            name: plan.name,
+            quarters: plan
+                .quarters
+                .into_iter()
+                .map(|quarter| BodyPlannedQuarter {
+                    term: quarter.term,
+                    courses: quarter.courses,
+                })
+                .collect(),
+        })
+        .collect();
+    let custom_requirements: Vec<_> = custom_requirements
+        .into_iter()
+        .map(|set| BodyCustomRequirements {
+            name: set.name,
+            subrequirements: set.subrequirements,
+        })
+        .collect();
+
+    // Only the user's own overrides, which are imported for whoever imports the bundle
+    let requirement_overrides: Vec<_> = overrides
+        .iter()
+        .filter_map(|e| serde_json::from_str(e.content.as_deref()?).ok())
+        .filter(|o: &RequirementOverride| o.user.as_deref() == Some(prefix.as_str()))
+        .map(|o| BundleRequirementOverride {
+            requirement: o.requirement,
+            status: o.status,
+            kind: o.kind,
+        })
+        .collect();
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "version": BUNDLE_VERSION,
+            "exportedAt": Utc::now().to_rfc3339(),
+            "enrollJobs": enroll_jobs,
+            "savedPlans": saved_plans,
+            "cart": cart,
+            "coursePlans": course_plans,
+            "customRequirements": custom_requirements,
+            "requirementOverrides": requirement_overrides,
+        })),
+    )
+        .into_response()
+}
+
+/// POST /users/:id/import
+///
+/// Adds what's in a bundle made by `GET /users/:id/export` for the user. Custom requirement
+/// sets are kept for the caller's API key, replacing their sets with the same name;
+/// requirement overrides are recorded as new versions of the overrides on the caller's
+/// audit, made by the caller; and everything else is added to the caller's session:
+/// enrollment jobs are added as pending jobs, saved plans and cart items replace the
+/// session's plans with the same schedule name and items for the same course, and course
+/// plans are added unless the session already has one with the same name. Everything is checked before anything is added, and it's all
+/// added in one transaction, so either the whole bundle is imported or none of it is.
+/// Returns the IDs of what was added.
+pub async fn post_user_import(
+    headers: HeaderMap,
+    Extension(prefix): Extension<String>,
+    Path(user): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyUserBundle>,
+) -> Response {
+    info!("POST /users/{}/import", user);
+
+    if let Some(response) = check_user(&user, &prefix, OTHER_USER) {
+        return response;
+    }
+
+    // The watches need a session's cookies to enroll with, so they go in the caller's session
+    let token = session_token(&headers).filter(|t| s.sessions.info(t, Some(&prefix)).is_some());
+    let Some(token) = token else {
+        return invalid_token();
+    };
+
+    let mut new_jobs = Vec::with_capacity(body.enroll_jobs.len());
+    let mut jobs_per_term: HashMap<&str, usize> = HashMap::new();
+    for bundled in &body.enroll_jobs {
+        if !s.all_terms.contains_key(&bundled.term) {
+            return untracked_term(&bundled.term);
+        }
+
+        // The times were checked when the body was read
+        let run_at = bundled.job.run_at.as_deref().and_then(parse_time);
+        let expires_at = job_expiry(
+            bundled.job.expires_at.as_deref().and_then(parse_time),
+            s.term(&bundled.term).and_then(|t| t.add_deadline),
+        );
+        *jobs_per_term.entry(&bundled.term).or_default() += 1;
+        new_jobs.push(NewEnrollJob {
+            term: &bundled.term,
+            session_token: token,
+            section_id: bundled.job.section_id.trim(),
+            subject_code: bundled.job.subject_code.trim(),
+            course_code: bundled.job.course_code.trim(),
+            grading_option: bundled.job.grading_option.as_deref(),
+            unit_count: bundled.job.unit_count,
+            run_at,
+            expires_at,
+            notify_channels: &bundled.job.notify,
+            alerts: alerts_from_body(&bundled.job),
+        });
+    }
+
+    for (term, count) in jobs_per_term {
+        let pending = match s.schedule_db.get_enroll_jobs(term, token) {
+            Ok(jobs) => jobs
+                .iter()
+                .filter(|j| j.status == ENROLL_JOB_PENDING)
+                .count(),
+            Err(e) => return db_error(e, DB_ERROR),
+        };
+
+        if pending + count > MAX_PENDING_JOBS {
+            return too_many(
+                "Importing this bundle would give this session too many pending enrollment \
+                 jobs in a term.",
+                Some(term),
+            );
+        }
+    }
+
+    // Saved plans and cart items replace the ones with the same schedule name or course, so
+    // only the others count toward the limits
+    let mut plans_per_term: HashMap<&str, HashSet<String>> = HashMap::new();
+    for bundled in &body.saved_plans {
+        if !s.all_terms.contains_key(&bundled.term) {
+            return untracked_term(&bundled.term);
+        }
+
+        let schedule_name = bundled.plan.schedule_name.trim().to_owned();
+        plans_per_term
+            .entry(&bundled.term)
+            .or_default()
+            .insert(schedule_name);
+    }
+
+    for (term, schedule_names) in &mut plans_per_term {
+        match s.schedule_db.get_saved_plans(term, token) {
+            Ok(plans) => schedule_names.extend(plans.into_iter().map(|p| p.schedule_name)),
+            Err(e) => return db_error(e, DB_ERROR),
+        }
+
+        if schedule_names.len() > MAX_SAVED_PLANS {
+            return too_many(
+                "Importing this bundle would give this session too many saved plans in a term.",
+                Some(term),
+            );
+        }
+    }
+
+    let mut cart_per_term: HashMap<&str, HashSet<(String, String)>> = HashMap::new();
+    for bundled in &body.cart {
+        if !s.all_terms.contains_key(&bundled.term) {
+            return untracked_term(&bundled.term);
+        }
+
+        let course = (
+            bundled.item.subject_code.trim().to_uppercase(),
+            bundled.item.course_code.trim().to_uppercase(),
+        );
+        cart_per_term
+            .entry(&bundled.term)
+            .or_default()
+            .insert(course);
+    }
+
+    // Items without a priority go after the rest of the term's cart, in the bundle's order
+    let mut next_priority: HashMap<&str, i64> = HashMap::new();
+    for (term, courses) in &mut cart_per_term {
+        let cart = match s.schedule_db.get_cart(term, token) {
+            Ok(cart) => cart,
+            Err(e) => return db_error(e, DB_ERROR),
+        };
+
+        next_priority.insert(term, cart.iter().map(|i| i.priority + 1).max().unwrap_or(0));
+        courses.extend(
+            cart.into_iter()
+                .map(|item| (item.subject_code, item.course_code)),
+        );
+        if courses.len() > MAX_CART_ITEMS {
+            return too_many(
+                "Importing this bundle would fill this session's cart for a term.",
+                Some(term),
+            );
+        }
+    }
+
+    if !body.course_plans.is_empty() {
+        let mut names: HashSet<String> = match s.schedule_db.get_course_plans(token) {
+            Ok(plans) => plans.into_iter().map(|p| p.name).collect(),
+            Err(e) => return db_error(e, DB_ERROR),
+        };
+
+        if names.len() + body.course_plans.len() > MAX_COURSE_PLANS {
+            return too_many(
+                "Importing this bundle would give this session too many course plans.",
+                None,
+            );
+        }
+
+        for plan in &body.course_plans {
+            let name = plan.name.trim();
+            if !names.insert(name.to_owned()) {
+                return ApiErrorType::from((
+                    StatusCode::CONFLICT,
+                    "This session already has a course plan with a name in the bundle, or \
+                     the bundle has two with the same name.",
+                    Some(name.to_owned()),
+                ))
+                .into_response();
+            }
+        }
+    }
+
+    if !body.custom_requirements.is_empty() {
+        let mut names: HashSet<String> = match s.schedule_db.get_custom_requirement_sets(&prefix) {
+            Ok(sets) => sets.into_iter().map(|set| set.name).collect(),
+            Err(e) => return db_error(e, DB_ERROR),
+        };
+
+        names.extend(
+            body.custom_requirements
+                .iter()
+                .map(|set| set.name.trim().to_owned()),
+        );
+        if names.len() > MAX_CUSTOM_REQUIREMENT_SETS {
+            return too_many(
+                "Importing this bundle would give you too many custom requirement sets.",
+                None,
+            );
+        }
+    }
+
+    let saved_plans = body
+        .saved_plans
+        .iter()
+        .map(|bundled| BundledSavedPlan {
+            term: &bundled.term,
+            schedule_name: bundled.plan.schedule_name.trim(),
+            entries: entries_from_body(&bundled.plan),
+        })
+        .collect();
+
+    // The cart items only borrow their sections and codes, so those are worked out first
+    let cart_fields: Vec<_> = body
+        .cart
+        .iter()
+        .map(|bundled| {
+            let item = &bundled.item;
+            let priority = match item.priority {
+                Some(priority) => priority,
+                None => {
+                    let next = next_priority.entry(&bundled.term).or_default();
+                    *next += 1;
+                    *next - 1
+                }
+            };
+            (
+                sections_from_body(item),
+                item.subject_code.trim().to_uppercase(),
+                item.course_code.trim().to_uppercase(),
+                priority,
+            )
+        })
+        .collect();
+    let cart = body
+        .cart
+        .iter()
+        .zip(&cart_fields)
+        .map(
+            |(bundled, (sections, subject_code, course_code, priority))| NewCartItem {
+                term: &bundled.term,
+                session_token: token,
+                subject_code,
+                course_code,
+                sections,
+                notes: bundled
+                    .item
+                    .notes
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|n| !n.is_empty()),
+                priority: *priority,
+                grading_option: bundled.item.grading_option.as_deref(),
+                unit_count: bundled.item.unit_count,
+            },
+        )
+        .collect();
+
+    let course_plans = body
+        .course_plans
+        .iter()
+        .map(|plan| BundledCoursePlan {
+            name: plan.name.trim(),
+            quarters: quarters_from_body(plan),
+        })
+        .collect();
+
+    let custom_requirements = body
+        .custom_requirements
+        .iter()
+        .map(|set| BundledRequirementSet {
+            name: set.name.trim(),
+            subrequirements: &set.subrequirements,
+        })
+        .collect();
+
+    let requirement_overrides = body
+        .requirement_overrides
+        .iter()
+        .map(|o| {
+            let requirement = o.requirement.trim();
+            BundledOverride {
+                entity_key: override_key(Some(&prefix), requirement),
+                content: json!(RequirementOverride {
+                    requirement: requirement.to_owned(),
+                    status: o.status.clone(),
+                    kind: o.kind,
+                    user: Some(prefix.clone()),
+                })
+                .to_string(),
+            }
+        })
+        .collect();
+
+    let bundle = UserBundle {
+        key_prefix: &prefix,
+        session_token: token,
+        enroll_jobs: new_jobs,
+        saved_plans,
+        cart,
+        course_plans,
+        custom_requirements,
+        requirement_overrides,
+    };
+    let imported = match s.schedule_db.import_user_bundle(&bundle) {
+        Ok(imported) => imported,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    (
+        StatusCode::CREATED,
+        Json(json!({
+            "enrollJobIds": imported.enroll_job_ids,
+            "savedPlanIds": imported.saved_plan_ids,
+            "cartItemIds": imported.cart_item_ids,
+            "coursePlanIds": imported.course_plan_ids,
+            "customRequirementIds": imported.custom_requirement_ids,
+            "requirementOverrideIds": imported.requirement_override_ids,
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit_trail::{current_overrides, OverrideKind};
+    use crate::db::{CartSection, PlanEntry, PlannedQuarter, WatchAlerts};
+    use crate::degree_audit::config::SubrequirementConfig;
+    use crate::degree_audit::RequirementStatus;
+    use crate::notify::NotifyChannel;
+    use crate::sessions::SESSION_TOKEN_HEADER;
+
+    fn headers(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(SESSION_TOKEN_HEADER, token.parse().unwrap());
+        headers
+    }
+
+    async fn body_json(res: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    /// Registers a session for a user, the way `POST /sessions` does.
+    fn register(s: &WrapperState, prefix: &str) -> String {
+        let token = s.sessions.register("a=1", Some(prefix));
+        s.schedule_db.record_session_owner(&token, prefix).unwrap();
+        token
+    }
+
+    async fn export(s: &Arc<WrapperState>, prefix: &str) -> serde_json::Value {
+        let res = get_user_export(
+            Extension(prefix.to_owned()),
+            Path("me".to_owned()),
+            State(s.clone()),
+        )
+        .await;
+        assert_eq!(StatusCode::OK, res.status());
+        body_json(res).await
+    }
+
+    async fn import(
+        s: &Arc<WrapperState>,
+        prefix: &str,
+        token: &str,
+        body: BodyUserBundle,
+    ) -> Response {
+        post_user_import(
+            headers(token),
+            Extension(prefix.to_owned()),
+            Path("me".to_owned()),
+            State(s.clone()),
+            ValidJson(body),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_bundle_round_trip() {
+        let s = Arc::new(crate::types::tests::state("bundle", &["FA24"], json!({})));
+
+        // The user's data is spread over a session that's gone and the one they have now
+        let gone = "gone";
+        s.schedule_db.record_session_owner(gone, "old").unwrap();
+        let notify = [NotifyChannel::Ntfy {
+            topic: "cse-100".to_owned(),
+            server: None,
+        }];
+        s.schedule_db
+            .insert_enroll_job(&NewEnrollJob {
+                term: "FA24",
+                session_token: gone,
+                section_id: "123456",
+                subject_code: "CSE",
+                course_code: "100",
+                grading_option: Some("L"),
+                unit_count: Some(4),
+                run_at: None,
+                expires_at: None,
+                notify_channels: &notify,
+                alerts: WatchAlerts::default(),
+            })
+            .unwrap();
+        let quarters = [PlannedQuarter {
+            term: "FA24".to_owned(),
+            courses: vec!["CSE 100".to_owned()],
+        }];
+        s.schedule_db
+            .insert_course_plan(gone, "Four years", &quarters)
+            .unwrap();
+
+        let from = register(&s, "old");
+        let entry = PlanEntry {
+            subject_code: "CSE".to_owned(),
+            course_code: "100".to_owned(),
+            section_id: "123456".to_owned(),
+            section_code: "A01".to_owned(),
+            grading_option: None,
+            unit_count: Some(4),
+        };
+        s.schedule_db
+            .upsert_saved_plan("FA24", &from, "Main", &[entry])
+            .unwrap();
+        let sections = [CartSection {
+            section_id: "123456".to_owned(),
+            section_code: "A01".to_owned(),
+        }];
+        s.schedule_db
+            .upsert_cart_item(&NewCartItem {
+                term: "FA24",
+                session_token: &from,
+                subject_code: "CSE",
+                course_code: "100",
+                sections: &sections,
+                notes: Some("Needed for the major"),
+                priority: 3,
+                grading_option: Some("L"),
+                unit_count: None,
+            })
+            .unwrap();
+        // The later session's course plan replaces the earlier one with the same name
+        let quarters = [PlannedQuarter {
+            term: "FA24".to_owned(),
+            courses: vec!["CSE 100".to_owned(), "CSE 101".to_owned()],
+        }];
+        s.schedule_db
+            .insert_course_plan(&from, "Four years", &quarters)
+            .unwrap();
+        let subrequirements = [SubrequirementConfig {
+            title: "Biology".to_owned(),
+            required_units: 8.0,
+            eligible_courses: vec!["BILD 1".to_owned()],
+            departments: vec![],
+            level_filters: vec![],
+        }];
+        s.schedule_db
+            .upsert_custom_requirement_set("old", "Pre-med", &subrequirements)
+            .unwrap();
+        // Only the user's own overrides are theirs to export
+        for user in [Some("old"), Some("other"), None] {
+            let content = json!(RequirementOverride {
+                requirement: "Writing".to_owned(),
+                status: RequirementStatus::Complete,
+                kind: OverrideKind::Petition,
+                user: user.map(str::to_owned),
+            })
+            .to_string();
+            s.schedule_db
+                .record_trail_version(
+                    TrailKind::Override.as_str(),
+                    &override_key(user, "Writing"),
+                    Some(&content),
+                    "advisor",
+                    "Petition approved",
+                )
+                .unwrap();
+        }
+
+        let bundle = export(&s, "old").await;
+        assert_eq!(BUNDLE_VERSION, bundle["version"]);
+        assert_eq!("ntfy", bundle["enrollJobs"][0]["notify"][0]["kind"]);
+        assert_eq!(
+            json!(["CSE 100", "CSE 101"]),
+            bundle["coursePlans"][0]["quarters"][0]["courses"]
+        );
+        let body: BodyUserBundle = serde_json::from_value(bundle.clone()).unwrap();
+        assert!(body.validate().is_empty());
+
+        // Nothing of the user's is exported for anyone else
+        let to = register(&s, "new");
+        let empty = export(&s, "new").await;
+        for key in [
+            "enrollJobs",
+            "savedPlans",
+            "cart",
+            "coursePlans",
+            "customRequirements",
+            "requirementOverrides",
+        ] {
+            assert_eq!(json!([]), empty[key]);
+        }
+
+        let res = import(&s, "new", &to, body).await;
+        assert_eq!(StatusCode::CREATED, res.status());
+
+        let imported = export(&s, "new").await;
+        for key in [
+            "enrollJobs",
+            "savedPlans",
+            "cart",
+            "coursePlans",
+            "customRequirements",
+            "requirementOverrides",
+        ] {
+            assert_eq!(1, bundle[key].as_array().unwrap().len());
+            assert_eq!(bundle[key], imported[key]);
+        }
+        // The imported override applies to the new user's audit
+        let overrides = current_overrides(&s.schedule_db, Some("new"));
+        assert_eq!(1, overrides.len());
+        assert_eq!("Writing", overrides[0].requirement);
+        assert_eq!(OverrideKind::Petition, overrides[0].kind);
+        assert!(matches!(overrides[0].status, RequirementStatus::Complete));
+
+        // Importing the plans again would give the session two course plans with one name
+        let body: BodyUserBundle = serde_json::from_value(bundle).unwrap();
+        let res = import(&s, "new", &to, body).await;
+        assert_eq!(StatusCode::CONFLICT, res.status());
+    }
+
+    #[tokio::test]
+    async fn test_only_own_data() {
+        let s = Arc::new(crate::types::tests::state(
+            "bundle-own",
+            &["FA24"],
+            json!({}),
+        ));
+        let token = register(&s, "old");
+
+        let res = get_user_export(
+            Extension("new".to_owned()),
+            Path("old".to_owned()),
+            State(s.clone()),
+        )
+        .await;
+        assert_eq!(StatusCode::FORBIDDEN, res.status());
+
+        // Someone else's session can't be imported into
+        let res = import(&s, "new", &token, job_bundle(None)).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+    }
+
+    fn job_bundle(saved_plan_term: Option<&str>) -> BodyUserBundle {
+        let saved_plans: Vec<_> = saved_plan_term
+            .iter()
+            .map(|term| json!({ "term": term, "scheduleName": "Main", "entries": [] }))
+            .collect();
+        serde_json::from_value(json!({
+            "version": BUNDLE_VERSION,
+            "enrollJobs": [{
+                "term": "FA24",
+                "sectionId": "123456",
+                "subjectCode": "CSE",
+                "courseCode": "100",
+                "unitCount": 4,
+            }],
+            "savedPlans": saved_plans,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_import_returns_ids() {
+        let s = Arc::new(crate::types::tests::state(
+            "import-ids",
+            &["FA24"],
+            json!({}),
+        ));
+        let token = register(&s, "key");
+
+        let res = import(&s, "key", &token, job_bundle(None)).await;
+        assert_eq!(StatusCode::CREATED, res.status());
+        let body = body_json(res).await;
+        let jobs = s.schedule_db.get_enroll_jobs("FA24", &token).unwrap();
+        assert_eq!(json!([jobs[0].job_id]), body["enrollJobIds"]);
+        for key in [
+            "savedPlanIds",
+            "cartItemIds",
+            "coursePlanIds",
+            "customRequirementIds",
+            "requirementOverrideIds",
+        ] {
+            assert_eq!(json!([]), body[key]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejected_bundle_adds_nothing() {
+        let s = Arc::new(crate::types::tests::state(
+            "import-rejected",
+            &["FA24"],
+            json!({}),
+        ));
+        let token = register(&s, "key");
+
+        // The enrollment job is fine, but the saved plan's term isn't tracked
+        let res = import(&s, "key", &token, job_bundle(Some("WI25"))).await;
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+        assert!(s
+            .schedule_db
+            .get_enroll_jobs("FA24", &token)
+            .unwrap()
+            .is_empty());
+    }
+}
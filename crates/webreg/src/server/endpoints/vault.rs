@@ -0,0 +1,313 @@
+//! Endpoints for storing a user's WebReg credentials in the credential vault (see
+//! [`crate::vault`]), so that the cookie server assigned to them can log in as them.
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::error::WebregError;
+use crate::server::endpoints::db_error;
+use crate::server::types::{ApiErrorType, BodyVaultCredentials, FieldError};
+use crate::server::validation::{require, ValidJson, Validate};
+use crate::types::WrapperState;
+use crate::vault::{push_credentials, CredentialVault, WebRegCredentials};
+
+/// The message for a database error.
+const DB_ERROR: &str = "Failed to access the credential vault";
+
+/// Creates the response for when the vault is disabled.
+fn vault_disabled() -> Response {
+    ApiErrorType::from((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "The credential vault isn't enabled on this server.",
+        None,
+    ))
+    .into_response()
+}
+
+/// Sends a version of a user's credentials to their cookie server, and records the result.
+///
+/// # Returns
+/// The sync status, to be included in responses.
+async fn sync(
+    s: &WrapperState,
+    vault: &CredentialVault,
+    prefix: &str,
+    version: i64,
+    credentials: &WebRegCredentials,
+) -> Value {
+    let cookie_server = s.user_cookie_servers.read().unwrap().get(prefix).cloned();
+    let result = match cookie_server {
+        Some(cookie_server) => {
+            push_credentials(&s.client, vault, &cookie_server, credentials).await
+        }
+        None => Err("no cookie server is assigned to this key".to_owned()),
+    };
+
+    if let Err(e) = &result {
+        warn!("Failed to send credentials for key '{prefix}' to its cookie server: {e}");
+    }
+
+    if let Err(e) =
+        s.schedule_db
+            .record_vault_sync(prefix, version, result.as_ref().err().map(String::as_str))
+    {
+        warn!("Failed to record the credential sync for key '{prefix}': {e}");
+    }
+
+    json!({
+        "version": version,
+        "synced": result.is_ok(),
+        "sync_error": result.err(),
+    })
+}
+
+/// GET /vault/credentials
+///
+/// Returns the username stored for the requesting key, along with the version of its
+/// credentials and whether its cookie server has that version. The password isn't returned.
+pub async fn get_credentials(
+    Extension(prefix): Extension<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("GET /vault/credentials");
+
+    match s.schedule_db.get_vault_entry(&prefix) {
+        Ok(Some(entry)) => (StatusCode::OK, Json(entry)).into_response(),
+        Ok(None) => ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "No credentials are stored for this key.",
+            None,
+        ))
+        .into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+impl Validate for BodyVaultCredentials {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        require(&mut errors, "username", &self.username);
+        if self.password.is_empty() {
+            errors.push(FieldError::new("password", "must be given"));
+        }
+        errors
+    }
+}
+
+/// PUT /vault/credentials
+///
+/// Sets (or rotates) the WebReg credentials for the requesting key, and sends them to the
+/// key's cookie server. The credentials are stored even if they couldn't be sent; they can
+/// be sent again with `POST /vault/credentials/sync`.
+pub async fn put_credentials(
+    Extension(prefix): Extension<String>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyVaultCredentials>,
+) -> Response {
+    info!("PUT /vault/credentials");
+
+    let Some(vault) = &s.credential_vault else {
+        return vault_disabled();
+    };
+
+    let credentials = WebRegCredentials {
+        username: body.username.trim().to_owned(),
+        password: body.password,
+    };
+    let sealed = vault.seal(&prefix, &credentials);
+    let version = match s
+        .schedule_db
+        .put_vault_credentials(&prefix, &sealed, &credentials.username)
+    {
+        Ok(version) => version,
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    (
+        StatusCode::OK,
+        Json(sync(&s, vault, &prefix, version, &credentials).await),
+    )
+        .into_response()
+}
+
+/// POST /vault/credentials/sync
+///
+/// Sends the requesting key's current credentials to its cookie server again, e.g., after
+/// the cookie server was unreachable when they were set.
+pub async fn post_sync_credentials(
+    Extension(prefix): Extension<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("POST /vault/credentials/sync");
+
+    let Some(vault) = &s.credential_vault else {
+        return vault_disabled();
+    };
+
+    let (sealed, version) = match s.schedule_db.get_vault_credentials(&prefix) {
+        Ok(Some(stored)) => stored,
+        Ok(None) => {
+            return ApiErrorType::from((
+                StatusCode::NOT_FOUND,
+                "No credentials are stored for this key.",
+                None,
+            ))
+            .into_response();
+        }
+        Err(e) => return db_error(e, DB_ERROR),
+    };
+
+    let credentials = match vault.open(&prefix, &sealed) {
+        Ok(credentials) => credentials,
+        Err(e) => {
+            return WebregError::internal(
+                "The stored credentials couldn't be opened; set them again.",
+                e,
+            )
+            .into_response();
+        }
+    };
+
+    (
+        StatusCode::OK,
+        Json(sync(&s, vault, &prefix, version, &credentials).await),
+    )
+        .into_response()
+}
+
+/// DELETE /vault/credentials
+///
+/// Deletes the requesting key's credentials. The cookie server keeps whatever credentials
+/// it last received.
+pub async fn delete_credentials(
+    Extension(prefix): Extension<String>,
+    State(s): State<Arc<WrapperState>>,
+) -> Response {
+    info!("DELETE /vault/credentials");
+
+    match s.schedule_db.delete_vault_credentials(&prefix) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "No credentials are stored for this key.",
+            None,
+        ))
+        .into_response(),
+        Err(e) => db_error(e, DB_ERROR),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+
+    use crate::types::tests::state;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    fn vault_state(name: &str) -> Arc<WrapperState> {
+        Arc::new(state(name, &["FA24"], json!({ "vaultMasterKey": KEY })))
+    }
+
+    async fn body(res: Response) -> (StatusCode, Value) {
+        let status = res.status();
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    async fn put(s: &Arc<WrapperState>, prefix: &str, password: &str) -> (StatusCode, Value) {
+        let credentials = BodyVaultCredentials {
+            username: " student ".to_owned(),
+            password: password.to_owned(),
+        };
+        body(
+            put_credentials(
+                Extension(prefix.to_owned()),
+                State(s.clone()),
+                ValidJson(credentials),
+            )
+            .await,
+        )
+        .await
+    }
+
+    async fn get(s: &Arc<WrapperState>, prefix: &str) -> (StatusCode, Value) {
+        body(get_credentials(Extension(prefix.to_owned()), State(s.clone())).await).await
+    }
+
+    #[tokio::test]
+    async fn test_credentials_round_trip() {
+        let s = vault_state("vault-round-trip");
+
+        let (status, put_body) = put(&s, "a", "hunter2").await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(1, put_body["version"]);
+        // There's no cookie server for the key, so the credentials are kept but not sent
+        assert_eq!(false, put_body["synced"]);
+        assert!(put_body["sync_error"].is_string());
+
+        let (status, entry) = get(&s, "a").await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("student", entry["username"]);
+        assert_eq!(1, entry["version"]);
+        assert!(entry["synced_version"].is_null());
+        assert!(entry.get("password").is_none());
+
+        // Rotating the credentials makes a new version, which opens to the new password
+        let (_, put_body) = put(&s, "a", "correct horse").await;
+        assert_eq!(2, put_body["version"]);
+        let (sealed, version) = s.schedule_db.get_vault_credentials("a").unwrap().unwrap();
+        assert_eq!(2, version);
+        let vault = s.credential_vault.as_ref().unwrap();
+        assert_eq!("correct horse", vault.open("a", &sealed).unwrap().password);
+
+        let (status, sync_body) =
+            body(post_sync_credentials(Extension("a".to_owned()), State(s.clone())).await).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(2, sync_body["version"]);
+
+        let res = delete_credentials(Extension("a".to_owned()), State(s.clone())).await;
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+        assert_eq!(StatusCode::NOT_FOUND, get(&s, "a").await.0);
+    }
+
+    #[tokio::test]
+    async fn test_credentials_per_key() {
+        let s = vault_state("vault-per-key");
+        put(&s, "a", "hunter2").await;
+
+        // Another key can't see, send, or delete the credentials
+        assert_eq!(StatusCode::NOT_FOUND, get(&s, "b").await.0);
+        let res = post_sync_credentials(Extension("b".to_owned()), State(s.clone())).await;
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+        let res = delete_credentials(Extension("b".to_owned()), State(s.clone())).await;
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+        assert_eq!(StatusCode::OK, get(&s, "a").await.0);
+
+        // The sealed credentials are bound to their key, so they can't be opened as another
+        let (sealed, _) = s.schedule_db.get_vault_credentials("a").unwrap().unwrap();
+        let vault = s.credential_vault.as_ref().unwrap();
+        assert!(vault.open("b", &sealed).is_err());
+
+        // Each key's versions are its own
+        let (_, put_body) = put(&s, "b", "swordfish").await;
+        assert_eq!(1, put_body["version"]);
+        let (_, entry) = get(&s, "a").await;
+        assert_eq!(1, entry["version"]);
+    }
+
+    #[tokio::test]
+    async fn test_vault_disabled() {
+        let s = Arc::new(state("vault-disabled", &["FA24"], json!({})));
+        let (status, _) = put(&s, "a", "hunter2").await;
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, status);
+        assert_eq!(StatusCode::NOT_FOUND, get(&s, "a").await.0);
+    }
+}
@@ -1,6 +1,7 @@
 //! All methods in this file make the assumption that the provided cookies from
 //! the request header exist AND only has ASCII characters. This is enforced by
-//! the middleware.
+//! the middleware, which also fills in the header from the student's session when
+//! a session token is used instead.
 
 use std::sync::Arc;
 
@@ -9,18 +10,34 @@ use axum::http::header::COOKIE;
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use serde_json::json;
-use tracing::info;
-use webweg::types::EnrollmentStatus;
+use chrono::Local;
+use futures::stream::{self, StreamExt};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+use webweg::types::{EnrollmentStatus, ScheduledSection as WebRegSection, WrapperError};
 use webweg::wrapper::input_types::{AddType, ExplicitAddType};
 
+use crate::drift::ResponseKind;
+use crate::error::WebregError;
+use crate::receipts::{find_enrolled, record_change, EnrollmentAction, SwapState};
+use crate::retry::Idempotency;
+use crate::schedule::{check_add, AddConflict, MeetingSlot, ScheduledSection, SubSession};
 use crate::server::types::{
-    ApiErrorType, BodyAddInfo, BodyPlanAdd, BodyScheduleNameChange, BodySectionId,
-    BodySectionScheduleNameId, RawParsedApiResp, RawQueryStr, ScheduleQueryStr,
+    error_response, ApiErrorType, BodyAddInfo, BodyBulkAdd, BodyPlanAdd, BodyScheduleNameChange,
+    BodySectionId, BodySectionScheduleNameId, BodySwapSections, DryRunQueryStr, ErrorCode,
+    FieldError, ForceQueryStr, RawParsedApiResp, RawQueryStr, ScheduleQueryStr,
 };
-use crate::server::util::{build_add_plan_object, build_add_section_object};
+use crate::server::util::{build_add_plan_object, build_add_section_object, to_scheduled_sections};
+use crate::server::validation::{extend_nested, ValidJson, Validate};
 use crate::types::WrapperState;
 
+/// The number of times that a dropped section is added back when a swap fails.
+const SWAP_RESTORE_ATTEMPTS: usize = 3;
+/// The largest number of sections that can be added at once.
+const MAX_BULK_SECTIONS: usize = 10;
+/// The number of sections that are validated with WebReg at once.
+const VALIDATE_CONCURRENCY: usize = 4;
+
 /// A function which should be called when the `register_term` endpoint is called.
 #[tracing::instrument(level = "info", skip(s))]
 pub async fn post_register_term(
@@ -31,11 +48,13 @@ pub async fn post_register_term(
     info!("POST endpoint `register_term` called");
 
     let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
-    s.c_wrapper
+    let requester = s
+        .c_wrapper
         .req(term.as_str())
         .override_cookies(cookies)
-        .parsed()
-        .associate_term()
+        .parsed();
+    s.webreg_retry
+        .run(Idempotency::Idempotent, || requester.associate_term())
         .await
         .map_or_else(
             |e| ApiErrorType::from(e).into_response(),
@@ -59,9 +78,21 @@ pub async fn get_schedule(
     let builder = s.c_wrapper.req(term.as_str()).override_cookies(cookies);
 
     if req_type.raw.unwrap_or(false) {
-        RawParsedApiResp::Raw(builder.raw().get_schedule(schedule_slice).await)
+        let req = builder.raw();
+        let resp = s
+            .webreg_retry
+            .run(Idempotency::Idempotent, || req.get_schedule(schedule_slice))
+            .await;
+        s.drift_tracker
+            .observe_result(ResponseKind::Schedule, &resp);
+        RawParsedApiResp::Raw(resp)
     } else {
-        RawParsedApiResp::Parsed(builder.parsed().get_schedule(schedule_slice).await)
+        let req = builder.parsed();
+        RawParsedApiResp::Parsed(
+            s.webreg_retry
+                .run(Idempotency::Idempotent, || req.get_schedule(schedule_slice))
+                .await,
+        )
     }
     .into_response()
 }
@@ -80,9 +111,19 @@ pub async fn get_schedule_list(
     let builder = s.c_wrapper.req(term.as_str()).override_cookies(cookies);
 
     if req_type.raw.unwrap_or(false) {
-        RawParsedApiResp::Raw(builder.raw().get_schedule_list().await)
+        let req = builder.raw();
+        RawParsedApiResp::Raw(
+            s.webreg_retry
+                .run(Idempotency::Idempotent, || req.get_schedule_list())
+                .await,
+        )
     } else {
-        RawParsedApiResp::Parsed(builder.parsed().get_schedule_list().await)
+        let req = builder.parsed();
+        RawParsedApiResp::Parsed(
+            s.webreg_retry
+                .run(Idempotency::Idempotent, || req.get_schedule_list())
+                .await,
+        )
     }
     .into_response()
 }
@@ -97,12 +138,14 @@ pub async fn get_events(
     info!("GET endpoint `events` called");
     let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
 
-    let req = s
+    let requester = s
         .c_wrapper
         .req(term.as_str())
         .override_cookies(cookies)
-        .parsed()
-        .get_events()
+        .parsed();
+    let req = s
+        .webreg_retry
+        .run(Idempotency::Idempotent, || requester.get_events())
         .await;
 
     req.map_or_else(
@@ -117,17 +160,21 @@ pub async fn post_rename_schedule(
     headers: HeaderMap,
     Path(term): Path<String>,
     State(s): State<Arc<WrapperState>>,
-    Json(body): Json<BodyScheduleNameChange>,
+    ValidJson(body): ValidJson<BodyScheduleNameChange>,
 ) -> Response {
     info!("POST endpoint `rename_schedule` called");
     let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
 
-    let req = s
+    let requester = s
         .c_wrapper
         .req(term.as_str())
         .override_cookies(cookies)
-        .parsed()
-        .rename_schedule(body.old_name, body.new_name)
+        .parsed();
+    let req = s
+        .webreg_retry
+        .run(Idempotency::NonIdempotent, || {
+            requester.rename_schedule(&body.old_name, &body.new_name)
+        })
         .await;
 
     req.map_or_else(
@@ -137,55 +184,135 @@ pub async fn post_rename_schedule(
 }
 
 /// A function which should be called when the `validate_add_section` endpoint is called.
+///
+/// Along with whether WebReg would accept the section, the body has a warning for each
+/// enrollment restriction in the section's notes (see [`crate::restrictions`]).
 #[tracing::instrument(level = "info", skip(s))]
 pub async fn post_validate_add_section(
     headers: HeaderMap,
     Path(term): Path<String>,
     State(s): State<Arc<WrapperState>>,
-    Json(body): Json<BodyAddInfo>,
+    ValidJson(body): ValidJson<BodyAddInfo>,
 ) -> Response {
     info!("POST endpoint `validate_add_section` called");
 
     let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
     let add_req = build_add_section_object(&body);
-    let req = s
+    let requester = s
         .c_wrapper
         .req(term.as_str())
         .override_cookies(cookies)
-        .parsed()
-        .validate_add_section(AddType::DecideForMe, &add_req)
+        .parsed();
+    let req = s
+        .webreg_retry
+        .run(Idempotency::Idempotent, || {
+            requester.validate_add_section(AddType::DecideForMe, &add_req)
+        })
         .await;
 
     req.map_or_else(
         |e| ApiErrorType::from(e).into_response(),
-        |b| (StatusCode::OK, Json(json!({ "success": b }))).into_response(),
+        |b| {
+            let warnings = restriction_warnings(&s, &term, &body.section_id);
+            (
+                StatusCode::OK,
+                Json(json!({ "success": b, "warnings": warnings })),
+            )
+                .into_response()
+        },
     )
 }
 
 /// A function which should be called when the `add_section` endpoint is called.
+///
+/// Unless `?force=true` is given, the section is only added if the student isn't already
+/// enrolled in (or waitlisted for) the same course and the section doesn't overlap any
+/// section they're in; otherwise, a 409 describing the conflicting section is returned.
+///
+/// Once WebReg has responded, the student's schedule is fetched again to make sure that the
+/// section was added, and an [`crate::receipts::EnrollmentReceipt`] is returned.
+///
+/// With `?dry_run=true`, nothing is added; instead, the section is checked as above, WebReg
+/// is asked whether it'd accept the section, and the body says whether the student would be
+/// enrolled or waitlisted (see [`preview_add`]).
 #[tracing::instrument(level = "info", skip(s))]
 pub async fn post_add_section(
     headers: HeaderMap,
     Path(term): Path<String>,
+    Query(query): Query<ForceQueryStr>,
+    Query(dry_run): Query<DryRunQueryStr>,
     State(s): State<Arc<WrapperState>>,
-    Json(body): Json<BodyAddInfo>,
+    ValidJson(body): ValidJson<BodyAddInfo>,
 ) -> Response {
     info!("POST endpoint `add_section` called");
 
     let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
-    let add_req = build_add_section_object(&body);
-    let req = s
+    let requester = s
         .c_wrapper
         .req(term.as_str())
         .override_cookies(cookies)
-        .parsed()
-        .add_section(AddType::DecideForMe, add_req, body.validate.unwrap_or(true))
+        .parsed();
+
+    let before = match s
+        .webreg_retry
+        .run(Idempotency::Idempotent, || requester.get_schedule(None))
+        .await
+    {
+        Ok(before) => before,
+        Err(err) => return ApiErrorType::from(err).into_response(),
+    };
+
+    if dry_run.dry_run.unwrap_or(false) {
+        let mut current = current_sections(&s, &term, &before);
+        let preview = preview_add(
+            &s,
+            &term,
+            cookies,
+            &body,
+            &mut current,
+            query.force.unwrap_or(false),
+            true,
+        )
         .await;
+        return dry_run_response("add", preview);
+    }
 
-    req.map_or_else(
-        |e| ApiErrorType::from(e).into_response(),
-        |b| (StatusCode::OK, Json(json!({ "success": b }))).into_response(),
-    )
+    if !query.force.unwrap_or(false) {
+        if let Some(response) = guard_add_section(&s, &term, &body.section_id, &before) {
+            return response;
+        }
+    }
+
+    let req = s
+        .webreg_retry
+        .run(Idempotency::NonIdempotent, || {
+            requester.add_section(
+                AddType::DecideForMe,
+                build_add_section_object(&body),
+                body.validate.unwrap_or(true),
+            )
+        })
+        .await;
+
+    match req {
+        Ok(b) => {
+            let after = s
+                .webreg_retry
+                .run(Idempotency::Idempotent, || requester.get_schedule(None))
+                .await
+                .ok();
+            enrollment_receipt(
+                &s,
+                &term,
+                EnrollmentAction::Add,
+                &body.section_id,
+                b,
+                &before,
+                after.as_deref(),
+            )
+        }
+        Err(e) => ApiErrorType::from(e).into_response(),
+    }
 }
 
 /// A function which should be called when the `validate_add_plan` endpoint is called.
@@ -194,18 +321,22 @@ pub async fn post_validate_add_plan(
     headers: HeaderMap,
     Path(term): Path<String>,
     State(s): State<Arc<WrapperState>>,
-    Json(body): Json<BodyPlanAdd>,
+    ValidJson(body): ValidJson<BodyPlanAdd>,
 ) -> Response {
     info!("POST endpoint `validate_add_plan` called");
 
     let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
     let plan_add = build_add_plan_object(&body);
-    let req = s
+    let requester = s
         .c_wrapper
         .req(term.as_str())
         .override_cookies(cookies)
-        .parsed()
-        .validate_add_to_plan(&plan_add)
+        .parsed();
+    let req = s
+        .webreg_retry
+        .run(Idempotency::Idempotent, || {
+            requester.validate_add_to_plan(&plan_add)
+        })
         .await;
 
     req.map_or_else(
@@ -215,23 +346,54 @@ pub async fn post_validate_add_plan(
 }
 
 /// A function which should be called when the `add_plan` endpoint is called.
+///
+/// With `?dry_run=true`, nothing is planned; instead, the body says whether WebReg would
+/// accept the section into the plan.
 #[tracing::instrument(level = "info", skip(s))]
 pub async fn post_add_plan(
     headers: HeaderMap,
     Path(term): Path<String>,
+    Query(dry_run): Query<DryRunQueryStr>,
     State(s): State<Arc<WrapperState>>,
-    Json(body): Json<BodyPlanAdd>,
+    ValidJson(body): ValidJson<BodyPlanAdd>,
 ) -> Response {
     info!("POST endpoint `add_plan` called");
 
     let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
-    let plan_add = build_add_plan_object(&body);
-    let req = s
+    let requester = s
         .c_wrapper
         .req(term.as_str())
         .override_cookies(cookies)
-        .parsed()
-        .add_to_plan(plan_add, body.validate.unwrap_or(true))
+        .parsed();
+
+    if dry_run.dry_run.unwrap_or(false) {
+        let plan_add = build_add_plan_object(&body);
+        let result = s
+            .webreg_retry
+            .run(Idempotency::Idempotent, || {
+                requester.validate_add_to_plan(&plan_add)
+            })
+            .await;
+        let preview = match result {
+            Ok(b) => json!({
+                "section_id": body.section_id,
+                "would_succeed": b,
+                "messages": if b { vec![] } else { vec!["WebReg didn't accept the section.".to_owned()] },
+            }),
+            Err(WrapperError::WebRegError(message)) => json!({
+                "section_id": body.section_id,
+                "would_succeed": false,
+                "messages": [message],
+            }),
+            Err(e) => return ApiErrorType::from(e).into_response(),
+        };
+        return dry_run_response("add_plan", preview);
+    }
+    let req = s
+        .webreg_retry
+        .run(Idempotency::NonIdempotent, || {
+            requester.add_to_plan(build_add_plan_object(&body), body.validate.unwrap_or(true))
+        })
         .await;
 
     req.map_or_else(
@@ -241,22 +403,64 @@ pub async fn post_add_plan(
 }
 
 /// A function which should be called when the `remove_plan` endpoint is called.
+///
+/// With `?dry_run=true`, nothing is removed; instead, the body says whether the section is
+/// planned in the schedule.
 #[tracing::instrument(level = "info", skip(s))]
 pub async fn post_remove_plan(
     headers: HeaderMap,
     Path(term): Path<String>,
+    Query(dry_run): Query<DryRunQueryStr>,
     State(s): State<Arc<WrapperState>>,
-    Json(body): Json<BodySectionScheduleNameId>,
+    ValidJson(body): ValidJson<BodySectionScheduleNameId>,
 ) -> Response {
     info!("POST endpoint `remove_plan` called");
     let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
 
-    let req = s
+    let requester = s
         .c_wrapper
         .req(term.as_str())
         .override_cookies(cookies)
-        .parsed()
-        .remove_from_plan(body.section_id.as_str(), body.schedule_name.as_deref())
+        .parsed();
+
+    if dry_run.dry_run.unwrap_or(false) {
+        let schedule = match s
+            .webreg_retry
+            .run(Idempotency::Idempotent, || {
+                requester.get_schedule(body.schedule_name.as_deref())
+            })
+            .await
+        {
+            Ok(schedule) => schedule,
+            Err(e) => return ApiErrorType::from(e).into_response(),
+        };
+
+        let planned = schedule.iter().any(|sec| {
+            sec.section_id == body.section_id
+                && matches!(sec.enrolled_status, EnrollmentStatus::Planned)
+        });
+        let messages: Vec<String> = if planned {
+            vec![]
+        } else {
+            vec![format!(
+                "Section {} isn't planned in this schedule.",
+                body.section_id
+            )]
+        };
+        return dry_run_response(
+            "remove_plan",
+            json!({
+                "section_id": body.section_id,
+                "would_succeed": planned,
+                "messages": messages,
+            }),
+        );
+    }
+    let req = s
+        .webreg_retry
+        .run(Idempotency::NonIdempotent, || {
+            requester.remove_from_plan(body.section_id.as_str(), body.schedule_name.as_deref())
+        })
         .await;
 
     req.map_or_else(
@@ -266,12 +470,19 @@ pub async fn post_remove_plan(
 }
 
 /// A function which should be called when the `drop_section` endpoint is called.
+///
+/// Once WebReg has responded, the student's schedule is fetched again to make sure that the
+/// section was dropped, and an [`crate::receipts::EnrollmentReceipt`] is returned.
+///
+/// With `?dry_run=true`, nothing is dropped; instead, the body says what the student would
+/// lose (see [`preview_drop`]).
 #[tracing::instrument(level = "info", skip(s))]
 pub async fn post_drop_section(
     headers: HeaderMap,
     Path(term): Path<String>,
+    Query(dry_run): Query<DryRunQueryStr>,
     State(s): State<Arc<WrapperState>>,
-    Json(body): Json<BodySectionId>,
+    ValidJson(body): ValidJson<BodySectionId>,
 ) -> Response {
     info!("POST endpoint `drop_section` called");
     let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
@@ -282,58 +493,1110 @@ pub async fn post_drop_section(
         .override_cookies(cookies)
         .parsed();
 
-    let enroll_status = match requester.get_schedule(None).await {
-        Ok(o) => {
-            let sec = o
-                .into_iter()
-                .filter(|s| match s.enrolled_status {
-                    EnrollmentStatus::Enrolled => true,
-                    EnrollmentStatus::Waitlist { .. } => true,
-                    EnrollmentStatus::Planned => false,
-                    EnrollmentStatus::Unknown => false,
-                })
-                .find(|d| d.section_id == body.section_id.as_str());
-
-            match sec {
-                None => {
-                    return ApiErrorType::from((
-                        StatusCode::NOT_FOUND,
-                        format!(
-                            "You don't appeared to be enrolled in section {}",
-                            body.section_id
-                        ),
-                        None,
-                    ))
-                    .into_response();
-                }
-                Some(s) => match s.enrolled_status {
-                    EnrollmentStatus::Enrolled => ExplicitAddType::Enroll,
-                    EnrollmentStatus::Waitlist { .. } => ExplicitAddType::Waitlist,
-                    s => {
-                        return ApiErrorType::from((
-                            StatusCode::NOT_FOUND,
-                            format!(
-                                "You don't appeared to be enrolled in section {}",
-                                body.section_id
-                            ),
-                            Some(format!("Your enrollment status: {:?}", s)),
-                        ))
-                        .into_response();
-                    }
-                },
-            }
-        }
+    let before = match s
+        .webreg_retry
+        .run(Idempotency::Idempotent, || requester.get_schedule(None))
+        .await
+    {
+        Ok(o) => o,
         Err(err) => {
             return ApiErrorType::from(err).into_response();
         }
     };
 
-    let req = requester
-        .drop_section(enroll_status, body.section_id.as_str())
+    if dry_run.dry_run.unwrap_or(false) {
+        if let Some(sec) = find_enrolled(&before, body.section_id.as_str()) {
+            return dry_run_response("drop", preview_drop(sec));
+        }
+    }
+
+    let waitlisted = match find_enrolled(&before, body.section_id.as_str()) {
+        Some(sec) => matches!(sec.enrolled_status, EnrollmentStatus::Waitlist { .. }),
+        None => {
+            return ApiErrorType::from((
+                StatusCode::NOT_FOUND,
+                format!(
+                    "You don't appeared to be enrolled in section {}",
+                    body.section_id
+                ),
+                None,
+            ))
+            .into_response();
+        }
+    };
+
+    let req = s
+        .webreg_retry
+        .run(Idempotency::NonIdempotent, || {
+            requester.drop_section(drop_type(waitlisted), body.section_id.as_str())
+        })
         .await;
 
-    req.map_or_else(
-        |e| ApiErrorType::from(e).into_response(),
-        |b| (StatusCode::OK, Json(json!({ "success": b }))).into_response(),
+    match req {
+        Ok(b) => {
+            let after = s
+                .webreg_retry
+                .run(Idempotency::Idempotent, || requester.get_schedule(None))
+                .await
+                .ok();
+            enrollment_receipt(
+                &s,
+                &term,
+                EnrollmentAction::Drop,
+                &body.section_id,
+                b,
+                &before,
+                after.as_deref(),
+            )
+        }
+        Err(e) => ApiErrorType::from(e).into_response(),
+    }
+}
+
+/// POST /live/:term/swap_sections
+/// Drops one section and adds another in its place. Unless `?force=true` is given, the new
+/// section is checked against the student's schedule without the dropped section, as with
+/// `add_section`. If the new section isn't in the student's schedule after it's added, the
+/// dropped section is added back with its original grading option and units, so that the
+/// student doesn't lose their seat. A waitlisted section that's added back goes to the end
+/// of the waitlist. If the schedule can't be fetched after the add, it isn't known whether
+/// the new section was added, so the dropped section isn't added back (which could leave
+/// the student in both).
+///
+/// The body has a receipt for each change that was made, and a `state` of `swapped`,
+/// `restored` (the dropped section was added back), `not_restored`, or `unknown`.
+///
+/// With `?dry_run=true`, nothing is dropped or added; instead, the body has what the drop
+/// and the add would do. WebReg only validates a section against the student's current
+/// schedule, so the new section isn't validated with WebReg, since it would be judged
+/// against a schedule that still has the dropped section.
+#[tracing::instrument(level = "info", skip(s))]
+pub async fn post_swap_sections(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    Query(query): Query<ForceQueryStr>,
+    Query(dry_run): Query<DryRunQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodySwapSections>,
+) -> Response {
+    info!("POST endpoint `swap_sections` called");
+
+    let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
+    let requester = s
+        .c_wrapper
+        .req(term.as_str())
+        .override_cookies(cookies)
+        .parsed();
+
+    let before = match s
+        .webreg_retry
+        .run(Idempotency::Idempotent, || requester.get_schedule(None))
+        .await
+    {
+        Ok(before) => before,
+        Err(err) => return ApiErrorType::from(err).into_response(),
+    };
+
+    let Some(dropped) = find_enrolled(&before, &body.drop_section_id).cloned() else {
+        return ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            format!(
+                "You don't appeared to be enrolled in section {}",
+                body.drop_section_id
+            ),
+            None,
+        ))
+        .into_response();
+    };
+
+    if dry_run.dry_run.unwrap_or(false) {
+        let remaining: Vec<_> = before
+            .iter()
+            .filter(|sec| sec.section_id != dropped.section_id)
+            .cloned()
+            .collect();
+        let mut current = current_sections(&s, &term, &remaining);
+        let add = preview_add(
+            &s,
+            &term,
+            cookies,
+            &body.add,
+            &mut current,
+            query.force.unwrap_or(false),
+            false,
+        )
+        .await;
+        return dry_run_response(
+            "swap",
+            json!({
+                "would_succeed": add["would_succeed"],
+                "drop": preview_drop(&dropped),
+                "add": add,
+            }),
+        );
+    }
+
+    if !query.force.unwrap_or(false) {
+        let remaining: Vec<_> = before
+            .iter()
+            .filter(|sec| sec.section_id != dropped.section_id)
+            .cloned()
+            .collect();
+        if let Some(response) = guard_add_section(&s, &term, &body.add.section_id, &remaining) {
+            return response;
+        }
+    }
+
+    let waitlisted = matches!(dropped.enrolled_status, EnrollmentStatus::Waitlist { .. });
+
+    let drop_success = match s
+        .webreg_retry
+        .run(Idempotency::NonIdempotent, || {
+            requester.drop_section(drop_type(waitlisted), dropped.section_id.as_str())
+        })
+        .await
+    {
+        Ok(b) => b,
+        Err(e) => return ApiErrorType::from(e).into_response(),
+    };
+
+    let after_drop = s
+        .webreg_retry
+        .run(Idempotency::Idempotent, || requester.get_schedule(None))
+        .await
+        .ok();
+    let drop = record_change(
+        &s,
+        &term,
+        EnrollmentAction::Drop,
+        &dropped.section_id,
+        drop_success,
+        &before,
+        after_drop.as_deref(),
+    );
+
+    // A verified drop means that the schedule after the drop was fetched
+    let Some(after_drop) = after_drop.filter(|_| drop.verified) else {
+        return (
+            StatusCode::BAD_GATEWAY,
+            Json(json!({ "swapped": false, "drop": drop })),
+        )
+            .into_response();
+    };
+
+    let add_result = s
+        .webreg_retry
+        .run(Idempotency::NonIdempotent, || {
+            requester.add_section(
+                AddType::DecideForMe,
+                build_add_section_object(&body.add),
+                body.add.validate.unwrap_or(true),
+            )
+        })
+        .await;
+    let after_add = s
+        .webreg_retry
+        .run(Idempotency::Idempotent, || requester.get_schedule(None))
+        .await
+        .ok();
+    let add = record_change(
+        &s,
+        &term,
+        EnrollmentAction::Add,
+        &body.add.section_id,
+        matches!(add_result, Ok(true)),
+        &after_drop,
+        after_add.as_deref(),
+    );
+
+    let state = SwapState::after_add(&body.add.section_id, after_add.as_deref());
+    let after_add = match (state, after_add) {
+        (Some(SwapState::Swapped), _) => {
+            return (
+                StatusCode::OK,
+                Json(json!({
+                    "swapped": true,
+                    "state": SwapState::Swapped,
+                    "drop": drop,
+                    "add": add,
+                })),
+            )
+                .into_response();
+        }
+        (None, Some(after_add)) => after_add,
+        _ => {
+            warn!(
+                "[{term}] Section {} was dropped for a swap, but whether {} was added is unknown.",
+                dropped.section_id, body.add.section_id
+            );
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(json!({
+                    "swapped": null,
+                    "state": SwapState::Unknown,
+                    "error": add_result.err().map(error_message),
+                    "drop": drop,
+                    "add": add,
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    let readd = BodyAddInfo {
+        section_id: dropped.section_id.clone(),
+        grading_option: Some(dropped.grade_option.clone()),
+        unit_count: Some(dropped.units),
+        validate: Some(false),
+    };
+    let mut current = after_add;
+    let mut restore = None;
+    for _ in 0..SWAP_RESTORE_ATTEMPTS {
+        let success = s
+            .webreg_retry
+            .run(Idempotency::NonIdempotent, || {
+                requester.add_section(
+                    add_type(waitlisted),
+                    build_add_section_object(&readd),
+                    false,
+                )
+            })
+            .await
+            .unwrap_or(false);
+        let after = s
+            .webreg_retry
+            .run(Idempotency::Idempotent, || requester.get_schedule(None))
+            .await
+            .ok();
+        let receipt = record_change(
+            &s,
+            &term,
+            EnrollmentAction::Add,
+            &dropped.section_id,
+            success,
+            &current,
+            after.as_deref(),
+        );
+
+        let verified = receipt.verified;
+        restore = Some(receipt);
+        if verified {
+            break;
+        }
+
+        if let Some(after) = after {
+            current = after;
+        }
+    }
+
+    let state = SwapState::after_restore(restore.as_ref());
+    if state == SwapState::NotRestored {
+        warn!(
+            "[{term}] Section {} was dropped for a swap, but could not be added back.",
+            dropped.section_id
+        );
+    }
+
+    (
+        StatusCode::BAD_GATEWAY,
+        Json(json!({
+            "swapped": false,
+            "state": state,
+            "restored": state == SwapState::Restored,
+            "error": add_result.err().map(error_message),
+            "drop": drop,
+            "add": add,
+            "restore": restore,
+        })),
     )
+        .into_response()
+}
+
+impl Validate for BodyBulkAdd {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        if self.sections.is_empty() || self.sections.len() > MAX_BULK_SECTIONS {
+            errors.push(FieldError::new(
+                "sections",
+                format!("must have between 1 and {MAX_BULK_SECTIONS} sections"),
+            ));
+        }
+        for (i, add) in self.sections.iter().enumerate() {
+            extend_nested(&mut errors, &format!("sections[{i}]"), add.validate());
+        }
+        errors
+    }
+}
+
+/// POST /live/:term/bulk_add
+/// Adds several sections, one at a time and in the order given. Unless `?force=true` is
+/// given, each section is checked against the student's schedule (including any sections
+/// added before it) as with `add_section`. A section that can't be added doesn't stop the
+/// others from being added; the body has a result for each section.
+///
+/// With `?dry_run=true`, nothing is added; instead, the body has what adding each section
+/// would do, as with `add_section`. Each section is checked against the sections before it
+/// that would be added, but WebReg only validates each against the current schedule.
+#[tracing::instrument(level = "info", skip(s))]
+pub async fn post_bulk_add(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    Query(query): Query<ForceQueryStr>,
+    Query(dry_run): Query<DryRunQueryStr>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyBulkAdd>,
+) -> Response {
+    info!("POST endpoint `bulk_add` called");
+
+    let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
+    let requester = s
+        .c_wrapper
+        .req(term.as_str())
+        .override_cookies(cookies)
+        .parsed();
+
+    let mut schedule = match s
+        .webreg_retry
+        .run(Idempotency::Idempotent, || requester.get_schedule(None))
+        .await
+    {
+        Ok(schedule) => schedule,
+        Err(err) => return ApiErrorType::from(err).into_response(),
+    };
+
+    if dry_run.dry_run.unwrap_or(false) {
+        let mut current = current_sections(&s, &term, &schedule);
+        let mut results = Vec::with_capacity(body.sections.len());
+        for add in &body.sections {
+            results.push(
+                preview_add(
+                    &s,
+                    &term,
+                    cookies,
+                    add,
+                    &mut current,
+                    query.force.unwrap_or(false),
+                    true,
+                )
+                .await,
+            );
+        }
+        return dry_run_response("bulk_add", json!({ "results": results }));
+    }
+
+    let mut results = Vec::with_capacity(body.sections.len());
+    for add in &body.sections {
+        if !query.force.unwrap_or(false) {
+            match find_add_conflict(&s, &term, &add.section_id, &schedule) {
+                Ok(None) => {}
+                Ok(Some(conflict)) => {
+                    results.push(json!({
+                        "section_id": add.section_id,
+                        "status": "conflict",
+                        "conflict": conflict,
+                    }));
+                    continue;
+                }
+                Err(e) => {
+                    let error = WebregError::from(e)
+                        .with_message("Failed to check the section for conflicts.");
+                    error.log_context();
+                    results.push(json!({
+                        "section_id": add.section_id,
+                        "status": "error",
+                        "code": error.code(),
+                        "error": error.message(),
+                    }));
+                    continue;
+                }
+            }
+        }
+
+        let webreg_success = match s
+            .webreg_retry
+            .run(Idempotency::NonIdempotent, || {
+                requester.add_section(
+                    AddType::DecideForMe,
+                    build_add_section_object(add),
+                    add.validate.unwrap_or(true),
+                )
+            })
+            .await
+        {
+            Ok(b) => b,
+            Err(e) => {
+                let error = WebregError::from(e);
+                error.log_context();
+                results.push(json!({
+                    "section_id": add.section_id,
+                    "status": "error",
+                    "code": error.code(),
+                    "error": error.message(),
+                }));
+                continue;
+            }
+        };
+
+        let after = s
+            .webreg_retry
+            .run(Idempotency::Idempotent, || requester.get_schedule(None))
+            .await
+            .ok();
+        let receipt = record_change(
+            &s,
+            &term,
+            EnrollmentAction::Add,
+            &add.section_id,
+            webreg_success,
+            &schedule,
+            after.as_deref(),
+        );
+
+        results.push(json!({
+            "section_id": add.section_id,
+            "status": if receipt.verified { "added" } else { "unverified" },
+            "receipt": receipt,
+        }));
+
+        if let Some(after) = after {
+            schedule = after;
+        }
+    }
+
+    (StatusCode::OK, Json(json!({ "results": results }))).into_response()
+}
+
+/// POST /live/:term/validate_schedule
+/// Asks WebReg whether each section of a candidate schedule could be added (as with
+/// `validate_add_section`), several at a time, without adding any of them. The body has a
+/// result for each section, in the order given, with WebReg's messages for the sections it
+/// wouldn't accept, and an overall verdict: `enrollable` if every section would be accepted,
+/// `not_enrollable` if WebReg turned any down, and `unknown` if WebReg couldn't be asked
+/// about some section. Each section is checked against the student's current schedule, not
+/// against the other sections in the candidate schedule. Each result also has a warning for
+/// each enrollment restriction in the section's notes.
+#[tracing::instrument(level = "info", skip(s))]
+pub async fn post_validate_schedule(
+    headers: HeaderMap,
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyBulkAdd>,
+) -> Response {
+    info!("POST endpoint `validate_schedule` called");
+
+    let cookies = headers.get(COOKIE).unwrap().to_str().unwrap();
+    let requester = s
+        .c_wrapper
+        .req(term.as_str())
+        .override_cookies(cookies)
+        .parsed();
+
+    let (requester, retry) = (&requester, &s.webreg_retry);
+    let results: Vec<_> = stream::iter(body.sections)
+        .map(|add| async move {
+            let add_req = build_add_section_object(&add);
+            let result = retry
+                .run(Idempotency::Idempotent, || {
+                    requester.validate_add_section(AddType::DecideForMe, &add_req)
+                })
+                .await;
+            (add, result)
+        })
+        .buffered(VALIDATE_CONCURRENCY)
+        .collect()
+        .await;
+
+    let results = results
+        .into_iter()
+        .map(|(add, result)| {
+            let warnings = restriction_warnings(&s, &term, &add.section_id);
+            (add.section_id, result, warnings)
+        })
+        .collect();
+
+    (StatusCode::OK, Json(schedule_validation(results))).into_response()
+}
+
+/// Builds the body of the response to `validate_schedule`.
+///
+/// # Parameters
+/// - `results`: Each section's ID, whether WebReg would accept it, and the warnings about
+///   its enrollment restrictions.
+///
+/// # Returns
+/// The result for each section, and the overall verdict. A section that WebReg turned down
+/// makes the schedule `not_enrollable`, even if WebReg couldn't be asked about another.
+fn schedule_validation(results: Vec<(String, Result<bool, WrapperError>, Vec<String>)>) -> Value {
+    let (mut rejected, mut unknown) = (false, false);
+    let results: Vec<_> = results
+        .into_iter()
+        .map(|(section_id, result, warnings)| match result {
+            Ok(true) => json!({
+                "section_id": section_id,
+                "status": "valid",
+                "messages": [],
+                "warnings": warnings,
+            }),
+            Ok(false) => {
+                rejected = true;
+                json!({
+                    "section_id": section_id,
+                    "status": "invalid",
+                    "messages": ["WebReg didn't accept the section."],
+                    "warnings": warnings,
+                })
+            }
+            Err(WrapperError::WebRegError(message)) => {
+                rejected = true;
+                json!({
+                    "section_id": section_id,
+                    "status": "invalid",
+                    "messages": [message],
+                    "warnings": warnings,
+                })
+            }
+            Err(e) => {
+                unknown = true;
+                let error = WebregError::from(e);
+                error.log_context();
+                json!({
+                    "section_id": section_id,
+                    "status": "error",
+                    "messages": [error.message()],
+                    "warnings": warnings,
+                    "code": error.code(),
+                    "error": error.message(),
+                })
+            }
+        })
+        .collect();
+
+    let verdict = if rejected {
+        "not_enrollable"
+    } else if unknown {
+        "unknown"
+    } else {
+        "enrollable"
+    };
+
+    json!({
+        "verdict": verdict,
+        "enrollable": verdict == "enrollable",
+        "results": results,
+    })
+}
+
+/// Describes the enrollment restrictions in a section's notes, and when the section opens to
+/// non-majors if it hasn't yet, for warning a student who's about to add it. The warnings
+/// are only informational, so if the notes can't be read, there are none.
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `term`: The term.
+/// - `section_id`: The section.
+///
+/// # Returns
+/// The warnings.
+fn restriction_warnings(s: &WrapperState, term: &str, section_id: &str) -> Vec<String> {
+    match s.schedule_db.get_section_notes(term, section_id) {
+        Ok(Some(notes)) => {
+            let mut warnings: Vec<_> = notes.restrictions.iter().map(|r| r.warning()).collect();
+            if let Some(window) = notes
+                .window
+                .filter(|w| w.is_pending(Local::now().date_naive()))
+            {
+                warnings.push(format!(
+                    "This section opens to non-majors on {}.",
+                    window.opens_to_non_majors
+                ));
+            }
+            warnings
+        }
+        Ok(None) => vec![],
+        Err(e) => {
+            warn!("[{term}] Failed to look up the notes of section {section_id}: {e}");
+            vec![]
+        }
+    }
+}
+
+/// Works out what adding a section would do, without adding it, for a dry run. Unless
+/// `force` is set, the section is checked against the given sections as with
+/// [`guard_add_section`]; then, if `ask_webreg` is set, WebReg is asked whether it'd accept
+/// the section, and the section's seats are looked up to tell whether the student would be
+/// enrolled or waitlisted. A section that would be added is added to the given sections, so
+/// that sections added after it in the same request are checked against it.
+///
+/// The `outcome` is `enroll` or `waitlist` if the section would be added, `add` if it would
+/// be but its seats couldn't be looked up, `conflict` if it conflicts with the schedule,
+/// `rejected` if WebReg wouldn't accept it, and `unknown` if it couldn't be checked.
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `term`: The term.
+/// - `cookies`: The student's WebReg cookies.
+/// - `add`: The section being added.
+/// - `current`: The sections that the student would be in, each with its course.
+/// - `force`: Whether to skip checking the section against `current`.
+/// - `ask_webreg`: Whether to ask WebReg to validate the section.
+///
+/// # Returns
+/// What would happen.
+async fn preview_add(
+    s: &WrapperState,
+    term: &str,
+    cookies: &str,
+    add: &BodyAddInfo,
+    current: &mut Vec<(String, ScheduledSection)>,
+    force: bool,
+    ask_webreg: bool,
+) -> Value {
+    let warnings = restriction_warnings(s, term, &add.section_id);
+    let not_added = |outcome: &str, details: Value| {
+        let mut preview = json!({
+            "section_id": add.section_id,
+            "outcome": outcome,
+            "would_succeed": false,
+            "warnings": warnings,
+        });
+        extend_object(&mut preview, details);
+        preview
+    };
+
+    let new_section = match lookup_section(s, term, &add.section_id) {
+        Ok(new_section) => new_section,
+        Err(e) => {
+            let error = WebregError::from(e).with_message("Failed to look up the section.");
+            return not_added("unknown", item_error(error));
+        }
+    };
+
+    if !force {
+        if let Some(conflict) = new_section
+            .as_ref()
+            .and_then(|(course, section)| check_add(course, section, current))
+        {
+            return not_added("conflict", json!({ "conflict": conflict }));
+        }
+    }
+
+    if ask_webreg {
+        let requester = s.c_wrapper.req(term).override_cookies(cookies).parsed();
+        let add_req = build_add_section_object(add);
+        let result = s
+            .webreg_retry
+            .run(Idempotency::Idempotent, || {
+                requester.validate_add_section(AddType::DecideForMe, &add_req)
+            })
+            .await;
+        match result {
+            Ok(true) => {}
+            Ok(false) => {
+                return not_added(
+                    "rejected",
+                    json!({ "messages": ["WebReg didn't accept the section."] }),
+                )
+            }
+            Err(WrapperError::WebRegError(message)) => {
+                return not_added("rejected", json!({ "messages": [message] }))
+            }
+            Err(e) => return not_added("unknown", item_error(e.into())),
+        }
+    }
+
+    // The seats are public, so they're looked up with the scraper's session
+    let mut seats = None;
+    if let Some((course, _)) = &new_section {
+        let mut parts = course.split_whitespace();
+        if let (Some(subject), Some(number)) = (parts.next(), parts.next()) {
+            seats = s
+                .wrapper
+                .req(term)
+                .parsed()
+                .get_enrollment_count(subject, number)
+                .await
+                .inspect_err(|e| warn!("[{term}] Failed to get seats for {course}: {e}"))
+                .ok()
+                .and_then(|sections| {
+                    sections
+                        .into_iter()
+                        .find(|sec| sec.section_id == add.section_id)
+                });
+        }
+    }
+
+    let outcome = match &seats {
+        Some(sec) if sec.available_seats > 0 => "enroll",
+        Some(_) => "waitlist",
+        None => "add",
+    };
+    if let Some(section) = new_section {
+        current.push(section);
+    }
+
+    json!({
+        "section_id": add.section_id,
+        "outcome": outcome,
+        "would_succeed": true,
+        "validated_with_webreg": ask_webreg,
+        "seats": seats.map(|sec| json!({
+            "available": sec.available_seats,
+            "total": sec.total_seats,
+            "waitlist": sec.waitlist_ct,
+        })),
+        "warnings": warnings,
+    })
+}
+
+/// Describes what dropping a section would do, for a dry run.
+///
+/// # Parameters
+/// - `section`: The section, as it appears in the student's schedule.
+///
+/// # Returns
+/// What would happen.
+fn preview_drop(section: &WebRegSection) -> Value {
+    let (status, warnings) = match section.enrolled_status {
+        EnrollmentStatus::Waitlist { waitlist_pos } => (
+            "waitlisted",
+            vec![format!(
+                "You would lose your place ({waitlist_pos}) on the waitlist; adding the section back puts you at the end."
+            )],
+        ),
+        _ => (
+            "enrolled",
+            vec!["You would lose your seat; it may be taken before you can add the section back.".to_owned()],
+        ),
+    };
+
+    json!({
+        "section_id": section.section_id,
+        "status": status,
+        "would_succeed": true,
+        "units": section.units,
+        "warnings": warnings,
+    })
+}
+
+/// Gets how a section should be dropped. The drop type can't be copied, so each attempt at
+/// a drop gets its own.
+///
+/// # Parameters
+/// - `waitlisted`: Whether the student is on the section's waitlist.
+///
+/// # Returns
+/// The drop type.
+fn drop_type(waitlisted: bool) -> ExplicitAddType {
+    if waitlisted {
+        ExplicitAddType::Waitlist
+    } else {
+        ExplicitAddType::Enroll
+    }
+}
+
+/// Gets how a section should be added back. Like [`drop_type`], each attempt gets its own.
+///
+/// # Parameters
+/// - `waitlisted`: Whether the student was on the section's waitlist.
+///
+/// # Returns
+/// The add type.
+fn add_type(waitlisted: bool) -> AddType {
+    if waitlisted {
+        AddType::Waitlist
+    } else {
+        AddType::Enroll
+    }
+}
+
+/// Builds the response to a dry run.
+///
+/// # Parameters
+/// - `action`: The change that would be made (e.g., `add`).
+/// - `preview`: What would happen.
+///
+/// # Returns
+/// The response.
+fn dry_run_response(action: &str, preview: Value) -> Response {
+    let mut body = json!({ "dry_run": true, "action": action });
+    extend_object(&mut body, preview);
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// Reports an error by only its code and message, for a result that's reported alongside
+/// others. Its context is logged.
+fn item_error(error: WebregError) -> Value {
+    error.log_context();
+    json!({ "code": error.code(), "error": error.message() })
+}
+
+/// Gets the message of an error from WebReg, logging its context.
+fn error_message(error: WrapperError) -> String {
+    WebregError::from(error).log_context().message().to_owned()
+}
+
+/// Copies the fields of one JSON object into another.
+fn extend_object(object: &mut Value, fields: Value) {
+    if let (Value::Object(object), Value::Object(fields)) = (object, fields) {
+        object.extend(fields);
+    }
+}
+
+/// Checks whether an add or drop took effect, records it in the action log, and builds the
+/// response for it. The response is a `200 OK` if the change can be seen in the student's
+/// schedule, and a `502 Bad Gateway` otherwise (e.g., if WebReg said that the change
+/// succeeded when it didn't); either way, the body is an [`crate::receipts::EnrollmentReceipt`].
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `term`: The term.
+/// - `action`: The change that was made.
+/// - `section_id`: The section that was added or dropped.
+/// - `webreg_success`: Whether WebReg said that the change succeeded.
+/// - `before`: The student's schedule before the change.
+/// - `after`: The student's schedule after the change, if it could be fetched.
+///
+/// # Returns
+/// The response.
+fn enrollment_receipt(
+    s: &WrapperState,
+    term: &str,
+    action: EnrollmentAction,
+    section_id: &str,
+    webreg_success: bool,
+    before: &[WebRegSection],
+    after: Option<&[WebRegSection]>,
+) -> Response {
+    let receipt = record_change(s, term, action, section_id, webreg_success, before, after);
+    let status = if receipt.verified {
+        StatusCode::OK
+    } else {
+        StatusCode::BAD_GATEWAY
+    };
+
+    (status, Json(receipt)).into_response()
+}
+
+/// Checks that adding a section wouldn't put the student in a course that they're already
+/// enrolled in (or waitlisted for), or in a section that overlaps one they're already in.
+/// Sections that aren't in the schedule database are left for WebReg to validate.
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `term`: The term.
+/// - `section_id`: The section being added.
+/// - `schedule`: The student's current schedule.
+///
+/// # Returns
+/// The response to return instead if the section shouldn't be added.
+fn guard_add_section(
+    s: &WrapperState,
+    term: &str,
+    section_id: &str,
+    schedule: &[WebRegSection],
+) -> Option<Response> {
+    let conflict = match find_add_conflict(s, term, section_id, schedule) {
+        Ok(conflict) => conflict?,
+        Err(e) => {
+            return Some(
+                WebregError::from(e)
+                    .with_message("Failed to look up section")
+                    .into_response(),
+            );
+        }
+    };
+
+    Some(error_response(
+        StatusCode::CONFLICT,
+        ErrorCode::ScheduleConflict,
+        "This section conflicts with your schedule. Use ?force=true to add it anyway.",
+        Some(json!({ "conflict": conflict })),
+    ))
+}
+
+/// Finds the reason that a section shouldn't be added to the student's schedule, if there
+/// is one. See [`guard_add_section`].
+///
+/// # Parameters
+/// The same as [`guard_add_section`].
+///
+/// # Returns
+/// The conflict, if any, or an error if the section couldn't be looked up.
+fn find_add_conflict(
+    s: &WrapperState,
+    term: &str,
+    section_id: &str,
+    schedule: &[WebRegSection],
+) -> rusqlite::Result<Option<AddConflict>> {
+    let Some((course, new_section)) = lookup_section(s, term, section_id)? else {
+        return Ok(None);
+    };
+
+    Ok(check_add(
+        &course,
+        &new_section,
+        &current_sections(s, term, schedule),
+    ))
+}
+
+/// Looks a section up in the schedule database, for checking it against the student's
+/// schedule.
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `term`: The term.
+/// - `section_id`: The section.
+///
+/// # Returns
+/// The section's course (e.g., `CSE 100`) and its meetings, or `None` if the section isn't
+/// in the database.
+fn lookup_section(
+    s: &WrapperState,
+    term: &str,
+    section_id: &str,
+) -> rusqlite::Result<Option<(String, ScheduledSection)>> {
+    let Some((course, section, meetings)) = s.schedule_db.get_section(term, section_id)? else {
+        return Ok(None);
+    };
+
+    let new_section = ScheduledSection {
+        term: term.to_owned(),
+        section_id: section.section_id,
+        title: format!("{} ({})", course.trim(), section.section_code.trim()),
+        sub_session: s
+            .term(term)
+            .map(|t| t.sub_session)
+            .unwrap_or_else(|| SubSession::from_term(term)),
+        slots: meetings.iter().filter_map(MeetingSlot::from_db).collect(),
+    };
+
+    Ok(Some((course, new_section)))
+}
+
+/// Gets the sections that the student is enrolled in (or waitlisted for), by course, for
+/// checking a new section against.
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `term`: The term.
+/// - `schedule`: The student's schedule.
+///
+/// # Returns
+/// The sections, each with its course (e.g., `CSE 100`).
+fn current_sections(
+    s: &WrapperState,
+    term: &str,
+    schedule: &[WebRegSection],
+) -> Vec<(String, ScheduledSection)> {
+    let enrolled: Vec<_> = schedule
+        .iter()
+        .filter(|sec| !matches!(sec.enrolled_status, EnrollmentStatus::Planned))
+        .cloned()
+        .collect();
+
+    enrolled
+        .iter()
+        .map(|sec| format!("{} {}", sec.subject_code, sec.course_code))
+        .zip(to_scheduled_sections(s, term, &enrolled))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statuses(body: &Value) -> Vec<(&str, &str)> {
+        body["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                (
+                    r["section_id"].as_str().unwrap(),
+                    r["status"].as_str().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_schedule_enrollable() {
+        let body = schedule_validation(vec![
+            ("1".to_owned(), Ok(true), vec![]),
+            (
+                "2".to_owned(),
+                Ok(true),
+                vec!["Department approval required".to_owned()],
+            ),
+        ]);
+
+        assert_eq!("enrollable", body["verdict"]);
+        assert_eq!(true, body["enrollable"]);
+        assert_eq!(vec![("1", "valid"), ("2", "valid")], statuses(&body));
+        // Restrictions are only warnings
+        assert_eq!(
+            json!(["Department approval required"]),
+            body["results"][1]["warnings"]
+        );
+    }
+
+    #[test]
+    fn test_validate_schedule_unknown() {
+        let body = schedule_validation(vec![
+            ("1".to_owned(), Ok(true), vec![]),
+            (
+                "2".to_owned(),
+                Err(WrapperError::BadStatusCode(
+                    503,
+                    Some("<html>Maintenance on webreg-node-3</html>".to_owned()),
+                )),
+                vec![],
+            ),
+        ]);
+
+        assert_eq!("unknown", body["verdict"]);
+        assert_eq!(false, body["enrollable"]);
+        assert_eq!(vec![("1", "valid"), ("2", "error")], statuses(&body));
+        assert_eq!("SERVICE_UNAVAILABLE", body["results"][1]["code"]);
+        assert!(body["results"][1]["error"].is_string());
+        // WebReg's response is only logged
+        assert!(!body.to_string().contains("webreg-node-3"));
+    }
+
+    #[test]
+    fn test_validate_schedule_not_enrollable() {
+        // A section that WebReg turned down decides the verdict, even though WebReg
+        // couldn't be asked about another
+        let body = schedule_validation(vec![
+            (
+                "1".to_owned(),
+                Err(WrapperError::BadStatusCode(503, None)),
+                vec![],
+            ),
+            (
+                "2".to_owned(),
+                Err(WrapperError::WebRegError("Class is full.".to_owned())),
+                vec![],
+            ),
+            ("3".to_owned(), Ok(false), vec![]),
+            ("4".to_owned(), Ok(true), vec![]),
+        ]);
+
+        assert_eq!("not_enrollable", body["verdict"]);
+        assert_eq!(false, body["enrollable"]);
+        assert_eq!(
+            vec![
+                ("1", "error"),
+                ("2", "invalid"),
+                ("3", "invalid"),
+                ("4", "valid")
+            ],
+            statuses(&body)
+        );
+        assert_eq!(json!(["Class is full."]), body["results"][1]["messages"]);
+        assert_eq!(
+            json!(["WebReg didn't accept the section."]),
+            body["results"][2]["messages"]
+        );
+    }
 }
@@ -1,23 +1,64 @@
+use std::future::Future;
 use std::sync::Arc;
 
+use crate::drift::ResponseKind;
+use crate::error::WebregError;
+use crate::retry::Idempotency;
 use crate::server::types::{
-    ApiErrorType, BodySearchType, CourseQueryStr, RawParsedApiResp, RawQueryStr, SubjListQueryStr,
+    ApiErrorType, BodyCourseInfoBatch, BodySearchType, CourseQueryStr, FieldError,
+    RawParsedApiResp, RawQueryStr, SubjListQueryStr,
 };
+use crate::server::util::term_info;
+use crate::server::validation::{require, ValidJson, Validate};
 use crate::types::WrapperState;
 use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
+use futures::stream::{self, StreamExt};
+use serde_json::{json, Value};
 use tracing::log::info;
+use webweg::types::{CourseSection, WrapperError};
+
+/// The most courses that can be looked up in one batch.
+const MAX_BATCH_COURSES: usize = 25;
+/// The number of courses in a batch that are looked up with WebReg at once.
+const BATCH_CONCURRENCY: usize = 4;
+
+impl Validate for BodyCourseInfoBatch {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        if self.courses.is_empty() || self.courses.len() > MAX_BATCH_COURSES {
+            errors.push(FieldError::new(
+                "courses",
+                format!("must have between 1 and {MAX_BATCH_COURSES} courses"),
+            ));
+        }
+
+        for (i, course) in self.courses.iter().enumerate() {
+            require(
+                &mut errors,
+                &format!("courses[{i}].subject"),
+                &course.subject,
+            );
+            require(&mut errors, &format!("courses[{i}].number"), &course.number);
+        }
+
+        errors
+    }
+}
 
 /// A function which should be called when the `terms` endpoint is called.
 #[tracing::instrument(level = "info", skip(s))]
 pub async fn get_all_terms(State(s): State<Arc<WrapperState>>) -> Response {
     info!("GET endpoint `terms` called");
-    s.wrapper.get_all_terms().await.map_or_else(
-        |e| ApiErrorType::from(e).into_response(),
-        |t| (StatusCode::OK, Json(t)).into_response(),
-    )
+    s.webreg_retry
+        .run(Idempotency::Idempotent, || s.wrapper.get_all_terms())
+        .await
+        .map_or_else(
+            |e| ApiErrorType::from(e).into_response(),
+            |t| (StatusCode::OK, Json(t)).into_response(),
+        )
 }
 
 /// A function which should be called when the `course_info` endpoint is called.
@@ -29,25 +70,142 @@ pub async fn get_course_info(
     State(s): State<Arc<WrapperState>>,
 ) -> Response {
     info!("GET endpoint `course_info` called");
-    let builder = s.wrapper.req(term.as_str());
+    let info = match term_info(&s, &term) {
+        Ok(info) => info,
+        Err(e) => return e.into_response(),
+    };
+    let builder = info.wrapper.req(term.as_str());
     if req_type.raw.unwrap_or(false) {
-        RawParsedApiResp::Raw(
-            builder
-                .raw()
-                .get_course_info(crsc.subject, crsc.number)
-                .await,
-        )
+        let req = builder.raw();
+        let resp = s
+            .webreg_retry
+            .run(Idempotency::Idempotent, || {
+                req.get_course_info(&crsc.subject, &crsc.number)
+            })
+            .await;
+        s.drift_tracker
+            .observe_result(ResponseKind::CourseInfo, &resp);
+        RawParsedApiResp::Raw(resp)
     } else {
+        let req = builder.parsed();
         RawParsedApiResp::Parsed(
-            builder
-                .parsed()
-                .get_course_info(crsc.subject, crsc.number)
+            s.webreg_retry
+                .run(Idempotency::Idempotent, || {
+                    req.get_course_info(&crsc.subject, &crsc.number)
+                })
                 .await,
         )
     }
     .into_response()
 }
 
+/// POST /live/:term/course_info/batch
+/// Looks up the sections of several courses at once, a few at a time, rather than with a
+/// request to `course_info` for each. The body has a result for each course, in the order
+/// given: its sections if it was looked up, or the error and its code if it couldn't be. A
+/// course that couldn't be looked up doesn't fail the others.
+#[tracing::instrument(level = "info", skip(s, body))]
+pub async fn post_course_info_batch(
+    Path(term): Path<String>,
+    State(s): State<Arc<WrapperState>>,
+    ValidJson(body): ValidJson<BodyCourseInfoBatch>,
+) -> Response {
+    info!(
+        "POST endpoint `course_info/batch` called with {} courses",
+        body.courses.len()
+    );
+
+    let info = match term_info(&s, &term) {
+        Ok(info) => info,
+        Err(e) => return e.into_response(),
+    };
+    let requester = info.wrapper.req(term.as_str()).parsed();
+    let (requester, retry) = (&requester, &s.webreg_retry);
+    let results = lookup_courses(body.courses, |subject, number| async move {
+        retry
+            .run(Idempotency::Idempotent, || {
+                requester.get_course_info(&subject, &number)
+            })
+            .await
+    })
+    .await;
+
+    (StatusCode::OK, Json(batch_response(results))).into_response()
+}
+
+/// Looks up each course in a batch, [`BATCH_CONCURRENCY`] at a time.
+///
+/// # Parameters
+/// - `courses`: The courses to look up.
+/// - `lookup`: Looks up a course, given its subject and number.
+///
+/// # Returns
+/// Each course with the result of looking it up, in the order that the courses were given,
+/// no matter which lookups finished first.
+async fn lookup_courses<T, F, Fut>(
+    courses: Vec<CourseQueryStr>,
+    lookup: F,
+) -> Vec<(CourseQueryStr, T)>
+where
+    F: Fn(String, String) -> Fut,
+    Fut: Future<Output = T>,
+{
+    stream::iter(courses)
+        .map(|course| {
+            let result = lookup(
+                course.subject.trim().to_owned(),
+                course.number.trim().to_owned(),
+            );
+            async move { (course, result.await) }
+        })
+        .buffered(BATCH_CONCURRENCY)
+        .collect()
+        .await
+}
+
+/// Builds the body of the response to a batch.
+///
+/// # Parameters
+/// - `results`: Each course with the result of looking it up.
+///
+/// # Returns
+/// The number of courses that were and weren't looked up, along with the result for each
+/// course.
+fn batch_response(
+    results: Vec<(CourseQueryStr, Result<Vec<CourseSection>, WrapperError>)>,
+) -> Value {
+    let mut failed = 0;
+    let results: Vec<_> = results
+        .into_iter()
+        .map(|(course, result)| match result {
+            Ok(sections) => json!({
+                "subject": course.subject,
+                "number": course.number,
+                "status": "ok",
+                "sections": sections,
+            }),
+            Err(e) => {
+                failed += 1;
+                let error = WebregError::from(&e);
+                error.log_context();
+                json!({
+                    "subject": course.subject,
+                    "number": course.number,
+                    "status": "error",
+                    "code": error.code(),
+                    "error": error.message(),
+                })
+            }
+        })
+        .collect();
+
+    json!({
+        "succeeded": results.len() - failed,
+        "failed": failed,
+        "results": results,
+    })
+}
+
 /// A function which should be called when the `prerequisites` endpoint is called.
 #[tracing::instrument(level = "info", skip(s))]
 pub async fn get_prerequisites(
@@ -58,19 +216,29 @@ pub async fn get_prerequisites(
 ) -> Response {
     info!("GET endpoint `prerequisites` called");
 
-    let builder = s.wrapper.req(term.as_str());
+    let info = match term_info(&s, &term) {
+        Ok(info) => info,
+        Err(e) => return e.into_response(),
+    };
+    let builder = info.wrapper.req(term.as_str());
     if req_type.raw.unwrap_or(false) {
-        RawParsedApiResp::Raw(
-            builder
-                .raw()
-                .get_prerequisites(crsc.subject, crsc.number)
-                .await,
-        )
+        let req = builder.raw();
+        let resp = s
+            .webreg_retry
+            .run(Idempotency::Idempotent, || {
+                req.get_prerequisites(&crsc.subject, &crsc.number)
+            })
+            .await;
+        s.drift_tracker
+            .observe_result(ResponseKind::Prerequisites, &resp);
+        RawParsedApiResp::Raw(resp)
     } else {
+        let req = builder.parsed();
         RawParsedApiResp::Parsed(
-            builder
-                .parsed()
-                .get_prerequisites(crsc.subject, crsc.number)
+            s.webreg_retry
+                .run(Idempotency::Idempotent, || {
+                    req.get_prerequisites(&crsc.subject, &crsc.number)
+                })
                 .await,
         )
     }
@@ -84,15 +252,34 @@ pub async fn get_search_courses(
     Query(req_type): Query<RawQueryStr>,
     State(s): State<Arc<WrapperState>>,
     // The Json needs to be the last parameter since its request body is being consumed.
-    Json(search_info): Json<BodySearchType>,
+    ValidJson(search_info): ValidJson<BodySearchType>,
 ) -> Response {
     info!("GET endpoint `search` called");
 
-    let builder = s.wrapper.req(term.as_str());
+    let info = match term_info(&s, &term) {
+        Ok(info) => info,
+        Err(e) => return e.into_response(),
+    };
+    let builder = info.wrapper.req(term.as_str());
     if req_type.raw.unwrap_or(false) {
-        RawParsedApiResp::Raw(builder.raw().search_courses(search_info.into()).await)
+        let req = builder.raw();
+        let resp = s
+            .webreg_retry
+            .run(Idempotency::Idempotent, || {
+                req.search_courses(search_info.clone().into())
+            })
+            .await;
+        s.drift_tracker.observe_result(ResponseKind::Search, &resp);
+        RawParsedApiResp::Raw(resp)
     } else {
-        RawParsedApiResp::Parsed(builder.parsed().search_courses(search_info.into()).await)
+        let req = builder.parsed();
+        RawParsedApiResp::Parsed(
+            s.webreg_retry
+                .run(Idempotency::Idempotent, || {
+                    req.search_courses(search_info.clone().into())
+                })
+                .await,
+        )
     }
     .into_response()
 }
@@ -104,11 +291,14 @@ pub async fn get_subject_codes(
     State(s): State<Arc<WrapperState>>,
 ) -> Response {
     info!("GET endpoint `subject_codes` called");
+    let info = match term_info(&s, &term) {
+        Ok(info) => info,
+        Err(e) => return e.into_response(),
+    };
+    let req = info.wrapper.req(term.as_str()).parsed();
     let req = s
-        .wrapper
-        .req(term.as_str())
-        .parsed()
-        .get_subject_codes()
+        .webreg_retry
+        .run(Idempotency::Idempotent, || req.get_subject_codes())
         .await;
 
     match req {
@@ -124,11 +314,14 @@ pub async fn get_department_codes(
     State(s): State<Arc<WrapperState>>,
 ) -> Response {
     info!("GET endpoint `department_codes` called");
+    let info = match term_info(&s, &term) {
+        Ok(info) => info,
+        Err(e) => return e.into_response(),
+    };
+    let req = info.wrapper.req(term.as_str()).parsed();
     let req = s
-        .wrapper
-        .req(term.as_str())
-        .parsed()
-        .get_department_codes()
+        .webreg_retry
+        .run(Idempotency::Idempotent, || req.get_department_codes())
         .await;
 
     match req {
@@ -145,11 +338,15 @@ pub async fn get_course_text(
     State(s): State<Arc<WrapperState>>,
 ) -> Response {
     info!("GET endpoint `course_text` called");
+    let subjects: Vec<_> = q.subjects.split(':').collect();
+    let info = match term_info(&s, &term) {
+        Ok(info) => info,
+        Err(e) => return e.into_response(),
+    };
+    let req = info.wrapper.req(term.as_str()).parsed();
     let req = s
-        .wrapper
-        .req(term.as_str())
-        .parsed()
-        .get_course_notes(&q.subjects.split(':').collect::<Vec<_>>())
+        .webreg_retry
+        .run(Idempotency::Idempotent, || req.get_course_notes(&subjects))
         .await;
 
     match req {
@@ -166,11 +363,16 @@ pub async fn get_section_text(
     State(s): State<Arc<WrapperState>>,
 ) -> Response {
     info!("GET endpoint `section_text` called");
+    let info = match term_info(&s, &term) {
+        Ok(info) => info,
+        Err(e) => return e.into_response(),
+    };
+    let req = info.wrapper.req(term.as_str()).parsed();
     let req = s
-        .wrapper
-        .req(term.as_str())
-        .parsed()
-        .get_section_notes_by_course(crsc.subject, crsc.number)
+        .webreg_retry
+        .run(Idempotency::Idempotent, || {
+            req.get_section_notes_by_course(&crsc.subject, &crsc.number)
+        })
         .await;
 
     match req {
@@ -178,3 +380,71 @@ pub async fn get_section_text(
         Err(e) => ApiErrorType::from(e).into_response(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::Notify;
+
+    use super::*;
+
+    fn course(subject: &str, number: &str) -> CourseQueryStr {
+        CourseQueryStr {
+            subject: subject.to_owned(),
+            number: number.to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_keeps_order() {
+        // The first course's lookup can't finish until the second's has, so the lookups
+        // finish out of order
+        let second_done = Notify::new();
+        let results = lookup_courses(
+            vec![course(" CSE ", " 100 "), course("MATH", "20C")],
+            |subject, number| {
+                let second_done = &second_done;
+                async move {
+                    if number == "100" {
+                        second_done.notified().await;
+                    } else {
+                        second_done.notify_one();
+                    }
+                    format!("{subject} {number}")
+                }
+            },
+        )
+        .await;
+
+        let looked_up: Vec<_> = results.iter().map(|(_, r)| r.as_str()).collect();
+        assert_eq!(vec!["CSE 100", "MATH 20C"], looked_up);
+        // The courses are returned as given
+        assert_eq!(" CSE ", results[0].0.subject);
+    }
+
+    #[test]
+    fn test_batch_partial_failure() {
+        let body = batch_response(vec![
+            (course("CSE", "100"), Ok(vec![])),
+            (
+                course("CSE", "999"),
+                Err(WrapperError::BadStatusCode(404, None)),
+            ),
+            (course("MATH", "20C"), Ok(vec![])),
+        ]);
+
+        assert_eq!(2, body["succeeded"]);
+        assert_eq!(1, body["failed"]);
+        let results = body["results"].as_array().unwrap();
+        let statuses: Vec<_> = results
+            .iter()
+            .map(|r| (r["number"].as_str().unwrap(), r["status"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            vec![("100", "ok"), ("999", "error"), ("20C", "ok")],
+            statuses
+        );
+        assert_eq!("NOT_FOUND", results[1]["code"]);
+        assert!(results[1]["error"].is_string());
+        assert!(results[1].get("sections").is_none());
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::log::warn;
+
+use crate::server::types::{error_response, ErrorCode};
+use crate::sharing::constant_time_eq;
+use crate::types::WrapperState;
+
+/// A middleware function that checks that requests to the admin API carry the configured
+/// admin token as a bearer token. This is separate from the API keys that students' clients
+/// use, so that a client's key never grants access to the admin API.
+///
+/// If no admin token is configured, every request is turned away, unless the admin API was
+/// explicitly allowed to be served without one (see `allowUnauthenticatedAdmin`).
+#[tracing::instrument(skip(state, req, next))]
+pub async fn check_admin_token(
+    State(state): State<Arc<WrapperState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(admin_token) = state.admin_token.as_deref() else {
+        if state.allow_unauthenticated_admin {
+            return next.run(req).await;
+        }
+
+        warn!("Rejected a request to the admin API, which has no admin token configured.");
+        return error_response(
+            StatusCode::UNAUTHORIZED,
+            ErrorCode::InvalidToken,
+            "The admin API has no admin token configured.",
+            None,
+        );
+    };
+
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|auth_header| auth_header.to_str().ok())
+        .and_then(|auth_value| auth_value.strip_prefix("Bearer "));
+
+    match token {
+        Some(token) if constant_time_eq(token.as_bytes(), admin_token.as_bytes()) => {
+            next.run(req).await
+        }
+        _ => {
+            warn!("Rejected a request to the admin API without a valid admin token.");
+            error_response(
+                StatusCode::UNAUTHORIZED,
+                ErrorCode::InvalidToken,
+                "A valid admin token is required.",
+                None,
+            )
+        }
+    }
+}
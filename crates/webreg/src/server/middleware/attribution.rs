@@ -0,0 +1,28 @@
+//! A middleware that tags responses with where the data comes from and the terms that it's
+//! served under (see [`crate::attribution`]).
+
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::types::WrapperState;
+
+/// A middleware function that adds the configured data license's headers to the response,
+/// if a data license is configured.
+pub async fn add_attribution_headers(
+    State(s): State<Arc<WrapperState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let mut resp = next.run(req).await;
+    if let Some(license) = &s.data_license {
+        let headers = resp.headers_mut();
+        for (name, value) in license.headers() {
+            headers.append(name, value.clone());
+        }
+    }
+
+    resp
+}
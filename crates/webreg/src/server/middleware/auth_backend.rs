@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::json;
+
+use crate::cookie_health::HEALTH_CHECK_INTERVAL;
+use crate::server::types::{error_response, ErrorCode};
+use crate::types::WrapperState;
+
+/// A middleware function that rejects requests with a `503 Service Unavailable` while the
+/// cookie server is known to be down. The body's code is `AUTH_BACKEND_UNAVAILABLE`, and its
+/// details have the result of the most recent health check. The `Retry-After` header is set
+/// to when the cookie server will next be checked.
+#[tracing::instrument(skip(state, req, next))]
+pub async fn check_auth_backend(
+    State(state): State<Arc<WrapperState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !state.cookie_server_health.is_down() {
+        return next.run(req).await;
+    }
+
+    let mut resp = error_response(
+        StatusCode::SERVICE_UNAVAILABLE,
+        ErrorCode::AuthBackendUnavailable,
+        "The WebReg login server is unavailable; try again later.",
+        Some(json!({ "cookie_server": state.cookie_server_health.report() })),
+    );
+    resp.headers_mut().insert(
+        RETRY_AFTER,
+        HeaderValue::from(HEALTH_CHECK_INTERVAL.as_secs()),
+    );
+    resp
+}
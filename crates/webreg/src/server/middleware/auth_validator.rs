@@ -1,11 +1,12 @@
+use crate::api_keys::{parse_scopes, ApiScope, KeyScopes};
+use crate::redaction::ResponseFilter;
+use crate::server::types::{error_response, ErrorCode};
 use crate::types::WrapperState;
 use axum::extract::{Request, State};
 use axum::http::{header, StatusCode};
 use axum::middleware::Next;
-use axum::response::IntoResponse;
-use axum::Json;
+use axum::response::{IntoResponse, Response};
 use basicauth::AuthCheckResult;
-use serde_json::{json, Value};
 use std::sync::Arc;
 use tracing::log::{info, warn};
 
@@ -14,7 +15,7 @@ pub async fn auth(
     State(state): State<Arc<WrapperState>>,
     mut req: Request,
     next: Next,
-) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+) -> Result<impl IntoResponse, Response> {
     info!("Auth middleware invoked.");
     let token = req
         .headers()
@@ -25,11 +26,11 @@ pub async fn auth(
 
     let Some(token) = token else {
         warn!("The request did not attach a token to the authorization header.");
-        return Err((
+        return Err(error_response(
             StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "error": "You didn't provide a bearer token."
-            })),
+            ErrorCode::InvalidToken,
+            "You didn't provide a bearer token.",
+            None,
         ));
     };
 
@@ -37,38 +38,81 @@ pub async fn auth(
 
     let Some((prefix, key)) = token.split_once('#') else {
         warn!("The given token is not valid due to missing separator: '{token}'");
-        return Err((
+        return Err(error_response(
             StatusCode::UNAUTHORIZED,
-            Json(json!({
-                "error": "Token is in invalid format (missing separator)."
-            })),
+            ErrorCode::InvalidToken,
+            "Token is in invalid format (missing separator).",
+            None,
         ));
     };
 
     match state.auth_manager.check_key(prefix, key) {
         AuthCheckResult::Valid => {
             info!("The given token has been validated, prefix is '{prefix}'");
+            let scopes = match state.schedule_db.get_key_scopes(prefix) {
+                Ok(Some(scopes)) => parse_scopes(&scopes),
+                Ok(None) => KeyScopes(ApiScope::ALL.to_vec()),
+                Err(e) => {
+                    warn!("Failed to look up the scopes of '{prefix}': {e}");
+                    return Err(error_response(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        ErrorCode::InternalError,
+                        "Failed to look up the token's scopes.",
+                        None,
+                    ));
+                }
+            };
+            // Better to refuse than to serve fields that the key mustn't see, so a filter
+            // that can't be read (or understood) fails the request
+            let filter = match state.schedule_db.get_key_filter(prefix) {
+                Ok(None) => None,
+                Ok(Some(filter)) => match serde_json::from_str::<ResponseFilter>(&filter) {
+                    Ok(filter) => Some(filter).filter(|f| !f.is_empty()),
+                    Err(e) => {
+                        warn!("The response filter of '{prefix}' is invalid: {e}");
+                        return Err(error_response(
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            ErrorCode::InternalError,
+                            "The token's response filter is invalid.",
+                            None,
+                        ));
+                    }
+                },
+                Err(e) => {
+                    warn!("Failed to look up the response filter of '{prefix}': {e}");
+                    return Err(error_response(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        ErrorCode::InternalError,
+                        "Failed to look up the token's response filter.",
+                        None,
+                    ));
+                }
+            };
             req.extensions_mut().insert(prefix.to_owned());
+            req.extensions_mut().insert(scopes);
+            if let Some(filter) = filter {
+                req.extensions_mut().insert(filter);
+            }
             Ok(next.run(req).await)
         }
         AuthCheckResult::NoPrefixOrTokenFound => {
             info!("The given token is either not valid, or the key doesn't exist.");
 
-            Err((
+            Err(error_response(
                 StatusCode::UNAUTHORIZED,
-                Json(json!({
-                    "error": "Token is invalid or the key doesn't exist."
-                })),
+                ErrorCode::InvalidToken,
+                "Token is invalid or the key doesn't exist.",
+                None,
             ))
         }
         AuthCheckResult::ExpiredKey => {
             info!("The given token has expired, prefix is '{prefix}'");
 
-            Err((
+            Err(error_response(
                 StatusCode::UNAUTHORIZED,
-                Json(json!({
-                    "error": "Token is expired."
-                })),
+                ErrorCode::InvalidToken,
+                "Token is expired.",
+                None,
             ))
         }
     }
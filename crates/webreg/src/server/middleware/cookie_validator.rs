@@ -1,36 +1,81 @@
-use axum::extract::Request;
-use axum::http::header::COOKIE;
-use axum::http::{HeaderMap, StatusCode};
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::header::{COOKIE, RETRY_AFTER};
+use axum::http::{HeaderValue, StatusCode};
 use axum::middleware::Next;
-use axum::response::IntoResponse;
-use axum::Json;
-use serde_json::{json, Value};
+use axum::response::Response;
 use tracing::log::info;
 
-/// A middleware function that checks if the wrapper is able to handle requests.
-#[tracing::instrument(skip(req, next))]
+use crate::server::types::{error_response, ErrorCode};
+use crate::sessions::{SessionError, SESSION_TOKEN_HEADER};
+use crate::types::WrapperState;
+
+/// A middleware function that ensures the request has WebReg cookies to work with.
+///
+/// Cookies can either be given directly through the `Cookie` header, or indirectly
+/// through a session token (see [`SESSION_TOKEN_HEADER`]). In the latter case, the
+/// session's cookies are placed in the `Cookie` header so that the handlers don't
+/// need to know which was used. A session can only be used with the API key that
+/// registered it.
+#[tracing::instrument(skip(state, req, next))]
 pub async fn check_cookies(
-    header_map: HeaderMap,
-    req: Request,
+    State(state): State<Arc<WrapperState>>,
+    mut req: Request,
     next: Next,
-) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+) -> Response {
     info!("Validating if cookie header is available.");
-    if let Some(header) = header_map.get(COOKIE) {
+    let headers = req.headers();
+
+    if let Some(token) = headers.get(SESSION_TOKEN_HEADER) {
+        let Ok(token) = token.to_str() else {
+            return bad_request("Your session token must only contain ASCII characters.");
+        };
+
+        // The auth middleware attaches the caller's key prefix
+        let key_prefix = req.extensions().get::<String>().map(String::as_str);
+        let cookies = match state.sessions.acquire(token, key_prefix) {
+            Ok(c) => c,
+            Err(SessionError::NotFound) => {
+                return error_response(
+                    StatusCode::UNAUTHORIZED,
+                    ErrorCode::CookieExpired,
+                    "The given session token is not valid.",
+                    None,
+                );
+            }
+            Err(SessionError::RateLimited(retry_after)) => {
+                let mut resp = error_response(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    ErrorCode::RateLimited,
+                    "Too many requests have been made with this session.",
+                    None,
+                );
+                resp.headers_mut()
+                    .insert(RETRY_AFTER, HeaderValue::from(retry_after.as_secs().max(1)));
+                return resp;
+            }
+        };
+
+        let Ok(cookies) = HeaderValue::from_str(&cookies) else {
+            return bad_request("The cookies for this session are not valid.");
+        };
+
+        req.headers_mut().insert(COOKIE, cookies);
+        return next.run(req).await;
+    }
+
+    if let Some(header) = headers.get(COOKIE) {
         match header.to_str() {
-            Ok(_) => Ok(next.run(req).await),
-            Err(_) => Err((
-                StatusCode::BAD_REQUEST,
-                Json(json!({
-                    "error": "Your cookies must only contain ASCII characters."
-                })),
-            )),
+            Ok(_) => next.run(req).await,
+            Err(_) => bad_request("Your cookies must only contain ASCII characters."),
         }
     } else {
-        Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({
-                "error": "You must provide your WebReg cookies for this endpoint."
-            })),
-        ))
+        bad_request("You must provide your WebReg cookies or a session token for this endpoint.")
     }
 }
+
+/// Creates a `400 Bad Request` response with the given error message.
+fn bad_request(msg: &str) -> Response {
+    error_response(StatusCode::BAD_REQUEST, ErrorCode::CookieExpired, msg, None)
+}
@@ -0,0 +1,179 @@
+//! A middleware responsible for marking deprecated routes.
+//!
+//! To deprecate a route, add an entry to [`DEPRECATED_ROUTES`] using the route's path
+//! as it appears in the router (e.g., `/live/:term/search`). Every response from that
+//! route will then carry `Deprecation` and, if set, `Sunset` and `Link` headers, and
+//! each use is counted so we know when it's safe to remove the route.
+
+use std::sync::Arc;
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::{NaiveDate, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::types::WrapperState;
+
+/// Information about a deprecated route.
+#[derive(Debug, Clone, Copy)]
+pub struct DeprecatedRoute {
+    /// The route's path, as registered with the router.
+    pub path: &'static str,
+    /// The date (`YYYY-MM-DD`) on which the route was deprecated.
+    pub since: &'static str,
+    /// The date (`YYYY-MM-DD`) after which the route may be removed, if decided.
+    pub sunset: Option<&'static str>,
+    /// The route that should be used instead, if any.
+    pub replacement: Option<&'static str>,
+}
+
+/// All deprecated routes.
+pub const DEPRECATED_ROUTES: &[DeprecatedRoute] = &[];
+
+/// Usage statistics for a single deprecated route.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeprecatedRouteUsage {
+    /// The number of requests made to this route since the server started.
+    pub count: usize,
+    /// When the route was last used, in RFC 3339 format.
+    pub last_used: Option<String>,
+}
+
+/// Tracks how often each deprecated route is still used.
+#[derive(Default)]
+pub struct DeprecationTracker {
+    usage: DashMap<&'static str, DeprecatedRouteUsage>,
+}
+
+impl DeprecationTracker {
+    /// Creates a new, empty tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a single use of the given deprecated route.
+    ///
+    /// # Parameters
+    /// - `path`: The deprecated route's path.
+    ///
+    /// # Returns
+    /// The number of times the route has been used, including this one.
+    pub fn record(&self, path: &'static str) -> usize {
+        let mut entry = self.usage.entry(path).or_default();
+        entry.count += 1;
+        entry.last_used = Some(Utc::now().to_rfc3339());
+        entry.count
+    }
+
+    /// Gets the usage for the given deprecated route.
+    ///
+    /// # Parameters
+    /// - `path`: The deprecated route's path.
+    ///
+    /// # Returns
+    /// The usage statistics, which are empty if the route was never used.
+    pub fn usage(&self, path: &str) -> DeprecatedRouteUsage {
+        self.usage.get(path).map(|u| u.clone()).unwrap_or_default()
+    }
+}
+
+/// Converts a `YYYY-MM-DD` date into a Unix timestamp at midnight UTC.
+fn date_to_timestamp(date: &str) -> Option<i64> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|d| d.and_utc().timestamp())
+}
+
+/// Formats the value of the `Deprecation` header (RFC 9745).
+fn deprecation_header_value(since: &str) -> String {
+    match date_to_timestamp(since) {
+        Some(ts) => format!("@{ts}"),
+        None => "true".to_string(),
+    }
+}
+
+/// Formats the value of the `Sunset` header (RFC 8594), which is an HTTP-date.
+fn sunset_header_value(sunset: &str) -> Option<String> {
+    NaiveDate::parse_from_str(sunset, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|d| d.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+}
+
+/// A middleware function that attaches deprecation headers to responses from deprecated
+/// routes and records their usage.
+pub async fn mark_deprecated(
+    State(state): State<Arc<WrapperState>>,
+    matched_path: Option<MatchedPath>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let route = matched_path.and_then(|p| {
+        DEPRECATED_ROUTES
+            .iter()
+            .find(|r| r.path == p.as_str())
+            .copied()
+    });
+
+    let mut response = next.run(req).await;
+    let Some(route) = route else {
+        return response;
+    };
+
+    let count = state.deprecation_tracker.record(route.path);
+    warn!(
+        "Deprecated route '{}' was used (total uses: {count}).",
+        route.path
+    );
+
+    let headers = response.headers_mut();
+    if let Ok(v) = HeaderValue::from_str(&deprecation_header_value(route.since)) {
+        headers.insert("Deprecation", v);
+    }
+
+    if let Some(v) = route
+        .sunset
+        .and_then(sunset_header_value)
+        .and_then(|s| HeaderValue::from_str(&s).ok())
+    {
+        headers.insert("Sunset", v);
+    }
+
+    if let Some(v) = route
+        .replacement
+        .and_then(|r| HeaderValue::from_str(&format!("<{r}>; rel=\"successor-version\"")).ok())
+    {
+        headers.insert("Link", v);
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_values() {
+        assert_eq!("@1704067200", deprecation_header_value("2024-01-01"));
+        assert_eq!("true", deprecation_header_value("not a date"));
+        assert_eq!(
+            Some("Sat, 01 Jun 2024 00:00:00 GMT".to_string()),
+            sunset_header_value("2024-06-01")
+        );
+    }
+
+    #[test]
+    fn test_usage_is_counted() {
+        let tracker = DeprecationTracker::new();
+        assert_eq!(0, tracker.usage("/old").count);
+        tracker.record("/old");
+        assert_eq!(2, tracker.record("/old"));
+        assert_eq!(2, tracker.usage("/old").count);
+    }
+}
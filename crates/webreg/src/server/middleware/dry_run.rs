@@ -0,0 +1,131 @@
+//! A middleware responsible for making sure that dry runs never change anything.
+//!
+//! Clients that build automation on `?dry_run=true` may pass it to every request. A route
+//! that doesn't support dry runs would ignore it and make the change anyway, so requests
+//! that ask for a dry run are turned away from every route that changes something, except
+//! those in [`DRY_RUN_ROUTES`]. A `dry_run` that isn't `true` or `false` is turned away
+//! too, rather than being taken to mean that the change should be made.
+
+use axum::extract::{MatchedPath, Query, Request};
+use axum::http::{Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::server::types::{error_response, DryRunQueryStr, ErrorCode};
+
+/// The routes that support dry runs, or that never change anything, by the end of their path
+/// as registered with the router.
+pub const DRY_RUN_ROUTES: &[&str] = &[
+    "/add_section",
+    "/validate_add_section",
+    "/drop_section",
+    "/swap_sections",
+    "/bulk_add",
+    "/validate_schedule",
+    "/add_plan",
+    "/validate_add_plan",
+    "/remove_plan",
+    "/cart/:item_id/promote",
+    "/plans/:plan_id/sync_to_webreg",
+];
+
+/// A middleware function that rejects requests for a dry run of a change that the route
+/// can't do a dry run of.
+#[tracing::instrument(skip(req, next))]
+pub async fn reject_unsupported_dry_run(
+    matched_path: Option<MatchedPath>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let dry_run = match Query::<DryRunQueryStr>::try_from_uri(req.uri()) {
+        Ok(Query(query)) => query.dry_run.unwrap_or(false),
+        Err(_) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::BadRequest,
+                "dry_run must be true or false, so nothing was changed.",
+                None,
+            )
+        }
+    };
+    let supported = matched_path.is_some_and(|p| {
+        DRY_RUN_ROUTES
+            .iter()
+            .any(|route| p.as_str().ends_with(route))
+    });
+
+    if !dry_run || supported || req.method() == Method::GET {
+        return next.run(req).await;
+    }
+
+    error_response(
+        StatusCode::BAD_REQUEST,
+        ErrorCode::BadRequest,
+        "This endpoint doesn't support dry runs, so nothing was changed.",
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::post;
+    use axum::{middleware as mw, Router};
+    use tower::ServiceExt;
+
+    async fn status(router: &Router, method: Method, uri: &str) -> StatusCode {
+        let req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        router.clone().oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_dry_run() {
+        let live = Router::new()
+            .route("/add_section", post(|| async { "added" }))
+            .route(
+                "/local_events",
+                post(|| async { "created" }).get(|| async { "[]" }),
+            );
+        let router = Router::new()
+            .nest("/live/:term", live)
+            .layer(mw::from_fn(reject_unsupported_dry_run));
+
+        let add = "/live/S124/add_section";
+        let events = "/live/S124/local_events";
+        assert_eq!(StatusCode::OK, status(&router, Method::POST, add).await);
+        let dry_add = format!("{add}?dry_run=true");
+        assert_eq!(
+            StatusCode::OK,
+            status(&router, Method::POST, &dry_add).await
+        );
+
+        assert_eq!(StatusCode::OK, status(&router, Method::POST, events).await);
+        let dry_events = format!("{events}?dry_run=true");
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            status(&router, Method::POST, &dry_events).await
+        );
+        assert_eq!(
+            StatusCode::OK,
+            status(&router, Method::GET, &dry_events).await
+        );
+        let not_dry = format!("{events}?dry_run=false");
+        assert_eq!(
+            StatusCode::OK,
+            status(&router, Method::POST, &not_dry).await
+        );
+
+        // A dry run that can't be understood isn't taken as a request to make the change
+        for uri in [format!("{add}?dry_run=1"), format!("{events}?dry_run=yes")] {
+            assert_eq!(
+                StatusCode::BAD_REQUEST,
+                status(&router, Method::POST, &uri).await
+            );
+        }
+    }
+}
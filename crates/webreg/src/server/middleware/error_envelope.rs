@@ -0,0 +1,54 @@
+//! A middleware that puts the errors that don't come from the handlers in the same body as
+//! the ones that do.
+//!
+//! Requests that axum rejects before a handler runs (e.g., a malformed query string, an
+//! unknown route, or a body that's too large) get a plain-text body from axum. This
+//! rewrites those into the `{code, message, details}` body that every other error has.
+
+use axum::body::to_bytes;
+use axum::extract::Request;
+use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::server::types::{error_response, ErrorCode};
+
+/// The most of a plain-text error body that's read into the error's message.
+const MAX_MESSAGE_SIZE: usize = 4 * 1024;
+
+/// A middleware function that rewrites error responses without a JSON body into the
+/// usual error body. The code is picked from the status, and the message is the original
+/// body (or the status's reason, if the body was empty).
+pub async fn wrap_errors(req: Request, next: Next) -> Response {
+    let resp = next.run(req).await;
+    let status = resp.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return resp;
+    }
+
+    let is_json = resp
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if is_json {
+        return resp;
+    }
+
+    let (parts, body) = resp.into_parts();
+    let message = match to_bytes(body, MAX_MESSAGE_SIZE).await {
+        Ok(bytes) if !bytes.is_empty() => String::from_utf8_lossy(&bytes).into_owned(),
+        _ => status.canonical_reason().unwrap_or("Error").to_owned(),
+    };
+
+    let wrapped = error_response(status, ErrorCode::from_status(status), &message, None);
+    // Keep the original headers (e.g., `Allow` on a 405), other than the ones describing
+    // the original body
+    let (mut wrapped_parts, body) = wrapped.into_parts();
+    for (name, value) in &parts.headers {
+        if name != CONTENT_TYPE && name != CONTENT_LENGTH {
+            wrapped_parts.headers.append(name, value.clone());
+        }
+    }
+    Response::from_parts(wrapped_parts, body)
+}
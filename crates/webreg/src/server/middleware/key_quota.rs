@@ -0,0 +1,50 @@
+//! Middleware that enforces the quotas of API keys (see [`crate::key_quota`]).
+
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::json;
+use tracing::log::warn;
+
+use crate::server::types::{error_response, ErrorCode};
+use crate::types::WrapperState;
+
+/// A middleware function that counts each request made with an API key towards the key's
+/// quotas, and rejects it with a `429 Too Many Requests` once a quota is used up. Requests
+/// without an API key aren't counted.
+#[tracing::instrument(skip(state, req, next))]
+pub async fn enforce_key_quota(
+    State(state): State<Arc<WrapperState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // The auth middleware attaches the prefix of the request's API key
+    let Some(key) = req.extensions().get::<String>() else {
+        return next.run(req).await;
+    };
+
+    let Err(exceeded) = state.key_quota.try_consume(key) else {
+        return next.run(req).await;
+    };
+
+    warn!("Key '{key}' has used up its {:?} quota.", exceeded.period);
+    let mut resp = error_response(
+        StatusCode::TOO_MANY_REQUESTS,
+        ErrorCode::QuotaExceeded,
+        "This API key has used up its quota. Try again later.",
+        Some(json!({
+            "period": exceeded.period,
+            "used": exceeded.used,
+            "limit": exceeded.limit,
+        })),
+    );
+    resp.headers_mut().insert(
+        RETRY_AFTER,
+        HeaderValue::from(exceeded.resets_in.num_seconds().max(1)),
+    );
+    resp
+}
@@ -0,0 +1,50 @@
+//! Middleware that sheds load when the server is overloaded (see [`crate::load_shed`]).
+
+use std::sync::Arc;
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::warn;
+
+use crate::load_shed::Priority;
+use crate::server::types::{error_response, ErrorCode};
+use crate::types::WrapperState;
+
+/// How long, in seconds, clients are asked to wait before retrying a shed request.
+const SHED_RETRY_AFTER_SECS: u64 = 5;
+
+/// A middleware function that rejects requests with a `503 Service Unavailable` when the
+/// server is too loaded to serve them, based on their route's priority. The body's code is
+/// `OVERLOADED`.
+#[tracing::instrument(skip(state, req, next))]
+pub async fn shed_load(
+    State(state): State<Arc<WrapperState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let priority = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(Priority::Normal, |path| Priority::of_route(path.as_str()));
+
+    let Some(_in_flight) = state.load_shedder.admit(priority) else {
+        warn!(
+            "Shed a {priority:?} priority request to {}.",
+            req.uri().path()
+        );
+        let mut resp = error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::Overloaded,
+            "The server is overloaded; try again later.",
+            None,
+        );
+        resp.headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(SHED_RETRY_AFTER_SECS));
+        return resp;
+    };
+
+    next.run(req).await
+}
@@ -1,5 +1,20 @@
+pub mod admin_auth;
+pub mod attribution;
+pub mod auth_backend;
 #[cfg(feature = "auth")]
 pub mod auth_validator;
 pub mod cookie_validator;
+pub mod deprecation;
+pub mod dry_run;
+pub mod error_envelope;
+pub mod key_quota;
+pub mod load_shedder;
+pub mod rate_limiter;
+#[cfg(feature = "auth")]
+pub mod redaction;
+pub mod request_id;
+pub mod retry_count;
 pub mod running_validator;
+#[cfg(feature = "auth")]
+pub mod scope_validator;
 pub mod term_validator;
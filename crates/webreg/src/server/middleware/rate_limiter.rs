@@ -0,0 +1,133 @@
+//! Middleware that enforces the API's rate limits (see [`crate::rate_limit`]).
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::log::warn;
+
+use crate::rate_limit::TokenBucketLimiter;
+use crate::server::types::{error_response, ErrorCode};
+use crate::types::WrapperState;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_REAL_IP: &str = "x-real-ip";
+
+/// A middleware function that limits how many requests each IP address may make. This runs
+/// before requests are authenticated, so that requests with bad API keys are limited too.
+#[tracing::instrument(skip(state, req, next))]
+pub async fn limit_by_ip(
+    State(state): State<Arc<WrapperState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let client = match client_ip(&state, &req) {
+        Some(ip) => format!("ip:{ip}"),
+        None => "unknown".to_owned(),
+    };
+
+    match state.rate_limits.per_ip.acquire(&client) {
+        Ok(()) => next.run(req).await,
+        Err(retry_after) => {
+            warn!("Rate limited address '{client}'.");
+            too_many_requests(retry_after)
+        }
+    }
+}
+
+/// A middleware function that limits how many requests each client may make.
+#[tracing::instrument(skip(state, req, next))]
+pub async fn limit_requests(
+    State(state): State<Arc<WrapperState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    match check(&state, &state.rate_limits.general, &req) {
+        Some(resp) => resp,
+        None => next.run(req).await,
+    }
+}
+
+/// A middleware function that limits how many enrollment changes (i.e., any request to a
+/// cookie endpoint that isn't a `GET`) each client may make.
+#[tracing::instrument(skip(state, req, next))]
+pub async fn limit_mutations(
+    State(state): State<Arc<WrapperState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if req.method() == Method::GET {
+        return next.run(req).await;
+    }
+
+    match check(&state, &state.rate_limits.mutations, &req) {
+        Some(resp) => resp,
+        None => next.run(req).await,
+    }
+}
+
+/// Takes a token for the request's client from the given limiter.
+///
+/// # Parameters
+/// - `state`: The app state.
+/// - `limiter`: The limiter.
+/// - `req`: The request.
+///
+/// # Returns
+/// A `429 Too Many Requests` response if the client has made too many requests.
+fn check(state: &WrapperState, limiter: &TokenBucketLimiter, req: &Request) -> Option<Response> {
+    let client = client_key(state, req);
+    let retry_after = limiter.acquire(&client).err()?;
+    warn!("Rate limited client '{client}'.");
+    Some(too_many_requests(retry_after))
+}
+
+/// Identifies the client making a request: by the prefix of its API key if the request was
+/// authenticated, and by its IP address otherwise.
+fn client_key(state: &WrapperState, req: &Request) -> String {
+    if let Some(prefix) = req.extensions().get::<String>() {
+        return format!("key:{prefix}");
+    }
+
+    match client_ip(state, req) {
+        Some(ip) => format!("ip:{ip}"),
+        None => "unknown".to_owned(),
+    }
+}
+
+/// Works out the IP address of the client making a request, looking through trusted proxies
+/// (see [`crate::rate_limit::RateLimits::client_ip`]).
+fn client_ip(state: &WrapperState, req: &Request) -> Option<IpAddr> {
+    let ConnectInfo(addr) = req.extensions().get::<ConnectInfo<SocketAddr>>()?;
+    let header = |name| {
+        req.headers()
+            .get(name)
+            .and_then(|value: &HeaderValue| value.to_str().ok())
+    };
+
+    Some(
+        state
+            .rate_limits
+            .client_ip(addr.ip(), header(X_FORWARDED_FOR), header(X_REAL_IP)),
+    )
+}
+
+/// Creates a `429 Too Many Requests` response with a `Retry-After` header.
+fn too_many_requests(retry_after: Duration) -> Response {
+    let mut resp = error_response(
+        StatusCode::TOO_MANY_REQUESTS,
+        ErrorCode::RateLimited,
+        "Too many requests have been made. Try again later.",
+        None,
+    );
+    resp.headers_mut().insert(
+        RETRY_AFTER,
+        HeaderValue::from(retry_after.as_secs_f64().ceil().max(1.0) as u64),
+    );
+    resp
+}
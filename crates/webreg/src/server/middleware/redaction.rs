@@ -0,0 +1,154 @@
+//! A middleware that applies the response filter of a request's API key (see
+//! [`crate::redaction`]) to the response.
+//!
+//! Filters are applied to the JSON that the handlers serialized, so every endpoint is
+//! covered, including ones added later. A response that isn't JSON (e.g., a CSV export, a
+//! calendar file, or a WebSocket) can't be filtered, so keys with a filter are refused it
+//! instead.
+
+use axum::body::{to_bytes, Body};
+use axum::extract::Request;
+use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::{Map, Value};
+use tracing::log::warn;
+
+use crate::redaction::ResponseFilter;
+use crate::server::types::{error_response, ErrorCode};
+
+/// A middleware function that filters the response with the API key's response filter,
+/// if it has one. Error responses keep their code and message, and everything else in them
+/// (e.g., the sections in a failed enrollment's receipt) is filtered.
+#[tracing::instrument(skip(req, next))]
+pub async fn filter_response(req: Request, next: Next) -> Response {
+    // The auth middleware attaches the filter of every key that has one
+    let Some(filter) = req.extensions().get::<ResponseFilter>().cloned() else {
+        return next.run(req).await;
+    };
+
+    let resp = next.run(req).await;
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+    let is_error = status.is_client_error() || status.is_server_error();
+
+    match content_type {
+        Some(content_type) if content_type.starts_with("application/json") => {}
+        // Plain-text errors come from axum itself, and don't have any data in them
+        _ if is_error => return resp,
+        None if status != StatusCode::SWITCHING_PROTOCOLS => return resp,
+        _ => return refuse(),
+    }
+
+    let (mut parts, body) = resp.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return refuse();
+    };
+    let Ok(body) = serde_json::from_slice::<Value>(&bytes) else {
+        return refuse();
+    };
+
+    let body = match body {
+        Value::Object(mut fields) if is_error => {
+            let kept = ["code", "message"].map(|field| (field, fields.remove(field)));
+            let mut fields = match filter.apply(Value::Object(fields)) {
+                Value::Object(fields) => fields,
+                _ => Map::new(),
+            };
+            for (field, value) in kept {
+                if let Some(value) = value {
+                    fields.insert(field.to_owned(), value);
+                }
+            }
+            Value::Object(fields)
+        }
+        body => filter.apply(body),
+    };
+
+    parts.headers.remove(CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(serde_json::to_vec(&body).unwrap()))
+}
+
+/// The response for a key whose filter can't be applied to a response.
+fn refuse() -> Response {
+    warn!("Refused a response that the key's response filter can't be applied to.");
+    error_response(
+        StatusCode::FORBIDDEN,
+        ErrorCode::ResponseFiltered,
+        "This API key's response filter can't be applied to this endpoint's responses.",
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::{middleware as mw, Json, Router};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    async fn body(filter: ResponseFilter) -> Value {
+        let router = Router::new()
+            .route(
+                "/add_section",
+                get(|| async {
+                    (
+                        StatusCode::BAD_GATEWAY,
+                        Json(json!({
+                            "code": "webreg",
+                            "message": "The section was added, but it isn't in your schedule.",
+                            "details": null,
+                            "before": [{ "sectionId": "123456", "gradeOption": "L" }],
+                            "after": [],
+                        })),
+                    )
+                }),
+            )
+            .layer(mw::from_fn(filter_response))
+            .layer(axum::Extension(filter));
+
+        let req = Request::builder()
+            .uri("/add_section")
+            .body(Body::empty())
+            .unwrap();
+        let resp = router.oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::BAD_GATEWAY, resp.status());
+        serde_json::from_slice(&to_bytes(resp.into_body(), usize::MAX).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_error_body_filtered() {
+        let denied = body(ResponseFilter {
+            allow: vec![],
+            deny: vec!["grade_option".to_owned(), "message".to_owned()],
+        })
+        .await;
+        assert_eq!(
+            json!({
+                "code": "webreg",
+                "message": "The section was added, but it isn't in your schedule.",
+                "details": null,
+                "before": [{ "sectionId": "123456" }],
+                "after": [],
+            }),
+            denied
+        );
+
+        // The code and message are kept even if nothing else is allowed
+        let allowed = body(ResponseFilter {
+            allow: vec!["sectionId".to_owned()],
+            deny: vec![],
+        })
+        .await;
+        assert_eq!("webreg", allowed["code"]);
+        assert!(allowed["message"].is_string());
+        assert_eq!(json!([{ "sectionId": "123456" }]), allowed["before"]);
+        assert!(allowed.get("details").is_none());
+    }
+}
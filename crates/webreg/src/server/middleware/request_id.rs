@@ -0,0 +1,37 @@
+//! A middleware that gives every request an ID.
+//!
+//! The ID is attached to a `request` span that every handler runs in, so that all lines
+//! logged while serving the request carry it, and it's returned to the client in the
+//! [`REQUEST_ID_HEADER`] header. If the request log is enabled, the lines can then be looked
+//! up with `/admin/logs/:request_id`.
+
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::{info_span, Instrument};
+
+use crate::request_log::generate_request_id;
+
+/// The header that the request ID is returned in.
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// A middleware function that assigns the request an ID and runs the rest of the request
+/// in a span that carries it. IDs given by clients are ignored, so that one client can't
+/// make its requests share an ID with another's.
+pub async fn assign_request_id(req: Request, next: Next) -> Response {
+    let request_id = generate_request_id();
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        method = %req.method(),
+        path = %req.uri().path(),
+    );
+
+    let mut resp = next.run(req).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        resp.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    resp
+}
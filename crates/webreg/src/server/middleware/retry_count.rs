@@ -0,0 +1,21 @@
+//! A middleware that reports how many times WebReg requests were retried while serving a
+//! request (see [`crate::retry`]).
+
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::retry::{count_retries, RETRY_COUNT_HEADER};
+
+/// A middleware function that counts the WebReg retries made by the handler and, if there
+/// were any, returns the count in the [`RETRY_COUNT_HEADER`] header.
+pub async fn report_retries(req: Request, next: Next) -> Response {
+    let (mut resp, retries) = count_retries(next.run(req)).await;
+    if retries > 0 {
+        resp.headers_mut()
+            .insert(RETRY_COUNT_HEADER, HeaderValue::from(retries));
+    }
+
+    resp
+}
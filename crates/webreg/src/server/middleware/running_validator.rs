@@ -1,31 +1,100 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use axum::extract::{Request, State};
+use axum::extract::{Path, Request, State};
 use axum::http::StatusCode;
 use axum::middleware::Next;
-use axum::response::IntoResponse;
-use axum::Json;
-use serde_json::{json, Value};
+use axum::response::{IntoResponse, Response};
 use tracing::log::info;
 
+use crate::server::types::{error_response, ErrorCode};
 use crate::types::WrapperState;
 
-/// A middleware function that checks if the wrapper is able to handle requests.
+/// A middleware function that checks if the wrapper for the term in the path is able
+/// to handle requests. Other terms being unavailable doesn't affect this term.
 #[tracing::instrument(skip(state, req, next))]
 pub async fn validate_wrapper_running(
+    Path(params): Path<HashMap<String, String>>,
     State(state): State<Arc<WrapperState>>,
     req: Request,
     next: Next,
-) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+) -> Result<impl IntoResponse, Response> {
     info!("Validating if API is ready.");
-    if state.is_running() {
+    let is_running = params
+        .get("term")
+        .and_then(|term| state.term(&term.to_uppercase()))
+        .is_some_and(|t| t.is_running());
+
+    if is_running {
         Ok(next.run(req).await)
     } else {
-        Err((
+        Err(error_response(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({
-                "error": "The API isn't ready to make requests at this time."
-            })),
+            ErrorCode::NotReady,
+            "The API isn't ready to make requests at this time.",
+            None,
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::{middleware as mw, Router};
+    use serde_json::json;
+    use std::sync::atomic::Ordering;
+    use tower::ServiceExt;
+
+    use crate::types::tests::state;
+
+    async fn status(router: &Router, uri: &str) -> StatusCode {
+        let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        router.clone().oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_terms_run_independently() {
+        let state = Arc::new(state("running-validator", &["FA24", "WI25"], json!({})));
+        let live = Router::new()
+            .route("/course_info", get(|| async { "[]" }))
+            .layer(mw::from_fn_with_state(
+                state.clone(),
+                validate_wrapper_running,
+            ));
+        let router = Router::new()
+            .nest("/live/:term", live)
+            .with_state(state.clone());
+
+        let fall = "/live/FA24/course_info";
+        let winter = "/live/WI25/course_info";
+        let error = StatusCode::INTERNAL_SERVER_ERROR;
+        assert!(!state.is_running());
+        assert_eq!(error, status(&router, fall).await);
+        assert_eq!(error, status(&router, winter).await);
+
+        // One term running doesn't make the other one available
+        let fa24 = state.term("FA24").unwrap();
+        fa24.is_running.store(true, Ordering::SeqCst);
+        assert!(state.is_running());
+        assert_eq!(StatusCode::OK, status(&router, fall).await);
+        assert_eq!(
+            StatusCode::OK,
+            status(&router, "/live/fa24/course_info").await
+        );
+        assert_eq!(error, status(&router, winter).await);
+        assert_eq!(error, status(&router, "/live/SP25/course_info").await);
+
+        // And a term stopping doesn't take the other one down with it
+        state
+            .term("WI25")
+            .unwrap()
+            .is_running
+            .store(true, Ordering::SeqCst);
+        fa24.is_running.store(false, Ordering::SeqCst);
+        assert!(state.is_running());
+        assert_eq!(error, status(&router, fall).await);
+        assert_eq!(StatusCode::OK, status(&router, winter).await);
+    }
+}
@@ -0,0 +1,58 @@
+//! Middleware that checks that a request's API key has the scope needed for the route
+//! group that it's for (see [`crate::api_keys`]).
+
+use axum::extract::Request;
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::json;
+use tracing::log::warn;
+
+use crate::api_keys::{ApiScope, KeyScopes};
+use crate::server::types::{error_response, ErrorCode};
+
+/// Rejects the request with a `403 Forbidden` unless its API key has the given scope.
+async fn require_scope(scope: ApiScope, req: Request, next: Next) -> Response {
+    // The auth middleware attaches the scopes of every key that it lets through
+    if req
+        .extensions()
+        .get::<KeyScopes>()
+        .is_some_and(|scopes| scopes.allows(scope))
+    {
+        return next.run(req).await;
+    }
+
+    let prefix = req
+        .extensions()
+        .get::<String>()
+        .cloned()
+        .unwrap_or_default();
+    warn!(
+        "Key '{prefix}' doesn't have the '{}' scope.",
+        scope.as_str()
+    );
+    error_response(
+        StatusCode::FORBIDDEN,
+        ErrorCode::ScopeDenied,
+        "This API key isn't allowed to use this endpoint.",
+        Some(json!({ "required_scope": scope })),
+    )
+}
+
+/// A middleware function for routes that need the `schedule` scope.
+#[tracing::instrument(skip(req, next))]
+pub async fn require_schedule(req: Request, next: Next) -> Response {
+    require_scope(ApiScope::Schedule, req, next).await
+}
+
+/// A middleware function for routes that need the `enroll` scope.
+#[tracing::instrument(skip(req, next))]
+pub async fn require_enroll(req: Request, next: Next) -> Response {
+    require_scope(ApiScope::Enroll, req, next).await
+}
+
+/// A middleware function for routes that need the `degree_audit` scope.
+#[tracing::instrument(skip(req, next))]
+pub async fn require_degree_audit(req: Request, next: Next) -> Response {
+    require_scope(ApiScope::DegreeAudit, req, next).await
+}
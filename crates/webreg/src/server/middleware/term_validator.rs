@@ -1,36 +1,40 @@
 //! A middleware responsible for ensuring the term is valid.
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use axum::extract::{Path, Request, State};
 use axum::http::StatusCode;
 use axum::middleware::Next;
-use axum::response::IntoResponse;
-use axum::Json;
-use serde_json::{json, Value};
+use axum::response::{IntoResponse, Response};
 use tracing::info;
 
+use crate::server::types::{error_response, ErrorCode};
 use crate::types::WrapperState;
 
 /// A middleware function that validates a term that's passed as part of the path
 /// is supported by the server.
 #[tracing::instrument(skip(state, req, next))]
 pub async fn validate_term(
-    Path(term): Path<String>,
+    Path(params): Path<HashMap<String, String>>,
     State(state): State<Arc<WrapperState>>,
     req: Request,
     next: Next,
-) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+) -> Result<impl IntoResponse, Response> {
     info!("Validating if term is supported.");
-    let term = term.to_uppercase();
+    // Routes under this middleware may have other path parameters besides the term.
+    let term = params
+        .get("term")
+        .map(|t| t.to_uppercase())
+        .unwrap_or_default();
     if state.all_terms.contains_key(&term) {
         Ok(next.run(req).await)
     } else {
-        Err((
+        Err(error_response(
             StatusCode::NOT_FOUND,
-            Json(json!({
-                "error": "The specified term cannot be found"
-            })),
+            ErrorCode::TermNotFound,
+            "The specified term cannot be found",
+            Some(term.into()),
         ))
     }
 }
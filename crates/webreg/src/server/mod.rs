@@ -1,18 +1,37 @@
 use std::sync::Arc;
 
-use axum::routing::{get, post};
+use axum::extract::DefaultBodyLimit;
+use axum::http::{Extensions, HeaderMap, StatusCode, Version};
+use axum::routing::{delete, get, post, put};
 use axum::{middleware as mw, Router};
+use tower_http::compression::predicate::{DefaultPredicate, Predicate};
+use tower_http::compression::CompressionLayer;
 
-use crate::server::endpoints::{degree_audit, schedule, status, ww_cookies, ww_general};
+use crate::grades::MAX_GRADE_CSV_SIZE;
+use crate::replication::MAX_SNAPSHOT_SIZE;
+use crate::server::endpoints::{
+    about, admin, analytics, audit_trail, cart, catalog, changes, course_plans, degree_audit,
+    enroll_jobs, events, grades, instructors, live, offerings, overview, plans, rooms, schedule,
+    search, sessions, sharing, status, sync, ww_cookies, ww_general,
+};
+#[cfg(feature = "auth")]
+use crate::server::endpoints::{
+    api_keys, custom_requirements, my_schedule, registration, user_bundles, vault,
+};
 use crate::server::middleware::*;
 use crate::types::WrapperState;
 
 mod endpoints;
 mod middleware;
-mod types;
+pub mod types;
 mod util;
+mod validation;
 
-/// Creates a router that can be used by `axum`.
+pub use middleware::deprecation::DeprecationTracker;
+pub use util::parse_grade_option_unit_count;
+
+/// Creates the student-facing router that can be used by `axum`. This doesn't include the
+/// admin API; see [`create_admin_router`].
 ///
 /// # Parameters
 /// - `app_state`: The app server state.
@@ -28,6 +47,12 @@ pub fn create_router(app_state: Arc<WrapperState>) -> Router {
             post(ww_cookies::post_validate_add_section),
         )
         .route("/drop_section", post(ww_cookies::post_drop_section))
+        .route("/swap_sections", post(ww_cookies::post_swap_sections))
+        .route("/bulk_add", post(ww_cookies::post_bulk_add))
+        .route(
+            "/validate_schedule",
+            post(ww_cookies::post_validate_schedule),
+        )
         .route("/add_plan", post(ww_cookies::post_add_plan))
         .route(
             "/validate_add_plan",
@@ -36,22 +61,116 @@ pub fn create_router(app_state: Arc<WrapperState>) -> Router {
         .route("/remove_plan", post(ww_cookies::post_remove_plan))
         .route("/schedule", get(ww_cookies::get_schedule))
         .route("/schedule_list", get(ww_cookies::get_schedule_list))
+        .route("/overview", get(overview::get_overview))
         .route("/register_term", post(ww_cookies::post_register_term))
         .route("/events", get(ww_cookies::get_events))
         .route("/rename_schedule", post(ww_cookies::post_rename_schedule))
-        .layer(mw::from_fn(cookie_validator::check_cookies));
+        .route(
+            "/local_events",
+            get(events::get_local_events).post(events::post_local_event),
+        )
+        .route(
+            "/local_events/:event_id",
+            put(events::put_local_event).delete(events::delete_local_event),
+        )
+        .route("/local_events/sync", post(events::post_sync_local_events))
+        .route("/share_schedule", post(sharing::post_share_schedule))
+        .route(
+            "/enroll_jobs",
+            get(enroll_jobs::get_enroll_jobs).post(enroll_jobs::post_enroll_job),
+        )
+        .route(
+            "/enroll_jobs/:job_id",
+            delete(enroll_jobs::delete_enroll_job),
+        )
+        .route("/watches", get(enroll_jobs::get_watches))
+        .route("/cart", get(cart::get_cart).post(cart::post_cart_item))
+        .route("/cart/:item_id", delete(cart::delete_cart_item))
+        .route("/cart/:item_id/promote", post(cart::post_promote_cart_item))
+        .route(
+            "/plans",
+            get(plans::get_saved_plans).post(plans::post_saved_plan),
+        )
+        .route("/plans/:plan_id", delete(plans::delete_saved_plan))
+        .route(
+            "/plans/:plan_id/sync_to_webreg",
+            post(plans::post_sync_plan),
+        );
+
+    // The mirror of the student's schedule, and their passes, are kept per API key
+    #[cfg(feature = "auth")]
+    let cookie_router = cookie_router
+        .route(
+            "/sync_my_schedule",
+            post(my_schedule::post_sync_my_schedule),
+        )
+        .route(
+            "/sync_my_passtimes",
+            post(my_schedule::post_sync_my_passtimes),
+        );
+
+    let cookie_router = cookie_router
+        .layer(mw::from_fn_with_state(
+            app_state.clone(),
+            cookie_validator::check_cookies,
+        ))
+        .layer(mw::from_fn_with_state(
+            app_state.clone(),
+            rate_limiter::limit_mutations,
+        ))
+        .layer(mw::from_fn_with_state(
+            app_state.clone(),
+            auth_backend::check_auth_backend,
+        ));
+
+    #[cfg(feature = "auth")]
+    let cookie_router = cookie_router.layer(mw::from_fn(scope_validator::require_enroll));
 
     // General router
     let parsed_router = Router::new()
         .route("/course_info", get(ww_general::get_course_info))
+        .route(
+            "/course_info/batch",
+            post(ww_general::post_course_info_batch),
+        )
         .route("/prerequisites", get(ww_general::get_prerequisites))
         .route("/search", get(ww_general::get_search_courses))
+        .route("/search/v2", get(search::get_search_v2))
+        .route("/search_semantic", get(search::get_search_semantic))
+        .route(
+            "/alternatives/:section_id",
+            get(search::get_section_alternatives),
+        )
         .route("/department_codes", get(ww_general::get_department_codes))
         .route("/subject_codes", get(ww_general::get_subject_codes))
         .route("/course_text", get(ww_general::get_course_text))
         .route("/section_text", get(ww_general::get_section_text))
         .route("/schedule_data", get(schedule::get_schedule_data))
+        .route(
+            "/schedule_data/export",
+            get(schedule::get_schedule_data_export),
+        )
         .route("/schedule_data/:section_id", get(schedule::get_section_meetings))
+        .route("/heatmap", get(schedule::get_heatmap))
+        .route("/finals", get(schedule::get_finals))
+        .route("/analytics/fill_rate", get(analytics::get_fill_rate))
+        .route("/changes", get(changes::get_section_changes))
+        .route("/ws", get(live::get_live_events))
+        .route("/rooms/free", get(rooms::get_free_rooms))
+        .route("/rooms/:building", get(rooms::get_building_rooms))
+        .route("/instructors", get(instructors::get_instructors))
+        .route(
+            "/instructors/:name/sections",
+            get(instructors::get_instructor_sections),
+        );
+
+    #[cfg(feature = "auth")]
+    let parsed_router = parsed_router
+        .route("/my_schedule", get(my_schedule::get_my_schedule))
+        .route("/my_passtimes", get(my_schedule::get_my_passtimes))
+        .layer(mw::from_fn(scope_validator::require_schedule));
+
+    let parsed_router = parsed_router
         .merge(cookie_router)
         .layer(mw::from_fn_with_state(
             app_state.clone(),
@@ -69,7 +188,19 @@ pub fn create_router(app_state: Arc<WrapperState>) -> Router {
     // Degree audit router (not nested under /live/:term/ since it's student-specific)
     let degree_audit_router = Router::new()
         .route("/degree_audit", get(degree_audit::get_audit))
+        .route(
+            "/degree_audit/jobs/:handle",
+            get(degree_audit::get_audit_job),
+        )
         .route("/degree_audit/progress", get(degree_audit::get_degree_progress))
+        .route(
+            "/degree_audit/report.pdf",
+            get(degree_audit::get_audit_report),
+        )
+        .route(
+            "/degree_audit/export.xlsx",
+            get(degree_audit::get_audit_export),
+        )
         .route(
             "/degree_audit/completed_courses",
             get(degree_audit::get_completed_courses),
@@ -82,6 +213,14 @@ pub fn create_router(app_state: Arc<WrapperState>) -> Router {
             "/degree_audit/next_courses",
             get(degree_audit::get_next_courses),
         )
+        .route(
+            "/degree_audit/elective_suggestions",
+            get(degree_audit::get_elective_suggestions),
+        )
+        .route(
+            "/degree_audit/graduation_plan",
+            get(degree_audit::get_graduation_plan),
+        )
         .route(
             "/degree_audit/subrequirement/:subreq_id/eligible_courses",
             get(degree_audit::get_eligible_courses_for_subreq),
@@ -96,24 +235,553 @@ pub fn create_router(app_state: Arc<WrapperState>) -> Router {
             post(degree_audit::invalidate_cache),
         );
 
+    // Custom requirement sets are kept per API key
+    #[cfg(feature = "auth")]
+    let degree_audit_router = degree_audit_router
+        .route(
+            "/users/:id/custom_requirements",
+            get(custom_requirements::get_custom_requirements)
+                .post(custom_requirements::post_custom_requirements),
+        )
+        .route(
+            "/users/:id/custom_requirements/:requirement_id",
+            delete(custom_requirements::delete_custom_requirements),
+        )
+        .layer(mw::from_fn(scope_validator::require_degree_audit));
+
     let router = Router::new()
         .route("/health", get(status::get_health))
+        .route("/about", get(about::get_about))
+        .route("/status", get(status::get_status_page))
+        .route("/status/history", get(status::get_status_history))
         .nest("/live/:term", webreg_router)
         .route("/terms", get(ww_general::get_all_terms))
+        .route(
+            "/catalog/:subject/:number",
+            get(catalog::get_catalog_course),
+        )
+        .route(
+            "/courses/:course/grade_distribution",
+            get(grades::get_grade_distribution),
+        )
+        .route(
+            "/courses/:course/offering_history",
+            get(offerings::get_offering_history),
+        )
+        .route(
+            "/analytics/co_enrollment/:course",
+            get(analytics::get_co_enrollment),
+        )
         .route("/timing/:term", get(status::get_timing_stats))
-        .route("/login_stat/:stat", get(status::get_login_script_stats))
-        .merge(degree_audit_router)
+        .route("/login_stat/cookie_health", get(status::get_cookie_health))
+        .route("/login_stat/:stat", get(status::get_login_script_stats));
+
+    // Schedule data that isn't tied to a term
+    let schedule_router = Router::new()
+        .route("/schedule_conflicts", get(schedule::get_schedule_conflicts))
+        .route("/schedule_ical", get(schedule::get_schedule_ical));
+
+    #[cfg(feature = "auth")]
+    let schedule_router = schedule_router.layer(mw::from_fn(scope_validator::require_schedule));
+
+    // Router whose endpoints are backed by a session, which can hold the student's cookies
+    // and queue enrollments for them
+    let session_router = Router::new()
+        .route(
+            "/sessions",
+            post(sessions::post_session)
+                .get(sessions::get_session)
+                .put(sessions::put_session)
+                .delete(sessions::delete_session),
+        )
+        .route("/sessions/reattach", post(sessions::post_session_reattach))
+        .route("/sync", get(sync::get_sync))
+        .route("/plans", post(course_plans::post_course_plan))
+        .route(
+            "/plans/:plan_id",
+            get(course_plans::get_course_plan).put(course_plans::put_course_plan),
+        );
+
+    // The credential vault and bundles of a user's data are per API key, so they're only
+    // available with the auth feature
+    #[cfg(feature = "auth")]
+    let session_router = session_router
+        .route("/users/:id/export", get(user_bundles::get_user_export))
+        .route("/users/:id/import", post(user_bundles::post_user_import))
+        .route(
+            "/vault/credentials",
+            get(vault::get_credentials)
+                .put(vault::put_credentials)
+                .delete(vault::delete_credentials),
+        )
+        .route(
+            "/vault/credentials/sync",
+            post(vault::post_sync_credentials),
+        )
+        .layer(mw::from_fn(scope_validator::require_enroll));
+
+    let router = router.merge(schedule_router).merge(session_router);
+
+    // Degree audits are fetched through the cookie server (or, in multi-user mode, the
+    // caller's own), so they can't be served without one
+    let router = if app_state.cookie_server.is_some() || app_state.is_multi_user() {
+        router.merge(degree_audit_router)
+    } else {
+        router
+    };
+
+    let router = router
+        .layer(mw::from_fn_with_state(
+            app_state.clone(),
+            deprecation::mark_deprecated,
+        ))
+        .layer(mw::from_fn_with_state(
+            app_state.clone(),
+            attribution::add_attribution_headers,
+        ))
+        .layer(mw::from_fn_with_state(
+            app_state.clone(),
+            key_quota::enforce_key_quota,
+        ))
+        .layer(mw::from_fn_with_state(
+            app_state.clone(),
+            rate_limiter::limit_requests,
+        ))
+        .layer(mw::from_fn_with_state(
+            app_state.clone(),
+            load_shedder::shed_load,
+        ))
         .with_state(app_state.clone());
 
+    // Response filters are applied inside the auth middleware, which attaches them
     #[cfg(feature = "auth")]
-    {
-        router.layer(mw::from_fn_with_state(
+    let router = router
+        .layer(mw::from_fn(redaction::filter_response))
+        .layer(mw::from_fn_with_state(
             app_state.clone(),
             auth_validator::auth,
         ))
+        // Registering is how clients get an API key, so it can't need one. It's still rate
+        // limited, so that invite codes can't be guessed quickly
+        .merge(
+            Router::new()
+                .route("/register", post(registration::post_register))
+                .layer(mw::from_fn_with_state(
+                    app_state.clone(),
+                    rate_limiter::limit_mutations,
+                ))
+                .with_state(app_state.clone()),
+        );
+
+    // Share links are opened by people who don't have an API key (that's the point of them),
+    // so they're outside the auth layer. The token itself says which snapshot can be read
+    let router = router.merge(
+        Router::new()
+            .route("/shared/:token", get(sharing::get_shared_schedule))
+            .layer(mw::from_fn_with_state(
+                app_state.clone(),
+                rate_limiter::limit_requests,
+            ))
+            .with_state(app_state.clone()),
+    );
+
+    // Every address is limited before its requests are authenticated, so that requests with
+    // bad keys can't be made as fast as the client likes
+    let router = router.layer(mw::from_fn_with_state(
+        app_state.clone(),
+        rate_limiter::limit_by_ip,
+    ));
+
+    // Outermost, so that every request (including rejected ones) gets an ID. Responses are
+    // compressed with gzip or Brotli for clients that accept them, which matters most for
+    // large responses like `schedule_data`. Dry runs are checked on every route, so that no
+    // route that changes something can be asked for one and make the change anyway
+    router
+        .layer(mw::from_fn(dry_run::reject_unsupported_dry_run))
+        .layer(mw::from_fn(error_envelope::wrap_errors))
+        .layer(mw::from_fn(retry_count::report_retries))
+        .layer(mw::from_fn(request_id::assign_request_id))
+        .layer(CompressionLayer::new().compress_when(DefaultPredicate::new().and(has_body)))
+}
+
+/// Whether a response with a status has a body to compress. WebSocket upgrades and `304 Not
+/// Modified` responses don't, and shouldn't be marked as compressed.
+fn has_body(status: StatusCode, _: Version, _: &HeaderMap, _: &Extensions) -> bool {
+    !status.is_informational()
+        && status != StatusCode::NO_CONTENT
+        && status != StatusCode::NOT_MODIFIED
+}
+
+/// Creates the router for the admin API, whose endpoints are all under `/admin`. Requests
+/// to it need the admin token rather than a student API key.
+///
+/// # Parameters
+/// - `app_state`: The app server state.
+///
+/// # Returns
+/// The router.
+pub fn create_admin_router(app_state: Arc<WrapperState>) -> Router {
+    let router = Router::new()
+        .route("/admin/upstream_drift", get(admin::get_upstream_drift))
+        .route("/admin/deprecations", get(admin::get_deprecations))
+        .route("/admin/status", get(admin::get_status))
+        .route("/admin/load", get(admin::get_load))
+        .route("/admin/leader", get(admin::get_leader))
+        .route("/admin/jobs", get(admin::get_jobs))
+        .route("/admin/replication", get(admin::get_replication))
+        .route(
+            "/admin/replication/snapshot",
+            post(admin::post_replication_snapshot).layer(DefaultBodyLimit::max(MAX_SNAPSHOT_SIZE)),
+        )
+        .route(
+            "/admin/replication/promote",
+            post(admin::post_replication_promote),
+        )
+        .route(
+            "/admin/grade_distributions",
+            post(admin::post_grade_distributions).layer(DefaultBodyLimit::max(MAX_GRADE_CSV_SIZE)),
+        )
+        .route("/admin/advising/gap_report", get(admin::get_gap_report))
+        .route("/admin/logs/:request_id", get(admin::get_request_logs))
+        .route("/admin/keys/:key_id/usage", get(admin::get_key_usage))
+        .route("/admin/db/vacuum", post(admin::post_db_vacuum))
+        .route("/admin/db/analyze", post(admin::post_db_analyze))
+        .route("/admin/payloads", get(admin::get_payloads))
+        .route("/admin/payloads/*key", get(admin::get_payload))
+        .route(
+            "/admin/requirements/:kind/:code",
+            put(audit_trail::put_requirement_config),
+        )
+        .route(
+            "/admin/overrides",
+            put(audit_trail::put_requirement_override),
+        )
+        .route("/admin/audit_trail", get(audit_trail::get_audit_trail))
+        .route(
+            "/admin/audit_trail/:entry_id/rollback",
+            post(audit_trail::post_rollback),
+        )
+        .route(
+            "/admin/scrape_term/:term",
+            get(admin::get_scrape_term).post(admin::post_scrape_term),
+        );
+
+    #[cfg(feature = "auth")]
+    let router = router
+        .route(
+            "/admin/invites",
+            get(registration::get_invites).post(registration::post_invite),
+        )
+        .route(
+            "/admin/invites/:invite_id",
+            delete(registration::delete_invite),
+        )
+        .route(
+            "/admin/keys",
+            get(api_keys::get_keys).post(api_keys::post_key),
+        )
+        .route("/admin/keys/:key_id", delete(api_keys::delete_key))
+        .route(
+            "/admin/keys/:key_id/filter",
+            put(api_keys::put_key_filter).delete(api_keys::delete_key_filter),
+        );
+
+    router
+        .layer(mw::from_fn_with_state(
+            app_state.clone(),
+            admin_auth::check_admin_token,
+        ))
+        .with_state(app_state)
+        .layer(mw::from_fn(dry_run::reject_unsupported_dry_run))
+        .layer(mw::from_fn(error_envelope::wrap_errors))
+        .layer(mw::from_fn(request_id::assign_request_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{header, Request};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use crate::types::tests::state;
+
+    const ADMIN_TOKEN: &str = "admin-token";
+
+    /// Creates a key like the ones that students' clients are given.
+    #[cfg(feature = "auth")]
+    fn student_key(s: &WrapperState) -> String {
+        s.auth_manager.generate_api_key(Some("student"))
     }
+
+    /// Without the auth feature, any bearer token is let through the student-facing API.
     #[cfg(not(feature = "auth"))]
-    {
-        router
+    fn student_key(_: &WrapperState) -> String {
+        "prefix#key".to_owned()
+    }
+
+    /// Creates a key that only has the given scope.
+    #[cfg(feature = "auth")]
+    fn scoped_key(s: &WrapperState, scope: &str) -> String {
+        let key = s.auth_manager.generate_api_key(Some(scope));
+        let (prefix, _) = key.split_once('#').unwrap();
+        s.schedule_db.set_key_scopes(prefix, scope).unwrap();
+        key
+    }
+
+    async fn status(router: &Router, uri: &str, token: Option<&str>) -> StatusCode {
+        let mut req = Request::builder().uri(uri);
+        if let Some(token) = token {
+            req = req.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+
+        let req = req.body(Body::empty()).unwrap();
+        router.clone().oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_admin_api_not_in_student_router() {
+        let s = Arc::new(state(
+            "student-router",
+            &["FA24"],
+            json!({ "adminToken": ADMIN_TOKEN }),
+        ));
+        let key = student_key(&s);
+        let router = create_router(s);
+
+        assert_eq!(StatusCode::OK, status(&router, "/health", Some(&key)).await);
+        for uri in [
+            "/admin/status",
+            "/admin/leader",
+            "/admin/keys",
+            "/admin/jobs",
+        ] {
+            assert_eq!(
+                StatusCode::NOT_FOUND,
+                status(&router, uri, Some(&key)).await
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_admin_router_needs_admin_token() {
+        let s = Arc::new(state(
+            "admin-router",
+            &["FA24"],
+            json!({ "adminToken": ADMIN_TOKEN }),
+        ));
+        let key = student_key(&s);
+        let router = create_admin_router(s);
+
+        let unauthorized = StatusCode::UNAUTHORIZED;
+        assert_eq!(unauthorized, status(&router, "/admin/leader", None).await);
+        assert_eq!(
+            unauthorized,
+            status(&router, "/admin/leader", Some(&key)).await
+        );
+        assert_eq!(
+            StatusCode::OK,
+            status(&router, "/admin/leader", Some(ADMIN_TOKEN)).await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_admin_router_without_token_is_closed() {
+        let s = Arc::new(state("admin-router-no-token", &["FA24"], json!({})));
+        let router = create_admin_router(s);
+        assert_eq!(
+            StatusCode::UNAUTHORIZED,
+            status(&router, "/admin/leader", None).await
+        );
+        assert_eq!(
+            StatusCode::UNAUTHORIZED,
+            status(&router, "/admin/leader", Some("anything")).await
+        );
+
+        // Unless it's explicitly allowed to be open
+        let s = Arc::new(state(
+            "admin-router-open",
+            &["FA24"],
+            json!({ "allowUnauthenticatedAdmin": true }),
+        ));
+        let router = create_admin_router(s);
+        assert_eq!(StatusCode::OK, status(&router, "/admin/leader", None).await);
+    }
+
+    #[cfg(feature = "auth")]
+    #[tokio::test]
+    async fn test_schedule_key_cant_import_bundles() {
+        let s = Arc::new(state("schedule-key-import", &["FA24"], json!({})));
+        let key = scoped_key(&s, "schedule");
+        let router = create_router(s);
+
+        let req = Request::builder()
+            .method("POST")
+            .uri("/users/me/import")
+            .header(header::AUTHORIZATION, format!("Bearer {key}"))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from("{}"))
+            .unwrap();
+        let res = router.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::FORBIDDEN, res.status());
+
+        // Schedule reads are still allowed
+        assert_ne!(
+            StatusCode::FORBIDDEN,
+            status(
+                &router,
+                "/schedule_conflicts?sections=FA24:123456",
+                Some(&key)
+            )
+            .await
+        );
+    }
+
+    #[cfg(feature = "auth")]
+    #[tokio::test]
+    async fn test_enroll_key_cant_read_schedules() {
+        let s = Arc::new(state("enroll-key-schedule", &["FA24"], json!({})));
+        let key = scoped_key(&s, "enroll");
+        let router = create_router(s);
+
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            status(&router, "/schedule_ical?sections=FA24:123456", Some(&key)).await
+        );
+        assert_ne!(
+            StatusCode::FORBIDDEN,
+            status(&router, "/sessions", Some(&key)).await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_share_link_needs_no_key() {
+        let s = Arc::new(state("share-no-key", &["FA24"], json!({})));
+        let expires_at = chrono::Utc::now() + chrono::Duration::hours(1);
+        let share_id = s
+            .schedule_db
+            .insert_shared_schedule(
+                "FA24",
+                "Main",
+                "[]",
+                &expires_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            )
+            .unwrap();
+        let token = s.share_signer.sign(share_id, expires_at);
+        let router = create_router(s);
+
+        assert_eq!(
+            StatusCode::OK,
+            status(&router, &format!("/shared/{token}"), None).await
+        );
+        assert_eq!(
+            StatusCode::OK,
+            status(&router, &format!("/shared/{token}.ics"), None).await
+        );
+        assert_eq!(
+            StatusCode::NOT_FOUND,
+            status(&router, &format!("/shared/{token}.pdf"), None).await
+        );
+
+        let req = Request::builder()
+            .uri(format!("/shared/{token}.png"))
+            .body(Body::empty())
+            .unwrap();
+        let res = router.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+        assert_eq!("image/png", res.headers()[header::CONTENT_TYPE]);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(b"\x89PNG\r\n\x1a\n", &body[..8]);
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_share_snapshot() {
+        let s = Arc::new(state("share-corrupt", &["FA24"], json!({})));
+        let expires_at = chrono::Utc::now() + chrono::Duration::hours(1);
+        let share_id = s
+            .schedule_db
+            .insert_shared_schedule(
+                "FA24",
+                "Main",
+                "{not json",
+                &expires_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            )
+            .unwrap();
+        let token = s.share_signer.sign(share_id, expires_at);
+        let router = create_router(s);
+
+        for suffix in ["", ".ics", ".png"] {
+            assert_eq!(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                status(&router, &format!("/shared/{token}{suffix}"), None).await
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_addresses_limited_before_auth() {
+        let s = Arc::new(state(
+            "ip-rate-limit",
+            &["FA24"],
+            json!({ "ipRateLimit": 2, "trustedProxies": ["127.0.0.1"] }),
+        ));
+        let router = create_router(s);
+        let proxy = std::net::SocketAddr::from(([127, 0, 0, 1], 8000));
+        let request = |client: &str| {
+            let mut req = Request::builder()
+                .uri("/health")
+                .header(header::AUTHORIZATION, "Bearer not#valid")
+                .header("X-Forwarded-For", client)
+                .body(Body::empty())
+                .unwrap();
+            req.extensions_mut()
+                .insert(axum::extract::ConnectInfo(proxy));
+            router.clone().oneshot(req)
+        };
+
+        // Requests with a bad key count against their address, too
+        for _ in 0..2 {
+            let res = request("203.0.113.7").await.unwrap();
+            assert_ne!(StatusCode::TOO_MANY_REQUESTS, res.status());
+        }
+        let res = request("203.0.113.7").await.unwrap();
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, res.status());
+
+        // Other clients behind the same proxy aren't affected
+        let res = request("203.0.113.8").await.unwrap();
+        assert_ne!(StatusCode::TOO_MANY_REQUESTS, res.status());
+    }
+
+    #[tokio::test]
+    async fn test_admin_status() {
+        let s = Arc::new(state(
+            "admin-status",
+            &["WI25", "FA24"],
+            json!({ "adminToken": ADMIN_TOKEN }),
+        ));
+        s.schedule_db.insert_scrape_job("FA24").unwrap();
+        let router = create_admin_router(s);
+
+        let req = Request::builder()
+            .uri("/admin/status")
+            .header(header::AUTHORIZATION, format!("Bearer {ADMIN_TOKEN}"))
+            .body(Body::empty())
+            .unwrap();
+        let res = router.oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        // Terms are sorted, and haven't made any requests yet
+        assert_eq!("FA24", body["terms"][0]["term"]);
+        assert_eq!("WI25", body["terms"][1]["term"]);
+        assert_eq!(json!(null), body["terms"][0]["avg_request_ms"]);
+        assert_eq!(1, body["queues"]["scrape_jobs"]);
+        assert_eq!(0, body["queues"]["enroll_jobs"]);
     }
 }
@@ -6,49 +6,113 @@ use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use webweg::types::{SectionIdNotFoundContext, WrapperError};
-use webweg::wrapper::input_types::{
-    CourseLevelFilter, DayOfWeek, SearchRequestBuilder, SearchType,
+use webweg::types::WrapperError;
+pub use webreg_types::requests::{
+    AuditQueryParams, BodyAddInfo, BodyBulkAdd, BodyCourseInfoBatch, BodyPlanAdd,
+    BodyScheduleNameChange, BodySearchType, BodySectionId, BodySectionScheduleNameId,
+    BodySessionCookies, BodySwapSections, CourseQueryStr, ForceQueryStr, GraduationPlanQueryParams,
+    ScheduleQueryStr, SearchV2QueryStr, SubjListQueryStr,
 };
 
-#[derive(Deserialize, Debug)]
-pub struct BodySectionId {
+use crate::audit_trail::OverrideKind;
+use crate::degree_audit::config::SubrequirementConfig;
+use crate::degree_audit::RequirementStatus;
+use crate::error::WebregError;
+use crate::notify::NotifyChannel;
+
+/// A structure meant for a request body, used to schedule an enrollment job.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyEnrollJob {
     #[serde(rename = "sectionId")]
     pub section_id: String,
+    #[serde(rename = "subjectCode")]
+    pub subject_code: String,
+    #[serde(rename = "courseCode")]
+    pub course_code: String,
+    #[serde(rename = "gradingOption")]
+    pub grading_option: Option<String>,
+    #[serde(rename = "unitCount")]
+    pub unit_count: Option<i64>,
+    /// When to start trying to enroll, in RFC 3339 format.
+    #[serde(rename = "runAt")]
+    pub run_at: Option<String>,
+    /// When to give up, in RFC 3339 format.
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<String>,
+    /// The channels to notify once the student is enrolled, or the job fails or expires.
+    #[serde(default)]
+    pub notify: Vec<NotifyChannel>,
+    /// Minutes to wait after alerting the channels about open seats before alerting again.
+    #[serde(rename = "cooldownMins")]
+    pub cooldown_mins: Option<u32>,
+    /// Minutes that an alert about the same number of open seats is dropped for.
+    #[serde(rename = "dedupMins")]
+    pub dedup_mins: Option<u32>,
+    /// The number of seats that need to be open before the channels are alerted.
+    #[serde(rename = "minSeats")]
+    pub min_seats: Option<i64>,
 }
 
-#[derive(Deserialize, Debug)]
-pub struct BodySectionScheduleNameId {
+/// A section in a request body that adds a course to the cart.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyCartSection {
     #[serde(rename = "sectionId")]
     pub section_id: String,
-
-    #[serde(rename = "scheduleName")]
-    pub schedule_name: Option<String>,
+    #[serde(rename = "sectionCode")]
+    pub section_code: String,
 }
 
-#[derive(Deserialize, Debug)]
-pub struct BodyScheduleNameChange {
-    #[serde(rename = "oldName")]
-    pub old_name: String,
+/// A structure meant for a request body, used to add a course to the cart or replace its
+/// item.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyCartItem {
+    #[serde(rename = "subjectCode")]
+    pub subject_code: String,
+    #[serde(rename = "courseCode")]
+    pub course_code: String,
+    /// The sections that the student would take, most preferred first.
+    #[serde(default)]
+    pub sections: Vec<BodyCartSection>,
+    pub notes: Option<String>,
+    /// Lower is more important.
+    pub priority: Option<i64>,
+    #[serde(rename = "gradingOption")]
+    pub grading_option: Option<String>,
+    #[serde(rename = "unitCount")]
+    pub unit_count: Option<i64>,
+}
 
-    #[serde(rename = "newName")]
-    pub new_name: String,
+/// Where a cart item is promoted to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CartTarget {
+    /// The student's WebReg plan.
+    Plan,
+    /// The student's schedule, by enrolling (or waitlisting) them.
+    Enroll,
 }
 
-#[derive(Deserialize, Debug)]
-pub struct BodyAddInfo {
+/// A structure meant for a request body, used to promote a cart item.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyCartPromote {
+    pub target: CartTarget,
+    /// The section to promote, which must be one of the item's sections. Defaults to the
+    /// item's most preferred section.
     #[serde(rename = "sectionId")]
-    pub section_id: String,
-    #[serde(rename = "gradingOption")]
-    pub grading_option: Option<String>,
+    pub section_id: Option<String>,
+    /// The plan to add the section to, if promoting to a plan.
+    #[serde(rename = "scheduleName")]
+    pub schedule_name: Option<String>,
+    /// The units to plan the section for, if promoting to a plan. Defaults to the item's,
+    /// and then to the course's, if it can only be taken for one number of units.
     #[serde(rename = "unitCount")]
     pub unit_count: Option<i64>,
-    #[serde(rename = "validate")]
     pub validate: Option<bool>,
 }
 
-#[derive(Deserialize, Debug)]
-pub struct BodyPlanAdd {
+/// A section in a saved plan.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyPlanEntry {
     #[serde(rename = "subjectCode")]
     pub subject_code: String,
     #[serde(rename = "courseCode")]
@@ -59,49 +123,562 @@ pub struct BodyPlanAdd {
     pub section_code: String,
     #[serde(rename = "gradingOption")]
     pub grading_option: Option<String>,
-    #[serde(rename = "scheduleName")]
-    pub schedule_name: Option<String>,
     #[serde(rename = "unitCount")]
-    pub unit_count: i64,
-    pub validate: Option<bool>,
+    pub unit_count: Option<i64>,
+}
+
+/// A structure meant for a request body, used to save a plan or replace the plan with the
+/// same schedule name.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodySavedPlan {
+    /// The WebReg schedule that the plan is synced into.
+    #[serde(rename = "scheduleName")]
+    pub schedule_name: String,
+    #[serde(default)]
+    pub entries: Vec<BodyPlanEntry>,
+}
+
+/// The courses planned for one quarter of a course plan.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyPlannedQuarter {
+    /// The quarter (e.g., `FA24`).
+    pub term: String,
+    /// The codes of the courses planned for the quarter (e.g., `CSE 100`).
+    #[serde(default)]
+    pub courses: Vec<String>,
+}
+
+/// A structure meant for a request body, used to define a requirement set, or replace the
+/// user's set with the same name.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyCustomRequirements {
+    pub name: String,
+    #[serde(default)]
+    pub subrequirements: Vec<SubrequirementConfig>,
+}
+
+/// A structure meant for a request body, used to save a course plan or replace one.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyCoursePlan {
+    pub name: String,
+    #[serde(default)]
+    pub quarters: Vec<BodyPlannedQuarter>,
+}
+
+/// An enrollment job in a user's bundle (see [`BodyUserBundle`]).
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BundleEnrollJob {
+    pub term: String,
+    #[serde(flatten)]
+    pub job: BodyEnrollJob,
+}
+
+/// A saved plan in a user's bundle (see [`BodyUserBundle`]).
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BundleSavedPlan {
+    pub term: String,
+    #[serde(flatten)]
+    pub plan: BodySavedPlan,
+}
+
+/// A cart item in a user's bundle (see [`BodyUserBundle`]).
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BundleCartItem {
+    pub term: String,
+    #[serde(flatten)]
+    pub item: BodyCartItem,
+}
+
+/// A requirement override in a user's bundle (see [`BodyUserBundle`]), which is for the
+/// user's own audit.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BundleRequirementOverride {
+    pub requirement: String,
+    pub status: RequirementStatus,
+    pub kind: OverrideKind,
+}
+
+/// A bundle made by `GET /users/:id/export`, to be imported on another deployment.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyUserBundle {
+    pub version: u32,
+    #[serde(rename = "enrollJobs", default)]
+    pub enroll_jobs: Vec<BundleEnrollJob>,
+    #[serde(rename = "savedPlans", default)]
+    pub saved_plans: Vec<BundleSavedPlan>,
+    #[serde(default)]
+    pub cart: Vec<BundleCartItem>,
+    #[serde(rename = "coursePlans", default)]
+    pub course_plans: Vec<BodyCoursePlan>,
+    #[serde(rename = "customRequirements", default)]
+    pub custom_requirements: Vec<BodyCustomRequirements>,
+    #[serde(rename = "requirementOverrides", default)]
+    pub requirement_overrides: Vec<BundleRequirementOverride>,
+}
+
+/// The session whose enrollment jobs are moved onto the caller's session by
+/// `POST /sessions/reattach`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodySessionReattach {
+    #[serde(rename = "previousToken")]
+    pub previous_token: String,
 }
 
-/// A structure meant for a query string, intended to require the user to provide a name
-/// for the schedule.
-#[derive(Deserialize, Debug)]
-pub struct ScheduleQueryStr {
+#[cfg(feature = "auth")]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyRegister {
+    #[serde(rename = "inviteCode")]
+    pub invite_code: String,
+    /// Who the key is for, which is recorded in the key's description.
     pub name: Option<String>,
 }
 
-/// A structure meant for a query string, intended to have the user provide a course to
-/// search up in some way.
-#[derive(Deserialize, Debug)]
-pub struct CourseQueryStr {
-    pub subject: String,
-    pub number: String,
+#[cfg(feature = "auth")]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyMintInvite {
+    pub description: Option<String>,
+    #[serde(rename = "maxUses")]
+    pub max_uses: Option<i64>,
+    #[serde(rename = "expiresInDays")]
+    pub expires_in_days: Option<i64>,
+}
+
+#[cfg(feature = "auth")]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyMintKey {
+    pub description: Option<String>,
+    pub scopes: Vec<crate::api_keys::ApiScope>,
+    /// The fields that the key may and may not see in responses. If not set, it sees them
+    /// all.
+    #[serde(default)]
+    pub filter: Option<crate::redaction::ResponseFilter>,
+}
+
+/// Not `Debug`, so that the password can't end up in the logs.
+#[cfg(feature = "auth")]
+#[derive(Serialize, Deserialize)]
+pub struct BodyVaultCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// A structure meant for a request body, used to create or edit a custom event.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyCustomEvent {
+    pub name: String,
+    pub location: Option<String>,
+    pub days: Vec<String>,
+    #[serde(rename = "startHr")]
+    pub start_hr: i32,
+    #[serde(rename = "startMin")]
+    pub start_min: i32,
+    #[serde(rename = "endHr")]
+    pub end_hr: i32,
+    #[serde(rename = "endMin")]
+    pub end_min: i32,
+}
+
+/// A structure meant for a request body, used to share a snapshot of one of the student's
+/// schedules.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyShareSchedule {
+    #[serde(rename = "scheduleName")]
+    pub schedule_name: String,
+    /// How long the link should work for, in hours.
+    #[serde(rename = "ttlHours")]
+    pub ttl_hours: Option<u32>,
+}
+
+/// A structure meant for a request body, used to replace a college's or major's
+/// requirement config.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyRequirementConfig {
+    /// The config, in the same format as the files in `requirements_config`.
+    pub config: Value,
+    pub author: String,
+    pub reason: String,
+}
+
+/// A structure meant for a request body, used to override the status of a requirement on
+/// the student's audit.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyRequirementOverride {
+    /// The requirement's or subrequirement's name, as shown on the audit.
+    pub requirement: String,
+    /// The status to give the requirement, or nothing to remove the override.
+    pub status: Option<RequirementStatus>,
+    /// Whether the override is manual or from a petition; manual if not given.
+    pub kind: Option<OverrideKind>,
+    /// The prefix of the API key of the user whose audit the override is for, in
+    /// multi-user mode. If not given, the override is for the shared audit.
+    #[serde(default)]
+    pub user: Option<String>,
+    pub author: String,
+    pub reason: String,
+}
+
+/// A structure meant for a request body, used to say who rolled something back and why.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BodyRollback {
+    pub author: String,
+    pub reason: String,
+}
+
+/// A structure meant for a query string, intended to let the user see what an operation
+/// would change without changing anything
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DryRunQueryStr {
+    pub dry_run: Option<bool>,
+}
+
+/// A structure meant for a query string, intended to have the user optionally filter
+/// instructors by name
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InstructorQueryStr {
+    pub search: Option<String>,
+}
+
+/// A structure meant for a query string, intended to have the user provide a time window
+/// in which to look for free rooms
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FreeRoomQueryStr {
+    /// The day (e.g., `M` or `Tu`).
+    pub day: String,
+    /// The start of the window, in `HH:MM` format.
+    pub start: String,
+    /// The end of the window, in `HH:MM` format.
+    pub end: String,
+    /// Only look for rooms in this building.
+    pub building: Option<String>,
+}
+
+/// A structure meant for a query string, intended to have the user search the catalog's
+/// course descriptions
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SemanticSearchQueryStr {
+    /// What to search for (e.g., `machine learning with lots of math`).
+    pub q: String,
+    pub limit: Option<usize>,
+    /// Whether to only include courses offered in the term. Defaults to `true`.
+    #[serde(rename = "offeredOnly")]
+    pub offered_only: Option<bool>,
+}
+
+/// A structure meant for a query string, intended to limit the number of alternatives to a
+/// section
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AlternativesQueryStr {
+    pub limit: Option<usize>,
+}
+
+/// A structure meant for a query string, intended to pick how many days of the status
+/// history to return
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StatusHistoryQueryStr {
+    pub days: Option<u32>,
+}
+
+/// A structure meant for a query string, intended to have the user pick whether cancelled
+/// sections are included in a term's schedule data
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ScheduleDataQueryStr {
+    #[serde(default)]
+    pub include_cancelled: bool,
+}
+
+/// A structure meant for a query string, intended to have the user pick the format of a
+/// schedule data export
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ScheduleExportQueryStr {
+    /// `csv` (the default) or `parquet`.
+    pub format: Option<String>,
+    /// Whether to sort the rows canonically and include the data's hash.
+    #[serde(default)]
+    pub canonical: bool,
+}
+
+/// A structure meant for a query string, intended to have the user provide a major to
+/// build a requirement gap report for
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GapReportQueryStr {
+    /// The major code (e.g., `MA30`).
+    pub major: String,
+    /// The largest number of subrequirements and courses to return.
+    pub limit: Option<usize>,
+}
+
+/// A structure meant for a query string, intended to have the user filter the archived
+/// payloads
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PayloadQueryStr {
+    /// The prefix that the payloads' keys start with (e.g., `webreg/FA24/`).
+    pub prefix: Option<String>,
+}
+
+/// A structure meant for a query string, intended to have the user filter the audit trail
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuditTrailQueryStr {
+    /// Only versions of this kind (`college`, `major`, or `override`).
+    pub kind: Option<String>,
+    /// Only versions of this entity (e.g., `MA30`).
+    pub key: Option<String>,
+    /// The largest number of versions to return.
+    pub limit: Option<usize>,
+}
+
+/// A structure meant for a query string, intended to have the user provide a "list" of
+/// sections, each given as `TERM:SECTION_ID` (e.g., `S124:123456`)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SectionListQueryStr {
+    pub sections: String,
+    /// Whether the session's custom events for the sections' terms should be included, which
+    /// needs a session token.
+    #[serde(rename = "includeEvents")]
+    pub include_events: Option<bool>,
+}
+
+/// A structure meant for a query string, used to narrow down the co-enrollment graph.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CoEnrollmentQueryStr {
+    /// Only count schedules from this term, if given.
+    pub term: Option<String>,
+    /// The most courses to return.
+    pub limit: Option<usize>,
+}
+
+/// A structure meant for a query string, used to choose how the student's mirrored
+/// schedule is returned.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MyScheduleQueryStr {
+    /// `json` (the default) or `ics`.
+    pub format: Option<String>,
 }
 
 /// A structure meant for a query string, intended to have the user provide a "list" of
-/// subject code (e.g., CSE)
-#[derive(Deserialize, Debug)]
-pub struct SubjListQueryStr {
-    pub subjects: String,
+/// section IDs in one term (e.g., `123456,234567`)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FinalsQueryStr {
+    pub sections: String,
+}
+
+/// A structure meant for a query string, intended to have the user ask for the section
+/// changes since some time, optionally for one subject code (e.g., CSE)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChangesQueryStr {
+    /// Milliseconds since the epoch, or an RFC 3339 time.
+    pub since: String,
+    pub subject: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// The query string for delta sync; the cursor is the one returned by the last sync, if
+/// any
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SyncQueryStr {
+    pub cursor: Option<String>,
+}
+
+/// A structure meant for a query string, intended to let the user see the watches that
+/// have expired along with the pending ones
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WatchesQueryStr {
+    pub include_expired: Option<bool>,
+}
+
+/// The query string for a live event stream, intended to let the user throttle the alerts
+/// for a flapping section (see [`crate::scraper::throttle`])
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LiveWatchQueryStr {
+    /// Minutes to wait after an alert about a section before alerting about it again.
+    pub cooldown_mins: Option<u64>,
+    /// Minutes that an alert about the same state of a section is dropped for.
+    pub dedup_mins: Option<u64>,
+    /// The number of seats that need to be open for a seat opening to be alerted about.
+    pub min_seats: Option<i64>,
 }
 
 /// A structure meant for a query string, intended to give users the ability to control
 /// the type of response they wanted.
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct RawQueryStr {
     pub raw: Option<bool>,
 }
 
+/// Query parameters for the elective suggestions endpoint.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ElectiveQueryParams {
+    /// If true, bypass cache and fetch fresh data
+    #[serde(default)]
+    pub refresh: bool,
+    /// The most electives to suggest
+    pub limit: Option<usize>,
+}
+
+/// A machine-readable code for an error that the API responds with, so that clients can
+/// handle errors without matching on their messages. Every error has a code: a specific one
+/// where the API knows what went wrong, and otherwise the general one for its status.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    // The general codes, one for each status that the API responds with
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+    Gone,
+    PayloadTooLarge,
+    RateLimited,
+    InternalError,
+    NotImplemented,
+    UpstreamError,
+    ServiceUnavailable,
+
+    /// The request body isn't JSON, or doesn't have the expected shape.
+    InvalidBody,
+    /// The request body has the expected shape, but some of its fields aren't valid. The
+    /// error's details list them (see [`FieldError`]).
+    ValidationFailed,
+    /// The term isn't one that the API serves.
+    TermNotFound,
+    /// The section doesn't exist in the term, or isn't in the student's schedule.
+    SectionNotFound,
+    /// The section doesn't have any open seats.
+    SectionFull,
+    /// The section conflicts with the student's schedule.
+    ScheduleConflict,
+    /// The WebReg cookies or session token are missing, invalid, or expired.
+    CookieExpired,
+    /// The bearer token is missing, invalid, or expired.
+    InvalidToken,
+    /// The API key doesn't have the scope that the endpoint needs.
+    ScopeDenied,
+    /// The API key has used up its quota.
+    QuotaExceeded,
+    /// The API key has a response filter, which can't be applied to the endpoint's
+    /// responses (e.g., because they aren't JSON).
+    ResponseFiltered,
+    /// The term's scraper isn't ready to make requests yet.
+    NotReady,
+    /// The server is too loaded to serve the request.
+    Overloaded,
+    /// The WebReg login server is down.
+    AuthBackendUnavailable,
+}
+
+impl ErrorCode {
+    /// Gets the general code for a status.
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED => Self::Unauthorized,
+            StatusCode::FORBIDDEN => Self::Forbidden,
+            StatusCode::NOT_FOUND => Self::NotFound,
+            StatusCode::CONFLICT => Self::Conflict,
+            StatusCode::GONE => Self::Gone,
+            StatusCode::PAYLOAD_TOO_LARGE => Self::PayloadTooLarge,
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited,
+            StatusCode::NOT_IMPLEMENTED => Self::NotImplemented,
+            StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => Self::UpstreamError,
+            StatusCode::SERVICE_UNAVAILABLE => Self::ServiceUnavailable,
+            s if s.is_server_error() => Self::InternalError,
+            _ => Self::BadRequest,
+        }
+    }
+}
+
+/// A field of a request body that isn't valid.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// The field's path in the body, as it's named in JSON (e.g., `add.sectionId` or
+    /// `sections[1].unitCount`). Empty for the body as a whole.
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Creates an error response, with the body that every error response has:
+/// `{ "code": ..., "message": ..., "details": ... }`.
+///
+/// # Parameters
+/// - `status`: The response's status.
+/// - `code`: The error's code.
+/// - `message`: What went wrong, for people.
+/// - `details`: More about what went wrong, if anything (e.g., the invalid fields of a
+///   request body, or the context of an error from WebReg).
+///
+/// # Returns
+/// The response.
+pub fn error_response(
+    status: StatusCode,
+    code: ErrorCode,
+    message: &str,
+    details: Option<Value>,
+) -> Response {
+    (
+        status,
+        Json(json!({
+            "code": code,
+            "message": message,
+            "details": details,
+        })),
+    )
+        .into_response()
+}
+
 /// An enum that represents some sort of an error by the API.
 pub enum ApiErrorType<'a> {
     /// Whether the error was from WebReg.
     WebReg(WrapperError),
 
+    /// Whether the error was already sorted into a category.
+    Categorized(WebregError),
+
     /// Whether the error is custom-made.
-    General(StatusCode, Cow<'a, str>, Option<String>),
+    General {
+        status: StatusCode,
+        code: ErrorCode,
+        message: Cow<'a, str>,
+        details: Option<Value>,
+    },
+}
+
+impl<'a> ApiErrorType<'a> {
+    /// Gives the error a more specific code than its status's.
+    pub fn with_code(self, code: ErrorCode) -> Self {
+        match self {
+            ApiErrorType::WebReg(err) => WebregError::from(err).with_code(code).into(),
+            ApiErrorType::Categorized(err) => err.with_code(code).into(),
+            ApiErrorType::General {
+                status,
+                message,
+                details,
+                ..
+            } => ApiErrorType::General {
+                status,
+                code,
+                message,
+                details,
+            },
+        }
+    }
+
+    /// Creates a `400 Bad Request` error for a request body whose fields aren't valid.
+    pub fn invalid_fields(errors: Vec<FieldError>) -> Self {
+        ApiErrorType::General {
+            status: StatusCode::BAD_REQUEST,
+            code: ErrorCode::ValidationFailed,
+            message: "Some of the request's fields aren't valid.".into(),
+            details: Some(json!(errors)),
+        }
+    }
 }
 
 impl<'a> From<WrapperError> for ApiErrorType<'a> {
@@ -110,77 +687,38 @@ impl<'a> From<WrapperError> for ApiErrorType<'a> {
     }
 }
 
+impl<'a> From<WebregError> for ApiErrorType<'a> {
+    fn from(value: WebregError) -> Self {
+        Self::Categorized(value)
+    }
+}
+
 impl<'a, T> From<(StatusCode, T, Option<String>)> for ApiErrorType<'a>
 where
     T: Into<Cow<'a, str>>,
 {
     fn from((status, base, additional): (StatusCode, T, Option<String>)) -> Self {
-        Self::General(status, base.into(), additional)
+        Self::General {
+            status,
+            code: ErrorCode::from_status(status),
+            message: base.into(),
+            details: additional.map(Value::from),
+        }
     }
 }
 
 impl<'a> IntoResponse for ApiErrorType<'a> {
     fn into_response(self) -> Response {
-        let (status_code, base_error, additional_error) = match self {
-            ApiErrorType::WebReg(err) => match err {
-                WrapperError::RequestError(r) => {
-                    (StatusCode::INTERNAL_SERVER_ERROR, "An internal request error occurred.".into(), Some(r.to_string()))
-                }
-                WrapperError::UrlParseError(_) => {
-                    (StatusCode::INTERNAL_SERVER_ERROR, "An internal URL parsing error occurred.".into(), None)
-                }
-                WrapperError::InputError(i, e) => {
-                    (StatusCode::BAD_REQUEST, "A bad argument was passed in.".into(), Some(format!("input={i}, bad arg value={e}")))
-                }
-                WrapperError::SerdeError(s) => {
-                    (StatusCode::IM_A_TEAPOT, "An error occurred when trying to convert a string to a JSON object. It's possible your session is not valid.".into(), Some(s.to_string()))
-                }
-                WrapperError::BadStatusCode(b, c) => {
-                    (StatusCode::from_u16(b).unwrap(), "A non-OK status code was hit.".into(), c)
-                }
-                WrapperError::WebRegError(w) => {
-                    (StatusCode::BAD_REQUEST, "WebReg returned an error regarding your request.".into(), Some(w))
-                }
-                WrapperError::SectionIdNotFound(s, c) => {
-                    let base = match c {
-                        SectionIdNotFoundContext::Schedule => {
-                            "The section ID you specified wasn't found in your schedule.".into()
-                        }
-                        SectionIdNotFoundContext::Catalog => {
-                            "The section ID you specified doesn't appear to be offered in the specified term.".into()
-                        }
-                    };
-
-                    (StatusCode::NOT_FOUND, base, Some(s))
-                }
-                WrapperError::WrapperParsingError(p) => {
-                    (StatusCode::INTERNAL_SERVER_ERROR, "An error occurred when trying to convert the response JSON into an object.".into(), Some(p))
-                }
-                WrapperError::SessionNotValid => {
-                    (StatusCode::UNAUTHORIZED, "Your session isn't valid. Try a different set of WebReg cookies.".into(), None)
-                }
-                WrapperError::BadTimeError => {
-                    (StatusCode::INTERNAL_SERVER_ERROR, "An error occurred when trying to parse a time unit.".into(), None)
-                }
-            }
-            ApiErrorType::General(code, err, additional_info) => {
-                (code, err, additional_info)
-            }
-        };
-
-        let json_obj = match additional_error {
-            None => {
-                json!({ "error": base_error })
-            }
-            Some(a) => {
-                json!({
-                    "error": base_error,
-                    "context": a
-                })
-            }
-        };
-
-        (status_code, Json(json_obj)).into_response()
+        match self {
+            ApiErrorType::WebReg(err) => WebregError::from(err).into_response(),
+            ApiErrorType::Categorized(err) => err.into_response(),
+            ApiErrorType::General {
+                status,
+                code,
+                message,
+                details,
+            } => error_response(status, code, &message, details),
+        }
     }
 }
 
@@ -222,136 +760,3 @@ where
         }
     }
 }
-
-// https://serde.rs/enum-representations.html#untagged
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
-#[allow(clippy::large_enum_variant)]
-pub enum BodySearchType {
-    SectionId {
-        #[serde(rename = "sectionId")]
-        section_id: String,
-    },
-    SectionIds {
-        #[serde(rename = "sectionIds")]
-        section_ids: Vec<String>,
-    },
-    SearchAdvanced {
-        subjects: Option<Vec<String>>,
-        courses: Option<Vec<String>>,
-        departments: Option<Vec<String>>,
-        instructor: Option<String>,
-        title: Option<String>,
-        #[serde(rename = "onlyOpen")]
-        only_open: Option<bool>,
-        #[serde(rename = "startHour")]
-        start_hour: Option<i64>,
-        #[serde(rename = "startMin")]
-        start_min: Option<i64>,
-        #[serde(rename = "endHour")]
-        end_hour: Option<i64>,
-        #[serde(rename = "endMin")]
-        end_min: Option<i64>,
-        days: Option<Vec<String>>,
-        #[serde(rename = "levelFilter")]
-        level_filter: Option<Vec<String>>,
-    },
-}
-
-impl From<BodySearchType> for SearchType {
-    fn from(value: BodySearchType) -> Self {
-        match value {
-            BodySearchType::SectionId { section_id } => SearchType::BySection(section_id),
-            BodySearchType::SectionIds { section_ids } => {
-                SearchType::ByMultipleSections(section_ids)
-            }
-            BodySearchType::SearchAdvanced {
-                subjects,
-                courses,
-                departments,
-                instructor,
-                title,
-                only_open,
-                start_hour,
-                start_min,
-                end_hour,
-                end_min,
-                days,
-                level_filter,
-            } => {
-                let mut search = SearchRequestBuilder::new();
-                if let Some(s) = subjects {
-                    search.subjects = s;
-                }
-
-                if let Some(c) = courses {
-                    search.courses = c;
-                }
-
-                if let Some(d) = departments {
-                    search.departments = d;
-                }
-
-                if let Some(i) = instructor {
-                    search = search.set_instructor(i);
-                }
-
-                if let Some(t) = title {
-                    search = search.set_title(t);
-                }
-
-                if let Some(o) = only_open {
-                    search.only_open = o;
-                }
-
-                if let (Some(h), Some(m)) = (
-                    start_hour.and_then(|h| u32::try_from(h).ok()),
-                    start_min.and_then(|m| u32::try_from(m).ok()),
-                ) {
-                    search = search.set_start_time(h, m);
-                }
-
-                if let (Some(h), Some(m)) = (
-                    end_hour.and_then(|h| u32::try_from(h).ok()),
-                    end_min.and_then(|m| u32::try_from(m).ok()),
-                ) {
-                    search = search.set_end_time(h, m);
-                }
-
-                if let Some(d) = days {
-                    for day in d {
-                        match day.as_str() {
-                            "M" | "m" => search = search.apply_day(DayOfWeek::Monday),
-                            "Tu" | "tu" => search = search.apply_day(DayOfWeek::Tuesday),
-                            "W" | "w" => search = search.apply_day(DayOfWeek::Wednesday),
-                            "Th" | "th" => search = search.apply_day(DayOfWeek::Thursday),
-                            "F" | "f" => search = search.apply_day(DayOfWeek::Friday),
-                            "Sa" | "sa" => search = search.apply_day(DayOfWeek::Saturday),
-                            "Su" | "su" => search = search.apply_day(DayOfWeek::Sunday),
-                            _ => {}
-                        }
-                    }
-                }
-
-                if let Some(f) = level_filter {
-                    for level in f {
-                        match level.as_str() {
-                            "l" | "L" => {
-                                search = search.filter_courses_by(CourseLevelFilter::LowerDivision)
-                            }
-                            "u" | "U" => {
-                                search = search.filter_courses_by(CourseLevelFilter::UpperDivision)
-                            }
-                            "g" | "G" => {
-                                search = search.filter_courses_by(CourseLevelFilter::Graduate)
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-
-                SearchType::Advanced(search)
-            }
-        }
-    }
-}
@@ -1,6 +1,34 @@
-use crate::server::types::{BodyAddInfo, BodyPlanAdd};
+use std::sync::Arc;
+
+use axum::http::header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH};
+use axum::http::{HeaderMap, HeaderName, StatusCode};
+use axum::response::{IntoResponse, Response};
+
+use crate::schedule::{MeetingSlot, ScheduledSection, SubSession};
+use crate::server::types::{ApiErrorType, BodyAddInfo, BodyPlanAdd};
+use crate::types::{TermInfo, WrapperState};
+use webweg::types::ScheduledSection as WebRegSection;
 use webweg::wrapper::input_types::{EnrollWaitAdd, GradeOption, PlanAdd};
 
+/// Gets the state of the term in a request's path. Requests to WebReg for the term should
+/// be made with its wrapper, so that they aren't affected by the other terms.
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `term`: The term.
+///
+/// # Returns
+/// The term's state, or an error if the term isn't tracked.
+pub fn term_info(s: &WrapperState, term: &str) -> Result<Arc<TermInfo>, ApiErrorType<'static>> {
+    s.term(&term.to_uppercase()).ok_or_else(|| {
+        ApiErrorType::from((
+            StatusCode::NOT_FOUND,
+            "This term isn't tracked.",
+            Some(term.to_owned()),
+        ))
+    })
+}
+
 /// A helper function to automatically convert the given grading option and unit count from
 /// a request body to something that the library can use.
 ///
@@ -76,3 +104,124 @@ pub fn build_add_section_object(body: &BodyAddInfo) -> EnrollWaitAdd {
 
     add_req.try_build().unwrap()
 }
+
+/// Converts sections from a student's WebReg schedule so that they can be checked for
+/// conflicts or exported.
+///
+/// # Parameters
+/// - `s`: The wrapper state.
+/// - `term`: The term that the sections are in.
+/// - `sections`: The sections.
+///
+/// # Returns
+/// The converted sections.
+pub fn to_scheduled_sections(
+    s: &WrapperState,
+    term: &str,
+    sections: &[WebRegSection],
+) -> Vec<ScheduledSection> {
+    let term_info = s.term(term);
+    let dates = term_info.as_ref().and_then(|t| t.date_range);
+    let sub_session = term_info
+        .map(|t| t.sub_session)
+        .unwrap_or_else(|| SubSession::from_term(term));
+
+    sections
+        .iter()
+        .map(|section| ScheduledSection {
+            term: term.to_owned(),
+            section_id: section.section_id.clone(),
+            title: format!(
+                "{} {} ({})",
+                section.subject_code.trim(),
+                section.course_code.trim(),
+                section.section_code.trim()
+            ),
+            sub_session,
+            slots: section
+                .meetings
+                .iter()
+                .filter_map(MeetingSlot::from_webreg)
+                .map(|mut slot| {
+                    slot.dates = dates;
+                    slot
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Builds the ETag of a response that's built from a term's schedule data. It's weak, since
+/// the response may be compressed differently for different clients.
+///
+/// # Parameters
+/// - `term`: The term.
+/// - `version`: The version of the term's data.
+/// - `variant`: What sets the response apart from others built from the same data (e.g.,
+///   an export's format), if anything.
+///
+/// # Returns
+/// The ETag.
+pub fn data_etag(term: &str, version: i64, variant: &str) -> String {
+    if variant.is_empty() {
+        format!("W/\"{term}-{version}\"")
+    } else {
+        format!("W/\"{term}-{version}-{variant}\"")
+    }
+}
+
+/// Whether a request's `If-None-Match` header matches an ETag, meaning that the client
+/// already has the response. ETags are compared weakly.
+///
+/// # Parameters
+/// - `headers`: The request's headers.
+/// - `etag`: The response's ETag.
+///
+/// # Returns
+/// Whether the header matches.
+pub fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+/// The caching headers of a response with an ETag. Clients may keep the response, but have
+/// to check that it's still current before using it again.
+pub fn cache_headers(etag: String) -> [(HeaderName, String); 2] {
+    [(ETAG, etag), (CACHE_CONTROL, "no-cache".to_owned())]
+}
+
+/// Creates the `304 Not Modified` response for a client that already has the response.
+pub fn not_modified(etag: String) -> Response {
+    (StatusCode::NOT_MODIFIED, cache_headers(etag)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_etag_matches() {
+        let etag = data_etag("FA24", 17, "");
+        assert_eq!(r#"W/"FA24-17""#, etag);
+        assert_eq!(r#"W/"FA24-17-csv""#, data_etag("FA24", 17, "csv"));
+
+        let mut headers = HeaderMap::new();
+        assert!(!etag_matches(&headers, &etag));
+
+        headers.insert(
+            IF_NONE_MATCH,
+            HeaderValue::from_static(r#"W/"FA24-16", "FA24-17""#),
+        );
+        assert!(etag_matches(&headers, &etag));
+        assert!(!etag_matches(&headers, &data_etag("FA24", 18, "")));
+
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_static("*"));
+        assert!(etag_matches(&headers, &etag));
+    }
+}
@@ -0,0 +1,255 @@
+//! Checking request bodies before they reach a handler.
+//!
+//! Handlers take their bodies through [`ValidJson`] rather than axum's `Json`, so that every
+//! bad body is rejected the same way. A body that isn't JSON, or that doesn't have the
+//! expected shape (e.g., a missing field or a number where a string belongs), is rejected
+//! with `INVALID_BODY`, naming the field that couldn't be read. A body that has the expected
+//! shape, but whose fields don't pass its type's [`Validate`] checks, is rejected with
+//! `VALIDATION_FAILED`, listing every field that's wrong rather than only the first.
+
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+
+use axum::async_trait;
+use axum::extract::{FromRequest, Request};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::server::types::{
+    error_response, ApiErrorType, BodyAddInfo, BodyCustomEvent, BodyPlanAdd,
+    BodyScheduleNameChange, BodySearchType, BodySectionId, BodySectionScheduleNameId,
+    BodySwapSections, ErrorCode, FieldError,
+};
+
+/// The grading options that WebReg accepts.
+const GRADING_OPTIONS: [&str; 3] = ["L", "P", "S"];
+
+/// A request body whose fields can be checked once it's been read.
+pub trait Validate {
+    /// Checks the body's fields.
+    ///
+    /// # Returns
+    /// Every field that isn't valid, or nothing if the body is valid. Bodies don't check
+    /// anything by default.
+    fn validate(&self) -> Vec<FieldError> {
+        vec![]
+    }
+}
+
+/// A JSON request body that's been read and checked (see the module's documentation).
+pub struct ValidJson<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S> for ValidJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Validate,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<Value>::from_request(req, state).await.map_err(|e| {
+            error_response(e.status(), ErrorCode::InvalidBody, &e.body_text(), None)
+        })?;
+
+        let body: T = serde_path_to_error::deserialize(value).map_err(|e| {
+            let field = FieldError::new(field_path(&e.path().to_string()), e.inner().to_string());
+            error_response(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidBody,
+                "The request body doesn't have the expected fields.",
+                Some(json!([field])),
+            )
+        })?;
+
+        let errors = body.validate();
+        if !errors.is_empty() {
+            return Err(ApiErrorType::invalid_fields(errors).into_response());
+        }
+
+        Ok(Self(body))
+    }
+}
+
+/// Converts a path from `serde_path_to_error` to a field's path, which is empty for the
+/// body as a whole rather than `.`.
+fn field_path(path: &str) -> String {
+    if path == "." {
+        String::new()
+    } else {
+        path.to_owned()
+    }
+}
+
+/// Adds the errors of a body nested in another to the outer body's errors, with their
+/// paths under the field that the nested body is in.
+///
+/// # Parameters
+/// - `errors`: The outer body's errors.
+/// - `field`: The field that the nested body is in (e.g., `add` or `sections[1]`).
+/// - `nested`: The nested body's errors.
+pub fn extend_nested(errors: &mut Vec<FieldError>, field: &str, nested: Vec<FieldError>) {
+    errors.extend(nested.into_iter().map(|e| FieldError {
+        field: if e.field.is_empty() {
+            field.to_owned()
+        } else {
+            format!("{field}.{}", e.field)
+        },
+        message: e.message,
+    }));
+}
+
+/// Checks that a field isn't empty or only whitespace.
+pub fn require(errors: &mut Vec<FieldError>, field: &str, value: &str) {
+    if value.trim().is_empty() {
+        errors.push(FieldError::new(field, "must be given"));
+    }
+}
+
+/// Checks that a field is a section ID, which is made of digits (e.g., `079911`).
+pub fn check_section_id(errors: &mut Vec<FieldError>, field: &str, value: &str) {
+    let value = value.trim();
+    if value.is_empty() {
+        errors.push(FieldError::new(field, "must be given"));
+    } else if !value.chars().all(|c| c.is_ascii_digit()) {
+        errors.push(FieldError::new(
+            field,
+            "must be a section ID, made of digits",
+        ));
+    }
+}
+
+/// Checks that a field, if it's given, is within a range.
+pub fn check_range<T>(
+    errors: &mut Vec<FieldError>,
+    field: &str,
+    value: Option<T>,
+    range: RangeInclusive<T>,
+) where
+    T: PartialOrd + Display,
+{
+    if let Some(value) = value {
+        if !range.contains(&value) {
+            errors.push(FieldError::new(
+                field,
+                format!("must be between {} and {}", range.start(), range.end()),
+            ));
+        }
+    }
+}
+
+/// Checks the grading option and unit count of a section being added.
+fn check_grading(errors: &mut Vec<FieldError>, grading_option: Option<&str>, units: Option<i64>) {
+    if let Some(option) = grading_option {
+        if !GRADING_OPTIONS.contains(&option.trim().to_uppercase().as_str()) {
+            errors.push(FieldError::new("gradingOption", "must be L, P, or S"));
+        }
+    }
+
+    check_range(errors, "unitCount", units, 1..=u8::MAX as i64);
+}
+
+impl Validate for BodySectionId {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        check_section_id(&mut errors, "sectionId", &self.section_id);
+        errors
+    }
+}
+
+impl Validate for BodySectionScheduleNameId {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        check_section_id(&mut errors, "sectionId", &self.section_id);
+        errors
+    }
+}
+
+impl Validate for BodyScheduleNameChange {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        require(&mut errors, "oldName", &self.old_name);
+        require(&mut errors, "newName", &self.new_name);
+        errors
+    }
+}
+
+impl Validate for BodyAddInfo {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        check_section_id(&mut errors, "sectionId", &self.section_id);
+        check_grading(&mut errors, self.grading_option.as_deref(), self.unit_count);
+        errors
+    }
+}
+
+impl Validate for BodySwapSections {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        check_section_id(&mut errors, "dropSectionId", &self.drop_section_id);
+        extend_nested(&mut errors, "add", self.add.validate());
+        errors
+    }
+}
+
+impl Validate for BodyPlanAdd {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = vec![];
+        require(&mut errors, "subjectCode", &self.subject_code);
+        require(&mut errors, "courseCode", &self.course_code);
+        check_section_id(&mut errors, "sectionId", &self.section_id);
+        require(&mut errors, "sectionCode", &self.section_code);
+        check_grading(
+            &mut errors,
+            self.grading_option.as_deref(),
+            Some(self.unit_count),
+        );
+        errors
+    }
+}
+
+// Checked by the handlers, which need more than the body to do so
+impl Validate for BodySearchType {}
+impl Validate for BodyCustomEvent {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::types::BodyBulkAdd;
+
+    #[test]
+    fn test_validate_bodies() {
+        let swap: BodySwapSections = serde_json::from_value(json!({
+            "dropSectionId": "079911",
+            "add": { "sectionId": "A01", "gradingOption": "X", "unitCount": 4 },
+        }))
+        .unwrap();
+        assert_eq!(
+            vec![
+                FieldError::new("add.sectionId", "must be a section ID, made of digits"),
+                FieldError::new("add.gradingOption", "must be L, P, or S"),
+            ],
+            swap.validate()
+        );
+
+        let add: BodyAddInfo = serde_json::from_value(json!({
+            "sectionId": " 079911 ",
+            "gradingOption": "p",
+            "unitCount": 2,
+        }))
+        .unwrap();
+        assert!(add.validate().is_empty());
+
+        // Bodies that don't have the expected shape are reported by the field's path
+        let err = serde_path_to_error::deserialize::<_, BodyBulkAdd>(json!({
+            "sections": [{ "sectionId": "079911" }, { "sectionId": 79912 }],
+        }))
+        .err()
+        .unwrap();
+        assert_eq!("sections[1].sectionId", field_path(&err.path().to_string()));
+        assert_eq!("", field_path("."));
+    }
+}
@@ -0,0 +1,281 @@
+//! A registry of students' WebReg sessions.
+//!
+//! Rather than sending their WebReg cookies with every request, a student can register
+//! their cookies once and get back a session token. Requests to the cookie endpoints
+//! that carry the token are then served using that student's cookies, subject to a
+//! per-session rate limit. With the `auth` feature, each session belongs to the API key
+//! that registered it, and can't be used or looked up with any other key.
+
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use dashmap::mapref::one::RefMut;
+use dashmap::DashMap;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+pub use webreg_types::sessions::{SessionInfo, SESSION_TOKEN_HEADER};
+
+/// The default number of requests a single session may make per window.
+pub const DEFAULT_SESSION_RATE_LIMIT: usize = 60;
+/// The length of a rate limit window.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+/// The length of a generated session token.
+const TOKEN_LENGTH: usize = 40;
+
+/// A single student's session.
+struct UserSession {
+    /// The prefix of the API key that registered the session, if keys are used.
+    owner: Option<String>,
+    /// The student's WebReg cookies.
+    cookies: String,
+    /// When the session was created, in RFC 3339 format.
+    created_at: String,
+    /// When the session was last used, in RFC 3339 format.
+    last_used: Option<String>,
+    /// When the current rate limit window started.
+    window_start: Instant,
+    /// The number of requests made in the current rate limit window.
+    window_requests: usize,
+}
+
+/// The reason a session could not be used.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SessionError {
+    /// No session exists for the given token, or it belongs to another API key.
+    NotFound,
+    /// The session has made too many requests; the caller should retry after the
+    /// given duration.
+    RateLimited(Duration),
+}
+
+/// Holds every registered session, keyed by session token.
+pub struct SessionRegistry {
+    sessions: DashMap<String, UserSession>,
+    rate_limit: usize,
+}
+
+impl SessionRegistry {
+    /// Creates a new, empty registry.
+    ///
+    /// # Parameters
+    /// - `rate_limit`: The number of requests each session may make per minute.
+    pub fn new(rate_limit: usize) -> Self {
+        Self {
+            sessions: DashMap::new(),
+            rate_limit,
+        }
+    }
+
+    /// Registers a session for the given cookies.
+    ///
+    /// # Parameters
+    /// - `cookies`: The student's WebReg cookies.
+    /// - `owner`: The prefix of the caller's API key, if keys are used.
+    ///
+    /// # Returns
+    /// The token for the new session.
+    pub fn register(&self, cookies: &str, owner: Option<&str>) -> String {
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+
+        self.sessions.insert(
+            token.clone(),
+            UserSession {
+                owner: owner.map(str::to_owned),
+                cookies: cookies.to_owned(),
+                created_at: Utc::now().to_rfc3339(),
+                last_used: None,
+                window_start: Instant::now(),
+                window_requests: 0,
+            },
+        );
+
+        token
+    }
+
+    /// Replaces the cookies for an existing session, e.g., after the student logs in again.
+    ///
+    /// # Parameters
+    /// - `token`: The session token.
+    /// - `owner`: The prefix of the caller's API key, if keys are used.
+    /// - `cookies`: The new cookies.
+    ///
+    /// # Returns
+    /// `true` if the session exists and belongs to the caller.
+    pub fn update_cookies(&self, token: &str, owner: Option<&str>, cookies: &str) -> bool {
+        match self.owned_mut(token, owner) {
+            Some(mut session) => {
+                session.cookies = cookies.to_owned();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes a session.
+    ///
+    /// # Parameters
+    /// - `token`: The session token.
+    /// - `owner`: The prefix of the caller's API key, if keys are used.
+    ///
+    /// # Returns
+    /// `true` if the session existed and belonged to the caller.
+    pub fn remove(&self, token: &str, owner: Option<&str>) -> bool {
+        self.sessions
+            .remove_if(token, |_, s| s.owner.as_deref() == owner)
+            .is_some()
+    }
+
+    /// Whether a session exists, whoever it belongs to.
+    ///
+    /// # Parameters
+    /// - `token`: The session token.
+    pub fn contains(&self, token: &str) -> bool {
+        self.sessions.contains_key(token)
+    }
+
+    /// Gets the cookies for a session, counting this as one request against its rate limit.
+    ///
+    /// # Parameters
+    /// - `token`: The session token.
+    /// - `owner`: The prefix of the caller's API key, if keys are used.
+    ///
+    /// # Returns
+    /// The session's cookies, or the reason they can't be used.
+    pub fn acquire(&self, token: &str, owner: Option<&str>) -> Result<String, SessionError> {
+        let Some(session) = self.owned_mut(token, owner) else {
+            return Err(SessionError::NotFound);
+        };
+        self.use_session(session)
+    }
+
+    /// Gets the cookies for a session on behalf of work that its owner queued (e.g., an
+    /// enrollment job), counting this as one request against its rate limit. The owner was
+    /// checked when the work was queued, so it isn't checked again.
+    ///
+    /// # Parameters
+    /// - `token`: The session token.
+    ///
+    /// # Returns
+    /// The session's cookies, or the reason they can't be used.
+    pub fn acquire_queued(&self, token: &str) -> Result<String, SessionError> {
+        let Some(session) = self.sessions.get_mut(token) else {
+            return Err(SessionError::NotFound);
+        };
+        self.use_session(session)
+    }
+
+    /// Gets a session, if it belongs to the given API key.
+    fn owned_mut(
+        &self,
+        token: &str,
+        owner: Option<&str>,
+    ) -> Option<RefMut<'_, String, UserSession>> {
+        self.sessions
+            .get_mut(token)
+            .filter(|s| s.owner.as_deref() == owner)
+    }
+
+    /// Counts a request against a session's rate limit.
+    fn use_session(
+        &self,
+        mut session: RefMut<'_, String, UserSession>,
+    ) -> Result<String, SessionError> {
+        let elapsed = session.window_start.elapsed();
+        if elapsed >= RATE_LIMIT_WINDOW {
+            session.window_start = Instant::now();
+            session.window_requests = 0;
+        } else if session.window_requests >= self.rate_limit {
+            return Err(SessionError::RateLimited(RATE_LIMIT_WINDOW - elapsed));
+        }
+
+        session.window_requests += 1;
+        session.last_used = Some(Utc::now().to_rfc3339());
+        Ok(session.cookies.clone())
+    }
+
+    /// Gets the cookies of every session, without counting against any rate limits. This
+    /// is meant for server-side jobs (e.g., reports) rather than for serving requests.
+    ///
+    /// # Returns
+    /// The cookies of each session.
+    pub fn all_cookies(&self) -> Vec<String> {
+        self.sessions.iter().map(|s| s.cookies.clone()).collect()
+    }
+
+    /// Gets information about a session.
+    ///
+    /// # Parameters
+    /// - `token`: The session token.
+    /// - `owner`: The prefix of the caller's API key, if keys are used.
+    ///
+    /// # Returns
+    /// The session information, if the session exists and belongs to the caller.
+    pub fn info(&self, token: &str, owner: Option<&str>) -> Option<SessionInfo> {
+        self.sessions
+            .get(token)
+            .filter(|s| s.owner.as_deref() == owner)
+            .map(|s| SessionInfo {
+                created_at: s.created_at.clone(),
+                last_used: s.last_used.clone(),
+                window_requests: s.window_requests,
+                rate_limit: self.rate_limit,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions_are_isolated() {
+        let registry = SessionRegistry::new(10);
+        let a = registry.register("a=1", None);
+        let b = registry.register("b=2", None);
+        assert_ne!(a, b);
+        assert_eq!(Ok("a=1".to_string()), registry.acquire(&a, None));
+        assert_eq!(Ok("b=2".to_string()), registry.acquire(&b, None));
+        assert_eq!(Err(SessionError::NotFound), registry.acquire("nope", None));
+        assert!(registry.remove(&a, None));
+        assert_eq!(Err(SessionError::NotFound), registry.acquire(&a, None));
+    }
+
+    #[test]
+    fn test_sessions_belong_to_their_key() {
+        let registry = SessionRegistry::new(10);
+        let token = registry.register("a=1", Some("alice"));
+
+        // Another key (or no key) can't use, see, change, or remove the session
+        for other in [Some("bob"), None] {
+            assert_eq!(Err(SessionError::NotFound), registry.acquire(&token, other));
+            assert!(registry.info(&token, other).is_none());
+            assert!(!registry.update_cookies(&token, other, "b=2"));
+            assert!(!registry.remove(&token, other));
+        }
+
+        assert_eq!(
+            Ok("a=1".to_string()),
+            registry.acquire(&token, Some("alice"))
+        );
+        assert!(registry.info(&token, Some("alice")).is_some());
+        assert_eq!(Ok("a=1".to_string()), registry.acquire_queued(&token));
+        assert!(registry.remove(&token, Some("alice")));
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let registry = SessionRegistry::new(2);
+        let token = registry.register("a=1", None);
+        assert!(registry.acquire(&token, None).is_ok());
+        assert!(registry.acquire(&token, None).is_ok());
+        assert!(matches!(
+            registry.acquire(&token, None),
+            Err(SessionError::RateLimited(_))
+        ));
+    }
+}
@@ -0,0 +1,136 @@
+//! Signed, expiring tokens for sharing schedule snapshots.
+//!
+//! A student can share a read-only snapshot of one of their schedules without sharing their
+//! cookies. The snapshot itself is stored in the database; the token given out identifies
+//! the snapshot, says when it expires, and is signed so that it can't be forged or have its
+//! expiry extended.
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use ring::hmac;
+
+use crate::degree_audit::cache::hex;
+
+/// The number of bytes of the signature kept in a token.
+const SIGNATURE_LENGTH: usize = 16;
+
+/// The reason a share token could not be used.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ShareTokenError {
+    /// The token is malformed or its signature doesn't match.
+    Invalid,
+    /// The token was valid, but has expired.
+    Expired,
+}
+
+/// Creates and verifies share tokens.
+pub struct ShareSigner {
+    /// The key used to sign tokens.
+    key: hmac::Key,
+}
+
+impl ShareSigner {
+    /// Creates a new signer. If no secret is given, a random one is generated, in which
+    /// case tokens stop working when the application restarts.
+    ///
+    /// # Parameters
+    /// - `secret`: The secret used to sign tokens, if any.
+    ///
+    /// # Returns
+    /// The signer.
+    pub fn new(secret: Option<&str>) -> Self {
+        let secret = match secret {
+            Some(s) if !s.is_empty() => s.as_bytes().to_vec(),
+            _ => rand::thread_rng().gen::<[u8; 32]>().to_vec(),
+        };
+
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, &secret),
+        }
+    }
+
+    /// Creates a token for a shared snapshot.
+    ///
+    /// # Parameters
+    /// - `share_id`: The ID of the snapshot.
+    /// - `expires_at`: When the token should stop working.
+    ///
+    /// # Returns
+    /// The token.
+    pub fn sign(&self, share_id: i64, expires_at: DateTime<Utc>) -> String {
+        let payload = format!("{share_id}-{}", expires_at.timestamp());
+        format!("{payload}-{}", self.signature(&payload))
+    }
+
+    /// Checks a token, returning the ID of the snapshot that it's for.
+    ///
+    /// # Parameters
+    /// - `token`: The token.
+    /// - `now`: The current time.
+    ///
+    /// # Returns
+    /// The snapshot ID, or the reason the token can't be used.
+    pub fn verify(&self, token: &str, now: DateTime<Utc>) -> Result<i64, ShareTokenError> {
+        let (payload, signature) = token.rsplit_once('-').ok_or(ShareTokenError::Invalid)?;
+        if !constant_time_eq(signature.as_bytes(), self.signature(payload).as_bytes()) {
+            return Err(ShareTokenError::Invalid);
+        }
+
+        let (share_id, expires_at) = payload.split_once('-').ok_or(ShareTokenError::Invalid)?;
+        let share_id: i64 = share_id.parse().map_err(|_| ShareTokenError::Invalid)?;
+        let expires_at: i64 = expires_at.parse().map_err(|_| ShareTokenError::Invalid)?;
+        if now.timestamp() >= expires_at {
+            return Err(ShareTokenError::Expired);
+        }
+
+        Ok(share_id)
+    }
+
+    /// Computes the (truncated) HMAC-SHA256 signature of a payload, as hex.
+    fn signature(&self, payload: &str) -> String {
+        let tag = hmac::sign(&self.key, payload.as_bytes());
+        hex::encode(&tag.as_ref()[..SIGNATURE_LENGTH])
+    }
+}
+
+/// Compares two byte strings without stopping at the first difference.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_token_round_trip() {
+        let signer = ShareSigner::new(Some("secret"));
+        let now = Utc::now();
+        let token = signer.sign(42, now + Duration::hours(1));
+
+        assert_eq!(Ok(42), signer.verify(&token, now));
+        assert_eq!(
+            Err(ShareTokenError::Expired),
+            signer.verify(&token, now + Duration::hours(2))
+        );
+    }
+
+    #[test]
+    fn test_tampered_token() {
+        let signer = ShareSigner::new(Some("secret"));
+        let now = Utc::now();
+        let token = signer.sign(42, now + Duration::hours(1));
+
+        // Changing the ID or extending the expiry invalidates the signature.
+        let (_, rest) = token.split_once('-').unwrap();
+        assert_eq!(
+            Err(ShareTokenError::Invalid),
+            signer.verify(&format!("43-{rest}"), now)
+        );
+
+        let other = ShareSigner::new(Some("other"));
+        assert_eq!(Err(ShareTokenError::Invalid), other.verify(&token, now));
+        assert_eq!(Err(ShareTokenError::Invalid), signer.verify("garbage", now));
+    }
+}
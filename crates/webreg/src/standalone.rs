@@ -0,0 +1,138 @@
+//! Running the scraper from nothing but its binary (e.g., in a Docker container).
+//!
+//! With `--standalone`, the scraper keeps everything it needs in one data directory: its
+//! configuration file, which is generated with sensible defaults the first time it runs,
+//! and its databases. The generated configuration scrapes the current term for every
+//! department and serves the API on port 8080 of every interface. There's no cookie server
+//! by default, so the tracker logs in with the session cookies given in the
+//! `WEBREG_SESSION_COOKIES` environment variable (or the configuration file), and the
+//! degree audit endpoints aren't served. The read-only endpoints serve the schedule data
+//! as soon as the first scrape has finished.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chrono::{Datelike, Local, NaiveDate};
+use serde_json::{json, Value};
+use thiserror::Error;
+use tracing::info;
+
+use crate::types::ConfigScraper;
+
+/// The data directory, if it's given neither as an argument nor in [`DATA_DIR_ENV`].
+pub const DEFAULT_DATA_DIR: &str = "data";
+/// The environment variable that the data directory can be given in.
+pub const DATA_DIR_ENV: &str = "WEBREG_DATA_DIR";
+/// The environment variable that the tracker's session cookies can be given in.
+pub const SESSION_COOKIES_ENV: &str = "WEBREG_SESSION_COOKIES";
+/// The name of the configuration file in the data directory.
+pub const CONFIG_FILE_NAME: &str = "config.json";
+/// The port that the generated configuration serves the API on.
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// Why the standalone configuration couldn't be loaded.
+#[derive(Debug, Error)]
+pub enum StandaloneError {
+    #[error("couldn't access the data directory: {0}")]
+    Io(#[from] io::Error),
+    #[error("bad configuration file: {0}")]
+    Config(#[from] serde_json::Error),
+}
+
+/// Gets the term that's in session on a date (e.g., `FA24` in October 2024). Summer
+/// Session I is taken to run through July, and Summer Session II through August.
+pub fn current_term(date: NaiveDate) -> String {
+    let season = match date.month() {
+        1..=3 => "WI",
+        4..=6 => "SP",
+        7 => "S1",
+        8 => "S2",
+        _ => "FA",
+    };
+    format!("{season}{:02}", date.year() % 100)
+}
+
+/// Builds the configuration that's generated the first time the scraper runs standalone.
+///
+/// # Parameters
+/// - `term`: The term to scrape.
+///
+/// # Returns
+/// The configuration, as it's written to the configuration file.
+pub fn default_config(term: &str) -> Value {
+    json!({
+        "configName": "Standalone",
+        "apiBaseEndpoint": {
+            "address": "0.0.0.0",
+            "port": DEFAULT_PORT
+        },
+        "verbose": false,
+        "wrapperData": [
+            {
+                "term": term,
+                "cooldown": 3,
+                "searchQuery": [
+                    {
+                        "levels": ["l", "u", "g"],
+                        "departments": []
+                    }
+                ],
+                "saveDataToFile": false
+            }
+        ]
+    })
+}
+
+/// Loads the configuration from the data directory, creating the directory and generating
+/// the configuration file first if they don't exist.
+///
+/// # Parameters
+/// - `data_dir`: The data directory.
+///
+/// # Returns
+/// The configuration, with its data directory set, or why it couldn't be loaded.
+pub fn load_config(data_dir: &Path) -> Result<ConfigScraper, StandaloneError> {
+    fs::create_dir_all(data_dir)?;
+
+    let config_path = data_dir.join(CONFIG_FILE_NAME);
+    if !config_path.exists() {
+        let config = default_config(&current_term(Local::now().date_naive()));
+        fs::write(&config_path, serde_json::to_string_pretty(&config)?)?;
+        info!(
+            "Generated a default configuration file at {}.",
+            config_path.display()
+        );
+    }
+
+    let mut config: ConfigScraper = serde_json::from_str(&fs::read_to_string(&config_path)?)?;
+    if config.data_dir.is_none() {
+        config.data_dir = Some(data_dir.to_string_lossy().into_owned());
+    }
+
+    if config.session_cookies.is_none() {
+        config.session_cookies = std::env::var(SESSION_COOKIES_ENV)
+            .ok()
+            .filter(|cookies| !cookies.trim().is_empty());
+    }
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!("WI25", current_term(date(2025, 2, 1)));
+        assert_eq!("S125", current_term(date(2025, 7, 15)));
+        assert_eq!("FA05", current_term(date(2005, 10, 1)));
+
+        let config: ConfigScraper = serde_json::from_value(default_config("FA24")).unwrap();
+        assert!(config.cookie_server.is_none());
+        assert_eq!("FA24", config.wrapper_data[0].term);
+        assert_eq!(DEFAULT_PORT as i64, config.api_base_endpoint.port);
+    }
+}
@@ -0,0 +1,379 @@
+//! The history of whether the services that the API depends on were up.
+//!
+//! When a request fails, it's often because WebReg, DARS, or the cookie server
+//! (`webregautoin`) is down rather than because of the API itself. To make that visible,
+//! each service's state is checked periodically by the scheduler (see
+//! [`crate::scheduler`]), and the periods during which it was up or down are stored. A
+//! period only grows while the service stays in the same state, so the history stays small.
+//!
+//! - WebReg is up while the tracker's session cookies work (see
+//!   [`crate::cookie_freshness`]).
+//! - DARS is up while its circuit breaker is closed.
+//! - The cookie server is up unless enough health checks in a row have failed (see
+//!   [`crate::cookie_health`]).
+//!
+//! `GET /status/history` returns each service's uptime by day, and its outages, and
+//! `GET /status` shows the same as a page.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Days, NaiveDate, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::cookie_health::CookieServerStatus;
+use crate::db::StatusInterval;
+use crate::types::WrapperState;
+
+/// How often the services are checked, if not configured.
+pub const DEFAULT_STATUS_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// The longest time between two checks of a service for them to count as one period. A
+/// longer gap (e.g., while the API itself was down) is left out of the uptime.
+pub const MAX_CHECK_GAP: Duration = Duration::from_secs(10 * 60);
+/// The most days that the history can be requested for.
+pub const MAX_HISTORY_DAYS: u32 = 90;
+
+/// A service that the API depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dependency {
+    Webreg,
+    Dars,
+    CookieServer,
+}
+
+impl Dependency {
+    /// Every dependency, in the order that they're shown.
+    pub const ALL: [Dependency; 3] = [Self::Webreg, Self::Dars, Self::CookieServer];
+
+    /// The name that the dependency's history is stored under.
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Webreg => "webreg",
+            Self::Dars => "dars",
+            Self::CookieServer => "cookie_server",
+        }
+    }
+
+    /// The dependency's name, for the status page.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Webreg => "WebReg",
+            Self::Dars => "DARS (degree audits)",
+            Self::CookieServer => "Cookie server",
+        }
+    }
+}
+
+/// How much of a day a service was up.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DayUptime {
+    /// The day, in UTC, in `YYYY-MM-DD` format.
+    pub date: String,
+    /// The percentage of the time that the service was checked during the day that it was
+    /// up, or nothing if it wasn't checked at all.
+    pub uptime_percent: Option<f64>,
+    /// How long the service was seen to be down during the day, in seconds.
+    pub down_secs: i64,
+}
+
+/// A period during which a service was down.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Outage {
+    /// When the service was first seen to be down, in RFC 3339 format.
+    pub started_at: String,
+    /// When the service was last seen to be down, in RFC 3339 format, or nothing if it still
+    /// is.
+    pub ended_at: Option<String>,
+    pub duration_secs: i64,
+}
+
+/// A service's history over the requested days.
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyHistory {
+    pub dependency: Dependency,
+    pub name: &'static str,
+    /// Whether the service is up, or nothing if it hasn't been checked recently.
+    pub up: Option<bool>,
+    /// The percentage of the time that the service was checked that it was up, or nothing if
+    /// it wasn't checked at all.
+    pub uptime_percent: Option<f64>,
+    /// The service's uptime on each day, oldest first.
+    pub days: Vec<DayUptime>,
+    /// The service's outages, most recent first.
+    pub outages: Vec<Outage>,
+}
+
+/// Checks whether each service is up. Services that haven't been checked yet are left out.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+pub fn current_statuses(state: &WrapperState) -> Vec<(Dependency, bool)> {
+    let mut statuses = vec![];
+    if let Some(valid) = state.cookie_freshness.report().valid {
+        statuses.push((Dependency::Webreg, valid));
+    }
+
+    statuses.push((
+        Dependency::Dars,
+        !state.degree_audit_cache_state.circuit_breaker.is_open(),
+    ));
+
+    match state.cookie_server_health.report().status {
+        CookieServerStatus::Unknown => {}
+        status => statuses.push((Dependency::CookieServer, status == CookieServerStatus::Up)),
+    }
+
+    statuses
+}
+
+/// Records whether each service is up. This is run periodically by the scheduler.
+///
+/// # Parameters
+/// - `state`: The wrapper state.
+///
+/// # Returns
+/// The services whose state changed, or why the states couldn't be recorded.
+pub fn record_dependency_statuses(state: &Arc<WrapperState>) -> Result<String, String> {
+    let now = Utc::now().timestamp_millis();
+    let max_gap = MAX_CHECK_GAP.as_millis() as i64;
+    let mut changed = vec![];
+    for (dependency, up) in current_statuses(state) {
+        let started = state
+            .schedule_db
+            .record_dependency_status(dependency.key(), up, now, max_gap)
+            .map_err(|e| format!("Failed to record the status of {}: {e}", dependency.key()))?;
+        if started {
+            changed.push(format!(
+                "{} is {}",
+                dependency.key(),
+                if up { "up" } else { "down" }
+            ));
+        }
+    }
+
+    if changed.is_empty() {
+        Ok("No changes.".to_owned())
+    } else {
+        Ok(format!("{}.", changed.join(", ")))
+    }
+}
+
+/// Summarizes a service's history over the last few days.
+///
+/// # Parameters
+/// - `dependency`: The service.
+/// - `intervals`: The periods during which the service was checked, which may include
+///   other services' periods.
+/// - `days`: The number of days, including today, to summarize.
+/// - `now`: The current time.
+pub fn summarize(
+    dependency: Dependency,
+    intervals: &[StatusInterval],
+    days: u32,
+    now: DateTime<Utc>,
+) -> DependencyHistory {
+    let intervals: Vec<_> = intervals
+        .iter()
+        .filter(|i| i.dependency == dependency.key())
+        .collect();
+    let now_ms = now.timestamp_millis();
+    let first_day = now.date_naive() - Days::new(u64::from(days.max(1) - 1));
+    let window_start = day_start(first_day);
+
+    let day_uptimes = first_day
+        .iter_days()
+        .take(days.max(1) as usize)
+        .map(|date| {
+            let end = day_start(date + Days::new(1)).min(now_ms);
+            let (up, observed) = coverage(&intervals, day_start(date), end);
+            DayUptime {
+                date: date.to_string(),
+                uptime_percent: percent(up, observed),
+                down_secs: (observed - up) / 1000,
+            }
+        })
+        .collect();
+
+    let (up, observed) = coverage(&intervals, window_start, now_ms);
+    let latest = intervals.iter().max_by_key(|i| i.last_checked_at);
+    let max_gap = MAX_CHECK_GAP.as_millis() as i64;
+    let outages = intervals
+        .iter()
+        .rev()
+        .filter(|i| !i.up && i.last_checked_at >= window_start)
+        .map(|i| {
+            let ongoing = latest == Some(i) && now_ms - i.last_checked_at <= max_gap;
+            Outage {
+                started_at: rfc3339(i.started_at),
+                ended_at: (!ongoing).then(|| rfc3339(i.last_checked_at)),
+                duration_secs: ((if ongoing { now_ms } else { i.last_checked_at }) - i.started_at)
+                    / 1000,
+            }
+        })
+        .collect();
+
+    DependencyHistory {
+        dependency,
+        name: dependency.label(),
+        up: latest
+            .filter(|i| now_ms - i.last_checked_at <= max_gap)
+            .map(|i| i.up),
+        uptime_percent: percent(up, observed),
+        days: day_uptimes,
+        outages,
+    }
+}
+
+/// Adds up how long, between `start` and `end`, a service was checked and how much of that
+/// time it was up, in milliseconds.
+fn coverage(intervals: &[&StatusInterval], start: i64, end: i64) -> (i64, i64) {
+    intervals.iter().fold((0, 0), |(up, observed), i| {
+        let overlap = (i.last_checked_at.min(end) - i.started_at.max(start)).max(0);
+        (up + if i.up { overlap } else { 0 }, observed + overlap)
+    })
+}
+
+/// Gets `part` as a percentage of `whole`, to two decimal places.
+fn percent(part: i64, whole: i64) -> Option<f64> {
+    (whole > 0).then(|| (part as f64 / whole as f64 * 10_000.0).round() / 100.0)
+}
+
+/// Gets the start of a day, in milliseconds since the epoch.
+fn day_start(date: NaiveDate) -> i64 {
+    date.and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc()
+        .timestamp_millis()
+}
+
+/// Formats milliseconds since the epoch in RFC 3339 format.
+fn rfc3339(ms: i64) -> String {
+    Utc.timestamp_millis_opt(ms)
+        .single()
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_default()
+}
+
+/// Renders the status page.
+///
+/// # Parameters
+/// - `histories`: Each service's history.
+/// - `days`: The number of days that the histories cover.
+pub fn render_status_page(histories: &[DependencyHistory], days: u32) -> String {
+    let mut rows = String::new();
+    for history in histories {
+        let (status_class, status) = match history.up {
+            Some(true) => ("up", "Operational"),
+            Some(false) => ("down", "Down"),
+            None => ("unknown", "Unknown"),
+        };
+        let uptime = history
+            .uptime_percent
+            .map(|p| format!("{p}% uptime"))
+            .unwrap_or_else(|| "No data".to_owned());
+
+        let bars: String = history
+            .days
+            .iter()
+            .map(|day| {
+                let (class, label) = match day.uptime_percent {
+                    None => ("unknown", "no data".to_owned()),
+                    Some(p) if p >= 99.9 => ("up", format!("{p}% up")),
+                    Some(p) if p >= 95.0 => ("degraded", format!("{p}% up")),
+                    Some(p) => ("down", format!("{p}% up")),
+                };
+                format!(
+                    r#"<span class="bar {class}" title="{}: {label}"></span>"#,
+                    day.date
+                )
+            })
+            .collect();
+
+        rows.push_str(&format!(
+            r#"<section><h2>{} <span class="status {status_class}">{status}</span></h2><div class="bars">{bars}</div><p>{uptime} over the last {days} days</p></section>"#,
+            history.name
+        ));
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>WebReg API Status</title>
+<style>
+body {{ font-family: sans-serif; max-width: 48rem; margin: 2rem auto; padding: 0 1rem; color: #222; }}
+h2 {{ font-size: 1.1rem; display: flex; justify-content: space-between; }}
+.bars {{ display: flex; gap: 2px; }}
+.bar {{ flex: 1; height: 2rem; border-radius: 2px; }}
+.bar.up {{ background: #3ba55c; }}
+.bar.degraded {{ background: #f0a500; }}
+.bar.down {{ background: #d9534f; }}
+.bar.unknown {{ background: #ccc; }}
+.status.up {{ color: #3ba55c; }}
+.status.down {{ color: #d9534f; }}
+.status.unknown {{ color: #888; }}
+p {{ color: #666; font-size: 0.9rem; }}
+</style>
+</head>
+<body>
+<h1>WebReg API Status</h1>
+<p>Whether the UCSD services that the API depends on were up, by day (UTC). If one of them is down, requests that need it will fail until it's back.</p>
+{rows}
+</body>
+</html>
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 60 * 60 * 1000;
+
+    fn interval(dependency: Dependency, up: bool, start: i64, end: i64) -> StatusInterval {
+        StatusInterval {
+            dependency: dependency.key().to_owned(),
+            up,
+            started_at: start,
+            last_checked_at: end,
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 2, 12, 0, 0).unwrap();
+        let today = day_start(now.date_naive());
+        let intervals = [
+            // Yesterday: up for 18 hours, down for 6
+            interval(
+                Dependency::Webreg,
+                true,
+                today - 24 * HOUR,
+                today - 6 * HOUR,
+            ),
+            interval(Dependency::Webreg, false, today - 6 * HOUR, today),
+            // Today: not checked for the first hour, then up until now
+            interval(Dependency::Webreg, true, today + HOUR, today + 12 * HOUR),
+            interval(Dependency::Dars, false, today, today + 12 * HOUR),
+        ];
+
+        let history = summarize(Dependency::Webreg, &intervals, 3, now);
+        assert_eq!(Some(true), history.up);
+        assert_eq!(3, history.days.len());
+        assert_eq!(None, history.days[0].uptime_percent);
+        assert_eq!(Some(75.0), history.days[1].uptime_percent);
+        assert_eq!(6 * 60 * 60, history.days[1].down_secs);
+        assert_eq!(Some(100.0), history.days[2].uptime_percent);
+        assert_eq!(Some(82.86), history.uptime_percent);
+        assert_eq!(1, history.outages.len());
+        assert!(history.outages[0].ended_at.is_some());
+
+        let dars = summarize(Dependency::Dars, &intervals, 3, now);
+        assert_eq!(Some(false), dars.up);
+        assert_eq!(None, dars.outages[0].ended_at);
+        assert_eq!(12 * 60 * 60, dars.outages[0].duration_secs);
+    }
+}
@@ -0,0 +1,114 @@
+//! Delta sync, which lets a client (e.g., a mobile app) refresh everything it shows in one
+//! small request rather than polling each endpoint.
+//!
+//! Every entity that clients sync (a term's schedule, an enrollment job, a section's seats,
+//! or the degree audit summary) has a row in the schedule database holding its place in a
+//! single change sequence. Each time an entity changes, it's moved to the end of the
+//! sequence, so a client that remembers the last sequence number it saw (its cursor) only
+//! needs the entities past it. Enrollment jobs and enrollment changes are recorded by
+//! triggers in the schema; the tracker and the degree audit cache record the rest.
+
+use serde::Serialize;
+use tracing::warn;
+
+use crate::db::ScheduleDbManager;
+
+/// The most changes returned by one sync request.
+pub const MAX_SYNC_CHANGES: usize = 500;
+/// The key of the degree audit summary, of which there's only one.
+pub const AUDIT_SUMMARY_KEY: &str = "summary";
+
+/// The kind of entity that changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// A term's enrolled schedule, keyed by the term.
+    Schedule,
+    /// An enrollment job, keyed by its ID.
+    Watch,
+    /// A section's seats, keyed by `TERM:SECTION_ID`.
+    Seats,
+    /// The degree audit summary, keyed by [`AUDIT_SUMMARY_KEY`].
+    Audit,
+}
+
+impl ChangeKind {
+    /// The name that the kind is stored under.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Schedule => "schedule",
+            ChangeKind::Watch => "watch",
+            ChangeKind::Seats => "seats",
+            ChangeKind::Audit => "audit",
+        }
+    }
+
+    /// Parses a stored kind.
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "schedule" => Some(ChangeKind::Schedule),
+            "watch" => Some(ChangeKind::Watch),
+            "seats" => Some(ChangeKind::Seats),
+            "audit" => Some(ChangeKind::Audit),
+            _ => None,
+        }
+    }
+}
+
+/// The key of a section's seats.
+pub fn seats_key(term: &str, section_id: &str) -> String {
+    format!("{term}:{section_id}")
+}
+
+/// Records that entities changed. Failures are only logged, since a missed change only
+/// means that clients see it later.
+///
+/// # Parameters
+/// - `db`: The schedule database.
+/// - `kind`: The kind of the entities.
+/// - `keys`: The keys of the entities.
+pub fn record_changes(db: &ScheduleDbManager, kind: ChangeKind, keys: &[String]) {
+    if let Err(e) = db.record_changes(kind.as_str(), keys) {
+        warn!("Failed to record {} change(s): {e}", kind.as_str());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{NewEnrollJob, WatchAlerts};
+
+    #[test]
+    fn test_change_sequence() {
+        let db = ScheduleDbManager::new(":memory:");
+        let job_id = db
+            .insert_enroll_job(&NewEnrollJob {
+                term: "FA24",
+                session_token: "token",
+                section_id: "123456",
+                subject_code: "CSE",
+                course_code: "100",
+                grading_option: None,
+                unit_count: None,
+                run_at: None,
+                expires_at: None,
+                notify_channels: &[],
+                alerts: WatchAlerts::default(),
+            })
+            .unwrap();
+        record_changes(&db, ChangeKind::Seats, &[seats_key("FA24", "123456")]);
+
+        let changes = db.get_changes_since(0, MAX_SYNC_CHANGES).unwrap();
+        let kinds: Vec<_> = changes.iter().map(|c| c.kind.as_str()).collect();
+        assert_eq!(vec!["watch", "seats"], kinds);
+        assert_eq!(job_id.to_string(), changes[0].entity_key);
+
+        // A change moves the entity past the cursor, leaving one row for it
+        let cursor = db.get_latest_change_seq().unwrap();
+        assert!(db.cancel_enroll_job("FA24", "token", job_id).unwrap());
+        let changes = db.get_changes_since(cursor, MAX_SYNC_CHANGES).unwrap();
+        assert_eq!(1, changes.len());
+        assert_eq!(Some(ChangeKind::Watch), ChangeKind::parse(&changes[0].kind));
+        assert_eq!(2, db.get_changes_since(0, MAX_SYNC_CHANGES).unwrap().len());
+    }
+}
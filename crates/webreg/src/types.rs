@@ -1,24 +1,68 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::net::IpAddr;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use dashmap::DashMap;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use webweg::wrapper::input_types::{CourseLevelFilter, SearchRequestBuilder};
 use webweg::wrapper::WebRegWrapper;
 
-use crate::degree_audit::{AuditCacheState, DegreeAuditClient};
+use crate::attribution::DataLicense;
+use crate::cookie_freshness::{
+    CookieFreshness, DEFAULT_MAX_COOKIE_AGE, DEFAULT_VALIDATION_INTERVAL,
+};
+use crate::cookie_health::CookieServerHealth;
+use crate::db::normalize_course_code;
+use crate::degree_audit::client::DegreeAuditConfig;
+use crate::degree_audit::{
+    AuditArchive, AuditCacheState, AuditOwner, AuditQuota, AuditTtlPolicy, DegreeAuditClient,
+    DegreeAuditError, FixtureMode, GradeScale, GradeScaleOverrides, PollStrategyKind,
+    DEFAULT_ARCHIVE_RETENTION_DAYS, DEFAULT_AUDIT_TTL, DEFAULT_DAILY_AUDIT_QUOTA,
+    DEFAULT_GRADE_POSTING_TTL,
+};
+use crate::drift::DriftTracker;
+use crate::hooks::EnrollmentBus;
+use crate::key_quota::KeyQuota;
+use crate::leader::{LeaderElection, DEFAULT_LEASE_DURATION};
+use crate::load_shed::{LoadShedder, DEFAULT_MAX_IN_FLIGHT, DEFAULT_MAX_QUEUE_DEPTH};
+use crate::notify::NotifyChannel;
+use crate::payload_store::PayloadArchive;
+use crate::rate_limit::{
+    RateLimits, DEFAULT_GENERAL_RATE_LIMIT, DEFAULT_IP_RATE_LIMIT, DEFAULT_MUTATION_RATE_LIMIT,
+};
+use crate::replication::{ReplicationState, StandbyTarget, DEFAULT_REPLICATION_INTERVAL};
+use crate::restrictions::RestrictionWindowRule;
+use crate::retry::{
+    RetryPolicy, DEFAULT_RETRY_ATTEMPTS, DEFAULT_RETRY_BASE_DELAY, DEFAULT_RETRY_MAX_DELAY,
+};
+use crate::schedule::{build_heatmap, DateRange, Heatmap, MeetingLoad, MeetingSlot, SubSession};
+use crate::scheduler::Scheduler;
+use crate::scraper::live::LiveFeed;
+use crate::scraper::throttle::AlertThrottle;
+use crate::semantic::{Embedder, SemanticIndex};
+use crate::server::DeprecationTracker;
+use crate::sessions::{SessionRegistry, DEFAULT_SESSION_RATE_LIMIT};
+use crate::sharing::ShareSigner;
+#[cfg(feature = "auth")]
+use crate::vault::CredentialVault;
+#[cfg(feature = "auth")]
+use crate::{api_keys::ApiScope, redaction::ResponseFilter};
 
 const MAX_RECENT_REQUESTS: usize = 2000;
 
 /// A structure that represents the current state of all wrappers.
 pub struct WrapperState {
-    /// A map containing all active scrapers, grouped by term.
+    /// A map containing all active scrapers, grouped by term. Each term has its own
+    /// state, so that several terms (e.g., fall and a summer session) can be scraped
+    /// and served at the same time.
     pub all_terms: WrapperMap,
     /// The stop flag; i.e., the flag that indicates whether the scraper should be stopped.
     pub stop_flag: AtomicBool,
-    /// Whether the scrapers are running at this moment.
-    pub is_running: AtomicBool,
     /// The client that can be used to make requests.
     pub client: Client,
     /// The wrapper that can be used to make requests to WebReg.
@@ -28,19 +72,99 @@ pub struct WrapperState {
     /// The address for which the endpoints specified in this application is made
     /// available for other applications to use.
     pub api_base_endpoint: AddressPortInfo,
-    /// The cookie server.
-    pub cookie_server: AddressPortInfo,
+    /// The address that the admin API is served on, if it's served separately from the
+    /// rest of the API.
+    pub admin_endpoint: Option<AddressPortInfo>,
+    /// The bearer token that the admin API requires, if any.
+    pub admin_token: Option<String>,
+    /// Whether the admin API may be served without a token, on its own listener.
+    pub allow_unauthenticated_admin: bool,
+    /// The cookie server, if there is one.
+    pub cookie_server: Option<AddressPortInfo>,
+    /// The session cookies that the tracker logs in with when there's no cookie server.
+    pub session_cookies: Option<String>,
+    /// The result of the most recent health checks against the cookie server.
+    pub cookie_server_health: CookieServerHealth,
+    /// How fresh the tracker's session cookies are.
+    pub cookie_freshness: CookieFreshness,
     /// Database manager for schedule/meeting data.
     pub schedule_db: crate::db::ScheduleDbManager,
     /// The authentication manager, to be used by the server.
     #[cfg(feature = "auth")]
     pub auth_manager: basicauth::AuthManager,
-    /// Requirements configuration for colleges and majors.
-    pub requirements_config: crate::degree_audit::config::RequirementsConfig,
+    /// Requirements configuration for colleges and majors, including edits made through
+    /// the admin API.
+    pub requirements_config: RwLock<crate::degree_audit::config::RequirementsConfig>,
     /// Degree audit client for fetching and caching audits.
     pub degree_audit_client: DegreeAuditClient,
     /// Shared cache state for degree audits.
     pub degree_audit_cache_state: Arc<AuditCacheState>,
+    /// Tracks differences between WebReg's responses and the schemas we expect.
+    pub drift_tracker: DriftTracker,
+    /// Usage counts for deprecated routes.
+    pub deprecation_tracker: DeprecationTracker,
+    /// Changes to tracked sections, published as the tracker sees them.
+    pub live_feed: LiveFeed,
+    /// How the seat alerts of each saved watch have been throttled, keyed by job ID.
+    pub watch_throttles: DashMap<i64, AlertThrottle>,
+    /// Students' WebReg sessions, keyed by session token.
+    pub sessions: SessionRegistry,
+    /// Signs and verifies schedule sharing links.
+    pub share_signer: ShareSigner,
+    /// Request counts and quotas per API key.
+    pub key_quota: KeyQuota,
+    /// The full-term scrapes that are running, keyed by term, with their IDs.
+    pub active_term_scrapes: DashMap<String, i64>,
+    /// Class density heatmaps, keyed by term. These are recomputed after each scrape.
+    pub heatmaps: DashMap<String, Arc<Heatmap>>,
+    /// Per-client rate limits for the API.
+    pub rate_limits: RateLimits,
+    /// Turns requests away when the server is overloaded.
+    pub load_shedder: LoadShedder,
+    /// How WebReg requests that fail for transient reasons are retried.
+    pub webreg_retry: RetryPolicy,
+    /// The data's provenance and terms of use, which responses are tagged with, if
+    /// configured.
+    pub data_license: Option<DataLicense>,
+    /// The dates that departments open their sections to non-majors on, as configured.
+    pub restriction_windows: Vec<RestrictionWindowRule>,
+    /// Whether this instance is the primary or a standby, and where it replicates to.
+    pub replication: ReplicationState,
+    /// Whether this instance runs the background work, when several share the database.
+    pub leader: LeaderElection,
+    /// The recurring background jobs, and when they run.
+    pub scheduler: Scheduler,
+    /// Verified enrollment changes, published for the post-enrollment hooks.
+    pub enrollment_bus: EnrollmentBus,
+    /// The hooks to fire after a student's enrollment changes.
+    pub enrollment_hooks: Vec<ConfigHook>,
+    /// The reminders to send before students' passes open, if any.
+    pub pass_time_reminders: Option<ConfigPassTimeReminders>,
+    /// The settings for channels that notifications are sent to (e.g., the SMTP relay).
+    pub notifications: ConfigNotifications,
+    /// The client that notifications are sent with, which doesn't follow redirects.
+    pub notify_client: Client,
+    /// The client that notifications are sent to students' own channels with, which also
+    /// only connects to public addresses.
+    pub student_notify_client: Client,
+    /// Where to fetch grade distributions from every day, if anywhere.
+    pub grade_distribution_url: Option<String>,
+    /// Whether to scrape the course catalog every week.
+    pub scrape_catalog: bool,
+    /// The embeddings of the catalog's courses, if semantic search is on.
+    pub semantic_index: Option<SemanticIndex>,
+    /// Where raw upstream payloads are archived to, if anywhere.
+    pub payload_archive: Option<Arc<PayloadArchive>>,
+    /// Seals and opens users' WebReg credentials, if a master key is set.
+    #[cfg(feature = "auth")]
+    pub credential_vault: Option<CredentialVault>,
+    /// The cookie server that logs in for each user, keyed by the prefix of their API key.
+    /// Users who register with an invite code are added as they're given one.
+    #[cfg(feature = "auth")]
+    pub user_cookie_servers: RwLock<HashMap<String, AddressPortInfo>>,
+    /// What users who register with an invite code are given.
+    #[cfg(feature = "auth")]
+    pub registration: RegistrationConfig,
 }
 
 impl WrapperState {
@@ -52,10 +176,44 @@ impl WrapperState {
     /// # Returns
     /// The wrapper state.
     pub fn new(config: ConfigScraper) -> Self {
+        let data_dir = config.data_dir.clone();
+        let data_path = |file_name| data_path(data_dir.as_deref(), file_name);
         let term_info: WrapperMap = config
             .wrapper_data
             .into_iter()
             .map(|data| TermInfo {
+                sub_session: SubSession::from_term(&data.term),
+                date_range: match (&data.start_date, &data.end_date) {
+                    (Some(start), Some(end)) => {
+                        let range = DateRange::parse(start, end);
+                        if range.is_none() {
+                            tracing::warn!("[{}] Ignoring invalid date range.", data.term);
+                        }
+
+                        range
+                    }
+                    _ => None,
+                },
+                first_pass: data.first_pass_start.as_deref().and_then(|start| {
+                    let first_pass = NaiveDateTime::parse_from_str(start, "%Y-%m-%dT%H:%M")
+                        .ok()
+                        .and_then(|t| t.and_local_timezone(Local).earliest());
+                    if first_pass.is_none() {
+                        tracing::warn!("[{}] Ignoring invalid first pass start.", data.term);
+                    }
+
+                    first_pass.map(|t| t.timestamp_millis())
+                }),
+                add_deadline: data.add_deadline.as_deref().and_then(|deadline| {
+                    let add_deadline = NaiveDateTime::parse_from_str(deadline, "%Y-%m-%dT%H:%M")
+                        .ok()
+                        .and_then(|t| t.and_local_timezone(Local).earliest());
+                    if add_deadline.is_none() {
+                        tracing::warn!("[{}] Ignoring invalid add deadline.", data.term);
+                    }
+
+                    add_deadline.map(|t| t.timestamp_millis())
+                }),
                 term: data.term,
                 cooldown: data.cooldown,
                 search_query: data
@@ -78,34 +236,114 @@ impl WrapperState {
                         parsed
                     })
                     .collect(),
-                tracker: StatTracker {
-                    recent_requests: Default::default(),
-                    num_requests: Default::default(),
-                    total_time_spent: Default::default(),
-                },
+                tracker: StatTracker::default(),
+                is_running: AtomicBool::from(false),
+                is_logged_in: AtomicBool::from(false),
+                wrapper: WebRegWrapper::builder()
+                    .with_cookies("To be loaded later")
+                    .try_build_wrapper()
+                    .unwrap(),
             })
             .map(|data| (data.term.to_owned(), Arc::new(data)))
             .collect();
 
         // Load requirements config from directory
         let requirements_config_path = std::path::Path::new("requirements_config");
-        let requirements_config = crate::degree_audit::config::RequirementsConfig::load_from_directory(
+        let mut requirements_config = crate::degree_audit::config::RequirementsConfig::load_from_directory(
             requirements_config_path,
         )
         .unwrap_or_else(|e| {
             tracing::warn!("Failed to load requirements config: {}. Using empty config.", e);
             crate::degree_audit::config::RequirementsConfig::default()
         });
+        if let Some(ref scale) = config.grade_scale {
+            requirements_config.grade_scale = GradeScale::from_config(
+                scale.profile.as_deref(),
+                &scale.profiles,
+                &scale.overrides,
+            );
+        }
+        let schedule_db = crate::db::ScheduleDbManager::new(&data_path("schedules.db"));
+        crate::audit_trail::load_config_edits(&schedule_db, &mut requirements_config);
+
+        // Registered users keep the cookie servers they were given across restarts
+        #[cfg(feature = "auth")]
+        let mut user_cookie_servers = config.user_cookie_servers;
+        #[cfg(feature = "auth")]
+        for (prefix, address, port) in schedule_db
+            .get_cookie_server_slots()
+            .expect("Failed to load the cookie servers of registered keys")
+        {
+            user_cookie_servers
+                .entry(prefix)
+                .or_insert(AddressPortInfo { address, port });
+        }
 
         // Initialize degree audit cache state and client
-        let degree_audit_cache_state = Arc::new(AuditCacheState::new());
-        let degree_audit_client = DegreeAuditClient::new(degree_audit_cache_state.clone())
-            .expect("Failed to create degree audit client");
+        let audit_quota = AuditQuota::open(
+            &data_path("audit_quota.db"),
+            config
+                .daily_audit_quota
+                .unwrap_or(DEFAULT_DAILY_AUDIT_QUOTA),
+        );
+        let cache_state = match &config.audit_cache_dir {
+            Some(dir) => AuditCacheState::with_disk_cache(dir),
+            None => AuditCacheState::new(),
+        };
+        let ttl_policy = AuditTtlPolicy::new(
+            term_info.iter().filter_map(|t| t.date_range).collect(),
+            config
+                .audit_ttl_minutes
+                .map_or(DEFAULT_AUDIT_TTL, |m| Duration::from_secs(m * 60)),
+            config
+                .grade_posting_audit_ttl_minutes
+                .map_or(DEFAULT_GRADE_POSTING_TTL, |m| Duration::from_secs(m * 60)),
+        );
+        let payload_archive = config.payload_store.as_ref().and_then(|store| {
+            PayloadArchive::from_config(store, data_dir.as_deref(), Client::new())
+                .map(Arc::new)
+                .map_err(|e| tracing::warn!("Not archiving payloads: {}", e))
+                .ok()
+        });
+        let cache_state = match &config.audit_archive_db {
+            Some(path) => {
+                let days = config
+                    .audit_archive_retention_days
+                    .unwrap_or(DEFAULT_ARCHIVE_RETENTION_DAYS);
+                let retention = Duration::from_secs(days * 24 * 60 * 60);
+                let archive = AuditArchive::open(path, retention);
+                cache_state.with_archive(match &payload_archive {
+                    Some(payloads) => archive.with_payloads(payloads.clone()),
+                    None => archive,
+                })
+            }
+            None => cache_state,
+        };
+        let degree_audit_cache_state = Arc::new(
+            cache_state
+                .with_quota(audit_quota)
+                .with_ttl_policy(ttl_policy),
+        );
+        let default_audit_config = DegreeAuditConfig::default();
+        let audit_config = DegreeAuditConfig {
+            poll_strategy: config.audit_poll_strategy.unwrap_or_default().build(
+                Duration::from_millis(config.audit_poll_interval_ms.unwrap_or(500)),
+            ),
+            max_poll_timeout: config
+                .audit_max_poll_seconds
+                .map_or(default_audit_config.max_poll_timeout, Duration::from_secs),
+            fixtures: config
+                .audit_fixture_dir
+                .map(|dir| FixtureMode::Record(dir.into())),
+            ..default_audit_config
+        };
+        let degree_audit_client =
+            DegreeAuditClient::with_config(audit_config, degree_audit_cache_state.clone())
+                .expect("Failed to create degree audit client");
 
         Self {
             all_terms: term_info,
             stop_flag: AtomicBool::from(false),
-            is_running: AtomicBool::from(false),
             client: Default::default(),
             wrapper: WebRegWrapper::builder()
                 .with_cookies("To be loaded later")
@@ -117,13 +355,145 @@ impl WrapperState {
                 .try_build_wrapper()
                 .unwrap(),
             api_base_endpoint: config.api_base_endpoint,
+            admin_endpoint: config.admin_endpoint,
+            admin_token: config.admin_token,
+            allow_unauthenticated_admin: config.allow_unauthenticated_admin,
             cookie_server: config.cookie_server,
-            schedule_db: crate::db::ScheduleDbManager::new("schedules.db"),
+            session_cookies: config.session_cookies,
+            cookie_server_health: CookieServerHealth::new(),
+            cookie_freshness: CookieFreshness::new(
+                config
+                    .cookie_max_age_minutes
+                    .map_or(DEFAULT_MAX_COOKIE_AGE, |m| Duration::from_secs(m * 60)),
+            ),
+            schedule_db,
             #[cfg(feature = "auth")]
-            auth_manager: basicauth::AuthManager::new("auth.db"),
-            requirements_config,
+            auth_manager: basicauth::AuthManager::new(&data_path("auth.db")),
+            requirements_config: RwLock::new(requirements_config),
             degree_audit_client,
             degree_audit_cache_state,
+            drift_tracker: DriftTracker::new(),
+            deprecation_tracker: DeprecationTracker::new(),
+            live_feed: LiveFeed::new(),
+            watch_throttles: DashMap::new(),
+            sessions: SessionRegistry::new(
+                config
+                    .session_rate_limit
+                    .unwrap_or(DEFAULT_SESSION_RATE_LIMIT),
+            ),
+            share_signer: ShareSigner::new(config.share_secret.as_deref()),
+            key_quota: KeyQuota::open(
+                &data_path("key_usage.db"),
+                config.daily_key_quota,
+                config.monthly_key_quota,
+            ),
+            active_term_scrapes: DashMap::new(),
+            heatmaps: DashMap::new(),
+            rate_limits: RateLimits::new(
+                config
+                    .general_rate_limit
+                    .unwrap_or(DEFAULT_GENERAL_RATE_LIMIT),
+                config
+                    .mutation_rate_limit
+                    .unwrap_or(DEFAULT_MUTATION_RATE_LIMIT),
+                config.ip_rate_limit.unwrap_or(DEFAULT_IP_RATE_LIMIT),
+                config.trusted_proxies,
+            ),
+            load_shedder: LoadShedder::new(
+                config
+                    .max_in_flight_requests
+                    .unwrap_or(DEFAULT_MAX_IN_FLIGHT),
+                config.max_queue_depth.unwrap_or(DEFAULT_MAX_QUEUE_DEPTH),
+            ),
+            webreg_retry: config
+                .webreg_retry
+                .map_or_else(RetryPolicy::default, |retry| RetryPolicy {
+                    max_attempts: retry.attempts.unwrap_or(DEFAULT_RETRY_ATTEMPTS).max(1),
+                    base_delay: retry
+                        .base_delay_ms
+                        .map_or(DEFAULT_RETRY_BASE_DELAY, Duration::from_millis),
+                    max_delay: retry
+                        .max_delay_ms
+                        .map_or(DEFAULT_RETRY_MAX_DELAY, Duration::from_millis),
+                    jitter: retry.jitter.unwrap_or(true),
+                }),
+            restriction_windows: config
+                .restriction_windows
+                .into_iter()
+                .map(|w| RestrictionWindowRule {
+                    opens_to_non_majors: NaiveDate::parse_from_str(
+                        &w.opens_to_non_majors,
+                        "%Y-%m-%d",
+                    )
+                    .unwrap_or_else(|_| {
+                        panic!(
+                            "Invalid opensToNonMajors for {} in {}: {}",
+                            w.department, w.term, w.opens_to_non_majors
+                        )
+                    }),
+                    term: w.term,
+                    department: w.department,
+                    courses: w.courses.iter().map(|c| normalize_course_code(c)).collect(),
+                })
+                .collect(),
+            data_license: config.data_license.map(|license| {
+                DataLicense::from_config(license)
+                    .unwrap_or_else(|e| panic!("Invalid dataLicense: {e}"))
+            }),
+            replication: ReplicationState::new(
+                config.standby,
+                config.standby_url.map(|url| StandbyTarget {
+                    url,
+                    token: config.standby_token,
+                }),
+                config
+                    .replication_interval_secs
+                    .map_or(DEFAULT_REPLICATION_INTERVAL, Duration::from_secs),
+            ),
+            leader: LeaderElection::new(
+                config.leader_election,
+                config.instance_id,
+                config
+                    .lease_duration_secs
+                    .map_or(DEFAULT_LEASE_DURATION, Duration::from_secs),
+            ),
+            scheduler: Scheduler::with_builtin_jobs(
+                &config.job_schedules,
+                config
+                    .cookie_validation_interval_secs
+                    .map_or(DEFAULT_VALIDATION_INTERVAL, |s| {
+                        Duration::from_secs(s.max(1))
+                    }),
+            )
+            .unwrap_or_else(|(job, e)| panic!("Invalid schedule for job {job}: {e}")),
+            enrollment_bus: EnrollmentBus::new(),
+            enrollment_hooks: config.enrollment_hooks,
+            pass_time_reminders: config.pass_time_reminders,
+            notifications: config.notifications.unwrap_or_default(),
+            notify_client: crate::notify::client(),
+            student_notify_client: crate::notify::student_client(),
+            grade_distribution_url: config.grade_distribution_url,
+            scrape_catalog: config.scrape_catalog,
+            semantic_index: config.semantic_search.map(|c| {
+                SemanticIndex::new(match c.embedding_url {
+                    Some(url) => Embedder::Api {
+                        url,
+                        model: c.embedding_model.unwrap_or_default(),
+                        token: c.embedding_token,
+                    },
+                    None => Embedder::Local,
+                })
+            }),
+            payload_archive,
+            #[cfg(feature = "auth")]
+            credential_vault: config
+                .vault_master_key
+                .as_deref()
+                .map(|key| CredentialVault::new(key).expect("Invalid vaultMasterKey")),
+            #[cfg(feature = "auth")]
+            user_cookie_servers: RwLock::new(user_cookie_servers),
+            #[cfg(feature = "auth")]
+            registration: config.registration,
         }
     }
 
@@ -144,16 +514,126 @@ impl WrapperState {
         self.stop_flag.store(stop_status, Ordering::SeqCst);
     }
 
-    /// Indicates whether the scraper for _all_ terms is running.
+    /// Indicates whether the scraper for _any_ term is running.
     ///
     /// # Returns
-    /// `true` if the scraper is running, and `false` otherwise.
+    /// `true` if at least one scraper is running, and `false` otherwise.
     pub fn is_running(&self) -> bool {
-        self.is_running.load(Ordering::SeqCst)
+        self.all_terms.iter().any(|t| t.is_running())
+    }
+
+    /// Gets the address of the cookie server (e.g., `127.0.0.1:3000`), if there is one.
+    pub fn cookie_server_address(&self) -> Option<String> {
+        self.cookie_server
+            .as_ref()
+            .map(|server| format!("{}:{}", server.address, server.port))
+    }
+
+    /// Whether users are assigned cookie servers of their own, in which case each one only
+    /// has access to their own degree audit (see [`crate::degree_audit::owner`]).
+    pub fn is_multi_user(&self) -> bool {
+        #[cfg(feature = "auth")]
+        return !self.user_cookie_servers.read().unwrap().is_empty()
+            || !self.registration.cookie_servers.is_empty();
+        #[cfg(not(feature = "auth"))]
+        false
+    }
+
+    /// Works out whose degree audit a request is for.
+    ///
+    /// # Parameters
+    /// - `key_prefix`: The prefix of the caller's API key, if the request carried one.
+    ///
+    /// # Returns
+    /// The owner of the audit, or why the caller can't have one.
+    pub fn audit_owner(&self, key_prefix: Option<&str>) -> Result<AuditOwner, DegreeAuditError> {
+        if !self.is_multi_user() {
+            return AuditOwner::resolve(key_prefix, self.cookie_server_address(), None);
+        }
+
+        #[cfg(feature = "auth")]
+        let user_servers = self.user_cookie_servers.read().unwrap();
+        #[cfg(not(feature = "auth"))]
+        let user_servers = HashMap::new();
+        AuditOwner::resolve(
+            key_prefix,
+            self.cookie_server_address(),
+            Some(&user_servers),
+        )
+    }
+
+    /// Gets the state for the specified term.
+    ///
+    /// # Parameters
+    /// - `term`: The term.
+    ///
+    /// # Returns
+    /// The term's state, if the term is supported.
+    pub fn term(&self, term: &str) -> Option<Arc<TermInfo>> {
+        self.all_terms.get(term).map(|t| t.value().clone())
+    }
+
+    /// Gets the state for every term, so that it can be used without holding onto the map.
+    ///
+    /// # Returns
+    /// The state of each term.
+    pub fn terms(&self) -> Vec<Arc<TermInfo>> {
+        self.all_terms.iter().map(|t| t.value().clone()).collect()
+    }
+
+    /// Gets a copy of the requirements configuration, so that it can be used without
+    /// holding the lock.
+    ///
+    /// # Returns
+    /// The requirements configuration.
+    pub fn requirements(&self) -> crate::degree_audit::config::RequirementsConfig {
+        self.requirements_config.read().unwrap().clone()
+    }
+
+    /// Gets the class density heatmap for a term, computing it if it isn't cached.
+    ///
+    /// # Parameters
+    /// - `term`: The term.
+    ///
+    /// # Returns
+    /// The heatmap, or an error if the meetings couldn't be loaded.
+    pub fn heatmap(&self, term: &str) -> rusqlite::Result<Arc<Heatmap>> {
+        match self.heatmaps.get(term) {
+            Some(heatmap) => Ok(heatmap.clone()),
+            None => self.refresh_heatmap(term),
+        }
+    }
+
+    /// Recomputes and caches the class density heatmap for a term. This should be called
+    /// whenever the term's schedule data changes.
+    ///
+    /// # Parameters
+    /// - `term`: The term.
+    ///
+    /// # Returns
+    /// The new heatmap, or an error if the meetings couldn't be loaded.
+    pub fn refresh_heatmap(&self, term: &str) -> rusqlite::Result<Arc<Heatmap>> {
+        let loads: Vec<MeetingLoad> = self
+            .schedule_db
+            .get_meeting_loads(term)?
+            .into_iter()
+            .filter_map(|(meeting, seats, enrolled)| {
+                Some(MeetingLoad {
+                    building: meeting.building.clone()?,
+                    slot: MeetingSlot::from_db(&meeting)?,
+                    seats,
+                    enrolled,
+                })
+            })
+            .collect();
+
+        let heatmap = Arc::new(build_heatmap(term, &loads));
+        self.heatmaps.insert(term.to_owned(), heatmap.clone());
+        Ok(heatmap)
     }
 }
 
-pub type WrapperMap = HashMap<String, Arc<TermInfo>>;
+pub type WrapperMap = DashMap<String, Arc<TermInfo>>;
 
 /// A structure that holds basic stats about the tracker's requests.
 #[derive(Default)]
@@ -164,6 +644,10 @@ pub struct StatTracker {
     pub num_requests: AtomicUsize,
     /// The total amount of time spent making those requests, in milliseconds.
     pub total_time_spent: AtomicUsize,
+    /// When the tracker last got a course's enrollment counts, in RFC 3339 format.
+    pub last_success: Mutex<Option<String>>,
+    /// The number of requests in a row that failed.
+    pub consecutive_failures: AtomicUsize,
 }
 
 impl StatTracker {
@@ -182,6 +666,17 @@ impl StatTracker {
 
         recent_requests.push_back(time_of_req);
     }
+
+    /// Records that a request for a course's enrollment counts succeeded.
+    pub fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::SeqCst);
+        *self.last_success.lock().unwrap() = Some(chrono::Utc::now().to_rfc3339());
+    }
+
+    /// Records that a request for a course's enrollment counts failed.
+    pub fn record_failure(&self) {
+        self.consecutive_failures.fetch_add(1, Ordering::SeqCst);
+    }
 }
 
 /// A structure that holds information relating to the scraper and, more importantly, the
@@ -189,12 +684,47 @@ impl StatTracker {
 pub struct TermInfo {
     /// The term associated with this scraper.
     pub term: String,
+    /// The part of the year that this term covers, which matters for summer sessions.
+    pub sub_session: SubSession,
+    /// The first and last day of instruction for this term, if configured.
+    pub date_range: Option<DateRange>,
+    /// When first pass enrollment starts, in milliseconds since the epoch, if configured.
+    pub first_pass: Option<i64>,
+    /// The last time that classes can be added, in milliseconds since the epoch, if
+    /// configured. Enrollment jobs expire at it.
+    pub add_deadline: Option<i64>,
     /// The cooldown, in seconds, between requests.
     pub cooldown: f64,
     /// The courses to search for.
     pub search_query: Vec<SearchRequestBuilder>,
     /// Tracker stats. This field contains information on the performance of the scraper.
     pub tracker: StatTracker,
+    /// Whether the scraper for this term is running at this moment.
+    pub is_running: AtomicBool,
+    /// Whether this term could be used with the session cookies when they were last logged
+    /// in with. Terms that couldn't aren't tracked until the next login.
+    pub is_logged_in: AtomicBool,
+    /// The wrapper that this term's scraper uses to make requests to WebReg. This shares
+    /// session cookies with the other terms, but is otherwise independent of them.
+    pub wrapper: WebRegWrapper,
+}
+
+impl TermInfo {
+    /// Indicates whether the scraper for this term is running.
+    ///
+    /// # Returns
+    /// `true` if the scraper is running, and `false` otherwise.
+    pub fn is_running(&self) -> bool {
+        self.is_running.load(Ordering::SeqCst)
+    }
+
+    /// Indicates whether this term could be used with the current session cookies.
+    ///
+    /// # Returns
+    /// `true` if the term was logged in, and `false` otherwise.
+    pub fn is_logged_in(&self) -> bool {
+        self.is_logged_in.load(Ordering::SeqCst)
+    }
 }
 
 /// A structure that represents a configuration file specifically for the scraper. See the
@@ -208,14 +738,220 @@ pub struct ConfigScraper {
     /// The address for which the endpoints specified in this application is made
     /// available for other applications to use.
     pub api_base_endpoint: AddressPortInfo,
+    /// The address to serve the admin API on. If not set, the admin API is served
+    /// alongside the rest of the API, but only if `admin_token` is set.
+    #[serde(default)]
+    pub admin_endpoint: Option<AddressPortInfo>,
+    /// The bearer token that requests to the admin API must carry.
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    /// Whether to serve the admin API on `admin_endpoint` without a token. This should only
+    /// be set if that address can't be reached by anyone but whoever runs the scraper.
+    #[serde(default)]
+    pub allow_unauthenticated_admin: bool,
     /// The recovery address/port information. When the scraper is unable to get data
     /// for this particular term, it will attempt to request new session cookies for this
-    /// term so it can continue to get data.
-    pub cookie_server: AddressPortInfo,
+    /// term so it can continue to get data. If not set, the tracker logs in with
+    /// `session_cookies` instead, and degree audits aren't served.
+    #[serde(default)]
+    pub cookie_server: Option<AddressPortInfo>,
+    /// The WebReg session cookies that the tracker logs in with when there's no cookie
+    /// server. They can't be renewed, so the tracker stops once they expire.
+    #[serde(default)]
+    pub session_cookies: Option<String>,
+    /// The directory that the databases are kept in. If not set, they're kept in the
+    /// working directory.
+    #[serde(default)]
+    pub data_dir: Option<String>,
     /// Information about what terms the scraper will be gathering data for.
     pub wrapper_data: Vec<ConfigTermDatum>,
     /// Whether the logging should be verbose or not.
     pub verbose: bool,
+    /// The number of requests, per minute, that a single student's session may make to
+    /// the cookie endpoints.
+    #[serde(default)]
+    pub session_rate_limit: Option<usize>,
+    /// The number of requests, per minute, that a single client may make to the API.
+    #[serde(default)]
+    pub general_rate_limit: Option<u32>,
+    /// The number of requests, per minute, that a single client may make to the cookie
+    /// endpoints that change a student's enrollment or plans.
+    #[serde(default)]
+    pub mutation_rate_limit: Option<u32>,
+    /// The number of requests, per minute, that a single IP address may make to the API,
+    /// whether or not they're authenticated.
+    #[serde(default)]
+    pub ip_rate_limit: Option<u32>,
+    /// The addresses of the reverse proxies in front of the API. Requests from them are
+    /// attributed to the address in their `X-Forwarded-For` or `X-Real-IP` header.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+    /// The number of requests that a single API key may make per day. If not set, there's
+    /// no daily quota.
+    #[serde(default)]
+    pub daily_key_quota: Option<u32>,
+    /// The number of requests that a single API key may make per month. If not set, there's
+    /// no monthly quota.
+    #[serde(default)]
+    pub monthly_key_quota: Option<u32>,
+    /// The number of requests that may be served at once before low-priority requests are
+    /// turned away.
+    #[serde(default)]
+    pub max_in_flight_requests: Option<usize>,
+    /// The number of tasks that may be waiting for the runtime before low-priority requests
+    /// are turned away.
+    #[serde(default)]
+    pub max_queue_depth: Option<usize>,
+    /// How often the tracker's session cookies are validated, in seconds.
+    #[serde(default)]
+    pub cookie_validation_interval_secs: Option<u64>,
+    /// The schedules of the recurring background jobs, keyed by job name, which override
+    /// their defaults. Each is a cron expression, an interval like `@every 5m`, or `off`.
+    #[serde(default)]
+    pub job_schedules: HashMap<String, String>,
+    /// How old the tracker's session cookies may get, in minutes, before new ones are
+    /// requested ahead of time.
+    #[serde(default)]
+    pub cookie_max_age_minutes: Option<u64>,
+    /// How WebReg requests that fail for transient reasons (e.g., 5xx statuses and
+    /// timeouts) are retried. If not set, they're made up to three times.
+    #[serde(default)]
+    pub webreg_retry: Option<ConfigRetry>,
+    /// Where the data comes from and the terms that it's served under, which every
+    /// response is tagged with and `/about` describes. If not set, responses aren't tagged.
+    #[serde(default)]
+    pub data_license: Option<ConfigDataLicense>,
+    /// The dates that departments open their sections to non-majors on, for departments
+    /// that don't say so in their sections' notes.
+    #[serde(default)]
+    pub restriction_windows: Vec<ConfigRestrictionWindow>,
+    /// Whether this instance is a warm standby, which restores the snapshots pushed by the
+    /// primary and doesn't run its background workers until it's promoted.
+    #[serde(default)]
+    pub standby: bool,
+    /// The base URL of the standby's admin API, to push snapshots of the schedule database
+    /// to. If not set, nothing is replicated.
+    #[serde(default)]
+    pub standby_url: Option<String>,
+    /// The standby's admin token.
+    #[serde(default)]
+    pub standby_token: Option<String>,
+    /// How often snapshots are pushed to the standby, in seconds.
+    #[serde(default)]
+    pub replication_interval_secs: Option<u64>,
+    /// Whether this instance shares the schedule database with others, in which case only
+    /// the one holding the leader lease runs the background work.
+    #[serde(default)]
+    pub leader_election: bool,
+    /// The ID that this instance holds the leader lease under. If not set, a random one is
+    /// used.
+    #[serde(default)]
+    pub instance_id: Option<String>,
+    /// How long the leader lease lasts without being renewed, in seconds.
+    #[serde(default)]
+    pub lease_duration_secs: Option<u64>,
+    /// A URL to fetch grade distributions from, as CSV, once a day.
+    #[serde(default)]
+    pub grade_distribution_url: Option<String>,
+    /// Whether to scrape the UCSD course catalog for course descriptions every week.
+    #[serde(default)]
+    pub scrape_catalog: bool,
+    /// Semantic search over the catalog's course descriptions. If not set, it's off.
+    #[serde(default)]
+    pub semantic_search: Option<ConfigSemanticSearch>,
+    /// The grade scale used to compute GPAs and decide which grades pass in degree
+    /// audits. If not set, UCSD's is used.
+    #[serde(default)]
+    pub grade_scale: Option<ConfigGradeScale>,
+    /// Hooks to fire after a student's enrollment changes.
+    #[serde(default)]
+    pub enrollment_hooks: Vec<ConfigHook>,
+    /// The reminders to send before students' passes open. If not set, none are sent.
+    #[serde(default)]
+    pub pass_time_reminders: Option<ConfigPassTimeReminders>,
+    /// The settings for channels that notifications are sent to. If not set, email can't
+    /// be sent and ntfy notifications go to `https://ntfy.sh`.
+    #[serde(default)]
+    pub notifications: Option<ConfigNotifications>,
+    /// The directory to persist cached degree audits to, so that they survive restarts.
+    #[serde(default)]
+    pub audit_cache_dir: Option<String>,
+    /// The directory to record the pages that DARS serves to, scrubbed of personal
+    /// information, so that they can be replayed as test fixtures. If not set, pages aren't
+    /// recorded.
+    #[serde(default)]
+    pub audit_fixture_dir: Option<String>,
+    /// The number of degree audits that a single session may trigger per day.
+    #[serde(default)]
+    pub daily_audit_quota: Option<u32>,
+    /// How long degree audits are cached for, in minutes, outside of grade-posting weeks.
+    #[serde(default)]
+    pub audit_ttl_minutes: Option<u64>,
+    /// How long degree audits are cached for, in minutes, in the weeks after a term's last
+    /// day of instruction.
+    #[serde(default)]
+    pub grade_posting_audit_ttl_minutes: Option<u64>,
+    /// The SQLite database to archive the raw pages of fetched degree audits to. Identical
+    /// pages are only stored once. If not set, pages aren't archived.
+    #[serde(default)]
+    pub audit_archive_db: Option<String>,
+    /// How long archived degree audits are kept for, in days.
+    #[serde(default)]
+    pub audit_archive_retention_days: Option<u64>,
+    /// Where to archive the raw payloads that WebReg returns to scrapes and DARS returns to
+    /// archived degree audits. If set, audit pages are kept there instead of in
+    /// `audit_archive_db`, which still records their snapshots.
+    #[serde(default)]
+    pub payload_store: Option<ConfigPayloadStore>,
+    /// How to wait between polls while DARS generates a degree audit.
+    #[serde(default)]
+    pub audit_poll_strategy: Option<PollStrategyKind>,
+    /// The interval between polls, in milliseconds, that the poll strategy starts from.
+    #[serde(default)]
+    pub audit_poll_interval_ms: Option<u64>,
+    /// The most seconds to wait for DARS to generate a degree audit. Requests can ask to
+    /// wait for less.
+    #[serde(default)]
+    pub audit_max_poll_seconds: Option<u64>,
+    /// The secret used to sign schedule sharing links.
+    #[serde(default)]
+    pub share_secret: Option<String>,
+    /// The number of log lines to keep in memory so that they can be looked up by request
+    /// ID. If not set, lines aren't kept.
+    #[serde(default)]
+    pub request_log_capacity: Option<usize>,
+    /// The master key that users' WebReg credentials are sealed with, as 64 hex characters.
+    /// If not set, the credential vault is disabled.
+    #[cfg(feature = "auth")]
+    #[serde(default)]
+    pub vault_master_key: Option<String>,
+    /// The cookie server that logs in for each user, keyed by the prefix of their API key.
+    /// Users' credentials are sent to theirs whenever they change.
+    #[cfg(feature = "auth")]
+    #[serde(default)]
+    pub user_cookie_servers: HashMap<String, AddressPortInfo>,
+    /// What users who register with an invite code (see `POST /register`) are given.
+    #[cfg(feature = "auth")]
+    #[serde(default)]
+    pub registration: RegistrationConfig,
+}
+
+/// Gets the path of a file in the data directory.
+///
+/// # Parameters
+/// - `data_dir`: The data directory, if there is one.
+/// - `file_name`: The file's name (e.g., `schedules.db`).
+///
+/// # Returns
+/// The path, which is just the file's name if there's no data directory.
+pub fn data_path(data_dir: Option<&str>, file_name: &str) -> String {
+    match data_dir {
+        Some(dir) => std::path::Path::new(dir)
+            .join(file_name)
+            .to_string_lossy()
+            .into_owned(),
+        None => file_name.to_owned(),
+    }
 }
 
 /// A structure that represents an address and port.
@@ -227,6 +963,253 @@ pub struct AddressPortInfo {
     pub port: i64,
 }
 
+/// What users who register with an invite code are given, in place of the entries that
+/// would otherwise be added to the configuration file for each of them.
+#[cfg(feature = "auth")]
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrationConfig {
+    /// The cookie servers that registered users are given, one each. If users have cookie
+    /// servers of their own (i.e., the server is in multi-user mode), registering fails once
+    /// every one of these has been given out.
+    #[serde(default)]
+    pub cookie_servers: Vec<AddressPortInfo>,
+    /// The scopes that registered keys have. If not set, they have every scope.
+    #[serde(default)]
+    pub scopes: Option<Vec<ApiScope>>,
+    /// The response filter that registered keys start with, if any.
+    #[serde(default)]
+    pub filter: Option<ResponseFilter>,
+}
+
+/// A structure that represents a hook to fire after a student's enrollment changes. Each
+/// filter that's left empty lets every event through.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigHook {
+    /// The name of the hook, used for logging.
+    pub name: String,
+    /// The URL to post each event to, as JSON.
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// The channels (e.g., Discord or email) to notify of each event.
+    #[serde(default)]
+    pub channels: Vec<NotifyChannel>,
+    /// The shell command to run for each event. The event is given as JSON in the
+    /// `WEBREG_EVENT` environment variable.
+    #[serde(default)]
+    pub command: Option<String>,
+    /// The actions (`add` or `drop`) to fire the hook for.
+    #[serde(default)]
+    pub actions: Vec<String>,
+    /// The terms to fire the hook for.
+    #[serde(default)]
+    pub terms: Vec<String>,
+    /// The courses (e.g., `CSE 100`) to fire the hook for.
+    #[serde(default)]
+    pub courses: Vec<String>,
+}
+
+/// Reminders to send before students' enrollment appointments (passes) open.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigPassTimeReminders {
+    /// The URLs to post each reminder to, as JSON.
+    #[serde(default)]
+    pub webhook_urls: Vec<String>,
+    /// The channels (e.g., Discord or email) to send each reminder to.
+    #[serde(default)]
+    pub channels: Vec<NotifyChannel>,
+    /// How many hours before a pass opens to send reminders at. If empty, a reminder is
+    /// sent a day before.
+    #[serde(default)]
+    pub hours_before: Vec<u64>,
+}
+
+/// The settings for channels that notifications are sent to.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigNotifications {
+    /// The SMTP relay to send email through. If not set, email can't be sent.
+    #[serde(default)]
+    pub smtp: Option<ConfigSmtp>,
+    /// The ntfy server to publish to, unless a channel gives its own.
+    #[serde(default)]
+    pub ntfy_server: Option<String>,
+}
+
+/// An SMTP server to send email through.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigSmtp {
+    /// The server's address and port (e.g., `smtp.example.com:587`).
+    pub server: String,
+    /// The address that email is sent from.
+    pub from: String,
+    /// How the connection is secured. Defaults to STARTTLS.
+    #[serde(default)]
+    pub tls: SmtpTls,
+    /// The username to log in with, if the server needs one.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// The password to log in with.
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// How the connection to an SMTP server is secured.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// The connection is upgraded with STARTTLS, which must succeed (e.g., on port 587).
+    #[default]
+    Starttls,
+    /// The connection uses TLS from the start (e.g., on port 465).
+    Tls,
+    /// Plain SMTP, without TLS or logging in. Only allowed for a relay on this machine
+    /// (e.g., `localhost:25`).
+    None,
+}
+
+/// Changes to UCSD's grade scale. The named profile, if any, is applied first, and then the
+/// rest of the changes.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigGradeScale {
+    /// The institution profile to use, from `profiles`.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Institution profiles, each a set of changes to UCSD's grade scale.
+    #[serde(default)]
+    pub profiles: HashMap<String, GradeScaleOverrides>,
+    /// The changes to apply on top of the profile.
+    #[serde(flatten)]
+    pub overrides: GradeScaleOverrides,
+}
+
+/// How the embeddings for semantic search are made. Without an `embedding_url`, a local
+/// model is used.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigSemanticSearch {
+    /// The URL of an OpenAI-style embedding API.
+    #[serde(default)]
+    pub embedding_url: Option<String>,
+    /// The model to ask the embedding API for.
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+    /// The token to attach to requests to the embedding API.
+    #[serde(default)]
+    pub embedding_token: Option<String>,
+}
+
+/// How WebReg requests that fail for transient reasons are retried. Requests that change a
+/// student's enrollment or plans are only retried if WebReg turned them away unhandled.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigRetry {
+    /// The number of times that a request is made, including the first.
+    #[serde(default)]
+    pub attempts: Option<u32>,
+    /// The delay before the first retry, in milliseconds. It doubles with each retry.
+    #[serde(default)]
+    pub base_delay_ms: Option<u64>,
+    /// The longest delay between retries, in milliseconds.
+    #[serde(default)]
+    pub max_delay_ms: Option<u64>,
+    /// Whether each delay is randomized, so that requests that failed together aren't all
+    /// retried together. Defaults to `true`.
+    #[serde(default)]
+    pub jitter: Option<bool>,
+}
+
+/// Where the raw payloads that WebReg and DARS return are archived to.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigPayloadStore {
+    pub backend: PayloadBackend,
+    /// The directory to keep payloads in, for the `local` backend. Relative paths are under
+    /// the data directory. Defaults to `payloads`.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// The base URL of the object store, for the `s3` backend (e.g.,
+    /// `https://s3.us-west-2.amazonaws.com`).
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// The bucket to keep payloads in, for the `s3` backend.
+    #[serde(default)]
+    pub bucket: Option<String>,
+    /// The region that requests to the object store are signed for. Defaults to
+    /// `us-east-1`.
+    #[serde(default)]
+    pub region: Option<String>,
+    /// The object store's access key ID. If not set, it's read from `AWS_ACCESS_KEY_ID`.
+    #[serde(default)]
+    pub access_key_id: Option<String>,
+    /// The object store's secret access key. If not set, it's read from
+    /// `AWS_SECRET_ACCESS_KEY`.
+    #[serde(default)]
+    pub secret_access_key: Option<String>,
+    /// How long each kind of payload (`webreg` or `dars`) is kept for, in days.
+    #[serde(default)]
+    pub retention_days: HashMap<String, u64>,
+}
+
+/// Where payloads are archived to.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadBackend {
+    /// A directory on the local file system.
+    Local,
+    /// An S3-compatible bucket.
+    S3,
+}
+
+/// The date that a department opens its sections to non-majors on.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigRestrictionWindow {
+    pub term: String,
+    /// The department's subject code (e.g., `CSE`).
+    pub department: String,
+    /// The courses that the window applies to (e.g., `CSE 100`). If empty, it applies to
+    /// every course of the department.
+    #[serde(default)]
+    pub courses: Vec<String>,
+    /// The date, in `YYYY-MM-DD` format.
+    pub opens_to_non_majors: String,
+}
+
+/// The data's provenance and terms of use, for serving it publicly.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigDataLicense {
+    /// Where the data comes from. Defaults to UC San Diego's WebReg.
+    #[serde(default)]
+    pub source: Option<String>,
+    /// The name of the license that the data is served under.
+    #[serde(default)]
+    pub license: Option<String>,
+    /// A link to the license's text.
+    #[serde(default)]
+    pub license_url: Option<String>,
+    /// The notice that has to be shown with the data.
+    #[serde(default)]
+    pub attribution: Option<String>,
+    /// The terms that the data may be used under.
+    #[serde(default)]
+    pub terms_of_use: Option<String>,
+    /// A link to the full terms of use.
+    #[serde(default)]
+    pub terms_url: Option<String>,
+    /// Who to contact about the data.
+    #[serde(default)]
+    pub contact: Option<String>,
+    /// Other headers to add to every response, keyed by name.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
 /// A structure that represents a specific term that the scraper should consider.
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -238,6 +1221,7 @@ pub struct ConfigTermDatum {
     /// - `SP` for Spring term
     /// - `S1` for Summer 1 term
     /// - `S2` for Summer 2 term
+    /// - `S3` for Summer 3 term
     ///
     /// The last two characters must represent the year associated with that term.
     /// For example, `FA22` represents the Fall 2022 term, and `S120` represents the
@@ -249,6 +1233,21 @@ pub struct ConfigTermDatum {
     pub search_query: Vec<ConfigSearchQuery>,
     /// Whether we should be saving data scraped for this term to a file.
     pub save_data_to_file: bool,
+    /// The first day of instruction for this term, in `YYYY-MM-DD` format. This is
+    /// mainly useful for summer sub-sessions, which run over different weeks.
+    #[serde(default)]
+    pub start_date: Option<String>,
+    /// The last day of instruction for this term, in `YYYY-MM-DD` format.
+    #[serde(default)]
+    pub end_date: Option<String>,
+    /// When first pass enrollment starts for this term, in `YYYY-MM-DDTHH:MM` format
+    /// (local time). Fill rates are measured from it.
+    #[serde(default)]
+    pub first_pass_start: Option<String>,
+    /// The last time that classes can be added in this term, in `YYYY-MM-DDTHH:MM` format
+    /// (local time). Enrollment jobs expire at it.
+    #[serde(default)]
+    pub add_deadline: Option<String>,
 }
 
 /// A structure that represents a search query for a term for the scraper.
@@ -263,3 +1262,59 @@ pub struct ConfigSearchQuery {
     /// specified, then all courses will be fetched.
     pub departments: Vec<String>,
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    /// Creates a state for tests that tracks the given terms, keeping its data in a
+    /// temporary directory named after the test
+    ///
+    /// # Parameters
+    /// - `name`: The name of the test.
+    /// - `terms`: The terms to track.
+    /// - `config`: Any other configuration, which takes precedence over the defaults.
+    pub fn state(name: &str, terms: &[&str], config: Value) -> WrapperState {
+        let dir = std::env::temp_dir().join(format!("webreg-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let wrapper_data: Vec<_> = terms
+            .iter()
+            .map(|term| {
+                json!({
+                    "term": term,
+                    "cooldown": 0.0,
+                    "searchQuery": [],
+                    "saveDataToFile": false,
+                })
+            })
+            .collect();
+        let mut base = json!({
+            "configName": name,
+            "apiBaseEndpoint": { "address": "127.0.0.1", "port": 0 },
+            "dataDir": dir,
+            "wrapperData": wrapper_data,
+            "verbose": false,
+        });
+        if let (Some(base), Value::Object(config)) = (base.as_object_mut(), config) {
+            base.extend(config);
+        }
+
+        WrapperState::new(serde_json::from_value(base).unwrap())
+    }
+
+    #[test]
+    fn test_stat_tracker_failures() {
+        let tracker = StatTracker::default();
+        tracker.record_failure();
+        tracker.record_failure();
+        assert_eq!(2, tracker.consecutive_failures.load(Ordering::SeqCst));
+        assert!(tracker.last_success.lock().unwrap().is_none());
+
+        // A success ends the run of failures
+        tracker.record_success();
+        assert_eq!(0, tracker.consecutive_failures.load(Ordering::SeqCst));
+        assert!(tracker.last_success.lock().unwrap().is_some());
+    }
+}
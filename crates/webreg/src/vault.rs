@@ -0,0 +1,266 @@
+//! The credential vault, which stores each user's WebReg credentials so that they can be
+//! handed to the cookie server that logs in on the user's behalf.
+//!
+//! Credentials are sealed with AES-256-GCM under a master key from the configuration, so
+//! the database alone isn't enough to recover them. Each user's credentials are bound to
+//! their API key's prefix as associated data, so sealed credentials can't be moved from one
+//! user to another in the database.
+//!
+//! Whenever a user's credentials change, they're sent to the cookie server assigned to the
+//! user (`POST /credentials`), so that its next login uses them. Each push is signed with
+//! HMAC-SHA256 under a push key derived from the master key (see [`PUSH_KEY_LABEL`]), which
+//! the cookie server is configured with, so that no one else can replace its credentials.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use reqwest::Client;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::types::AddressPortInfo;
+
+/// The length of the master key, in bytes.
+const MASTER_KEY_LEN: usize = 32;
+/// How long a cookie server has to accept new credentials.
+const PUSH_TIMEOUT: Duration = Duration::from_secs(15);
+/// The message whose HMAC-SHA256 under the master key is the push key, which is what the
+/// cookie server is configured with. It can be found with
+/// `printf 'webregautoin credential push' | openssl dgst -sha256 -mac HMAC -macopt hexkey:<master key>`.
+pub const PUSH_KEY_LABEL: &str = "webregautoin credential push";
+/// The header that a push's time is sent in, in seconds since the epoch.
+pub const PUSH_TIMESTAMP_HEADER: &str = "X-Credentials-Timestamp";
+/// The header that a push's signature is sent in: the hex HMAC-SHA256, under the push key,
+/// of the push's time, a `.`, and its body.
+pub const PUSH_SIGNATURE_HEADER: &str = "X-Credentials-Signature";
+
+/// A user's WebReg credentials.
+#[derive(Serialize, Deserialize)]
+pub struct WebRegCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// Why the vault couldn't be opened, or credentials couldn't be sealed or opened.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VaultError {
+    #[error("the master key must be {MASTER_KEY_LEN} bytes, written as 64 hex characters")]
+    InvalidMasterKey,
+    #[error("the sealed credentials are corrupt, or were sealed with another key")]
+    Corrupt,
+}
+
+/// Seals and opens credentials with the master key, and signs them for cookie servers.
+pub struct CredentialVault {
+    key: LessSafeKey,
+    push_key: hmac::Key,
+    rng: SystemRandom,
+}
+
+impl CredentialVault {
+    /// Creates a vault with the given master key.
+    ///
+    /// # Parameters
+    /// - `master_key`: The master key, as 64 hex characters.
+    pub fn new(master_key: &str) -> Result<Self, VaultError> {
+        let master_key = master_key.trim();
+        if master_key.len() != MASTER_KEY_LEN * 2
+            || !master_key.bytes().all(|b| b.is_ascii_hexdigit())
+        {
+            return Err(VaultError::InvalidMasterKey);
+        }
+
+        let bytes = (0..master_key.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&master_key[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| VaultError::InvalidMasterKey)?;
+        let key =
+            UnboundKey::new(&AES_256_GCM, &bytes).map_err(|_| VaultError::InvalidMasterKey)?;
+        let push_key = hmac::sign(
+            &hmac::Key::new(hmac::HMAC_SHA256, &bytes),
+            PUSH_KEY_LABEL.as_bytes(),
+        );
+
+        Ok(Self {
+            key: LessSafeKey::new(key),
+            push_key: hmac::Key::new(hmac::HMAC_SHA256, push_key.as_ref()),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// Signs a push to a cookie server.
+    ///
+    /// # Parameters
+    /// - `timestamp`: When the push is sent, in seconds since the epoch.
+    /// - `body`: The push's body.
+    ///
+    /// # Returns
+    /// The signature, as hex.
+    pub fn sign_push(&self, timestamp: i64, body: &[u8]) -> String {
+        let mut message = format!("{timestamp}.").into_bytes();
+        message.extend_from_slice(body);
+        hmac::sign(&self.push_key, &message)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    /// Seals a user's credentials.
+    ///
+    /// # Parameters
+    /// - `owner`: The prefix of the user's API key.
+    /// - `credentials`: The credentials.
+    ///
+    /// # Returns
+    /// A fresh nonce followed by the ciphertext and its tag.
+    pub fn seal(&self, owner: &str, credentials: &WebRegCredentials) -> Vec<u8> {
+        let mut nonce = [0; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .expect("Failed to generate a nonce");
+
+        let mut in_out = serde_json::to_vec(credentials).expect("Credentials always serialize");
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(owner.as_bytes()),
+                &mut in_out,
+            )
+            .expect("Failed to seal credentials");
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(in_out);
+        sealed
+    }
+
+    /// Opens a user's sealed credentials.
+    ///
+    /// # Parameters
+    /// - `owner`: The prefix of the user's API key.
+    /// - `sealed`: The sealed credentials, from [`CredentialVault::seal`].
+    pub fn open(&self, owner: &str, sealed: &[u8]) -> Result<WebRegCredentials, VaultError> {
+        if sealed.len() < NONCE_LEN {
+            return Err(VaultError::Corrupt);
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| VaultError::Corrupt)?;
+        let mut in_out = ciphertext.to_vec();
+        let plaintext = self
+            .key
+            .open_in_place(nonce, Aad::from(owner.as_bytes()), &mut in_out)
+            .map_err(|_| VaultError::Corrupt)?;
+
+        serde_json::from_slice(plaintext).map_err(|_| VaultError::Corrupt)
+    }
+}
+
+/// Sends credentials to a cookie server, signed with the vault's push key.
+///
+/// # Parameters
+/// - `client`: The client to send them with.
+/// - `vault`: The vault.
+/// - `cookie_server`: The cookie server.
+/// - `credentials`: The credentials.
+///
+/// # Returns
+/// Nothing if the cookie server accepted them, or why it didn't.
+pub async fn push_credentials(
+    client: &Client,
+    vault: &CredentialVault,
+    cookie_server: &AddressPortInfo,
+    credentials: &WebRegCredentials,
+) -> Result<(), String> {
+    let url = format!(
+        "http://{}:{}/credentials",
+        cookie_server.address, cookie_server.port
+    );
+
+    let body = serde_json::to_vec(credentials).expect("Credentials always serialize");
+    let timestamp = Utc::now().timestamp();
+    match client
+        .post(&url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(PUSH_TIMESTAMP_HEADER, timestamp)
+        .header(PUSH_SIGNATURE_HEADER, vault.sign_push(timestamp, &body))
+        .body(body)
+        .timeout(PUSH_TIMEOUT)
+        .send()
+        .await
+    {
+        Ok(resp) if resp.status().is_success() => Ok(()),
+        Ok(resp) => Err(format!(
+            "the cookie server responded with {}",
+            resp.status()
+        )),
+        Err(e) => Err(format!("the cookie server couldn't be reached: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    #[test]
+    fn test_seal_and_open() {
+        let vault = CredentialVault::new(KEY).unwrap();
+        let credentials = WebRegCredentials {
+            username: "student".to_owned(),
+            password: "hunter2".to_owned(),
+        };
+
+        let sealed = vault.seal("alice", &credentials);
+        assert_ne!(sealed, vault.seal("alice", &credentials));
+        let opened = vault.open("alice", &sealed).unwrap();
+        assert_eq!(
+            ("student", "hunter2"),
+            (opened.username.as_str(), opened.password.as_str())
+        );
+
+        // Credentials belong to the user they were sealed for
+        assert_eq!(Some(VaultError::Corrupt), vault.open("bob", &sealed).err());
+
+        let other = CredentialVault::new(&KEY.replace("00", "ff")).unwrap();
+        assert_eq!(
+            Some(VaultError::Corrupt),
+            other.open("alice", &sealed).err()
+        );
+
+        assert_eq!(
+            Some(VaultError::InvalidMasterKey),
+            CredentialVault::new("abcd").err()
+        );
+        assert_eq!(
+            Some(VaultError::InvalidMasterKey),
+            CredentialVault::new(&KEY.replace("0f", "zz")).err()
+        );
+    }
+
+    #[test]
+    fn test_sign_push() {
+        let vault = CredentialVault::new(KEY).unwrap();
+        let body = br#"{"username":"student","password":"hunter2"}"#;
+
+        // What the cookie server computes with the push key from the documented command
+        assert_eq!(
+            "4775c71060c012171c352025c024c0748cb5e4c4fdc12bffc374a9f82fc9bb48",
+            vault.sign_push(1_700_000_000, body)
+        );
+        assert_ne!(
+            vault.sign_push(1_700_000_000, body),
+            vault.sign_push(1_700_000_001, body)
+        );
+        let other = CredentialVault::new(&KEY.replace("00", "ff")).unwrap();
+        assert_ne!(
+            vault.sign_push(1_700_000_000, body),
+            other.sign_push(1_700_000_000, body)
+        );
+    }
+}
@@ -0,0 +1,6 @@
+//! Copies of webweg's types that can be deserialized (see `webreg_types::webweg`).
+
+pub use webreg_types::webweg::{
+    CoursePrerequisite, CourseSection, EnrollmentStatus, Event, Meeting, MeetingDay,
+    PrerequisiteInfo, ScheduledSection, SearchResultItem, Term,
+};
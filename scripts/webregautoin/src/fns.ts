@@ -439,9 +439,10 @@ export async function fetchDarsCookies(ctx: Context, browser: puppeteer.Browser)
  *
  * @param ctx The context containing credentials and session info
  * @param browser The Puppeteer browser instance
+ * @param fresh Whether to run a new audit even if one already exists
  * @returns JSON object containing degree audit data
  */
-export async function fetchDegreeAudit(ctx: Context, browser: puppeteer.Browser): Promise<any> {
+export async function fetchDegreeAudit(ctx: Context, browser: puppeteer.Browser, fresh: boolean = false): Promise<any> {
     const termLog = ctx.termInfo?.termName ?? "N/A";
     logNice(termLog, "Starting degree audit scrape");
 
@@ -475,9 +476,11 @@ export async function fetchDegreeAudit(ctx: Context, browser: puppeteer.Browser)
         // The audit list has links like: read.html?id=JobQueueRun!!!!...
         let auditIdMatch = pageContent.match(/read\.html\?id=([^"]+)/);
 
-        if (!auditIdMatch) {
-            // No existing audits found, need to create one
-            logNice(termLog, "No existing audit found, attempting to create new audit");
+        if (!auditIdMatch || fresh) {
+            // No existing audits found (or a fresh one was asked for), need to create one
+            logNice(termLog, auditIdMatch
+                ? "Fresh audit requested, attempting to create new audit"
+                : "No existing audit found, attempting to create new audit");
 
             try {
                 // Navigate to create page
@@ -5,13 +5,47 @@
 // the headless browser and then return the cookies which can then be used by the tracker
 // application.
 
+import * as crypto from "crypto";
 import * as fs from "fs";
 import * as path from "path";
 import * as puppeteer from "puppeteer";
 import * as http from "http";
 import { parseArgs } from 'node:util';
 import { PUSH, fetchCookies, fetchDarsCookies, fetchDegreeAudit, getTermSeqId, logNice, printHelpMessage } from "./fns";
-import { IConfig, Context, ITermInfo } from "./types";
+import { IConfig, Context, ICredentials, ITermInfo } from "./types";
+
+// The largest credentials update that will be read, in bytes.
+const MAX_CREDENTIALS_BODY = 4096;
+// How far a credentials update's timestamp may be from now, in seconds.
+const MAX_CREDENTIALS_SKEW = 5 * 60;
+
+/**
+ * Checks that a credentials update was signed by the scraper's credential vault, i.e., that
+ * its signature is the HMAC-SHA256, under the push key, of its timestamp, a `.`, and its body.
+ *
+ * @param pushKey The push key, as hex.
+ * @param req The request.
+ * @param body The request's body.
+ * @returns Whether the update was signed with the push key, recently.
+ */
+function isSignedByVault(pushKey: string, req: http.IncomingMessage, body: Buffer): boolean {
+    const timestamp = req.headers["x-credentials-timestamp"];
+    const signature = req.headers["x-credentials-signature"];
+    if (typeof timestamp !== "string" || typeof signature !== "string" || !/^\d+$/.test(timestamp)) {
+        return false;
+    }
+
+    if (Math.abs(Date.now() / 1000 - Number.parseInt(timestamp, 10)) > MAX_CREDENTIALS_SKEW) {
+        return false;
+    }
+
+    const expected = crypto.createHmac("sha256", Buffer.from(pushKey, "hex"))
+        .update(`${timestamp}.`)
+        .update(body)
+        .digest();
+    const actual = Buffer.from(signature, "hex");
+    return actual.length === expected.length && crypto.timingSafeEqual(actual, expected);
+}
 
 async function main(): Promise<void> {
     const args = parseArgs({
@@ -44,8 +78,8 @@ async function main(): Promise<void> {
         headless: !debug
     });
 
-    const config: IConfig = JSON.parse(
-        fs.readFileSync(path.join(__dirname, "..", "credentials.json")).toString());
+    const credentialsPath = path.join(__dirname, "..", "credentials.json");
+    const config: IConfig = JSON.parse(fs.readFileSync(credentialsPath).toString());
 
     const term = args.values.term?.toUpperCase();
     let termInfo: ITermInfo | null = null;
@@ -79,6 +113,88 @@ async function main(): Promise<void> {
 
     // Very basic server.
     const server = http.createServer(async (req, res) => {
+        if (req.method === "POST" && req.url === "/credentials") {
+            // Sent by the scraper's credential vault whenever the user's credentials change.
+            // The new credentials are used from the next login onwards.
+            const pushKey = config.settings.credentialPushKey;
+            if (!pushKey) {
+                res.statusCode = 403;
+                res.end(
+                    JSON.stringify({
+                        error: "Credential updates aren't enabled; set settings.credentialPushKey."
+                    })
+                );
+
+                return;
+            }
+
+            const chunks: Buffer[] = [];
+            let size = 0;
+            for await (const chunk of req) {
+                size += chunk.length;
+                if (size > MAX_CREDENTIALS_BODY) {
+                    res.statusCode = 413;
+                    res.setHeader("Connection", "close");
+                    res.end(
+                        JSON.stringify({
+                            error: http.STATUS_CODES[413]
+                        })
+                    );
+                    req.destroy();
+
+                    return;
+                }
+
+                chunks.push(chunk);
+            }
+
+            const body = Buffer.concat(chunks);
+            if (!isSignedByVault(pushKey, req, body)) {
+                res.statusCode = 401;
+                res.end(
+                    JSON.stringify({
+                        error: "The credentials update isn't signed by the credential vault."
+                    })
+                );
+
+                return;
+            }
+
+            let credentials: Partial<ICredentials>;
+            try {
+                credentials = JSON.parse(body.toString());
+            } catch {
+                credentials = {};
+            }
+
+            if (typeof credentials.username !== "string" || typeof credentials.password !== "string"
+                || !credentials.username || !credentials.password) {
+                res.statusCode = 400;
+                res.end(
+                    JSON.stringify({
+                        error: "Expected a username and password."
+                    })
+                );
+
+                return;
+            }
+
+            context.webreg = {
+                username: credentials.username,
+                password: credentials.password
+            };
+            config.webreg = context.webreg;
+            fs.writeFileSync(credentialsPath, JSON.stringify(config, null, 4));
+            logNice("Credentials", `Updated the credentials for ${credentials.username}.`);
+            res.end(
+                JSON.stringify({
+                    updated: true
+                })
+            );
+
+            return;
+        }
+
         if (req.method !== "GET") {
             res.end(
                 JSON.stringify({
@@ -121,9 +237,11 @@ async function main(): Promise<void> {
                     })
                 );
             }
-        } else if (req.url === "/degree_audit") {
+        } else if (req.url === "/degree_audit" || req.url?.startsWith("/degree_audit?")) {
+            // ?fresh=true runs a new audit instead of reading the most recent one.
+            const fresh = new URL(req.url, "http://localhost").searchParams.get("fresh") === "true";
             try {
-                const auditData = await fetchDegreeAudit(context, browser);
+                const auditData = await fetchDegreeAudit(context, browser, fresh);
                 res.end(JSON.stringify(auditData));
             } catch (error) {
                 res.statusCode = 500;
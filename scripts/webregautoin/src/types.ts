@@ -25,6 +25,9 @@ export interface IConfig {
         loginType: string;
         automaticPushEnabled: boolean;
         smsTokens?: string[];
+        // The key that credential updates must be signed with, as hex. Updates are refused
+        // without it.
+        credentialPushKey?: string;
     };
 }
 
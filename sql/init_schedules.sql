@@ -21,6 +21,12 @@ CREATE TABLE IF NOT EXISTS sections (
     course_id INTEGER NOT NULL,
     section_id VARCHAR(20) NOT NULL,
     section_code VARCHAR(10) NOT NULL,
+    start_date DATE,  -- first day of instruction (YYYY-MM-DD), if known
+    end_date DATE,  -- last day of instruction (YYYY-MM-DD), if known
+    total_seats INTEGER,  -- seat capacity when the section was scraped
+    enrolled_ct INTEGER,  -- number of students enrolled when the section was scraped
+    last_seen_at DATETIME,  -- when a scrape last found the section
+    cancelled_at DATETIME,  -- when a scrape first found the section missing, if it's cancelled
     created_at DATETIME NOT NULL,
     FOREIGN KEY (course_id) REFERENCES courses(course_id) ON DELETE CASCADE,
     UNIQUE(course_id, section_id)
@@ -43,8 +49,481 @@ CREATE TABLE IF NOT EXISTS meetings (
     building VARCHAR(50),
     room VARCHAR(50),
     instructors TEXT,  -- JSON array of instructor names
+    start_date DATE,  -- first day this meeting happens (YYYY-MM-DD), if it differs by meeting
+    end_date DATE,  -- last day this meeting happens (YYYY-MM-DD), if it differs by meeting
+    room_id INTEGER,  -- the meeting's room in the rooms table, if it has one
+    exam_kind TEXT,  -- 'final' or 'midterm' for exams (FI and MI meetings), otherwise null
     created_at DATETIME NOT NULL,
     FOREIGN KEY (section_id_pk) REFERENCES sections(section_id_pk) ON DELETE CASCADE
 );
 
 CREATE INDEX IF NOT EXISTS idx_meetings_section ON meetings(section_id_pk);
+
+-- Custom events table (the student's own calendar events, e.g., work shifts or club
+-- meetings, mirrored to and from WebReg's custom event feature)
+CREATE TABLE IF NOT EXISTS custom_events (
+    event_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    term VARCHAR(10) NOT NULL,
+    session_token TEXT,  -- The session that owns the event; events without one aren't shown
+    name TEXT NOT NULL,
+    location TEXT,
+    days TEXT NOT NULL,  -- JSON array of day abbreviations (e.g., ["M", "W"])
+    start_hr INTEGER NOT NULL,
+    start_min INTEGER NOT NULL,
+    end_hr INTEGER NOT NULL,
+    end_min INTEGER NOT NULL,
+    webreg_timestamp TEXT,  -- WebReg's identifier for the event, once synced
+    sync_state VARCHAR(20) NOT NULL,  -- 'synced', 'pending_push', or 'pending_delete'
+    updated_at DATETIME NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_custom_events_term ON custom_events(term);
+
+-- Instructors table (one row per distinct instructor name)
+CREATE TABLE IF NOT EXISTS instructors (
+    instructor_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE
+);
+
+-- Which instructors teach each meeting
+CREATE TABLE IF NOT EXISTS meeting_instructors (
+    meeting_id INTEGER NOT NULL,
+    instructor_id INTEGER NOT NULL,
+    PRIMARY KEY (meeting_id, instructor_id),
+    FOREIGN KEY (meeting_id) REFERENCES meetings(meeting_id) ON DELETE CASCADE,
+    FOREIGN KEY (instructor_id) REFERENCES instructors(instructor_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_meeting_instructors_instructor ON meeting_instructors(instructor_id);
+
+-- Shared schedules table (read-only snapshots of a student's schedule, shared by link)
+CREATE TABLE IF NOT EXISTS shared_schedules (
+    share_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    term VARCHAR(10) NOT NULL,
+    schedule_name TEXT NOT NULL,
+    snapshot TEXT NOT NULL,  -- JSON array of the schedule's sections, as returned by WebReg
+    created_at DATETIME NOT NULL,
+    expires_at DATETIME NOT NULL
+);
+
+-- Rooms table (one row per distinct building and room)
+CREATE TABLE IF NOT EXISTS rooms (
+    room_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    building VARCHAR(50) NOT NULL,
+    room VARCHAR(50) NOT NULL,
+    UNIQUE(building, room)
+);
+
+CREATE INDEX IF NOT EXISTS idx_rooms_building ON rooms(building);
+
+-- Enrollment actions table (a log of adds and drops made through the API, and whether each
+-- one could be seen in the student's schedule afterwards)
+CREATE TABLE IF NOT EXISTS enrollment_actions (
+    action_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    term VARCHAR(10) NOT NULL,
+    section_id VARCHAR(20) NOT NULL,
+    action TEXT NOT NULL,  -- 'add' or 'drop'
+    webreg_success INTEGER NOT NULL,
+    verified INTEGER NOT NULL,
+    before_snapshot TEXT,  -- JSON of the section in the schedule before the change, if any
+    after_snapshot TEXT,   -- JSON of the section in the schedule after the change, if any
+    created_at DATETIME NOT NULL
+);
+
+-- Requests to enroll in a section once a seat opens or at a given time, run by a
+-- background worker using the student's session
+CREATE TABLE IF NOT EXISTS enroll_jobs (
+    job_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    term VARCHAR(10) NOT NULL,
+    session_token TEXT NOT NULL,
+    section_id VARCHAR(20) NOT NULL,
+    subject_code VARCHAR(10) NOT NULL,
+    course_code VARCHAR(10) NOT NULL,
+    grading_option VARCHAR(5),
+    unit_count INTEGER,
+    run_at DATETIME,  -- No attempts are made before this time, if set (e.g., first pass)
+    expires_at DATETIME,  -- The job gives up after this time, if set
+    status VARCHAR(20) NOT NULL,  -- 'pending', 'enrolled', 'failed', 'expired', or 'cancelled'
+    attempts INTEGER NOT NULL DEFAULT 0,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    -- Why an expired job expired: 'expiry_time', 'add_deadline', or 'enrolled_in_course'
+    expiry_reason VARCHAR(20),
+    notify_channels TEXT,  -- The channels to notify once the job finishes, as a JSON array
+    -- How the alerts sent to those channels when the section's seats open are throttled
+    alert_cooldown_mins INTEGER NOT NULL DEFAULT 0,
+    alert_dedup_mins INTEGER NOT NULL DEFAULT 0,
+    alert_min_seats INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE INDEX IF NOT EXISTS idx_enroll_jobs_status ON enroll_jobs(status);
+
+-- Every attempt that an enrollment job made, and how it went
+CREATE TABLE IF NOT EXISTS enroll_job_attempts (
+    attempt_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    job_id INTEGER NOT NULL,
+    outcome VARCHAR(20) NOT NULL,  -- 'enrolled', 'unverified', or 'error'
+    message TEXT,
+    action_id INTEGER,  -- The change in enrollment_actions, if WebReg accepted the add
+    attempted_at DATETIME NOT NULL,
+    FOREIGN KEY (job_id) REFERENCES enroll_jobs(job_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_enroll_job_attempts_job ON enroll_job_attempts(job_id);
+
+-- Full-term scrapes started by an admin, which walk every department of a term and can be
+-- resumed after a crash
+CREATE TABLE IF NOT EXISTS scrape_jobs (
+    job_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    term VARCHAR(10) NOT NULL,
+    status VARCHAR(20) NOT NULL,  -- 'running', 'completed', or 'failed'
+    courses_scraped INTEGER NOT NULL DEFAULT 0,
+    courses_failed INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    started_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    completed_at DATETIME,
+    dataset_hash TEXT  -- SHA-256 of the term's canonical export once the scrape completed
+);
+
+CREATE INDEX IF NOT EXISTS idx_scrape_jobs_term ON scrape_jobs(term);
+
+-- The departments that each scrape walks, which serve as its checkpoints
+CREATE TABLE IF NOT EXISTS scrape_job_departments (
+    job_id INTEGER NOT NULL,
+    dept_code VARCHAR(10) NOT NULL,
+    done INTEGER NOT NULL DEFAULT 0,
+    courses INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (job_id, dept_code),
+    FOREIGN KEY (job_id) REFERENCES scrape_jobs(job_id) ON DELETE CASCADE
+);
+
+-- Invite codes that let people register for an API key on their own
+CREATE TABLE IF NOT EXISTS invites (
+    invite_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    code_hash TEXT NOT NULL UNIQUE,  -- SHA-256 of the normalized code; the code isn't stored
+    description TEXT,
+    max_uses INTEGER NOT NULL,
+    uses INTEGER NOT NULL DEFAULT 0,
+    expires_at DATETIME,
+    revoked INTEGER NOT NULL DEFAULT 0,
+    created_at DATETIME NOT NULL
+);
+
+-- The API keys that were registered with each invite code
+CREATE TABLE IF NOT EXISTS invite_redemptions (
+    invite_id INTEGER NOT NULL,
+    key_prefix TEXT NOT NULL,
+    redeemed_at DATETIME NOT NULL,
+    FOREIGN KEY (invite_id) REFERENCES invites(invite_id) ON DELETE CASCADE
+);
+
+-- The cookie server that each API key registered with an invite code was given, out of
+-- `registration.cookieServers`; one key per server
+CREATE TABLE IF NOT EXISTS cookie_server_slots (
+    key_prefix TEXT PRIMARY KEY,
+    address TEXT NOT NULL,
+    port INTEGER NOT NULL,
+    assigned_at DATETIME NOT NULL,
+    UNIQUE (address, port)
+);
+
+-- What each API key can be used for; keys without a row can be used for anything
+CREATE TABLE IF NOT EXISTS api_key_scopes (
+    key_prefix TEXT PRIMARY KEY,
+    scopes TEXT NOT NULL,  -- comma-separated, see api_keys.rs
+    updated_at DATETIME NOT NULL
+);
+
+-- The fields that each API key may and may not see in responses; keys without a row see
+-- everything
+CREATE TABLE IF NOT EXISTS api_key_filters (
+    key_prefix TEXT PRIMARY KEY,
+    filter TEXT NOT NULL,  -- JSON, see redaction.rs
+    updated_at DATETIME NOT NULL
+);
+
+-- Each user's WebReg credentials, sealed with the vault's master key
+CREATE TABLE IF NOT EXISTS credential_vault (
+    key_prefix TEXT PRIMARY KEY,
+    sealed BLOB NOT NULL,  -- nonce, ciphertext, and tag; see vault.rs
+    username TEXT NOT NULL,
+    version INTEGER NOT NULL,  -- bumped each time the credentials are rotated
+    updated_at DATETIME NOT NULL,
+    synced_version INTEGER,  -- the last version the user's cookie server accepted
+    synced_at DATETIME,
+    sync_error TEXT
+);
+
+-- Leases that instances sharing this database take turns holding, so that only one of
+-- them runs each kind of background work at a time
+CREATE TABLE IF NOT EXISTS leases (
+    name TEXT PRIMARY KEY,
+    holder TEXT NOT NULL,  -- the ID of the instance holding the lease
+    expires_at INTEGER NOT NULL  -- milliseconds since the epoch
+);
+
+-- Historical grade distributions and course evaluations (e.g., from CAPE or SET), one row
+-- per course, term, and instructor
+CREATE TABLE IF NOT EXISTS grade_distributions (
+    course TEXT NOT NULL,  -- normalized, e.g. CSE 100
+    term TEXT NOT NULL,
+    instructor TEXT NOT NULL,
+    enrolled INTEGER,
+    avg_gpa REAL,  -- the average grade received, on a 4.0 scale
+    recommend_instructor REAL,  -- the percent of students who recommend the instructor
+    recommend_class REAL,  -- the percent of students who recommend the class
+    study_hours REAL,  -- the average hours studied per week
+    PRIMARY KEY (course, term, instructor)
+);
+
+-- Courses as described in the UCSD course catalog, which doesn't change from term to term
+CREATE TABLE IF NOT EXISTS catalog_courses (
+    subject TEXT NOT NULL,  -- e.g. CSE
+    number TEXT NOT NULL,  -- e.g. 100
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    min_units REAL,
+    max_units REAL,
+    prerequisites TEXT,
+    cross_listings TEXT NOT NULL,  -- comma-separated course codes, e.g. MATH 176
+    scraped_at DATETIME NOT NULL,
+    PRIMARY KEY (subject, number)
+);
+
+-- Embeddings of catalog courses' descriptions, for semantic search; see semantic.rs
+CREATE TABLE IF NOT EXISTS catalog_embeddings (
+    subject TEXT NOT NULL,
+    number TEXT NOT NULL,
+    model TEXT NOT NULL,  -- the model that made the embedding
+    text_hash TEXT NOT NULL,  -- the SHA-256 hash of the text that was embedded
+    embedding BLOB NOT NULL,  -- little-endian f32s
+    PRIMARY KEY (subject, number, model)
+);
+
+-- The latest change to each entity that clients sync (see sync.rs). Replacing a row gives it
+-- a new, higher seq, so a client only needs the rows past the last seq it saw
+CREATE TABLE IF NOT EXISTS sync_changes (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,  -- 'schedule', 'watch', 'seats', or 'audit'
+    entity_key TEXT NOT NULL,  -- the term, job ID, TERM:SECTION_ID, or 'summary'
+    changed_at DATETIME NOT NULL,
+    UNIQUE (kind, entity_key)
+);
+
+CREATE TRIGGER IF NOT EXISTS sync_enroll_job_inserted AFTER INSERT ON enroll_jobs
+BEGIN
+    INSERT OR REPLACE INTO sync_changes (kind, entity_key, changed_at)
+    VALUES ('watch', NEW.job_id, datetime('now'));
+END;
+
+CREATE TRIGGER IF NOT EXISTS sync_enroll_job_updated AFTER UPDATE ON enroll_jobs
+BEGIN
+    INSERT OR REPLACE INTO sync_changes (kind, entity_key, changed_at)
+    VALUES ('watch', NEW.job_id, datetime('now'));
+END;
+
+CREATE TRIGGER IF NOT EXISTS sync_enrollment_action AFTER INSERT ON enrollment_actions
+BEGIN
+    INSERT OR REPLACE INTO sync_changes (kind, entity_key, changed_at)
+    VALUES ('schedule', NEW.term, datetime('now'));
+END;
+
+-- Each tracked section's seats, appended to by the tracker whenever they change (see
+-- fill_rate.rs)
+CREATE TABLE IF NOT EXISTS seat_history (
+    history_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    term VARCHAR(10) NOT NULL,
+    subj_course_id VARCHAR(50) NOT NULL,
+    section_id VARCHAR(20) NOT NULL,
+    available_seats INTEGER NOT NULL,
+    waitlist_ct INTEGER NOT NULL,
+    total_seats INTEGER NOT NULL,
+    recorded_at INTEGER NOT NULL  -- milliseconds since the epoch
+);
+
+CREATE INDEX IF NOT EXISTS idx_seat_history_term ON seat_history(term, subj_course_id, section_id, recorded_at);
+
+-- Every version of each requirement config and requirement override that an admin has set,
+-- with who set it and why (see audit_trail.rs). The latest version of an entity is the
+-- current one; rolling back adds a new version with an older one's content
+CREATE TABLE IF NOT EXISTS audit_trail (
+    entry_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,  -- 'college', 'major', or 'override'
+    entity_key TEXT NOT NULL,  -- the college or major code, or the (user's) requirement name
+    version INTEGER NOT NULL,
+    content TEXT,  -- the entity as JSON, or null if it was removed
+    author TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    created_at DATETIME NOT NULL,
+    UNIQUE (kind, entity_key, version)
+);
+
+-- Sections that were added, cancelled, or modified between two scrapes of their course (see
+-- changes.rs), for the change feed
+CREATE TABLE IF NOT EXISTS section_changes (
+    change_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    term VARCHAR(10) NOT NULL,
+    subj_code VARCHAR(10) NOT NULL,
+    subj_course_id VARCHAR(50) NOT NULL,
+    section_id VARCHAR(20) NOT NULL,
+    section_code VARCHAR(10) NOT NULL,
+    kind TEXT NOT NULL,  -- 'added', 'cancelled', or 'modified'
+    fields TEXT NOT NULL,  -- JSON array of the meeting fields that changed
+    changed_at INTEGER NOT NULL  -- milliseconds since the epoch
+);
+
+CREATE INDEX IF NOT EXISTS idx_section_changes_term ON section_changes(term, changed_at);
+
+-- Pairs of cross-listed courses, i.e., one class offered under two codes (see
+-- cross_listings.rs). Pairs from the catalog hold in every term, so their term is empty
+CREATE TABLE IF NOT EXISTS cross_listings (
+    term VARCHAR(10) NOT NULL,
+    course_a VARCHAR(50) NOT NULL,
+    course_b VARCHAR(50) NOT NULL,
+    source TEXT NOT NULL,  -- 'catalog' or 'schedule'
+    PRIMARY KEY (term, course_a, course_b, source)
+);
+
+-- The courses that each session is considering for a term, with the sections it would take,
+-- kept independently of WebReg's plans, which only hold a few schedules
+CREATE TABLE IF NOT EXISTS cart_items (
+    item_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    term VARCHAR(10) NOT NULL,
+    session_token TEXT NOT NULL,
+    subject_code VARCHAR(10) NOT NULL,
+    course_code VARCHAR(10) NOT NULL,
+    sections TEXT NOT NULL,  -- JSON array of the sections, most preferred first
+    notes TEXT,
+    priority INTEGER NOT NULL,  -- lower is more important
+    grading_option VARCHAR(5),
+    unit_count INTEGER,
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    UNIQUE (term, session_token, subject_code, course_code)
+);
+
+-- The plans that each session has saved for a term, each of which can be synced into the
+-- WebReg schedule with the same name
+CREATE TABLE IF NOT EXISTS saved_plans (
+    plan_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    term VARCHAR(10) NOT NULL,
+    session_token TEXT NOT NULL,
+    schedule_name TEXT NOT NULL,
+    entries TEXT NOT NULL,  -- JSON array of the plan's sections
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    UNIQUE (term, session_token, schedule_name)
+);
+
+-- The sections that each user is enrolled or waitlisted in, as of the last time their
+-- WebReg schedule was mirrored, keyed by the prefix of their API key
+CREATE TABLE IF NOT EXISTS user_enrollments (
+    key_prefix TEXT NOT NULL,
+    term VARCHAR(10) NOT NULL,
+    section_id VARCHAR(20) NOT NULL,
+    subject_code VARCHAR(10) NOT NULL,
+    course_code VARCHAR(10) NOT NULL,
+    section_code VARCHAR(10) NOT NULL,
+    status VARCHAR(20) NOT NULL,  -- 'enrolled' or 'waitlisted'
+    waitlist_pos INTEGER,
+    units REAL NOT NULL,
+    grade_option VARCHAR(5) NOT NULL,
+    first_seen_at DATETIME NOT NULL,
+    synced_at DATETIME NOT NULL,
+    PRIMARY KEY (key_prefix, term, section_id)
+);
+
+-- The enrollment appointments (pass times) of each user, as of the last time they were
+-- fetched from WebReg, keyed by the prefix of their API key
+CREATE TABLE IF NOT EXISTS pass_times (
+    key_prefix TEXT NOT NULL,
+    term VARCHAR(10) NOT NULL,
+    pass VARCHAR(10) NOT NULL,  -- 'first' or 'second'
+    starts_at DATETIME NOT NULL,  -- local time
+    -- The fewest hours before the pass that a reminder has been sent at, if any
+    reminded_hours INTEGER,
+    synced_at DATETIME NOT NULL,
+    PRIMARY KEY (key_prefix, term, pass)
+);
+
+-- The requirement sets that each user has defined for themselves (e.g., a pre-med
+-- checklist), which their degree progress is computed against alongside their official
+-- requirements, keyed by the prefix of their API key
+CREATE TABLE IF NOT EXISTS custom_requirements (
+    requirement_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    key_prefix TEXT NOT NULL,
+    name TEXT NOT NULL,
+    subrequirements TEXT NOT NULL,  -- JSON array of the set's subrequirements
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    UNIQUE (key_prefix, name)
+);
+
+-- The quarter-by-quarter course plans that each session has saved, which are kept apart
+-- from WebReg's own planned schedules
+CREATE TABLE IF NOT EXISTS course_plans (
+    plan_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_token TEXT NOT NULL,
+    name TEXT NOT NULL,
+    quarters TEXT NOT NULL,  -- JSON array of each quarter's term and course codes
+    created_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL,
+    UNIQUE (session_token, name)
+);
+
+-- The API key that registered each session, so that a user's data can still be found by
+-- their key after the session itself (which is only kept in memory) is gone
+CREATE TABLE IF NOT EXISTS session_owners (
+    session_token TEXT PRIMARY KEY,
+    key_prefix TEXT NOT NULL,
+    created_at DATETIME NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_session_owners_key ON session_owners(key_prefix);
+
+-- The notes that WebReg attaches to sections, and the enrollment restrictions parsed out of
+-- them, as of each section's last scrape
+CREATE TABLE IF NOT EXISTS section_notes (
+    term VARCHAR(10) NOT NULL,
+    section_id VARCHAR(20) NOT NULL,
+    notes TEXT NOT NULL,  -- JSON array of the notes
+    restrictions TEXT NOT NULL,  -- JSON array of the restrictions
+    enrollment_window TEXT,  -- JSON object with when the section opens to non-majors, if known
+    updated_at DATETIME NOT NULL,
+    PRIMARY KEY (term, section_id)
+);
+
+-- A version for each term's schedule data, which changes whenever its sections, meetings,
+-- or section notes are written. Responses built from the data are tagged with it, so that
+-- clients can skip downloading data that they already have
+CREATE TABLE IF NOT EXISTS term_data_versions (
+    term VARCHAR(10) PRIMARY KEY,
+    version INTEGER NOT NULL
+);
+
+-- The periods during which each service that the API depends on (WebReg, DARS, and the
+-- cookie server) was seen to be up or down, for the status page. A period ends when the
+-- service's state changes, or when it stops being checked
+CREATE TABLE IF NOT EXISTS dependency_status_intervals (
+    interval_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    dependency TEXT NOT NULL,
+    up BOOLEAN NOT NULL,
+    started_at INTEGER NOT NULL,  -- milliseconds since the epoch
+    last_checked_at INTEGER NOT NULL,  -- milliseconds since the epoch
+    closed BOOLEAN NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS idx_dependency_status_intervals
+    ON dependency_status_intervals(dependency, last_checked_at);
+
+-- The terms that each course has been offered in, with how many sections and seats it had,
+-- which is recorded whenever a term's schedule data is scraped and kept after that data
+-- is gone
+CREATE TABLE IF NOT EXISTS course_offerings (
+    course_code VARCHAR(50) NOT NULL,  -- normalized, e.g. 'CSE 100'
+    term VARCHAR(10) NOT NULL,
+    section_count INTEGER NOT NULL,
+    total_seats INTEGER NOT NULL,
+    enrolled_ct INTEGER NOT NULL,
+    recorded_at DATETIME NOT NULL,
+    PRIMARY KEY (course_code, term)
+);